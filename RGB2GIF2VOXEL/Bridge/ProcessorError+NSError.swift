@@ -0,0 +1,64 @@
+//
+//  ProcessorError+NSError.swift
+//  RGB2GIF2VOXEL
+//
+//  Bridges the generated Rust ProcessorError to NSError codes and user info
+//  so failures keep their stage, frame and reason through Objective-C APIs and logs
+//
+
+import Foundation
+
+extension ProcessorError: CustomNSError {
+
+    public static var errorDomain: String { "com.rgb2gif2voxel.processor" }
+
+    /// Stable codes, in the order the variants are declared in rgb2gif.udl
+    public enum Code: Int {
+        case quantization = 1
+        case encoding = 2
+        case invalidInput = 3
+        case memory = 4
+        case invalidOption = 5
+    }
+
+    /// User info keys for the structured fields
+    public enum UserInfoKey {
+        public static let stage = "ProcessorErrorStage"
+        public static let frameIndex = "ProcessorErrorFrameIndex"
+        public static let field = "ProcessorErrorField"
+    }
+
+    public var code: Code {
+        switch self {
+        case .QuantizationError: return .quantization
+        case .EncodingError: return .encoding
+        case .InvalidInput: return .invalidInput
+        case .MemoryError: return .memory
+        case .InvalidOption: return .invalidOption
+        }
+    }
+
+    public var errorCode: Int { code.rawValue }
+
+    public var errorUserInfo: [String: Any] {
+        var info: [String: Any] = [:]
+        switch self {
+        case let .QuantizationError(stage, frameIndex, message),
+             let .EncodingError(stage, frameIndex, message),
+             let .InvalidInput(stage, frameIndex, message),
+             let .MemoryError(stage, frameIndex, message):
+            info[UserInfoKey.stage] = stage
+            if let frameIndex {
+                info[UserInfoKey.frameIndex] = Int(frameIndex)
+            }
+            info[NSDebugDescriptionErrorKey] = message
+            let frame = frameIndex.map { " (frame \($0))" } ?? ""
+            info[NSLocalizedDescriptionKey] = "\(stage)\(frame): \(message)"
+        case let .InvalidOption(field, reason):
+            info[UserInfoKey.field] = field
+            info[NSDebugDescriptionErrorKey] = reason
+            info[NSLocalizedDescriptionKey] = "Invalid \(field): \(reason)"
+        }
+        return info
+    }
+}
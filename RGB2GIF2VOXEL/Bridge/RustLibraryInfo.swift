@@ -0,0 +1,40 @@
+//
+//  RustLibraryInfo.swift
+//  RGB2GIF2VOXEL
+//
+//  Runtime view of the linked Rust library: version, interface revision and
+//  which optional subsystems it was built with, so the UI can hide what's missing
+//
+
+import Foundation
+
+/// Optional subsystems reported by `rgb2gifFeatures()`
+public struct RustFeatures: OptionSet, Sendable {
+    public let rawValue: UInt32
+
+    public init(rawValue: UInt32) {
+        self.rawValue = rawValue
+    }
+
+    // Bit values match rust-core/src/build_info.rs
+    public static let gpu = RustFeatures(rawValue: 1 << 0)
+    public static let webp = RustFeatures(rawValue: 1 << 1)
+    public static let apng = RustFeatures(rawValue: 1 << 2)
+    public static let oklab = RustFeatures(rawValue: 1 << 3)
+    public static let simd = RustFeatures(rawValue: 1 << 4)
+}
+
+public enum RustLibraryInfo {
+
+    /// Interface revision these Swift bindings were generated against
+    public static let expectedABIVersion: UInt32 = 1
+
+    public static var version: String { rgb2gifVersion() }
+
+    public static var abiVersion: UInt32 { rgb2gifAbiVersion() }
+
+    /// False when the linked library's interface differs from the bindings
+    public static var isCompatible: Bool { abiVersion == expectedABIVersion }
+
+    public static var features: RustFeatures { RustFeatures(rawValue: rgb2gifFeatures()) }
+}
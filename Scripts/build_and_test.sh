@@ -19,7 +19,7 @@ cd ..
 # 2. Copy library to expected location
 echo "📋 Copying library..."
 mkdir -p RGB2GIF2VOXEL/Frameworks
-cp rust-minimal/target/aarch64-apple-ios/release/librust_minimal.a RGB2GIF2VOXEL/Frameworks/
+cp target/aarch64-apple-ios/release/librust_minimal.a RGB2GIF2VOXEL/Frameworks/
 
 # 3. Fix iOS deployment target
 echo "🔧 Fixing deployment target..."
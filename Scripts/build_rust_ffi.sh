@@ -6,6 +6,7 @@ set -e
 SCRIPT_DIR="$( cd "$( dirname "${BASH_SOURCE[0]}" )" && pwd )"
 PROJECT_ROOT="$SCRIPT_DIR/.."
 RUST_DIR="$PROJECT_ROOT/rust-core"
+TARGET_DIR="$PROJECT_ROOT/target"  # Shared by every workspace member
 SWIFT_DIR="$PROJECT_ROOT/RGB2GIF2VOXEL/Bridge/Generated"
 
 echo "🦀 Building Rust library for iOS..."
@@ -30,12 +31,12 @@ mkdir -p "$PROJECT_ROOT/ThirdParty/RustCore.xcframework"
 
 # Create simulator universal binary
 lipo -create \
-    target/x86_64-apple-ios/release/librgb2gif_processor.a \
-    target/aarch64-apple-ios-sim/release/librgb2gif_processor.a \
+    "$TARGET_DIR"/x86_64-apple-ios/release/librgb2gif_processor.a \
+    "$TARGET_DIR"/aarch64-apple-ios-sim/release/librgb2gif_processor.a \
     -output "$PROJECT_ROOT/ThirdParty/librgb2gif_processor_sim.a"
 
 # Copy device binary
-cp target/aarch64-apple-ios/release/librgb2gif_processor.a \
+cp "$TARGET_DIR"/aarch64-apple-ios/release/librgb2gif_processor.a \
    "$PROJECT_ROOT/ThirdParty/librgb2gif_processor_device.a"
 
 # Generate Swift bindings
@@ -30,7 +30,8 @@ echo "Building for target: $TARGET"
 cargo build --target "$TARGET" --release
 
 # Copy the library to a known location
-BUILT_LIB="$RUST_DIR/target/$TARGET/release/librgb2gif_processor.a"
+# Workspace members share the target directory at the repo root
+BUILT_LIB="$PROJECT_DIR/target/$TARGET/release/librgb2gif_processor.a"
 OUTPUT_DIR="$PROJECT_DIR/ThirdParty/RustCore.xcframework/ios-arm64"
 
 if [ "$PLATFORM_NAME" == "iphonesimulator" ]; then
@@ -25,9 +25,28 @@ struct YinGifProcessor *yingif_processor_new(void);
  */
 void yingif_processor_free(struct YinGifProcessor *processor);
 
+/**
+ * Cap the frames a processor keeps, so a capture that is never finished
+ * can't grow forever; max_frames and max_bytes of 0 lift that limit, and new
+ * processors keep up to 256 MiB
+ * policy 0 refuses frames past a limit (the add returns -4), 1 drops the
+ * oldest frames to make room
+ * Returns 0, or -1 on bad arguments
+ */
+int32_t yingif_processor_set_limits(struct YinGifProcessor *processor,
+                                    int32_t max_frames,
+                                    int64_t max_bytes,
+                                    int32_t policy);
+
+/**
+ * Bytes of frame data a processor currently holds, or -1 for a bad handle
+ */
+int64_t yingif_processor_memory_usage(struct YinGifProcessor *processor);
+
 /**
  * Process a BGRA frame: downsize and quantize colors
- * Returns 0 on success, negative error code on failure
+ * Returns 0 on success, -4 if the processor's limits refuse the frame,
+ * another negative error code on failure
  */
 int32_t yingif_process_frame(struct YinGifProcessor *processor,
                              const uint8_t *bgra_data,
@@ -38,6 +57,46 @@ int32_t yingif_process_frame(struct YinGifProcessor *processor,
                              uint8_t *out_indices,
                              uint32_t *out_palette);
 
+/**
+ * Process a BGRA frame captured at timestamp_ns (monotonic clock)
+ * Same as yingif_process_frame, but keeps the timestamp so
+ * yingif_processor_create_gif can reproduce the real frame pacing
+ * Returns -3 if the timestamp is earlier than the previous frame's
+ */
+int32_t yingif_processor_add_frame_with_timestamp(struct YinGifProcessor *processor,
+                                                  const uint8_t *bgra_data,
+                                                  int32_t width,
+                                                  int32_t height,
+                                                  int32_t target_size,
+                                                  int32_t palette_size,
+                                                  uint64_t timestamp_ns,
+                                                  uint8_t *out_indices,
+                                                  uint32_t *out_palette);
+
+/**
+ * Create a GIF89a from the frames accumulated in the processor
+ * resample_fps 0 uses the capture timestamps as per-frame delays (-3 if
+ * frames were added without one); a positive value re-times the clip onto
+ * that constant frame rate
+ * Returns 0 on success, -1 on bad arguments, -2 if out_capacity is too small
+ */
+int32_t yingif_processor_create_gif(struct YinGifProcessor *processor,
+                                    int32_t resample_fps,
+                                    uint8_t *out_data,
+                                    int32_t out_capacity,
+                                    int32_t *out_size);
+
+/**
+ * Same as yingif_processor_create_gif, but streams the GIF to the file at
+ * path instead of a buffer, so long exports never sit whole in memory
+ * The file only appears once complete; its size is written to out_size
+ * Returns 0 on success, -1 on bad arguments or a failed write, -3 as above
+ */
+int32_t yingif_processor_create_gif_to_file(struct YinGifProcessor *processor,
+                                            int32_t resample_fps,
+                                            const char *path,
+                                            int64_t *out_size);
+
 /**
  * Create a GIF89a from indexed cube tensor data
  * Returns the size of the created GIF, or negative error code
@@ -52,10 +111,37 @@ int32_t yingif_create_gif89a(const uint8_t *indices,
                              int32_t *out_size);
 
 /**
- * Get estimated buffer size needed for GIF
+ * Predict the size and encode time of yingif_processor_create_gif
+ * Encodes a few evenly spaced accumulated frames and extrapolates to the
+ * whole clip: the likely size range in bytes and the time in milliseconds
+ * Returns 0, or -1 on bad arguments or an empty processor
+ */
+int32_t yingif_processor_estimate(struct YinGifProcessor *processor,
+                                  int32_t *out_bytes_low,
+                                  int32_t *out_bytes_high,
+                                  float *out_ms);
+
+/**
+ * Output buffer size that always fits a cube_size^3 GIF (a worst-case bound)
+ * Use yingif_processor_estimate for the size the clip will likely have
  */
 int32_t yingif_estimate_gif_size(int32_t cube_size, int32_t palette_size);
 
+/**
+ * Record panics for yingif_last_panic, also writing each to log_path if not NULL
+ * Call once at launch; a panic inside a C call aborts the process, so the
+ * file is what the next launch can read back
+ * Returns 0, or -1 if log_path isn't UTF-8
+ */
+int32_t yingif_install_panic_hook(const char *log_path);
+
+/**
+ * Copy the latest panic report (message, thread, backtrace) into out as a C string
+ * Returns the report's length, 0 if nothing has panicked, -1 on bad arguments,
+ * -2 if out_capacity is too small
+ */
+int32_t yingif_last_panic(char *out, int32_t out_capacity);
+
 /**
  * Process batch of RGBA frames: downsample and quantize
  * Returns 0 on success, negative error codes on failure
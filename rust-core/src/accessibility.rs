@@ -0,0 +1,176 @@
+// Color Vision Accessibility
+// Simulates dichromat vision on the palette and separates colors those viewers would confuse
+
+// Simulation matrices are quoted verbatim from Machado et al. (2009)
+#![allow(clippy::excessive_precision)]
+
+use crate::oklab_quantization::{oklab_to_linear_srgb_in_gamut, OklabColor};
+use crate::{AccessibilityOpts, ColorVisionDeficiency};
+
+/// Passes over every pair of colors while separating them; each pass only moves confused pairs
+const CONTRAST_PASSES: usize = 16;
+
+/// Linear RGB as seen with one cone type missing, at full severity
+fn matrix(deficiency: ColorVisionDeficiency) -> [[f32; 3]; 3] {
+    match deficiency {
+        ColorVisionDeficiency::Protanopia => [
+            [0.152286, 1.052583, -0.204868],
+            [0.114503, 0.786281, 0.099216],
+            [-0.003882, -0.048116, 1.051998],
+        ],
+        ColorVisionDeficiency::Deuteranopia => [
+            [0.367322, 0.860646, -0.227968],
+            [0.280085, 0.672501, 0.047413],
+            [-0.011820, 0.042940, 0.968881],
+        ],
+        ColorVisionDeficiency::Tritanopia => [
+            [1.255528, -0.076749, -0.178779],
+            [-0.078411, 0.930809, 0.147602],
+            [0.004733, 0.691367, 0.303900],
+        ],
+    }
+}
+
+/// Adjust `palette` in place for `opts.deficiency`
+///
+/// With `preserve_contrast`, every pair of colors that looks at least
+/// `min_contrast` apart (OKLab distance) to everyone, but closer to the
+/// simulated viewer, is pushed apart in lightness until it doesn't. Hue and
+/// chroma stay put, and lightness survives every form of color blindness, so
+/// the GIF looks nearly the same to everyone else. `simulate` then replaces
+/// each color with what the viewer sees. Transparent entries are left alone.
+pub fn apply(palette: &mut [[u8; 4]], opts: &AccessibilityOpts) {
+    let Some(deficiency) = opts.deficiency else {
+        return;
+    };
+    let matrix = matrix(deficiency);
+    let visible: Vec<usize> = (0..palette.len()).filter(|&i| palette[i][3] != 0).collect();
+
+    if opts.preserve_contrast {
+        let original: Vec<OklabColor> = visible.iter().map(|&i| to_oklab(linear(palette[i]))).collect();
+        let mut adjusted = original.clone();
+        let mut seen: Vec<OklabColor> = adjusted.iter().map(|c| simulate(c, &matrix)).collect();
+        for _ in 0..CONTRAST_PASSES {
+            let mut moved = false;
+            for i in 0..adjusted.len() {
+                for j in i + 1..adjusted.len() {
+                    let wanted = distance(&original[i], &original[j]).min(opts.min_contrast);
+                    let deficit = wanted - distance(&seen[i], &seen[j]);
+                    if deficit <= 1e-4 {
+                        continue;
+                    }
+                    // The pair that already looks lighter goes lighter; ties by position
+                    let direction = if seen[i].l >= seen[j].l { 0.5 } else { -0.5 };
+                    adjusted[i].l = (adjusted[i].l + direction * deficit).clamp(0.0, 1.0);
+                    adjusted[j].l = (adjusted[j].l - direction * deficit).clamp(0.0, 1.0);
+                    seen[i] = simulate(&adjusted[i], &matrix);
+                    seen[j] = simulate(&adjusted[j], &matrix);
+                    moved = true;
+                }
+            }
+            if !moved {
+                break;
+            }
+        }
+        for (&i, color) in visible.iter().zip(&adjusted) {
+            let [r, g, b] = encode(oklab_to_linear_srgb_in_gamut(color));
+            palette[i] = [r, g, b, palette[i][3]];
+        }
+    }
+
+    if opts.simulate {
+        for &i in &visible {
+            let [r, g, b] = encode(transform(&matrix, linear(palette[i])));
+            palette[i] = [r, g, b, palette[i][3]];
+        }
+    }
+}
+
+/// What the viewer sees of `color`, in OKLab
+fn simulate(color: &OklabColor, matrix: &[[f32; 3]; 3]) -> OklabColor {
+    let rgb = oklab_to_linear_srgb_in_gamut(color);
+    to_oklab(transform(matrix, rgb))
+}
+
+fn transform(matrix: &[[f32; 3]; 3], rgb: [f32; 3]) -> [f32; 3] {
+    matrix.map(|row| (row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2]).clamp(0.0, 1.0))
+}
+
+fn distance(a: &OklabColor, b: &OklabColor) -> f32 {
+    ((a.l - b.l).powi(2) + (a.a - b.a).powi(2) + (a.b - b.b).powi(2)).sqrt()
+}
+
+fn linear(rgba: [u8; 4]) -> [f32; 3] {
+    [rgba[0], rgba[1], rgba[2]].map(|c| {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    })
+}
+
+fn encode(rgb: [f32; 3]) -> [u8; 3] {
+    rgb.map(|c| {
+        let c = c.clamp(0.0, 1.0);
+        let c = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+        (c * 255.0).round() as u8
+    })
+}
+
+fn to_oklab([r, g, b]: [f32; 3]) -> OklabColor {
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+    OklabColor {
+        l: 0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        a: 1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        b: 0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opts(deficiency: ColorVisionDeficiency, preserve_contrast: bool, simulate: bool) -> AccessibilityOpts {
+        AccessibilityOpts { deficiency: Some(deficiency), preserve_contrast, simulate, ..Default::default() }
+    }
+
+    fn seen_distance(palette: &[[u8; 4]], deficiency: ColorVisionDeficiency) -> f32 {
+        let matrix = matrix(deficiency);
+        let [a, b] = [palette[0], palette[1]].map(|c| to_oklab(transform(&matrix, linear(c))));
+        distance(&a, &b)
+    }
+
+    #[test]
+    fn test_simulation_merges_red_and_green_for_deuteranopes() {
+        // A red and a green a deuteranope famously can't tell apart
+        let mut palette = [[200, 60, 40, 255], [60, 140, 20, 255], [0, 0, 0, 0]];
+        assert!(seen_distance(&palette, ColorVisionDeficiency::Deuteranopia) < 0.01);
+
+        apply(&mut palette, &opts(ColorVisionDeficiency::Deuteranopia, false, true));
+        assert!((0..3).all(|c| palette[0][c].abs_diff(palette[1][c]) <= 4), "{palette:?}");
+        assert_eq!(palette[2], [0, 0, 0, 0]);
+
+        // Gray has no hue to lose
+        let mut gray = [[128, 128, 128, 255]];
+        apply(&mut gray, &opts(ColorVisionDeficiency::Protanopia, false, true));
+        assert!(gray[0][..3].iter().all(|&c| c.abs_diff(128) <= 1));
+    }
+
+    #[test]
+    fn test_contrast_mode_separates_confused_pairs_only() {
+        let mut palette = [[200, 60, 40, 255], [60, 140, 20, 255]];
+        let original = palette;
+        apply(&mut palette, &opts(ColorVisionDeficiency::Deuteranopia, true, false));
+        assert!(seen_distance(&palette, ColorVisionDeficiency::Deuteranopia) >= AccessibilityOpts::default().min_contrast * 0.9);
+        assert_ne!(palette, original);
+
+        // Black and white are already far apart for everyone
+        let mut distinct = [[0, 0, 0, 255], [255, 255, 255, 255]];
+        apply(&mut distinct, &opts(ColorVisionDeficiency::Tritanopia, true, false));
+        assert_eq!(distinct, [[0, 0, 0, 255], [255, 255, 255, 255]]);
+
+        let mut untouched = original;
+        apply(&mut untouched, &AccessibilityOpts::default());
+        assert_eq!(untouched, original);
+    }
+}
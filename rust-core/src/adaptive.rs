@@ -0,0 +1,155 @@
+// Adaptive Imagequant Speed
+// Probes a clip at the fastest speed, then picks the slowest speed that fits a time budget
+
+use crate::quantization::{row_image, PixelLayout};
+use crate::{ProcessorError, QuantizeOpts, Result};
+use std::time::Instant;
+
+/// Frames remapped during the probe to time remapping
+const PROBE_FRAMES: usize = 3;
+
+/// Remapping with imagequant's dither map (speed 6 and below) costs about this much more
+const DITHER_MAP_REMAP_COST: f32 = 1.5;
+
+/// Settings picked for one clip
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveChoice {
+    pub speed: i32,
+    pub quality_min: u8,
+    pub probe_quality: u8,       // Quality reached by the speed-10 probe
+    pub estimated_ms: f32,       // Predicted quantize + remap time at `speed`
+}
+
+/// Palette search work at `speed` relative to speed 10
+///
+/// Mirrors how imagequant scales its feedback-loop trials and k-means
+/// iterations with speed; speed 10 runs neither.
+pub fn relative_cost(speed: i32) -> f32 {
+    let trials = (56 - 9 * speed).max(0);
+    let mut iterations = (8 - speed).max(0);
+    iterations += iterations * iterations / 2;
+    (1 + trials + iterations) as f32
+}
+
+/// Pick imagequant settings for `frames` so quantization fits `budget_ms`
+///
+/// Quantizes the first frame at speed 10 and remaps a few frames to time them,
+/// then predicts the run at each speed from `relative_cost`. The slowest speed
+/// from `opts.speed` up to 10 that fits wins. A clip the probe already
+/// quantizes at `quality_max` gains nothing from slower speeds and stays at 10.
+/// If nothing fits, speed 10 is used and `quality_min` drops to 0 so the
+/// encode can't fail on quality. Time spent probing counts against the budget.
+pub fn choose_settings(
+    frames: &[&[u8]],
+    width: u32,
+    height: u32,
+    opts: &QuantizeOpts,
+    budget_ms: f32,
+) -> Result<AdaptiveChoice> {
+    let start = Instant::now();
+    let first = frames.first().ok_or(ProcessorError::invalid_input("adaptive speed", "no frames to probe"))?;
+
+    let mut attr = imagequant::new();
+    attr.set_quality(0, opts.quality_max)
+        .map_err(|e| ProcessorError::quantization("adaptive speed", e))?;
+    attr.set_speed(10)
+        .map_err(|e| ProcessorError::quantization("adaptive speed", e))?;
+    attr.set_max_colors(opts.palette_size as u32)
+        .map_err(|e| ProcessorError::quantization("adaptive speed", e))?;
+
+    let to_image = |frame| row_image(&attr, frame, width, height, width as usize * 4, PixelLayout::Rgba);
+
+    let quantize_start = Instant::now();
+    let mut image = to_image(first)?;
+    let mut quantization = attr.quantize(&mut image)
+        .map_err(|e| ProcessorError::quantization("adaptive speed", e))?;
+    let quantize_ms = quantize_start.elapsed().as_secs_f32() * 1000.0;
+    let probe_quality = quantization.quantization_quality().unwrap_or(0);
+
+    quantization.set_dithering_level(opts.dithering_level)
+        .map_err(|e| ProcessorError::quantization("adaptive speed", e))?;
+    let remap_start = Instant::now();
+    let probed = frames.len().min(PROBE_FRAMES);
+    for (i, frame) in frames[..probed].iter().enumerate() {
+        let mut image = to_image(frame)?;
+        quantization.remapped(&mut image)
+            .map_err(|e| ProcessorError::quantization("adaptive speed", e).at_frame(i))?;
+    }
+    let remap_ms = remap_start.elapsed().as_secs_f32() * 1000.0 / probed as f32;
+
+    let estimate = |speed: i32| {
+        let remap_cost = if speed <= 6 { DITHER_MAP_REMAP_COST } else { 1.0 };
+        quantize_ms * relative_cost(speed) + remap_ms * remap_cost * frames.len() as f32
+    };
+    let remaining = budget_ms - start.elapsed().as_secs_f32() * 1000.0;
+
+    let slowest = opts.speed.clamp(1, 10);
+    let speed = if probe_quality >= opts.quality_max {
+        Some(10)
+    } else {
+        (slowest..=10).find(|&speed| estimate(speed) <= remaining)
+    };
+
+    Ok(match speed {
+        Some(speed) => AdaptiveChoice {
+            speed,
+            quality_min: opts.quality_min,
+            probe_quality,
+            estimated_ms: estimate(speed),
+        },
+        None => AdaptiveChoice {
+            speed: 10,
+            quality_min: 0,
+            probe_quality,
+            estimated_ms: estimate(10),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noisy_frames(count: usize, side: u32) -> Vec<Vec<u8>> {
+        (0..count)
+            .map(|f| {
+                (0..side * side)
+                    .flat_map(|i| {
+                        let v = i.wrapping_mul(2654435761).wrapping_add(f as u32 * 97);
+                        [(v >> 24) as u8, (v >> 16) as u8, (v >> 8) as u8, 255]
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cost_falls_with_speed() {
+        assert_eq!(relative_cost(10), 1.0);
+        assert!((1..10).all(|s| relative_cost(s) >= relative_cost(s + 1)));
+        assert!(relative_cost(1) > 50.0 * relative_cost(8));
+    }
+
+    #[test]
+    fn test_budget_steers_speed() {
+        let frames = noisy_frames(4, 64);
+        let refs: Vec<&[u8]> = frames.iter().map(|f| f.as_slice()).collect();
+        let opts = QuantizeOpts { speed: 1, ..Default::default() };
+
+        let generous = choose_settings(&refs, 64, 64, &opts, 1.0e6).unwrap();
+        assert_eq!(generous.speed, 1);
+        assert_eq!(generous.quality_min, opts.quality_min);
+
+        let impossible = choose_settings(&refs, 64, 64, &opts, 0.0).unwrap();
+        assert_eq!((impossible.speed, impossible.quality_min), (10, 0));
+    }
+
+    #[test]
+    fn test_simple_clip_stays_fast() {
+        let frames = [vec![40u8; 32 * 32 * 4]];
+        let refs: Vec<&[u8]> = frames.iter().map(|f| f.as_slice()).collect();
+        let choice = choose_settings(&refs, 32, 32, &QuantizeOpts::default(), 1.0e6).unwrap();
+        assert_eq!(choice.speed, 10);
+        assert!(choice.probe_quality >= 100);
+    }
+}
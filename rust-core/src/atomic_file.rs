@@ -0,0 +1,144 @@
+// Atomic File Writes
+// Saved artifacts appear whole or not at all, even if the process dies mid-write
+
+use std::fs::File;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// A file written under a hidden staging name and renamed into place by `commit`
+///
+/// The staging file sits next to `path` (a rename can't cross volumes) and is
+/// deleted if the writer is dropped without committing, so an error or a
+/// panic leaves the destination untouched: the old file, or none at all.
+pub struct AtomicFile {
+    file: File,
+    staging: PathBuf,
+    path: PathBuf,
+    committed: bool,
+}
+
+impl AtomicFile {
+    /// Start writing a replacement for `path`
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+        let mut staging_name = std::ffi::OsString::from(".");
+        staging_name.push(name);
+        staging_name.push(".partial");
+        let staging = path.with_file_name(staging_name);
+        let file = File::create(&staging)?;
+        Ok(Self { file, staging, path, committed: false })
+    }
+
+    /// Move the finished file into place
+    ///
+    /// With `sync` the data and then the rename are flushed to disk first, so
+    /// the file also survives a power loss; without it a crash can only lose
+    /// the whole file, never half of it.
+    pub fn commit(mut self, sync: bool) -> io::Result<()> {
+        self.file.flush()?;
+        if sync {
+            self.file.sync_all()?;
+        }
+        std::fs::rename(&self.staging, &self.path)?;
+        self.committed = true;
+        if sync {
+            sync_parent(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.file.write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Seek for AtomicFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = std::fs::remove_file(&self.staging);
+        }
+    }
+}
+
+/// Replace `path` with `data` in one step; see `AtomicFile::commit` for `sync`
+pub fn write(path: impl AsRef<Path>, data: &[u8], sync: bool) -> io::Result<()> {
+    let mut file = AtomicFile::create(path)?;
+    file.write_all(data)?;
+    file.commit(sync)
+}
+
+/// Persist the directory entry a rename just changed; only Unix can open a directory for this
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    match path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        Some(dir) => File::open(dir)?.sync_all(),
+        None => File::open(".")?.sync_all(),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_file_only_appears_on_commit() {
+        let dir = temp_dir("atomic-commit");
+        let path = dir.join("clip.gif");
+
+        let mut file = AtomicFile::create(&path).unwrap();
+        file.write_all(b"GIF89a").unwrap();
+        assert!(std::fs::metadata(&path).is_err());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        file.commit(true).unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"GIF89a");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_abandoned_write_keeps_the_old_file() {
+        let dir = temp_dir("atomic-abandon");
+        let path = dir.join("clip.gif");
+        write(&path, b"old", false).unwrap();
+
+        let mut file = AtomicFile::create(&path).unwrap();
+        file.write_all(b"half a new fi").unwrap();
+        drop(file);
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"old");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(write(dir.join("clip.gif"), b"", false).is_err());
+    }
+}
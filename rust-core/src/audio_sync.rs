@@ -0,0 +1,87 @@
+// Audio Sync
+// Frame delays and effect strength that follow the soundtrack's energy, for music-synced exports
+
+use crate::{Effect, EffectChain};
+
+/// Delay every frame keeps, in centiseconds; browsers slow anything shorter down to 10cs
+const MIN_DELAY_CS: u32 = 2;
+
+/// Energy of each frame still in play, scaled so the loudest input frame is 1
+///
+/// `sources` are the input frames kept, in order. A frame standing in for
+/// ones decimation dropped takes the loudest of them, so a beat on a dropped
+/// frame still lands. Frames past the end of `energy` are silent.
+pub fn frame_energy(energy: &[f32], sources: &[usize]) -> Vec<f32> {
+    let peak = energy.iter().copied().fold(0.0, f32::max);
+    sources
+        .iter()
+        .enumerate()
+        .map(|(i, &source)| {
+            let end = sources.get(i + 1).copied().filter(|&next| next > source).unwrap_or(source + 1);
+            let span = energy.get(source..end.min(energy.len())).unwrap_or(&[]);
+            let loudest = span.iter().copied().fold(0.0, f32::max);
+            if peak > 0.0 { loudest / peak } else { 0.0 }
+        })
+        .collect()
+}
+
+/// `base` delays spread again so loud frames go by faster and quiet ones linger
+///
+/// Past the 2cs each frame keeps, a frame's share of the clip's length is
+/// weighted by `1 - timing × energy`, so the export still lasts as long as the
+/// audio it was cut to. Shares are rounded running totals, like
+/// `frame_delays`, so no time is lost to rounding.
+pub fn delays(base: &[u16], energy: &[f32], timing: f32) -> Vec<u16> {
+    let total: u32 = base.iter().map(|&d| d as u32).sum();
+    let spare = total.saturating_sub(MIN_DELAY_CS * base.len() as u32) as f64;
+    let weights: Vec<f64> = (0..base.len()).map(|i| 1.0 - (timing * energy.get(i).copied().unwrap_or(0.0)) as f64).collect();
+    let sum: f64 = weights.iter().sum();
+    let (mut weight_so_far, mut given) = (0.0, 0);
+    weights
+        .iter()
+        .map(|weight| {
+            weight_so_far += weight;
+            let through = (spare * weight_so_far / sum).round() as u32;
+            let delay = MIN_DELAY_CS + through - given;
+            given = through;
+            delay.min(u16::MAX as u32) as u16
+        })
+        .collect()
+}
+
+/// `chain` for a frame of `energy`: full strength on the loudest, `1 - gain` of it in silence
+pub fn scaled_chain(chain: &EffectChain, energy: f32, gain: f32) -> EffectChain {
+    let scale = 1.0 - gain * (1.0 - energy);
+    EffectChain { effects: chain.effects.iter().map(|effect| Effect { intensity: effect.intensity * scale, ..*effect }).collect() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EffectKind;
+
+    #[test]
+    fn test_kept_frames_carry_the_loudest_they_stand_for() {
+        // Frames 1 and 2 were dropped; frame 0 stands in for them and their beat
+        let energy = [1.0, 4.0, 2.0, 0.0, 2.0];
+        assert_eq!(frame_energy(&energy, &[0, 3, 4]), [1.0, 0.0, 0.5]);
+        // Past the end is silence, and an all-silent curve stays silent
+        assert_eq!(frame_energy(&energy, &[4, 5, 9]), [0.5, 0.0, 0.0]);
+        assert_eq!(frame_energy(&[0.0; 3], &[0, 1, 2]), [0.0; 3]);
+    }
+
+    #[test]
+    fn test_delays_follow_energy_and_keep_the_length() {
+        let base = [10; 4];
+        let spread = delays(&base, &[0.0, 1.0, 0.0, 1.0], 0.5);
+        assert_eq!(spread.iter().map(|&d| d as u32).sum::<u32>(), 40);
+        assert!(spread[1] < spread[0] && spread[3] < spread[2], "{spread:?}");
+        assert_eq!(delays(&base, &[0.3; 4], 0.9), base);
+        // 50fps has nothing to spare above the 2cs floor
+        assert_eq!(delays(&[2; 3], &[1.0, 0.0, 0.5], 0.9), [2; 3]);
+
+        let chain = EffectChain { effects: vec![Effect { kind: EffectKind::Grain, intensity: 0.8, size: 1.0, seed: 0 }] };
+        assert_eq!(scaled_chain(&chain, 1.0, 0.5).effects[0].intensity, 0.8);
+        assert_eq!(scaled_chain(&chain, 0.0, 0.5).effects[0].intensity, 0.4);
+    }
+}
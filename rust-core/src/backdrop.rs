@@ -0,0 +1,49 @@
+// Preview Backdrop
+// Composites transparent GIF frames over a checkerboard or solid color, so the app can draw them as they are
+
+use crate::{Backdrop, BackdropKind};
+use rayon::prelude::*;
+
+/// Blend an RGBA canvas `width` pixels wide over `backdrop`, leaving every pixel opaque
+///
+/// The checkerboard is anchored to the canvas's top-left corner, so it stays
+/// put from frame to frame while the clip plays.
+pub fn composite(canvas: &mut [u8], width: u32, backdrop: &Backdrop) {
+    let cell = backdrop.cell_size.max(1) as usize;
+    let rgb = |c: u32| [(c >> 16) as u8, (c >> 8) as u8, c as u8];
+    let (light, dark) = (rgb(backdrop.color), rgb(backdrop.alt_color));
+
+    canvas.par_chunks_mut(width as usize * 4).enumerate().for_each(|(y, row)| {
+        for (x, px) in row.chunks_exact_mut(4).enumerate() {
+            let under = match backdrop.kind {
+                BackdropKind::Checkerboard if (x / cell + y / cell) % 2 == 1 => dark,
+                BackdropKind::Checkerboard | BackdropKind::Solid => light,
+            };
+            let alpha = px[3] as u32;
+            for c in 0..3 {
+                px[c] = ((px[c] as u32 * alpha + under[c] as u32 * (255 - alpha) + 127) / 255) as u8;
+            }
+            px[3] = 255;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transparent_pixels_show_the_backdrop() {
+        // 4×1: clear, clear, half-covered red, opaque blue
+        let frame = [[0, 0, 0, 0], [9, 9, 9, 0], [255, 0, 0, 128], [0, 0, 255, 255]].concat();
+        let checker = Backdrop { cell_size: 1, ..Default::default() };
+        let mut canvas = frame.clone();
+        composite(&mut canvas, 4, &checker);
+        assert_eq!(canvas, [[255, 255, 255, 255], [204, 204, 204, 255], [255, 127, 127, 255], [0, 0, 255, 255]].concat());
+
+        let solid = Backdrop { kind: BackdropKind::Solid, color: 0x102030, ..Default::default() };
+        let mut canvas = frame;
+        composite(&mut canvas, 4, &solid);
+        assert_eq!(canvas[..8], [16, 32, 48, 255, 16, 32, 48, 255]);
+    }
+}
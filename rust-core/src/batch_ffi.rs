@@ -5,6 +5,7 @@ use std::slice;
 use image::{imageops, RgbaImage};
 use color_quant::NeuQuant;
 use gif::{Encoder, Frame, Repeat};
+use crate::palette::{Color32, Palette};
 
 /// Process batch of RGBA frames - architecture v2 minimal FFI
 /// Returns 0 on success, negative on error
@@ -72,12 +73,8 @@ pub extern "C" fn yx_proc_batch_rgba8(
                 palette_len
             );
 
-            for (i, chunk) in palette.chunks(4).enumerate() {
-                if i >= palette_len { break; }
-                let r = chunk[0] as u32;
-                let g = chunk[1] as u32;
-                let b = chunk[2] as u32;
-                out_palette_slice[i] = (r << 16) | (g << 8) | b;
+            for (slot, color) in out_palette_slice.iter_mut().zip(&Palette::from_rgba_bytes(&palette)) {
+                *slot = color.to_rgb_u32();
             }
 
             // Quantize pixels to indices
@@ -102,7 +99,7 @@ pub extern "C" fn yx_proc_batch_rgba8(
 #[no_mangle]
 pub extern "C" fn yx_gif_encode(
     indices: *const u8,         // Palette indices for all frames
-    palettes: *const u32,       // RGB palettes for all frames
+    palettes: *const u32,       // RGB palettes for all frames (0x00RRGGBB)
     frame_count: i32,           // Number of frames
     side: i32,                  // Cube side length (132)
     delay_cs: i32,              // Delay in centiseconds
@@ -148,12 +145,11 @@ pub extern "C" fn yx_gif_encode(
                 );
 
                 // Convert palette to GIF format (RGB bytes)
-                let mut gif_palette = Vec::with_capacity(palette_size * 3);
-                for &color in frame_palette {
-                    gif_palette.push(((color >> 16) & 0xFF) as u8); // R
-                    gif_palette.push(((color >> 8) & 0xFF) as u8);  // G
-                    gif_palette.push((color & 0xFF) as u8);         // B
-                }
+                let gif_palette = frame_palette
+                    .iter()
+                    .map(|&color| Color32::from_rgb_u32(color))
+                    .collect::<Palette>()
+                    .to_rgb_bytes();
 
                 // Create GIF frame
                 let mut frame = Frame::from_palette_pixels(
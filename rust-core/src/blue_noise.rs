@@ -1,7 +1,14 @@
 // Blue Noise Dithering - Superior to Floyd-Steinberg for animations
 // Provides more pleasant error distribution without directional artifacts
 
-use crate::Result;
+use crate::color_distance::PaletteMatcher;
+use crate::oklab_quantization::OklabColor;
+
+/// Peak OKLab lightness offset applied at full dithering strength
+const OKLAB_NOISE_AMPLITUDE: f32 = 0.08;
+
+/// Default share of dithering removed on the strongest edges
+pub const DEFAULT_EDGE_PRESERVATION: f32 = 0.7;
 
 /// Pre-computed 64x64 blue noise matrix for high-quality dithering
 /// Values normalized to 0.0-1.0 range
@@ -90,19 +97,46 @@ pub struct AdaptiveBlueNoise {
     edge_map: Vec<f32>,
     width: usize,
     height: usize,
+    edge_preservation: f32,
 }
 
 impl AdaptiveBlueNoise {
     /// Create adaptive blue noise ditherer with edge detection
     pub fn new(pixels: &[u8], width: usize, height: usize) -> Self {
-        let edge_map = detect_edges(pixels, width, height);
+        Self::with_edge_preservation(pixels, width, height, DEFAULT_EDGE_PRESERVATION)
+    }
+
+    /// Ditherer that removes `edge_preservation` (0.0-1.0) of the noise on full-strength edges
+    ///
+    /// A preservation of 0.0 skips edge detection entirely and behaves like plain
+    /// blue noise.
+    pub fn with_edge_preservation(
+        pixels: &[u8],
+        width: usize,
+        height: usize,
+        edge_preservation: f32,
+    ) -> Self {
+        let edge_preservation = edge_preservation.clamp(0.0, 1.0);
+        let edge_map = if edge_preservation > 0.0 {
+            detect_edges(pixels, width, height)
+        } else {
+            vec![0.0; width * height]
+        };
+
         Self {
             edge_map,
             width,
             height,
+            edge_preservation,
         }
     }
 
+    /// Dithering strength at pixel `idx` after edge attenuation
+    #[inline]
+    fn strength_at(&self, idx: usize, base_strength: f32) -> f32 {
+        base_strength * (1.0 - self.edge_map[idx] * self.edge_preservation)
+    }
+
     /// Apply adaptive blue noise - less dithering on edges, more on gradients
     pub fn apply(
         &self,
@@ -126,8 +160,7 @@ impl AdaptiveBlueNoise {
                 // Adapt strength based on edge detection
                 // Less dithering on edges (preserves detail)
                 // More dithering on smooth areas (hides banding)
-                let edge_strength = self.edge_map[idx];
-                let strength = base_strength * (1.0 - edge_strength * 0.7);
+                let strength = self.strength_at(idx, base_strength);
 
                 // Get blue noise threshold
                 let noise = BLUE_NOISE_64[y % 64][x % 64];
@@ -148,11 +181,53 @@ impl AdaptiveBlueNoise {
 
         result
     }
+
+    /// Apply adaptive blue noise to OKLab pixels, offsetting lightness only
+    ///
+    /// Fully transparent pixels (weight 0.0) map to `transparent_index` when one
+    /// is reserved. Every pixel is independent, so frames can be dithered in
+    /// parallel.
+    pub fn apply_oklab(
+        &self,
+        pixels: &[OklabColor],
+        alpha: &[f32],
+        matcher: &PaletteMatcher,
+        transparent_index: Option<u8>,
+        base_strength: f32,
+    ) -> Vec<u8> {
+        let mut result = Vec::with_capacity(self.width * self.height);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let idx = y * self.width + x;
+
+                if alpha[idx] == 0.0 {
+                    if let Some(transparent) = transparent_index {
+                        result.push(transparent);
+                        continue;
+                    }
+                }
+
+                let noise = BLUE_NOISE_64[y % 64][x % 64] - 0.5;
+                let strength = self.strength_at(idx, base_strength);
+
+                let mut dithered = pixels[idx];
+                dithered.l += noise * strength * OKLAB_NOISE_AMPLITUDE;
+
+                result.push(matcher.nearest(&dithered) as u8);
+            }
+        }
+
+        result
+    }
 }
 
 /// Simple edge detection using Sobel operator
 fn detect_edges(pixels: &[u8], width: usize, height: usize) -> Vec<f32> {
     let mut edges = vec![0.0; width * height];
+    if width < 3 || height < 3 {
+        return edges;
+    }
 
     for y in 1..height-1 {
         for x in 1..width-1 {
@@ -242,4 +317,37 @@ pub fn temporal_blue_noise(
     }
 
     result
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oklab_quantization::srgb_to_oklab_batch;
+    use crate::DistanceMetric;
+
+    #[test]
+    fn test_full_edge_preservation_keeps_hard_edges_clean() {
+        // Black/white split down the middle of a mid-grey-palette image
+        let (width, height) = (16, 16);
+        let data: Vec<u8> = (0..width * height)
+            .flat_map(|i| if i % width < width / 2 { [0, 0, 0, 255] } else { [255, 255, 255, 255] })
+            .collect();
+        let oklab = srgb_to_oklab_batch(&data);
+        let alpha = vec![1.0; width * height];
+
+        let palette = srgb_to_oklab_batch(&[0, 0, 0, 255, 128, 128, 128, 255, 255, 255, 255, 255]);
+        let matcher = PaletteMatcher::new(&palette, DistanceMetric::Euclidean);
+
+        let preserved = AdaptiveBlueNoise::with_edge_preservation(&data, width, height, 1.0)
+            .apply_oklab(&oklab, &alpha, &matcher, None, 8.0);
+        let plain = AdaptiveBlueNoise::with_edge_preservation(&data, width, height, 0.0)
+            .apply_oklab(&oklab, &alpha, &matcher, None, 8.0);
+
+        // Columns either side of the split are full-strength edges
+        for y in 1..height - 1 {
+            assert_eq!(preserved[y * width + width / 2 - 1], 0);
+            assert_eq!(preserved[y * width + width / 2], 2);
+        }
+        assert_ne!(preserved, plain);
+    }
+}
@@ -0,0 +1,140 @@
+// Buffer Pool
+// Recycles frame-sized byte buffers across frames and captures
+
+use crate::BufferPoolStats;
+use std::sync::Mutex;
+
+/// Most bytes the shared pool holds on to; anything returned past this is freed
+pub const MAX_POOLED_BYTES: usize = 64 * 1024 * 1024;
+
+struct PoolState {
+    free: Vec<Vec<u8>>,
+    pooled_bytes: usize,
+    allocations: u64,
+    reuses: u64,
+}
+
+/// Free list of byte buffers, handed out smallest-fit
+///
+/// A burst allocates the same handful of sizes over and over (camera frame,
+/// output frame, the concatenated clip), so after the first capture nearly
+/// every `take` is served from buffers the previous one gave back.
+pub struct BufferPool {
+    state: Mutex<PoolState>,
+    max_bytes: usize,
+}
+
+/// Process-wide, so buffers outlive the capture or encode that allocated them
+pub static FRAME_POOL: BufferPool = BufferPool::new(MAX_POOLED_BYTES);
+
+impl BufferPool {
+    pub const fn new(max_bytes: usize) -> Self {
+        Self {
+            state: Mutex::new(PoolState {
+                free: Vec::new(),
+                pooled_bytes: 0,
+                allocations: 0,
+                reuses: 0,
+            }),
+            max_bytes,
+        }
+    }
+
+    /// Empty buffer with room for at least `len` bytes
+    pub fn take(&self, len: usize) -> Vec<u8> {
+        let Ok(mut state) = self.state.lock() else {
+            return Vec::with_capacity(len);
+        };
+        let best = (0..state.free.len())
+            .filter(|&i| state.free[i].capacity() >= len)
+            .min_by_key(|&i| state.free[i].capacity());
+        match best {
+            Some(i) => {
+                let mut buffer = state.free.swap_remove(i);
+                state.pooled_bytes -= buffer.capacity();
+                state.reuses += 1;
+                buffer.clear();
+                buffer
+            }
+            None => {
+                state.allocations += 1;
+                Vec::with_capacity(len)
+            }
+        }
+    }
+
+    /// Return a buffer for reuse, or free it if the pool is full
+    pub fn give(&self, buffer: Vec<u8>) {
+        let capacity = buffer.capacity();
+        if capacity == 0 {
+            return;
+        }
+        if let Ok(mut state) = self.state.lock() {
+            if state.pooled_bytes + capacity <= self.max_bytes {
+                state.pooled_bytes += capacity;
+                state.free.push(buffer);
+            }
+        }
+    }
+
+    pub fn give_all(&self, buffers: impl IntoIterator<Item = Vec<u8>>) {
+        for buffer in buffers {
+            self.give(buffer);
+        }
+    }
+
+    /// Free every pooled buffer, e.g. on a memory warning
+    pub fn release(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.free = Vec::new();
+            state.pooled_bytes = 0;
+        }
+    }
+
+    pub fn stats(&self) -> BufferPoolStats {
+        self.state
+            .lock()
+            .map(|state| BufferPoolStats {
+                allocations: state.allocations,
+                reuses: state.reuses,
+                pooled_bytes: state.pooled_bytes as u64,
+            })
+            .unwrap_or(BufferPoolStats { allocations: 0, reuses: 0, pooled_bytes: 0 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reuses_smallest_fit() {
+        let pool = BufferPool::new(1024);
+        let (small, large) = (pool.take(100), pool.take(400));
+        pool.give(large);
+        pool.give(small);
+
+        let buffer = pool.take(50);
+        assert!(buffer.capacity() >= 50 && buffer.capacity() < 400);
+        assert!(buffer.is_empty());
+        assert!(pool.take(300).capacity() >= 400);
+        // Nothing left big enough
+        pool.take(300);
+
+        let stats = pool.stats();
+        assert_eq!((stats.allocations, stats.reuses, stats.pooled_bytes), (3, 2, 0));
+    }
+
+    #[test]
+    fn test_frees_past_the_byte_limit() {
+        let pool = BufferPool::new(1000);
+        pool.give(Vec::with_capacity(600));
+        pool.give(Vec::with_capacity(600));
+        assert_eq!(pool.stats().pooled_bytes, 600);
+
+        pool.release();
+        assert_eq!(pool.stats().pooled_bytes, 0);
+        pool.take(10);
+        assert_eq!(pool.stats().allocations, 1);
+    }
+}
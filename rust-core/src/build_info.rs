@@ -0,0 +1,42 @@
+// Build Info
+// Version and compiled-in subsystems, so hosts can adapt to the library they linked
+
+/// Bumped whenever rgb2gif.udl changes in a way older bindings can't call
+pub const ABI_VERSION: u32 = 1;
+
+/// Bits of `rgb2gif_features()`; values are stable across releases
+pub const FEATURE_GPU: u32 = 1 << 0;
+pub const FEATURE_WEBP: u32 = 1 << 1;
+pub const FEATURE_APNG: u32 = 1 << 2;
+pub const FEATURE_OKLAB: u32 = 1 << 3;
+pub const FEATURE_SIMD: u32 = 1 << 4;
+
+pub fn version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
+
+/// Subsystems this build was compiled with
+///
+/// GPU, WebP and APNG have no backend yet, so their bits stay clear until one
+/// is built in. OKLab quantization is always present.
+pub fn features() -> u32 {
+    let mut features = FEATURE_OKLAB;
+    if cfg!(feature = "simd") {
+        features |= FEATURE_SIMD;
+    }
+    features
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_this_build() {
+        assert_eq!(version(), env!("CARGO_PKG_VERSION"));
+        let features = features();
+        assert_ne!(features & FEATURE_OKLAB, 0);
+        assert_eq!(features & FEATURE_SIMD != 0, cfg!(feature = "simd"));
+        assert_eq!(features & (FEATURE_GPU | FEATURE_WEBP | FEATURE_APNG), 0);
+    }
+}
@@ -0,0 +1,143 @@
+// Device Capability Probing
+// Core count, SIMD support and a short palette-matching benchmark for picking defaults
+
+use crate::color_distance::PaletteMatcher;
+use crate::oklab_quantization::srgb_to_oklab_batch;
+use crate::{DeviceCapabilities, DistanceMetric, GifOpts, ProcessorOptions, QuantizeOpts};
+use std::time::{Duration, Instant};
+
+/// Side of the benchmark frame
+const BENCH_SIDE: usize = 64;
+
+/// Palette size matched against in the benchmark
+const BENCH_COLORS: usize = 64;
+
+/// How long the benchmark keeps repeating its workload
+const BENCH_DURATION: Duration = Duration::from_millis(20);
+
+/// Effective throughput (score × cores) at or above which a device counts as fast
+const FAST_DEVICE_SCORE: f32 = 12.0;
+
+/// Below this the device gets fast settings and a tight time budget
+const SLOW_DEVICE_SCORE: f32 = 4.0;
+
+pub fn probe() -> DeviceCapabilities {
+    let instruction_set = simd_instruction_set();
+    DeviceCapabilities {
+        core_count: std::thread::available_parallelism().map_or(1, |n| n.get() as u32),
+        simd_available: instruction_set != "none",
+        simd_instruction_set: instruction_set.to_string(),
+        throughput_score: throughput_score(),
+    }
+}
+
+/// Widest vector extension the running CPU supports
+fn simd_instruction_set() -> &'static str {
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return "neon";
+        }
+    }
+    #[cfg(target_arch = "x86_64")]
+    {
+        if std::arch::is_x86_feature_detected!("avx2") {
+            return "avx2";
+        }
+        if std::arch::is_x86_feature_detected!("sse4.1") {
+            return "sse4.1";
+        }
+    }
+    "none"
+}
+
+/// Single-core megapixels per second through OKLab conversion and palette matching
+///
+/// The same inner loop the OKLab backends run per pixel, so the score tracks
+/// real encode speed across devices rather than raw clock rate.
+fn throughput_score() -> f32 {
+    let frame: Vec<u8> = (0..(BENCH_SIDE * BENCH_SIDE) as u32)
+        .flat_map(|i| {
+            let v = i.wrapping_mul(2654435761);
+            [(v >> 24) as u8, (v >> 16) as u8, (v >> 8) as u8, 255]
+        })
+        .collect();
+    let palette = srgb_to_oklab_batch(&frame[..BENCH_COLORS * 4]);
+    let matcher = PaletteMatcher::new(&palette, DistanceMetric::Euclidean);
+
+    let start = Instant::now();
+    let mut pixels = 0usize;
+    let mut checksum = 0usize;
+    while pixels == 0 || start.elapsed() < BENCH_DURATION {
+        for color in &srgb_to_oklab_batch(&frame) {
+            checksum = checksum.wrapping_add(matcher.nearest(color));
+        }
+        pixels += BENCH_SIDE * BENCH_SIDE;
+    }
+    std::hint::black_box(checksum);
+
+    pixels as f32 / start.elapsed().as_secs_f32() / 1.0e6
+}
+
+/// Settings scaled to what the device can sustain
+///
+/// Fast devices get slower, higher-quality imagequant settings. Slow devices
+/// get a fast speed plus a time budget so the adaptive mode can back off
+/// further on complex clips. GIF geometry stays at the defaults.
+pub fn auto_options(capabilities: &DeviceCapabilities) -> ProcessorOptions {
+    let effective = capabilities.throughput_score * capabilities.core_count.max(1) as f32;
+    let defaults = QuantizeOpts::default();
+
+    let quantize_opts = if effective >= FAST_DEVICE_SCORE {
+        QuantizeOpts { speed: 3, ..defaults }
+    } else if effective >= SLOW_DEVICE_SCORE {
+        QuantizeOpts { speed: 5, time_budget_ms: 4000, ..defaults }
+    } else {
+        QuantizeOpts {
+            speed: 8,
+            quality_min: 0,
+            dithering_level: 0.8,
+            time_budget_ms: 2000,
+            ..defaults
+        }
+    };
+
+    ProcessorOptions {
+        quantize_opts,
+        gif_opts: GifOpts::default(),
+        cache: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(cores: u32, score: f32) -> DeviceCapabilities {
+        DeviceCapabilities {
+            core_count: cores,
+            simd_available: true,
+            simd_instruction_set: "neon".to_string(),
+            throughput_score: score,
+        }
+    }
+
+    #[test]
+    fn test_probe_reports_plausible_values() {
+        let caps = probe();
+        assert!(caps.core_count >= 1);
+        assert!(caps.throughput_score > 0.0);
+        assert_eq!(caps.simd_available, caps.simd_instruction_set != "none");
+    }
+
+    #[test]
+    fn test_faster_devices_get_slower_settings() {
+        let fast = auto_options(&device(6, 3.0)).quantize_opts;
+        let mid = auto_options(&device(2, 3.0)).quantize_opts;
+        let slow = auto_options(&device(1, 1.5)).quantize_opts;
+
+        assert!(fast.speed < mid.speed && mid.speed < slow.speed);
+        assert_eq!(fast.time_budget_ms, 0);
+        assert!(slow.time_budget_ms > 0 && slow.time_budget_ms < mid.time_budget_ms);
+    }
+}
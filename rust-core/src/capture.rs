@@ -0,0 +1,345 @@
+// Ring-Buffer Capture Session
+// Keeps the last N seconds of downscaled camera frames for retroactive clipping
+
+use crate::buffer_pool::FRAME_POOL;
+use crate::intake::{FrameIntake, PendingFrame};
+use crate::matte;
+use crate::resize::area_resample;
+use crate::tensor::{TensorShape, SLICE_SIDE};
+use crate::validation::MAX_DIMENSION;
+use crate::{
+    deliver_tensor, finish_frame_stack, process_rgba, DropPolicy, GifOpts, IntakeStats, ProcessResult,
+    ProcessorError, QuantizeOpts, Result, TensorBuilder, TensorMode,
+};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Upper bound on buffered frames, ~34 seconds at 30fps
+const MAX_CAPACITY: usize = 1024;
+
+/// Rolling window of recent frames, stored at the session's output size
+///
+/// Frames are downscaled on push, so memory stays at `capacity × width × height × 4`
+/// regardless of camera resolution. `finalize` never drains the buffer; call
+/// `clear` to start over.
+///
+/// With `set_incremental_tensor` on, every push also lands in a `TensorBuilder`
+/// so a frame-stack tensor needs no rebuild at finalize.
+///
+/// With `set_intake` on, `push_frame` only queues the raw frame and returns;
+/// a processing thread ingests the backlog with `process_pending`, and frames
+/// the queue can't hold are dropped by the chosen policy.
+///
+/// `push_masked_frame` folds a background matte into the frame's alpha; once a
+/// session holds masked frames, `finalize` applies `GifOpts::matte_threshold`.
+pub struct CaptureSession {
+    width: u32,
+    height: u32,
+    capacity: usize,
+    ring: Mutex<VecDeque<Vec<u8>>>,
+    incremental_tensor: AtomicBool,
+    masked: AtomicBool,
+    tensor: TensorBuilder,
+    intake: FrameIntake,
+}
+
+impl CaptureSession {
+    /// Session keeping `seconds` of frames at `fps`, downscaled to `width`×`height`
+    pub fn new(width: u32, height: u32, seconds: f32, fps: u16) -> Result<Self> {
+        let capacity = (seconds * fps as f32).ceil();
+        if !(1..=MAX_DIMENSION).contains(&width) || !(1..=MAX_DIMENSION).contains(&height) || capacity.is_nan() || capacity < 1.0 || capacity as usize > MAX_CAPACITY {
+            return Err(ProcessorError::invalid_input("capture", format!("{}x{} frames kept for {}s at {}fps", width, height, seconds, fps)));
+        }
+
+        Ok(Self {
+            width,
+            height,
+            capacity: capacity as usize,
+            ring: Mutex::new(VecDeque::with_capacity(capacity as usize)),
+            incremental_tensor: AtomicBool::new(false),
+            masked: AtomicBool::new(false),
+            tensor: TensorBuilder::new(capacity as u32)?,
+            intake: FrameIntake::new(),
+        })
+    }
+
+    /// Buffer one camera frame, evicting the oldest once the window is full
+    pub fn push_frame(&self, frame_rgba: Vec<u8>, width: u32, height: u32) -> Result<()> {
+        let expected = (width as usize).checked_mul(height as usize).and_then(|pixels| pixels.checked_mul(4));
+        if width == 0 || height == 0 || Some(frame_rgba.len()) != expected {
+            return Err(ProcessorError::invalid_input("capture", format!("{} bytes for a {}x{} frame", frame_rgba.len(), width, height)));
+        }
+
+        if self.intake.is_enabled() {
+            self.intake.offer(PendingFrame { rgba: frame_rgba, width, height });
+            return Ok(());
+        }
+        self.ingest(&frame_rgba, width, height)
+    }
+
+    /// `push_frame` with a one byte per pixel matte multiplied into its alpha
+    pub fn push_masked_frame(&self, mut frame_rgba: Vec<u8>, mask: Vec<u8>, width: u32, height: u32) -> Result<()> {
+        if Some(mask.len()) != (width as usize).checked_mul(height as usize) || frame_rgba.len() != mask.len() * 4 {
+            return Err(ProcessorError::invalid_input("capture", format!("{} mask bytes for a {}x{} frame", mask.len(), width, height)));
+        }
+        matte::multiply(&mut frame_rgba, &mask);
+        self.masked.store(true, Ordering::Relaxed);
+        self.push_frame(frame_rgba, width, height)
+    }
+
+    /// Queue pushes in a bounded intake of `capacity` frames (0 = ingest directly)
+    ///
+    /// Frames already queued stay queued; turning the intake off ingests them.
+    pub fn set_intake(&self, capacity: u32, policy: DropPolicy) -> Result<()> {
+        if capacity as usize > MAX_CAPACITY {
+            return Err(ProcessorError::invalid_input("capture intake", format!("capacity {} is over {}", capacity, MAX_CAPACITY)));
+        }
+        if capacity == 0 {
+            self.process_pending(0)?;
+        }
+        self.intake.configure(capacity as usize, policy);
+        Ok(())
+    }
+
+    /// Ingest up to `max_frames` queued frames (0 = all) and return how many
+    pub fn process_pending(&self, max_frames: u32) -> Result<u32> {
+        let pending = self.intake.take(max_frames as usize);
+        for frame in &pending {
+            self.ingest(&frame.rgba, frame.width, frame.height)?;
+        }
+        Ok(pending.len() as u32)
+    }
+
+    /// Frames received, dropped and still queued since the last `clear`
+    pub fn intake_stats(&self) -> IntakeStats {
+        self.intake.stats()
+    }
+
+    /// Downscale a validated frame into the ring
+    fn ingest(&self, frame_rgba: &[u8], width: u32, height: u32) -> Result<()> {
+        let mut ring = self.ring.lock().map_err(|e| ProcessorError::memory("capture", e))?;
+
+        // Fed under the ring lock so both windows always cover the same frames
+        if self.incremental_tensor.load(Ordering::Relaxed) {
+            self.tensor.push_rgba(frame_rgba, width, height)?;
+        }
+
+        // Reuse the evicted frame's allocation
+        let mut slot = if ring.len() == self.capacity {
+            ring.pop_front().unwrap_or_default()
+        } else {
+            FRAME_POOL.take((self.width * self.height * 4) as usize)
+        };
+
+        if width == self.width && height == self.height {
+            slot.clear();
+            slot.extend_from_slice(frame_rgba);
+        } else {
+            area_resample(frame_rgba, width, height, self.width, self.height, &mut slot);
+        }
+
+        ring.push_back(slot);
+        Ok(())
+    }
+
+    /// Frames currently buffered
+    pub fn frame_count(&self) -> u32 {
+        self.ring.lock().map(|ring| ring.len() as u32).unwrap_or(0)
+    }
+
+    /// Maximum frames the window holds
+    pub fn capacity(&self) -> u32 {
+        self.capacity as u32
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut ring) = self.ring.lock() {
+            FRAME_POOL.give_all(ring.drain(..));
+            self.tensor.clear();
+            self.intake.clear();
+            self.masked.store(false, Ordering::Relaxed);
+        }
+    }
+
+    /// Build the frame-stack tensor as frames arrive instead of at finalize
+    ///
+    /// Takes effect from the next push; turning it off drops the partial tensor.
+    /// The incremental tensor skips `seamless_loop` blending of the tail.
+    pub fn set_incremental_tensor(&self, enabled: bool) {
+        if let Ok(_ring) = self.ring.lock() {
+            self.incremental_tensor.store(enabled, Ordering::Relaxed);
+            if !enabled {
+                self.tensor.clear();
+            }
+        }
+    }
+
+    /// Encode the most recent `frame_count` frames (or all, if fewer are buffered)
+    ///
+    /// `gif_opts` width, height and frame count are overridden with the session's
+    /// frame size and the number of frames actually taken. Frames still in the
+    /// intake are ingested first; `dropped_frames` reports the intake's losses.
+    /// A masked session rebuilds its tensor, since the matte threshold only
+    /// arrives here.
+    pub fn finalize(
+        &self,
+        frame_count: u32,
+        quantize_opts: QuantizeOpts,
+        gif_opts: GifOpts,
+    ) -> Result<ProcessResult> {
+        self.process_pending(0)?;
+
+        // The builder only produces default-sized slices
+        let wants_stack = gif_opts.include_tensor
+            && gif_opts.tensor.mode == TensorMode::FrameStack
+            && (gif_opts.tensor.cube_width as u32, gif_opts.tensor.cube_height as u32) == (SLICE_SIDE, SLICE_SIDE);
+        let (frames_rgba, taken, prebuilt) = {
+            let ring = self.ring.lock().map_err(|e| ProcessorError::memory("capture", e))?;
+            let taken = (frame_count as usize).min(ring.len());
+            if taken == 0 {
+                return Err(ProcessorError::invalid_input("capture", "no frames captured"));
+            }
+
+            let mut frames_rgba = FRAME_POOL.take(taken * (self.width * self.height * 4) as usize);
+            for frame in ring.iter().skip(ring.len() - taken) {
+                frames_rgba.extend_from_slice(frame);
+            }
+
+            // Only usable once the builder has seen every frame being taken
+            let masked = self.masked.load(Ordering::Relaxed);
+            if masked {
+                matte::cut(&mut frames_rgba, gif_opts.matte_threshold);
+            }
+            let ready = self.incremental_tensor.load(Ordering::Relaxed) && !masked && self.tensor.frame_count() as usize >= taken;
+            let prebuilt = if wants_stack && ready { Some(self.tensor.finish(taken as u32)?) } else { None };
+            (frames_rgba, taken, prebuilt)
+        };
+
+        let gif_opts = GifOpts {
+            width: self.width as u16,
+            height: self.height as u16,
+            frame_count: taken as u32,
+            include_tensor: gif_opts.include_tensor && prebuilt.is_none(),
+            ..gif_opts
+        };
+        let tensor_opts = gif_opts.clone();
+
+        let result = process_rgba(&frames_rgba, self.width, self.height, taken as u32, quantize_opts, gif_opts);
+        FRAME_POOL.give(frames_rgba);
+        let mut result = result?;
+        if let Some(tensor) = prebuilt {
+            let (tensor, motion) = finish_frame_stack(tensor, &tensor_opts)?;
+            result.motion_data = motion;
+            let shape = TensorShape::new(SLICE_SIDE, SLICE_SIDE, taken as u32);
+            deliver_tensor(&mut result, tensor, shape, &tensor_opts.tensor)?;
+        }
+        result.dropped_frames = self.intake.stats().dropped.min(u32::MAX as u64) as u32;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_keeps_most_recent_frames() {
+        let session = CaptureSession::new(2, 2, 1.0, 3).unwrap();
+        assert_eq!(session.capacity(), 3);
+
+        for i in 0..5u8 {
+            session.push_frame(vec![i; 16], 2, 2).unwrap();
+        }
+        assert_eq!(session.frame_count(), 3);
+
+        let ring = session.ring.lock().unwrap();
+        let firsts: Vec<u8> = ring.iter().map(|f| f[0]).collect();
+        assert_eq!(firsts, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_push_downscales_to_session_size() {
+        let session = CaptureSession::new(2, 1, 1.0, 1).unwrap();
+        // 4x2 frame: left half black, right half white
+        let frame: Vec<u8> = (0..8).flat_map(|i| if i % 4 < 2 { [0; 4] } else { [255; 4] }).collect();
+        session.push_frame(frame, 4, 2).unwrap();
+
+        let ring = session.ring.lock().unwrap();
+        assert_eq!(ring[0], vec![0, 0, 0, 0, 255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_rejects_bad_window_and_frames() {
+        assert!(CaptureSession::new(0, 16, 1.0, 30).is_err());
+        assert!(CaptureSession::new(16, 16, 0.0, 30).is_err());
+        assert!(CaptureSession::new(16, 16, f32::NAN, 30).is_err());
+        assert!(CaptureSession::new(MAX_DIMENSION + 1, 16, 1.0, 30).is_err());
+
+        let session = CaptureSession::new(4, 4, 1.0, 4).unwrap();
+        assert!(session.push_frame(vec![0; 10], 4, 4).is_err());
+        // 65536² × 4 wraps to 0 in u32
+        assert!(session.push_frame(Vec::new(), 1 << 16, 1 << 16).is_err());
+        assert!(session.push_masked_frame(Vec::new(), Vec::new(), 1 << 16, 1 << 16).is_err());
+        assert!(session.finalize(8, QuantizeOpts::default(), GifOpts::default()).is_err());
+    }
+
+    #[test]
+    fn test_incremental_tensor_matches_rebuilt_tensor() {
+        let session = CaptureSession::new(16, 16, 1.0, 6).unwrap();
+        session.set_incremental_tensor(true);
+        for i in 0..8u8 {
+            let frame: Vec<u8> = (0..16 * 16).flat_map(|p| [(p as u8).wrapping_mul(i), i * 20, 90, 255]).collect();
+            session.push_frame(frame, 16, 16).unwrap();
+        }
+        assert_eq!(session.tensor.frame_count(), 6);
+
+        let gif_opts = GifOpts { include_tensor: true, ..Default::default() };
+        let quantize_opts = QuantizeOpts { quality_min: 0, ..Default::default() };
+        let incremental = session.finalize(4, quantize_opts.clone(), gif_opts.clone()).unwrap();
+
+        session.set_incremental_tensor(false);
+        let rebuilt = session.finalize(4, quantize_opts, gif_opts).unwrap();
+        assert_eq!(incremental.tensor_data, rebuilt.tensor_data);
+        assert_eq!(incremental.tensor_data.map(|t| t.len()), Some(128 * 128 * 4 * 4));
+    }
+
+    #[test]
+    fn test_intake_defers_ingest_and_reports_drops() {
+        let session = CaptureSession::new(4, 4, 1.0, 8).unwrap();
+        session.set_intake(3, DropPolicy::DropOldest).unwrap();
+        for i in 0..5u8 {
+            session.push_frame(vec![i * 40; 64], 4, 4).unwrap();
+        }
+        assert_eq!(session.frame_count(), 0);
+        assert_eq!(session.process_pending(1).unwrap(), 1);
+        assert_eq!(session.frame_count(), 1);
+
+        let quantize_opts = QuantizeOpts { quality_min: 0, ..Default::default() };
+        let result = session.finalize(8, quantize_opts, GifOpts::default()).unwrap();
+        assert_eq!(result.actual_frame_count, 3);
+        assert_eq!(result.dropped_frames, 2);
+
+        let stats = session.intake_stats();
+        assert_eq!((stats.received, stats.dropped, stats.pending), (5, 2, 0));
+        assert!(session.set_intake(MAX_CAPACITY as u32 + 1, DropPolicy::Decimate).is_err());
+    }
+
+    #[test]
+    fn test_masked_frames_are_cut_at_finalize() {
+        let session = CaptureSession::new(2, 1, 1.0, 2).unwrap();
+        session.set_incremental_tensor(true);
+        assert!(session.push_masked_frame(vec![200; 8], vec![255], 2, 1).is_err());
+        session.push_masked_frame([[200, 40, 40, 255], [40, 200, 40, 255]].concat(), vec![255, 90], 2, 1).unwrap();
+        assert_eq!(session.ring.lock().unwrap()[0][7], 90);
+
+        let gif_opts = GifOpts { include_tensor: true, ..Default::default() };
+        let result = session.finalize(1, QuantizeOpts { quality_min: 0, ..Default::default() }, gif_opts).unwrap();
+        // The faint half is below the default threshold, so the tensor was rebuilt with it keyed out
+        let tensor = result.tensor_data.unwrap();
+        assert_eq!((tensor[3], tensor[tensor.len() - 1]), (255, 0));
+
+        session.clear();
+        assert!(!session.masked.load(Ordering::Relaxed));
+    }
+}
@@ -0,0 +1,190 @@
+// Quantization Checkpoints
+// Persists indexed frames and palette so an interrupted encode can skip requantizing
+
+use crate::{atomic_file, ProcessorError, Result};
+use std::fs::File;
+use std::io::Read;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAGIC: &[u8; 4] = b"RGBQ";
+
+/// Bumped whenever the layout below changes; older files are rejected
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Header: magic, version, created, expires, width, height, frames, palette len, transparent
+const HEADER_BYTES: usize = 4 + 2 + 8 + 8 + 4 + 4 + 4 + 2 + 2;
+
+/// Stored in place of a transparent index when there is none
+const NO_TRANSPARENT: u16 = 0xFFFF;
+
+/// Quantization output, everything the GIF encoder needs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub width: u32,
+    pub height: u32,
+    pub palette: Vec<[u8; 4]>,
+    pub transparent_index: Option<u8>,
+    pub indexed_frames: Vec<Vec<u8>>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Write quantized frames to `path` as a checkpoint valid for `ttl_secs`
+///
+/// Goes through a temporary file and a rename, so a process killed mid-write
+/// never leaves a truncated checkpoint behind.
+pub fn save(
+    path: &str,
+    width: u32,
+    height: u32,
+    palette: &[[u8; 4]],
+    transparent_index: Option<u8>,
+    indexed_frames: &[Vec<u8>],
+    ttl_secs: u32,
+) -> Result<()> {
+    let frame_size = (width as usize).checked_mul(height as usize);
+    if palette.is_empty() || palette.len() > 256 || indexed_frames.iter().any(|f| Some(f.len()) != frame_size) {
+        return Err(ProcessorError::invalid_input("checkpoint", "palette or frame sizes don't match the clip"));
+    }
+
+    let created = now_secs();
+    let frames_len: usize = indexed_frames.iter().map(Vec::len).sum();
+    let mut out = Vec::with_capacity(HEADER_BYTES + palette.len() * 4 + frames_len);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    out.extend_from_slice(&created.to_le_bytes());
+    out.extend_from_slice(&(created + ttl_secs as u64).to_le_bytes());
+    out.extend_from_slice(&width.to_le_bytes());
+    out.extend_from_slice(&height.to_le_bytes());
+    out.extend_from_slice(&(indexed_frames.len() as u32).to_le_bytes());
+    out.extend_from_slice(&(palette.len() as u16).to_le_bytes());
+    out.extend_from_slice(&transparent_index.map_or(NO_TRANSPARENT, u16::from).to_le_bytes());
+    out.extend(palette.iter().flatten());
+    for frame in indexed_frames {
+        out.extend_from_slice(frame);
+    }
+
+    atomic_file::write(path, &out, true).map_err(|e| ProcessorError::memory("checkpoint", e))
+}
+
+/// Read a checkpoint written by `save`
+///
+/// Wrong magic or version, truncation and expiry all fail with `InvalidInput`;
+/// an expired file is deleted on the way out.
+pub fn load(path: &str) -> Result<Checkpoint> {
+    let mut data = Vec::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut data))
+        .map_err(|e| ProcessorError::invalid_input("checkpoint", e))?;
+
+    if data.len() < HEADER_BYTES || &data[..4] != MAGIC {
+        return Err(ProcessorError::invalid_input("checkpoint", "not a checkpoint file"));
+    }
+    let u16_at = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let u32_at = |at: usize| u32::from_le_bytes(data[at..at + 4].try_into().unwrap());
+    let u64_at = |at: usize| u64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+
+    if u16_at(4) != FORMAT_VERSION {
+        return Err(ProcessorError::invalid_input("checkpoint", format!("format version {} is unsupported", u16_at(4))));
+    }
+    if now_secs() >= u64_at(14) {
+        let _ = std::fs::remove_file(path);
+        return Err(ProcessorError::invalid_input("checkpoint", "checkpoint has expired"));
+    }
+
+    let (width, height, frame_count) = (u32_at(22), u32_at(26), u32_at(30) as usize);
+    let palette_len = u16_at(34) as usize;
+    let transparent_index = match u16_at(36) {
+        NO_TRANSPARENT => None,
+        index => Some(index as u8),
+    };
+
+    let palette_end = HEADER_BYTES + palette_len * 4;
+    let frame_size = (width as usize).checked_mul(height as usize).filter(|&size| size > 0);
+    let expected_len = frame_size
+        .and_then(|size| size.checked_mul(frame_count))
+        .and_then(|frames_len| frames_len.checked_add(palette_end));
+    let valid = (1..=256).contains(&palette_len) && expected_len == Some(data.len());
+    let Some(frame_size) = frame_size.filter(|_| valid) else {
+        return Err(ProcessorError::invalid_input("checkpoint", "checkpoint is truncated or corrupt"));
+    };
+
+    Ok(Checkpoint {
+        width,
+        height,
+        palette: data[HEADER_BYTES..palette_end]
+            .chunks_exact(4)
+            .map(|c| [c[0], c[1], c[2], c[3]])
+            .collect(),
+        transparent_index,
+        indexed_frames: data[palette_end..].chunks_exact(frame_size).map(|f| f.to_vec()).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("{name}-{}.rgbq", std::process::id()));
+        path.to_string_lossy().into_owned()
+    }
+
+    fn save_sample(path: &str, ttl_secs: u32) {
+        let c = sample();
+        save(path, c.width, c.height, &c.palette, c.transparent_index, &c.indexed_frames, ttl_secs).unwrap();
+    }
+
+    fn sample() -> Checkpoint {
+        Checkpoint {
+            width: 3,
+            height: 2,
+            palette: vec![[0, 0, 0, 255], [255, 0, 0, 255], [0, 0, 0, 0]],
+            transparent_index: Some(2),
+            indexed_frames: vec![vec![0, 1, 2, 1, 0, 1], vec![1; 6]],
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let path = temp_path("checkpoint-round-trip");
+        save_sample(&path, 60);
+        assert_eq!(load(&path).unwrap(), sample());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_expired_and_foreign_files_are_rejected() {
+        let path = temp_path("checkpoint-expired");
+        save_sample(&path, 0);
+        assert!(load(&path).is_err());
+        assert!(std::fs::metadata(&path).is_err(), "expired checkpoint should be deleted");
+
+        save_sample(&path, 60);
+        let mut data = std::fs::read(&path).unwrap();
+        data[4] = FORMAT_VERSION as u8 + 1;
+        std::fs::write(&path, &data).unwrap();
+        assert!(load(&path).is_err());
+
+        data.truncate(HEADER_BYTES + 5);
+        data[4] = FORMAT_VERSION as u8;
+        std::fs::write(&path, &data).unwrap();
+        assert!(load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_oversized_header_is_rejected() {
+        let path = temp_path("checkpoint-oversized");
+        save_sample(&path, 60);
+        let mut data = std::fs::read(&path).unwrap();
+        for at in [22, 26, 30] {
+            data[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        }
+        std::fs::write(&path, &data).unwrap();
+        assert!(load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
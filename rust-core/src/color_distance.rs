@@ -0,0 +1,371 @@
+// Color Distance Metrics for Palette Matching
+// Euclidean OKLab (fast) and CIEDE2000 (perceptually accurate) nearest-color search
+
+use crate::oklab_quantization::{oklab_to_linear_srgb, OklabColor};
+use crate::DistanceMetric;
+use rayon::prelude::*;
+
+/// Candidates kept by the fast ΔE2000 mode before exact re-ranking
+const FAST_SHORTLIST: usize = 4;
+
+/// CIELAB color (D65 white point)
+#[derive(Clone, Copy, Debug)]
+pub struct CieLab {
+    pub l: f32,
+    pub a: f32,
+    pub b: f32,
+}
+
+/// Convert OKLab to CIELAB via linear sRGB and XYZ
+pub fn oklab_to_cielab(color: &OklabColor) -> CieLab {
+    let [r, g, b] = oklab_to_linear_srgb(color);
+
+    // Linear sRGB to XYZ, normalized to the D65 white point
+    let x = (0.4124564 * r + 0.3575761 * g + 0.1804375 * b) / 0.95047;
+    let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+    let z = (0.0193339 * r + 0.119192 * g + 0.9503041 * b) / 1.08883;
+
+    let f = |t: f32| {
+        const DELTA: f32 = 6.0 / 29.0;
+        if t > DELTA * DELTA * DELTA {
+            t.cbrt()
+        } else {
+            t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+        }
+    };
+
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    CieLab {
+        l: 116.0 * fy - 16.0,
+        a: 500.0 * (fx - fy),
+        b: 200.0 * (fy - fz),
+    }
+}
+
+/// CIEDE2000 color difference (Sharma, Wu & Dalal formulation)
+pub fn delta_e_2000(c1: &CieLab, c2: &CieLab) -> f32 {
+    use std::f32::consts::PI;
+
+    let pow7 = |v: f32| v.powi(7);
+    let twenty_five_7 = 6_103_515_625.0f32; // 25^7
+
+    let c1_ab = (c1.a * c1.a + c1.b * c1.b).sqrt();
+    let c2_ab = (c2.a * c2.a + c2.b * c2.b).sqrt();
+    let c_bar = (c1_ab + c2_ab) / 2.0;
+    let g = 0.5 * (1.0 - (pow7(c_bar) / (pow7(c_bar) + twenty_five_7)).sqrt());
+
+    let a1 = (1.0 + g) * c1.a;
+    let a2 = (1.0 + g) * c2.a;
+    let c1p = (a1 * a1 + c1.b * c1.b).sqrt();
+    let c2p = (a2 * a2 + c2.b * c2.b).sqrt();
+
+    let hue = |b: f32, a: f32| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            let h = b.atan2(a);
+            if h < 0.0 { h + 2.0 * PI } else { h }
+        }
+    };
+    let h1p = hue(c1.b, a1);
+    let h2p = hue(c2.b, a2);
+
+    let dl = c2.l - c1.l;
+    let dc = c2p - c1p;
+    let dh = if c1p * c2p == 0.0 {
+        0.0
+    } else {
+        let diff = h2p - h1p;
+        if diff > PI {
+            diff - 2.0 * PI
+        } else if diff < -PI {
+            diff + 2.0 * PI
+        } else {
+            diff
+        }
+    };
+    let d_big_h = 2.0 * (c1p * c2p).sqrt() * (dh / 2.0).sin();
+
+    let l_bar = (c1.l + c2.l) / 2.0;
+    let c_bar_p = (c1p + c2p) / 2.0;
+    let h_bar_p = if c1p * c2p == 0.0 {
+        h1p + h2p
+    } else if (h1p - h2p).abs() <= PI {
+        (h1p + h2p) / 2.0
+    } else if h1p + h2p < 2.0 * PI {
+        (h1p + h2p + 2.0 * PI) / 2.0
+    } else {
+        (h1p + h2p - 2.0 * PI) / 2.0
+    };
+
+    let t = 1.0 - 0.17 * (h_bar_p - PI / 6.0).cos()
+        + 0.24 * (2.0 * h_bar_p).cos()
+        + 0.32 * (3.0 * h_bar_p + PI / 30.0).cos()
+        - 0.20 * (4.0 * h_bar_p - 63.0 * PI / 180.0).cos();
+
+    let delta_theta = (PI / 6.0) * (-((h_bar_p * 180.0 / PI - 275.0) / 25.0).powi(2)).exp();
+    let r_c = 2.0 * (pow7(c_bar_p) / (pow7(c_bar_p) + twenty_five_7)).sqrt();
+    let l_term = (l_bar - 50.0) * (l_bar - 50.0);
+    let s_l = 1.0 + 0.015 * l_term / (20.0 + l_term).sqrt();
+    let s_c = 1.0 + 0.045 * c_bar_p;
+    let s_h = 1.0 + 0.015 * c_bar_p * t;
+    let r_t = -(2.0 * delta_theta).sin() * r_c;
+
+    let l_part = dl / s_l;
+    let c_part = dc / s_c;
+    let h_part = d_big_h / s_h;
+
+    (l_part * l_part + c_part * c_part + h_part * h_part + r_t * c_part * h_part).sqrt()
+}
+
+/// Squared Euclidean distance in OKLab
+#[inline]
+fn oklab_distance_sq(a: &OklabColor, b: &OklabColor) -> f32 {
+    let dl = a.l - b.l;
+    let da = a.a - b.a;
+    let db = a.b - b.b;
+    dl * dl + da * da + db * db
+}
+
+/// Nearest-color search against a fixed palette
+///
+/// CIELAB palette entries are converted once up front, so ΔE2000 only costs one
+/// conversion per query pixel plus the pairwise formula.
+pub struct PaletteMatcher<'a> {
+    palette: &'a [OklabColor],
+    palette_lab: Vec<CieLab>,
+    metric: DistanceMetric,
+    lut: Option<NearestLut>,
+}
+
+/// Answers for a grid over the palette's OKLab bounding box, one per cell center
+///
+/// Colors outside the box clamp onto its faces, which is where their nearest
+/// entry usually sits anyway.
+struct NearestLut {
+    resolution: usize,
+    min: [f32; 3],
+    cells_per_unit: [f32; 3],
+    table: Vec<u8>,
+}
+
+impl NearestLut {
+    #[inline]
+    fn lookup(&self, color: &OklabColor) -> usize {
+        let cell = |v: f32, axis: usize| {
+            (((v - self.min[axis]) * self.cells_per_unit[axis]) as isize).clamp(0, self.resolution as isize - 1) as usize
+        };
+        let n = self.resolution;
+        self.table[(cell(color.l, 0) * n + cell(color.a, 1)) * n + cell(color.b, 2)] as usize
+    }
+}
+
+impl<'a> PaletteMatcher<'a> {
+    pub fn new(palette: &'a [OklabColor], metric: DistanceMetric) -> Self {
+        let palette_lab = match metric {
+            DistanceMetric::Euclidean => Vec::new(),
+            DistanceMetric::Ciede2000 | DistanceMetric::Ciede2000Fast => {
+                palette.iter().map(oklab_to_cielab).collect()
+            }
+        };
+
+        Self { palette, palette_lab, metric, lut: None }
+    }
+
+    /// Answer `nearest` from a `resolution`³ table built now, instead of searching
+    ///
+    /// Costs one search per cell up front, so it pays off once there are many
+    /// more pixels than cells, as across a clip. Stateless dithers suit it best:
+    /// their matches don't feed errors forward, so a cell-sized miss stays put.
+    /// Resolutions below 2, and palettes over 256 entries, keep searching.
+    pub fn with_lut(mut self, resolution: u16) -> Self {
+        let n = resolution as usize;
+        if n < 2 || self.palette.is_empty() || self.palette.len() > 256 {
+            return self;
+        }
+
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for p in self.palette {
+            for (axis, v) in [p.l, p.a, p.b].into_iter().enumerate() {
+                min[axis] = min[axis].min(v);
+                max[axis] = max[axis].max(v);
+            }
+        }
+        let span = |axis: usize| (max[axis] - min[axis]).max(1.0e-6);
+        let step = [span(0) / n as f32, span(1) / n as f32, span(2) / n as f32];
+
+        let table = (0..n * n * n)
+            .into_par_iter()
+            .map(|i| {
+                let center = |cell: usize, axis: usize| min[axis] + (cell as f32 + 0.5) * step[axis];
+                let color = OklabColor { l: center(i / (n * n), 0), a: center(i / n % n, 1), b: center(i % n, 2) };
+                self.search(&color) as u8
+            })
+            .collect();
+
+        self.lut = Some(NearestLut {
+            resolution: n,
+            min,
+            cells_per_unit: [1.0 / step[0], 1.0 / step[1], 1.0 / step[2]],
+            table,
+        });
+        self
+    }
+
+    /// Index of the closest palette entry (0 for an empty palette)
+    ///
+    /// With a table from `with_lut`, the closest to the center of the color's cell.
+    pub fn nearest(&self, color: &OklabColor) -> usize {
+        match &self.lut {
+            Some(lut) => lut.lookup(color),
+            None => self.search(color),
+        }
+    }
+
+    fn search(&self, color: &OklabColor) -> usize {
+        match self.metric {
+            DistanceMetric::Euclidean => self.nearest_euclidean(color),
+            DistanceMetric::Ciede2000 => {
+                let lab = oklab_to_cielab(color);
+                (0..self.palette.len())
+                    .map(|i| (i, delta_e_2000(&lab, &self.palette_lab[i])))
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(i, _)| i)
+                    .unwrap_or(0)
+            }
+            DistanceMetric::Ciede2000Fast => self.nearest_shortlisted(color),
+        }
+    }
+
+    fn nearest_euclidean(&self, color: &OklabColor) -> usize {
+        self.palette
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (i, oklab_distance_sq(color, p)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+
+    /// Shortlist by OKLab distance, then re-rank the few survivors with ΔE2000
+    fn nearest_shortlisted(&self, color: &OklabColor) -> usize {
+        let mut shortlist = [(f32::MAX, 0usize); FAST_SHORTLIST];
+
+        for (i, p) in self.palette.iter().enumerate() {
+            let d = oklab_distance_sq(color, p);
+            if d < shortlist[FAST_SHORTLIST - 1].0 {
+                let mut slot = FAST_SHORTLIST - 1;
+                while slot > 0 && shortlist[slot - 1].0 > d {
+                    shortlist[slot] = shortlist[slot - 1];
+                    slot -= 1;
+                }
+                shortlist[slot] = (d, i);
+            }
+        }
+
+        let lab = oklab_to_cielab(color);
+        shortlist
+            .iter()
+            .filter(|(d, _)| *d < f32::MAX)
+            .map(|&(_, i)| (i, delta_e_2000(&lab, &self.palette_lab[i])))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta_e_2000_reference_pairs() {
+        // Pairs 1, 7 and 17 from Sharma et al., "The CIEDE2000 Color-Difference Formula"
+        let cases = [
+            ((50.0, 2.6772, -79.7751), (50.0, 0.0, -82.7485), 2.0425),
+            ((50.0, 0.0, 0.0), (50.0, -1.0, 2.0), 2.3669),
+            ((50.0, 2.5, 0.0), (73.0, 25.0, -18.0), 27.1492),
+        ];
+
+        for ((l1, a1, b1), (l2, a2, b2), expected) in cases {
+            let d = delta_e_2000(&CieLab { l: l1, a: a1, b: b1 }, &CieLab { l: l2, a: a2, b: b2 });
+            assert!((d - expected).abs() < 1e-3, "ΔE2000 {d} != {expected}");
+        }
+    }
+
+    #[test]
+    fn test_white_maps_to_cielab_white() {
+        let white = OklabColor { l: 1.0, a: 0.0, b: 0.0 };
+        let lab = oklab_to_cielab(&white);
+        assert!((lab.l - 100.0).abs() < 0.1);
+        assert!(lab.a.abs() < 0.1 && lab.b.abs() < 0.1);
+    }
+
+    #[test]
+    fn test_fast_mode_agrees_with_exact_on_distinct_palette() {
+        let palette: Vec<OklabColor> = (0..16)
+            .map(|i| {
+                let t = i as f32 / 15.0;
+                OklabColor { l: 0.2 + 0.7 * t, a: 0.2 * (t * 6.0).cos(), b: 0.2 * (t * 6.0).sin() }
+            })
+            .collect();
+
+        let exact = PaletteMatcher::new(&palette, DistanceMetric::Ciede2000);
+        let fast = PaletteMatcher::new(&palette, DistanceMetric::Ciede2000Fast);
+
+        for p in &palette {
+            let probe = OklabColor { l: p.l + 0.01, a: p.a, b: p.b - 0.005 };
+            assert_eq!(exact.nearest(&probe), fast.nearest(&probe));
+        }
+    }
+
+    /// Smooth ramps through the gamut, so neighbouring entries are close together
+    fn ramp_palette(colors: usize) -> Vec<OklabColor> {
+        (0..colors)
+            .map(|i| {
+                let t = i as f32 / (colors - 1) as f32;
+                OklabColor { l: 0.1 + 0.8 * t, a: 0.15 * (t * 23.0).cos(), b: 0.15 * (t * 17.0).sin() }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lut_clip_is_nearly_as_close_as_searching() {
+        // 256 frames of 32×32 drifting colors against a full palette
+        let palette = ramp_palette(256);
+        let pixels: Vec<OklabColor> = (0..256 * 32 * 32u32)
+            .map(|i| {
+                let (x, z) = ((i % 1024) as f32 / 1024.0, (i / 1024) as f32 / 256.0);
+                OklabColor { l: 0.05 + 0.9 * x, a: 0.2 * (x * 9.0 + z).sin(), b: 0.2 * (z * 5.0 - x).cos() }
+            })
+            .collect();
+        let distance = |p: &OklabColor, i: usize| oklab_distance_sq(p, &palette[i]).sqrt();
+
+        let searched = PaletteMatcher::new(&palette, DistanceMetric::Euclidean);
+        let exact: Vec<usize> = pixels.iter().map(|p| searched.nearest(p)).collect();
+
+        let table = PaletteMatcher::new(&palette, DistanceMetric::Euclidean).with_lut(32);
+        let looked_up: Vec<usize> = pixels.iter().map(|p| table.nearest(p)).collect();
+
+        let mean = |indices: &[usize]| pixels.iter().zip(indices).map(|(p, &i)| distance(p, i)).sum::<f32>() / pixels.len() as f32;
+        let (exact_error, lut_error) = (mean(&exact), mean(&looked_up));
+        assert!(lut_error <= exact_error * 1.25 + 0.002, "ΔE {lut_error} with the table vs {exact_error} searched");
+    }
+
+    #[test]
+    fn test_lut_matches_search_at_palette_entries_and_clamps() {
+        let palette = ramp_palette(12);
+        let matcher = PaletteMatcher::new(&palette, DistanceMetric::Ciede2000Fast).with_lut(48);
+        for (i, p) in palette.iter().enumerate() {
+            assert_eq!(matcher.nearest(p), i);
+        }
+
+        // Far outside the box answers like the nearest point on its faces
+        let below = OklabColor { l: -1.0, a: 0.0, b: 0.0 };
+        assert_eq!(matcher.nearest(&below), matcher.nearest(&OklabColor { l: palette[0].l, ..below }));
+
+        // Too coarse to help, so it keeps searching
+        assert!(PaletteMatcher::new(&palette, DistanceMetric::Euclidean).with_lut(1).lut.is_none());
+    }
+}
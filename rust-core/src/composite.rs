@@ -0,0 +1,157 @@
+// Clip Composition
+// Two clips laid out side by side, stacked or picture-in-picture on one canvas, for comparison exports
+
+use crate::resize;
+use crate::{CompositeClip, CompositeLayout, CompositeOpts, InsetCorner, LetterboxOpts, ScaleMode};
+use rayon::prelude::*;
+
+/// Canvas rectangle as (x, y, width, height)
+pub type Pane = (u32, u32, u32, u32);
+
+/// The region of `clip` that gets drawn
+fn region(clip: &CompositeClip) -> (u32, u32, u32, u32) {
+    match clip.roi {
+        Some(roi) => (roi.x as u32, roi.y as u32, roi.width as u32, roi.height as u32),
+        None => (0, 0, clip.width, clip.height),
+    }
+}
+
+/// Where the primary and secondary clips go on a `width`×`height` canvas
+///
+/// Side by side and stacked split the canvas in two around the gap, the
+/// primary taking the left or top half. Picture-in-picture gives the primary
+/// the whole canvas and insets the secondary in a corner at its own aspect,
+/// `inset_margin` from the edges.
+pub fn panes(opts: &CompositeOpts, width: u32, height: u32, secondary: &CompositeClip) -> [Pane; 2] {
+    let gap = opts.gap as u32;
+    match opts.layout {
+        CompositeLayout::SideBySide => {
+            let left = (width - gap) / 2;
+            [(0, 0, left, height), (left + gap, 0, width - gap - left, height)]
+        }
+        CompositeLayout::Stacked => {
+            let top = (height - gap) / 2;
+            [(0, 0, width, top), (0, top + gap, width, height - gap - top)]
+        }
+        CompositeLayout::PictureInPicture => {
+            let margin = opts.inset_margin as u32;
+            let (_, _, region_w, region_h) = region(secondary);
+            let inset_w = ((width as f32 * opts.inset_width).round() as u32).clamp(1, width - 2 * margin);
+            let inset_h = (inset_w * region_h / region_w).clamp(1, height - 2 * margin);
+            let x = match opts.inset_corner {
+                InsetCorner::TopLeft | InsetCorner::BottomLeft => margin,
+                InsetCorner::TopRight | InsetCorner::BottomRight => width - margin - inset_w,
+            };
+            let y = match opts.inset_corner {
+                InsetCorner::TopLeft | InsetCorner::TopRight => margin,
+                InsetCorner::BottomLeft | InsetCorner::BottomRight => height - margin - inset_h,
+            };
+            [(0, 0, width, height), (x, y, inset_w, inset_h)]
+        }
+    }
+}
+
+/// Every frame of the composite, back to back as RGBA
+///
+/// The composite runs as long as the longer clip; the shorter one holds its
+/// last frame. Each clip is scaled into its pane by its own `scale_mode`,
+/// Fit letterboxing with `letterbox`. The gap, and a `gap`-wide border
+/// around a picture-in-picture inset, are `background`.
+pub fn compose(
+    primary: &CompositeClip,
+    secondary: &CompositeClip,
+    opts: &CompositeOpts,
+    width: u32,
+    height: u32,
+    letterbox: &LetterboxOpts,
+) -> Vec<u8> {
+    let [primary_pane, secondary_pane] = panes(opts, width, height, secondary);
+    let frame_count = primary.frame_count.max(secondary.frame_count) as usize;
+    let background = [(opts.background >> 16) as u8, (opts.background >> 8) as u8, opts.background as u8, 255];
+    let border = match opts.layout {
+        CompositeLayout::PictureInPicture => Some(grow(secondary_pane, opts.gap as u32, width, height)),
+        CompositeLayout::SideBySide | CompositeLayout::Stacked => None,
+    };
+
+    let mut out = background.repeat(width as usize * height as usize * frame_count);
+    out.par_chunks_exact_mut((width * height * 4) as usize).enumerate().for_each(|(i, canvas)| {
+        let mut scaled = Vec::new();
+        draw(primary, i, primary_pane, letterbox, canvas, width, &mut scaled);
+        if let Some((x, y, w, h)) = border {
+            for row in y..y + h {
+                let start = ((row * width + x) * 4) as usize;
+                canvas[start..start + (w * 4) as usize].chunks_exact_mut(4).for_each(|px| px.copy_from_slice(&background));
+            }
+        }
+        draw(secondary, i, secondary_pane, letterbox, canvas, width, &mut scaled);
+    });
+    out
+}
+
+/// `pane` widened by `by` on every side, kept on the canvas
+fn grow((x, y, w, h): Pane, by: u32, width: u32, height: u32) -> Pane {
+    let (left, top) = (x.saturating_sub(by), y.saturating_sub(by));
+    (left, top, (x + w + by).min(width) - left, (y + h + by).min(height) - top)
+}
+
+/// Scale frame `index` of `clip` (or its last) into `pane` of the canvas
+fn draw(clip: &CompositeClip, index: usize, pane: Pane, letterbox: &LetterboxOpts, canvas: &mut [u8], width: u32, scaled: &mut Vec<u8>) {
+    let frame_size = (clip.width * clip.height * 4) as usize;
+    let index = index.min(clip.frame_count as usize - 1);
+    let frame = &clip.frames_rgba[index * frame_size..][..frame_size];
+    let (x, y, pane_w, pane_h) = pane;
+    match clip.scale_mode {
+        ScaleMode::Fit => resize::fit_resize_region(frame, clip.width, region(clip), pane_w, pane_h, letterbox, scaled),
+        ScaleMode::Area | ScaleMode::PixelArt => resize::cover_resize_region(frame, clip.width, region(clip), pane_w, pane_h, scaled),
+    }
+    for (row, pixels) in scaled.chunks_exact((pane_w * 4) as usize).enumerate() {
+        let start = (((y + row as u32) * width + x) * 4) as usize;
+        canvas[start..start + pixels.len()].copy_from_slice(pixels);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip(color: [u8; 4], width: u32, height: u32, frame_count: u32) -> CompositeClip {
+        CompositeClip {
+            frames_rgba: color.repeat((width * height * frame_count) as usize),
+            width,
+            height,
+            frame_count,
+            scale_mode: ScaleMode::Area,
+            roi: None,
+        }
+    }
+
+    #[test]
+    fn test_panes_split_around_the_gap_or_inset_the_secondary() {
+        let secondary = clip([0; 4], 16, 9, 1);
+        let side = CompositeOpts { gap: 2, ..Default::default() };
+        assert_eq!(panes(&side, 101, 50, &secondary), [(0, 0, 49, 50), (51, 0, 50, 50)]);
+        let stacked = CompositeOpts { layout: CompositeLayout::Stacked, gap: 0, ..Default::default() };
+        assert_eq!(panes(&stacked, 40, 30, &secondary), [(0, 0, 40, 15), (0, 15, 40, 15)]);
+
+        // A quarter-width inset at the secondary's 16:9, 4px in from the bottom right
+        let pip = CompositeOpts {
+            layout: CompositeLayout::PictureInPicture,
+            inset_width: 0.25,
+            inset_corner: InsetCorner::BottomRight,
+            inset_margin: 4,
+            ..Default::default()
+        };
+        assert_eq!(panes(&pip, 128, 128, &secondary), [(0, 0, 128, 128), (92, 106, 32, 18)]);
+    }
+
+    #[test]
+    fn test_compose_draws_both_clips_and_holds_the_shorter() {
+        let (red, blue) = ([255, 0, 0, 255], [0, 0, 255, 255]);
+        let opts = CompositeOpts { gap: 2, background: 0x00ff00, ..Default::default() };
+        let frames = compose(&clip(red, 8, 8, 3), &clip(blue, 4, 4, 1), &opts, 10, 4, &LetterboxOpts::default());
+        assert_eq!(frames.len(), 3 * 10 * 4 * 4);
+        // Every frame: four red columns, the green gap, four blue columns
+        let row: Vec<u8> = [red; 4].into_iter().chain([[0, 255, 0, 255]; 2]).chain([blue; 4]).flatten().collect();
+        assert!(frames.chunks_exact(10 * 4).all(|r| r == row));
+    }
+}
@@ -0,0 +1,83 @@
+// Tensor Payload Compression
+// zstd frames, optionally against a dictionary shared between encoder and app
+
+use crate::{ProcessorError, Result};
+use std::io::Read;
+
+/// Training samples are cut to one 128×128 RGBA slice each
+const SAMPLE_BYTES: usize = 128 * 128 * 4;
+
+/// Largest zstd level
+const MAX_LEVEL: u8 = 22;
+
+/// Compress a tensor at `level` (1-22), using `dictionary` when given
+pub fn compress(data: &[u8], level: u8, dictionary: Option<&[u8]>) -> Result<Vec<u8>> {
+    let level = level.clamp(1, MAX_LEVEL) as i32;
+    let mut compressor = match dictionary {
+        Some(dict) => zstd::bulk::Compressor::with_dictionary(level, dict),
+        None => zstd::bulk::Compressor::new(level),
+    }
+    .map_err(|e| ProcessorError::encoding("tensor compression", e))?;
+
+    compressor.compress(data).map_err(|e| ProcessorError::encoding("tensor compression", e))
+}
+
+/// Inverse of `compress`; the dictionary must match the one used to compress
+pub fn decompress(data: &[u8], dictionary: Option<&[u8]>) -> Result<Vec<u8>> {
+    let mut decoder = match dictionary {
+        Some(dict) => zstd::stream::Decoder::with_dictionary(data, dict),
+        None => zstd::stream::Decoder::with_buffer(data),
+    }
+    .map_err(|e| ProcessorError::invalid_input("tensor decompression", e))?;
+
+    let mut out = Vec::new();
+    decoder.read_to_end(&mut out).map_err(|e| ProcessorError::invalid_input("tensor decompression", e))?;
+    Ok(out)
+}
+
+/// Train a dictionary of at most `max_size` bytes from representative tensors
+///
+/// Each tensor is split into slice-sized samples, which is the granularity zstd
+/// trains well on. Needs a reasonable number of samples; a handful of typical
+/// captures is enough.
+pub fn train_dictionary(tensors: &[Vec<u8>], max_size: usize) -> Result<Vec<u8>> {
+    let samples: Vec<&[u8]> = tensors.iter().flat_map(|t| t.chunks(SAMPLE_BYTES)).collect();
+    if samples.is_empty() || max_size == 0 {
+        return Err(ProcessorError::invalid_input("dictionary training", "no samples or an empty size limit"));
+    }
+
+    zstd::dict::from_samples(&samples, max_size).map_err(|e| ProcessorError::invalid_input("dictionary training", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture(seed: u32) -> Vec<u8> {
+        (0..SAMPLE_BYTES as u32 * 8)
+            .map(|i| {
+                let (x, y) = (i / 4 % 128, i / 4 / 128 % 128);
+                (((x + y + seed) / 4) ^ ((i % 4) * 40)) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip_without_dictionary() {
+        let tensor = capture(0);
+        let packed = compress(&tensor, 3, None).unwrap();
+        assert!(packed.len() < tensor.len() / 4);
+        assert_eq!(decompress(&packed, None).unwrap(), tensor);
+    }
+
+    #[test]
+    fn test_round_trip_with_trained_dictionary() {
+        let training: Vec<Vec<u8>> = (0..4).map(|s| capture(s * 3)).collect();
+        let dictionary = train_dictionary(&training, 16 * 1024).unwrap();
+
+        let tensor = capture(17);
+        let packed = compress(&tensor, 3, Some(&dictionary)).unwrap();
+        assert_eq!(decompress(&packed, Some(&dictionary)).unwrap(), tensor);
+        assert!(decompress(&packed, None).is_err());
+    }
+}
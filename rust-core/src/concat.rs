@@ -0,0 +1,357 @@
+// Multi-Clip Concatenation with Transitions
+// Joins encoded GIFs frame-for-frame; only transition frames are re-indexed
+
+use crate::preview::Octree;
+use crate::{ProcessorError, Result, Transition, TransitionKind};
+use gif::{ColorOutput, DecodeOptions, DisposalMethod, Encoder, Frame, Repeat};
+
+/// One canvas pixel after compositing; `None` is transparent
+type Canvas = Vec<Option<[u8; 3]>>;
+
+/// A source frame kept verbatim, with the palette it was encoded against
+struct ClipFrame {
+    frame: Frame<'static>,
+    palette: Vec<u8>,
+}
+
+struct Clip {
+    width: u16,
+    height: u16,
+    frames: Vec<ClipFrame>,
+    first_canvas: Canvas,
+    last_canvas: Canvas,
+    repeat: Repeat,
+}
+
+/// Concatenate encoded GIF clips, inserting `transition` between each pair
+///
+/// Clip frames are copied as-is with their palette as a local color table, so
+/// they are never requantized. Crossfade and wipe frames are composited in RGB
+/// and indexed exactly when they fit 256 colors, otherwise through an octree.
+/// The joined GIF has one loop count, so it loops the way the first clip does.
+pub fn concatenate(clips: &[Vec<u8>], transition: &Transition) -> Result<Vec<u8>> {
+    let clips: Vec<Clip> = clips.iter().map(|data| decode_clip(data)).collect::<Result<_>>()?;
+    let first = clips.first().ok_or(ProcessorError::invalid_input("concat", "no clips given"))?;
+    let (width, height) = (first.width, first.height);
+    if clips.iter().any(|c| c.width != width || c.height != height) {
+        return Err(ProcessorError::invalid_input("concat", "clips have different sizes"));
+    }
+
+    let mut out = Vec::new();
+    {
+        let mut encoder = Encoder::new(&mut out, width, height, &[])
+            .map_err(|e| ProcessorError::encoding("concat", e))?;
+        encoder.set_repeat(first.repeat)
+            .map_err(|e| ProcessorError::encoding("concat", e))?;
+
+        for (i, clip) in clips.iter().enumerate() {
+            if i > 0 {
+                let prev = &clips[i - 1];
+                let delay = prev.frames.last().map_or(3, |f| f.frame.delay);
+                for step in 0..transition.frame_count {
+                    let t = (step + 1) as f32 / (transition.frame_count + 1) as f32;
+                    let Some(canvas) = blend(&prev.last_canvas, &clip.first_canvas, width, t, transition.kind) else {
+                        break;
+                    };
+                    let frame = index_canvas(&canvas, width, height, delay);
+                    encoder.write_frame(&frame).map_err(|e| ProcessorError::encoding("concat transition", e))?;
+                }
+            }
+
+            for (j, source) in clip.frames.iter().enumerate() {
+                let covers_canvas = source.frame.left == 0
+                    && source.frame.top == 0
+                    && source.frame.width == width
+                    && source.frame.height == height;
+
+                if j == 0 && i > 0 && !covers_canvas {
+                    // A partial first frame would show the previous clip underneath
+                    let frame = index_canvas(&clip.first_canvas, width, height, source.frame.delay);
+                    encoder.write_frame(&frame).map_err(|e| ProcessorError::encoding("concat", e).at_frame(j))?;
+                    continue;
+                }
+
+                let mut frame = source.frame.clone();
+                frame.palette = Some(source.palette.clone());
+                encoder.write_frame(&frame).map_err(|e| ProcessorError::encoding("concat", e).at_frame(j))?;
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+fn decode_clip(data: &[u8]) -> Result<Clip> {
+    let mut options = DecodeOptions::new();
+    options.set_color_output(ColorOutput::Indexed);
+    let mut decoder = options.read_info(data).map_err(|e| ProcessorError::invalid_input("concat decode", e))?;
+
+    let (width, height) = (decoder.width(), decoder.height());
+    let global = decoder.global_palette().map(|p| p.to_vec()).unwrap_or_default();
+    let pixels = width as usize * height as usize;
+
+    let mut canvas: Canvas = vec![None; pixels];
+    let mut first_canvas = None;
+    let mut last_displayed = None;
+    let mut frames = Vec::new();
+
+    while let Some(frame) = decoder.read_next_frame().map_err(|e| ProcessorError::invalid_input("concat decode", e).at_frame(frames.len()))? {
+        let mut frame = frame.clone();
+        // The decoder already de-interlaced the rows
+        frame.interlaced = false;
+        let palette = frame.palette.take().unwrap_or_else(|| global.clone());
+
+        let saved = (frame.dispose == DisposalMethod::Previous).then(|| canvas.clone());
+        draw(&mut canvas, width, &frame, &palette);
+        first_canvas.get_or_insert_with(|| canvas.clone());
+
+        // Remember what was on screen before disposal wipes it
+        last_displayed = None;
+        match frame.dispose {
+            DisposalMethod::Background => {
+                last_displayed = Some(canvas.clone());
+                clear_rect(&mut canvas, width, &frame);
+            }
+            DisposalMethod::Previous => {
+                last_displayed = Some(std::mem::replace(&mut canvas, saved.unwrap_or_default()));
+            }
+            _ => {}
+        }
+
+        frames.push(ClipFrame { frame, palette });
+    }
+
+    let Some(first_canvas) = first_canvas else {
+        return Err(ProcessorError::invalid_input("concat decode", "clip has no frames"));
+    };
+
+    Ok(Clip {
+        width,
+        height,
+        frames,
+        first_canvas,
+        last_canvas: last_displayed.unwrap_or(canvas),
+        // The loop extension comes before the first frame, so it has been read by now
+        repeat: decoder.repeat(),
+    })
+}
+
+/// Composite an indexed frame onto the canvas, skipping its transparent index
+fn draw(canvas: &mut Canvas, width: u16, frame: &Frame, palette: &[u8]) {
+    let canvas_h = canvas.len() / width.max(1) as usize;
+    for y in 0..frame.height as usize {
+        let cy = frame.top as usize + y;
+        if cy >= canvas_h {
+            break;
+        }
+        for x in 0..frame.width as usize {
+            let cx = frame.left as usize + x;
+            if cx >= width as usize {
+                break;
+            }
+            let index = frame.buffer[y * frame.width as usize + x];
+            if Some(index) == frame.transparent {
+                continue;
+            }
+            let i = index as usize * 3;
+            if let Some(rgb) = palette.get(i..i + 3) {
+                canvas[cy * width as usize + cx] = Some([rgb[0], rgb[1], rgb[2]]);
+            }
+        }
+    }
+}
+
+fn clear_rect(canvas: &mut Canvas, width: u16, frame: &Frame) {
+    // The rect comes from the file, so it can run past the canvas or even u16
+    let height = canvas.len() / width.max(1) as usize;
+    for y in frame.top as usize..(frame.top as usize + frame.height as usize).min(height) {
+        for x in frame.left as usize..(frame.left as usize + frame.width as usize).min(width as usize) {
+            if let Some(px) = canvas.get_mut(y * width as usize + x) {
+                *px = None;
+            }
+        }
+    }
+}
+
+/// Transition frame at progress `t` (0 = all `from`, 1 = all `to`)
+///
+/// Returns `None` for a cut, which inserts no frames.
+fn blend(from: &Canvas, to: &Canvas, width: u16, t: f32, kind: TransitionKind) -> Option<Canvas> {
+    match kind {
+        TransitionKind::Cut => None,
+        TransitionKind::Crossfade => Some(
+            from.iter()
+                .zip(to)
+                .map(|(a, b)| match (a, b) {
+                    (Some(a), Some(b)) => Some([
+                        (a[0] as f32 + (b[0] as f32 - a[0] as f32) * t).round() as u8,
+                        (a[1] as f32 + (b[1] as f32 - a[1] as f32) * t).round() as u8,
+                        (a[2] as f32 + (b[2] as f32 - a[2] as f32) * t).round() as u8,
+                    ]),
+                    // Fade against transparency by switching halfway
+                    (a, b) => if t < 0.5 { *a } else { *b },
+                })
+                .collect(),
+        ),
+        TransitionKind::Wipe => {
+            // Left-to-right reveal of the incoming clip
+            let edge = (t * width as f32).round() as usize;
+            Some(
+                from.iter()
+                    .zip(to)
+                    .enumerate()
+                    .map(|(i, (a, b))| if i % (width as usize) < edge { *b } else { *a })
+                    .collect(),
+            )
+        }
+    }
+}
+
+/// Index a composited canvas with its own local palette
+///
+/// Exact when the canvas has at most 255 colors (one slot stays free for
+/// transparency), otherwise octree-quantized.
+fn index_canvas(canvas: &Canvas, width: u16, height: u16, delay: u16) -> Frame<'static> {
+    let mut palette: Vec<[u8; 3]> = Vec::new();
+    let mut exact = true;
+    for rgb in canvas.iter().flatten() {
+        if !palette.contains(rgb) {
+            if palette.len() == 255 {
+                exact = false;
+                break;
+            }
+            palette.push(*rgb);
+        }
+    }
+
+    let lookup: Box<dyn Fn([u8; 3]) -> u8> = if exact {
+        let colors = palette.clone();
+        Box::new(move |rgb| colors.iter().position(|&c| c == rgb).unwrap_or(0) as u8)
+    } else {
+        let rgba: Vec<u8> = canvas.iter().flatten().flat_map(|c| [c[0], c[1], c[2], 255]).collect();
+        let tree = Octree::build(&rgba, 255);
+        palette = tree.palette().to_vec();
+        Box::new(move |rgb| tree.lookup(rgb))
+    };
+
+    let has_transparency = canvas.iter().any(|px| px.is_none());
+    let transparent = has_transparency.then_some(palette.len() as u8);
+    if palette.is_empty() || has_transparency {
+        palette.push([0, 0, 0]);
+    }
+
+    let buffer: Vec<u8> = canvas
+        .iter()
+        .map(|px| match px {
+            Some(rgb) => lookup(*rgb),
+            None => transparent.unwrap_or(0),
+        })
+        .collect();
+
+    Frame {
+        width,
+        height,
+        delay,
+        transparent,
+        palette: Some(palette.concat()),
+        buffer: buffer.into(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip(color: [u8; 3], frames: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        {
+            let palette = [color[0], color[1], color[2], 9, 9, 9];
+            let mut encoder = Encoder::new(&mut out, 4, 2, &palette).unwrap();
+            encoder.set_repeat(Repeat::Finite(3)).unwrap();
+            for _ in 0..frames {
+                let frame = Frame {
+                    width: 4,
+                    height: 2,
+                    delay: 4,
+                    buffer: vec![0u8; 8].into(),
+                    ..Default::default()
+                };
+                encoder.write_frame(&frame).unwrap();
+            }
+        }
+        out
+    }
+
+    fn decode_rgba(data: &[u8]) -> Vec<Vec<u8>> {
+        let mut options = DecodeOptions::new();
+        options.set_color_output(ColorOutput::RGBA);
+        let mut decoder = options.read_info(data).unwrap();
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frames.push(frame.buffer.to_vec());
+        }
+        frames
+    }
+
+    #[test]
+    fn test_cut_copies_frames_with_their_palettes() {
+        let clips = [clip([255, 0, 0], 2), clip([0, 0, 255], 3)];
+        let cut = Transition { kind: TransitionKind::Cut, frame_count: 4 };
+
+        let frames = decode_rgba(&concatenate(&clips, &cut).unwrap());
+        assert_eq!(frames.len(), 5);
+        assert_eq!(&frames[1][..4], &[255, 0, 0, 255]);
+        assert_eq!(&frames[2][..4], &[0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_loop_count_carries_over_and_odd_rects_stay_on_canvas() {
+        let cut = Transition { kind: TransitionKind::Cut, frame_count: 0 };
+        let joined = concatenate(&[clip([1, 2, 3], 1), clip([4, 5, 6], 1)], &cut).unwrap();
+        let mut decoder = DecodeOptions::new().read_info(joined.as_slice()).unwrap();
+        while decoder.read_next_frame().unwrap().is_some() {}
+        assert_eq!(decoder.repeat(), Repeat::Finite(3));
+
+        // A rect near u16::MAX used to overflow working out where it ends
+        let mut canvas: Canvas = vec![Some([1, 1, 1]); 8];
+        let far = Frame { left: 2, top: u16::MAX - 1, width: 10, height: 10, ..Default::default() };
+        clear_rect(&mut canvas, 4, &far);
+        let edge = Frame { left: 2, top: 1, width: u16::MAX, height: u16::MAX, ..Default::default() };
+        clear_rect(&mut canvas, 4, &edge);
+        assert_eq!(canvas.iter().filter(|px| px.is_none()).count(), 2);
+    }
+
+    #[test]
+    fn test_crossfade_blends_between_clips() {
+        let clips = [clip([200, 0, 0], 1), clip([0, 0, 200], 1)];
+        let fade = Transition { kind: TransitionKind::Crossfade, frame_count: 1 };
+
+        let frames = decode_rgba(&concatenate(&clips, &fade).unwrap());
+        assert_eq!(frames.len(), 3);
+        assert_eq!(&frames[1][..4], &[100, 0, 100, 255]);
+    }
+
+    #[test]
+    fn test_wipe_reveals_left_to_right() {
+        let clips = [clip([200, 0, 0], 1), clip([0, 0, 200], 1)];
+        let wipe = Transition { kind: TransitionKind::Wipe, frame_count: 1 };
+
+        let frames = decode_rgba(&concatenate(&clips, &wipe).unwrap());
+        let middle = &frames[1];
+        assert_eq!(&middle[..4], &[0, 0, 200, 255]);
+        assert_eq!(&middle[12..16], &[200, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_mismatched_sizes_are_rejected() {
+        let mut other = Vec::new();
+        {
+            let mut encoder = Encoder::new(&mut other, 2, 2, &[0, 0, 0, 1, 1, 1]).unwrap();
+            let frame = Frame { width: 2, height: 2, buffer: vec![0u8; 4].into(), ..Default::default() };
+            encoder.write_frame(&frame).unwrap();
+        }
+        let cut = Transition { kind: TransitionKind::Cut, frame_count: 0 };
+        assert!(concatenate(&[clip([1, 2, 3], 1), other], &cut).is_err());
+    }
+}
@@ -0,0 +1,198 @@
+// Frame Decimation for Frame Budgets
+// Picks which captured frames survive when a clip is longer than GifOpts::frame_count
+
+use crate::DecimationStrategy;
+use rayon::prelude::*;
+
+/// Sample every Nth pixel when scoring frames; scores only need to rank frames
+const SCORE_STRIDE: usize = 4;
+
+/// Floor on per-frame motion so still passages still get some frames
+const MIN_MOTION_WEIGHT: f32 = 0.05;
+
+/// Choose `budget` frame indices (ascending) out of `frames`
+///
+/// Returns every index when the clip already fits. The first and last frame are
+/// always kept by the weighted strategies so the clip keeps its full span.
+pub fn select_frames(
+    frames: &[&[u8]],
+    width: usize,
+    height: usize,
+    budget: usize,
+    strategy: DecimationStrategy,
+) -> Vec<usize> {
+    let count = frames.len();
+    if budget == 0 || count <= budget {
+        return (0..count).collect();
+    }
+
+    match strategy {
+        DecimationStrategy::Uniform => uniform(count, budget),
+        DecimationStrategy::MotionWeighted => {
+            let lumas: Vec<Vec<f32>> = frames.par_iter().map(|f| sampled_luma(f)).collect();
+
+            // Frame i's weight is how much it changed since frame i-1
+            let mut weights = vec![1.0f32; count];
+            let motion: Vec<f32> = lumas
+                .par_windows(2)
+                .map(|pair| mean_abs_diff(&pair[0], &pair[1]))
+                .collect();
+            let peak = motion.iter().cloned().fold(0.0f32, f32::max);
+            if peak > 0.0 {
+                for (w, m) in weights[1..].iter_mut().zip(&motion) {
+                    *w = (m / peak).max(MIN_MOTION_WEIGHT);
+                }
+            }
+
+            sample_by_weight(&weights, budget)
+        }
+        DecimationStrategy::SharpnessWeighted => {
+            let sharpness: Vec<f32> = frames
+                .par_iter()
+                .map(|f| laplacian_energy(f, width, height))
+                .collect();
+
+            // Sharpest frame from each of `budget` equal windows
+            (0..budget)
+                .map(|i| {
+                    let start = i * count / budget;
+                    let end = ((i + 1) * count / budget).max(start + 1);
+                    (start..end)
+                        .max_by(|&a, &b| sharpness[a].total_cmp(&sharpness[b]).then(b.cmp(&a)))
+                        .unwrap_or(start)
+                })
+                .collect()
+        }
+    }
+}
+
+/// Evenly spaced indices centred in each of `budget` windows
+fn uniform(count: usize, budget: usize) -> Vec<usize> {
+    (0..budget).map(|i| ((2 * i + 1) * count) / (2 * budget)).collect()
+}
+
+/// Spend `budget` picks along the cumulative weight curve
+///
+/// Picks land densely where weights are high. Indices are forced strictly
+/// increasing so no frame is chosen twice.
+fn sample_by_weight(weights: &[f32], budget: usize) -> Vec<usize> {
+    let count = weights.len();
+    if budget == 1 {
+        return vec![0];
+    }
+
+    let mut cumulative = Vec::with_capacity(count);
+    let mut total = 0.0f32;
+    for w in weights {
+        total += w;
+        cumulative.push(total);
+    }
+
+    let mut picks = Vec::with_capacity(budget);
+    let mut next_free = 0;
+    for i in 0..budget {
+        // Targets run from the first frame's weight to the total, so both ends are hit
+        let t = i as f32 / (budget - 1) as f32;
+        let target = cumulative[0] + t * (total - cumulative[0]);
+        let found = cumulative.partition_point(|&c| c < target).min(count - 1);
+
+        // Leave room for the picks still to come
+        let latest = count - (budget - i);
+        let index = found.clamp(next_free, latest);
+        picks.push(index);
+        next_free = index + 1;
+    }
+
+    picks
+}
+
+/// Rec. 601 luma of every SCORE_STRIDE-th pixel
+fn sampled_luma(rgba: &[u8]) -> Vec<f32> {
+    rgba.chunks_exact(4)
+        .step_by(SCORE_STRIDE)
+        .map(|p| p[0] as f32 * 0.299 + p[1] as f32 * 0.587 + p[2] as f32 * 0.114)
+        .collect()
+}
+
+fn mean_abs_diff(a: &[f32], b: &[f32]) -> f32 {
+    if a.is_empty() {
+        return 0.0;
+    }
+    a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum::<f32>() / a.len() as f32
+}
+
+/// Mean squared 4-neighbour Laplacian of luma, a cheap focus measure
+fn laplacian_energy(rgba: &[u8], width: usize, height: usize) -> f32 {
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+
+    let luma = |x: usize, y: usize| {
+        let i = (y * width + x) * 4;
+        rgba[i] as f32 * 0.299 + rgba[i + 1] as f32 * 0.587 + rgba[i + 2] as f32 * 0.114
+    };
+
+    let mut energy = 0.0f32;
+    let mut samples = 0usize;
+    for y in (1..height - 1).step_by(SCORE_STRIDE / 2) {
+        for x in (1..width - 1).step_by(SCORE_STRIDE / 2) {
+            let lap = 4.0 * luma(x, y) - luma(x - 1, y) - luma(x + 1, y) - luma(x, y - 1) - luma(x, y + 1);
+            energy += lap * lap;
+            samples += 1;
+        }
+    }
+
+    energy / samples.max(1) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(value: u8, width: usize, height: usize) -> Vec<u8> {
+        vec![value; width * height * 4]
+    }
+
+    #[test]
+    fn test_clip_within_budget_is_untouched() {
+        let frames: Vec<Vec<u8>> = (0..4).map(|i| solid(i, 4, 4)).collect();
+        let refs: Vec<&[u8]> = frames.iter().map(|f| f.as_slice()).collect();
+        assert_eq!(select_frames(&refs, 4, 4, 8, DecimationStrategy::Uniform), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_uniform_spreads_evenly() {
+        assert_eq!(uniform(10, 5), vec![1, 3, 5, 7, 9]);
+        assert_eq!(uniform(256, 128).len(), 128);
+    }
+
+    #[test]
+    fn test_motion_weighted_favours_moving_section() {
+        // 10 still frames, then 10 frames changing every step
+        let frames: Vec<Vec<u8>> = (0..20)
+            .map(|i| solid(if i < 10 { 0 } else { (i * 20) as u8 }, 8, 8))
+            .collect();
+        let refs: Vec<&[u8]> = frames.iter().map(|f| f.as_slice()).collect();
+
+        let picks = select_frames(&refs, 8, 8, 8, DecimationStrategy::MotionWeighted);
+        assert_eq!(picks.len(), 8);
+        assert!(picks.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!((picks[0], picks[7]), (0, 19));
+        assert!(picks.iter().filter(|&&i| i >= 10).count() >= 6, "{picks:?}");
+    }
+
+    #[test]
+    fn test_sharpness_weighted_picks_sharp_frame_per_window() {
+        let (width, height) = (8, 8);
+        let checker: Vec<u8> = (0..width * height)
+            .flat_map(|i| if (i % width + i / width) % 2 == 0 { [0; 4] } else { [255; 4] })
+            .collect();
+
+        // Windows of two frames: blurry, then sharp in the first; sharp, then blurry in the second
+        let frames = [solid(128, width, height), checker.clone(), checker, solid(128, width, height)];
+        let refs: Vec<&[u8]> = frames.iter().map(|f| f.as_slice()).collect();
+
+        let picks = select_frames(&refs, width, height, 2, DecimationStrategy::SharpnessWeighted);
+        assert_eq!(picks, vec![1, 2]);
+    }
+}
@@ -0,0 +1,162 @@
+// Frame Effects
+// Grain, vignette, scanlines and chromatic aberration applied before quantization
+
+use crate::{Effect, EffectChain, EffectKind, ProcessorError, Result};
+
+/// Grain amplitude in 8-bit levels at intensity 1.0
+const GRAIN_LEVELS: f32 = 48.0;
+
+/// Integer hash for grain noise, stable across platforms
+fn hash(mut v: u32) -> u32 {
+    v ^= v >> 16;
+    v = v.wrapping_mul(0x7feb_352d);
+    v ^= v >> 15;
+    v = v.wrapping_mul(0x846c_a68b);
+    v ^ (v >> 16)
+}
+
+/// Apply every effect in `chain`, in order, to one RGBA frame
+///
+/// `frame_index` feeds the grain seed so the noise moves between frames the
+/// way film grain does, while a given clip always renders the same.
+pub fn apply_chain(chain: &EffectChain, frame: &mut [u8], width: u32, height: u32, frame_index: u32) {
+    for effect in &chain.effects {
+        if effect.intensity <= 0.0 {
+            continue;
+        }
+        match effect.kind {
+            EffectKind::Grain => grain(frame, width, effect, frame_index),
+            EffectKind::Vignette => vignette(frame, width, height, effect),
+            EffectKind::Scanlines => scanlines(frame, width, effect),
+            EffectKind::ChromaticAberration => chromatic_aberration(frame, width, effect),
+        }
+    }
+}
+
+/// Luma noise in `size`×`size` pixel cells
+fn grain(frame: &mut [u8], width: u32, effect: &Effect, frame_index: u32) {
+    let cell = effect.size.max(1.0) as u32;
+    let seed = hash(effect.seed ^ hash(frame_index));
+    for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i as u32 % width / cell, i as u32 / width / cell);
+        let noise = hash(seed ^ hash(x ^ hash(y))) as f32 / u32::MAX as f32 * 2.0 - 1.0;
+        let offset = noise * GRAIN_LEVELS * effect.intensity;
+        for c in &mut pixel[..3] {
+            *c = (*c as f32 + offset).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// Quadratic darkening toward the corners (`size` unused)
+fn vignette(frame: &mut [u8], width: u32, height: u32, effect: &Effect) {
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    let corner_sq = cx * cx + cy * cy;
+    for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
+        let dx = (i as u32 % width) as f32 + 0.5 - cx;
+        let dy = (i as u32 / width) as f32 + 0.5 - cy;
+        let gain = 1.0 - effect.intensity.min(1.0) * (dx * dx + dy * dy) / corner_sq;
+        for c in &mut pixel[..3] {
+            *c = (*c as f32 * gain).round() as u8;
+        }
+    }
+}
+
+/// Darken the second half of every `size`-row period
+fn scanlines(frame: &mut [u8], width: u32, effect: &Effect) {
+    let period = effect.size.max(2.0) as usize;
+    let gain = 1.0 - effect.intensity.min(1.0);
+    for (y, row) in frame.chunks_exact_mut(width as usize * 4).enumerate() {
+        if y % period < period / 2 {
+            continue;
+        }
+        for pixel in row.chunks_exact_mut(4) {
+            for c in &mut pixel[..3] {
+                *c = (*c as f32 * gain).round() as u8;
+            }
+        }
+    }
+}
+
+/// Red shifted right and blue left by `size × intensity` pixels
+fn chromatic_aberration(frame: &mut [u8], width: u32, effect: &Effect) {
+    let shift = (effect.size * effect.intensity).round() as usize;
+    if shift == 0 {
+        return;
+    }
+    let w = width as usize;
+    for row in frame.chunks_exact_mut(w * 4) {
+        let original = row.to_vec();
+        for x in 0..w {
+            row[x * 4] = original[x.saturating_sub(shift) * 4];
+            row[x * 4 + 2] = original[(x + shift).min(w - 1) * 4 + 2];
+        }
+    }
+}
+
+/// Parse a chain saved by `to_json`
+pub fn from_json(json: &str) -> Result<EffectChain> {
+    serde_json::from_str(json).map_err(|e| ProcessorError::invalid_input("effect chain", e))
+}
+
+pub fn to_json(chain: &EffectChain) -> Result<String> {
+    serde_json::to_string(chain).map_err(|e| ProcessorError::invalid_input("effect chain", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(kind: EffectKind, intensity: f32, size: f32) -> EffectChain {
+        EffectChain {
+            effects: vec![Effect { kind, intensity, size, seed: 7 }],
+        }
+    }
+
+    fn gray(width: u32, height: u32) -> Vec<u8> {
+        [128u8, 128, 128, 255].repeat((width * height) as usize)
+    }
+
+    #[test]
+    fn test_grain_moves_between_frames() {
+        let grain = chain(EffectKind::Grain, 0.5, 1.0);
+        let (mut a, mut b, mut again) = (gray(8, 8), gray(8, 8), gray(8, 8));
+        apply_chain(&grain, &mut a, 8, 8, 0);
+        apply_chain(&grain, &mut b, 8, 8, 1);
+        apply_chain(&grain, &mut again, 8, 8, 0);
+
+        assert_ne!(a, gray(8, 8));
+        assert_ne!(a, b);
+        assert_eq!(a, again);
+    }
+
+    #[test]
+    fn test_vignette_and_scanlines_darken() {
+        let mut frame = gray(9, 9);
+        apply_chain(&chain(EffectKind::Vignette, 1.0, 0.0), &mut frame, 9, 9, 0);
+        assert_eq!(frame[(4 * 9 + 4) * 4], 128);
+        assert!(frame[0] < 40);
+
+        let mut frame = gray(2, 4);
+        apply_chain(&chain(EffectKind::Scanlines, 0.5, 2.0), &mut frame, 2, 4, 0);
+        let rows: Vec<u8> = frame.chunks_exact(8).map(|row| row[0]).collect();
+        assert_eq!(rows, vec![128, 64, 128, 64]);
+    }
+
+    #[test]
+    fn test_aberration_splits_channels() {
+        // Single white column at x = 2
+        let mut frame: Vec<u8> = (0..5).flat_map(|x| if x == 2 { [255; 4] } else { [0, 0, 0, 255] }).collect();
+        apply_chain(&chain(EffectKind::ChromaticAberration, 1.0, 1.0), &mut frame, 5, 1, 0);
+        let channel = |c: usize| -> Vec<u8> { frame.chunks_exact(4).map(|p| p[c]).collect() };
+        assert_eq!(channel(0), vec![0, 0, 0, 255, 0]);
+        assert_eq!(channel(1), vec![0, 0, 255, 0, 0]);
+        assert_eq!(channel(2), vec![0, 255, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let vhs = EffectChain::retro_vhs();
+        assert_eq!(from_json(&to_json(&vhs).unwrap()).unwrap(), vhs);
+        assert!(from_json("{\"effects\": [{\"kind\": \"Sepia\"}]}").is_err());
+    }
+}
@@ -0,0 +1,112 @@
+// Exact Palette Passthrough
+// Indexes clips that already fit in a GIF palette without any lossy quantization
+
+use std::collections::HashMap;
+
+/// Palette and indices that reproduce the input exactly
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExactPalette {
+    pub palette: Vec<[u8; 4]>,
+    pub transparent_index: Option<u8>,
+    pub indexed_frames: Vec<Vec<u8>>,
+}
+
+/// Index `frames` against their own colors if at most `max_colors` are used
+///
+/// Fully transparent pixels share a single entry whatever their RGB. Any
+/// partially transparent pixel returns `None`, since GIF transparency is
+/// binary and passing it through would no longer be lossless. The scan gives
+/// up as soon as the color count overflows, so photographic clips pay for
+/// little more than their first few rows.
+pub fn build(frames: &[&[u8]], max_colors: usize) -> Option<ExactPalette> {
+    let max_colors = max_colors.min(256);
+    let mut lookup: HashMap<u32, u8> = HashMap::new();
+    let mut palette: Vec<[u8; 4]> = Vec::new();
+    let mut transparent_index = None;
+    let mut indexed_frames = Vec::with_capacity(frames.len());
+
+    // Runs of one color are common in screen recordings and pixel art
+    let mut last: Option<(u32, u8)> = None;
+
+    for frame in frames {
+        let mut indices = Vec::with_capacity(frame.len() / 4);
+        for pixel in frame.chunks_exact(4) {
+            let key = match pixel[3] {
+                0 => 0,
+                255 => u32::from_le_bytes([pixel[0], pixel[1], pixel[2], 255]),
+                _ => return None,
+            };
+            if let Some((last_key, index)) = last {
+                if last_key == key {
+                    indices.push(index);
+                    continue;
+                }
+            }
+
+            let index = match lookup.get(&key) {
+                Some(&index) => index,
+                None => {
+                    if palette.len() == max_colors {
+                        return None;
+                    }
+                    let index = palette.len() as u8;
+                    if key == 0 {
+                        palette.push([0, 0, 0, 0]);
+                        transparent_index = Some(index);
+                    } else {
+                        palette.push([pixel[0], pixel[1], pixel[2], 255]);
+                    }
+                    lookup.insert(key, index);
+                    index
+                }
+            };
+            last = Some((key, index));
+            indices.push(index);
+        }
+        indexed_frames.push(indices);
+    }
+
+    if palette.is_empty() {
+        return None;
+    }
+    Some(ExactPalette {
+        palette,
+        transparent_index,
+        indexed_frames,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trips_few_colors() {
+        let frames = [
+            vec![255, 0, 0, 255, 0, 0, 255, 255, 9, 9, 9, 0],
+            vec![0, 0, 255, 255, 1, 2, 3, 0, 255, 0, 0, 255],
+        ];
+        let refs: Vec<&[u8]> = frames.iter().map(|f| f.as_slice()).collect();
+        let exact = build(&refs, 3).expect("three colors fit");
+
+        assert_eq!(exact.transparent_index, Some(2));
+        assert_eq!(exact.indexed_frames, vec![vec![0, 1, 2], vec![1, 2, 0]]);
+        for (frame, indices) in frames.iter().zip(&exact.indexed_frames) {
+            for (pixel, &index) in frame.chunks_exact(4).zip(indices) {
+                let color = exact.palette[index as usize];
+                match pixel[3] {
+                    0 => assert_eq!(color[3], 0),
+                    _ => assert_eq!(&color[..], pixel),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_falls_back_when_lossy() {
+        let gradient: Vec<u8> = (0..=255u8).flat_map(|v| [v, v, v, 255]).collect();
+        assert!(build(&[&gradient], 256).is_some());
+        assert!(build(&[&gradient], 255).is_none());
+        assert!(build(&[&[10, 20, 30, 128]], 256).is_none());
+    }
+}
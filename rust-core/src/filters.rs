@@ -0,0 +1,136 @@
+// Custom Frame Filters
+// Registry of host-supplied per-frame processing run alongside the built-in effects
+
+use crate::{ProcessorError, Result};
+use std::sync::{Arc, Mutex};
+
+/// Per-frame processing plugged into the pipeline before quantization
+///
+/// Frames arrive at output size. Filters run on the rayon pool, several
+/// frames at once, so implementations must not assume any ordering.
+pub trait FrameFilter: Send + Sync {
+    fn process(&self, frame_rgba: &mut [u8], width: u32, height: u32, frame_index: u32);
+}
+
+/// Foreign-language filter, exposed as a UniFFI callback interface
+///
+/// Takes the frame by value and returns the processed frame, since foreign
+/// code can't borrow Rust memory. The result must be the same length.
+pub trait HostFrameFilter: Send + Sync {
+    fn process(&self, frame_rgba: Vec<u8>, width: u32, height: u32, frame_index: u32) -> Vec<u8>;
+}
+
+/// Runs a `HostFrameFilter` as a `FrameFilter`
+struct HostAdapter(Box<dyn HostFrameFilter>);
+
+impl FrameFilter for HostAdapter {
+    fn process(&self, frame_rgba: &mut [u8], width: u32, height: u32, frame_index: u32) {
+        let processed = self.0.process(frame_rgba.to_vec(), width, height, frame_index);
+        if processed.len() == frame_rgba.len() {
+            frame_rgba.copy_from_slice(&processed);
+        } else {
+            eprintln!(
+                "[RUST] Frame filter returned {} bytes for a {} byte frame, ignoring it",
+                processed.len(),
+                frame_rgba.len()
+            );
+        }
+    }
+}
+
+/// Process-wide, so filters registered once at launch serve every encode
+static REGISTRY: Mutex<Vec<(String, Arc<dyn FrameFilter>)>> = Mutex::new(Vec::new());
+
+/// Add `filter` under `name`, replacing any filter already registered there
+pub fn register(name: &str, filter: Arc<dyn FrameFilter>) {
+    if let Ok(mut registry) = REGISTRY.lock() {
+        match registry.iter_mut().find(|(existing, _)| existing == name) {
+            Some(slot) => slot.1 = filter,
+            None => registry.push((name.to_string(), filter)),
+        }
+    }
+}
+
+pub fn register_host(name: &str, filter: Box<dyn HostFrameFilter>) {
+    register(name, Arc::new(HostAdapter(filter)));
+}
+
+/// Remove the filter registered under `name`; false if there was none
+pub fn unregister(name: &str) -> bool {
+    let Ok(mut registry) = REGISTRY.lock() else {
+        return false;
+    };
+    let before = registry.len();
+    registry.retain(|(existing, _)| existing != name);
+    registry.len() != before
+}
+
+pub fn names() -> Vec<String> {
+    REGISTRY
+        .lock()
+        .map(|registry| registry.iter().map(|(name, _)| name.clone()).collect())
+        .unwrap_or_default()
+}
+
+/// Look up `names` in order; any unknown name fails with `InvalidInput`
+///
+/// Resolved once per encode so filters registered or removed mid-encode
+/// don't change what the remaining frames go through.
+pub fn resolve(names: &[String]) -> Result<Vec<Arc<dyn FrameFilter>>> {
+    let registry = REGISTRY.lock().map_err(|e| ProcessorError::memory("frame filters", e))?;
+    names
+        .iter()
+        .map(|name| {
+            registry
+                .iter()
+                .find(|(existing, _)| existing == name)
+                .map(|(_, filter)| filter.clone())
+                .ok_or_else(|| ProcessorError::invalid_input("frame filters", format!("no filter registered as {:?}", name)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Invert;
+
+    impl FrameFilter for Invert {
+        fn process(&self, frame_rgba: &mut [u8], _width: u32, _height: u32, _frame_index: u32) {
+            for pixel in frame_rgba.chunks_exact_mut(4) {
+                for c in &mut pixel[..3] {
+                    *c = 255 - *c;
+                }
+            }
+        }
+    }
+
+    struct Truncate;
+
+    impl HostFrameFilter for Truncate {
+        fn process(&self, frame_rgba: Vec<u8>, _width: u32, _height: u32, _frame_index: u32) -> Vec<u8> {
+            frame_rgba[..4].to_vec()
+        }
+    }
+
+    #[test]
+    fn test_registry_resolves_in_order() {
+        register("filters-test-invert", Arc::new(Invert));
+        register_host("filters-test-truncate", Box::new(Truncate));
+        assert!(names().contains(&"filters-test-invert".to_string()));
+
+        let chain = resolve(&["filters-test-invert".to_string(), "filters-test-truncate".to_string()]).unwrap();
+        let mut frame = vec![10, 20, 30, 255, 0, 0, 0, 255];
+        for filter in &chain {
+            filter.process(&mut frame, 2, 1, 0);
+        }
+        // Host filter returned the wrong size, so only the inversion sticks
+        assert_eq!(frame, vec![245, 235, 225, 255, 255, 255, 255, 255]);
+
+        assert!(unregister("filters-test-invert"));
+        assert!(!unregister("filters-test-invert"));
+        assert!(resolve(&["filters-test-invert".to_string()]).is_err());
+        unregister("filters-test-truncate");
+    }
+}
@@ -1,8 +1,10 @@
 // GIF89a encoder module using the gif crate
 // Produces standard GIF files with loop extension and optimized palettes
 
+use std::borrow::Cow;
 use gif::{Encoder, Frame, Repeat};
 use crate::{ProcessorError, Result};
+use crate::palette::{Color32, Palette};
 use crate::quantization::QuantizeResult;
 
 pub struct GifOptions {
@@ -33,13 +35,14 @@ pub fn encode_gif(
     options: &GifOptions,
 ) -> Result<Vec<u8>> {
     if quantized_frames.is_empty() {
-        return Err(ProcessorError::InvalidInput("No frames to encode".into()));
+        return Err(ProcessorError::invalid_input("gif encoder", "No frames to encode"));
     }
 
     // Validate dimensions
     let first_frame = &quantized_frames[0];
     if first_frame.width != options.width as u32 || first_frame.height != options.height as u32 {
-        return Err(ProcessorError::InvalidInput(
+        return Err(ProcessorError::invalid_input(
+            "gif encoder",
             format!("Frame dimensions {}x{} don't match options {}x{}",
                     first_frame.width, first_frame.height,
                     options.width, options.height)
@@ -76,31 +79,12 @@ fn encode_with_global_palette(
     delay_cs: u16,
     output: &mut Vec<u8>,
 ) -> Result<()> {
-    let global_palette = &frames[0].palette;
-
-    // Convert palette to flat RGB bytes
-    let mut palette_rgb = Vec::with_capacity(global_palette.len() * 3);
-    for color in global_palette {
-        // Convert from u32 RGBA to RGB bytes
-        let r = (color >> 24) as u8;
-        let g = (color >> 16) as u8;
-        let b = (color >> 8) as u8;
-        // Alpha is ignored in GIF
-        palette_rgb.push(r);
-        palette_rgb.push(g);
-        palette_rgb.push(b);
-    }
-
-    // Pad palette to power of 2 if needed
-    let color_bits = (global_palette.len() as f32).log2().ceil() as usize;
-    let padded_size = 1 << color_bits;
-    while palette_rgb.len() < padded_size * 3 {
-        palette_rgb.extend_from_slice(&[0, 0, 0]);
-    }
+    // Alpha is ignored in GIF; the table is padded to a power of 2
+    let palette_rgb = frames[0].palette.to_gif_color_table();
 
     // Create encoder with global palette
     let mut encoder = Encoder::new(output, options.width, options.height, &palette_rgb)
-        .map_err(|e| ProcessorError::EncodingError(format!("Failed to create encoder: {}", e)))?;
+        .map_err(|e| ProcessorError::encoding("gif encoder", format!("Failed to create encoder: {}", e)))?;
 
     // Set loop extension
     let repeat = if options.loop_count == 0 {
@@ -110,24 +94,23 @@ fn encode_with_global_palette(
     };
 
     encoder.write_extension(gif::ExtensionData::Repetitions(repeat))
-        .map_err(|e| ProcessorError::EncodingError(format!("Failed to set loop: {}", e)))?;
+        .map_err(|e| ProcessorError::encoding("gif encoder", format!("Failed to set loop: {}", e)))?;
 
     // Write frames
     for (idx, quantized) in frames.iter().enumerate() {
-        let mut frame = Frame::from_indexed_pixels(
-            options.width,
-            options.height,
-            quantized.indices.clone(),
-            None,  // Use global palette
-        );
+        // Borrow the indices; the frame is written before the next one is built
+        let mut frame = Frame {
+            width: options.width,
+            height: options.height,
+            buffer: Cow::Borrowed(&quantized.indices),
+            ..Frame::default()  // Use global palette
+        };
 
         frame.delay = delay_cs;
         frame.dispose = gif::DisposalMethod::Keep;
 
         encoder.write_frame(&frame)
-            .map_err(|e| ProcessorError::EncodingError(
-                format!("Failed to write frame {}: {}", idx, e)
-            ))?;
+            .map_err(|e| ProcessorError::encoding("gif encoder", e).at_frame(idx))?;
     }
 
     Ok(())
@@ -141,28 +124,10 @@ fn encode_with_local_palettes(
     output: &mut Vec<u8>,
 ) -> Result<()> {
     // Use first frame's palette as global (required by GIF format)
-    let global_palette = &frames[0].palette;
-
-    let mut palette_rgb = Vec::with_capacity(global_palette.len() * 3);
-    for color in global_palette {
-        // Convert from u32 RGBA to RGB bytes
-        let r = (color >> 24) as u8;
-        let g = (color >> 16) as u8;
-        let b = (color >> 8) as u8;
-        palette_rgb.push(r);
-        palette_rgb.push(g);
-        palette_rgb.push(b);
-    }
-
-    // Pad to power of 2
-    let color_bits = (global_palette.len() as f32).log2().ceil() as usize;
-    let padded_size = 1 << color_bits;
-    while palette_rgb.len() < padded_size * 3 {
-        palette_rgb.extend_from_slice(&[0, 0, 0]);
-    }
+    let palette_rgb = frames[0].palette.to_gif_color_table();
 
     let mut encoder = Encoder::new(output, options.width, options.height, &palette_rgb)
-        .map_err(|e| ProcessorError::EncodingError(format!("Failed to create encoder: {}", e)))?;
+        .map_err(|e| ProcessorError::encoding("gif encoder", format!("Failed to create encoder: {}", e)))?;
 
     // Set loop extension
     let repeat = if options.loop_count == 0 {
@@ -172,42 +137,25 @@ fn encode_with_local_palettes(
     };
 
     encoder.write_extension(gif::ExtensionData::Repetitions(repeat))
-        .map_err(|e| ProcessorError::EncodingError(format!("Failed to set loop: {}", e)))?;
+        .map_err(|e| ProcessorError::encoding("gif encoder", format!("Failed to set loop: {}", e)))?;
 
     // Write frames with local palettes
     for (idx, quantized) in frames.iter().enumerate() {
         // Prepare local palette
-        let mut local_palette_rgb = Vec::with_capacity(quantized.palette.len() * 3);
-        for color in &quantized.palette {
-            let r = (color >> 24) as u8;
-            let g = (color >> 16) as u8;
-            let b = (color >> 8) as u8;
-            local_palette_rgb.push(r);
-            local_palette_rgb.push(g);
-            local_palette_rgb.push(b);
-        }
-
-        // Pad local palette
-        let local_bits = (quantized.palette.len() as f32).log2().ceil() as usize;
-        let local_padded = 1 << local_bits;
-        while local_palette_rgb.len() < local_padded * 3 {
-            local_palette_rgb.extend_from_slice(&[0, 0, 0]);
-        }
+        let local_palette_rgb = quantized.palette.to_gif_color_table();
 
-        let mut frame = Frame::from_indexed_pixels(
-            options.width,
-            options.height,
-            quantized.indices.clone(),
-            None,  // Local palettes not supported in this version
-        );
+        let mut frame = Frame {
+            width: options.width,
+            height: options.height,
+            buffer: Cow::Borrowed(&quantized.indices),
+            ..Frame::default()  // Local palettes not supported in this version
+        };
 
         frame.delay = delay_cs;
         frame.dispose = gif::DisposalMethod::Keep;
 
         encoder.write_frame(&frame)
-            .map_err(|e| ProcessorError::EncodingError(
-                format!("Failed to write frame {}: {}", idx, e)
-            ))?;
+            .map_err(|e| ProcessorError::encoding("gif encoder", e).at_frame(idx))?;
     }
 
     Ok(())
@@ -241,7 +189,7 @@ pub fn encode_rgba_to_gif(
     encode_gif(quantized, &gif_opts)
 }
 
-/// Legacy compatibility function for existing FFI
+/// Legacy compatibility function for existing FFI; `palettes` are packed 0x00RRGGBB
 pub fn encode_gif89a(
     indices: &[u8],
     palettes: &[u32],
@@ -253,18 +201,11 @@ pub fn encode_gif89a(
     let mut output = Vec::new();
 
     {
-        // Convert first palette to RGB (global palette)
-        let mut global_palette = Vec::with_capacity(256 * 3);
-        for i in 0..256 {
-            let color = if i < palettes.len() {
-                palettes[i]
-            } else {
-                0xFF000000 // Black
-            };
-            global_palette.push(((color >> 16) & 0xFF) as u8); // R
-            global_palette.push(((color >> 8) & 0xFF) as u8);  // G
-            global_palette.push((color & 0xFF) as u8);         // B
-        }
+        // Convert first palette to RGB (global palette), padded with black
+        let global_palette: Palette = (0..256)
+            .map(|i| palettes.get(i).map_or(Color32::BLACK, |&c| Color32::from_rgb_u32(c)))
+            .collect();
+        let global_palette = global_palette.to_rgb_bytes();
 
         // Create encoder
         let mut encoder = Encoder::new(&mut output, side as u16, side as u16, &global_palette)?;
@@ -304,12 +245,11 @@ mod tests {
 
     fn create_test_frame(width: u32, height: u32) -> QuantizeResult {
         let indices = vec![0u8; (width * height) as usize];
-        // Pack colors as u32 RGBA
-        let palette = vec![
-            0xFF0000FF_u32, // Red
-            0x00FF00FF_u32, // Green
-            0x0000FFFF_u32, // Blue
-        ];
+        let palette: Palette = vec![
+            Color32::opaque(255, 0, 0), // Red
+            Color32::opaque(0, 255, 0), // Green
+            Color32::opaque(0, 0, 255), // Blue
+        ].into();
 
         QuantizeResult {
             indices,
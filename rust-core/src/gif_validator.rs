@@ -0,0 +1,594 @@
+// GIF89a Spec Validator
+// Walks the block structure of an encoded GIF and reports structured findings
+
+/// How serious a finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,   // Violates GIF89a; some decoders will reject the file
+    Warning, // Legal but unexpected from our encoder
+}
+
+/// A single problem found in the stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub offset: usize, // Byte offset of the offending block
+    pub message: String,
+}
+
+/// Per-image summary
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameReport {
+    pub left: u16,
+    pub top: u16,
+    pub width: u16,
+    pub height: u16,
+    pub lzw_min_code_size: u8,
+    pub local_color_table: bool,
+    pub delay: u16,                    // Centiseconds from the Graphic Control Extension
+    pub transparent_index: Option<u8>,
+    pub decoded_pixels: usize,
+}
+
+/// Everything learned while validating a GIF
+#[derive(Debug, Clone, Default)]
+pub struct GifReport {
+    pub version: String,           // "89a" or "87a"
+    pub width: u16,
+    pub height: u16,
+    pub global_color_table_size: usize,
+    pub loop_count: Option<u16>,   // From NETSCAPE2.0, 0 = forever
+    pub frames: Vec<FrameReport>,
+    pub findings: Vec<Finding>,
+}
+
+impl GifReport {
+    /// True when no finding is an error
+    pub fn is_valid(&self) -> bool {
+        self.findings.iter().all(|f| f.severity != Severity::Error)
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(|f| f.severity == Severity::Error)
+    }
+}
+
+/// Validate an encoded GIF
+///
+/// In `strict` ("clean") mode anything our encoder never writes - plain-text
+/// extensions, unknown application extensions and bytes after the trailer - is
+/// an error rather than a warning.
+pub fn validate_gif(data: &[u8], strict: bool) -> GifReport {
+    let mut validator = Validator {
+        data,
+        pos: 0,
+        strict,
+        report: GifReport::default(),
+    };
+    validator.run();
+    validator.report
+}
+
+const EXTENSION_INTRODUCER: u8 = 0x21;
+const IMAGE_SEPARATOR: u8 = 0x2C;
+const TRAILER: u8 = 0x3B;
+
+const PLAIN_TEXT_LABEL: u8 = 0x01;
+const GRAPHIC_CONTROL_LABEL: u8 = 0xF9;
+const COMMENT_LABEL: u8 = 0xFE;
+const APPLICATION_LABEL: u8 = 0xFF;
+
+/// Largest code the GIF LZW variant may emit
+const MAX_LZW_CODES: usize = 4096;
+
+struct Validator<'a> {
+    data: &'a [u8],
+    pos: usize,
+    strict: bool,
+    report: GifReport,
+}
+
+/// Graphic Control Extension fields waiting for the next image
+#[derive(Default)]
+struct PendingControl {
+    delay: u16,
+    transparent_index: Option<u8>,
+}
+
+impl<'a> Validator<'a> {
+    fn run(&mut self) {
+        if !self.header() {
+            return;
+        }
+
+        let mut control: Option<PendingControl> = None;
+
+        loop {
+            let block_start = self.pos;
+            let Some(introducer) = self.byte() else {
+                self.error(block_start, "Missing trailer (0x3B)");
+                break;
+            };
+
+            match introducer {
+                EXTENSION_INTRODUCER => {
+                    if !self.extension(block_start, &mut control) {
+                        return;
+                    }
+                }
+                IMAGE_SEPARATOR => {
+                    if !self.image(block_start, control.take().unwrap_or_default()) {
+                        return;
+                    }
+                }
+                TRAILER => break,
+                other => {
+                    self.error(block_start, format!("Unknown block introducer 0x{other:02X}"));
+                    return;
+                }
+            }
+        }
+
+        if self.report.frames.is_empty() {
+            self.error(self.pos, "No image data before trailer");
+        }
+        if control.is_some() {
+            self.warning(self.pos, "Graphic Control Extension not followed by an image");
+        }
+        if self.report.frames.len() > 1 && self.report.loop_count.is_none() {
+            self.warning(self.pos, "Animated GIF without NETSCAPE2.0 loop extension");
+        }
+        if self.pos < self.data.len() {
+            let message = format!("{} bytes after trailer", self.data.len() - self.pos);
+            self.strict_finding(self.pos, message);
+        }
+    }
+
+    /// Header and Logical Screen Descriptor, plus the global color table
+    fn header(&mut self) -> bool {
+        let Some(signature) = self.take(6) else {
+            self.error(0, "Truncated header");
+            return false;
+        };
+        match signature {
+            b"GIF89a" => self.report.version = "89a".into(),
+            b"GIF87a" => self.report.version = "87a".into(),
+            _ => {
+                self.error(0, "Missing GIF87a/GIF89a signature");
+                return false;
+            }
+        }
+
+        let Some(screen) = self.take(7) else {
+            self.error(6, "Truncated Logical Screen Descriptor");
+            return false;
+        };
+        self.report.width = u16::from_le_bytes([screen[0], screen[1]]);
+        self.report.height = u16::from_le_bytes([screen[2], screen[3]]);
+        let flags = screen[4];
+
+        if self.report.width == 0 || self.report.height == 0 {
+            self.error(6, "Zero logical screen size");
+        }
+
+        if flags & 0x80 != 0 {
+            let size = 2usize << (flags & 0x07);
+            if self.take(size * 3).is_none() {
+                self.error(13, "Truncated global color table");
+                return false;
+            }
+            self.report.global_color_table_size = size;
+        }
+
+        true
+    }
+
+    fn extension(&mut self, start: usize, control: &mut Option<PendingControl>) -> bool {
+        let Some(label) = self.byte() else {
+            self.error(start, "Truncated extension label");
+            return false;
+        };
+
+        if self.report.version == "87a" {
+            self.warning(start, "Extension block in a GIF87a file");
+        }
+
+        let Some(blocks) = self.sub_blocks(start) else {
+            return false;
+        };
+        let first = blocks.first().copied().unwrap_or(&[]);
+
+        match label {
+            GRAPHIC_CONTROL_LABEL => {
+                if blocks.len() != 1 || first.len() != 4 {
+                    self.error(start, "Graphic Control Extension must be a single 4-byte block");
+                    return true;
+                }
+                if control.is_some() {
+                    self.warning(start, "Multiple Graphic Control Extensions before one image");
+                }
+                let delay = u16::from_le_bytes([first[1], first[2]]);
+                let transparent_index = (first[0] & 0x01 != 0).then_some(first[3]);
+                *control = Some(PendingControl { delay, transparent_index });
+            }
+            APPLICATION_LABEL => {
+                if first.len() != 11 {
+                    self.error(start, "Application Extension identifier must be 11 bytes");
+                    return true;
+                }
+                if first == b"NETSCAPE2.0" || first == b"ANIMEXTS1.0" {
+                    match blocks.get(1) {
+                        Some(sub) if sub.len() == 3 && sub[0] == 0x01 => {
+                            if self.report.loop_count.is_some() {
+                                self.warning(start, "Duplicate loop extension");
+                            }
+                            self.report.loop_count = Some(u16::from_le_bytes([sub[1], sub[2]]));
+                        }
+                        _ => self.error(start, "Malformed NETSCAPE2.0 loop sub-block"),
+                    }
+                    if !self.report.frames.is_empty() {
+                        self.warning(start, "Loop extension after the first image");
+                    }
+                } else if first == crate::metadata::GIF_APPLICATION_ID || first == crate::integrity::GIF_APPLICATION_ID {
+                    // Our own clip metadata and content hash
+                } else {
+                    let id = String::from_utf8_lossy(first).into_owned();
+                    self.strict_finding(start, format!("Unknown application extension {id:?}"));
+                }
+            }
+            PLAIN_TEXT_LABEL => {
+                if first.len() != 12 {
+                    self.error(start, "Plain Text Extension header must be 12 bytes");
+                }
+                self.strict_finding(start, "Plain Text Extension present");
+                // A plain-text extension consumes the pending control block
+                control.take();
+            }
+            COMMENT_LABEL => {}
+            other => {
+                self.strict_finding(start, format!("Unknown extension label 0x{other:02X}"));
+            }
+        }
+
+        true
+    }
+
+    fn image(&mut self, start: usize, control: PendingControl) -> bool {
+        let Some(descriptor) = self.take(9) else {
+            self.error(start, "Truncated Image Descriptor");
+            return false;
+        };
+        let left = u16::from_le_bytes([descriptor[0], descriptor[1]]);
+        let top = u16::from_le_bytes([descriptor[2], descriptor[3]]);
+        let width = u16::from_le_bytes([descriptor[4], descriptor[5]]);
+        let height = u16::from_le_bytes([descriptor[6], descriptor[7]]);
+        let flags = descriptor[8];
+
+        if width == 0 || height == 0 {
+            self.error(start, "Zero-sized image");
+        }
+        if left as u32 + width as u32 > self.report.width as u32
+            || top as u32 + height as u32 > self.report.height as u32
+        {
+            self.error(start, format!(
+                "Image {width}x{height} at ({left}, {top}) exceeds logical screen {}x{}",
+                self.report.width, self.report.height
+            ));
+        }
+
+        let local_color_table = flags & 0x80 != 0;
+        let table_size = if local_color_table {
+            let size = 2usize << (flags & 0x07);
+            if self.take(size * 3).is_none() {
+                self.error(start, "Truncated local color table");
+                return false;
+            }
+            size
+        } else {
+            self.report.global_color_table_size
+        };
+
+        if table_size == 0 {
+            self.error(start, "Image has neither a local nor a global color table");
+        }
+        if let Some(transparent) = control.transparent_index {
+            if transparent as usize >= table_size.max(1) {
+                self.error(start, format!(
+                    "Transparent index {transparent} outside color table of {table_size}"
+                ));
+            }
+        }
+
+        let code_start = self.pos;
+        let Some(min_code_size) = self.byte() else {
+            self.error(start, "Missing LZW minimum code size");
+            return false;
+        };
+        if !(2..=8).contains(&min_code_size) {
+            self.error(code_start, format!("LZW minimum code size {min_code_size} outside 2..=8"));
+            return false;
+        }
+
+        let Some(blocks) = self.sub_blocks(code_start) else {
+            return false;
+        };
+        let stream: Vec<u8> = blocks.concat();
+
+        let expected = width as usize * height as usize;
+        let decoded = match decode_lzw(&stream, min_code_size, expected) {
+            Ok(lzw) => {
+                if !lzw.saw_end {
+                    self.warning(code_start, "LZW stream has no end-of-information code");
+                }
+                if let Some(max) = lzw.max_index {
+                    if max as usize >= table_size.max(1) {
+                        self.error(code_start, format!(
+                            "Pixel index {max} outside color table of {table_size}"
+                        ));
+                    }
+                }
+                lzw.pixels
+            }
+            Err(message) => {
+                self.error(code_start, message);
+                0
+            }
+        };
+
+        if decoded < expected {
+            self.error(code_start, format!("Image data has {decoded} of {expected} pixels"));
+        } else if decoded > expected {
+            self.warning(code_start, format!("Image data has {} surplus pixels", decoded - expected));
+        }
+
+        self.report.frames.push(FrameReport {
+            left,
+            top,
+            width,
+            height,
+            lzw_min_code_size: min_code_size,
+            local_color_table,
+            delay: control.delay,
+            transparent_index: control.transparent_index,
+            decoded_pixels: decoded,
+        });
+
+        true
+    }
+
+    /// Read data sub-blocks up to and including the zero-length terminator
+    fn sub_blocks(&mut self, start: usize) -> Option<Vec<&'a [u8]>> {
+        let data = self.data;
+        let mut blocks = Vec::new();
+
+        loop {
+            let Some(&len) = data.get(self.pos) else {
+                self.error(start, "Sub-block chain is missing its terminator");
+                return None;
+            };
+            self.pos += 1;
+            if len == 0 {
+                return Some(blocks);
+            }
+
+            let end = self.pos + len as usize;
+            let Some(block) = data.get(self.pos..end) else {
+                self.error(start, "Truncated data sub-block");
+                return None;
+            };
+            blocks.push(block);
+            self.pos = end;
+        }
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        let b = *self.data.get(self.pos)?;
+        self.pos += 1;
+        Some(b)
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let slice = self.data.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        Some(slice)
+    }
+
+    fn error(&mut self, offset: usize, message: impl Into<String>) {
+        self.push(Severity::Error, offset, message);
+    }
+
+    fn warning(&mut self, offset: usize, message: impl Into<String>) {
+        self.push(Severity::Warning, offset, message);
+    }
+
+    /// Error in strict mode, warning otherwise
+    fn strict_finding(&mut self, offset: usize, message: impl Into<String>) {
+        let severity = if self.strict { Severity::Error } else { Severity::Warning };
+        self.push(severity, offset, message);
+    }
+
+    fn push(&mut self, severity: Severity, offset: usize, message: impl Into<String>) {
+        self.report.findings.push(Finding {
+            severity,
+            offset,
+            message: message.into(),
+        });
+    }
+}
+
+/// Outcome of walking one image's LZW stream
+struct LzwStats {
+    pixels: usize,
+    max_index: Option<u8>,
+    saw_end: bool,
+}
+
+/// Decode a GIF LZW stream far enough to count pixels and check every code
+///
+/// Gives up once the stream yields far more pixels than the image holds, so a
+/// corrupt stream can't spin forever.
+fn decode_lzw(stream: &[u8], min_code_size: u8, expected: usize) -> Result<LzwStats, String> {
+    let clear = 1usize << min_code_size;
+    let end = clear + 1;
+
+    // Each dictionary entry is (prefix code, last byte, first byte, length)
+    let mut prefix = vec![0u16; MAX_LZW_CODES];
+    let mut suffix = vec![0u8; MAX_LZW_CODES];
+    let mut first = vec![0u8; MAX_LZW_CODES];
+    let mut length = vec![0u32; MAX_LZW_CODES];
+    for code in 0..clear {
+        suffix[code] = code as u8;
+        first[code] = code as u8;
+        length[code] = 1;
+    }
+
+    let mut code_size = min_code_size as u32 + 1;
+    let mut next = clear + 2;
+    let mut prev: Option<usize> = None;
+
+    let mut stats = LzwStats { pixels: 0, max_index: None, saw_end: false };
+    let limit = expected.saturating_mul(2).max(MAX_LZW_CODES);
+
+    let mut bit_buffer = 0u32;
+    let mut bit_count = 0u32;
+    let mut bytes = stream.iter();
+
+    loop {
+        while bit_count < code_size {
+            let Some(&b) = bytes.next() else {
+                return Ok(stats);
+            };
+            bit_buffer |= (b as u32) << bit_count;
+            bit_count += 8;
+        }
+        let code = (bit_buffer & ((1 << code_size) - 1)) as usize;
+        bit_buffer >>= code_size;
+        bit_count -= code_size;
+
+        if code == clear {
+            code_size = min_code_size as u32 + 1;
+            next = clear + 2;
+            prev = None;
+            continue;
+        }
+        if code == end {
+            stats.saw_end = true;
+            return Ok(stats);
+        }
+
+        let entry = match prev {
+            None if code < clear => code,
+            None => return Err(format!("LZW code {code} before any literal")),
+            Some(p) => {
+                let entry_first = if code < next {
+                    first[code]
+                } else if code == next {
+                    first[p]
+                } else {
+                    return Err(format!("LZW code {code} beyond next free code {next}"));
+                };
+
+                if next < MAX_LZW_CODES {
+                    prefix[next] = p as u16;
+                    suffix[next] = entry_first;
+                    first[next] = first[p];
+                    length[next] = length[p] + 1;
+                    next += 1;
+                    if next == 1 << code_size && code_size < 12 {
+                        code_size += 1;
+                    }
+                }
+                code
+            }
+        };
+
+        // Walk the entry back to its root to track the largest palette index
+        let mut walk = entry;
+        loop {
+            let index = suffix[walk];
+            stats.max_index = Some(stats.max_index.map_or(index, |m| m.max(index)));
+            if length[walk] <= 1 {
+                break;
+            }
+            walk = prefix[walk] as usize;
+        }
+
+        stats.pixels += length[entry] as usize;
+        if stats.pixels > limit {
+            return Err("LZW stream decodes to far more pixels than the image holds".into());
+        }
+        prev = Some(entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(frames: usize, repeat: bool) -> Vec<u8> {
+        let mut out = Vec::new();
+        {
+            let palette = [0u8, 0, 0, 255, 255, 255, 255, 0, 0, 0, 0, 255];
+            let mut encoder = gif::Encoder::new(&mut out, 4, 4, &palette).unwrap();
+            if repeat {
+                encoder.set_repeat(gif::Repeat::Infinite).unwrap();
+            }
+            for i in 0..frames {
+                let frame = gif::Frame {
+                    width: 4,
+                    height: 4,
+                    buffer: vec![(i % 4) as u8; 16].into(),
+                    delay: 5,
+                    transparent: Some(3),
+                    ..Default::default()
+                };
+                encoder.write_frame(&frame).unwrap();
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_encoder_output_is_clean() {
+        let report = validate_gif(&encode(3, true), true);
+        assert!(report.findings.is_empty(), "{:?}", report.findings);
+        assert_eq!(report.loop_count, Some(0));
+        assert_eq!(report.frames.len(), 3);
+        assert_eq!(report.frames[0].decoded_pixels, 16);
+        assert_eq!(report.frames[0].transparent_index, Some(3));
+    }
+
+    #[test]
+    fn test_missing_trailer_and_loop() {
+        let mut data = encode(2, false);
+        data.pop();
+
+        let report = validate_gif(&data, false);
+        assert!(!report.is_valid());
+        assert!(report.errors().any(|f| f.message.contains("trailer")));
+        assert!(report.findings.iter().any(|f| f.message.contains("NETSCAPE2.0")));
+    }
+
+    #[test]
+    fn test_plain_text_extension_only_fails_strict() {
+        let mut data = encode(1, true);
+        let trailer = data.pop().unwrap();
+        data.extend_from_slice(&[EXTENSION_INTRODUCER, PLAIN_TEXT_LABEL, 12]);
+        data.extend_from_slice(&[0; 12]);
+        data.extend_from_slice(&[2, b'h', b'i', 0, trailer]);
+
+        assert!(validate_gif(&data, false).is_valid());
+        assert!(!validate_gif(&data, true).is_valid());
+    }
+
+    #[test]
+    fn test_truncated_image_data() {
+        let mut data = encode(1, true);
+        // Drop the tail of the LZW stream along with its terminator and the trailer
+        data.truncate(data.len() - 4);
+        data.extend_from_slice(&[0, TRAILER]);
+
+        let report = validate_gif(&data, false);
+        assert!(!report.is_valid(), "{:?}", report.findings);
+    }
+}
@@ -0,0 +1,48 @@
+// Tensor Handoff Through a Mapped File
+// Lets the app mmap the tensor instead of copying it across the FFI boundary
+
+use crate::tensor::TensorShape;
+use crate::{ProcessorError, Result, TensorHandle};
+use std::fs::File;
+use std::io::Write;
+
+/// Write a finished tensor to `path` and describe it for the reader
+///
+/// The file is replaced if it exists. Strides assume the uncompressed
+/// frame-major RGBA layout; they are still reported for other layouts but only
+/// `byte_length` is meaningful there.
+pub fn write_tensor_file(path: &str, tensor: &[u8], shape: TensorShape) -> Result<TensorHandle> {
+    let mut file = File::create(path).map_err(|e| ProcessorError::memory("tensor handoff", e))?;
+    file.write_all(tensor).map_err(|e| ProcessorError::memory("tensor handoff", e))?;
+
+    Ok(TensorHandle {
+        path: path.to_string(),
+        byte_length: tensor.len() as u64,
+        width: shape.width,
+        height: shape.height,
+        depth: shape.frames,
+        row_stride: shape.width * 4,
+        slice_stride: shape.width * shape.height * 4,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_describes_written_file() {
+        let path = std::env::temp_dir().join(format!("tensor-handoff-{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        let shape = TensorShape::new(4, 2, 3);
+        let tensor: Vec<u8> = (0..shape.total_elements() * 4).map(|i| i as u8).collect();
+
+        let handle = write_tensor_file(path, &tensor, shape).unwrap();
+        assert_eq!((handle.row_stride, handle.slice_stride), (16, 32));
+        assert_eq!(handle.byte_length, tensor.len() as u64);
+        assert_eq!(std::fs::read(path).unwrap(), tensor);
+
+        std::fs::remove_file(path).unwrap();
+        assert!(write_tensor_file("/nonexistent-dir/tensor.bin", &tensor, shape).is_err());
+    }
+}
@@ -0,0 +1,393 @@
+// Indexed GIF Writer
+// LZW and GIF89a block writing on `core` and `alloc` alone, so tiny targets can encode without the pipeline
+//
+// Nothing here touches std, I/O or another crate: rust-minimal compiles this
+// same file into its `minimal` build for watchOS-class targets.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// LZW codes never grow past 12 bits
+const MAX_CODE_BITS: u32 = 12;
+const MAX_CODES: u32 = 1 << MAX_CODE_BITS;
+
+/// What the decoder does with a frame before drawing the next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Disposal {
+    Unspecified = 0,
+    Keep = 1,
+    Background = 2,
+    Previous = 3,
+}
+
+/// A GIF89a file built frame by frame in memory
+///
+/// Every frame covers the whole screen and uses the global palette. Callers
+/// streaming somewhere else can `take_bytes` or `drain_with` between frames,
+/// so only the current frame is ever held.
+pub struct GifWriter {
+    out: Vec<u8>,
+    taken: usize,
+    width: u16,
+    height: u16,
+    min_code_size: u8,
+}
+
+impl GifWriter {
+    /// Header, screen descriptor and global palette (RGB triples, padded to a power of two)
+    pub fn new(width: u16, height: u16, palette_rgb: &[u8]) -> Self {
+        let entries = (palette_rgb.len() / 3).clamp(2, 256);
+        let table_bits = entries.next_power_of_two().trailing_zeros();
+
+        let mut out = Vec::with_capacity(13 + (3 << table_bits));
+        out.extend_from_slice(b"GIF89a");
+        out.extend_from_slice(&width.to_le_bytes());
+        out.extend_from_slice(&height.to_le_bytes());
+        out.push(0x80 | 0x70 | (table_bits - 1) as u8); // Global table, 8-bit color resolution
+        out.push(0); // Background index
+        out.push(0); // No aspect ratio
+        let table_len = 3 << table_bits;
+        out.extend_from_slice(&palette_rgb[..palette_rgb.len().min(table_len)]);
+        out.resize(13 + table_len, 0);
+
+        Self { out, taken: 0, width, height, min_code_size: table_bits.max(2) as u8 }
+    }
+
+    /// Palette index of the logical screen background; only before the first `take_bytes`
+    pub fn set_background(&mut self, index: u8) {
+        self.out[11] = index;
+    }
+
+    /// NETSCAPE2.0 loop extension; 0 loops forever
+    pub fn write_repeat(&mut self, loops: u16) {
+        self.out.extend_from_slice(&[0x21, 0xFF, 11]);
+        self.out.extend_from_slice(b"NETSCAPE2.0");
+        self.out.extend_from_slice(&[3, 1]);
+        self.out.extend_from_slice(&loops.to_le_bytes());
+        self.out.push(0);
+    }
+
+    /// Application extension: the 11-byte identifier and authentication code, then `payload`
+    pub fn write_application_extension(&mut self, id: &[u8; 11], payload: &[u8]) {
+        self.out.extend_from_slice(&[0x21, 0xFF, 11]);
+        self.out.extend_from_slice(id);
+        write_sub_blocks(&mut self.out, payload);
+    }
+
+    /// One full-screen frame of palette indices, `delay_cs` centiseconds long
+    pub fn write_frame(&mut self, indices: &[u8], delay_cs: u16, disposal: Disposal, transparent: Option<u8>) {
+        debug_assert_eq!(indices.len(), self.width as usize * self.height as usize);
+        let data = lzw_encode(indices, self.min_code_size);
+        self.write_compressed_frame(&data, delay_cs, disposal, transparent);
+    }
+
+    /// LZW code size frames of this file are compressed with, for `lzw_encode`
+    pub fn min_code_size(&self) -> u8 {
+        self.min_code_size
+    }
+
+    /// `write_frame` for indices already compressed with `lzw_encode` at `min_code_size`
+    ///
+    /// Frames compress independently, so callers with threads can compress
+    /// several at once and write them here in order.
+    pub fn write_compressed_frame(&mut self, data: &[u8], delay_cs: u16, disposal: Disposal, transparent: Option<u8>) {
+        // Graphic control extension
+        let flags = (disposal as u8) << 2 | transparent.is_some() as u8;
+        self.out.extend_from_slice(&[0x21, 0xF9, 4, flags]);
+        self.out.extend_from_slice(&delay_cs.to_le_bytes());
+        self.out.extend_from_slice(&[transparent.unwrap_or(0), 0]);
+
+        // Image descriptor at the origin, no local palette, not interlaced
+        self.out.extend_from_slice(&[0x2C, 0, 0, 0, 0]);
+        self.out.extend_from_slice(&self.width.to_le_bytes());
+        self.out.extend_from_slice(&self.height.to_le_bytes());
+        self.out.push(0);
+
+        self.out.push(self.min_code_size);
+        write_sub_blocks(&mut self.out, data);
+    }
+
+    /// Bytes written so far, including any already taken
+    pub fn len(&self) -> usize {
+        self.taken + self.out.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Hand over the bytes written since the last call
+    pub fn take_bytes(&mut self) -> Vec<u8> {
+        self.taken += self.out.len();
+        core::mem::take(&mut self.out)
+    }
+
+    /// `take_bytes` without giving up the buffer: `write` sees the bytes, then they are cleared
+    ///
+    /// The next frame reuses the allocation instead of growing a fresh one.
+    pub fn drain_with<R>(&mut self, write: impl FnOnce(&[u8]) -> R) -> R {
+        let result = write(&self.out);
+        self.taken += self.out.len();
+        self.out.clear();
+        result
+    }
+
+    /// Append the trailer and return the bytes not yet taken
+    pub fn finish(mut self) -> Vec<u8> {
+        self.out.push(0x3B);
+        self.out
+    }
+}
+
+/// Data as 255-byte sub-blocks, then the zero-length terminator
+fn write_sub_blocks(out: &mut Vec<u8>, data: &[u8]) {
+    for block in data.chunks(255) {
+        out.push(block.len() as u8);
+        out.extend_from_slice(block);
+    }
+    out.push(0);
+}
+
+/// GIF-flavored LZW: variable-width codes up to 12 bits, packed least significant bit first
+///
+/// Every index must be below `1 << min_code_size`. The dictionary is cleared
+/// and restarted whenever it fills.
+pub fn lzw_encode(indices: &[u8], min_code_size: u8) -> Vec<u8> {
+    let mut out = Vec::with_capacity(indices.len() / 2 + 16);
+    lzw_encode_into(indices, min_code_size, &mut out);
+    out
+}
+
+/// `lzw_encode` into `out`, replacing what it held but keeping its allocation
+pub fn lzw_encode_into(indices: &[u8], min_code_size: u8, out: &mut Vec<u8>) {
+    out.clear();
+    let clear = 1u32 << min_code_size;
+    debug_assert!(indices.iter().all(|&index| (index as u32) < clear), "index past the {} color table", clear);
+    let end = clear + 1;
+    let mut bits = BitWriter { out, acc: 0, count: 0 };
+    let mut table = CodeTable::new();
+
+    let mut width = min_code_size as u32 + 1;
+    let mut next = clear + 2;
+    bits.write(clear, width);
+
+    let Some((&first, rest)) = indices.split_first() else {
+        bits.write(end, width);
+        return bits.finish();
+    };
+    let mut prefix = first as u32;
+    for &index in rest {
+        if let Some(code) = table.get(prefix, index) {
+            prefix = code;
+            continue;
+        }
+        bits.write(prefix, width);
+        // The decoder widens once its table reaches the current code limit
+        if next >= 1 << width && width < MAX_CODE_BITS {
+            width += 1;
+        }
+        if next < MAX_CODES {
+            table.insert(prefix, index, next);
+            next += 1;
+        } else {
+            bits.write(clear, width);
+            table.clear();
+            width = min_code_size as u32 + 1;
+            next = clear + 2;
+        }
+        prefix = index as u32;
+    }
+    bits.write(prefix, width);
+    if next >= 1 << width && width < MAX_CODE_BITS {
+        width += 1;
+    }
+    bits.write(end, width);
+    bits.finish()
+}
+
+/// Open-addressed map from (prefix code, next index) to the code for that string
+struct CodeTable {
+    keys: Vec<u32>,
+    codes: Vec<u16>,
+}
+
+impl CodeTable {
+    /// Twice the most codes ever stored, a power of two for masking
+    const SLOTS: usize = 2 * MAX_CODES as usize;
+    const EMPTY: u32 = u32::MAX;
+
+    fn new() -> Self {
+        Self { keys: vec![Self::EMPTY; Self::SLOTS], codes: vec![0; Self::SLOTS] }
+    }
+
+    fn slot(&self, key: u32) -> usize {
+        let mut slot = (key.wrapping_mul(0x9E37_79B1) >> 19) as usize & (Self::SLOTS - 1);
+        while self.keys[slot] != Self::EMPTY && self.keys[slot] != key {
+            slot = (slot + 1) & (Self::SLOTS - 1);
+        }
+        slot
+    }
+
+    fn get(&self, prefix: u32, index: u8) -> Option<u32> {
+        let key = prefix << 8 | index as u32;
+        let slot = self.slot(key);
+        (self.keys[slot] == key).then(|| self.codes[slot] as u32)
+    }
+
+    fn insert(&mut self, prefix: u32, index: u8, code: u32) {
+        let key = prefix << 8 | index as u32;
+        let slot = self.slot(key);
+        self.keys[slot] = key;
+        self.codes[slot] = code as u16;
+    }
+
+    fn clear(&mut self) {
+        self.keys.fill(Self::EMPTY);
+    }
+}
+
+struct BitWriter<'a> {
+    out: &'a mut Vec<u8>,
+    acc: u32,
+    count: u32,
+}
+
+impl BitWriter<'_> {
+    fn write(&mut self, code: u32, width: u32) {
+        self.acc |= code << self.count;
+        self.count += width;
+        while self.count >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn finish(self) {
+        if self.count > 0 {
+            self.out.push(self.acc as u8);
+        }
+    }
+}
+
+/// Nearest-color lookup into an RGB palette, remembering recent answers
+///
+/// Squared RGB distance, so it suits palettes picked elsewhere rather than
+/// replacing the pipeline's perceptual matching.
+pub struct PaletteMap<'a> {
+    palette_rgb: &'a [u8],
+    cache: Vec<(u32, u8)>,
+}
+
+impl<'a> PaletteMap<'a> {
+    /// Direct-mapped cache slots, keyed by the full color
+    const CACHE_SLOTS: usize = 4096;
+    const EMPTY: u32 = u32::MAX;
+
+    pub fn new(palette_rgb: &'a [u8]) -> Self {
+        Self { palette_rgb, cache: vec![(Self::EMPTY, 0); Self::CACHE_SLOTS] }
+    }
+
+    pub fn index(&mut self, r: u8, g: u8, b: u8) -> u8 {
+        let key = (r as u32) << 16 | (g as u32) << 8 | b as u32;
+        let slot = (key.wrapping_mul(0x9E37_79B1) >> 20) as usize;
+        if self.cache[slot].0 == key {
+            return self.cache[slot].1;
+        }
+        let mut best = (u32::MAX, 0u8);
+        for (i, c) in self.palette_rgb.chunks_exact(3).enumerate().take(256) {
+            let (dr, dg, db) = (r as i32 - c[0] as i32, g as i32 - c[1] as i32, b as i32 - c[2] as i32);
+            let distance = (dr * dr + dg * dg + db * db) as u32;
+            if distance < best.0 {
+                best = (distance, i as u8);
+            }
+        }
+        self.cache[slot] = (key, best.1);
+        best.1
+    }
+
+    /// One index per RGBA pixel; pixels under half alpha go to `transparent` when given
+    pub fn map_rgba(&mut self, rgba: &[u8], transparent: Option<u8>) -> Vec<u8> {
+        rgba.chunks_exact(4)
+            .map(|px| match transparent {
+                Some(clear) if px[3] < 128 => clear,
+                _ => self.index(px[0], px[1], px[2]),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(gif_data: &[u8]) -> Vec<Vec<u8>> {
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(gif_data).unwrap();
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frames.push(frame.buffer.to_vec());
+        }
+        frames
+    }
+
+    #[test]
+    fn test_frames_decode_back_to_their_indices() {
+        let (width, height) = (61u16, 47u16);
+        let palette: Vec<u8> = (0..=255u8).flat_map(|i| [i, 255 - i, i / 2]).collect();
+        // Noise overflows the dictionary several times; runs exercise long strings
+        let mut seed = 0x1234_5678u32;
+        let noise: Vec<u8> = (0..width as usize * height as usize)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            })
+            .collect();
+        let runs: Vec<u8> = (0..width as usize * height as usize).map(|i| (i / 97) as u8).collect();
+        let flat = vec![7u8; width as usize * height as usize];
+
+        let mut writer = GifWriter::new(width, height, &palette);
+        writer.write_repeat(0);
+        writer.write_application_extension(b"TESTAPP1.00", &[42; 600]);
+        for frame in [&noise, &runs, &flat] {
+            writer.write_frame(frame, 4, Disposal::Keep, None);
+        }
+        let gif_data = writer.finish();
+
+        assert_eq!(decode(&gif_data), vec![noise.clone(), runs.clone(), flat]);
+
+        // A reused buffer ends up holding exactly the fresh encode
+        let mut scratch = lzw_encode(&noise, 8);
+        lzw_encode_into(&runs, 8, &mut scratch);
+        assert_eq!(scratch, lzw_encode(&runs, 8));
+    }
+
+    #[test]
+    fn test_small_palettes_use_small_codes() {
+        // Four colors: 2-bit codes, padded table
+        let palette = [0, 0, 0, 255, 0, 0, 0, 255, 0];
+        let indices: Vec<u8> = (0..64u32).map(|i| (i % 3) as u8).collect();
+        let mut writer = GifWriter::new(8, 8, &palette);
+        writer.set_background(2);
+        writer.write_frame(&indices, 10, Disposal::Background, Some(2));
+        let gif_data = writer.finish();
+
+        assert_eq!(gif_data[10] & 0x07, 1); // 4-entry table
+        assert_eq!(gif_data[11], 2);
+        assert_eq!(decode(&gif_data), vec![indices]);
+        assert_eq!(lzw_encode(&[], 2), [0b0010_1100]); // Clear, end
+    }
+
+    #[test]
+    fn test_palette_map_finds_nearest_and_transparent() {
+        let palette = [0, 0, 0, 250, 10, 10, 10, 10, 250];
+        let mut map = PaletteMap::new(&palette);
+        assert_eq!(map.index(200, 40, 0), 1);
+        assert_eq!(map.index(0, 0, 200), 2);
+        assert_eq!(map.map_rgba(&[255, 0, 0, 255, 255, 0, 0, 10], Some(0)), [1, 0]);
+        assert_eq!(map.map_rgba(&[255, 0, 0, 10], None), [1]);
+    }
+}
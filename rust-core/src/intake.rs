@@ -0,0 +1,179 @@
+// Bounded Frame Intake
+// Decouples a fast producer (camera) from frame processing, dropping frames by policy when full
+
+use crate::{DropPolicy, IntakeStats};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// A raw frame waiting to be ingested
+pub struct PendingFrame {
+    pub rgba: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+struct IntakeState {
+    capacity: usize,
+    policy: DropPolicy,
+    queue: VecDeque<PendingFrame>,
+    received: u64,
+    dropped: u64,
+}
+
+/// Bounded queue of raw frames; capacity 0 means disabled
+///
+/// `offer` never blocks the producer: when the queue is full the policy picks
+/// what to lose. Drop-oldest keeps the queue current, drop-newest keeps it
+/// contiguous, and decimate thins the queue to every other frame so the
+/// stretch of time it covers is kept at a lower frame rate.
+pub struct FrameIntake {
+    state: Mutex<IntakeState>,
+}
+
+impl FrameIntake {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(IntakeState {
+                capacity: 0,
+                policy: DropPolicy::DropOldest,
+                queue: VecDeque::new(),
+                received: 0,
+                dropped: 0,
+            }),
+        }
+    }
+
+    /// Resize and repolicy; frames beyond the new capacity are dropped oldest first
+    pub fn configure(&self, capacity: usize, policy: DropPolicy) {
+        if let Ok(mut state) = self.state.lock() {
+            state.capacity = capacity;
+            state.policy = policy;
+            while state.queue.len() > capacity {
+                state.queue.pop_front();
+                state.dropped += 1;
+            }
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.state.lock().map(|state| state.capacity > 0).unwrap_or(false)
+    }
+
+    /// Queue a frame, making room according to the policy
+    pub fn offer(&self, frame: PendingFrame) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.received += 1;
+
+        if state.queue.len() >= state.capacity {
+            match state.policy {
+                DropPolicy::DropNewest => {
+                    state.dropped += 1;
+                    return;
+                }
+                DropPolicy::DropOldest => {}
+                DropPolicy::Decimate => {
+                    // Keep frames 0, 2, 4, ... of the backlog
+                    let before = state.queue.len();
+                    let mut position = 0;
+                    state.queue.retain(|_| {
+                        position += 1;
+                        position % 2 == 1
+                    });
+                    state.dropped += (before - state.queue.len()) as u64;
+                }
+            }
+            // Still full (drop-oldest, or a backlog too short to thin)
+            while state.queue.len() >= state.capacity.max(1) {
+                state.queue.pop_front();
+                state.dropped += 1;
+            }
+        }
+        state.queue.push_back(frame);
+    }
+
+    /// Remove up to `max` queued frames (0 = all), oldest first
+    pub fn take(&self, max: usize) -> Vec<PendingFrame> {
+        let Ok(mut state) = self.state.lock() else {
+            return Vec::new();
+        };
+        let count = if max == 0 { state.queue.len() } else { max.min(state.queue.len()) };
+        state.queue.drain(..count).collect()
+    }
+
+    pub fn stats(&self) -> IntakeStats {
+        self.state
+            .lock()
+            .map(|state| IntakeStats {
+                received: state.received,
+                dropped: state.dropped,
+                pending: state.queue.len() as u32,
+            })
+            .unwrap_or(IntakeStats { received: 0, dropped: 0, pending: 0 })
+    }
+
+    /// Drop queued frames and reset the counters
+    pub fn clear(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.queue.clear();
+            state.received = 0;
+            state.dropped = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer_all(intake: &FrameIntake, ids: std::ops::Range<u8>) {
+        for id in ids {
+            intake.offer(PendingFrame { rgba: vec![id; 4], width: 1, height: 1 });
+        }
+    }
+
+    fn queued(intake: &FrameIntake) -> Vec<u8> {
+        intake.take(0).iter().map(|f| f.rgba[0]).collect()
+    }
+
+    #[test]
+    fn test_drop_policies() {
+        let intake = FrameIntake::new();
+
+        intake.configure(3, DropPolicy::DropOldest);
+        offer_all(&intake, 0..5);
+        assert_eq!(queued(&intake), vec![2, 3, 4]);
+
+        intake.configure(3, DropPolicy::DropNewest);
+        offer_all(&intake, 0..5);
+        assert_eq!(queued(&intake), vec![0, 1, 2]);
+
+        intake.configure(4, DropPolicy::Decimate);
+        offer_all(&intake, 0..6);
+        // Full at 0-3: thinned to 0, 2, then 4 and 5 arrive
+        assert_eq!(queued(&intake), vec![0, 2, 4, 5]);
+
+        let stats = intake.stats();
+        assert_eq!((stats.received, stats.dropped, stats.pending), (16, 6, 0));
+    }
+
+    #[test]
+    fn test_take_is_bounded_and_clear_resets() {
+        let intake = FrameIntake::new();
+        assert!(!intake.is_enabled());
+        intake.configure(1, DropPolicy::Decimate);
+        offer_all(&intake, 0..3);
+        assert_eq!(intake.stats().pending, 1);
+        assert_eq!(queued(&intake), vec![2]);
+
+        intake.configure(8, DropPolicy::DropOldest);
+        offer_all(&intake, 0..5);
+        assert_eq!(intake.take(2).len(), 2);
+        assert_eq!(intake.stats().pending, 3);
+
+        intake.clear();
+        let stats = intake.stats();
+        assert_eq!((stats.received, stats.dropped, stats.pending), (0, 0, 0));
+    }
+}
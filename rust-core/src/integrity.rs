@@ -0,0 +1,117 @@
+// Integrity Footers
+// A BLAKE3 hash of everything before it closes the file, so a corrupted sync can be detected
+
+use crate::IntegrityCheck;
+
+/// Application identifier (8 bytes) and authentication code (3 bytes) of the GIF hash extension
+///
+/// The extension sits right before the trailer and holds one 32-byte
+/// sub-block: the hash of every byte in front of the extension.
+pub const GIF_APPLICATION_ID: &[u8; 11] = b"RGB2GIF2IH1";
+
+/// Closes a YXV container with a footer, after the 32-byte hash of every byte before it
+///
+/// Containers without one end in chunk padding, which is zero, so the two
+/// can't be confused.
+pub const YXV_FOOTER_MAGIC: &[u8; 4] = b"YXVH";
+
+pub const HASH_BYTES: usize = 32;
+
+/// Introducer, label, identifier length and identifier, one sub-block, terminator
+const GIF_EXTENSION_BYTES: usize = 3 + 11 + 1 + HASH_BYTES + 1;
+
+pub const YXV_FOOTER_BYTES: usize = HASH_BYTES + YXV_FOOTER_MAGIC.len();
+
+/// Incremental hashing for writers that stream their output
+pub type Hasher = blake3::Hasher;
+
+pub fn content_hash(data: &[u8]) -> [u8; HASH_BYTES] {
+    *blake3::hash(data).as_bytes()
+}
+
+/// Check a GIF against the hash extension before its trailer
+pub fn check_gif(data: &[u8]) -> IntegrityCheck {
+    if data.len() < 6 + GIF_EXTENSION_BYTES + 1 || data.last() != Some(&0x3B) {
+        return IntegrityCheck::Unsigned;
+    }
+    let start = data.len() - 1 - GIF_EXTENSION_BYTES;
+    let extension = &data[start..data.len() - 1];
+    let framed = extension[..3] == [0x21, 0xFF, 11]
+        && &extension[3..14] == GIF_APPLICATION_ID
+        && extension[14] as usize == HASH_BYTES
+        && extension[GIF_EXTENSION_BYTES - 1] == 0;
+    if !framed {
+        return IntegrityCheck::Unsigned;
+    }
+    compare(&data[..start], &extension[15..15 + HASH_BYTES])
+}
+
+/// The footer that closes a YXV container whose bytes so far hash to `hash`
+pub fn yxv_footer(hash: &[u8; HASH_BYTES]) -> [u8; YXV_FOOTER_BYTES] {
+    let mut footer = [0u8; YXV_FOOTER_BYTES];
+    footer[..HASH_BYTES].copy_from_slice(hash);
+    footer[HASH_BYTES..].copy_from_slice(YXV_FOOTER_MAGIC);
+    footer
+}
+
+/// Whether a YXV container ending in `tail` carries a footer
+pub fn has_yxv_footer(tail: &[u8]) -> bool {
+    tail.ends_with(YXV_FOOTER_MAGIC)
+}
+
+/// Check a YXV container against its footer
+pub fn check_yxv(data: &[u8]) -> IntegrityCheck {
+    if data.len() < YXV_FOOTER_BYTES || !has_yxv_footer(data) {
+        return IntegrityCheck::Unsigned;
+    }
+    let start = data.len() - YXV_FOOTER_BYTES;
+    compare(&data[..start], &data[start..start + HASH_BYTES])
+}
+
+fn compare(content: &[u8], stored: &[u8]) -> IntegrityCheck {
+    if content_hash(content) == stored {
+        IntegrityCheck::Verified
+    } else {
+        IntegrityCheck::Corrupted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexed::GifWriter;
+
+    fn signed_gif() -> Vec<u8> {
+        let mut writer = GifWriter::new(2, 2, &[0, 0, 0, 255, 255, 255]);
+        writer.write_frame(&[0, 1, 1, 0], 10, crate::indexed::Disposal::Keep, None);
+        let mut content = writer.take_bytes();
+        writer.write_application_extension(GIF_APPLICATION_ID, &content_hash(&content));
+        content.extend(writer.finish());
+        content
+    }
+
+    #[test]
+    fn test_gif_hash_catches_flipped_bits() {
+        let gif_data = signed_gif();
+        assert_eq!(check_gif(&gif_data), IntegrityCheck::Verified);
+
+        for i in [8, 20, gif_data.len() - 10] {
+            let mut damaged = gif_data.clone();
+            damaged[i] ^= 0x10;
+            assert_eq!(check_gif(&damaged), IntegrityCheck::Corrupted, "byte {i}");
+        }
+        assert_eq!(check_gif(&gif_data[..gif_data.len() - 1]), IntegrityCheck::Unsigned);
+    }
+
+    #[test]
+    fn test_yxv_footer_round_trip() {
+        let mut data = b"YXV\0 some chunks\0\0\0".to_vec();
+        assert_eq!(check_yxv(&data), IntegrityCheck::Unsigned);
+
+        let footer = yxv_footer(&content_hash(&data));
+        data.extend_from_slice(&footer);
+        assert_eq!(check_yxv(&data), IntegrityCheck::Verified);
+        data[6] = b'S';
+        assert_eq!(check_yxv(&data), IntegrityCheck::Corrupted);
+    }
+}
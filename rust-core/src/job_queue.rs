@@ -0,0 +1,415 @@
+// Background Encode Queue
+// Runs queued encode jobs on a fixed number of worker threads, highest priority first
+
+use crate::progress::{self, FrameProgress};
+use crate::{process_all_frames, process_masked_frames, EncodeJob, JobState, JobStatus, ProcessResult, ProcessorError, Result};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+/// Upper bound on worker threads
+const MAX_WORKERS: usize = 8;
+
+struct Entry {
+    priority: u8,
+    state: JobState,
+    job: Option<EncodeJob>,
+    result: Option<Result<ProcessResult>>,
+    started: Option<Instant>,
+    elapsed_ms: u64,
+    progress: Arc<FrameProgress>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    next_id: u64,
+    // Highest priority first, then oldest; stale (cancelled) ids are skipped on pop
+    pending: BinaryHeap<(u8, Reverse<u64>)>,
+    jobs: HashMap<u64, Entry>,
+    paused: bool,
+    shutdown: bool,
+}
+
+struct Shared {
+    state: Mutex<QueueState>,
+    wake: Condvar,
+}
+
+/// Work queue for background encodes such as re-exporting a library
+///
+/// At most `max_concurrent` jobs run at once; each still parallelizes its own
+/// frames through the global rayon pool. Cancelling a queued job removes it
+/// before it starts. A running job can't be interrupted, so cancelling it
+/// discards its result when it finishes. Finished jobs stay listed until their
+/// result is taken or `clear_finished` is called.
+pub struct JobQueue {
+    shared: Arc<Shared>,
+    workers: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl JobQueue {
+    pub fn new(max_concurrent: u32) -> Result<Self> {
+        let workers = max_concurrent as usize;
+        if workers == 0 || workers > MAX_WORKERS {
+            return Err(ProcessorError::invalid_input("job queue", format!("{} workers is outside 1-{}", workers, MAX_WORKERS)));
+        }
+
+        let shared = Arc::new(Shared {
+            state: Mutex::new(QueueState::default()),
+            wake: Condvar::new(),
+        });
+        let handles = (0..workers)
+            .map(|_| {
+                let shared = Arc::clone(&shared);
+                std::thread::spawn(move || run_worker(&shared))
+            })
+            .collect();
+
+        Ok(Self {
+            shared,
+            workers: Mutex::new(handles),
+        })
+    }
+
+    /// Queue a job and return its id; higher `priority` runs sooner
+    pub fn submit(&self, job: EncodeJob, priority: u8) -> Result<u64> {
+        let expected = job.width as u64 * job.height as u64 * 4 * job.frame_count as u64;
+        if expected == 0 || job.frames_rgba.len() as u64 != expected {
+            return Err(ProcessorError::invalid_input("job queue", format!("{} bytes for {} {}x{} frames", job.frames_rgba.len(), job.frame_count, job.width, job.height)));
+        }
+        if job.masks.as_ref().is_some_and(|masks| masks.len() as u64 * 4 != expected) {
+            return Err(ProcessorError::invalid_input("job queue", format!("mask is not one byte per pixel of {} {}x{} frames", job.frame_count, job.width, job.height)));
+        }
+
+        let mut state = self.shared.state.lock().map_err(|e| ProcessorError::memory("job queue", e))?;
+        if state.shutdown {
+            return Err(ProcessorError::invalid_input("job queue", "queue is shutting down"));
+        }
+
+        let id = state.next_id;
+        state.next_id += 1;
+        state.jobs.insert(id, Entry {
+            priority,
+            state: JobState::Queued,
+            job: Some(job),
+            result: None,
+            started: None,
+            elapsed_ms: 0,
+            progress: Arc::default(),
+        });
+        state.pending.push((priority, Reverse(id)));
+        self.shared.wake.notify_one();
+        Ok(id)
+    }
+
+    /// Cancel a queued or running job; false if it already finished or is unknown
+    pub fn cancel(&self, id: u64) -> bool {
+        let Ok(mut state) = self.shared.state.lock() else {
+            return false;
+        };
+        match state.jobs.get_mut(&id) {
+            Some(entry) if matches!(entry.state, JobState::Queued | JobState::Running) => {
+                entry.state = JobState::Cancelled;
+                entry.job = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn status(&self, id: u64) -> Option<JobStatus> {
+        let state = self.shared.state.lock().ok()?;
+        let entry = state.jobs.get(&id)?;
+
+        let queue_position = if entry.state == JobState::Queued {
+            let key = (entry.priority, Reverse(id));
+            state
+                .pending
+                .iter()
+                .filter(|&&(p, r)| (p, r) > key && state.jobs.get(&r.0).is_some_and(|e| e.state == JobState::Queued))
+                .count() as u32
+        } else {
+            0
+        };
+        let elapsed_ms = match (entry.state, entry.started) {
+            (JobState::Running, Some(started)) => started.elapsed().as_millis() as u64,
+            _ => entry.elapsed_ms,
+        };
+
+        let progress = match entry.state {
+            JobState::Completed => 1.0,
+            _ => entry.progress.fraction(),
+        };
+
+        Some(JobStatus {
+            state: entry.state,
+            priority: entry.priority,
+            queue_position,
+            elapsed_ms,
+            progress,
+        })
+    }
+
+    /// Remove a finished job and return its result
+    ///
+    /// `None` while the job is queued or running, and for cancelled or unknown
+    /// ids. A failed job returns its error.
+    pub fn take_result(&self, id: u64) -> Result<Option<ProcessResult>> {
+        let mut state = self.shared.state.lock().map_err(|e| ProcessorError::memory("job queue", e))?;
+        match state.jobs.get(&id).map(|e| e.state) {
+            Some(JobState::Completed | JobState::Failed) => {}
+            _ => return Ok(None),
+        }
+        let entry = state.jobs.remove(&id).ok_or_else(|| ProcessorError::invalid_input("job queue", format!("no job {}", id)))?;
+        entry.result.transpose()
+    }
+
+    /// Jobs not yet finished (queued or running)
+    pub fn pending_count(&self) -> u32 {
+        self.shared
+            .state
+            .lock()
+            .map(|state| {
+                state
+                    .jobs
+                    .values()
+                    .filter(|e| matches!(e.state, JobState::Queued | JobState::Running))
+                    .count() as u32
+            })
+            .unwrap_or(0)
+    }
+
+    /// Fraction of listed jobs that have finished, for a batch progress bar
+    pub fn progress(&self) -> f32 {
+        let Ok(state) = self.shared.state.lock() else {
+            return 0.0;
+        };
+        if state.jobs.is_empty() {
+            return 1.0;
+        }
+        let done = state
+            .jobs
+            .values()
+            .filter(|e| !matches!(e.state, JobState::Queued | JobState::Running))
+            .count();
+        done as f32 / state.jobs.len() as f32
+    }
+
+    /// Stop starting new jobs (e.g. while the app is backgrounded); running jobs finish
+    pub fn set_paused(&self, paused: bool) {
+        if let Ok(mut state) = self.shared.state.lock() {
+            state.paused = paused;
+        }
+        self.shared.wake.notify_all();
+    }
+
+    /// Forget cancelled jobs and drop results nobody took
+    pub fn clear_finished(&self) {
+        if let Ok(mut state) = self.shared.state.lock() {
+            state.jobs.retain(|_, e| matches!(e.state, JobState::Queued | JobState::Running));
+        }
+    }
+}
+
+impl Drop for JobQueue {
+    /// Drops queued jobs and waits for running ones to finish
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.shared.state.lock() {
+            state.shutdown = true;
+            state.pending.clear();
+        }
+        self.shared.wake.notify_all();
+
+        if let Ok(mut workers) = self.workers.lock() {
+            for handle in workers.drain(..) {
+                let _ = handle.join();
+            }
+        }
+    }
+}
+
+fn run_worker(shared: &Shared) {
+    loop {
+        let (id, job, job_progress) = {
+            let Ok(mut state) = shared.state.lock() else {
+                return;
+            };
+            loop {
+                if state.shutdown {
+                    return;
+                }
+                let next = if state.paused { None } else { state.pending.pop() };
+                if let Some((_, Reverse(id))) = next {
+                    let Some(entry) = state.jobs.get_mut(&id) else {
+                        continue;
+                    };
+                    // Cancelled while queued
+                    let Some(job) = entry.job.take() else {
+                        continue;
+                    };
+                    entry.state = JobState::Running;
+                    entry.started = Some(Instant::now());
+                    break (id, job, Arc::clone(&entry.progress));
+                }
+                state = match shared.wake.wait(state) {
+                    Ok(state) => state,
+                    Err(_) => return,
+                };
+            }
+        };
+
+        let result = progress::scoped(Some(job_progress), || run_guarded(|| match job.masks {
+            Some(masks) => process_masked_frames(
+                job.frames_rgba,
+                masks,
+                job.width,
+                job.height,
+                job.frame_count,
+                job.quantize_opts,
+                job.gif_opts,
+            ),
+            None => process_all_frames(
+                job.frames_rgba,
+                job.width,
+                job.height,
+                job.frame_count,
+                job.quantize_opts,
+                job.gif_opts,
+            ),
+        }));
+
+        let Ok(mut state) = shared.state.lock() else {
+            return;
+        };
+        if let Some(entry) = state.jobs.get_mut(&id) {
+            entry.elapsed_ms = entry.started.map_or(0, |s| s.elapsed().as_millis() as u64);
+            if entry.state == JobState::Running {
+                entry.state = if result.is_ok() { JobState::Completed } else { JobState::Failed };
+                entry.result = Some(result);
+            }
+        }
+    }
+}
+
+/// Run a job, turning a panic into an error so its entry still finishes as `Failed`
+fn run_guarded(run: impl FnOnce() -> Result<ProcessResult>) -> Result<ProcessResult> {
+    std::panic::catch_unwind(AssertUnwindSafe(run)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        Err(ProcessorError::encoding("job queue", format!("job panicked: {}", message)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GifOpts, QuantizeOpts};
+    use std::time::Duration;
+
+    fn job(frames: u32) -> EncodeJob {
+        EncodeJob {
+            frames_rgba: (0..16 * 16 * 4 * frames).map(|i| (i % 251) as u8).collect(),
+            masks: None,
+            width: 16,
+            height: 16,
+            frame_count: frames,
+            quantize_opts: QuantizeOpts { quality_min: 0, ..Default::default() },
+            gif_opts: GifOpts { width: 16, height: 16, frame_count: frames, ..Default::default() },
+        }
+    }
+
+    fn wait(queue: &JobQueue) {
+        while queue.pending_count() > 0 {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// When a job started running; with one worker, also the order jobs finished in
+    fn started(queue: &JobQueue, id: u64) -> Instant {
+        queue.shared.state.lock().unwrap().jobs[&id].started.expect("job ran")
+    }
+
+    #[test]
+    fn test_jobs_complete_and_results_are_taken_once() {
+        let queue = JobQueue::new(2).unwrap();
+        let ids: Vec<u64> = (1..=3).map(|n| queue.submit(job(n), 0).unwrap()).collect();
+        wait(&queue);
+        assert_eq!(queue.progress(), 1.0);
+
+        for (n, id) in (1..=3u32).zip(ids) {
+            assert_eq!(queue.status(id).unwrap().state, JobState::Completed);
+            let result = queue.take_result(id).unwrap().expect("result ready");
+            assert_eq!(result.actual_frame_count, n);
+            assert!(queue.status(id).is_none());
+        }
+    }
+
+    #[test]
+    fn test_priority_order_and_cancellation() {
+        let queue = JobQueue::new(1).unwrap();
+        queue.set_paused(true);
+        let low = queue.submit(job(1), 1).unwrap();
+        let high = queue.submit(job(1), 9).unwrap();
+        let dropped = queue.submit(job(1), 5).unwrap();
+        assert_eq!(queue.status(high).unwrap().queue_position, 0);
+        assert_eq!(queue.status(low).unwrap().queue_position, 2);
+
+        assert!(queue.cancel(dropped));
+        assert_eq!(queue.status(low).unwrap().queue_position, 1);
+        queue.set_paused(false);
+        wait(&queue);
+
+        assert!(started(&queue, high) < started(&queue, low), "high priority job ran after the low one");
+        assert_eq!(queue.status(dropped).unwrap().state, JobState::Cancelled);
+        assert!(queue.take_result(dropped).unwrap().is_none());
+        assert!(!queue.cancel(high));
+        assert!(queue.take_result(high).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_running_job_reports_frame_progress() {
+        let queue = JobQueue::new(1).unwrap();
+        queue.set_paused(true);
+        let id = queue.submit(job(24), 0).unwrap();
+        assert_eq!(queue.status(id).unwrap().progress, 0.0);
+        queue.set_paused(false);
+
+        let mut seen = Vec::new();
+        while let Some(status) = queue.status(id).filter(|s| matches!(s.state, JobState::Queued | JobState::Running)) {
+            seen.push(status.progress);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(seen.windows(2).all(|w| w[0] <= w[1]), "progress went backwards: {seen:?}");
+        assert_eq!(queue.status(id).unwrap().progress, 1.0);
+
+        // Reported by the pipeline itself, not just filled in on completion
+        let fed = queue.shared.state.lock().unwrap().jobs[&id].progress.fraction();
+        assert_eq!(fed, 1.0);
+    }
+
+    #[test]
+    fn test_invalid_jobs_are_rejected() {
+        assert!(JobQueue::new(0).is_err());
+        let queue = JobQueue::new(1).unwrap();
+        let mut bad = job(2);
+        bad.frame_count = 3;
+        assert!(queue.submit(bad, 0).is_err());
+        let short_mask = EncodeJob { masks: Some(vec![255; 16 * 16]), ..job(2) };
+        assert!(queue.submit(short_mask, 0).is_err());
+    }
+
+    #[test]
+    fn test_panicking_job_fails_instead_of_running_forever() {
+        let result = run_guarded(|| panic!("palette slot {} out of range", 300));
+        match result {
+            Err(ProcessorError::EncodingError { message, .. }) => assert!(message.contains("palette slot 300")),
+            other => panic!("expected EncodingError, got {:?}", other.map(|r| r.actual_frame_count)),
+        }
+    }
+}
@@ -3,16 +3,85 @@
 
 #![allow(clippy::empty_line_after_doc_comments)]
 
+extern crate alloc;
+
 use std::time::Instant;
-use imagequant::RGBA;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use buffer_pool::FRAME_POOL;
 
 // ============================================================================
 // MODULE IMPORTS
 // ============================================================================
 
+// Not every helper in these modules is reachable from the public pipeline yet
+#[allow(dead_code)]
 mod quantization;
+#[allow(dead_code)]
 mod oklab_quantization;
+#[allow(dead_code)]
 mod blue_noise;
+#[allow(dead_code)]
+mod parallel;
+#[allow(dead_code)]
+mod tensor;
+mod color_distance;
+mod adaptive;
+mod capabilities;
+mod thermal;
+mod decimation;
+mod preview;
+mod capture;
+mod concat;
+mod composite;
+mod transcode;
+mod resize;
+mod sharpen;
+mod effects;
+mod filters;
+mod loop_seam;
+mod compression;
+mod handoff;
+mod intake;
+mod checkpoint;
+mod exact_palette;
+mod pixel_art;
+mod tensor_builder;
+mod job_queue;
+mod player;
+mod validation;
+mod buffer_pool;
+mod metadata;
+mod sampling;
+mod motion_mask;
+mod motion_crop;
+mod matte;
+mod warp;
+mod posterize;
+mod accessibility;
+mod turntable;
+mod long_exposure;
+mod text_art;
+mod audio_sync;
+mod backdrop;
+mod result_cache;
+mod preflight;
+mod panic_log;
+mod progress;
+pub mod gif_validator;
+pub mod palette;
+pub mod palette_io;
+pub mod build_info;
+pub mod indexed;
+pub mod atomic_file;
+pub mod integrity;
+
+pub use capture::CaptureSession;
+pub use tensor_builder::TensorBuilder;
+pub use job_queue::JobQueue;
+pub use player::GifPlayer;
+pub use filters::{FrameFilter, HostFrameFilter};
+pub use quantization::PixelLayout;
 
 // ============================================================================
 // TYPE DEFINITIONS
@@ -22,358 +91,2890 @@ mod blue_noise;
 pub type Result<T> = std::result::Result<T, ProcessorError>;
 
 /// Error types for UniFFI interop
+///
+/// `stage` names the pipeline step that failed (e.g. "quantize", "transcode"),
+/// `frame_index` the frame it was working on when there is one, and `message`
+/// the underlying error's text.
 #[derive(Debug, thiserror::Error)]
 pub enum ProcessorError {
-    #[error("Quantization error")]
-    QuantizationError,
+    #[error("Quantization error in {stage}{}: {message}", frame_suffix(.frame_index))]
+    QuantizationError { stage: String, frame_index: Option<u32>, message: String },
+
+    #[error("Encoding error in {stage}{}: {message}", frame_suffix(.frame_index))]
+    EncodingError { stage: String, frame_index: Option<u32>, message: String },
+
+    #[error("Invalid input to {stage}{}: {message}", frame_suffix(.frame_index))]
+    InvalidInput { stage: String, frame_index: Option<u32>, message: String },
+
+    #[error("Memory error in {stage}{}: {message}", frame_suffix(.frame_index))]
+    MemoryError { stage: String, frame_index: Option<u32>, message: String },
+
+    #[error("Invalid {field}: {reason}")]
+    InvalidOption { field: String, reason: String },
+}
+
+impl ProcessorError {
+    pub(crate) fn quantization(stage: &str, message: impl ToString) -> Self {
+        Self::QuantizationError { stage: stage.to_string(), frame_index: None, message: message.to_string() }
+    }
+
+    pub(crate) fn encoding(stage: &str, message: impl ToString) -> Self {
+        Self::EncodingError { stage: stage.to_string(), frame_index: None, message: message.to_string() }
+    }
+
+    pub(crate) fn invalid_input(stage: &str, message: impl ToString) -> Self {
+        Self::InvalidInput { stage: stage.to_string(), frame_index: None, message: message.to_string() }
+    }
 
-    #[error("Encoding error")]
-    EncodingError,
+    pub(crate) fn memory(stage: &str, message: impl ToString) -> Self {
+        Self::MemoryError { stage: stage.to_string(), frame_index: None, message: message.to_string() }
+    }
 
-    #[error("Invalid input")]
-    InvalidInput,
+    /// Record the frame that was being processed when this happened
+    pub(crate) fn at_frame(mut self, index: usize) -> Self {
+        match &mut self {
+            Self::QuantizationError { frame_index, .. }
+            | Self::EncodingError { frame_index, .. }
+            | Self::InvalidInput { frame_index, .. }
+            | Self::MemoryError { frame_index, .. } => *frame_index = Some(index as u32),
+            Self::InvalidOption { .. } => {}
+        }
+        self
+    }
+}
 
-    #[error("Memory error")]
-    MemoryError,
+fn frame_suffix(frame_index: &Option<u32>) -> String {
+    frame_index.map(|index| format!(" (frame {})", index)).unwrap_or_default()
 }
 
 // ============================================================================
 // CONFIGURATION STRUCTURES
 // ============================================================================
 
+/// Palette generation backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuantizerBackend {
+    Imagequant,                  // libimagequant (default)
+    Oklab,                       // Median cut in OKLab space with temporal dithering
+    Oklch,                       // Hue-preserving median cut in OKLCh space
+}
+
+/// Nearest-color metric for the OKLab backends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DistanceMetric {
+    Euclidean,                   // Squared distance in OKLab (fastest)
+    Ciede2000,                   // Exact ΔE2000 against every palette entry
+    Ciede2000Fast,               // OKLab shortlist re-ranked by ΔE2000
+}
+
+/// Dithering applied by the OKLab backends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DitherMode {
+    Sierra,                      // Temporal Sierra error diffusion (default)
+    Atkinson,                    // Atkinson error diffusion, high-contrast retro look
+    Stucki,                      // Stucki error diffusion
+    Jarvis,                      // Jarvis-Judice-Ninke error diffusion
+    BlueNoise,                   // Ordered blue noise, frames dithered in parallel
+    AdaptiveBlueNoise,           // Blue noise attenuated on edges by `edge_preservation`
+}
+
 /// Color quantization options
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct QuantizeOpts {
     pub quality_min: u8,         // 0-100, lower = better compression
     pub quality_max: u8,         // 0-100, higher = better quality
     pub speed: i32,              // 1-10, 1=slowest/best quality
-    pub palette_size: u16,       // Max colors (typically 255)
+    pub palette_size: u16,       // Max colors, 2-256; the GIF color table shrinks to fit
     pub dithering_level: f32,    // 0.0-1.0, dithering strength
     pub shared_palette: bool,    // Use same palette for all frames
+    pub backend: QuantizerBackend, // Palette builder to use
+    pub distance_metric: DistanceMetric, // Palette matching metric (OKLab backends)
+    pub dither_mode: DitherMode, // Dithering algorithm (OKLab backends)
+    pub edge_preservation: f32,  // 0.0-1.0, noise removed on edges (AdaptiveBlueNoise)
+    pub time_budget_ms: u32,     // Pick speed per clip to finish within this (Imagequant, 0 = fixed speed)
+    pub exact_colors: bool,      // Skip quantization when the clip already fits in `palette_size` colors
+    pub locked_palette: Option<Vec<u8>>, // RGBA quads to map onto instead of building a palette (e.g. a previous `palette_rgba`)
+    pub sample_fraction: f32,    // 0.0-1.0, share of each frame's pixels the palette is built from (OKLab backends)
+    pub lut_resolution: u16,     // Cells per OKLab axis of the nearest-color table (blue noise, 0 = exact search)
+    pub adaptive_palette: bool,  // Stop below `palette_size` once extra colors barely lower the error (OKLab backends)
+    pub motion_weight: f32,      // 0.0-10.0, extra palette weight for pixels that change across frames (0 = off)
 }
 
-/// GIF output options
-#[derive(Debug, Clone)]
-pub struct GifOpts {
-    pub width: u16,              // Output width in pixels
-    pub height: u16,             // Output height in pixels
-    pub frame_count: u16,        // Number of frames
-    pub fps: u16,                // Frames per second
-    pub loop_count: u16,         // 0 = infinite loop
-    pub optimize: bool,          // Apply additional optimizations
-    pub include_tensor: bool,    // Generate 16×16×256 tensor data
+impl Default for QuantizeOpts {
+    fn default() -> Self {
+        Self {
+            quality_min: 70,
+            quality_max: 100,
+            speed: 5,
+            palette_size: 256,
+            dithering_level: 1.0,
+            shared_palette: true,
+            backend: QuantizerBackend::Imagequant,
+            distance_metric: DistanceMetric::Euclidean,
+            dither_mode: DitherMode::Sierra,
+            edge_preservation: blue_noise::DEFAULT_EDGE_PRESERVATION,
+            time_budget_ms: 0,
+            exact_colors: true,
+            locked_palette: None,
+            sample_fraction: 1.0,
+            lut_resolution: 32,
+            adaptive_palette: false,
+            motion_weight: 0.0,
+        }
+    }
 }
 
-/// Processing result with metrics
-#[derive(Debug, Clone)]
-pub struct ProcessResult {
-    pub gif_data: Vec<u8>,           // Complete GIF89a file data
-    pub tensor_data: Option<Vec<u8>>, // Optional tensor for voxel visualization
-    pub final_file_size: u32,         // Size in bytes
-    pub processing_time_ms: f32,      // Total processing time
-    pub actual_frame_count: u16,      // Frames processed
-    pub palette_size_used: u16,       // Colors in palette
+/// How to drop frames when a clip is longer than `GifOpts::frame_count`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DecimationStrategy {
+    Uniform,                     // Evenly spaced frames (default)
+    MotionWeighted,              // Keep more frames where the scene changes
+    SharpnessWeighted,           // Sharpest frame from each evenly sized window
 }
 
-// ============================================================================
-// MAIN PROCESSING PIPELINE
-// ============================================================================
+/// How frames are scaled to the output size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScaleMode {
+    Area,                        // Crop to the output aspect and area-average (default)
+    PixelArt,                    // Integer-factor nearest neighbor, the GIF shrinks to fit
+    Fit,                         // Area-average the whole frame inside the output, borders filled per GifOpts::letterbox
+}
 
-/// Process all frames in a single FFI call for maximum performance
-///
-/// # Arguments
-/// * `frames_rgba` - Flattened RGBA data for all frames
-/// * `width` - Frame width in pixels
-/// * `height` - Frame height in pixels
-/// * `frame_count` - Number of frames to process
-/// * `quantize_opts` - Color quantization settings
-/// * `gif_opts` - GIF output settings
-///
-/// # Returns
-/// * `ProcessResult` containing GIF data and optional tensor
-pub fn process_all_frames(
-    frames_rgba: Vec<u8>,
-    width: u32,
-    height: u32,
-    frame_count: u32,
-    quantize_opts: QuantizeOpts,
-    gif_opts: GifOpts,
-) -> Result<ProcessResult> {
-    let start = Instant::now();
+/// What fills the borders a Fit resize leaves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LetterboxFill {
+    Solid,                       // LetterboxOpts::color
+    Blur,                        // The frame itself, cropped to fill the output and blurred
+    Transparent,                 // Clear, for the transparent palette slot
+}
 
-    // Validate input buffer size
-    let expected_size = (width * height * 4 * frame_count) as usize;
-    if frames_rgba.len() != expected_size {
-        return Err(ProcessorError::InvalidInput);
+/// Border fill for `ScaleMode::Fit`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LetterboxOpts {
+    pub fill: LetterboxFill,
+    pub color: u32,              // 0xRRGGBB for Solid
+    pub blur_radius: u16,        // Blur radius in output pixels (0 = a sixteenth of the longer side)
+    pub dim: f32,                // 0.0-1.0, darkens the blurred copy so the frame stands out
+}
+
+impl Default for LetterboxOpts {
+    fn default() -> Self {
+        Self { fill: LetterboxFill::Solid, color: 0x000000, blur_radius: 0, dim: 0.0 }
     }
+}
 
-    // Split buffer into individual frames
-    let frame_size = (width * height * 4) as usize;
-    let frames: Vec<&[u8]> = frames_rgba.chunks_exact(frame_size).collect();
+/// What a viewer does with a frame before drawing the next one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FrameDisposal {
+    Unspecified,                 // Left to the viewer, most keep the frame
+    Keep,                        // Next frame draws over this one (default)
+    Background,                  // Cleared to transparent, so transparent pixels never show stale ones
+    Previous,                    // Restored to the canvas before this frame
+}
 
-    // Use imagequant for proven quality
-    process_with_imagequant(frames, width, height, quantize_opts, gif_opts)
+/// Whether a file still matches the content hash it was saved with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrityCheck {
+    Verified,                    // Hash matches, the file is as written
+    Corrupted,                   // Hash differs: damaged or edited since
+    Unsigned,                    // No hash to check, saved without one or cut short
 }
 
-// ============================================================================
-// OKLAB PROCESSING PIPELINE
-// ============================================================================
+/// Look applied to frames before quantization
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EffectKind {
+    Grain,                       // Luma noise reseeded every frame
+    Vignette,                    // Darkened corners
+    Scanlines,                   // Darkened alternate row bands
+    ChromaticAberration,         // Red and blue channels shifted apart
+}
 
-/// Process frames using perceptually uniform OKLab color space
-fn process_with_oklab(
-    frames: Vec<&[u8]>,
-    width: u32,
-    height: u32,
-    quantize_opts: QuantizeOpts,
-    gif_opts: GifOpts,
-) -> Result<ProcessResult> {
-    use oklab_quantization::{
-        srgb_to_oklab_batch,
-        build_oklab_palette,
-        oklab_palette_to_srgb,
-        TemporalDither,
-    };
+/// One stage of an `EffectChain`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Effect {
+    pub kind: EffectKind,
+    pub intensity: f32,          // 0.0-1.0, 0 disables the stage
+    pub size: f32,               // Grain cell, scanline period or aberration shift in pixels
+    pub seed: u32,               // Grain noise seed, combined with the frame index
+}
 
-    let start = Instant::now();
+/// Preprocessing applied to output-size frames right before quantization
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreprocessOpts {
+    pub posterize_red_bits: u8,   // 1-8 bits kept per channel (8 = off); 5-6-5 is the 16-bit display look
+    pub posterize_green_bits: u8,
+    pub posterize_blue_bits: u8,
+}
 
-    // Convert all frames to OKLab color space
-    let mut all_oklab_pixels = Vec::new();
-    for frame in &frames {
-        let oklab = srgb_to_oklab_batch(frame);
-        all_oklab_pixels.extend(oklab);
+impl Default for PreprocessOpts {
+    fn default() -> Self {
+        Self {
+            posterize_red_bits: 8,
+            posterize_green_bits: 8,
+            posterize_blue_bits: 8,
+        }
     }
+}
 
-    // Build optimal palette in OKLab space
-    let palette_size = quantize_opts.palette_size.min(255) as usize;
-    let oklab_palette = build_oklab_palette(&all_oklab_pixels, palette_size);
+/// Color vision deficiency `AccessibilityOpts` designs the palette for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorVisionDeficiency {
+    Deuteranopia,                // No green cones, the most common form
+    Protanopia,                  // No red cones, reds also look darker
+    Tritanopia,                  // No blue cones, blue-green and yellow-violet merge
+}
 
-    // Convert palette back to sRGB for GIF encoding
-    let srgb_palette = oklab_palette_to_srgb(&oklab_palette);
+/// Palette post-process for color-blind viewers, applied once the frames are indexed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilityOpts {
+    pub deficiency: Option<ColorVisionDeficiency>, // Viewer to design for (None = off)
+    pub preserve_contrast: bool, // Shift colors that viewer would confuse apart in lightness
+    pub min_contrast: f32,       // OKLab distance kept between colors as that viewer sees them
+    pub simulate: bool,          // Replace the palette with what that viewer sees, for previews
+}
+
+impl Default for AccessibilityOpts {
+    fn default() -> Self {
+        Self {
+            deficiency: None,
+            preserve_contrast: false,
+            min_contrast: 0.06,
+            simulate: false,
+        }
+    }
+}
 
-    // Apply temporal dithering for smooth animation
-    let mut temporal_dither = TemporalDither::new();
-    let mut indexed_frames = Vec::new();
+/// Effects applied in order to every frame
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EffectChain {
+    pub effects: Vec<Effect>,
+}
 
-    for (_frame_idx, frame_data) in frames.iter().enumerate() {
-        let frame_oklab = srgb_to_oklab_batch(frame_data);
-        let indices = temporal_dither.apply(
-            &frame_oklab,
-            &oklab_palette,
-            width as usize,
-            height as usize,
-        );
-        indexed_frames.push(indices);
+impl EffectChain {
+    /// Worn videotape look: aberration, scanlines, grain and a vignette
+    pub fn retro_vhs() -> Self {
+        let effect = |kind, intensity, size| Effect { kind, intensity, size, seed: 0 };
+        Self {
+            effects: vec![
+                effect(EffectKind::ChromaticAberration, 1.0, 2.0),
+                effect(EffectKind::Scanlines, 0.25, 2.0),
+                effect(EffectKind::Grain, 0.2, 1.0),
+                effect(EffectKind::Vignette, 0.5, 0.0),
+            ],
+        }
     }
+}
 
-    // Encode as GIF89a
-    let gif_buffer = encode_gif(&indexed_frames, &srgb_palette, &gif_opts)?;
+/// What to do when GifOpts asks for a side over `validation::MAX_GIF_SIDE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OversizePolicy {
+    Downscale,                   // Shrink width×height, keeping the aspect, until both sides fit
+    Reject,                      // Fail validation naming the oversized field
+}
 
-    // Generate tensor if requested (for voxel visualization)
-    let tensor_data = if gif_opts.include_tensor {
-        eprintln!("[RUST] Building tensor for voxel visualization...");
-        eprintln!("[RUST]   Frame count: {}", frames.len());
-        eprintln!("[RUST]   Frame dimensions: {}x{}", width, height);
-        let tensor = build_tensor_from_frames(&frames, width, height)?;
-        eprintln!("[RUST]   Tensor size: {} bytes", tensor.len());
-        eprintln!("[RUST]   Expected size for 128³: {} bytes", 128*128*128*4);
+/// Extra output size rendered from the same quantized frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GifVariant {
+    pub width: u16,
+    pub height: u16,
+}
 
-        // Verify tensor is not empty
-        let has_data = tensor.iter().take(1000).any(|&b| b != 0);
-        eprintln!("[RUST]   Contains non-zero data: {}", has_data);
+/// GIF output options
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GifOpts {
+    pub width: u16,              // Output width in pixels
+    pub height: u16,             // Output height in pixels
+    pub frame_count: u32,        // Frame budget, longer clips are decimated (0 = keep all)
+    pub fps: u16,                // Frames per second
+    pub loop_count: u16,         // 0 = infinite loop
+    pub optimize: bool,          // Apply additional optimizations
+    pub include_tensor: bool,    // Generate 16×16×256 tensor data
+    pub variants: Vec<GifVariant>, // Additional sizes (e.g. preview thumbnails)
+    pub decimation: DecimationStrategy, // Frame selection when over budget
+    pub seamless_loop: bool,     // Crossfade the last frames into the first
+    pub loop_crossfade_frames: u16, // Tail frames blended when seamless_loop is set
+    pub align_tensor_slices: bool, // Remove camera shake along the tensor's Z axis
+    pub occupancy: OccupancyOpts, // Per-voxel alpha keying for hollow cubes
+    pub include_motion: bool,    // Motion energy volume alongside the tensor
+    pub tensor: TensorOpts,      // Tensor content when include_tensor is set
+    pub include_turntable: bool, // Raycast a spinning preview of the voxel cube into turntable_data
+    pub turntable: TurntableOpts, // Preview settings when include_turntable is set
+    pub include_long_exposure: bool, // Stack every frame into one still in long_exposure_data
+    pub long_exposure: LongExposureOpts, // Still settings when include_long_exposure is set
+    pub include_text_art: bool,  // Redraw every frame as characters or emoji into text_art
+    pub text_art: TextArtOpts,   // Text art settings when include_text_art is set
+    #[serde(skip)]
+    pub checkpoint_path: Option<String>, // Save quantized frames here until the encode finishes
+    pub checkpoint_ttl_secs: u32, // After this long resume_encoding refuses the checkpoint
+    #[serde(skip)]
+    pub output_path: Option<String>, // Stream the GIF to this file; gif_data comes back empty
+    pub scale_mode: ScaleMode,   // Resampling used to reach width×height
+    pub letterbox: LetterboxOpts, // Border fill when scale_mode is Fit
+    pub oversize: OversizePolicy, // Outputs over 4096 px a side (GIF allows 65535, but nothing plays them)
+    pub pixel_grid: u16,         // Source pixels per art pixel for PixelArt (0 = detect)
+    pub sharpen_amount: f32,     // Unsharp mask strength after resizing (0 = off, 0.5-1.5 typical)
+    pub sharpen_radius: f32,     // Unsharp mask blur sigma in output pixels
+    pub effects: EffectChain,    // Looks applied before quantization
+    pub frame_filters: Vec<String>, // Registered custom filters run after `effects`, in order
+    pub preprocess: PreprocessOpts, // Last steps before quantization, after filters
+    pub accessibility: AccessibilityOpts, // Color-blind simulation or contrast fixes on the final palette
+    pub frame_range: Option<FrameRange>, // Input frames to export (None = all), before decimation
+    pub roi: Option<CropRect>,   // Input region to export (None = whole frame), before scaling
+    pub motion_crop: MotionCropOpts, // Find the region the subject moves in, and optionally crop to it
+    pub matte_threshold: u8,     // Masked alpha below this turns fully transparent (process_masked_frames, push_masked_frame)
+    #[serde(skip)]
+    pub frame_transforms: Vec<AffineTransform>, // Per-input-frame warps applied while scaling (Area), from the first frame; the rest stay put
+    pub segment_max_bytes: u32,  // Also split the GIF into segments at most this large (0 = no limit)
+    pub segment_max_frames: u32, // Also split the GIF into segments of at most this many frames (0 = no limit)
+    pub disposal: FrameDisposal, // Disposal for every frame not listed in frame_disposals
+    pub frame_disposals: Vec<FrameDisposal>, // Per-output-frame overrides, from the first frame
+    pub frame_delays: Vec<u16>,  // Per-output-frame delays in centiseconds, from the first frame; fps paces the rest
+    pub audio_sync: AudioSyncOpts, // Delays and effect strength following the soundtrack; frame_delays still win
+    #[serde(skip)]
+    pub frame_metadata: Vec<FrameMetadata>, // Capture details per input frame, summarized into the GIF
+    pub background_index: u8,    // Palette index of the logical screen background
+    pub integrity_hash: bool,    // End the GIF with a BLAKE3 hash of its content, see verify_gif_integrity
+    pub color_space: ColorSpace, // Space the input frames are in; recorded in the GIF's metadata, not converted
+}
 
-        if !has_data {
-            eprintln!("[RUST] WARNING: Tensor appears to be all zeros!");
+impl Default for GifOpts {
+    fn default() -> Self {
+        Self {
+            width: 128,              // N=128 mathematically optimal
+            height: 128,
+            frame_count: 128,
+            fps: 30,
+            loop_count: 0,           // Infinite loop
+            optimize: true,
+            include_tensor: false,
+            variants: Vec::new(),
+            decimation: DecimationStrategy::Uniform,
+            seamless_loop: false,
+            loop_crossfade_frames: 8,
+            align_tensor_slices: false,
+            occupancy: OccupancyOpts::default(),
+            include_motion: false,
+            tensor: TensorOpts::default(),
+            include_turntable: false,
+            turntable: TurntableOpts::default(),
+            include_long_exposure: false,
+            long_exposure: LongExposureOpts::default(),
+            include_text_art: false,
+            text_art: TextArtOpts::default(),
+            checkpoint_path: None,
+            checkpoint_ttl_secs: 24 * 60 * 60,
+            output_path: None,
+            scale_mode: ScaleMode::Area,
+            letterbox: LetterboxOpts::default(),
+            oversize: OversizePolicy::Downscale,
+            pixel_grid: 0,
+            sharpen_amount: 0.0,
+            sharpen_radius: 1.0,
+            effects: EffectChain::default(),
+            preprocess: PreprocessOpts::default(),
+            accessibility: AccessibilityOpts::default(),
+            frame_filters: Vec::new(),
+            frame_range: None,
+            roi: None,
+            motion_crop: MotionCropOpts::default(),
+            matte_threshold: 128,
+            frame_transforms: Vec::new(),
+            segment_max_bytes: 0,
+            segment_max_frames: 0,
+            disposal: FrameDisposal::Keep,
+            frame_disposals: Vec::new(),
+            frame_delays: Vec::new(),
+            audio_sync: AudioSyncOpts::default(),
+            frame_metadata: Vec::new(),
+            background_index: 0,
+            integrity_hash: false,
+            color_space: ColorSpace::Srgb,
         }
+    }
+}
 
-        Some(tensor)
-    } else {
-        eprintln!("[RUST] Tensor generation skipped (include_tensor = false)");
-        None
-    };
+/// Encoded GIF for one requested variant size
+#[derive(Debug, Clone)]
+pub struct GifVariantOutput {
+    pub width: u16,
+    pub height: u16,
+    pub gif_data: Vec<u8>,
+}
 
-    let file_size = gif_buffer.len() as u32;
-    Ok(ProcessResult {
-        gif_data: gif_buffer,
-        tensor_data,
-        final_file_size: file_size,
-        processing_time_ms: start.elapsed().as_millis() as f32,
-        actual_frame_count: frames.len() as u16,
-        palette_size_used: srgb_palette.len() as u16,
-    })
+/// Camera details recorded with one frame; any field can be missing
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrameMetadata {
+    pub timestamp_ms: Option<u64>, // Capture time, Unix milliseconds
+    pub exposure_s: Option<f64>, // Exposure duration in seconds
+    pub iso: Option<u32>,
+    pub latitude: Option<f64>,   // WGS84 degrees
+    pub longitude: Option<f64>,
 }
 
-// ============================================================================
-// FALLBACK IMAGEQUANT PIPELINE
-// ============================================================================
+/// Color space frame values are meant in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorSpace {
+    Srgb,                        // What viewers assume for an untagged GIF
+    DisplayP3,                   // Wide-gamut iPhone capture, same transfer curve as sRGB
+}
 
-/// Primary processing using imagequant library
-fn process_with_imagequant(
-    frames: Vec<&[u8]>,
-    width: u32,
-    height: u32,
-    quantize_opts: QuantizeOpts,
-    gif_opts: GifOpts,
-) -> Result<ProcessResult> {
-    let start = Instant::now();
+/// Clip-wide summary of `FrameMetadata`, stored in GIF and YXV files
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClipMetadata {
+    pub frame_count: u32,        // Frames that carried any metadata
+    pub start_ms: Option<u64>,   // Earliest and latest capture timestamps
+    pub end_ms: Option<u64>,
+    pub exposure_min_s: Option<f64>,
+    pub exposure_max_s: Option<f64>,
+    pub iso_min: Option<u32>,
+    pub iso_max: Option<u32>,
+    pub latitude: Option<f64>,   // Location of the first frame that has one
+    pub longitude: Option<f64>,
+    pub color_space: Option<ColorSpace>, // GifOpts::color_space when it isn't sRGB (None = sRGB)
+}
 
-    // Setup imagequant
-    let mut attr = imagequant::new();
-    attr.set_quality(quantize_opts.quality_min, quantize_opts.quality_max)
-        .map_err(|_| ProcessorError::QuantizationError)?;
-    attr.set_speed(quantize_opts.speed)
-        .map_err(|_| ProcessorError::QuantizationError)?;
+/// One part of a clip split by `GifOpts::segment_max_bytes` / `segment_max_frames`
+#[derive(Debug, Clone)]
+pub struct GifSegment {
+    pub first_frame: u32,        // Index of the segment's first frame in the full GIF
+    pub frame_count: u32,
+    pub gif_data: Vec<u8>,       // Standalone GIF89a sharing the full GIF's palette
+}
 
-    // Convert frames to RGBA pixels
-    let mut images = Vec::new();
-    for frame_data in &frames {
-        let pixels: Vec<RGBA> = frame_data
-            .chunks_exact(4)
-            .map(|chunk| RGBA::new(chunk[0], chunk[1], chunk[2], chunk[3]))
-            .collect();
-
-        let img = attr.new_image(&pixels[..], width as usize, height as usize, 0.0)
-            .map_err(|_| ProcessorError::QuantizationError)?;
-        images.push(img);
-    }
+/// Effect inserted between concatenated clips
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionKind {
+    Cut,                         // Hard cut, no extra frames
+    Crossfade,                   // Blend last frame of one clip into the next
+    Wipe,                        // Left-to-right reveal of the next clip
+}
 
-    // Quantize with shared palette
-    if images.is_empty() {
-        return Err(ProcessorError::InvalidInput);
-    }
+/// Transition settings for `concatenate_clips`
+#[derive(Debug, Clone)]
+pub struct Transition {
+    pub kind: TransitionKind,
+    pub frame_count: u16,        // Frames inserted per transition (ignored for Cut)
+}
 
-    let mut quantization = attr.quantize(&mut images[0])
-        .map_err(|_| ProcessorError::QuantizationError)?;
-    quantization.set_dithering_level(quantize_opts.dithering_level)
-        .map_err(|_| ProcessorError::QuantizationError)?;
+/// How `compose_clips` arranges its two clips
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompositeLayout {
+    SideBySide,                  // Primary on the left, secondary on the right
+    Stacked,                     // Primary on top, secondary below
+    PictureInPicture,            // Primary fills the canvas, secondary inset in a corner
+}
 
-    // Remap frames to palette indices
-    let mut indexed_frames = Vec::new();
-    for i in 0..images.len() {
-        let (_, indices) = quantization.remapped(&mut images[i])
-            .map_err(|_| ProcessorError::QuantizationError)?;
-        indexed_frames.push(indices);
+/// Corner a picture-in-picture inset sits in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsetCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// One of the clips `compose_clips` lays out, with how it fills its pane
+#[derive(Debug, Clone)]
+pub struct CompositeClip {
+    pub frames_rgba: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub frame_count: u32,
+    pub scale_mode: ScaleMode,   // Area crops to fill the pane, Fit letterboxes with gif_opts.letterbox (not PixelArt)
+    pub roi: Option<CropRect>,   // Region of the clip shown (None = whole frame)
+}
+
+/// Layout settings for `compose_clips`
+#[derive(Debug, Clone)]
+pub struct CompositeOpts {
+    pub layout: CompositeLayout,
+    pub gap: u16,                // Pixels between the panes, or of border around the inset
+    pub background: u32,         // 0xRRGGBB of the gap and border
+    pub inset_width: f32,        // Inset width as a share of the canvas, 0.05-1; its height follows its aspect
+    pub inset_corner: InsetCorner,
+    pub inset_margin: u16,       // Pixels between the inset and the canvas edges
+}
+
+impl Default for CompositeOpts {
+    fn default() -> Self {
+        Self {
+            layout: CompositeLayout::SideBySide,
+            gap: 0,
+            background: 0x000000,
+            inset_width: 0.3,
+            inset_corner: InsetCorner::BottomRight,
+            inset_margin: 8,
+        }
     }
+}
 
-    // Get palette after remapping
-    let palette = quantization.palette();
-    let palette_size = palette.len() as u16;
+/// Slice of a clip's frames, `start` inclusive to `end` exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrameRange {
+    pub start: u32,
+    pub end: u32,
+}
 
-    // Convert palette for GIF
-    let srgb_palette: Vec<[u8; 4]> = palette.iter()
-        .map(|c| [c.r, c.g, c.b, c.a])
-        .collect();
+/// Rectangle in pixels: `transcode_gif` crop, `GifOpts::roi`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CropRect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
 
-    // Encode GIF
-    let gif_buffer = encode_gif(&indexed_frames, &srgb_palette, &gif_opts)?;
+/// 2D affine map in input pixels, laid out like CGAffineTransform
+///
+/// A frame pixel at (x, y), origin top-left, lands at
+/// (a·x + c·y + tx, b·x + d·y + ty). Default is the identity.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AffineTransform {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub tx: f32,
+    pub ty: f32,
+}
 
-    // Generate tensor if requested
-    let tensor_data = if gif_opts.include_tensor {
-        eprintln!("[RUST] Building tensor for voxel visualization (imagequant path)...");
-        eprintln!("[RUST]   Frame count: {}", frames.len());
-        eprintln!("[RUST]   Frame dimensions: {}x{}", width, height);
-        let tensor = build_tensor_from_frames(&frames, width, height)?;
-        eprintln!("[RUST]   Tensor size: {} bytes", tensor.len());
-        eprintln!("[RUST]   Expected size for 128³: {} bytes", 128*128*128*4);
+impl Default for AffineTransform {
+    fn default() -> Self {
+        Self { a: 1.0, b: 0.0, c: 0.0, d: 1.0, tx: 0.0, ty: 0.0 }
+    }
+}
 
-        // Verify tensor is not empty
-        let has_data = tensor.iter().take(1000).any(|&b| b != 0);
-        eprintln!("[RUST]   Contains non-zero data: {}", has_data);
+/// What `GifOpts::motion_crop` does with the region the clip moves in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MotionCropMode {
+    Off,                         // Don't look for it
+    Detect,                      // Report it as `motion_rect`, encode the whole frame
+    Crop,                        // Report it and export only that region
+}
 
-        if !has_data {
-            eprintln!("[RUST] WARNING: Tensor appears to be all zeros!");
-        }
+/// Auto-crop to where the clip moves
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MotionCropOpts {
+    pub mode: MotionCropMode,
+    pub threshold: u8,           // Channel change between frames that counts as motion
+    pub padding: u16,            // Input pixels kept around the moving region on every side
+}
 
-        Some(tensor)
-    } else {
-        eprintln!("[RUST] Tensor generation skipped (include_tensor = false)");
-        None
-    };
+impl Default for MotionCropOpts {
+    fn default() -> Self {
+        Self { mode: MotionCropMode::Off, threshold: 24, padding: 8 }
+    }
+}
 
-    let file_size = gif_buffer.len() as u32;
-    Ok(ProcessResult {
-        gif_data: gif_buffer,
-        tensor_data,
-        final_file_size: file_size,
-        processing_time_ms: start.elapsed().as_millis() as f32,
-        actual_frame_count: frames.len() as u16,
-        palette_size_used: palette_size,
-    })
+/// What `transcode_gif` changes about an existing GIF
+#[derive(Debug, Clone, Default)]
+pub struct TranscodeOpts {
+    pub first_frame: u32,        // First frame kept
+    pub frame_count: u32,        // Frames kept from first_frame (0 = through the end)
+    pub fps: u16,                // Constant rate to retime to (0 = keep the original delays)
+    pub loop_count: u16,         // 0 = infinite loop
+    pub crop: Option<CropRect>,  // Region kept (None = whole canvas)
 }
 
-// ============================================================================
-// GIF ENCODING
-// ============================================================================
+/// What the tensor output contains
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TensorMode {
+    FrameStack,                  // cube_width×cube_height slices, one per frame
+    ColorHistogram,              // 64³ RGB histogram of the whole clip
+}
 
-/// Encode indexed frames as GIF89a
-fn encode_gif(
-    indexed_frames: &[Vec<u8>],
-    palette: &[[u8; 4]],
-    opts: &GifOpts,
-) -> Result<Vec<u8>> {
-    use gif::{Encoder, Frame, Repeat};
+/// Voxel linearization of tensor buffers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TensorLayout {
+    FrameMajor,                  // [frame][y][x], slices back to back
+    Morton,                      // Z-order curve over the enclosing 2^n cube
+    Hilbert,                     // Hilbert curve, neighbours stay adjacent in memory
+}
 
-    let mut gif_buffer = Vec::new();
+/// Tensor output settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TensorOpts {
+    pub mode: TensorMode,
+    pub cube_width: u16,         // Frame-stack slice size, independent of the GIF size
+    pub cube_height: u16,
+    pub layout: TensorLayout,    // Applies to tensor and motion volumes
+    pub compression_level: u8,   // zstd level 1-22 for tensor_data (0 = raw)
+    pub dictionary: Option<Vec<u8>>, // Shared zstd dictionary from train_tensor_dictionary
+    #[serde(skip)]
+    pub handoff_path: Option<String>, // Write the tensor here and return a TensorHandle instead
+}
 
-    // Convert palette to GIF format (RGB, no alpha)
-    let mut global_palette = Vec::with_capacity(768);
-    for color in palette.iter().take(256) {
-        global_palette.push(color[0]);
-        global_palette.push(color[1]);
-        global_palette.push(color[2]);
-    }
-
-    // Pad to 256 colors if needed
-    while global_palette.len() < 768 {
-        global_palette.push(0);
-    }
-
-    // Encode in a block to ensure encoder is dropped
-    {
-        let mut encoder = Encoder::new(
-            &mut gif_buffer,
-            opts.width,
-            opts.height,
-            &global_palette[0..768],
-        ).map_err(|_| ProcessorError::EncodingError)?;
-
-        // Set infinite loop
-        encoder.set_repeat(Repeat::Infinite)
-            .map_err(|_| ProcessorError::EncodingError)?;
-
-        // Write frames
-        for indices in indexed_frames {
-            let frame = Frame {
-                width: opts.width,
-                height: opts.height,
-                buffer: indices.clone().into(),
-                delay: 100 / opts.fps, // Convert FPS to centiseconds
-                ..Default::default()
-            };
-            encoder.write_frame(&frame)
-                .map_err(|_| ProcessorError::EncodingError)?;
+impl Default for TensorOpts {
+    fn default() -> Self {
+        Self {
+            mode: TensorMode::FrameStack,
+            cube_width: 128,
+            cube_height: 128,
+            layout: TensorLayout::FrameMajor,
+            compression_level: 0,
+            dictionary: None,
+            handoff_path: None,
         }
-    } // encoder is dropped here
+    }
+}
 
-    Ok(gif_buffer)
+/// What `ProcessResult::turntable_data` holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TurntableFormat {
+    Gif,                         // Looping GIF, quantized on its own palette
+    Rgba,                        // frame_count size×size RGBA frames back to back, transparent around the cube
 }
 
-// ============================================================================
-// TENSOR GENERATION FOR VOXEL VISUALIZATION
-// ============================================================================
+/// Turntable preview settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TurntableOpts {
+    pub size: u16,               // Preview width and height in pixels
+    pub frame_count: u16,        // Frames in one full turn
+    pub fps: u16,                // Playback rate of the GIF
+    pub elevation_deg: f32,      // Camera height above the cube's equator, -89 to 89
+    pub format: TurntableFormat,
+}
 
-/// Build 128×128×128 tensor from frames for voxel cube visualization (N=128 optimal)
-/// Optimal resolution tensor for exploring the voxel cube as a 3D object
-fn build_tensor_from_frames(frames: &[&[u8]], width: u32, height: u32) -> Result<Vec<u8>> {
-    eprintln!("[RUST] build_tensor_from_frames called");
-    eprintln!("[RUST]   Input: {} frames at {}x{}", frames.len(), width, height);
+impl Default for TurntableOpts {
+    fn default() -> Self {
+        Self {
+            size: 128,
+            frame_count: 60,
+            fps: 30,
+            elevation_deg: 20.0,
+            format: TurntableFormat::Gif,
+        }
+    }
+}
 
-    // For 128×128×128 voxel cube, we need 128 frames at 128×128 resolution
-    // If input is already 128×128, use directly; otherwise resample
+/// How the long-exposure still stacks frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExposureBlend {
+    Max,                         // Brightest value per channel (light trails)
+    Average,                     // Mean of every frame (motion blur, moving crowds fade out)
+    Screen,                      // Every frame projected at once, lighter than Max
+}
 
-    if width == 128 && height == 128 {
-        eprintln!("[RUST]   Using direct copy (frames already 128x128)");
-        // Direct copy - frames are already the right size
-        let mut tensor = Vec::with_capacity(frames.len() * 128 * 128 * 4);
+/// Long-exposure still settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LongExposureOpts {
+    pub blend: ExposureBlend,
+    pub build_up_frames: u16,    // Also a GIF of the exposure building up over this many frames (0 = still only)
+}
+
+impl Default for LongExposureOpts {
+    fn default() -> Self {
+        Self { blend: ExposureBlend::Max, build_up_frames: 0 }
+    }
+}
+
+/// Characters `GifOpts::text_art` draws each cell with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextArtStyle {
+    Ascii,                       // Plain characters from " " to "@" by brightness
+    Ansi,                        // The same characters in each cell's color, as 24-bit terminal escapes
+    Emoji,                       // Nearest colored square emoji, e.g. 🟥 or ⬛
+}
+
+/// Text art settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextArtOpts {
+    pub style: TextArtStyle,
+    pub columns: u16,            // Characters (or emoji) per line; rows follow the frame's aspect
+    pub invert: bool,            // Densest characters for the darkest cells, for light terminal backgrounds
+    pub render_gif: bool,        // Also draw the art into text_art_gif
+}
+
+impl Default for TextArtOpts {
+    fn default() -> Self {
+        Self { style: TextArtStyle::Ascii, columns: 64, invert: false, render_gif: true }
+    }
+}
+
+/// Soundtrack-driven pacing and effects
+///
+/// The host reduces its audio to one energy value per input frame (RMS, onset
+/// strength, a beat envelope, in any units); the loudest frame counts as 1.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSyncOpts {
+    pub energy: Vec<f32>,        // Non-negative energy per input frame, from the first frame; the rest are silent (empty = off)
+    pub timing: f32,             // 0-0.95: how much faster loud frames play than quiet ones; the clip's length is kept (0 = fps pacing)
+    pub effect_gain: f32,        // 0-1: `effects` fade to 1 - gain of their intensity on silent frames, full on the loudest
+}
+
+impl Default for AudioSyncOpts {
+    fn default() -> Self {
+        Self { energy: Vec::new(), timing: 0.5, effect_gain: 0.0 }
+    }
+}
+
+/// How voxel alpha is derived for the tensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OccupancyMode {
+    Solid,                       // Keep source alpha (solid block)
+    Luminance,                   // Keep voxels at or above a brightness
+    ChromaKey,                   // Drop voxels near the key color
+    Motion,                      // Keep voxels that change between slices
+}
+
+/// Occupancy keying for tensor output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OccupancyOpts {
+    pub mode: OccupancyMode,
+    pub threshold: f32,          // 0.0-1.0 cutoff for the selected mode
+    pub key_color: u32,          // 0xRRGGBB key for ChromaKey
+}
+
+impl Default for OccupancyOpts {
+    fn default() -> Self {
+        Self {
+            mode: OccupancyMode::Solid,
+            threshold: 0.1,
+            key_color: 0x00FF00,     // Green screen
+        }
+    }
+}
+
+/// Symmetry or time transform applied to a voxel cube
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoxelEffect {
+    MirrorOctants,               // Mirror one octant across all three axes
+    Kaleidoscope,                // 4-fold mirrored kaleidoscope around the Z axis
+    RadialRepeat,                // Inner disc repeated outward in mirrored rings
+    SlitScan,                    // Columns sweep through time, left edge now, right edge nearly a loop ahead
+    TimeDisplacement,            // Brighter voxels show further into the past
+}
+
+/// Axis a voxel cube is sliced across to play it back as frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliceAxis {
+    X,                           // One frame per column, time running left to right
+    Y,                           // One frame per row, time running down
+    Z,                           // One frame per slice, the original playback
+}
+
+/// Viewfinder preview options
+#[derive(Debug, Clone)]
+pub struct PreviewOpts {
+    pub max_colors: u16,         // Palette budget, mirrors QuantizeOpts::palette_size
+    pub dithering_level: f32,    // 0.0-1.0, ordered dither strength
+    pub max_dimension: u16,      // Longer side of the output (0 = input size)
+}
+
+impl Default for PreviewOpts {
+    fn default() -> Self {
+        Self {
+            max_colors: 256,
+            dithering_level: 1.0,
+            max_dimension: 128,
+        }
+    }
+}
+
+/// What transparent pixels are drawn over in preview frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackdropKind {
+    Checkerboard,                // Alternating squares of color and alt_color
+    Solid,                       // Flat color
+}
+
+/// Backdrop for displaying transparent GIFs
+#[derive(Debug, Clone)]
+pub struct Backdrop {
+    pub kind: BackdropKind,
+    pub color: u32,              // 0xRRGGBB fill, or the checkerboard's top-left squares
+    pub alt_color: u32,          // 0xRRGGBB for the other checkerboard squares
+    pub cell_size: u16,          // Checkerboard square side in canvas pixels
+}
+
+impl Default for Backdrop {
+    fn default() -> Self {
+        Self {
+            kind: BackdropKind::Checkerboard,
+            color: 0xFFFFFF,
+            alt_color: 0xCCCCCC,
+            cell_size: 8,
+        }
+    }
+}
+
+/// Quantized preview frame
+#[derive(Debug, Clone)]
+pub struct PreviewImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,               // RGBA8, alpha copied from the input
+}
+
+/// Tensor written to a file for the app to map instead of copying
+#[derive(Debug, Clone)]
+pub struct TensorHandle {
+    pub path: String,            // File holding the tensor bytes
+    pub byte_length: u64,        // Payload size (compressed size if compressed)
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,              // Slices (frames, or histogram bins)
+    pub row_stride: u32,         // Bytes per row, frame-major RGBA
+    pub slice_stride: u32,       // Bytes per slice, frame-major RGBA
+}
+
+/// How closely imagequant's palette reproduced the clip, as it measured each frame's remap
+///
+/// Errors are imagequant's mean square error (0 = exact); quality is its
+/// 0-100 estimate of how the frame looks remapped. A low worst-frame quality
+/// means one palette can't hold the clip, so splitting it into segments with
+/// their own palettes or raising palette_size should help.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct QuantizationReport {
+    pub mean_square_error: f32,  // Averaged over the clip's frames
+    pub quality: u8,             // The worst frame's quality
+    pub worst_frame: u32,        // Frame with the largest error
+    pub worst_frame_error: f32,  // That frame's mean square error
+}
+
+/// Wall-clock time spent in each pipeline stage
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct StageTimings {
+    pub preprocess_ms: f32,          // Trim, decimation, resize, sharpen, loop seam, effects
+    pub quantize_ms: f32,            // Palette and indexing, including dithering
+    pub encode_ms: f32,              // Main GIF, variants and segments
+    pub tensor_ms: f32,              // Tensor, motion, turntable, long-exposure and text art outputs, compression, handoff
+}
+
+/// Processing result with metrics
+///
+/// Serializes without its byte outputs, variants, segments and handle; the
+/// result cache stores the GIF, tensor and motion bytes beside the rest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessResult {
+    #[serde(skip)]
+    pub gif_data: Vec<u8>,           // Complete GIF89a file data
+    #[serde(skip)]
+    pub tensor_data: Option<Vec<u8>>, // Optional tensor for voxel visualization
+    #[serde(skip)]
+    pub motion_data: Option<Vec<u8>>, // Optional motion energy volume, 1 byte per voxel
+    #[serde(skip)]
+    pub turntable_data: Option<Vec<u8>>, // Spinning voxel cube preview, in GifOpts::turntable's format
+    #[serde(skip)]
+    pub long_exposure_data: Option<Vec<u8>>, // Every frame stacked into one RGBA still at the GIF's size
+    #[serde(skip)]
+    pub long_exposure_gif: Option<Vec<u8>>, // GIF of that still building up, when long_exposure.build_up_frames is set
+    #[serde(skip)]
+    pub text_art: Vec<String>,       // One block of text lines per frame, from GifOpts::text_art (empty = off)
+    #[serde(skip)]
+    pub text_art_gif: Option<Vec<u8>>, // GIF of the text art drawn in pixels, when text_art.render_gif is set
+    #[serde(skip)]
+    pub tensor_handle: Option<TensorHandle>, // Set instead of tensor_data when handing off via file
+    pub final_file_size: u32,         // Size in bytes
+    pub processing_time_ms: f32,      // Total processing time
+    pub actual_frame_count: u32,      // Frames processed
+    pub palette_size_used: u16,       // Colors in palette, after any adaptive truncation
+    #[serde(skip)]
+    pub variants: Vec<GifVariantOutput>, // One entry per GifOpts::variants
+    pub dropped_frames: u32,          // Frames lost to a full CaptureSession intake
+    #[serde(skip)]
+    pub segments: Vec<GifSegment>,    // The GIF split under the segment limits (empty if none set)
+    pub stage_timings: StageTimings,  // Where processing_time_ms went, plus preprocessing
+    pub palette_rgba: Vec<u8>,        // The GIF's palette as RGBA quads; reuse via QuantizeOpts::locked_palette
+    pub motion_rect: Option<CropRect>, // Moving region in input pixels, from GifOpts::motion_crop (None = off or nothing moved)
+    pub color_space: ColorSpace,      // Space the GIF's palette colors are meant in, from GifOpts::color_space
+    pub quantization: Option<QuantizationReport>, // imagequant's measure of the remap (None for the other backends)
+}
+
+/// Palette and size estimate from `analyze_frames`, for previewing settings before the encode
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipAnalysis {
+    pub palette_rgba: Vec<u8>,        // Palette the encode will use, as RGBA quads (transparent slot last)
+    pub exact: bool,                  // The palette holds every color of the clip, so the encode is lossless
+    pub width: u16,                   // Output size after scaling
+    pub height: u16,
+    pub frame_count: u32,             // Frames left after trimming and decimation
+    pub estimated_size: u32,          // Predicted GIF bytes, from encoding a few sample frames
+    pub analyze_ms: f32,              // Time the analysis took
+    pub motion_rect: Option<CropRect>, // Moving region in input pixels, from GifOpts::motion_crop; confirm it as GifOpts::roi
+}
+
+/// Settings `encode_quality_ladder` tries: every palette size with every dither mode
+#[derive(Debug, Clone)]
+pub struct QualityLadder {
+    pub palette_sizes: Vec<u16>,
+    pub dither_modes: Vec<DitherMode>, // OKLab backends; imagequant rungs differ by palette size only
+}
+
+impl Default for QualityLadder {
+    fn default() -> Self {
+        Self {
+            palette_sizes: vec![64, 128, 256],
+            dither_modes: vec![DitherMode::Sierra, DitherMode::BlueNoise],
+        }
+    }
+}
+
+/// One encode from `encode_quality_ladder`
+#[derive(Debug, Clone)]
+pub struct LadderRung {
+    pub palette_size: u16,
+    pub dither_mode: DitherMode,
+    pub gif_data: Vec<u8>,
+    pub file_size: u32,
+    pub mean_error: f32,              // Mean OKLab distance from the frames to the GIF (0 = identical)
+    pub encode_ms: f32,               // Quantize and encode time for this rung alone
+}
+
+/// Predicted GIF size range and encode time, from `estimate_encode`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EncodeEstimate {
+    pub bytes_low: u32,               // Likely GIF size range in bytes
+    pub bytes_high: u32,
+    pub est_ms: f32,                  // Likely process_all_frames time on this device
+}
+
+/// What `process_all_frames_validate` expects of an export, worked out without any frames
+///
+/// The projections are filled in even when `error` is set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationReport {
+    pub error: Option<String>,        // Why process_all_frames would refuse the input (None = it would run)
+    pub error_field: Option<String>,  // Option at fault when the error names one, e.g. "gif_opts.fps"
+    pub warnings: Vec<String>,        // Settings that run but may not do what was meant, e.g. a downscaled output
+    pub width: u16,                   // Output size after OversizePolicy; PixelArt may settle smaller
+    pub height: u16,
+    pub frame_count: u32,             // Frames left after frame_range and the frame budget
+    pub input_bytes: u64,             // RGBA buffer process_all_frames expects
+    pub max_gif_bytes: u64,           // Largest the GIF can be; estimate_encode predicts the likely size
+    pub tensor_bytes: u64,            // Uncompressed tensor and motion volume (0 = none)
+    pub peak_memory_bytes: u64,       // Rough high-water mark of the export, input buffer included
+}
+
+/// What the running device offers, from `probe_capabilities`
+#[derive(Debug, Clone)]
+pub struct DeviceCapabilities {
+    pub core_count: u32,
+    pub simd_available: bool,
+    pub simd_instruction_set: String, // "neon", "avx2", "sse4.1" or "none"
+    pub throughput_score: f32,   // Single-core Mpx/s on the palette-matching benchmark
+}
+
+/// What a full `CaptureSession` intake gives up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropPolicy {
+    DropOldest,                  // Discard the oldest queued frame
+    DropNewest,                  // Discard the incoming frame
+    Decimate,                    // Thin the backlog to every other frame
+}
+
+/// Counters for a `CaptureSession` intake
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntakeStats {
+    pub received: u64,           // Frames pushed since the last clear
+    pub dropped: u64,            // Frames discarded by the drop policy
+    pub pending: u32,            // Frames queued, not yet ingested
+}
+
+/// Counters for the shared frame buffer pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferPoolStats {
+    pub allocations: u64,        // Buffers the pool had to allocate
+    pub reuses: u64,             // Buffers handed back out instead of allocated
+    pub pooled_bytes: u64,       // Capacity currently held for reuse
+}
+
+/// Device thermal pressure as reported by the host (e.g. ProcessInfo.thermalState)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ThermalState {
+    Nominal,                     // Full speed
+    Fair,                        // One thread fewer, slightly faster quantizer
+    Serious,                     // Half the threads, optional passes skipped
+    Critical,                    // Single thread, fastest settings
+}
+
+/// On-disk cache of finished exports for `process_with_options`
+///
+/// Entries are keyed by a hash of the frames and every setting, so only an
+/// identical re-export hits. Once a store puts the directory over either
+/// limit, the least recently used entries go first; an entry larger than
+/// `max_bytes` on its own is not kept.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheConfig {
+    pub directory: String,       // Created on first store; e.g. the app's Caches directory
+    pub max_bytes: u64,          // Total size kept (0 = no limit)
+    pub max_entries: u32,        // Entries kept (0 = no limit)
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            directory: String::new(),
+            max_bytes: 256 * 1024 * 1024,
+            max_entries: 64,
+        }
+    }
+}
+
+/// `ProcessorOptions::one_bit` palette
+const ONE_BIT_PALETTE: [[u8; 4]; 2] = [[0, 0, 0, 255], [255, 255, 255, 255]];
+
+/// `ProcessorOptions::game_boy` palette, darkest shade first
+const GAME_BOY_PALETTE: [[u8; 4]; 4] = [[15, 56, 15, 255], [48, 98, 48, 255], [139, 172, 15, 255], [155, 188, 15, 255]];
+
+/// Quantizer and GIF settings chosen together
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessorOptions {
+    pub quantize_opts: QuantizeOpts,
+    pub gif_opts: GifOpts,
+    #[serde(skip)]
+    pub cache: Option<CacheConfig>, // Reuse identical exports from disk (None = always encode)
+}
+
+impl ProcessorOptions {
+    /// Sane defaults for a device, instead of hardcoding per model
+    pub fn auto_for(capabilities: &DeviceCapabilities) -> Self {
+        capabilities::auto_options(capabilities)
+    }
+
+    /// Crisp settings for pixel art captured on a `pixel_grid` (0 = detect)
+    ///
+    /// Whole-pixel nearest-neighbor scaling, no dithering, and the exact
+    /// palette whenever the art fits in 256 colors.
+    pub fn pixel_art(pixel_grid: u16) -> Self {
+        Self {
+            quantize_opts: QuantizeOpts {
+                dithering_level: 0.0,
+                exact_colors: true,
+                ..Default::default()
+            },
+            gif_opts: GifOpts {
+                scale_mode: ScaleMode::PixelArt,
+                pixel_grid,
+                ..Default::default()
+            },
+            cache: None,
+        }
+    }
+
+    /// `palette_size` colors picked from the clip, e.g. 2, 4 or 16 for tiny stickers
+    ///
+    /// The GIF's color table shrinks to match, so codes get shorter too.
+    /// Dithering follows the palette: Atkinson up to 4 colors, since it
+    /// spreads only three quarters of the error and flat areas stay clean
+    /// instead of turning to noise, and Sierra above that.
+    pub fn low_color(palette_size: u16) -> Self {
+        Self {
+            quantize_opts: QuantizeOpts {
+                quality_min: 0,
+                palette_size,
+                // The OKLab backend is the one that honors dither_mode
+                backend: QuantizerBackend::Oklab,
+                dither_mode: if palette_size <= 4 { DitherMode::Atkinson } else { DitherMode::Sierra },
+                ..Default::default()
+            },
+            gif_opts: GifOpts::default(),
+            cache: None,
+        }
+    }
+
+    /// Black and white only, Atkinson-dithered like early Mac screens
+    ///
+    /// Add a fully transparent entry to `locked_palette` for cut-out
+    /// stickers; it takes the third slot of a 2-bit table.
+    pub fn one_bit() -> Self {
+        let mut options = Self::low_color(2);
+        options.quantize_opts.locked_palette = Some(ONE_BIT_PALETTE.concat());
+        options
+    }
+
+    /// The original Game Boy's four greens on its 160×144 screen, with ordered dithering
+    pub fn game_boy() -> Self {
+        let mut options = Self::low_color(4);
+        options.quantize_opts.locked_palette = Some(GAME_BOY_PALETTE.concat());
+        options.quantize_opts.dither_mode = DitherMode::BlueNoise;
+        options.gif_opts.width = 160;
+        options.gif_opts.height = 144;
+        options
+    }
+
+    /// `size`×`size` GIF of a portrait or landscape capture, like social video apps post it
+    ///
+    /// The whole frame stays in view, centred over a blurred and slightly
+    /// dimmed copy of itself that fills the square.
+    pub fn blurred_square(size: u16) -> Self {
+        Self {
+            quantize_opts: QuantizeOpts::default(),
+            gif_opts: GifOpts {
+                width: size,
+                height: size,
+                scale_mode: ScaleMode::Fit,
+                letterbox: LetterboxOpts { fill: LetterboxFill::Blur, dim: 0.2, ..Default::default() },
+                ..Default::default()
+            },
+            cache: None,
+        }
+    }
+}
+
+/// Settings a capture was made with, under a name, so another machine can replay them
+///
+/// Stored in YXV containers. Paths and per-frame capture details are left
+/// out, since they only make sense for the original capture.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureProfile {
+    pub name: String,
+    pub options: ProcessorOptions,
+}
+
+/// One `process_all_frames` call, queued
+#[derive(Debug, Clone)]
+pub struct EncodeJob {
+    pub frames_rgba: Vec<u8>,
+    pub masks: Option<Vec<u8>>,  // Matte bytes for process_masked_frames (None = no matte)
+    pub width: u32,
+    pub height: u32,
+    pub frame_count: u32,
+    pub quantize_opts: QuantizeOpts,
+    pub gif_opts: GifOpts,
+}
+
+/// Lifecycle of a queued job
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,                   // Result waiting in take_result
+    Failed,
+    Cancelled,
+}
+
+/// Snapshot of one job's progress
+#[derive(Debug, Clone)]
+pub struct JobStatus {
+    pub state: JobState,
+    pub priority: u8,
+    pub queue_position: u32,     // Jobs that run before this one (0 unless Queued)
+    pub elapsed_ms: u64,         // Time spent running so far, or in total once finished
+    pub progress: f32,           // 0-1, frames quantized and encoded; 1 once completed
+}
+
+// ============================================================================
+// MAIN PROCESSING PIPELINE
+// ============================================================================
+
+/// Process all frames in a single FFI call for maximum performance
+///
+/// # Arguments
+/// * `frames_rgba` - Flattened RGBA data for all frames
+/// * `width` - Frame width in pixels
+/// * `height` - Frame height in pixels
+/// * `frame_count` - Number of frames to process
+/// * `quantize_opts` - Color quantization settings
+/// * `gif_opts` - GIF output settings
+///
+/// # Returns
+/// * `ProcessResult` containing GIF data and optional tensor
+pub fn process_all_frames(
+    frames_rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+    frame_count: u32,
+    quantize_opts: QuantizeOpts,
+    gif_opts: GifOpts,
+) -> Result<ProcessResult> {
+    process_rgba(&frames_rgba, width, height, frame_count, quantize_opts, gif_opts)
+}
+
+/// Pre-flight a `process_all_frames` call without its frames
+///
+/// Runs every check the export would make up front and projects its output
+/// size, frame count and memory, so an export can be vetted before the clip
+/// is even captured. The size is a hard upper bound, not a prediction.
+pub fn process_all_frames_validate(
+    width: u32,
+    height: u32,
+    frame_count: u32,
+    quantize_opts: QuantizeOpts,
+    gif_opts: GifOpts,
+) -> ValidationReport {
+    preflight::report(width, height, frame_count, &quantize_opts, &gif_opts)
+}
+
+/// `process_all_frames` with a background matte, e.g. from Vision person segmentation
+///
+/// `masks` holds one byte per pixel for every frame, in the same order as
+/// `frames_rgba`, and multiplies into each pixel's alpha. Pixels left below
+/// `gif_opts.matte_threshold` become transparent in the GIF; the rest keep the
+/// soft alpha as their palette weight and, with solid occupancy, voxel alpha.
+pub fn process_masked_frames(
+    mut frames_rgba: Vec<u8>,
+    masks: Vec<u8>,
+    width: u32,
+    height: u32,
+    frame_count: u32,
+    quantize_opts: QuantizeOpts,
+    gif_opts: GifOpts,
+) -> Result<ProcessResult> {
+    let expected = width as usize * height as usize * frame_count as usize;
+    if masks.len() != expected {
+        return Err(ProcessorError::invalid_input("matte", format!("{} mask bytes for {} {}x{} frames", masks.len(), frame_count, width, height)));
+    }
+    if frames_rgba.len() == expected * 4 {
+        matte::multiply(&mut frames_rgba, &masks);
+        matte::cut(&mut frames_rgba, gif_opts.matte_threshold);
+    }
+    process_rgba(&frames_rgba, width, height, frame_count, quantize_opts, gif_opts)
+}
+
+/// `process_all_frames` with both settings from `options`, reusing `options.cache` when it is set
+///
+/// A re-export of the same frames with the same settings returns the stored
+/// GIF and tensor without encoding; anything else encodes and stores the
+/// result. The key covers the options after thermal throttling, so a clip
+/// encoded while the device was hot is encoded again once it cools.
+/// Settings that write files or produce outputs the cache doesn't hold
+/// (variants, segments, turntable, long exposure, text art, custom filters) skip it.
+/// Cache I/O problems are logged and never fail the export.
+pub fn process_with_options(
+    frames_rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+    frame_count: u32,
+    options: ProcessorOptions,
+) -> Result<ProcessResult> {
+    let ProcessorOptions { quantize_opts, gif_opts, cache } = options;
+    let Some(cache) = cache.filter(|_| result_cache::cacheable(&gif_opts)) else {
+        return process_rgba(&frames_rgba, width, height, frame_count, quantize_opts, gif_opts);
+    };
+    validation::validate(frames_rgba.len(), width, height, frame_count, &quantize_opts, &gif_opts)?;
+    validation::cache(&cache)?;
+
+    let start = Instant::now();
+    let (key_quantize, key_gif) = thermal::throttle(quantize_opts.clone(), gif_opts.clone(), thermal::current());
+    let key = result_cache::key(&frames_rgba, width, height, frame_count, &key_quantize, &key_gif)?;
+    if let Some(mut result) = result_cache::load(&cache, &key) {
+        eprintln!("[RUST] Cache hit {}", &key[..16]);
+        result.processing_time_ms = start.elapsed().as_secs_f32() * 1000.0;
+        result.stage_timings = StageTimings::default();
+        return Ok(result);
+    }
+
+    let result = process_rgba(&frames_rgba, width, height, frame_count, quantize_opts, gif_opts)?;
+    if result.tensor_handle.is_none() {
+        if let Err(e) = result_cache::store(&cache, &key, &result) {
+            eprintln!("[RUST] Couldn't cache result: {}", e);
+        }
+    }
+    Ok(result)
+}
+
+/// Empty the `process_with_options` cache in `cache.directory`, returning how many entries were removed
+pub fn clear_result_cache(cache: CacheConfig) -> Result<u32> {
+    validation::cache(&cache)?;
+    result_cache::clear(&cache)
+}
+
+/// `process_all_frames` on borrowed frames, so callers can recycle the buffer
+fn process_rgba(
+    frames_rgba: &[u8],
+    width: u32,
+    height: u32,
+    frame_count: u32,
+    quantize_opts: QuantizeOpts,
+    gif_opts: GifOpts,
+) -> Result<ProcessResult> {
+    validation::validate(frames_rgba.len(), width, height, frame_count, &quantize_opts, &gif_opts)?;
+    throttled(quantize_opts, gif_opts, |quantize_opts, gif_opts| {
+        process_frames(frames_rgba, width, height, frame_count, quantize_opts, gif_opts)
+    })
+}
+
+/// Run `stage` with options and thread count backed off while the device is hot
+fn throttled<T: Send>(
+    quantize_opts: QuantizeOpts,
+    gif_opts: GifOpts,
+    stage: impl FnOnce(QuantizeOpts, GifOpts) -> Result<T> + Send,
+) -> Result<T> {
+    panic_log::ensure_installed();
+    let thermal_state = thermal::current();
+    let (quantize_opts, gif_opts) = thermal::throttle(quantize_opts, gif_opts, thermal_state);
+    match thermal::thread_limit(thermal_state, rayon::current_num_threads()) {
+        Some(threads) if threads < rayon::current_num_threads() => {
+            eprintln!("[RUST] Thermal state {:?}: limiting to {} threads", thermal_state, threads);
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .map_err(|e| ProcessorError::memory("thread pool", e))?;
+            let progress = progress::current();
+            pool.install(|| progress::scoped(progress, || stage(quantize_opts, gif_opts)))
+        }
+        _ => stage(quantize_opts, gif_opts),
+    }
+}
+
+/// `process_all_frames` after thermal throttling
+fn process_frames(
+    frames_rgba: &[u8],
+    width: u32,
+    height: u32,
+    frame_count: u32,
+    quantize_opts: QuantizeOpts,
+    gif_opts: GifOpts,
+) -> Result<ProcessResult> {
+    let start = Instant::now();
+    let tensor_opts = gif_opts.tensor.clone();
+    let checkpoint_path = gif_opts.checkpoint_path.clone();
+
+    let mut result = prepare_frames(frames_rgba, width, height, frame_count, gif_opts, |frames, width, height, gif_opts, motion_rect| {
+        let preprocess_ms = elapsed_ms(start);
+
+        let extras_start = Instant::now();
+        let turntable_data = build_turntable(&frames, width, height, &gif_opts)?;
+        let (long_exposure_data, long_exposure_gif) = build_long_exposure(&frames, width, height, &gif_opts)?;
+        let (text_art, text_art_gif) = build_text_art(&frames, width, height, &gif_opts)?;
+        let extras_ms = elapsed_ms(extras_start);
+
+        let mut result = encode_prepared(frames, width, height, quantize_opts, gif_opts)?;
+        result.stage_timings.preprocess_ms = preprocess_ms;
+        result.stage_timings.tensor_ms += extras_ms;
+        result.turntable_data = turntable_data;
+        result.long_exposure_data = long_exposure_data;
+        result.long_exposure_gif = long_exposure_gif;
+        result.text_art = text_art;
+        result.text_art_gif = text_art_gif;
+        result.motion_rect = motion_rect;
+        Ok(result)
+    })?;
+
+    if let Some(tensor) = result.tensor_data.take() {
+        let tensor_start = Instant::now();
+        let shape = match tensor_opts.mode {
+            TensorMode::FrameStack => tensor::TensorShape::new(
+                tensor_opts.cube_width as u32,
+                tensor_opts.cube_height as u32,
+                result.actual_frame_count as u32,
+            ),
+            TensorMode::ColorHistogram => tensor::TensorShape::cube(tensor::HISTOGRAM_BINS as u32),
+        };
+        deliver_tensor(&mut result, tensor, shape, &tensor_opts)?;
+        result.stage_timings.tensor_ms += elapsed_ms(tensor_start);
+    }
+
+    // Finished, nothing left to resume
+    if let Some(path) = checkpoint_path {
+        let _ = std::fs::remove_file(path);
+    }
+    Ok(result)
+}
+
+/// Quantize and encode frames `prepare_frames` produced, on the backend `quantize_opts` picks
+fn encode_prepared(frames: Vec<&[u8]>, width: u32, height: u32, quantize_opts: QuantizeOpts, gif_opts: GifOpts) -> Result<ProcessResult> {
+    if let Some(progress) = progress::current() {
+        progress.begin(frames.len());
+    }
+    let exact_start = Instant::now();
+    let exact = if quantize_opts.exact_colors && quantize_opts.locked_palette.is_none() {
+        exact_palette::build(&frames, quantize_opts.palette_size as usize)
+    } else {
+        None
+    };
+    let exact_ms = elapsed_ms(exact_start);
+
+    let mut result = match (exact, quantize_opts.backend) {
+        // Screen recordings and pixel art can be indexed losslessly
+        (Some(exact), _) => process_exact(frames, width, height, exact, gif_opts),
+        // A locked palette only needs mapping, which the OKLab path does for any palette
+        (None, _) if quantize_opts.locked_palette.is_some() => {
+            process_with_oklab(frames, width, height, quantize_opts, gif_opts)
+        }
+        // Use imagequant for proven quality
+        (None, QuantizerBackend::Imagequant) => {
+            process_with_imagequant(frames, width, height, quantize_opts, gif_opts)
+        }
+        (None, QuantizerBackend::Oklab | QuantizerBackend::Oklch) => {
+            process_with_oklab(frames, width, height, quantize_opts, gif_opts)
+        }
+    }?;
+    result.stage_timings.quantize_ms += exact_ms;
+    Ok(result)
+}
+
+/// Shrink the output and its variants to `MAX_GIF_SIDE`, or refuse them under `OversizePolicy::Reject`
+fn cap_output_size(gif_opts: &mut GifOpts) -> Result<()> {
+    let max = validation::MAX_GIF_SIDE;
+    let reject = gif_opts.oversize == OversizePolicy::Reject;
+    let sizes = std::iter::once((&mut gif_opts.width, &mut gif_opts.height))
+        .chain(gif_opts.variants.iter_mut().map(|v| (&mut v.width, &mut v.height)));
+    for (width, height) in sizes {
+        let longest = (*width).max(*height) as u32;
+        if longest <= max {
+            continue;
+        }
+        if reject {
+            return Err(ProcessorError::invalid_input("output size", format!("{}x{} is over {} a side", width, height, max)));
+        }
+        let fit = |side: u16| ((side as u32 * max + longest / 2) / longest).max(1) as u16;
+        eprintln!("[RUST] Output {}x{} is over {} a side, downscaling", width, height, max);
+        (*width, *height) = (fit(*width), fit(*height));
+    }
+    Ok(())
+}
+
+/// Trim, decimate, crop, scale, sharpen, loop and style the frames, then hand them to `then`
+///
+/// `then` gets the frames ready to quantize, their size, the options with the
+/// size pixel art settled on and the region `motion_crop` found; the buffers go
+/// back to the pool once it returns.
+fn prepare_frames<T>(
+    frames_rgba: &[u8],
+    width: u32,
+    height: u32,
+    frame_count: u32,
+    mut gif_opts: GifOpts,
+    then: impl FnOnce(Vec<&[u8]>, u32, u32, GifOpts, Option<CropRect>) -> Result<T>,
+) -> Result<T> {
+    cap_output_size(&mut gif_opts)?;
+
+    // Validate input buffer size
+    let expected_size = calculate_buffer_size(width, height, frame_count);
+    if frames_rgba.len() as u64 != expected_size {
+        return Err(ProcessorError::invalid_input("process", format!("{} bytes for {} {}x{} frames", frames_rgba.len(), frame_count, width, height)));
+    }
+
+    // Split buffer into individual frames
+    let frame_size = (width * height * 4) as usize;
+    let mut frames: Vec<&[u8]> = frames_rgba.chunks_exact(frame_size).collect();
+    // Input index of each frame still in play, for the per-input-frame options
+    let mut sources: Vec<usize> = (0..frames.len()).collect();
+
+    // Trim to the requested range; an end past the clip stops at its last frame
+    if let Some(range) = gif_opts.frame_range {
+        let end = (range.end as usize).min(frames.len());
+        if range.start as usize >= end {
+            return Err(ProcessorError::invalid_input("frame range", format!("{}..{} selects none of {} frames", range.start, range.end, frames.len())));
+        }
+        frames = frames[range.start as usize..end].to_vec();
+        sources = sources[range.start as usize..end].to_vec();
+    }
+
+    // Decimate down to the frame budget before quantization
+    let budget = gif_opts.frame_count as usize;
+    if budget > 0 && frames.len() > budget {
+        let keep = decimation::select_frames(
+            &frames,
+            width as usize,
+            height as usize,
+            budget,
+            gif_opts.decimation,
+        );
+        sources = keep.iter().map(|&i| sources[i]).collect();
+        frames = keep.into_iter().map(|i| frames[i]).collect();
+    }
+
+    // Soundtrack energy of the frames kept; loud ones go by faster, explicit delays still win
+    let energy = if gif_opts.audio_sync.energy.is_empty() {
+        Vec::new()
+    } else {
+        audio_sync::frame_energy(&gif_opts.audio_sync.energy, &sources)
+    };
+    if !energy.is_empty() && gif_opts.audio_sync.timing > 0.0 {
+        let mut delays = audio_sync::delays(&frame_delays(gif_opts.fps, energy.len())?, &energy, gif_opts.audio_sync.timing);
+        for (delay, &explicit) in delays.iter_mut().zip(&gif_opts.frame_delays) {
+            *delay = explicit;
+        }
+        gif_opts.frame_delays = delays;
+    }
+
+    // Region of interest; Area scaling reads it in place, pixel art needs it cut out
+    let (mut width, mut height) = (width, height);
+    let mut roi = match gif_opts.roi {
+        Some(rect) => {
+            let (x, y, w, h) = (rect.x as u32, rect.y as u32, rect.width as u32, rect.height as u32);
+            if w == 0 || h == 0 || x + w > width || y + h > height {
+                return Err(ProcessorError::invalid_input("roi", format!("{}x{} at ({}, {}) is outside the {}x{} frame", w, h, x, y, width, height)));
+            }
+            (x, y, w, h)
+        }
+        None => (0, 0, width, height),
+    };
+
+    // Where the clip moves within that region; cropping to it narrows the region
+    let motion = &gif_opts.motion_crop;
+    let motion_rect = match motion.mode {
+        MotionCropMode::Off => None,
+        MotionCropMode::Detect | MotionCropMode::Crop => motion_crop::changed_bounds(&frames, width, roi, motion.threshold)
+            .map(|bounds| {
+                let (out_width, out_height) = (gif_opts.width.max(1) as u32, gif_opts.height.max(1) as u32);
+                motion_crop::padded_to_aspect(bounds, motion.padding as u32, roi, out_width, out_height)
+            }),
+    };
+    if let (MotionCropMode::Crop, Some(rect)) = (motion.mode, motion_rect) {
+        eprintln!("[RUST] Cropping to motion {}x{} at ({}, {})", rect.2, rect.3, rect.0, rect.1);
+        roi = rect;
+    }
+    let motion_rect = motion_rect.map(|(x, y, w, h)| CropRect { x: x as u16, y: y as u16, width: w as u16, height: h as u16 });
+
+    let cropped: Vec<Vec<u8>> = if roi != (0, 0, width, height) && gif_opts.scale_mode == ScaleMode::PixelArt {
+        frames
+            .par_iter()
+            .map(|frame| {
+                let mut out = FRAME_POOL.take((roi.2 * roi.3 * 4) as usize);
+                resize::crop(frame, width, roi, &mut out);
+                out
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    if !cropped.is_empty() {
+        frames = cropped.iter().map(|f| f.as_slice()).collect();
+        (width, height) = (roi.2, roi.3);
+        roi = (0, 0, width, height);
+    }
+
+    // Fit the camera frames to the output size
+    let (mut out_width, mut out_height) = (gif_opts.width as u32, gif_opts.height as u32);
+    if out_width == 0 || out_height == 0 {
+        return Err(ProcessorError::invalid_input("resize", "output size is empty"));
+    }
+    let resized: Vec<Vec<u8>> = match gif_opts.scale_mode {
+        // Whole art pixels only; the output becomes the largest integer downscale that fits
+        ScaleMode::PixelArt => {
+            let grid = match gif_opts.pixel_grid {
+                0 => frames.first().map_or(1, |frame| pixel_art::detect_grid(frame, width, height)),
+                grid => grid as u32,
+            };
+            let factor = pixel_art::choose_factor(width, height, grid, out_width, out_height);
+            (out_width, out_height) = ((width / factor).max(1), (height / factor).max(1));
+            gif_opts.width = out_width as u16;
+            gif_opts.height = out_height as u16;
+            eprintln!("[RUST] Pixel art grid {}, scaling down {}x to {}x{}", grid, factor, out_width, out_height);
+
+            if factor == 1 {
+                Vec::new()
+            } else {
+                frames
+                    .par_iter()
+                    .map(|frame| {
+                        let mut out = FRAME_POOL.take((out_width * out_height * 4) as usize);
+                        pixel_art::downscale_nearest(frame, width, height, factor, &mut out);
+                        out
+                    })
+                    .collect()
+            }
+        }
+        // Crop to the output aspect, then area-average; warped frames are filtered as they turn
+        ScaleMode::Area
+            if roi != (0, 0, width, height)
+                || (width, height) != (out_width, out_height)
+                || !gif_opts.frame_transforms.is_empty() =>
+        {
+            frames
+                .par_iter()
+                .zip(&sources)
+                .map(|(frame, &source)| {
+                    let mut out = FRAME_POOL.take((out_width * out_height * 4) as usize);
+                    match gif_opts.frame_transforms.get(source).filter(|t| **t != AffineTransform::default()) {
+                        Some(transform) => warp::cover_warp_region(frame, width, roi, transform, out_width, out_height, &mut out),
+                        None => resize::cover_resize_region(frame, width, roi, out_width, out_height, &mut out),
+                    }
+                    out
+                })
+                .collect()
+        }
+        // Keep the whole frame and fill around it
+        ScaleMode::Fit if roi != (0, 0, width, height) || (width, height) != (out_width, out_height) => frames
+            .par_iter()
+            .map(|frame| {
+                let mut out = FRAME_POOL.take((out_width * out_height * 4) as usize);
+                resize::fit_resize_region(frame, width, roi, out_width, out_height, &gif_opts.letterbox, &mut out);
+                out
+            })
+            .collect(),
+        ScaleMode::Area | ScaleMode::Fit => Vec::new(),
+    };
+    if !resized.is_empty() {
+        frames = resized.iter().map(|f| f.as_slice()).collect();
+    }
+    let (width, height) = (out_width, out_height);
+
+    // Win back the detail averaged away by the downscale
+    let sharpened: Vec<Vec<u8>> = if gif_opts.sharpen_amount > 0.0 {
+        frames
+            .par_iter()
+            .map(|frame| {
+                let mut out = FRAME_POOL.take(frame.len());
+                out.extend_from_slice(frame);
+                sharpen::unsharp_mask(&mut out, width, height, gif_opts.sharpen_radius, gif_opts.sharpen_amount);
+                out
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    if !sharpened.is_empty() {
+        frames = sharpened.iter().map(|f| f.as_slice()).collect();
+    }
+
+    // Smooth the wrap-around on the frames that will actually be encoded
+    let seam_tail = if gif_opts.seamless_loop {
+        loop_seam::smooth_loop_seam(&frames, gif_opts.loop_crossfade_frames as usize)
+    } else {
+        Vec::new()
+    };
+    let tail_start = frames.len() - seam_tail.len();
+    for (slot, blended) in frames[tail_start..].iter_mut().zip(&seam_tail) {
+        *slot = blended;
+    }
+
+    // Filters go last so grain and the like land on the final frames;
+    // posterizing after them settles their noise onto fixed levels
+    let custom_filters = filters::resolve(&gif_opts.frame_filters)?;
+    let posterize = posterize::Posterize::new(&gif_opts.preprocess);
+    let styled: Vec<Vec<u8>> = if gif_opts.effects.effects.is_empty() && custom_filters.is_empty() && posterize.is_none() {
+        Vec::new()
+    } else {
+        frames
+            .par_iter()
+            .enumerate()
+            .map(|(index, frame)| {
+                let mut out = FRAME_POOL.take(frame.len());
+                out.extend_from_slice(frame);
+                match energy.get(index) {
+                    Some(&energy) if gif_opts.audio_sync.effect_gain > 0.0 => {
+                        let chain = audio_sync::scaled_chain(&gif_opts.effects, energy, gif_opts.audio_sync.effect_gain);
+                        effects::apply_chain(&chain, &mut out, width, height, index as u32);
+                    }
+                    _ => effects::apply_chain(&gif_opts.effects, &mut out, width, height, index as u32),
+                }
+                for filter in &custom_filters {
+                    filter.process(&mut out, width, height, index as u32);
+                }
+                if let Some(posterize) = &posterize {
+                    posterize.apply(&mut out);
+                }
+                out
+            })
+            .collect()
+    };
+    if !styled.is_empty() {
+        frames = styled.iter().map(|f| f.as_slice()).collect();
+    }
+
+    let result = then(frames, width, height, gif_opts, motion_rect);
+
+    // Hand the frame buffers to the next encode
+    FRAME_POOL.give_all(cropped.into_iter().chain(resized).chain(sharpened).chain(styled));
+    result
+}
+
+/// Compress a finished tensor and return it inline or through a handoff file
+fn deliver_tensor(
+    result: &mut ProcessResult,
+    tensor: Vec<u8>,
+    shape: tensor::TensorShape,
+    opts: &TensorOpts,
+) -> Result<()> {
+    let tensor = if opts.compression_level == 0 {
+        tensor
+    } else {
+        compression::compress(&tensor, opts.compression_level, opts.dictionary.as_deref())?
+    };
+
+    match &opts.handoff_path {
+        Some(path) => result.tensor_handle = Some(handoff::write_tensor_file(path, &tensor, shape)?),
+        None => result.tensor_data = Some(tensor),
+    }
+    Ok(())
+}
+
+// ============================================================================
+// TWO-PASS ENCODING
+// ============================================================================
+
+/// Frames `analyze_frames` actually encodes to estimate the full clip's size
+const ESTIMATE_SAMPLE_FRAMES: usize = 4;
+
+/// First pass: preprocess the clip and build its palette without encoding it
+///
+/// Frames are frame-independent in a GIF, so the size estimate encodes a few
+/// evenly spaced frames with the real palette and dithering and scales their
+/// average up to the whole clip. Hand the result and the same options to
+/// `encode_analyzed` to finish.
+pub fn analyze_frames(
+    frames_rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+    frame_count: u32,
+    quantize_opts: QuantizeOpts,
+    gif_opts: GifOpts,
+) -> Result<ClipAnalysis> {
+    validation::validate(frames_rgba.len(), width, height, frame_count, &quantize_opts, &gif_opts)?;
+    let start = Instant::now();
+    throttled(quantize_opts, gif_opts, |quantize_opts, gif_opts| {
+        prepare_frames(&frames_rgba, width, height, frame_count, gif_opts, |frames, width, height, gif_opts, motion_rect| {
+            let (palette, transparent_index, exact) = clip_palette(&frames, width, height, &quantize_opts)?;
+            let step = frames.len().div_ceil(ESTIMATE_SAMPLE_FRAMES);
+            let samples: Vec<&[u8]> = frames.iter().step_by(step).copied().collect();
+            let (header_size, frame_sizes) =
+                sample_frame_sizes(samples, width, height, &palette, transparent_index, &quantize_opts, &gif_opts)?;
+            let frame_bytes = frame_sizes.iter().sum::<usize>() as f64 / frame_sizes.len() as f64;
+
+            Ok(ClipAnalysis {
+                palette_rgba: palette.concat(),
+                exact,
+                width: gif_opts.width,
+                height: gif_opts.height,
+                frame_count: frames.len() as u32,
+                estimated_size: (header_size as f64 + frame_bytes * frames.len() as f64).round() as u32,
+                analyze_ms: elapsed_ms(start),
+                motion_rect,
+            })
+        })
+    })
+}
+
+/// Second pass: encode the clip onto the palette `analyze_frames` built
+///
+/// Takes the same frames and options as the analysis. Exact palettes are
+/// rebuilt rather than locked so the encode stays lossless; either way the
+/// GIF's palette matches `analysis.palette_rgba`, before any
+/// `GifOpts::accessibility` adjustment.
+pub fn encode_analyzed(
+    frames_rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+    frame_count: u32,
+    analysis: ClipAnalysis,
+    quantize_opts: QuantizeOpts,
+    gif_opts: GifOpts,
+) -> Result<ProcessResult> {
+    let quantize_opts = if analysis.exact {
+        quantize_opts
+    } else {
+        QuantizeOpts { locked_palette: Some(analysis.palette_rgba), ..quantize_opts }
+    };
+    process_rgba(&frames_rgba, width, height, frame_count, quantize_opts, gif_opts)
+}
+
+/// Predict the size and encode time of a clip from a few of its frames
+///
+/// The probe frames are preprocessed, given their own palette and encoded
+/// exactly as the full clip would be; `clip_frame_count` is the length of the
+/// whole clip, before `gif_opts` trims and decimates it. The size range is the
+/// probe's mean frame size plus or minus twice its standard error, widened to
+/// at least ±15%: a probe's palette fits it more tightly than the whole clip's
+/// will, so small probes of varied clips come in low. The time
+/// scales the probe's by frame count, so it runs long for imagequant, whose
+/// palette cost doesn't grow with the clip.
+pub fn estimate_encode(
+    probe_rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+    probe_count: u32,
+    clip_frame_count: u32,
+    quantize_opts: QuantizeOpts,
+    gif_opts: GifOpts,
+) -> Result<EncodeEstimate> {
+    validation::validate(probe_rgba.len(), width, height, probe_count, &quantize_opts, &gif_opts)?;
+    let mut output_frames = clip_frame_count;
+    if let Some(range) = gif_opts.frame_range {
+        output_frames = output_frames.min(range.end).saturating_sub(range.start);
+    }
+    if gif_opts.frame_count > 0 {
+        output_frames = output_frames.min(gif_opts.frame_count);
+    }
+
+    // The probe is already the sample; keep every frame of it
+    let probe_opts = GifOpts { frame_range: None, frame_count: 0, ..gif_opts };
+    let start = Instant::now();
+    let (header_size, frame_sizes) = throttled(quantize_opts, probe_opts, |quantize_opts, probe_opts| {
+        prepare_frames(&probe_rgba, width, height, probe_count, probe_opts, |frames, width, height, probe_opts, _| {
+            let (palette, transparent_index, _) = clip_palette(&frames, width, height, &quantize_opts)?;
+            sample_frame_sizes(frames, width, height, &palette, transparent_index, &quantize_opts, &probe_opts)
+        })
+    })?;
+    let probe_ms = elapsed_ms(start);
+
+    let count = frame_sizes.len() as f64;
+    let mean = frame_sizes.iter().sum::<usize>() as f64 / count;
+    let variance = frame_sizes.iter().map(|&size| (size as f64 - mean).powi(2)).sum::<f64>() / (count - 1.0).max(1.0);
+    let spread = (2.0 * (variance / count).sqrt()).max(0.15 * mean);
+    let total = |frame_bytes: f64| (header_size as f64 + frame_bytes.max(0.0) * output_frames as f64).round() as u32;
+    Ok(EncodeEstimate {
+        bytes_low: total(mean - spread),
+        bytes_high: total(mean + spread),
+        est_ms: probe_ms * output_frames as f32 / count as f32,
+    })
+}
+
+/// Encoded size of the GIF's fixed parts, and of each of `frames` mapped onto `palette`
+///
+/// Frames are compressed independently, so the per-frame sizes come straight
+/// from one-frame segments of a single encode.
+fn sample_frame_sizes(
+    frames: Vec<&[u8]>,
+    width: u32,
+    height: u32,
+    palette: &[[u8; 4]],
+    transparent_index: Option<u8>,
+    quantize_opts: &QuantizeOpts,
+    gif_opts: &GifOpts,
+) -> Result<(usize, Vec<usize>)> {
+    let sample_gif_opts = GifOpts {
+        include_tensor: false,
+        include_motion: false,
+        variants: Vec::new(),
+        checkpoint_path: None,
+        output_path: None,
+        segment_max_bytes: 0,
+        segment_max_frames: 1,
+        frame_disposals: Vec::new(),
+        frame_delays: Vec::new(),
+        ..gif_opts.clone()
+    };
+    let header_size = encode_gif(&[], palette, transparent_index, &sample_gif_opts)?.len();
+    let sample_opts = QuantizeOpts { locked_palette: Some(palette.concat()), ..quantize_opts.clone() };
+    let sample = process_with_oklab(frames, width, height, sample_opts, sample_gif_opts)?;
+    Ok((header_size, sample.segments.iter().map(|segment| segment.gif_data.len() - header_size).collect()))
+}
+
+/// The palette `process_frames` would quantize `frames` to, its transparent slot, and whether it is exact
+fn clip_palette(frames: &[&[u8]], width: u32, height: u32, quantize_opts: &QuantizeOpts) -> Result<(Vec<[u8; 4]>, Option<u8>, bool)> {
+    if let Some(locked) = &quantize_opts.locked_palette {
+        let (_, srgb_palette, transparent_index) = locked_oklab_palette(locked);
+        return Ok((srgb_palette, transparent_index, false));
+    }
+    if quantize_opts.exact_colors {
+        if let Some(exact) = exact_palette::build(frames, quantize_opts.palette_size as usize) {
+            return Ok((exact.palette, exact.transparent_index, true));
+        }
+    }
+    match quantize_opts.backend {
+        QuantizerBackend::Imagequant => {
+            let (mut quantization, _) = imagequant_quantize(frames, width, height, quantize_opts)?;
+            let palette = quantization.palette().iter().map(|c| [c.r, c.g, c.b, c.a]).collect();
+            Ok((palette, None, false))
+        }
+        QuantizerBackend::Oklab | QuantizerBackend::Oklch => {
+            let oklab_pixels: Vec<_> = frames.iter().flat_map(|f| oklab_quantization::srgb_to_oklab_batch(f)).collect();
+            let weights: Vec<f32> = frames.iter().flat_map(|f| oklab_quantization::alpha_weights(f)).collect();
+            let (_, srgb_palette, transparent_index) = oklab_clip_palette(frames, width, height, quantize_opts, &oklab_pixels, &weights);
+            Ok((srgb_palette, transparent_index, false))
+        }
+    }
+}
+
+// ============================================================================
+// QUALITY LADDER
+// ============================================================================
+
+/// Encode one clip at every rung of `ladder`, for comparing size against fidelity
+///
+/// The frames are trimmed, scaled and filtered once; each rung then only
+/// quantizes and encodes, with `quantize_opts` at the rung's palette size
+/// and dither mode. Tensors, extra outputs, variants, segments and
+/// `output_path` are skipped. Rungs come back palette size first, in the
+/// order given.
+pub fn encode_quality_ladder(
+    frames_rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+    frame_count: u32,
+    ladder: QualityLadder,
+    quantize_opts: QuantizeOpts,
+    gif_opts: GifOpts,
+) -> Result<Vec<LadderRung>> {
+    validation::validate(frames_rgba.len(), width, height, frame_count, &quantize_opts, &gif_opts)?;
+    if ladder.palette_sizes.is_empty() || ladder.dither_modes.is_empty() {
+        return Err(ProcessorError::invalid_input("quality ladder", "no palette sizes or dither modes"));
+    }
+    for &palette_size in &ladder.palette_sizes {
+        validation::quantize(&QuantizeOpts { palette_size, ..quantize_opts.clone() })?;
+    }
+
+    throttled(quantize_opts, gif_opts, |quantize_opts, gif_opts| {
+        prepare_frames(&frames_rgba, width, height, frame_count, gif_opts, |frames, width, height, gif_opts, _| {
+            let gif_opts = GifOpts {
+                include_tensor: false,
+                include_motion: false,
+                include_turntable: false,
+                include_long_exposure: false,
+                include_text_art: false,
+                variants: Vec::new(),
+                checkpoint_path: None,
+                output_path: None,
+                segment_max_bytes: 0,
+                segment_max_frames: 0,
+                ..gif_opts
+            };
+            let mut rungs = Vec::with_capacity(ladder.palette_sizes.len() * ladder.dither_modes.len());
+            for &palette_size in &ladder.palette_sizes {
+                for &dither_mode in &ladder.dither_modes {
+                    let start = Instant::now();
+                    let rung_opts = QuantizeOpts { palette_size, dither_mode, ..quantize_opts.clone() };
+                    let result = encode_prepared(frames.clone(), width, height, rung_opts, gif_opts.clone())?;
+                    let encode_ms = elapsed_ms(start);
+                    rungs.push(LadderRung {
+                        palette_size,
+                        dither_mode,
+                        mean_error: ladder_error(&frames, &result.gif_data)?,
+                        file_size: result.final_file_size,
+                        gif_data: result.gif_data,
+                        encode_ms,
+                    });
+                }
+            }
+            Ok(rungs)
+        })
+    })
+}
+
+/// Mean OKLab distance between the frames and the GIF showing them, over pixels that aren't transparent
+fn ladder_error(frames: &[&[u8]], gif_data: &[u8]) -> Result<f32> {
+    use oklab_quantization::srgb_to_oklab_batch;
+
+    let player = GifPlayer::new(gif_data.to_vec(), 1)?;
+    let mut total = (0.0f64, 0usize);
+    for (index, frame) in frames.iter().enumerate() {
+        let shown = player.frame_rgba(index as u32)?;
+        let (source, decoded) = (srgb_to_oklab_batch(frame), srgb_to_oklab_batch(&shown));
+        for ((a, b), px) in source.iter().zip(&decoded).zip(frame.chunks_exact(4)) {
+            if px[3] > 0 {
+                total.0 += ((a.l - b.l).powi(2) + (a.a - b.a).powi(2) + (a.b - b.b).powi(2)).sqrt() as f64;
+                total.1 += 1;
+            }
+        }
+    }
+    Ok(if total.1 == 0 { 0.0 } else { (total.0 / total.1 as f64) as f32 })
+}
+
+// ============================================================================
+// OKLAB PROCESSING PIPELINE
+// ============================================================================
+
+/// Process frames using perceptually uniform OKLab color space
+///
+/// `QuantizerBackend::Oklch` swaps in the hue-preserving palette builder; mapping
+/// and dithering stay in OKLab either way.
+fn process_with_oklab(
+    frames: Vec<&[u8]>,
+    width: u32,
+    height: u32,
+    quantize_opts: QuantizeOpts,
+    gif_opts: GifOpts,
+) -> Result<ProcessResult> {
+    use oklab_quantization::{
+        srgb_to_oklab_batch,
+        alpha_weights,
+        TemporalDither,
+        ATKINSON,
+        JARVIS,
+        SIERRA,
+        STUCKI,
+    };
+    use blue_noise::AdaptiveBlueNoise;
+    use color_distance::PaletteMatcher;
+    use parallel::process_frames_parallel;
+
+    let start = Instant::now();
+
+    // Convert all frames to OKLab color space, keeping alpha as sample weight
+    let mut all_oklab_pixels = Vec::new();
+    let mut all_weights = Vec::new();
+    for frame in &frames {
+        all_oklab_pixels.extend(srgb_to_oklab_batch(frame));
+        all_weights.extend(alpha_weights(frame));
+    }
+
+    let (oklab_palette, mut srgb_palette, transparent_index) = match &quantize_opts.locked_palette {
+        Some(locked) => locked_oklab_palette(locked),
+        None => oklab_clip_palette(&frames, width, height, &quantize_opts, &all_oklab_pixels, &all_weights),
+    };
+
+    let pixels_per_frame = (width * height) as usize;
+    let indexed_frames = match quantize_opts.dither_mode {
+        DitherMode::Sierra | DitherMode::Atkinson | DitherMode::Stucki | DitherMode::Jarvis => {
+            let kernel = match quantize_opts.dither_mode {
+                DitherMode::Atkinson => &ATKINSON,
+                DitherMode::Stucki => &STUCKI,
+                DitherMode::Jarvis => &JARVIS,
+                _ => &SIERRA,
+            };
+
+            // Apply temporal dithering for smooth animation
+            let mut temporal_dither = TemporalDither::with_metric(quantize_opts.distance_metric)
+                .with_kernel(kernel);
+            let mut indexed_frames = Vec::new();
+
+            for (frame_data, frame_weights) in frames.iter().zip(all_weights.chunks_exact(pixels_per_frame)) {
+                let frame_oklab = srgb_to_oklab_batch(frame_data);
+                let indices = temporal_dither.apply(
+                    &frame_oklab,
+                    frame_weights,
+                    &oklab_palette,
+                    transparent_index,
+                    width as usize,
+                    height as usize,
+                );
+                indexed_frames.push(indices);
+            }
+
+            indexed_frames
+        }
+        DitherMode::BlueNoise | DitherMode::AdaptiveBlueNoise => {
+            // Blue noise has no state between pixels or frames, so frames run in parallel
+            let edge_preservation = match quantize_opts.dither_mode {
+                DitherMode::AdaptiveBlueNoise => quantize_opts.edge_preservation,
+                _ => 0.0,
+            };
+            // One table per clip turns every pixel's search into a lookup
+            let matcher = PaletteMatcher::new(&oklab_palette, quantize_opts.distance_metric)
+                .with_lut(quantize_opts.lut_resolution);
+            let jobs: Vec<_> = frames
+                .iter()
+                .zip(all_oklab_pixels.chunks_exact(pixels_per_frame))
+                .zip(all_weights.chunks_exact(pixels_per_frame))
+                .collect();
+
+            process_frames_parallel(jobs, |((frame_data, frame_oklab), frame_weights)| {
+                AdaptiveBlueNoise::with_edge_preservation(
+                    frame_data,
+                    width as usize,
+                    height as usize,
+                    edge_preservation,
+                )
+                .apply_oklab(
+                    frame_oklab,
+                    frame_weights,
+                    &matcher,
+                    transparent_index,
+                    quantize_opts.dithering_level,
+                )
+            })
+        }
+    };
+
+    accessibility::apply(&mut srgb_palette, &gif_opts.accessibility);
+    let quantize_ms = elapsed_ms(start);
+
+    save_checkpoint(&indexed_frames, &srgb_palette, transparent_index, width, height, &gif_opts)?;
+
+    // Encode as GIF89a
+    let encode_start = Instant::now();
+    let (gif_buffer, file_size) = encode_output(&indexed_frames, &srgb_palette, transparent_index, &gif_opts)?;
+    let variants = encode_variants(&indexed_frames, width, height, &srgb_palette, transparent_index, &gif_opts)?;
+    let segments = encode_segments(&indexed_frames, &srgb_palette, transparent_index, &gif_opts)?;
+    let encode_ms = elapsed_ms(encode_start);
+
+    let tensor_start = Instant::now();
+    let (tensor_data, motion_data) = build_tensor_outputs(&frames, width, height, &gif_opts)?;
+
+    Ok(ProcessResult {
+        gif_data: gif_buffer,
+        tensor_data,
+        motion_data,
+        turntable_data: None,
+        long_exposure_data: None,
+        long_exposure_gif: None,
+        text_art: Vec::new(),
+        text_art_gif: None,
+        tensor_handle: None,
+        final_file_size: file_size,
+        processing_time_ms: start.elapsed().as_millis() as f32,
+        actual_frame_count: frames.len() as u32,
+        palette_size_used: srgb_palette.len() as u16,
+        variants,
+        segments,
+        dropped_frames: 0,
+        stage_timings: StageTimings { quantize_ms, encode_ms, tensor_ms: elapsed_ms(tensor_start), ..Default::default() },
+        palette_rgba: srgb_palette.concat(),
+        motion_rect: None,
+        color_space: gif_opts.color_space,
+        quantization: None,
+    })
+}
+
+/// Median-cut palette for the OKLab backends, in OKLab and sRGB, plus its transparent slot
+///
+/// `oklab_pixels` and `weights` cover every pixel of `frames`; with a
+/// `sample_fraction` below 1 the palette is built from a stratified subset instead.
+/// `adaptive_palette` lets it come out smaller than `palette_size`, and
+/// `motion_weight` favours pixels that change across frames. Moving pixels only
+/// weigh more in the palette; mapping and dithering see every pixel the same.
+fn oklab_clip_palette(
+    frames: &[&[u8]],
+    width: u32,
+    height: u32,
+    quantize_opts: &QuantizeOpts,
+    oklab_pixels: &[oklab_quantization::OklabColor],
+    weights: &[f32],
+) -> (Vec<oklab_quantization::OklabColor>, Vec<[u8; 4]>, Option<u8>) {
+    use oklab_quantization::{
+        alpha_weights, build_adaptive_oklab_palette, build_adaptive_oklch_palette, build_weighted_oklab_palette,
+        build_weighted_oklch_palette, oklab_palette_to_srgb, srgb_to_oklab_batch,
+    };
+
+    // Reserve one palette slot for fully transparent pixels if the clip has any
+    let has_transparency = weights.contains(&0.0);
+    let mut palette_size = quantize_opts.palette_size.min(255) as usize;
+    if has_transparency {
+        palette_size = palette_size.saturating_sub(1).max(1);
+    }
+
+    // Build optimal palette in OKLab space
+    let build_palette = match (quantize_opts.backend, quantize_opts.adaptive_palette) {
+        (QuantizerBackend::Oklch, false) => build_weighted_oklch_palette,
+        (QuantizerBackend::Oklch, true) => build_adaptive_oklch_palette,
+        (_, false) => build_weighted_oklab_palette,
+        (_, true) => build_adaptive_oklab_palette,
+    };
+    let motion = (quantize_opts.motion_weight > 0.0)
+        .then(|| motion_mask::importance(frames, width, height, quantize_opts.motion_weight));
+    let oklab_palette = if quantize_opts.sample_fraction < 1.0 {
+        let samples = sampling::stratified(frames, width, height, quantize_opts.sample_fraction);
+        let mut sample_weights = alpha_weights(&samples);
+        if let Some(motion) = &motion {
+            // Sampling the mask as a clip of identical frames picks the same positions as the samples
+            let mask: Vec<u8> = motion_mask::importance_map(motion).iter().flat_map(|&m| [m; 4]).collect();
+            let peak = motion.iter().cloned().fold(1.0f32, f32::max);
+            let sampled = sampling::stratified(&vec![mask.as_slice(); frames.len()], width, height, quantize_opts.sample_fraction);
+            for (weight, m) in sample_weights.iter_mut().zip(sampled.chunks_exact(4)) {
+                *weight *= m[0] as f32 / 255.0 * peak;
+            }
+        }
+        build_palette(&srgb_to_oklab_batch(&samples), &sample_weights, palette_size)
+    } else if let Some(motion) = &motion {
+        let weighted: Vec<f32> = weights.iter().enumerate().map(|(i, &w)| w * motion[i % motion.len()]).collect();
+        build_palette(oklab_pixels, &weighted, palette_size)
+    } else {
+        build_palette(oklab_pixels, weights, palette_size)
+    };
+
+    // Convert palette back to sRGB for GIF encoding
+    let mut srgb_palette = oklab_palette_to_srgb(&oklab_palette);
+    let transparent_index = if has_transparency {
+        srgb_palette.push([0, 0, 0, 0]);
+        Some((srgb_palette.len() - 1) as u8)
+    } else {
+        None
+    };
+    (oklab_palette, srgb_palette, transparent_index)
+}
+
+/// Split a locked palette into its OKLab matching palette, GIF palette and transparent slot
+///
+/// The first fully transparent entry becomes the transparent slot and moves to
+/// the end, since matching only sees the opaque entries before it; any other
+/// transparent entries are dropped.
+fn locked_oklab_palette(locked: &[u8]) -> (Vec<oklab_quantization::OklabColor>, Vec<[u8; 4]>, Option<u8>) {
+    let entries = locked.chunks_exact(4).map(|c| [c[0], c[1], c[2], c[3]]);
+    let mut srgb_palette: Vec<[u8; 4]> = entries.clone().filter(|c| c[3] != 0).collect();
+    let oklab_palette = oklab_quantization::srgb_to_oklab_batch(&srgb_palette.concat());
+
+    let transparent_index = entries.clone().find(|c| c[3] == 0).map(|clear| {
+        srgb_palette.push(clear);
+        (srgb_palette.len() - 1) as u8
+    });
+    (oklab_palette, srgb_palette, transparent_index)
+}
+
+// ============================================================================
+// FALLBACK IMAGEQUANT PIPELINE
+// ============================================================================
+
+/// Primary processing using imagequant library
+fn process_with_imagequant(
+    frames: Vec<&[u8]>,
+    width: u32,
+    height: u32,
+    quantize_opts: QuantizeOpts,
+    gif_opts: GifOpts,
+) -> Result<ProcessResult> {
+    let start = Instant::now();
+    let (quantization, mut images) = imagequant_quantize(&frames, width, height, &quantize_opts)?;
+
+    // Remap frames to palette indices in parallel, noting how well each one fit.
+    // Remapping only reads the shared palette, so each thread works on its own
+    // copy; the output matches remapping the frames one after another.
+    let last = images.len().saturating_sub(1);
+    let remapped: Vec<RemappedFrame> = images
+        .par_iter_mut()
+        .enumerate()
+        .map_init(
+            || quantization.clone(),
+            |quantization, (i, image)| {
+                let (_, indices) = quantization.remapped(image)
+                    .map_err(|e| ProcessorError::quantization("imagequant remap", e).at_frame(i))?;
+                let fit = quantization.remapping_error().zip(quantization.remapping_quality());
+                // imagequant settles the final palette while remapping; keep the last frame's, as a serial loop would
+                let palette = (i == last).then(|| quantization.palette().to_vec());
+                Ok((indices, fit.map(|(error, quality)| (error as f32, quality)), palette))
+            },
+        )
+        .collect::<Result<_>>()?;
+    let mut indexed_frames = Vec::with_capacity(remapped.len());
+    let mut remap_errors = Vec::new();
+    let mut palette = Vec::new();
+    for (indices, fit, last_palette) in remapped {
+        indexed_frames.push(indices);
+        remap_errors.extend(fit);
+        if let Some(last_palette) = last_palette {
+            palette = last_palette;
+        }
+    }
+    let quantization_report = quantization_report(&remap_errors);
+    let palette_size = palette.len() as u16;
+
+    // Convert palette for GIF
+    let mut srgb_palette: Vec<[u8; 4]> = palette.iter()
+        .map(|c| [c.r, c.g, c.b, c.a])
+        .collect();
+    accessibility::apply(&mut srgb_palette, &gif_opts.accessibility);
+
+    let quantize_ms = elapsed_ms(start);
+
+    save_checkpoint(&indexed_frames, &srgb_palette, None, width, height, &gif_opts)?;
+
+    // Encode GIF
+    let encode_start = Instant::now();
+    let (gif_buffer, file_size) = encode_output(&indexed_frames, &srgb_palette, None, &gif_opts)?;
+    let variants = encode_variants(&indexed_frames, width, height, &srgb_palette, None, &gif_opts)?;
+    let segments = encode_segments(&indexed_frames, &srgb_palette, None, &gif_opts)?;
+    let encode_ms = elapsed_ms(encode_start);
+
+    let tensor_start = Instant::now();
+    let (tensor_data, motion_data) = build_tensor_outputs(&frames, width, height, &gif_opts)?;
+
+    Ok(ProcessResult {
+        gif_data: gif_buffer,
+        tensor_data,
+        motion_data,
+        turntable_data: None,
+        long_exposure_data: None,
+        long_exposure_gif: None,
+        text_art: Vec::new(),
+        text_art_gif: None,
+        tensor_handle: None,
+        final_file_size: file_size,
+        processing_time_ms: start.elapsed().as_millis() as f32,
+        actual_frame_count: frames.len() as u32,
+        palette_size_used: palette_size,
+        variants,
+        segments,
+        dropped_frames: 0,
+        stage_timings: StageTimings { quantize_ms, encode_ms, tensor_ms: elapsed_ms(tensor_start), ..Default::default() },
+        palette_rgba: srgb_palette.concat(),
+        motion_rect: None,
+        color_space: gif_opts.color_space,
+        quantization: quantization_report,
+    })
+}
+
+/// One frame out of imagequant's remap: its indices, its (error, quality) fit, and the palette if it was the last frame
+type RemappedFrame = (Vec<u8>, Option<(f32, u8)>, Option<Vec<imagequant::RGBA>>);
+
+/// `QuantizationReport` from each frame's remap error and quality, None if imagequant measured none
+fn quantization_report(remap_errors: &[(f32, u8)]) -> Option<QuantizationReport> {
+    let (worst_frame, &(worst_frame_error, _)) = remap_errors
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1 .0.total_cmp(&b.1 .0))?;
+    Some(QuantizationReport {
+        mean_square_error: remap_errors.iter().map(|e| e.0).sum::<f32>() / remap_errors.len() as f32,
+        quality: remap_errors.iter().map(|e| e.1).min().unwrap_or(100),
+        worst_frame: worst_frame as u32,
+        worst_frame_error,
+    })
+}
+
+/// Shared imagequant palette for the clip, built from its first frame, and every frame as an image to remap
+fn imagequant_quantize<'a>(
+    frames: &[&'a [u8]],
+    width: u32,
+    height: u32,
+    quantize_opts: &QuantizeOpts,
+) -> Result<(imagequant::QuantizationResult, Vec<imagequant::Image<'a>>)> {
+    // Trade speed for the time budget, judged on a quick probe of the clip
+    let (speed, quality_min) = if quantize_opts.time_budget_ms > 0 {
+        let choice = adaptive::choose_settings(
+            frames,
+            width,
+            height,
+            quantize_opts,
+            quantize_opts.time_budget_ms as f32,
+        )?;
+        eprintln!(
+            "[RUST] Adaptive speed {} (probe quality {}, ~{:.0}ms)",
+            choice.speed, choice.probe_quality, choice.estimated_ms
+        );
+        (choice.speed, choice.quality_min)
+    } else {
+        (quantize_opts.speed, quantize_opts.quality_min)
+    };
+
+    // Setup imagequant
+    let mut attr = imagequant::new();
+    attr.set_quality(quality_min, quantize_opts.quality_max)
+        .map_err(|e| ProcessorError::quantization("imagequant", e))?;
+    attr.set_speed(speed)
+        .map_err(|e| ProcessorError::quantization("imagequant", e))?;
+    attr.set_max_colors(quantize_opts.palette_size as u32)
+        .map_err(|e| ProcessorError::quantization("imagequant", e))?;
+
+    // Images read the frames in place
+    let mut images = Vec::new();
+    for (i, frame_data) in frames.iter().enumerate() {
+        let img = quantization::row_image(&attr, frame_data, width, height, width as usize * 4, quantization::PixelLayout::Rgba)
+            .map_err(|e| e.at_frame(i))?;
+        images.push(img);
+    }
+
+    // Quantize with shared palette
+    if images.is_empty() {
+        return Err(ProcessorError::invalid_input("imagequant", "no frames to quantize"));
+    }
+
+    // The palette comes from the first frame, weighted by where the whole clip moves
+    if quantize_opts.motion_weight > 0.0 {
+        let motion = motion_mask::importance(frames, width, height, quantize_opts.motion_weight);
+        images[0].set_importance_map(motion_mask::importance_map(&motion))
+            .map_err(|e| ProcessorError::quantization("imagequant", e))?;
+    }
+
+    let mut quantization = match attr.quantize(&mut images[0]) {
+        // The palette budget wins over quality_min; the quantization report says what was reached
+        Err(imagequant::Error::QualityTooLow) if quantize_opts.palette_size < 256 => {
+            eprintln!("[RUST] {} colors can't reach quality {}, keeping the budget", quantize_opts.palette_size, quality_min);
+            attr.set_quality(0, quantize_opts.quality_max)
+                .map_err(|e| ProcessorError::quantization("imagequant", e))?;
+            attr.quantize(&mut images[0])
+        }
+        quantized => quantized,
+    }
+    .map_err(|e| ProcessorError::quantization("imagequant", e))?;
+    quantization.set_dithering_level(quantize_opts.dithering_level)
+        .map_err(|e| ProcessorError::quantization("imagequant", e))?;
+    Ok((quantization, images))
+}
+
+// ============================================================================
+// EXACT PALETTE PIPELINE
+// ============================================================================
+
+/// Encode frames that `exact_palette::build` already indexed without loss
+fn process_exact(
+    frames: Vec<&[u8]>,
+    width: u32,
+    height: u32,
+    exact: exact_palette::ExactPalette,
+    gif_opts: GifOpts,
+) -> Result<ProcessResult> {
+    let start = Instant::now();
+    let exact_palette::ExactPalette { mut palette, transparent_index, indexed_frames } = exact;
+    eprintln!("[RUST] Clip uses {} colors, skipping quantization", palette.len());
+    accessibility::apply(&mut palette, &gif_opts.accessibility);
+
+    save_checkpoint(&indexed_frames, &palette, transparent_index, width, height, &gif_opts)?;
+
+    let (gif_buffer, file_size) = encode_output(&indexed_frames, &palette, transparent_index, &gif_opts)?;
+    let variants = encode_variants(&indexed_frames, width, height, &palette, transparent_index, &gif_opts)?;
+    let segments = encode_segments(&indexed_frames, &palette, transparent_index, &gif_opts)?;
+    let encode_ms = elapsed_ms(start);
+
+    let tensor_start = Instant::now();
+    let (tensor_data, motion_data) = build_tensor_outputs(&frames, width, height, &gif_opts)?;
+
+    Ok(ProcessResult {
+        gif_data: gif_buffer,
+        tensor_data,
+        motion_data,
+        turntable_data: None,
+        long_exposure_data: None,
+        long_exposure_gif: None,
+        text_art: Vec::new(),
+        text_art_gif: None,
+        tensor_handle: None,
+        final_file_size: file_size,
+        processing_time_ms: start.elapsed().as_millis() as f32,
+        actual_frame_count: frames.len() as u32,
+        palette_size_used: palette.len() as u16,
+        variants,
+        segments,
+        dropped_frames: 0,
+        stage_timings: StageTimings { encode_ms, tensor_ms: elapsed_ms(tensor_start), ..Default::default() },
+        palette_rgba: palette.concat(),
+        motion_rect: None,
+        color_space: gif_opts.color_space,
+        quantization: None,
+    })
+}
+
+// ============================================================================
+// GIF ENCODING
+// ============================================================================
+
+/// Persist quantization output when `gif_opts.checkpoint_path` is set
+fn save_checkpoint(
+    indexed_frames: &[Vec<u8>],
+    palette: &[[u8; 4]],
+    transparent_index: Option<u8>,
+    width: u32,
+    height: u32,
+    gif_opts: &GifOpts,
+) -> Result<()> {
+    match &gif_opts.checkpoint_path {
+        Some(path) => checkpoint::save(
+            path,
+            width,
+            height,
+            palette,
+            transparent_index,
+            indexed_frames,
+            gif_opts.checkpoint_ttl_secs,
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Encode indexed frames as GIF89a
+///
+/// `transparent_index` marks the palette slot reserved for fully transparent pixels.
+fn encode_gif(
+    indexed_frames: &[Vec<u8>],
+    palette: &[[u8; 4]],
+    transparent_index: Option<u8>,
+    opts: &GifOpts,
+) -> Result<Vec<u8>> {
+    encode_gif_measured(indexed_frames, palette, transparent_index, opts).map(|(gif_buffer, _)| gif_buffer)
+}
+
+/// The main GIF and its size, or an empty buffer once streamed to `output_path`
+///
+/// The file only appears once it is complete, so a failed encode never leaves
+/// a truncated GIF behind. Every backend calls this right after quantizing,
+/// so it is also where the encode's progress is reported.
+fn encode_output(
+    indexed_frames: &[Vec<u8>],
+    palette: &[[u8; 4]],
+    transparent_index: Option<u8>,
+    opts: &GifOpts,
+) -> Result<(Vec<u8>, u32)> {
+    let progress = progress::current();
+    if let Some(progress) = &progress {
+        progress.advance(indexed_frames.len());
+    }
+
+    let Some(path) = &opts.output_path else {
+        let mut gif_buffer = Vec::new();
+        write_gif(indexed_frames, palette, transparent_index, opts, progress.as_deref(), &mut gif_buffer)?;
+        let file_size = gif_buffer.len() as u32;
+        return Ok((gif_buffer, file_size));
+    };
+
+    let mut file = atomic_file::AtomicFile::create(path).map_err(|e| ProcessorError::memory("output", e))?;
+    let (_, total) = write_gif(indexed_frames, palette, transparent_index, opts, progress.as_deref(), &mut file)?;
+    file.commit(false).map_err(|e| ProcessorError::memory("output", e))?;
+    Ok((Vec::new(), total.min(u32::MAX as u64) as u32))
+}
+
+/// `encode_gif`, plus the byte offset where the header ends and each frame ends
+///
+/// GIF frames are compressed independently, so these offsets are enough to
+/// splice any run of frames into a standalone file.
+fn encode_gif_measured(
+    indexed_frames: &[Vec<u8>],
+    palette: &[[u8; 4]],
+    transparent_index: Option<u8>,
+    opts: &GifOpts,
+) -> Result<(Vec<u8>, Vec<usize>)> {
+    let mut gif_buffer = Vec::new();
+    let (offsets, _) = write_gif(indexed_frames, palette, transparent_index, opts, None, &mut gif_buffer)?;
+    Ok((gif_buffer, offsets))
+}
+
+/// Write the GIF to `sink` a frame at a time
+///
+/// Returns the same offsets as `encode_gif_measured` and the file's length;
+/// only the frame being compressed is held in memory. Each frame written
+/// advances `progress`.
+fn write_gif<W: std::io::Write>(
+    indexed_frames: &[Vec<u8>],
+    palette: &[[u8; 4]],
+    transparent_index: Option<u8>,
+    opts: &GifOpts,
+    progress: Option<&progress::FrameProgress>,
+    sink: &mut W,
+) -> Result<(Vec<usize>, u64)> {
+    if opts.background_index as usize >= palette.len() {
+        return Err(ProcessorError::invalid_input("encode", format!("background index {} is past the {} color palette", opts.background_index, palette.len())));
+    }
+
+    let mut offsets = Vec::with_capacity(indexed_frames.len() + 1);
+    let mut hasher = opts.integrity_hash.then(integrity::Hasher::new);
+    let mut emit = |bytes: &[u8], hasher: Option<&mut integrity::Hasher>| {
+        if let Some(hasher) = hasher {
+            hasher.update(bytes);
+        }
+        sink.write_all(bytes).map_err(|e| ProcessorError::memory("output", e))
+    };
+
+    // Convert palette to GIF format (RGB, no alpha); the writer pads it to the
+    // next power of two, so small palettes get small tables and shorter codes
+    let global_palette: Vec<u8> = palette.iter().take(256).flat_map(|color| [color[0], color[1], color[2]]).collect();
+
+    let mut writer = indexed::GifWriter::new(opts.width, opts.height, &global_palette);
+    writer.set_background(opts.background_index);
+    writer.write_repeat(0); // Infinite loop
+
+    // Capture details and the color space ride along in their own application extension
+    if let Some(summary) = metadata::for_gif(&opts.frame_metadata, opts.color_space) {
+        writer.write_application_extension(metadata::GIF_APPLICATION_ID, &metadata::to_json(&summary)?);
+    }
+    offsets.push(writer.len());
+
+    // Write frames
+    let frame_len = opts.width as usize * opts.height as usize;
+    if let Some(i) = indexed_frames.iter().position(|indices| indices.len() != frame_len) {
+        return Err(ProcessorError::encoding("encode", format!("{} indices for a {}x{} frame", indexed_frames[i].len(), opts.width, opts.height)).at_frame(i));
+    }
+    let mut delays = frame_delays(opts.fps, indexed_frames.len())?;
+    for (delay, &explicit) in delays.iter_mut().zip(&opts.frame_delays) {
+        *delay = explicit;
+    }
+    // Frames compress independently: one batch per round, a frame per thread, written in order.
+    // Each thread's output buffer and the writer's are reused round to round, not reallocated.
+    let min_code_size = writer.min_code_size();
+    let batch = rayon::current_num_threads().max(1);
+    let mut compressed: Vec<Vec<u8>> = vec![Vec::new(); batch.min(indexed_frames.len())];
+    for (batch_index, frames) in indexed_frames.chunks(batch).enumerate() {
+        let compressed = &mut compressed[..frames.len()];
+        compressed.par_iter_mut().zip(frames).for_each(|(out, indices)| indexed::lzw_encode_into(indices, min_code_size, out));
+        for (offset, data) in compressed.iter().enumerate() {
+            let i = batch_index * batch + offset;
+            let disposal = disposal_method(*opts.frame_disposals.get(i).unwrap_or(&opts.disposal));
+            writer.write_compressed_frame(data, delays[i], disposal, transparent_index);
+            offsets.push(writer.len());
+            writer.drain_with(|bytes| emit(bytes, hasher.as_mut()))?;
+        }
+        if let Some(progress) = progress {
+            progress.advance(frames.len());
+        }
+    }
+
+    // The hash covers everything before its own extension
+    if let Some(hasher) = &mut hasher {
+        writer.drain_with(|bytes| emit(bytes, Some(hasher)))?;
+        writer.write_application_extension(integrity::GIF_APPLICATION_ID, hasher.finalize().as_bytes());
+    }
+    let total = writer.len() as u64 + 1; // Trailer
+    emit(&writer.finish(), None)?;
+    Ok((offsets, total))
+}
+
+fn disposal_method(disposal: FrameDisposal) -> indexed::Disposal {
+    match disposal {
+        FrameDisposal::Unspecified => indexed::Disposal::Unspecified,
+        FrameDisposal::Keep => indexed::Disposal::Keep,
+        FrameDisposal::Background => indexed::Disposal::Background,
+        FrameDisposal::Previous => indexed::Disposal::Previous,
+    }
+}
+
+/// Centisecond delays that play `count` frames at `fps` on average
+///
+/// GIF delays are whole centiseconds, so 30fps alternates 3, 3, 4 instead of
+/// a flat 3 (33fps). Browsers slow anything under 2cs down to 10cs, so rates
+/// above 50fps play at 50.
+fn frame_delays(fps: u16, count: usize) -> Result<Vec<u16>> {
+    if fps == 0 {
+        return Err(ProcessorError::invalid_input("frame delays", "fps is 0"));
+    }
+    let fps = fps.min(50) as usize;
+    Ok((0..count).map(|i| ((i + 1) * 100 / fps - i * 100 / fps) as u16).collect())
+}
+
+/// Encode every requested variant from the already-quantized frames
+///
+/// Indices are resampled nearest-neighbor against the shared palette, so
+/// thumbnails cost an LZW pass each but no second quantization.
+fn encode_variants(
+    indexed_frames: &[Vec<u8>],
+    width: u32,
+    height: u32,
+    palette: &[[u8; 4]],
+    transparent_index: Option<u8>,
+    opts: &GifOpts,
+) -> Result<Vec<GifVariantOutput>> {
+    opts.variants
+        .iter()
+        .map(|variant| {
+            if variant.width == 0 || variant.height == 0 {
+                return Err(ProcessorError::invalid_input("variants", format!("{}x{} variant has an empty side", variant.width, variant.height)));
+            }
+
+            let resampled: Vec<Vec<u8>> = indexed_frames
+                .iter()
+                .map(|indices| resample_indices(indices, width, height, variant.width as u32, variant.height as u32))
+                .collect();
+
+            let variant_opts = GifOpts {
+                width: variant.width,
+                height: variant.height,
+                variants: Vec::new(),
+                ..opts.clone()
+            };
+
+            Ok(GifVariantOutput {
+                width: variant.width,
+                height: variant.height,
+                gif_data: encode_gif(&resampled, palette, transparent_index, &variant_opts)?,
+            })
+        })
+        .collect()
+}
+
+/// Split the GIF into standalone segments under the `GifOpts` segment limits
+///
+/// Frames are packed greedily in order, and each segment is spliced from one
+/// encode of the whole clip, so every segment carries the same palette and
+/// delays. Fails with `InvalidInput` if a single frame alone is over
+/// `segment_max_bytes`.
+fn encode_segments(
+    indexed_frames: &[Vec<u8>],
+    palette: &[[u8; 4]],
+    transparent_index: Option<u8>,
+    opts: &GifOpts,
+) -> Result<Vec<GifSegment>> {
+    if opts.segment_max_bytes == 0 && opts.segment_max_frames == 0 {
+        return Ok(Vec::new());
+    }
+    let (gif_buffer, offsets) = encode_gif_measured(indexed_frames, palette, transparent_index, opts)?;
+    let header = &gif_buffer[..offsets[0]];
+    let max_bytes = match opts.segment_max_bytes {
+        0 => usize::MAX,
+        limit => limit as usize,
+    };
+    let max_frames = match opts.segment_max_frames {
+        0 => usize::MAX,
+        limit => limit as usize,
+    };
+
+    let mut segments = Vec::new();
+    let mut first = 0;
+    while first < indexed_frames.len() {
+        // Header, frames, and the one-byte trailer
+        let size = |end: usize| header.len() + offsets[end] - offsets[first] + 1;
+        let mut end = first;
+        while end < indexed_frames.len() && end - first < max_frames && size(end + 1) <= max_bytes {
+            end += 1;
+        }
+        if end == first {
+            return Err(ProcessorError::invalid_input("segments", format!("frame alone is over the {} byte segment limit", max_bytes)).at_frame(first));
+        }
+
+        let mut gif_data = Vec::with_capacity(size(end));
+        gif_data.extend_from_slice(header);
+        gif_data.extend_from_slice(&gif_buffer[offsets[first]..offsets[end]]);
+        gif_data.push(0x3B);
+        segments.push(GifSegment {
+            first_frame: first as u32,
+            frame_count: (end - first) as u32,
+            gif_data,
+        });
+        first = end;
+    }
+    Ok(segments)
+}
+
+/// Nearest-neighbor resample of a palette index buffer
+fn resample_indices(indices: &[u8], width: u32, height: u32, dst_width: u32, dst_height: u32) -> Vec<u8> {
+    if width == dst_width && height == dst_height {
+        return indices.to_vec();
+    }
+
+    let mut output = Vec::with_capacity((dst_width * dst_height) as usize);
+    for y in 0..dst_height {
+        let src_y = ((y as u64 * height as u64) / dst_height as u64) as usize;
+        let row = &indices[src_y * width as usize..(src_y + 1) * width as usize];
+        for x in 0..dst_width {
+            let src_x = ((x as u64 * width as u64) / dst_width as u64) as usize;
+            output.push(row[src_x]);
+        }
+    }
+    output
+}
+
+// ============================================================================
+// TENSOR GENERATION FOR VOXEL VISUALIZATION
+// ============================================================================
+
+/// Tensor and motion channel, either of which may be absent
+type TensorOutputs = (Option<Vec<u8>>, Option<Vec<u8>>);
+
+/// Color histogram or frame-stack tensor for `frames`, as `gif_opts.tensor` asks
+///
+/// Both outputs are `None` when `include_tensor` is off.
+fn build_tensor_outputs(frames: &[&[u8]], width: u32, height: u32, gif_opts: &GifOpts) -> Result<TensorOutputs> {
+    let outputs = if gif_opts.include_tensor && gif_opts.tensor.mode == TensorMode::ColorHistogram {
+        eprintln!("[RUST] Building 64³ color histogram tensor...");
+        let bins = tensor::HISTOGRAM_BINS as u32;
+        let cube = tensor::relayout(
+            tensor::color_histogram(frames),
+            tensor::TensorShape::cube(bins),
+            4,
+            TensorLayout::FrameMajor,
+            gif_opts.tensor.layout,
+        )?;
+        (Some(cube), None)
+    } else if gif_opts.include_tensor {
+        eprintln!("[RUST] Building tensor for voxel visualization...");
+        eprintln!("[RUST]   Frame count: {}", frames.len());
+        eprintln!("[RUST]   Frame dimensions: {}x{}", width, height);
+        let (cube_w, cube_h) = (gif_opts.tensor.cube_width as u32, gif_opts.tensor.cube_height as u32);
+        let tensor = build_tensor_from_frames(frames, width, height, cube_w, cube_h)?;
+        eprintln!("[RUST]   Tensor size: {} bytes", tensor.len());
+        eprintln!("[RUST]   Expected size for {}×{}×{}: {} bytes", cube_w, cube_h, frames.len(), cube_w * cube_h * frames.len() as u32 * 4);
+
+        // Verify tensor is not empty
+        let has_data = tensor.iter().take(1000).any(|&b| b != 0);
+        eprintln!("[RUST]   Contains non-zero data: {}", has_data);
+
+        if !has_data {
+            eprintln!("[RUST] WARNING: Tensor appears to be all zeros!");
+        }
+
+        let (tensor, motion) = finish_frame_stack(tensor, gif_opts)?;
+        (Some(tensor), motion)
+    } else {
+        eprintln!("[RUST] Tensor generation skipped (include_tensor = false)");
+        (None, None)
+    };
+    Ok(outputs)
+}
+
+/// The turntable preview, when `gif_opts.include_turntable` asks for one
+///
+/// The clip's frames are stacked into a small volume and raycast from a
+/// camera circling it; a GIF preview then goes through the pipeline on its own.
+fn build_turntable(frames: &[&[u8]], width: u32, height: u32, gif_opts: &GifOpts) -> Result<Option<Vec<u8>>> {
+    if !gif_opts.include_turntable {
+        return Ok(None);
+    }
+    let opts = &gif_opts.turntable;
+    let (volume, shape) = turntable::volume(frames, width, height, &gif_opts.occupancy)?;
+    let rendered = turntable::render(&volume, shape, opts);
+    if opts.format == TurntableFormat::Rgba {
+        return Ok(Some(rendered));
+    }
+
+    let side = opts.size as u32;
+    let preview_opts = GifOpts {
+        width: opts.size,
+        height: opts.size,
+        frame_count: 0,
+        fps: opts.fps,
+        integrity_hash: gif_opts.integrity_hash,
+        ..Default::default()
+    };
+    let quantize_opts = QuantizeOpts { quality_min: 0, ..Default::default() };
+    let preview = process_frames(&rendered, side, side, opts.frame_count as u32, quantize_opts, preview_opts)?;
+    Ok(Some(preview.gif_data))
+}
+
+/// `ProcessResult::long_exposure_data` and `long_exposure_gif`
+type LongExposureOutput = (Option<Vec<u8>>, Option<Vec<u8>>);
+
+/// The long-exposure still and its build-up GIF, when `gif_opts.include_long_exposure` asks for them
+///
+/// Frames are stacked as they leave preprocessing, so the still matches the
+/// GIF's size and look; the build-up goes through the pipeline on its own.
+fn build_long_exposure(frames: &[&[u8]], width: u32, height: u32, gif_opts: &GifOpts) -> Result<LongExposureOutput> {
+    if !gif_opts.include_long_exposure {
+        return Ok((None, None));
+    }
+    let opts = &gif_opts.long_exposure;
+    let still = long_exposure::composite(frames, opts.blend);
+    if opts.build_up_frames == 0 {
+        return Ok((Some(still), None));
+    }
+
+    let steps = opts.build_up_frames as u32;
+    let build_up = long_exposure::build_up(frames, opts.blend, steps as usize);
+    let build_up_opts = GifOpts {
+        width: width as u16,
+        height: height as u16,
+        frame_count: 0,
+        fps: gif_opts.fps,
+        loop_count: gif_opts.loop_count,
+        integrity_hash: gif_opts.integrity_hash,
+        ..Default::default()
+    };
+    let quantize_opts = QuantizeOpts { quality_min: 0, ..Default::default() };
+    let gif = process_frames(&build_up, width, height, steps, quantize_opts, build_up_opts)?;
+    Ok((Some(still), Some(gif.gif_data)))
+}
+
+/// `ProcessResult::text_art` and `text_art_gif`
+type TextArtOutput = (Vec<String>, Option<Vec<u8>>);
+
+/// Every frame as text art, and the art drawn into a GIF, when `gif_opts.include_text_art` asks for them
+///
+/// Like the long exposure, the art is taken from the frames as they leave
+/// preprocessing; the rendered GIF plays at the main GIF's rate.
+fn build_text_art(frames: &[&[u8]], width: u32, height: u32, gif_opts: &GifOpts) -> Result<TextArtOutput> {
+    if !gif_opts.include_text_art {
+        return Ok((Vec::new(), None));
+    }
+    let opts = &gif_opts.text_art;
+    let cells: Vec<text_art::Cells> = frames.par_iter().map(|frame| text_art::cells(frame, width, height, opts)).collect();
+    let text = cells.par_iter().map(|cells| text_art::to_text(cells, opts.style)).collect();
+    if !opts.render_gif || cells.is_empty() {
+        return Ok((text, None));
+    }
+
+    let (columns, rows) = text_art::grid(width, height, opts);
+    let (art_width, art_height) = text_art::rendered_size(columns, rows, opts.style);
+    let rendered: Vec<u8> = cells.par_iter().flat_map_iter(|cells| text_art::render(cells, opts.style)).collect();
+    let art_opts = GifOpts {
+        width: art_width as u16,
+        height: art_height as u16,
+        frame_count: 0,
+        fps: gif_opts.fps,
+        loop_count: gif_opts.loop_count,
+        integrity_hash: gif_opts.integrity_hash,
+        ..Default::default()
+    };
+    let quantize_opts = QuantizeOpts { quality_min: 0, ..Default::default() };
+    let gif = process_frames(&rendered, art_width, art_height, cells.len() as u32, quantize_opts, art_opts)?;
+    Ok((text, Some(gif.gif_data)))
+}
+
+/// Build a cube_w×cube_h×N tensor from frames for voxel cube visualization (N=128 optimal)
+/// Optimal resolution tensor for exploring the voxel cube as a 3D object
+fn build_tensor_from_frames(frames: &[&[u8]], width: u32, height: u32, cube_w: u32, cube_h: u32) -> Result<Vec<u8>> {
+    eprintln!("[RUST] build_tensor_from_frames called");
+    eprintln!("[RUST]   Input: {} frames at {}x{}", frames.len(), width, height);
+
+    if cube_w == 0 || cube_h == 0 {
+        return Err(ProcessorError::invalid_input("tensor", "cube size is empty"));
+    }
+
+    // Slices are sized by TensorOpts, not by the GIF
+    // If input already matches, use directly; otherwise resample
+
+    if width == cube_w && height == cube_h {
+        eprintln!("[RUST]   Using direct copy (frames already {}x{})", cube_w, cube_h);
+        // Direct copy - frames are already the right size
+        let mut tensor = Vec::with_capacity(frames.len() * (cube_w * cube_h * 4) as usize);
 
         for (i, frame) in frames.iter().enumerate() {
             // Verify frame has data
@@ -387,47 +2988,691 @@ fn build_tensor_from_frames(frames: &[&[u8]], width: u32, height: u32) -> Result
         eprintln!("[RUST]   Final tensor size: {} bytes", tensor.len());
         Ok(tensor)
     } else {
-        eprintln!("[RUST]   Resampling from {}x{} to 128x128", width, height);
-        // Need to resample to 128×128
-        let mut tensor = Vec::with_capacity(128 * 128 * frames.len() * 4);
+        eprintln!("[RUST]   Resampling from {}x{} to {}x{}", width, height, cube_w, cube_h);
+        let mut tensor = Vec::with_capacity((cube_w * cube_h * 4) as usize * frames.len());
 
         for frame in frames {
-            // Simple nearest-neighbor resampling to 128×128
-            for y in 0..128 {
-                for x in 0..128 {
-                    // Map to source coordinates
-                    let src_x = (x as f32 * width as f32 / 128.0) as usize;
-                    let src_y = (y as f32 * height as f32 / 128.0) as usize;
-                    let src_idx = (src_y.min(height as usize - 1) * width as usize + src_x.min(width as usize - 1)) * 4;
-
-                    if src_idx + 3 < frame.len() {
-                        tensor.push(frame[src_idx]);     // R
-                        tensor.push(frame[src_idx + 1]); // G
-                        tensor.push(frame[src_idx + 2]); // B
-                        tensor.push(frame[src_idx + 3]); // A
-                    } else {
-                        tensor.extend_from_slice(&[0, 0, 0, 0]);
-                    }
-                }
-            }
+            tensor::resample_slice(frame, width, height, cube_w, cube_h, &mut tensor);
         }
 
         Ok(tensor)
     }
 }
 
+/// Post-process a raw frame-stack tensor as `gif_opts` asks
+///
+/// Slices are `gif_opts.tensor.cube_width`×`cube_height`. Aligns slices,
+/// derives the motion volume, keys occupancy and finally relayouts both
+/// volumes. Returns the tensor and the optional motion volume.
+fn finish_frame_stack(mut tensor: Vec<u8>, gif_opts: &GifOpts) -> Result<(Vec<u8>, Option<Vec<u8>>)> {
+    let (cube_w, cube_h) = (gif_opts.tensor.cube_width as u32, gif_opts.tensor.cube_height as u32);
+    let shape = tensor::TensorShape::new(cube_w, cube_h, (tensor.len() / (cube_w * cube_h * 4) as usize) as u32);
+    if gif_opts.align_tensor_slices {
+        tensor::align_slices(&mut tensor, shape, tensor::MAX_SLICE_SHIFT)?;
+    }
+    let motion = if gif_opts.include_motion {
+        Some(tensor::motion_energy(&tensor, shape)?)
+    } else {
+        None
+    };
+    tensor::compute_occupancy(&mut tensor, shape, &gif_opts.occupancy)?;
+
+    let layout = gif_opts.tensor.layout;
+    let tensor = tensor::relayout(tensor, shape, 4, TensorLayout::FrameMajor, layout)?;
+    let motion = motion
+        .map(|m| tensor::relayout(m, shape, 1, TensorLayout::FrameMajor, layout))
+        .transpose()?;
+    Ok((tensor, motion))
+}
+
+/// 64³ RGB histogram cube of a clip, without encoding a GIF
+///
+/// Same output as `TensorMode::ColorHistogram`: frame-major RGBA voxels, bin
+/// color with log-scaled pixel count as alpha.
+pub fn build_color_histogram(frames_rgba: Vec<u8>, width: u32, height: u32, frame_count: u32) -> Result<Vec<u8>> {
+    let frame_size = (width * height * 4) as usize;
+    if frame_size == 0 || frames_rgba.len() != frame_size * frame_count as usize {
+        return Err(ProcessorError::invalid_input("histogram", format!("{} bytes for {} {}x{} frames", frames_rgba.len(), frame_count, width, height)));
+    }
+
+    let frames: Vec<&[u8]> = frames_rgba.chunks_exact(frame_size).collect();
+    Ok(tensor::color_histogram(&frames))
+}
+
+/// Decompress a `tensor_data` payload produced with `TensorOpts::compression_level`
+///
+/// Pass the same dictionary the tensor was compressed with.
+pub fn decompress_tensor(tensor_data: Vec<u8>, dictionary: Option<Vec<u8>>) -> Result<Vec<u8>> {
+    compression::decompress(&tensor_data, dictionary.as_deref())
+}
+
+/// Train a shared zstd dictionary (at most `max_size` bytes) on typical tensors
+///
+/// Ship the result with the app and pass it in `TensorOpts::dictionary` and to
+/// `decompress_tensor`.
+pub fn train_tensor_dictionary(samples: Vec<Vec<u8>>, max_size: u32) -> Result<Vec<u8>> {
+    compression::train_dictionary(&samples, max_size as usize)
+}
+
+/// Reorder an RGBA tensor between frame-major, Morton and Hilbert layouts
+///
+/// Reading a curve-ordered buffer back as plain frames gives a space-filling
+/// "remix" of the clip.
+pub fn convert_tensor_layout(
+    tensor_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    depth: u32,
+    from: TensorLayout,
+    to: TensorLayout,
+) -> Result<Vec<u8>> {
+    tensor::relayout(tensor_data, tensor::TensorShape::new(width, height, depth), 4, from, to)
+}
+
+/// Resample a frame-major RGBA tensor to a new size and depth, e.g. 256³ down to 64³
+///
+/// Every axis is box-filtered, so downsizing averages the voxels each output
+/// voxel covers rather than skipping them.
+pub fn resample_tensor(
+    tensor_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    depth: u32,
+    out_width: u32,
+    out_height: u32,
+    out_depth: u32,
+) -> Result<Vec<u8>> {
+    tensor::resample(
+        &tensor_data,
+        tensor::TensorShape::new(width, height, depth),
+        tensor::TensorShape::new(out_width, out_height, out_depth),
+    )
+}
+
+// ============================================================================
+// VOXEL EFFECTS
+// ============================================================================
+
+/// Restyle a frame-major RGBA cube and re-encode its slices as a GIF
+///
+/// `ProcessResult::tensor_data` holds the stylized cube at the input shape, with
+/// `gif_opts.occupancy` applied (and `motion_data` its motion volume when
+/// requested); `gif_opts` size and frame count are overridden from the cube.
+pub fn apply_voxel_effect(
+    tensor_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    depth: u32,
+    effect: VoxelEffect,
+    quantize_opts: QuantizeOpts,
+    gif_opts: GifOpts,
+) -> Result<ProcessResult> {
+    let shape = tensor::TensorShape::new(width, height, depth);
+    let styled = tensor::apply_effect(&tensor_data, shape, effect)?;
+
+    let occupancy = gif_opts.occupancy.clone();
+    let include_motion = gif_opts.include_motion;
+    let tensor_opts = gif_opts.tensor.clone();
+    let gif_opts = GifOpts {
+        width: width as u16,
+        height: height as u16,
+        frame_count: depth,
+        include_tensor: false,
+        ..gif_opts
+    };
+    let mut result = process_all_frames(styled.clone(), width, height, depth, quantize_opts, gif_opts)?;
+
+    // Keying only shapes the cube; the GIF keeps every slice opaque
+    if include_motion {
+        result.motion_data = Some(tensor::motion_energy(&styled, shape)?);
+    }
+    let mut styled = styled;
+    tensor::compute_occupancy(&mut styled, shape, &occupancy)?;
+    deliver_tensor(&mut result, styled, shape, &tensor_opts)?;
+    Ok(result)
+}
+
+/// Play a frame-major RGBA cube across another axis and encode that as a GIF
+///
+/// See `tensor::reslice_to_frames` for the slice shapes; `gif_opts` size and
+/// frame count are overridden from them, and `ProcessResult::tensor_data` holds
+/// the resliced frames when `include_tensor` is set.
+pub fn reslice_voxel_cube(
+    tensor_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    depth: u32,
+    axis: SliceAxis,
+    quantize_opts: QuantizeOpts,
+    gif_opts: GifOpts,
+) -> Result<ProcessResult> {
+    let (frames, shape) = tensor::reslice_to_frames(&tensor_data, tensor::TensorShape::new(width, height, depth), 4, axis)?;
+    if shape.width > u16::MAX as u32 || shape.height > u16::MAX as u32 || shape.frames > u16::MAX as u32 {
+        return Err(ProcessorError::invalid_input("reslice", format!("{}x{}x{} slices are too large for a GIF", shape.width, shape.height, shape.frames)));
+    }
+
+    let include_tensor = gif_opts.include_tensor;
+    let tensor_opts = gif_opts.tensor.clone();
+    let gif_opts = GifOpts {
+        width: shape.width as u16,
+        height: shape.height as u16,
+        frame_count: shape.frames,
+        include_tensor: false,
+        ..gif_opts
+    };
+    let mut result = process_all_frames(frames.clone(), shape.width, shape.height, shape.frames, quantize_opts, gif_opts)?;
+    if include_tensor {
+        deliver_tensor(&mut result, frames, shape, &tensor_opts)?;
+    }
+    Ok(result)
+}
+
+// ============================================================================
+// FRAME EFFECTS
+// ============================================================================
+
+/// Parse an `EffectChain` saved as JSON, e.g. a filter preset shipped with the app
+pub fn effect_chain_from_json(json: String) -> Result<EffectChain> {
+    effects::from_json(&json)
+}
+
+/// Save an `EffectChain` as JSON for `effect_chain_from_json` to read back
+pub fn effect_chain_to_json(chain: EffectChain) -> Result<String> {
+    effects::to_json(&chain)
+}
+
+/// `EffectChain::retro_vhs` for FFI callers
+pub fn retro_vhs_effect_chain() -> EffectChain {
+    EffectChain::retro_vhs()
+}
+
+/// Make a Rust filter available to `GifOpts::frame_filters` under `name`
+pub fn register_native_frame_filter(name: &str, filter: std::sync::Arc<dyn FrameFilter>) {
+    filters::register(name, filter);
+}
+
+/// Make a host (Swift, Kotlin) filter available to `GifOpts::frame_filters` under `name`
+pub fn register_frame_filter(name: String, filter: Box<dyn HostFrameFilter>) {
+    filters::register_host(&name, filter);
+}
+
+/// Remove the filter registered under `name`; false, and nothing changes, if there was none
+pub fn unregister_frame_filter(name: String) -> bool {
+    filters::unregister(&name)
+}
+
+/// Names usable in `GifOpts::frame_filters`, in registration order
+pub fn registered_frame_filters() -> Vec<String> {
+    filters::names()
+}
+
+// ============================================================================
+// CLIP CONCATENATION
+// ============================================================================
+
+/// Join several encoded GIF clips of the same size into one GIF
+///
+/// Clip frames keep their original indices and palettes; only transition frames
+/// are composited and re-indexed. The result loops as the first clip does.
+pub fn concatenate_clips(clips: Vec<Vec<u8>>, transition: Transition) -> Result<Vec<u8>> {
+    concat::concatenate(&clips, &transition)
+}
+
+/// Lay two clips out on one `gif_opts.width`×`gif_opts.height` canvas and encode it
+///
+/// Side by side, stacked or picture-in-picture, each clip scaled into its
+/// pane on its own terms, for before/after and A/B comparison exports. The
+/// composite is quantized as one clip, so both share a palette, and
+/// `gif_opts` applies to it as to any capture. It runs as long as the longer
+/// clip; the shorter holds its last frame.
+pub fn compose_clips(
+    primary: CompositeClip,
+    secondary: CompositeClip,
+    composite_opts: CompositeOpts,
+    quantize_opts: QuantizeOpts,
+    gif_opts: GifOpts,
+) -> Result<ProcessResult> {
+    validation::composite(&primary, &secondary, &composite_opts, &gif_opts)?;
+    let (width, height) = (gif_opts.width as u32, gif_opts.height as u32);
+    let frame_count = primary.frame_count.max(secondary.frame_count);
+    let frames = composite::compose(&primary, &secondary, &composite_opts, width, height, &gif_opts.letterbox);
+    process_rgba(&frames, width, height, frame_count, quantize_opts, gif_opts)
+}
+
+// ============================================================================
+// TRANSCODING
+// ============================================================================
+
+/// Trim, crop, retime or change the loop count of an existing GIF
+///
+/// Palettes and indices are reused as they are, so this is fast and adds no
+/// quantization loss; see `transcode::transcode` for the one unsupported case.
+pub fn transcode_gif(gif_data: Vec<u8>, opts: TranscodeOpts) -> Result<Vec<u8>> {
+    transcode::transcode(&gif_data, &opts)
+}
+
+// ============================================================================
+// CLIP METADATA
+// ============================================================================
+
+/// The record `process_all_frames` writes for `GifOpts::frame_metadata`
+pub fn summarize_frame_metadata(frames: Vec<FrameMetadata>) -> Option<ClipMetadata> {
+    metadata::summarize(&frames)
+}
+
+/// Capture details stored in a GIF by this library, if it has any
+pub fn read_gif_metadata(gif_data: Vec<u8>) -> Result<Option<ClipMetadata>> {
+    metadata::read_gif(&gif_data)
+}
+
+// ============================================================================
+// LIVE PREVIEW
+// ============================================================================
+
+/// Approximate the final GIF look for a single camera frame
+///
+/// Uses an octree palette and ordered dither instead of the full pipeline so the
+/// capture screen can call it every frame (under 2ms at 128×128 in release).
+pub fn preview_quantize(
+    frame_rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+    opts: PreviewOpts,
+) -> Result<PreviewImage> {
+    if width == 0 || height == 0 || frame_rgba.len() != (width * height * 4) as usize {
+        return Err(ProcessorError::invalid_input("preview", format!("{} bytes for a {}x{} frame", frame_rgba.len(), width, height)));
+    }
+
+    let (pixels, width, height) = preview::downscale(
+        &frame_rgba,
+        width as usize,
+        height as usize,
+        opts.max_dimension as usize,
+    );
+    let rgba = preview::quantize_preview(
+        &pixels,
+        width,
+        opts.max_colors as usize,
+        opts.dithering_level,
+    );
+
+    Ok(PreviewImage {
+        width: width as u32,
+        height: height as u32,
+        rgba,
+    })
+}
+
+/// Every frame of a GIF as a viewer shows it, composited over `backdrop`
+///
+/// Frames come back to back as opaque canvas-sized RGBA, so the UI can draw a
+/// transparent clip without compositing it itself. Use `GifPlayer::frame_preview`
+/// to render one frame at a time instead.
+pub fn preview_gif_frames(gif_data: Vec<u8>, backdrop: Backdrop) -> Result<Vec<u8>> {
+    let player = GifPlayer::new(gif_data, 1)?;
+    let mut frames = Vec::with_capacity((player.width() * player.height() * 4 * player.frame_count()) as usize);
+    for index in 0..player.frame_count() {
+        frames.extend(player.frame_preview(index, backdrop.clone())?);
+    }
+    Ok(frames)
+}
+
+// ============================================================================
+// SINGLE-FRAME BUILDING BLOCKS
+// ============================================================================
+
+// Rust-only entry points for the C adapters (rust-ios-ffi, rust-minimal), which
+// hand over one frame at a time instead of a whole clip
+
+/// Area-resize one frame to `out_width`×`out_height`, cropping to the output aspect
+///
+/// Every channel is averaged the same way, so BGRA frames come out BGRA.
+pub fn resize_frame(frame: &[u8], width: u32, height: u32, out_width: u32, out_height: u32) -> Result<Vec<u8>> {
+    validation::input(frame.len(), width, height, 1)?;
+    if out_width == 0 || out_height == 0 {
+        return Err(ProcessorError::invalid_input("resize", "output size is empty"));
+    }
+    let mut out = Vec::new();
+    resize::cover_resize_region(frame, width, (0, 0, width, height), out_width, out_height, &mut out);
+    Ok(out)
+}
+
+/// Quantize one RGBA frame with libimagequant, returning its palette and indices
+pub fn quantize_frame(frame_rgba: &[u8], width: u32, height: u32, opts: &QuantizeOpts) -> Result<(palette::Palette, Vec<u8>)> {
+    validation::input(frame_rgba.len(), width, height, 1)?;
+    quantize_pixels(frame_rgba, width, height, width as usize * 4, PixelLayout::Rgba, opts)
+}
+
+/// `quantize_frame` for a frame in either channel order with `stride` bytes per row, read in place
+///
+/// Camera buffers, BGRA and padded rows included, go to libimagequant
+/// without being copied or swizzled first.
+pub fn quantize_pixels(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+    layout: PixelLayout,
+    opts: &QuantizeOpts,
+) -> Result<(palette::Palette, Vec<u8>)> {
+    validation::quantize(opts)?;
+    let options = quantization::QuantizeOptions {
+        quality_min: opts.quality_min,
+        quality_max: opts.quality_max,
+        speed: opts.speed,
+        palette_size: opts.palette_size,
+        dithering_level: opts.dithering_level,
+    };
+    let result = quantization::quantize_pixels(pixels, width, height, stride, layout, &options)?;
+    Ok((result.palette, result.indices))
+}
+
+/// Encode frames indexed against `palette` with the pipeline's GIF writer
+///
+/// Everything in `gif_opts` that applies after quantization is honored:
+/// delays, disposal, the background index and the accessibility palette pass.
+pub fn encode_indexed_frames(
+    indexed_frames: &[Vec<u8>],
+    palette: &palette::Palette,
+    transparent_index: Option<u8>,
+    gif_opts: &GifOpts,
+) -> Result<Vec<u8>> {
+    let palette = checked_indexed_input(indexed_frames, palette, transparent_index, gif_opts)?;
+    encode_gif(indexed_frames, &palette, transparent_index, gif_opts)
+}
+
+/// `reslice_voxel_cube` for indexed frames, so they keep their palette
+///
+/// Returns the slices with their width and height, ready for `encode_indexed_frames`.
+pub fn reslice_indexed_frames(indexed_frames: &[Vec<u8>], width: u32, height: u32, axis: SliceAxis) -> Result<(Vec<Vec<u8>>, u32, u32)> {
+    let frame_len = (width * height) as usize;
+    if let Some(i) = indexed_frames.iter().position(|frame| frame.len() != frame_len) {
+        return Err(ProcessorError::invalid_input("reslice", format!("{} indices for a {}x{} frame", indexed_frames[i].len(), width, height)).at_frame(i));
+    }
+    let shape = tensor::TensorShape::new(width, height, indexed_frames.len() as u32);
+    let (slices, shape) = tensor::reslice_to_frames(&indexed_frames.concat(), shape, 1, axis)?;
+    Ok((slices.chunks_exact(shape.frame_size()).map(<[u8]>::to_vec).collect(), shape.width, shape.height))
+}
+
+/// `encode_indexed_frames`, streamed into `sink` instead of returned
+///
+/// Frames are compressed a batch at a time, one per worker thread, and
+/// written as soon as their batch is done, so a long export never holds more
+/// than a batch of output. Returns the bytes written; on error
+/// `sink` may hold part of a file.
+pub fn encode_gif_to<W: std::io::Write>(
+    indexed_frames: &[Vec<u8>],
+    palette: &palette::Palette,
+    transparent_index: Option<u8>,
+    gif_opts: &GifOpts,
+    sink: &mut W,
+) -> Result<u64> {
+    let palette = checked_indexed_input(indexed_frames, palette, transparent_index, gif_opts)?;
+    let (_, total) = write_gif(indexed_frames, &palette, transparent_index, gif_opts, None, sink)?;
+    Ok(total)
+}
+
+/// Validate pre-indexed frames against their palette, returning it as RGBA
+///
+/// The color table only grows to the next power of two of the palette, so
+/// an index past the palette would corrupt the LZW stream rather than just
+/// showing black.
+fn checked_indexed_input(
+    indexed_frames: &[Vec<u8>],
+    palette: &palette::Palette,
+    transparent_index: Option<u8>,
+    gif_opts: &GifOpts,
+) -> Result<Vec<[u8; 4]>> {
+    validation::gif(gif_opts)?;
+    if palette.is_empty() || palette.len() > 256 {
+        return Err(ProcessorError::invalid_input("encode", format!("{} palette colors, expected 1-256", palette.len())));
+    }
+    let frame_len = gif_opts.width as usize * gif_opts.height as usize;
+    if let Some(i) = indexed_frames.iter().position(|frame| frame.len() != frame_len) {
+        return Err(ProcessorError::invalid_input("encode", format!("{} indices for a {}x{} frame", indexed_frames[i].len(), gif_opts.width, gif_opts.height)).at_frame(i));
+    }
+    for (i, frame) in indexed_frames.iter().enumerate() {
+        if let Some(&index) = frame.iter().find(|&&index| index as usize >= palette.len()) {
+            return Err(ProcessorError::invalid_input("encode", format!("index {} is past the {} color palette", index, palette.len())).at_frame(i));
+        }
+    }
+    if let Some(index) = transparent_index.filter(|&index| index as usize >= palette.len()) {
+        return Err(ProcessorError::invalid_input("encode", format!("transparent index {} is past the {} color palette", index, palette.len())));
+    }
+    if gif_opts.background_index as usize >= palette.len() {
+        return Err(ProcessorError::invalid_input("encode", format!("background index {} is past the {} color palette", gif_opts.background_index, palette.len())));
+    }
+    let mut palette: Vec<[u8; 4]> = palette.iter().map(|c| c.rgba()).collect();
+    accessibility::apply(&mut palette, &gif_opts.accessibility);
+    Ok(palette)
+}
+
+// ============================================================================
+// RESUMABLE ENCODING
+// ============================================================================
+
+/// Finish an encode interrupted after quantization, from its checkpoint
+///
+/// Pass the `checkpoint_path` the interrupted call was given. Only the GIF and
+/// its variants are produced: the source frames are gone, so no tensor. Width
+/// and height come from the checkpoint; the rest of `gif_opts` (fps, loop
+/// count, variants) applies as usual. The checkpoint is deleted on success.
+/// Fails with `InvalidInput` if the file is missing, from another format
+/// version, or past its `checkpoint_ttl_secs`.
+pub fn resume_encoding(checkpoint_path: String, gif_opts: GifOpts) -> Result<ProcessResult> {
+    let start = Instant::now();
+    let saved = checkpoint::load(&checkpoint_path)?;
+    let gif_opts = GifOpts {
+        width: saved.width as u16,
+        height: saved.height as u16,
+        ..gif_opts
+    };
+    validation::gif(&gif_opts)?;
+
+    let (gif_buffer, file_size) = encode_output(&saved.indexed_frames, &saved.palette, saved.transparent_index, &gif_opts)?;
+    let variants = encode_variants(
+        &saved.indexed_frames,
+        saved.width,
+        saved.height,
+        &saved.palette,
+        saved.transparent_index,
+        &gif_opts,
+    )?;
+    let segments = encode_segments(&saved.indexed_frames, &saved.palette, saved.transparent_index, &gif_opts)?;
+    let encode_ms = elapsed_ms(start);
+    let _ = std::fs::remove_file(&checkpoint_path);
+
+    Ok(ProcessResult {
+        final_file_size: file_size,
+        gif_data: gif_buffer,
+        tensor_data: None,
+        motion_data: None,
+        turntable_data: None,
+        long_exposure_data: None,
+        long_exposure_gif: None,
+        text_art: Vec::new(),
+        text_art_gif: None,
+        tensor_handle: None,
+        processing_time_ms: start.elapsed().as_millis() as f32,
+        actual_frame_count: saved.indexed_frames.len() as u32,
+        palette_size_used: saved.palette.len() as u16,
+        variants,
+        segments,
+        dropped_frames: 0,
+        stage_timings: StageTimings { encode_ms, ..Default::default() },
+        palette_rgba: saved.palette.concat(),
+        motion_rect: None,
+        color_space: gif_opts.color_space,
+        quantization: None,
+    })
+}
+
+// ============================================================================
+// SAVING
+// ============================================================================
+
+/// Write a finished GIF into the user's library without ever exposing half of it
+///
+/// The bytes go to a hidden file next to `path`, which is renamed over `path`
+/// only once complete; a crash leaves the previous file, or nothing. `sync`
+/// also flushes to disk before and after the rename, so the save survives a
+/// power loss. Blocks until done, so call it off the main thread. Fails with
+/// `InvalidInput` if `gif_data` isn't a GIF.
+pub fn save_gif(path: String, gif_data: Vec<u8>, sync: bool) -> Result<()> {
+    if !(gif_data.starts_with(b"GIF89a") || gif_data.starts_with(b"GIF87a")) || gif_data.last() != Some(&0x3B) {
+        return Err(ProcessorError::invalid_input("save", "not a complete GIF"));
+    }
+    atomic_file::write(&path, &gif_data, sync).map_err(|e| ProcessorError::memory("save", e))
+}
+
+/// Check a GIF saved with `GifOpts::integrity_hash` against its hash
+///
+/// `Unsigned` covers both files saved without a hash and files cut short
+/// before it; neither can be vouched for.
+pub fn verify_gif_integrity(gif_data: Vec<u8>) -> IntegrityCheck {
+    integrity::check_gif(&gif_data)
+}
+
+/// `verify_gif_integrity` for a YXV container written with a footer
+pub fn verify_yxv_integrity(yxv_data: Vec<u8>) -> IntegrityCheck {
+    integrity::check_yxv(&yxv_data)
+}
+
+/// `save_gif` for a YXV voxel container
+pub fn save_yxv(path: String, yxv_data: Vec<u8>, sync: bool) -> Result<()> {
+    if !yxv_data.starts_with(b"YXV\0") {
+        return Err(ProcessorError::invalid_input("save", "not a YXV container"));
+    }
+    atomic_file::write(&path, &yxv_data, sync).map_err(|e| ProcessorError::memory("save", e))
+}
+
+// ============================================================================
+// DEVICE CAPABILITIES
+// ============================================================================
+
+/// Report the device's thermal state; applies to every encode started afterwards
+///
+/// Hotter states cap worker threads, raise the imagequant speed and skip
+/// optional passes (see `ThermalState`). Requested outputs are still produced.
+pub fn set_thermal_state(state: ThermalState) {
+    thermal::set(state);
+}
+
+/// Thermal state encodes are throttled for: `Nominal` until `set_thermal_state` reports another
+pub fn thermal_state() -> ThermalState {
+    thermal::current()
+}
+
+/// Probe core count, SIMD support and throughput (runs a ~20ms benchmark)
+///
+/// Call once per launch, off the main thread, and cache the result.
+pub fn probe_capabilities() -> DeviceCapabilities {
+    capabilities::probe()
+}
+
+/// `ProcessorOptions::auto_for` for FFI callers
+pub fn auto_processor_options(capabilities: DeviceCapabilities) -> ProcessorOptions {
+    ProcessorOptions::auto_for(&capabilities)
+}
+
+/// `ProcessorOptions::pixel_art` for FFI callers
+pub fn pixel_art_processor_options(pixel_grid: u16) -> ProcessorOptions {
+    ProcessorOptions::pixel_art(pixel_grid)
+}
+
+/// `ProcessorOptions::low_color` for FFI callers
+pub fn low_color_processor_options(palette_size: u16) -> ProcessorOptions {
+    ProcessorOptions::low_color(palette_size)
+}
+
+/// `ProcessorOptions::one_bit` for FFI callers
+pub fn one_bit_processor_options() -> ProcessorOptions {
+    ProcessorOptions::one_bit()
+}
+
+/// `ProcessorOptions::game_boy` for FFI callers
+pub fn game_boy_processor_options() -> ProcessorOptions {
+    ProcessorOptions::game_boy()
+}
+
+/// `ProcessorOptions::blurred_square` for FFI callers
+pub fn blurred_square_processor_options(size: u16) -> ProcessorOptions {
+    ProcessorOptions::blurred_square(size)
+}
+
+/// Parse a `CaptureProfile` saved as JSON, e.g. one read back from a YXV container; malformed JSON fails with `InvalidInput`
+pub fn capture_profile_from_json(json: String) -> Result<CaptureProfile> {
+    serde_json::from_str(&json).map_err(|e| ProcessorError::invalid_input("capture profile", e))
+}
+
+/// Save a `CaptureProfile` as JSON for a YXV container or `capture_profile_from_json`
+pub fn capture_profile_to_json(profile: CaptureProfile) -> Result<String> {
+    serde_json::to_string(&profile).map_err(|e| ProcessorError::encoding("capture profile", e))
+}
+
 // ============================================================================
 // UTILITY FUNCTIONS
 // ============================================================================
 
-/// Calculate required buffer size for frames
-pub fn calculate_buffer_size(width: u32, height: u32, frame_count: u32) -> u32 {
-    width * height * 4 * frame_count
+/// Calculate required buffer size for frames; u64 since long 4K clips pass 4 GB
+pub fn calculate_buffer_size(width: u32, height: u32, frame_count: u32) -> u64 {
+    width as u64 * height as u64 * 4 * frame_count as u64
 }
 
 /// Validate buffer has expected size
-pub fn validate_buffer(buffer: Vec<u8>, expected_size: u32) -> bool {
-    buffer.len() == expected_size as usize
+pub fn validate_buffer(buffer: Vec<u8>, expected_size: u64) -> bool {
+    buffer.len() as u64 == expected_size
+}
+
+/// Frame buffers allocated and recycled across encodes and captures so far
+pub fn buffer_pool_stats() -> BufferPoolStats {
+    buffer_pool::FRAME_POOL.stats()
+}
+
+/// Free the frame buffers kept for reuse, e.g. on a memory warning
+pub fn release_buffer_pool() {
+    buffer_pool::FRAME_POOL.release();
+}
+
+/// Library version, e.g. "1.0.0"
+pub fn rgb2gif_version() -> String {
+    build_info::version().to_string()
+}
+
+/// Interface revision; Swift compares it with the one its bindings were generated for
+pub fn rgb2gif_abi_version() -> u32 {
+    build_info::ABI_VERSION
+}
+
+/// Optional subsystems compiled into this library, as bit flags
+///
+/// See the `build_info::FEATURE_*` bits: gpu = 1, webp = 2, apng = 4, oklab = 8, simd = 16.
+pub fn rgb2gif_features() -> u32 {
+    build_info::features()
+}
+
+/// Record panics for `rgb2gif_last_panic`; call once at app launch
+///
+/// The pipeline entry points install the hook themselves, so this only adds
+/// `log_path`: each report is also written there, which survives a panic that
+/// aborts the process and is read back by the next launch's `rgb2gif_last_panic`.
+pub fn install_panic_hook(log_path: Option<String>) {
+    panic_log::install(log_path);
+}
+
+/// Message, thread and backtrace of the latest panic, if any has been recorded
+pub fn rgb2gif_last_panic() -> Option<String> {
+    panic_log::last()
+}
+
+/// Forget recorded panics, including the log file
+pub fn clear_panic_log() {
+    panic_log::clear();
+}
+
+/// Milliseconds since `since`, with sub-millisecond precision for stage timings
+fn elapsed_ms(since: Instant) -> f32 {
+    since.elapsed().as_secs_f32() * 1000.0
 }
 
 // ============================================================================
@@ -0,0 +1,123 @@
+// Long Exposure
+// Stacks every frame into one still, like leaving the shutter open, for light trails and motion blur
+
+use crate::ExposureBlend;
+
+/// Running stack of frames, one `[f32; 4]` accumulator per pixel
+pub struct Exposure {
+    blend: ExposureBlend,
+    stack: Vec<[f32; 4]>,
+    frames: u32,
+}
+
+impl Exposure {
+    pub fn new(pixels: usize, blend: ExposureBlend) -> Self {
+        Self { blend, stack: vec![[0.0; 4]; pixels], frames: 0 }
+    }
+
+    /// Expose one more RGBA frame
+    ///
+    /// Max keeps each channel's brightest value, Average sums colors weighted
+    /// by alpha, and Screen lightens like projecting every frame at once.
+    /// Alpha is the brightest alpha for Max and Screen and the mean for Average.
+    pub fn add(&mut self, frame: &[u8]) {
+        for (acc, px) in self.stack.iter_mut().zip(frame.chunks_exact(4)) {
+            let alpha = px[3] as f32 / 255.0;
+            match self.blend {
+                ExposureBlend::Max => {
+                    for c in 0..3 {
+                        acc[c] = acc[c].max(px[c] as f32 / 255.0 * alpha);
+                    }
+                    acc[3] = acc[3].max(alpha);
+                }
+                ExposureBlend::Average => {
+                    for c in 0..3 {
+                        acc[c] += px[c] as f32 / 255.0 * alpha;
+                    }
+                    acc[3] += alpha;
+                }
+                ExposureBlend::Screen => {
+                    for c in 0..3 {
+                        acc[c] = 1.0 - (1.0 - acc[c]) * (1.0 - px[c] as f32 / 255.0 * alpha);
+                    }
+                    acc[3] = acc[3].max(alpha);
+                }
+            }
+        }
+        self.frames += 1;
+    }
+
+    /// The exposure so far as RGBA
+    pub fn image(&self) -> Vec<u8> {
+        let to_byte = |v: f32| (v * 255.0).round().clamp(0.0, 255.0) as u8;
+        self.stack
+            .iter()
+            .flat_map(|acc| {
+                // Colors were stacked weighted by alpha; undo that for the stored color
+                let weight = acc[3];
+                let color = |c: f32| if weight > 0.0 { to_byte(c / weight) } else { 0 };
+                let alpha = match self.blend {
+                    ExposureBlend::Average => weight / self.frames.max(1) as f32,
+                    ExposureBlend::Max | ExposureBlend::Screen => weight,
+                };
+                [color(acc[0]), color(acc[1]), color(acc[2]), to_byte(alpha)]
+            })
+            .collect()
+    }
+}
+
+/// Every frame stacked into one still
+pub fn composite(frames: &[&[u8]], blend: ExposureBlend) -> Vec<u8> {
+    let mut exposure = Exposure::new(frames.first().map_or(0, |f| f.len() / 4), blend);
+    frames.iter().for_each(|frame| exposure.add(frame));
+    exposure.image()
+}
+
+/// `steps` RGBA frames back to back, each stacking a larger share of the clip; the last is `composite`
+pub fn build_up(frames: &[&[u8]], blend: ExposureBlend, steps: usize) -> Vec<u8> {
+    let mut exposure = Exposure::new(frames.first().map_or(0, |f| f.len() / 4), blend);
+    let mut out = Vec::new();
+    let mut added = 0;
+    for step in 1..=steps {
+        let through = (step * frames.len()).div_ceil(steps);
+        for frame in &frames[added..through] {
+            exposure.add(frame);
+        }
+        added = through;
+        out.extend(exposure.image());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One pixel per frame: a dot that is bright in a different channel each time
+    fn dots() -> Vec<Vec<u8>> {
+        vec![vec![200, 10, 10, 255], vec![10, 100, 10, 255], vec![10, 10, 50, 255], vec![0, 0, 0, 0]]
+    }
+
+    #[test]
+    fn test_blends_stack_the_frames() {
+        let frames = dots();
+        let refs: Vec<&[u8]> = frames.iter().map(|f| f.as_slice()).collect();
+        assert_eq!(composite(&refs, ExposureBlend::Max), [200, 100, 50, 255]);
+        // The transparent frame adds nothing to the color, only lowers coverage
+        assert_eq!(composite(&refs, ExposureBlend::Average), [73, 40, 23, 191]);
+        let screen = composite(&refs, ExposureBlend::Screen);
+        assert!(screen[..3].iter().zip([200, 100, 50]).all(|(&s, m)| s > m), "{screen:?}");
+        assert_eq!(composite(&[], ExposureBlend::Max), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_build_up_ends_on_the_composite() {
+        let frames = dots();
+        let refs: Vec<&[u8]> = frames.iter().map(|f| f.as_slice()).collect();
+        let steps = build_up(&refs, ExposureBlend::Max, 3);
+        assert_eq!(steps.len(), 3 * 4);
+        // 2, 3 then all 4 frames stacked
+        assert_eq!(steps[..4], [200, 100, 10, 255]);
+        assert_eq!(steps[8..], composite(&refs, ExposureBlend::Max)[..]);
+    }
+}
@@ -0,0 +1,56 @@
+// Loop Seam Smoothing
+// Crossfades the tail of a clip into its first frame so infinite loops don't jump
+
+/// Blend the last `crossfade` frames progressively toward the first frame
+///
+/// Returns the replacement tail frames, oldest first. Tail frame `j` of `k` is
+/// mixed with the head at `(j + 1) / (k + 1)`, so the last frame sits closest to
+/// the first and the wrap-around step is no bigger than the ones before it. The
+/// crossfade is capped at half the clip so the head itself is never touched.
+pub fn smooth_loop_seam(frames: &[&[u8]], crossfade: usize) -> Vec<Vec<u8>> {
+    let crossfade = crossfade.min(frames.len() / 2);
+    if crossfade == 0 {
+        return Vec::new();
+    }
+
+    let head = frames[0];
+    let tail_start = frames.len() - crossfade;
+    frames[tail_start..]
+        .iter()
+        .enumerate()
+        .map(|(j, frame)| {
+            let t = (j + 1) as f32 / (crossfade + 1) as f32;
+            frame
+                .iter()
+                .zip(head)
+                .map(|(&a, &b)| (a as f32 + (b as f32 - a as f32) * t).round() as u8)
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tail_ramps_toward_first_frame() {
+        let frames: Vec<Vec<u8>> = [0u8, 50, 100, 150, 200, 250].iter().map(|&v| vec![v; 4]).collect();
+        let refs: Vec<&[u8]> = frames.iter().map(|f| f.as_slice()).collect();
+
+        let tail = smooth_loop_seam(&refs, 2);
+        assert_eq!(tail.len(), 2);
+        // 200 → 0 at 1/3, 250 → 0 at 2/3
+        assert_eq!(tail[0], vec![133; 4]);
+        assert_eq!(tail[1], vec![83; 4]);
+    }
+
+    #[test]
+    fn test_crossfade_is_capped_at_half_the_clip() {
+        let frames: Vec<Vec<u8>> = (0..3u8).map(|v| vec![v; 4]).collect();
+        let refs: Vec<&[u8]> = frames.iter().map(|f| f.as_slice()).collect();
+
+        assert_eq!(smooth_loop_seam(&refs, 10).len(), 1);
+        assert!(smooth_loop_seam(&refs[..1], 10).is_empty());
+    }
+}
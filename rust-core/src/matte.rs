@@ -0,0 +1,51 @@
+// Background Matte
+// Folds host-supplied alpha masks (e.g. Vision person segmentation) into the frames' alpha
+
+use rayon::prelude::*;
+
+/// Pixels per parallel work item
+const CHUNK_PIXELS: usize = 4096;
+
+/// Multiply every pixel's alpha by its mask byte, one byte per pixel (255 keeps it, 0 clears it)
+///
+/// Everything downstream already reads alpha: the palette weighs pixels by it,
+/// transparent pixels get the transparent slot, and solid occupancy copies it
+/// into the voxels, so the matte reaches all three from here.
+pub fn multiply(frames_rgba: &mut [u8], masks: &[u8]) {
+    frames_rgba.par_chunks_mut(CHUNK_PIXELS * 4).zip(masks.par_chunks(CHUNK_PIXELS)).for_each(|(pixels, mask)| {
+        for (pixel, &m) in pixels.chunks_exact_mut(4).zip(mask) {
+            pixel[3] = ((pixel[3] as u32 * m as u32 + 127) / 255) as u8;
+        }
+    });
+}
+
+/// Clear pixels whose alpha fell below `threshold`, so soft matte edges don't leave a faint halo
+pub fn cut(frames_rgba: &mut [u8], threshold: u8) {
+    if threshold == 0 {
+        return;
+    }
+    frames_rgba.par_chunks_mut(CHUNK_PIXELS * 4).for_each(|pixels| {
+        for pixel in pixels.chunks_exact_mut(4).filter(|p| p[3] < threshold) {
+            pixel[3] = 0;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_scales_alpha_and_cut_clears_the_fringe() {
+        let mut frames = [[10, 20, 30, 255], [10, 20, 30, 255], [10, 20, 30, 128], [10, 20, 30, 255]].concat();
+        multiply(&mut frames, &[255, 0, 255, 100]);
+        assert_eq!(frames, [[10, 20, 30, 255], [10, 20, 30, 0], [10, 20, 30, 128], [10, 20, 30, 100]].concat());
+
+        cut(&mut frames, 128);
+        assert_eq!(frames, [[10, 20, 30, 255], [10, 20, 30, 0], [10, 20, 30, 128], [10, 20, 30, 0]].concat());
+
+        let before = frames.clone();
+        cut(&mut frames, 0);
+        assert_eq!(frames, before);
+    }
+}
@@ -0,0 +1,165 @@
+// Clip Metadata
+// Summarizes per-frame capture details and stores them in a GIF application extension
+
+use crate::{ClipMetadata, ColorSpace, FrameMetadata, ProcessorError, Result};
+
+/// Application identifier (8 bytes) and authentication code (3 bytes)
+///
+/// The payload after it is the `ClipMetadata` as JSON, so fields can be
+/// added without breaking older readers.
+pub const GIF_APPLICATION_ID: &[u8; 11] = b"RGB2GIF2MD1";
+
+/// Fold per-frame entries into one record, or `None` if none carry anything
+pub fn summarize(frames: &[FrameMetadata]) -> Option<ClipMetadata> {
+    let mut summary = ClipMetadata::default();
+    for frame in frames {
+        if *frame == FrameMetadata::default() {
+            continue;
+        }
+        summary.frame_count += 1;
+        if let Some(ts) = frame.timestamp_ms {
+            summary.start_ms = Some(summary.start_ms.map_or(ts, |v| v.min(ts)));
+            summary.end_ms = Some(summary.end_ms.map_or(ts, |v| v.max(ts)));
+        }
+        if let Some(exposure) = frame.exposure_s.filter(|e| e.is_finite()) {
+            summary.exposure_min_s = Some(summary.exposure_min_s.map_or(exposure, |v| v.min(exposure)));
+            summary.exposure_max_s = Some(summary.exposure_max_s.map_or(exposure, |v| v.max(exposure)));
+        }
+        if let Some(iso) = frame.iso {
+            summary.iso_min = Some(summary.iso_min.map_or(iso, |v| v.min(iso)));
+            summary.iso_max = Some(summary.iso_max.map_or(iso, |v| v.max(iso)));
+        }
+        if summary.latitude.is_none() {
+            if let (Some(lat), Some(lon)) = (frame.latitude, frame.longitude) {
+                summary.latitude = Some(lat);
+                summary.longitude = Some(lon);
+            }
+        }
+    }
+    (summary.frame_count > 0).then_some(summary)
+}
+
+/// The record a GIF carries: the summary, tagged with `color_space` unless it is sRGB
+///
+/// Viewers read an untagged GIF as sRGB already, so sRGB clips stay exactly
+/// as before and only wide-gamut ones gain the tag (and, without capture
+/// details, an extension of their own).
+pub fn for_gif(frames: &[FrameMetadata], color_space: ColorSpace) -> Option<ClipMetadata> {
+    if color_space == ColorSpace::Srgb {
+        return summarize(frames);
+    }
+    Some(ClipMetadata { color_space: Some(color_space), ..summarize(frames).unwrap_or_default() })
+}
+
+pub fn to_json(metadata: &ClipMetadata) -> Result<Vec<u8>> {
+    serde_json::to_vec(metadata).map_err(|e| ProcessorError::encoding("metadata", e))
+}
+
+pub fn from_json(json: &[u8]) -> Result<ClipMetadata> {
+    serde_json::from_slice(json).map_err(|e| ProcessorError::invalid_input("metadata", e))
+}
+
+/// Find the metadata extension in a GIF, walking blocks up to the trailer
+pub fn read_gif(data: &[u8]) -> Result<Option<ClipMetadata>> {
+    let truncated = || ProcessorError::invalid_input("gif metadata", "file is truncated");
+    if data.len() < 13 || !data.starts_with(b"GIF") {
+        return Err(ProcessorError::invalid_input("gif metadata", "not a GIF file"));
+    }
+
+    let mut pos = 13 + color_table_len(data[10]);
+    loop {
+        match data.get(pos).copied().ok_or_else(truncated)? {
+            // Extension: label, then sub-blocks
+            0x21 => {
+                let label = *data.get(pos + 1).ok_or_else(truncated)?;
+                let (blocks, next) = sub_blocks(data, pos + 2).ok_or_else(truncated)?;
+                if label == 0xFF && blocks.first() == Some(&GIF_APPLICATION_ID.as_slice()) {
+                    return from_json(&blocks[1..].concat()).map(Some);
+                }
+                pos = next;
+            }
+            // Image: descriptor, optional local table, LZW code size, sub-blocks
+            0x2C => {
+                let flags = *data.get(pos + 9).ok_or_else(truncated)?;
+                let start = pos + 10 + color_table_len(flags) + 1;
+                pos = sub_blocks(data, start).ok_or_else(truncated)?.1;
+            }
+            _ => return Ok(None),
+        }
+    }
+}
+
+fn color_table_len(flags: u8) -> usize {
+    if flags & 0x80 != 0 {
+        3 << ((flags & 0x07) + 1)
+    } else {
+        0
+    }
+}
+
+/// Sub-blocks starting at `pos`, and the offset just past their terminator
+fn sub_blocks(data: &[u8], mut pos: usize) -> Option<(Vec<&[u8]>, usize)> {
+    let mut blocks = Vec::new();
+    loop {
+        let len = *data.get(pos)? as usize;
+        if len == 0 {
+            return Some((blocks, pos + 1));
+        }
+        blocks.push(data.get(pos + 1..pos + 1 + len)?);
+        pos += 1 + len;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_ranges_and_first_location() {
+        let frames = vec![
+            FrameMetadata { timestamp_ms: Some(2_000), exposure_s: Some(0.01), iso: Some(400), ..Default::default() },
+            FrameMetadata::default(),
+            FrameMetadata {
+                timestamp_ms: Some(1_000),
+                exposure_s: Some(0.02),
+                iso: Some(100),
+                latitude: Some(51.5),
+                longitude: Some(-0.12),
+            },
+            FrameMetadata { latitude: Some(40.7), longitude: Some(-74.0), ..Default::default() },
+        ];
+        let summary = summarize(&frames).unwrap();
+        assert_eq!(summary.frame_count, 3);
+        assert_eq!((summary.start_ms, summary.end_ms), (Some(1_000), Some(2_000)));
+        assert_eq!((summary.exposure_min_s, summary.exposure_max_s), (Some(0.01), Some(0.02)));
+        assert_eq!((summary.iso_min, summary.iso_max), (Some(100), Some(400)));
+        assert_eq!((summary.latitude, summary.longitude), (Some(51.5), Some(-0.12)));
+
+        assert_eq!(summarize(&[FrameMetadata::default()]), None);
+    }
+
+    #[test]
+    fn test_only_wide_gamut_clips_are_tagged() {
+        let frames = [FrameMetadata { iso: Some(200), ..Default::default() }];
+        assert_eq!(for_gif(&[], ColorSpace::Srgb), None);
+        assert_eq!(for_gif(&frames, ColorSpace::Srgb), summarize(&frames));
+
+        let p3 = for_gif(&[], ColorSpace::DisplayP3).unwrap();
+        assert_eq!((p3.frame_count, p3.color_space), (0, Some(ColorSpace::DisplayP3)));
+        let p3 = for_gif(&frames, ColorSpace::DisplayP3).unwrap();
+        assert_eq!((p3.iso_min, p3.color_space), (Some(200), Some(ColorSpace::DisplayP3)));
+        // Records written before the tag existed still parse
+        assert_eq!(from_json(br#"{"frame_count":1,"iso_min":200}"#).unwrap().color_space, None);
+    }
+
+    #[test]
+    fn test_read_skips_unrelated_blocks() {
+        // Header, screen descriptor with a 2-color table, a comment, then the trailer
+        let mut gif = b"GIF89a\x01\x00\x01\x00\x80\x00\x00".to_vec();
+        gif.extend_from_slice(&[0; 6]);
+        gif.extend_from_slice(&[0x21, 0xFE, 2, b'h', b'i', 0, 0x3B]);
+        assert_eq!(read_gif(&gif).unwrap(), None);
+        assert!(read_gif(&gif[..20]).is_err());
+        assert!(read_gif(b"PNG").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+// Motion Crop
+// Finds the region a clip's subject moves in, so the GIF can leave out the still surroundings
+
+use rayon::prelude::*;
+
+/// Pixel rectangle as (x, y, width, height)
+pub type Region = (u32, u32, u32, u32);
+
+/// Smallest rectangle inside `region` holding every pixel that changes between consecutive frames
+///
+/// A pixel changes when any of its channels moves by more than `threshold`,
+/// so sensor noise below it never grows the box. Returns None when nothing in
+/// the region moves, including for a single frame.
+pub fn changed_bounds(frames: &[&[u8]], width: u32, region: Region, threshold: u8) -> Option<Region> {
+    let (rx, ry, rw, rh) = region;
+    let bounds = frames
+        .par_windows(2)
+        .filter_map(|pair| {
+            let (mut x0, mut y0, mut x1, mut y1) = (u32::MAX, u32::MAX, 0, 0);
+            for y in ry..ry + rh {
+                let row = ((y * width + rx) * 4) as usize..((y * width + rx + rw) * 4) as usize;
+                let (before, after) = (&pair[0][row.clone()], &pair[1][row]);
+                for (x, (a, b)) in (rx..).zip(before.chunks_exact(4).zip(after.chunks_exact(4))) {
+                    if a.iter().zip(b).any(|(a, b)| a.abs_diff(*b) > threshold) {
+                        (x0, y0, x1, y1) = (x0.min(x), y0.min(y), x1.max(x), y1.max(y));
+                    }
+                }
+            }
+            (x0 <= x1).then_some((x0, y0, x1, y1))
+        })
+        .reduce_with(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)))?;
+    let (x0, y0, x1, y1) = bounds;
+    Some((x0, y0, x1 - x0 + 1, y1 - y0 + 1))
+}
+
+/// `bounds` grown by `padding` on every side, then to the `out_width`:`out_height` aspect, all within `region`
+///
+/// Scaling crops whatever it gets to the output aspect, so growing the box to
+/// that aspect first keeps the whole subject in frame. The box stays centered
+/// on the subject unless the region's edge pushes it back inside.
+pub fn padded_to_aspect(bounds: Region, padding: u32, region: Region, out_width: u32, out_height: u32) -> Region {
+    let (rx, ry, rw, rh) = region;
+    let x0 = bounds.0.saturating_sub(padding).max(rx);
+    let y0 = bounds.1.saturating_sub(padding).max(ry);
+    let x1 = (bounds.0 + bounds.2).saturating_add(padding).min(rx + rw);
+    let y1 = (bounds.1 + bounds.3).saturating_add(padding).min(ry + rh);
+    let (w, h) = (x1 - x0, y1 - y0);
+
+    // Widen the short side; integer ratios so square outputs stay exactly square
+    let want_w = (h as u64 * out_width as u64).div_ceil(out_height as u64) as u32;
+    let want_h = (w as u64 * out_height as u64).div_ceil(out_width as u64) as u32;
+    let (x, w) = widen(x0, w, want_w, rx, rw);
+    let (y, h) = widen(y0, h, want_h, ry, rh);
+    (x, y, w, h)
+}
+
+/// Grow the span `start..start + len` to `want` (never shrinking it), kept inside `lo..lo + span`
+fn widen(start: u32, len: u32, want: u32, lo: u32, span: u32) -> (u32, u32) {
+    let want = want.clamp(len, span);
+    let start = start.saturating_sub((want - len) / 2).max(lo).min(lo + span - want);
+    (start, want)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gray frames with a white 4×3 block at `(x, 10)` and faint noise everywhere
+    fn frame(x: u32, z: u32) -> Vec<u8> {
+        (0..40 * 30u32)
+            .flat_map(|i| {
+                let (px, py) = (i % 40, i / 40);
+                let value = if (x..x + 4).contains(&px) && (10..13).contains(&py) { 255 } else { 100 + ((i * 7 + z * 3) % 5) as u8 };
+                [value, value, value, 255]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bounds_cover_the_whole_path() {
+        let frames: Vec<Vec<u8>> = (0..4).map(|z| frame(6 + z * 5, z)).collect();
+        let refs: Vec<&[u8]> = frames.iter().map(|f| f.as_slice()).collect();
+        let whole = (0, 0, 40, 30);
+
+        // The block travels from x = 6 to x = 24
+        assert_eq!(changed_bounds(&refs, 40, whole, 16), Some((6, 10, 19, 3)));
+        // A low enough threshold counts the noise as motion
+        assert_eq!(changed_bounds(&refs, 40, whole, 2), Some(whole));
+        // Outside where the block goes, nothing moves
+        assert_eq!(changed_bounds(&refs, 40, (30, 0, 10, 30), 16), None);
+        assert_eq!(changed_bounds(&refs[..1], 40, whole, 16), None);
+    }
+
+    #[test]
+    fn test_padding_and_aspect_stay_in_region() {
+        let whole = (0, 0, 40, 30);
+        // 19×3 padded by 2 is 23×7, then grown to 23×23 for a square output that stops at the top edge
+        assert_eq!(padded_to_aspect((6, 10, 19, 3), 2, whole, 128, 128), (4, 0, 23, 23));
+        // A box at the edge is pushed back inside rather than cut off
+        assert_eq!(padded_to_aspect((0, 0, 4, 4), 0, whole, 2, 1), (0, 0, 8, 4));
+        // Taller than the frame allows: the frame's height is as far as it goes
+        assert_eq!(padded_to_aspect((10, 10, 20, 2), 0, whole, 1, 2), (10, 0, 20, 30));
+    }
+}
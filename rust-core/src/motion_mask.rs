@@ -0,0 +1,77 @@
+// Motion Mask
+// Per-pixel importance from temporal variance, so palettes favour the moving subject over a static background
+
+use rayon::prelude::*;
+
+/// How much each pixel position changes across the clip, 0 when static and 1 at the most changing position
+///
+/// The score is the standard deviation of the pixel's RGB values across
+/// frames, normalised by the clip's largest. A clip with no motion at all
+/// (including a single frame) scores 0 everywhere.
+pub fn temporal_spread(frames: &[&[u8]], width: u32, height: u32) -> Vec<f32> {
+    let pixels = (width * height) as usize;
+    let count = frames.len().max(1) as f32;
+    let mut spread: Vec<f32> = (0..pixels)
+        .into_par_iter()
+        .map(|p| {
+            let (mut sum, mut sum_sq) = ([0.0f32; 3], [0.0f32; 3]);
+            for frame in frames {
+                for c in 0..3 {
+                    let v = frame[p * 4 + c] as f32;
+                    sum[c] += v;
+                    sum_sq[c] += v * v;
+                }
+            }
+            let variance: f32 = (0..3).map(|c| (sum_sq[c] / count - (sum[c] / count).powi(2)).max(0.0)).sum();
+            variance.sqrt()
+        })
+        .collect();
+
+    let peak = spread.iter().cloned().fold(0.0f32, f32::max);
+    if peak > 0.0 {
+        spread.iter_mut().for_each(|s| *s /= peak);
+    }
+    spread
+}
+
+/// Palette weight multiplier per pixel position: 1 when static, up to `1 + strength` where motion peaks
+pub fn importance(frames: &[&[u8]], width: u32, height: u32, strength: f32) -> Vec<f32> {
+    temporal_spread(frames, width, height).into_iter().map(|s| 1.0 + strength * s).collect()
+}
+
+/// `importance` scaled so its peak is 255, as imagequant's importance map wants
+pub fn importance_map(importance: &[f32]) -> Vec<u8> {
+    let peak = importance.iter().cloned().fold(f32::MIN_POSITIVE, f32::max);
+    importance.iter().map(|&w| (w / peak * 255.0).round() as u8).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moving_pixels_outweigh_static_ones() {
+        // Gray background; pixel 1 flickers between black and white, pixel 2 only a little
+        let frames: Vec<Vec<u8>> = (0..4)
+            .map(|z| {
+                let flicker = if z % 2 == 0 { 0 } else { 255 };
+                let drift = 100 + z as u8 * 10;
+                [[128, 128, 128, 255], [flicker, flicker, flicker, 255], [drift, 128, 128, 255]].concat()
+            })
+            .collect();
+        let refs: Vec<&[u8]> = frames.iter().map(|f| f.as_slice()).collect();
+
+        let spread = temporal_spread(&refs, 3, 1);
+        assert_eq!(spread[0], 0.0);
+        assert_eq!(spread[1], 1.0);
+        assert!(spread[2] > 0.0 && spread[2] < 0.2, "{spread:?}");
+
+        let weights = importance(&refs, 3, 1, 3.0);
+        assert_eq!(weights[0], 1.0);
+        assert_eq!(weights[1], 4.0);
+        assert_eq!(importance_map(&weights)[..2], [64, 255]);
+
+        // Nothing moves in a single frame
+        assert!(importance(&refs[..1], 3, 1, 3.0).iter().all(|&w| w == 1.0));
+    }
+}
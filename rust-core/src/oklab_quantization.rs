@@ -1,9 +1,13 @@
 // OKLab Color Space Quantization for Superior GIF Quality
 // Perceptually uniform color space for better gradients and skin tones
 
-use crate::{ProcessorError, Result};
+// Matrix coefficients are quoted verbatim from the OKLab reference
+#![allow(clippy::excessive_precision)]
+
+use crate::color_distance::PaletteMatcher;
+use crate::parallel::process_frames_parallel_mut;
+use crate::{DistanceMetric, ProcessorError, Result};
 use rayon::prelude::*;
-use std::collections::HashMap;
 
 /// OKLab color representation
 #[derive(Clone, Copy, Debug)]
@@ -57,23 +61,68 @@ pub fn srgb_to_oklab_batch(rgba: &[u8]) -> Vec<OklabColor> {
         .collect()
 }
 
-/// Convert OKLab back to sRGB
+/// Per-pixel sample weights from alpha (0.0 = fully transparent, 1.0 = opaque)
+pub fn alpha_weights(rgba: &[u8]) -> Vec<f32> {
+    rgba.chunks_exact(4)
+        .map(|pixel| pixel[3] as f32 / 255.0)
+        .collect()
+}
+
+/// Convert a single OKLab color to (unclamped) linear sRGB
+pub fn oklab_to_linear_srgb(color: &OklabColor) -> [f32; 3] {
+    let l_ = color.l + 0.3963377774 * color.a + 0.2158037573 * color.b;
+    let m_ = color.l - 0.1055613458 * color.a - 0.0638541728 * color.b;
+    let s_ = color.l - 0.0894841775 * color.a - 1.2914855480 * color.b;
+
+    let l_cubed = l_ * l_ * l_;
+    let m_cubed = m_ * m_ * m_;
+    let s_cubed = s_ * s_ * s_;
+
+    [
+        4.0767416621 * l_cubed - 3.3077115913 * m_cubed + 0.2309699292 * s_cubed,
+        -1.2684380046 * l_cubed + 2.6097574011 * m_cubed - 0.3413193965 * s_cubed,
+        -0.0041960863 * l_cubed - 0.7034186147 * m_cubed + 1.7076147010 * s_cubed,
+    ]
+}
+
+/// Channel overshoot still treated as in gamut, so rounding doesn't cost a color its chroma
+const GAMUT_EPSILON: f32 = 1e-4;
+
+/// Bisection steps for the in-gamut chroma, each halving the remaining error
+const GAMUT_STEPS: usize = 20;
+
+/// Convert a single OKLab color to linear sRGB inside the gamut
+///
+/// Clamping each channel on its own shifts the hue of saturated colors (an
+/// out-of-range blue turns purple), so instead the lightness is clamped and
+/// the chroma reduced at constant hue until the color reaches the gamut
+/// boundary. Gray at any lightness is inside, so the search always succeeds.
+pub fn oklab_to_linear_srgb_in_gamut(color: &OklabColor) -> [f32; 3] {
+    let in_gamut = |rgb: &[f32; 3]| rgb.iter().all(|c| (-GAMUT_EPSILON..=1.0 + GAMUT_EPSILON).contains(c));
+    let scaled = |chroma: f32| OklabColor { l: color.l.clamp(0.0, 1.0), a: color.a * chroma, b: color.b * chroma };
+
+    let mut rgb = oklab_to_linear_srgb(&scaled(1.0));
+    if !in_gamut(&rgb) {
+        let (mut inside, mut outside) = (0.0f32, 1.0f32);
+        for _ in 0..GAMUT_STEPS {
+            let chroma = (inside + outside) * 0.5;
+            if in_gamut(&oklab_to_linear_srgb(&scaled(chroma))) {
+                inside = chroma;
+            } else {
+                outside = chroma;
+            }
+        }
+        rgb = oklab_to_linear_srgb(&scaled(inside));
+    }
+    rgb.map(|c| c.clamp(0.0, 1.0))
+}
+
+/// Convert OKLab back to sRGB, gamut mapped by `oklab_to_linear_srgb_in_gamut`
 pub fn oklab_to_srgb_batch(oklab_colors: &[OklabColor]) -> Vec<u8> {
     let mut result = Vec::with_capacity(oklab_colors.len() * 4);
 
     for color in oklab_colors {
-        // Manual OKLab to linear RGB conversion
-        let l_ = color.l + 0.3963377774 * color.a + 0.2158037573 * color.b;
-        let m_ = color.l - 0.1055613458 * color.a - 0.0638541728 * color.b;
-        let s_ = color.l - 0.0894841775 * color.a - 1.2914855480 * color.b;
-
-        let l_cubed = l_ * l_ * l_;
-        let m_cubed = m_ * m_ * m_;
-        let s_cubed = s_ * s_ * s_;
-
-        let linear_r = 4.0767416621 * l_cubed - 3.3077115913 * m_cubed + 0.2309699292 * s_cubed;
-        let linear_g = -1.2684380046 * l_cubed + 2.6097574011 * m_cubed - 0.3413193965 * s_cubed;
-        let linear_b = -0.0041960863 * l_cubed - 0.7034186147 * m_cubed + 1.7076147010 * s_cubed;
+        let [linear_r, linear_g, linear_b] = oklab_to_linear_srgb_in_gamut(color);
 
         // Convert linear RGB to sRGB
         let r = if linear_r <= 0.0031308 {
@@ -92,9 +141,9 @@ pub fn oklab_to_srgb_batch(oklab_colors: &[OklabColor]) -> Vec<u8> {
             1.055 * linear_b.powf(1.0 / 2.4) - 0.055
         };
 
-        result.push((r.clamp(0.0, 1.0) * 255.0) as u8);
-        result.push((g.clamp(0.0, 1.0) * 255.0) as u8);
-        result.push((b.clamp(0.0, 1.0) * 255.0) as u8);
+        result.push((r * 255.0) as u8);
+        result.push((g * 255.0) as u8);
+        result.push((b * 255.0) as u8);
         result.push(255); // Alpha
     }
 
@@ -108,14 +157,19 @@ pub fn quantize_in_oklab(
     height: u32,
     palette_size: usize,
 ) -> Result<(Vec<u8>, Vec<[u8; 4]>)> {
+    if rgba_data.len() != (width * height * 4) as usize {
+        return Err(ProcessorError::invalid_input("oklab quantize", format!("{} bytes for a {}x{} frame", rgba_data.len(), width, height)));
+    }
+
     // Convert to OKLab
     let oklab_pixels = srgb_to_oklab_batch(rgba_data);
 
-    // Build palette using median cut in OKLab space
-    let palette = build_oklab_palette(&oklab_pixels, palette_size);
+    // Build palette using median cut in OKLab space, ignoring transparent samples
+    let weights = alpha_weights(rgba_data);
+    let palette = build_weighted_oklab_palette(&oklab_pixels, &weights, palette_size);
 
     // Map pixels to nearest palette colors
-    let indices = map_to_palette(&oklab_pixels, &palette);
+    let indices = map_to_palette(&oklab_pixels, &palette, DistanceMetric::Euclidean);
 
     // Convert palette back to sRGB
     let srgb_palette = oklab_palette_to_srgb(&palette);
@@ -125,23 +179,112 @@ pub fn quantize_in_oklab(
 
 /// Build optimal palette using median cut algorithm in OKLab space
 pub fn build_oklab_palette(pixels: &[OklabColor], target_size: usize) -> Vec<OklabColor> {
-    if pixels.is_empty() || target_size == 0 {
-        return Vec::new();
+    let weights = vec![1.0; pixels.len()];
+    build_weighted_oklab_palette(pixels, &weights, target_size)
+}
+
+/// Median cut with per-sample weights (typically alpha)
+///
+/// Zero-weight samples are dropped entirely so fully transparent pixels never
+/// claim palette entries; translucent samples pull box averages proportionally less.
+pub fn build_weighted_oklab_palette(
+    pixels: &[OklabColor],
+    weights: &[f32],
+    target_size: usize,
+) -> Vec<OklabColor> {
+    median_cut(pixels, weights, target_size, SplitSpace::Oklab)
+}
+
+/// Hue-preserving median cut in OKLCh space
+///
+/// Boxes are split along hue before lightness or chroma, so each palette entry
+/// averages colors of nearly the same hue and gradients (skin tones especially)
+/// don't drift toward neighbouring hues.
+pub fn build_weighted_oklch_palette(
+    pixels: &[OklabColor],
+    weights: &[f32],
+    target_size: usize,
+) -> Vec<OklabColor> {
+    median_cut(pixels, weights, target_size, SplitSpace::Oklch)
+}
+
+/// `build_weighted_oklab_palette`, stopping once more colors barely lower the error
+///
+/// See `adaptive_size` for where it stops; simple scenes get small color
+/// tables, which also compress better.
+pub fn build_adaptive_oklab_palette(
+    pixels: &[OklabColor],
+    weights: &[f32],
+    max_size: usize,
+) -> Vec<OklabColor> {
+    let size = adaptive_size(pixels, weights, max_size, SplitSpace::Oklab);
+    median_cut(pixels, weights, size, SplitSpace::Oklab)
+}
+
+/// `build_weighted_oklch_palette`, stopping once more colors barely lower the error
+pub fn build_adaptive_oklch_palette(
+    pixels: &[OklabColor],
+    weights: &[f32],
+    max_size: usize,
+) -> Vec<OklabColor> {
+    let size = adaptive_size(pixels, weights, max_size, SplitSpace::Oklch);
+    median_cut(pixels, weights, size, SplitSpace::Oklch)
+}
+
+/// RMS OKLab error the remaining colors may still remove before they're dropped
+///
+/// A quarter of a just-noticeable difference: past it, extra colors only fix
+/// what nobody can see.
+const ADAPTIVE_ERROR_TOLERANCE: f32 = 0.005;
+
+/// Smallest palette size within `ADAPTIVE_ERROR_TOLERANCE` of the error at `max_size`
+///
+/// Median cut adds one color per split, so a single run to `max_size`
+/// records the weighted RMS error at every size on the way; each split's
+/// marginal gain is the drop between neighbouring sizes.
+fn adaptive_size(pixels: &[OklabColor], weights: &[f32], max_size: usize, space: SplitSpace) -> usize {
+    let Some(mut boxes) = initial_box(pixels, weights, max_size, space) else {
+        return max_size;
+    };
+    let total_weight: f64 = boxes[0].samples.iter().map(|&(_, w)| w as f64).sum();
+    let rms_of = |squared_error: f64| (squared_error.max(0.0) / total_weight).sqrt() as f32;
+    let mut squared_error = boxes[0].squared_error();
+    let mut rms = vec![rms_of(squared_error)];
+
+    while boxes.len() < max_size {
+        let Some(split_idx) = next_split(&boxes) else {
+            break;
+        };
+        let parent = boxes.remove(split_idx);
+        squared_error -= parent.squared_error();
+        let (box1, box2) = parent.split();
+        squared_error += box1.squared_error() + box2.squared_error();
+        boxes.push(box1);
+        boxes.push(box2);
+        rms.push(rms_of(squared_error));
     }
 
-    // Start with all pixels in one box
-    let mut boxes = vec![ColorBox::from_pixels(pixels)];
+    let floor = rms[rms.len() - 1];
+    let size = rms.iter().position(|&e| e - floor <= ADAPTIVE_ERROR_TOLERANCE).unwrap_or(rms.len() - 1) + 1;
+    size.max(2).min(max_size)
+}
 
-    // Split boxes until we reach target palette size
-    while boxes.len() < target_size && boxes.iter().any(|b| b.can_split()) {
-        // Find box with largest volume or variance
-        let (split_idx, _) = boxes
-            .iter()
-            .enumerate()
-            .filter(|(_, b)| b.can_split())
-            .max_by_key(|(_, b)| (b.variance() * 1000.0) as u32)
-            .unwrap();
+fn median_cut(
+    pixels: &[OklabColor],
+    weights: &[f32],
+    target_size: usize,
+    space: SplitSpace,
+) -> Vec<OklabColor> {
+    // Start with all samples in one box
+    let Some(mut boxes) = initial_box(pixels, weights, target_size, space) else {
+        return Vec::new();
+    };
 
+    // Split boxes until we reach target palette size
+    while boxes.len() < target_size {
+        let Some(split_idx) = next_split(&boxes) else {
+            break;
+        };
         let box_to_split = boxes.remove(split_idx);
         let (box1, box2) = box_to_split.split();
         boxes.push(box1);
@@ -152,109 +295,200 @@ pub fn build_oklab_palette(pixels: &[OklabColor], target_size: usize) -> Vec<Okl
     boxes.into_iter().map(|b| b.average()).collect()
 }
 
+/// Every positively weighted sample in one box, or `None` if there is nothing to split
+fn initial_box(pixels: &[OklabColor], weights: &[f32], target_size: usize, space: SplitSpace) -> Option<Vec<ColorBox>> {
+    let samples: Vec<([f32; 3], f32)> = pixels
+        .iter()
+        .zip(weights)
+        .filter(|(_, &w)| w > 0.0)
+        .map(|(p, &w)| (space.to_coords(p), w))
+        .collect();
+
+    if samples.is_empty() || target_size == 0 {
+        return None;
+    }
+    Some(vec![ColorBox::from_samples(samples, space)])
+}
+
+/// The splittable box with largest volume or variance
+fn next_split(boxes: &[ColorBox]) -> Option<usize> {
+    boxes
+        .iter()
+        .enumerate()
+        .filter(|(_, b)| b.can_split())
+        .max_by_key(|(_, b)| (b.priority() * 1000.0) as u32)
+        .map(|(i, _)| i)
+}
+
+/// Chroma below which hue is considered unreliable (near-gray)
+const ACHROMATIC_CHROMA: f32 = 0.04;
+
+/// Hue spread (radians, ~6°) above which OKLCh boxes are always split on hue
+const HUE_TOLERANCE: f32 = 0.1;
+
+/// Coordinate system median cut splits in
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SplitSpace {
+    Oklab, // L, a, b
+    Oklch, // L, chroma, hue (faded toward 0 for near-grays)
+}
+
+impl SplitSpace {
+    fn to_coords(self, color: &OklabColor) -> [f32; 3] {
+        match self {
+            SplitSpace::Oklab => [color.l, color.a, color.b],
+            SplitSpace::Oklch => {
+                let chroma = (color.a * color.a + color.b * color.b).sqrt();
+                // Fade hue out for near-grays so noise in a/b doesn't drive splits
+                let confidence = (chroma / ACHROMATIC_CHROMA).min(1.0);
+                [color.l, chroma, color.b.atan2(color.a) * confidence]
+            }
+        }
+    }
+
+    fn to_oklab(self, coords: [f32; 3]) -> OklabColor {
+        match self {
+            SplitSpace::Oklab => OklabColor { l: coords[0], a: coords[1], b: coords[2] },
+            SplitSpace::Oklch => {
+                let [l, chroma, faded_hue] = coords;
+                let confidence = (chroma / ACHROMATIC_CHROMA).min(1.0);
+                let hue = if confidence > 0.0 { faded_hue / confidence } else { 0.0 };
+                OklabColor { l, a: chroma * hue.cos(), b: chroma * hue.sin() }
+            }
+        }
+    }
+}
+
 /// Color box for median cut algorithm
 struct ColorBox {
-    pixels: Vec<OklabColor>,
-    min_l: f32,
-    max_l: f32,
-    min_a: f32,
-    max_a: f32,
-    min_b: f32,
-    max_b: f32,
+    samples: Vec<([f32; 3], f32)>, // Split-space coordinates and weight
+    space: SplitSpace,
+    min: [f32; 3],
+    max: [f32; 3],
 }
 
 impl ColorBox {
-    fn from_pixels(pixels: &[OklabColor]) -> Self {
-        let mut min_l = f32::MAX;
-        let mut max_l = f32::MIN;
-        let mut min_a = f32::MAX;
-        let mut max_a = f32::MIN;
-        let mut min_b = f32::MAX;
-        let mut max_b = f32::MIN;
-
-        for p in pixels {
-            min_l = min_l.min(p.l);
-            max_l = max_l.max(p.l);
-            min_a = min_a.min(p.a);
-            max_a = max_a.max(p.a);
-            min_b = min_b.min(p.b);
-            max_b = max_b.max(p.b);
+    fn from_samples(samples: Vec<([f32; 3], f32)>, space: SplitSpace) -> Self {
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+
+        for (coords, _) in &samples {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(coords[axis]);
+                max[axis] = max[axis].max(coords[axis]);
+            }
         }
 
-        Self {
-            pixels: pixels.to_vec(),
-            min_l, max_l,
-            min_a, max_a,
-            min_b, max_b,
-        }
+        Self { samples, space, min, max }
     }
 
     fn can_split(&self) -> bool {
-        self.pixels.len() > 1
+        self.samples.len() > 1
+    }
+
+    fn ranges(&self) -> [f32; 3] {
+        [
+            self.max[0] - self.min[0],
+            self.max[1] - self.min[1],
+            self.max[2] - self.min[2],
+        ]
     }
 
     fn variance(&self) -> f32 {
-        let l_range = self.max_l - self.min_l;
-        let a_range = self.max_a - self.min_a;
-        let b_range = self.max_b - self.min_b;
+        let [l_range, x_range, y_range] = self.ranges();
 
         // Weight luminance more heavily (human vision is more sensitive to it)
-        l_range * 2.0 + a_range + b_range
+        match self.space {
+            SplitSpace::Oklab => l_range * 2.0 + x_range + y_range,
+            // Hue spread counts as arc length at the box's outer chroma
+            SplitSpace::Oklch => l_range * 2.0 + x_range + y_range * self.max[1],
+        }
+    }
+
+    fn total_weight(&self) -> f32 {
+        self.samples.iter().map(|(_, w)| w).sum()
+    }
+
+    /// Split priority: spread scaled by mean weight, so boxes of mostly
+    /// translucent edge pixels are split last
+    fn priority(&self) -> f32 {
+        self.variance() * self.total_weight() / self.samples.len() as f32
     }
 
     fn split(mut self) -> (Self, Self) {
-        // Determine longest axis
-        let l_range = self.max_l - self.min_l;
-        let a_range = self.max_a - self.min_a;
-        let b_range = self.max_b - self.min_b;
+        // Determine longest axis (OKLCh boxes must be narrow in hue first)
+        let [l_range, x_range, y_range] = self.ranges();
+        let axis = match self.space {
+            SplitSpace::Oklch if y_range > HUE_TOLERANCE => 2,
+            SplitSpace::Oklch if l_range >= x_range => 0,
+            SplitSpace::Oklch => 1,
+            SplitSpace::Oklab if l_range >= x_range && l_range >= y_range => 0,
+            SplitSpace::Oklab if x_range >= y_range => 1,
+            SplitSpace::Oklab => 2,
+        };
 
         // Sort along longest axis
-        if l_range >= a_range && l_range >= b_range {
-            self.pixels.sort_by(|a, b| a.l.partial_cmp(&b.l).unwrap());
-        } else if a_range >= b_range {
-            self.pixels.sort_by(|a, b| a.a.partial_cmp(&b.a).unwrap());
-        } else {
-            self.pixels.sort_by(|a, b| a.b.partial_cmp(&b.b).unwrap());
+        self.samples.sort_by(|a, b| a.0[axis].partial_cmp(&b.0[axis]).unwrap());
+
+        // Split at weighted median
+        let half = self.total_weight() / 2.0;
+        let mut accumulated = 0.0;
+        let mut mid = self.samples.len() / 2;
+        for (i, (_, w)) in self.samples.iter().enumerate() {
+            accumulated += w;
+            if accumulated >= half {
+                mid = i + 1;
+                break;
+            }
         }
+        let mid = mid.clamp(1, self.samples.len() - 1);
+        let second_half = self.samples.split_off(mid);
 
-        // Split at median
-        let mid = self.pixels.len() / 2;
-        let second_half = self.pixels.split_off(mid);
+        (Self::from_samples(self.samples, self.space), Self::from_samples(second_half, self.space))
+    }
 
-        (Self::from_pixels(&self.pixels), Self::from_pixels(&second_half))
+    /// Weighted squared OKLab distance of the samples from `average`
+    fn squared_error(&self) -> f64 {
+        let mean = self.average();
+        self.samples
+            .iter()
+            .map(|&(coords, w)| {
+                let c = self.space.to_oklab(coords);
+                (w * ((c.l - mean.l).powi(2) + (c.a - mean.a).powi(2) + (c.b - mean.b).powi(2))) as f64
+            })
+            .sum()
     }
 
+    /// Weighted mean, always taken in OKLab so hue wrap-around can't skew it
     fn average(&self) -> OklabColor {
-        let sum_l: f32 = self.pixels.iter().map(|p| p.l).sum();
-        let sum_a: f32 = self.pixels.iter().map(|p| p.a).sum();
-        let sum_b: f32 = self.pixels.iter().map(|p| p.b).sum();
-        let count = self.pixels.len() as f32;
+        let total = self.total_weight();
+        let mut sum = OklabColor { l: 0.0, a: 0.0, b: 0.0 };
+
+        for &(coords, w) in &self.samples {
+            let color = self.space.to_oklab(coords);
+            sum.l += color.l * w;
+            sum.a += color.a * w;
+            sum.b += color.b * w;
+        }
 
         OklabColor {
-            l: sum_l / count,
-            a: sum_a / count,
-            b: sum_b / count,
+            l: sum.l / total,
+            a: sum.a / total,
+            b: sum.b / total,
         }
     }
 }
 
 /// Map pixels to nearest palette colors
-fn map_to_palette(pixels: &[OklabColor], palette: &[OklabColor]) -> Vec<u8> {
+pub fn map_to_palette(
+    pixels: &[OklabColor],
+    palette: &[OklabColor],
+    metric: DistanceMetric,
+) -> Vec<u8> {
+    let matcher = PaletteMatcher::new(palette, metric);
     pixels
         .par_iter()
-        .map(|pixel| {
-            palette
-                .iter()
-                .enumerate()
-                .min_by_key(|(_, p)| {
-                    let dl = pixel.l - p.l;
-                    let da = pixel.a - p.a;
-                    let db = pixel.b - p.b;
-                    ((dl * dl + da * da + db * db) * 1000.0) as u32
-                })
-                .map(|(idx, _)| idx as u8)
-                .unwrap_or(0)
-        })
+        .map(|pixel| matcher.nearest(pixel) as u8)
         .collect()
 }
 
@@ -269,30 +503,111 @@ pub fn oklab_palette_to_srgb(palette: &[OklabColor]) -> Vec<[u8; 4]> {
         .collect()
 }
 
+/// Error-diffusion kernel: taps of `(dx, dy, weight)` relative to the current pixel
+///
+/// Each tap receives `weight / divisor` of the quantization error. Taps only
+/// reach forward on the current row and at most two rows down.
+pub struct DiffusionKernel {
+    pub taps: &'static [(isize, usize, f32)],
+    pub divisor: f32,
+}
+
+/// Sierra (better for animations than Floyd-Steinberg)
+/// Distributes error to fewer pixels, reducing crawling
+pub const SIERRA: DiffusionKernel = DiffusionKernel {
+    taps: &[(1, 0, 5.0), (2, 0, 3.0), (-2, 1, 2.0), (-1, 1, 4.0), (0, 1, 5.0)],
+    divisor: 32.0,
+};
+
+/// Atkinson - spreads only 6/8 of the error for the high-contrast retro-Mac look
+pub const ATKINSON: DiffusionKernel = DiffusionKernel {
+    taps: &[(1, 0, 1.0), (2, 0, 1.0), (-1, 1, 1.0), (0, 1, 1.0), (1, 1, 1.0), (0, 2, 1.0)],
+    divisor: 8.0,
+};
+
+/// Stucki - wide three-row kernel, sharper than Jarvis
+pub const STUCKI: DiffusionKernel = DiffusionKernel {
+    taps: &[
+        (1, 0, 8.0), (2, 0, 4.0),
+        (-2, 1, 2.0), (-1, 1, 4.0), (0, 1, 8.0), (1, 1, 4.0), (2, 1, 2.0),
+        (-2, 2, 1.0), (-1, 2, 2.0), (0, 2, 4.0), (1, 2, 2.0), (2, 2, 1.0),
+    ],
+    divisor: 42.0,
+};
+
+/// Jarvis, Judice & Ninke - wide three-row kernel, smoothest gradients
+pub const JARVIS: DiffusionKernel = DiffusionKernel {
+    taps: &[
+        (1, 0, 7.0), (2, 0, 5.0),
+        (-2, 1, 3.0), (-1, 1, 5.0), (0, 1, 7.0), (1, 1, 5.0), (2, 1, 3.0),
+        (-2, 2, 1.0), (-1, 2, 3.0), (0, 2, 5.0), (1, 2, 3.0), (2, 2, 1.0),
+    ],
+    divisor: 48.0,
+};
+
+/// Rows below the current one that a [`DiffusionKernel`] may reach
+const KERNEL_SPILL_ROWS: usize = 2;
+
 /// Temporal dithering for animations - reduces "crawling ants"
 pub struct TemporalDither {
     prev_error: Option<Vec<f32>>,
     frame_index: usize,
+    metric: DistanceMetric,
+    kernel: &'static DiffusionKernel,
+}
+
+impl Default for TemporalDither {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TemporalDither {
     pub fn new() -> Self {
+        Self::with_metric(DistanceMetric::Euclidean)
+    }
+
+    /// Ditherer using the given metric for its nearest-color search
+    pub fn with_metric(metric: DistanceMetric) -> Self {
         Self {
             prev_error: None,
             frame_index: 0,
+            metric,
+            kernel: &SIERRA,
         }
     }
 
+    /// Swap the default Sierra kernel for another error-diffusion kernel
+    pub fn with_kernel(mut self, kernel: &'static DiffusionKernel) -> Self {
+        self.kernel = kernel;
+        self
+    }
+
     /// Apply temporal dithering with motion compensation
+    ///
+    /// Error spreads through the configured [`DiffusionKernel`].
+    ///
+    /// `alpha` holds per-pixel weights from [`alpha_weights`]. Fully transparent
+    /// pixels map straight to `transparent_index` and neither receive nor spread
+    /// error; translucent pixels diffuse error scaled by their alpha.
+    ///
+    /// Rows are dithered in bands of [`DITHER_BAND_ROWS`]. Even bands run in
+    /// parallel first and hand the error leaving their last row to the odd band
+    /// below, which then run in parallel. Error leaving an odd band lands on an
+    /// already-dithered row, so it only carries into the next frame. Band layout
+    /// is fixed, so output does not depend on the thread count.
     pub fn apply(
         &mut self,
         pixels: &[OklabColor],
+        alpha: &[f32],
         palette: &[OklabColor],
+        transparent_index: Option<u8>,
         width: usize,
         height: usize,
     ) -> Vec<u8> {
         let mut result = vec![0u8; width * height];
         let mut errors = vec![0f32; width * height * 3]; // L, a, b components
+        let matcher = PaletteMatcher::new(palette, self.metric);
 
         // Initialize with previous frame's error if available
         if let Some(prev) = &self.prev_error {
@@ -305,81 +620,321 @@ impl TemporalDither {
             }
         }
 
-        // Apply blue noise pattern offset based on frame index
-        let pattern_offset = (self.frame_index * 17) % 64; // Prime number for good distribution
+        if width > 0 {
+            let band_pixels = DITHER_BAND_ROWS * width;
+            let (mut even, mut odd): (Vec<_>, Vec<_>) = result
+                .chunks_mut(band_pixels)
+                .zip(errors.chunks_mut(band_pixels * 3))
+                .enumerate()
+                .map(|(band, (indices, errors))| DitherBand {
+                    first_row: band * DITHER_BAND_ROWS,
+                    indices,
+                    errors,
+                    spill: vec![0f32; KERNEL_SPILL_ROWS * width * 3],
+                })
+                .partition(|band| (band.first_row / DITHER_BAND_ROWS).is_multiple_of(2));
+
+            let frame = DitherFrame {
+                pixels,
+                alpha,
+                palette,
+                matcher: &matcher,
+                kernel: self.kernel,
+                transparent_index,
+                width,
+            };
+            let run = |band: &mut DitherBand| band.dither(&frame);
+
+            process_frames_parallel_mut(&mut even, run);
+            for (upper, lower) in even.iter().zip(odd.iter_mut()) {
+                lower.receive(&upper.spill);
+            }
+
+            process_frames_parallel_mut(&mut odd, run);
+            for (upper, lower) in odd.iter().zip(even.iter_mut().skip(1)) {
+                lower.receive(&upper.spill);
+            }
+        }
+
+        // Save error for next frame
+        self.prev_error = Some(errors);
+        self.frame_index += 1;
+
+        result
+    }
+}
+
+/// Rows per band in [`TemporalDither::apply`]
+pub const DITHER_BAND_ROWS: usize = 16;
+
+/// Read-only inputs shared by every band of a frame
+struct DitherFrame<'a> {
+    pixels: &'a [OklabColor],
+    alpha: &'a [f32],
+    palette: &'a [OklabColor],
+    matcher: &'a PaletteMatcher<'a>,
+    kernel: &'a DiffusionKernel,
+    transparent_index: Option<u8>,
+    width: usize,
+}
+
+/// A horizontal strip of the frame dithered as one unit
+struct DitherBand<'a> {
+    first_row: usize,
+    indices: &'a mut [u8],
+    errors: &'a mut [f32],
+    /// Error pushed past the last row of the band, destined for the rows below
+    spill: Vec<f32>,
+}
+
+impl DitherBand<'_> {
+    fn dither(&mut self, frame: &DitherFrame) {
+        let DitherFrame { pixels, alpha, palette, matcher, kernel, transparent_index, width } = *frame;
+        let rows = self.indices.len() / width;
+        let offset = self.first_row * width;
 
-        for y in 0..height {
+        for y in 0..rows {
             for x in 0..width {
-                let idx = y * width + x;
-                let pixel = pixels[idx];
+                let local = y * width + x;
+                let pixel = pixels[offset + local];
+                let weight = alpha[offset + local];
+
+                if weight == 0.0 {
+                    if let Some(transparent) = transparent_index {
+                        self.indices[local] = transparent;
+                        continue;
+                    }
+                }
 
                 // Add error from previous pixels and frames
-                let err_idx = idx * 3;
+                let err_idx = local * 3;
                 let corrected = OklabColor {
-                    l: pixel.l + errors[err_idx] * 0.5,
-                    a: pixel.a + errors[err_idx + 1] * 0.5,
-                    b: pixel.b + errors[err_idx + 2] * 0.5,
+                    l: pixel.l + self.errors[err_idx] * 0.5,
+                    a: pixel.a + self.errors[err_idx + 1] * 0.5,
+                    b: pixel.b + self.errors[err_idx + 2] * 0.5,
                 };
 
                 // Find nearest palette color
-                let (palette_idx, nearest) = palette
-                    .iter()
-                    .enumerate()
-                    .min_by_key(|(_, p)| {
-                        let dl = corrected.l - p.l;
-                        let da = corrected.a - p.a;
-                        let db = corrected.b - p.b;
-                        ((dl * dl + da * da + db * db) * 1000.0) as u32
-                    })
-                    .map(|(idx, p)| (idx, *p))
-                    .unwrap();
-
-                result[idx] = palette_idx as u8;
+                let palette_idx = matcher.nearest(&corrected);
+                let nearest = palette[palette_idx];
+
+                self.indices[local] = palette_idx as u8;
 
                 // Calculate and distribute error
-                let err_l = pixel.l - nearest.l;
-                let err_a = pixel.a - nearest.a;
-                let err_b = pixel.b - nearest.b;
-
-                // Sierra dithering (better for animations than Floyd-Steinberg)
-                // Distributes error to fewer pixels, reducing crawling
-                if x + 1 < width {
-                    let idx = (y * width + x + 1) * 3;
-                    errors[idx] += err_l * 5.0 / 32.0;
-                    errors[idx + 1] += err_a * 5.0 / 32.0;
-                    errors[idx + 2] += err_b * 5.0 / 32.0;
-                }
-                if x + 2 < width {
-                    let idx = (y * width + x + 2) * 3;
-                    errors[idx] += err_l * 3.0 / 32.0;
-                    errors[idx + 1] += err_a * 3.0 / 32.0;
-                    errors[idx + 2] += err_b * 3.0 / 32.0;
-                }
-                if y + 1 < height {
-                    if x > 1 {
-                        let idx = ((y + 1) * width + x - 2) * 3;
-                        errors[idx] += err_l * 2.0 / 32.0;
-                        errors[idx + 1] += err_a * 2.0 / 32.0;
-                        errors[idx + 2] += err_b * 2.0 / 32.0;
-                    }
-                    if x > 0 {
-                        let idx = ((y + 1) * width + x - 1) * 3;
-                        errors[idx] += err_l * 4.0 / 32.0;
-                        errors[idx + 1] += err_a * 4.0 / 32.0;
-                        errors[idx + 2] += err_b * 4.0 / 32.0;
-                    }
-                    let idx = ((y + 1) * width + x) * 3;
-                    errors[idx] += err_l * 5.0 / 32.0;
-                    errors[idx + 1] += err_a * 5.0 / 32.0;
-                    errors[idx + 2] += err_b * 5.0 / 32.0;
+                let err = [
+                    (pixel.l - nearest.l) * weight,
+                    (pixel.a - nearest.a) * weight,
+                    (pixel.b - nearest.b) * weight,
+                ];
+
+                for &(dx, dy, tap) in kernel.taps {
+                    let Some(tx) = x.checked_add_signed(dx).filter(|&tx| tx < width) else {
+                        continue;
+                    };
+                    let ty = y + dy;
+                    let target = if ty < rows {
+                        &mut self.errors[(ty * width + tx) * 3..]
+                    } else {
+                        &mut self.spill[((ty - rows) * width + tx) * 3..]
+                    };
+                    spread(target, err, tap / kernel.divisor);
                 }
             }
         }
+    }
 
-        // Save error for next frame
-        self.prev_error = Some(errors);
-        self.frame_index += 1;
+    /// Fold error spilled from the band above into this band's first rows
+    fn receive(&mut self, spill: &[f32]) {
+        for (e, s) in self.errors.iter_mut().zip(spill) {
+            *e += s;
+        }
+    }
+}
 
-        result
+/// Add `share` of an L, a, b error triple to the first three entries
+#[inline]
+fn spread(target: &mut [f32], err: [f32; 3], share: f32) {
+    for (t, e) in target.iter_mut().zip(err) {
+        *t += e * share;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(pixels: &[[u8; 4]]) -> Vec<u8> {
+        pixels.iter().flatten().copied().collect()
+    }
+
+    #[test]
+    fn test_adaptive_palette_stops_when_colors_run_out() {
+        // Six flat colors with a little sensor noise: far fewer entries than allowed
+        let flat: Vec<[u8; 4]> = (0..6 * 400u32)
+            .map(|i| {
+                let (base, noise) = ((i / 400) as u8 * 40, (i * 7 % 3) as u8);
+                [base + noise, 200 - base, base / 2 + noise, 255]
+            })
+            .collect();
+        let data = rgba(&flat);
+        let (oklab, weights) = (srgb_to_oklab_batch(&data), alpha_weights(&data));
+        let simple = build_adaptive_oklab_palette(&oklab, &weights, 255);
+        // Median cut splits through clusters on the way, so a few times six
+        assert!(simple.len() <= 48, "{} colors for six", simple.len());
+        assert!(build_adaptive_oklch_palette(&oklab, &weights, 255).len() <= 48);
+
+        // A smooth two-axis gradient keeps earning its colors
+        let gradient: Vec<[u8; 4]> = (0..128 * 128u32).map(|i| [(i % 128 * 2) as u8, (i / 128 * 2) as u8, 96, 255]).collect();
+        let data = rgba(&gradient);
+        let (oklab, weights) = (srgb_to_oklab_batch(&data), alpha_weights(&data));
+        let rich = build_adaptive_oklab_palette(&oklab, &weights, 255);
+        assert!(rich.len() > 96, "{} colors for a gradient", rich.len());
+    }
+
+    #[test]
+    fn test_transparent_pixels_do_not_claim_palette_entries() {
+        // Opaque red and blue, plus fully transparent green "garbage"
+        let mut pixels = vec![[255, 0, 0, 255]; 8];
+        pixels.extend(vec![[0, 0, 255, 255]; 8]);
+        pixels.extend(vec![[0, 255, 0, 0]; 16]);
+        let data = rgba(&pixels);
+
+        let oklab = srgb_to_oklab_batch(&data);
+        let weights = alpha_weights(&data);
+        let palette = oklab_palette_to_srgb(&build_weighted_oklab_palette(&oklab, &weights, 4));
+
+        assert!(!palette.is_empty());
+        assert!(palette.iter().all(|c| c[1] < 64), "Transparent green leaked into palette: {palette:?}");
+    }
+
+    #[test]
+    fn test_translucent_pixels_pull_less_than_opaque() {
+        // One box: mostly-opaque black with a faint white edge
+        let mut pixels = vec![[0, 0, 0, 255]; 4];
+        pixels.extend(vec![[255, 255, 255, 16]; 4]);
+        let data = rgba(&pixels);
+
+        let oklab = srgb_to_oklab_batch(&data);
+        let weighted = build_weighted_oklab_palette(&oklab, &alpha_weights(&data), 1);
+        let unweighted = build_oklab_palette(&oklab, 1);
+
+        assert!(weighted[0].l < unweighted[0].l * 0.5);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_dither_maps_transparent_to_reserved_index() {
+        let data = rgba(&[[255, 0, 0, 255], [0, 0, 0, 0], [255, 0, 0, 128], [0, 0, 0, 0]]);
+        let oklab = srgb_to_oklab_batch(&data);
+        let weights = alpha_weights(&data);
+        let palette = build_weighted_oklab_palette(&oklab, &weights, 2);
+        let transparent = palette.len() as u8;
+
+        let indices = TemporalDither::new().apply(&oklab, &weights, &palette, Some(transparent), 2, 2);
+
+        assert_eq!(indices[1], transparent);
+        assert_eq!(indices[3], transparent);
+        assert!(indices[0] < transparent && indices[2] < transparent);
+    }
+
+    #[test]
+    fn test_band_dither_is_independent_of_thread_count() {
+        // Tall enough for several bands, with a ragged last band
+        let (width, height) = (24, DITHER_BAND_ROWS * 3 + 5);
+        let data: Vec<u8> = (0..width * height)
+            .flat_map(|i| {
+                let (x, y) = (i % width, i / width);
+                [(x * 10) as u8, (y * 4) as u8, 128, 255]
+            })
+            .collect();
+        let oklab = srgb_to_oklab_batch(&data);
+        let weights = alpha_weights(&data);
+        let palette = build_oklab_palette(&oklab, 8);
+
+        let run = |threads: usize, kernel: &'static DiffusionKernel| {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            pool.install(|| {
+                let mut dither = TemporalDither::new().with_kernel(kernel);
+                (0..3)
+                    .map(|_| dither.apply(&oklab, &weights, &palette, None, width, height))
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        for kernel in [&SIERRA, &ATKINSON, &STUCKI, &JARVIS] {
+            let serial = run(1, kernel);
+            assert_eq!(serial, run(4, kernel));
+            assert!(serial[0].iter().any(|&i| i != serial[0][0]));
+        }
+    }
+
+    #[test]
+    fn test_kernels_only_reach_unvisited_pixels() {
+        for kernel in [&SIERRA, &ATKINSON, &STUCKI, &JARVIS] {
+            let total: f32 = kernel.taps.iter().map(|&(_, _, w)| w).sum();
+            assert!(total <= kernel.divisor);
+
+            for &(dx, dy, _) in kernel.taps {
+                assert!(dy <= KERNEL_SPILL_ROWS);
+                assert!(dy > 0 || dx > 0, "tap ({dx}, {dy}) points backwards");
+            }
+        }
+    }
+
+    #[test]
+    fn test_oklch_palette_keeps_hue_of_narrow_gradient() {
+        // Skin-tone-like ramp: constant hue, varying lightness, plus a cool accent
+        let mut pixels: Vec<[u8; 4]> = (0..64)
+            .map(|i| {
+                let t = i as f32 / 63.0;
+                [(120.0 + 120.0 * t) as u8, (80.0 + 100.0 * t) as u8, (60.0 + 80.0 * t) as u8, 255]
+            })
+            .collect();
+        pixels.extend(vec![[40, 90, 200, 255]; 16]);
+        let data = rgba(&pixels);
+
+        let oklab = srgb_to_oklab_batch(&data);
+        let weights = alpha_weights(&data);
+        let palette = build_weighted_oklch_palette(&oklab, &weights, 8);
+        assert_eq!(palette.len(), 8);
+
+        let ramp_hue = oklab[32].b.atan2(oklab[32].a);
+        let warm: Vec<_> = palette.iter().filter(|c| c.a > 0.0).collect();
+        assert!(!warm.is_empty());
+        for color in warm {
+            let hue = color.b.atan2(color.a);
+            assert!((hue - ramp_hue).abs() < 0.2, "Hue drifted: {hue} vs {ramp_hue}");
+        }
+    }
+
+    #[test]
+    fn test_oklch_coords_round_trip() {
+        let colors = srgb_to_oklab_batch(&rgba(&[[200, 30, 60, 255], [128, 128, 128, 255], [10, 200, 90, 255]]));
+        for color in colors {
+            let back = SplitSpace::Oklch.to_oklab(SplitSpace::Oklch.to_coords(&color));
+            assert!((back.l - color.l).abs() < 1e-5);
+            assert!((back.a - color.a).abs() < 1e-4);
+            assert!((back.b - color.b).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_out_of_gamut_colors_keep_their_hue() {
+        // Far more saturated than sRGB can show: clamping per channel would turn this blue purple
+        let hue = |c: &OklabColor| c.b.atan2(c.a);
+        let vivid = OklabColor { l: 0.45, a: -0.05, b: -0.45 };
+        let srgb = oklab_to_srgb_batch(&[vivid]);
+        let back = srgb_to_oklab_batch(&srgb)[0];
+        assert!((hue(&back) - hue(&vivid)).abs() < 0.05, "{srgb:?}");
+        assert!((back.l - vivid.l).abs() < 0.02);
+        assert!(srgb[..3].contains(&0) || srgb[..3].contains(&255));
+
+        // Colors already inside pass through, and lightness past white is white
+        let inside = srgb_to_oklab_batch(&rgba(&[[200, 30, 60, 255], [10, 200, 90, 255]]));
+        let round_trip = oklab_to_srgb_batch(&inside);
+        for (c, expected) in round_trip.iter().zip([200, 30, 60, 255, 10, 200, 90, 255]) {
+            assert!(c.abs_diff(expected) <= 1);
+        }
+        let white = oklab_to_srgb_batch(&[OklabColor { l: 1.3, a: 0.1, b: 0.0 }]);
+        assert!(white[..3].iter().all(|&c| c >= 254 && c == white[0]), "{white:?}");
+    }
+}
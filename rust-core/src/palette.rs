@@ -0,0 +1,231 @@
+// Palette Colors
+// One color type with named packings, so no module has to guess a u32's byte order
+
+use std::slice;
+
+/// Single palette entry as straight RGBA bytes
+///
+/// Packed forms only exist at boundaries (FFI, legacy options), and each has
+/// its own constructor and accessor so the layout is spelled out where used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Color32 {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color32 {
+    pub const BLACK: Color32 = Color32::opaque(0, 0, 0);
+
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub const fn opaque(r: u8, g: u8, b: u8) -> Self {
+        Self::new(r, g, b, 255)
+    }
+
+    /// From `0xRRGGBBAA`, the layout the quantizers used to pack
+    pub const fn from_rgba_u32(packed: u32) -> Self {
+        let [r, g, b, a] = packed.to_be_bytes();
+        Self::new(r, g, b, a)
+    }
+
+    pub const fn to_rgba_u32(self) -> u32 {
+        u32::from_be_bytes([self.r, self.g, self.b, self.a])
+    }
+
+    /// From `0xAARRGGBB`, the layout of CoreGraphics and UIColor hex values
+    pub const fn from_argb_u32(packed: u32) -> Self {
+        let [a, r, g, b] = packed.to_be_bytes();
+        Self::new(r, g, b, a)
+    }
+
+    pub const fn to_argb_u32(self) -> u32 {
+        u32::from_be_bytes([self.a, self.r, self.g, self.b])
+    }
+
+    /// From `0x00RRGGBB`; the top byte is ignored and the color is opaque
+    pub const fn from_rgb_u32(packed: u32) -> Self {
+        let [_, r, g, b] = packed.to_be_bytes();
+        Self::opaque(r, g, b)
+    }
+
+    /// `0x00RRGGBB`, dropping alpha
+    pub const fn to_rgb_u32(self) -> u32 {
+        u32::from_be_bytes([0, self.r, self.g, self.b])
+    }
+
+    pub const fn rgb(self) -> [u8; 3] {
+        [self.r, self.g, self.b]
+    }
+
+    pub const fn rgba(self) -> [u8; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+impl From<[u8; 4]> for Color32 {
+    fn from([r, g, b, a]: [u8; 4]) -> Self {
+        Self::new(r, g, b, a)
+    }
+}
+
+impl From<[u8; 3]> for Color32 {
+    fn from([r, g, b]: [u8; 3]) -> Self {
+        Self::opaque(r, g, b)
+    }
+}
+
+impl From<Color32> for [u8; 4] {
+    fn from(color: Color32) -> Self {
+        color.rgba()
+    }
+}
+
+impl From<imagequant::RGBA> for Color32 {
+    fn from(c: imagequant::RGBA) -> Self {
+        Self::new(c.r, c.g, c.b, c.a)
+    }
+}
+
+/// Ordered palette entries; a pixel's index points into this list
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Palette {
+    colors: Vec<Color32>,
+}
+
+impl Palette {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<Color32> {
+        self.colors.get(index).copied()
+    }
+
+    pub fn push(&mut self, color: Color32) {
+        self.colors.push(color);
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, Color32> {
+        self.colors.iter()
+    }
+
+    pub fn colors(&self) -> &[Color32] {
+        &self.colors
+    }
+
+    /// Flat `R G B` triples, as GIF color tables and `.rgb` palette files store them
+    pub fn from_rgb_bytes(bytes: &[u8]) -> Self {
+        bytes.chunks_exact(3).map(|c| Color32::opaque(c[0], c[1], c[2])).collect()
+    }
+
+    pub fn to_rgb_bytes(&self) -> Vec<u8> {
+        self.colors.iter().flat_map(|c| c.rgb()).collect()
+    }
+
+    /// Flat `R G B A` quads, as NeuQuant's color map and RGBA frames lay them out
+    pub fn from_rgba_bytes(bytes: &[u8]) -> Self {
+        bytes.chunks_exact(4).map(|c| Color32::new(c[0], c[1], c[2], c[3])).collect()
+    }
+
+    pub fn to_rgba_bytes(&self) -> Vec<u8> {
+        self.colors.iter().flat_map(|c| c.rgba()).collect()
+    }
+
+    /// GIF color table: RGB triples padded with black to a power of two, at least 2 entries
+    pub fn to_gif_color_table(&self) -> Vec<u8> {
+        let entries = self.colors.len().max(2).next_power_of_two();
+        let mut table = self.to_rgb_bytes();
+        table.resize(entries * 3, 0);
+        table
+    }
+}
+
+impl FromIterator<Color32> for Palette {
+    fn from_iter<I: IntoIterator<Item = Color32>>(iter: I) -> Self {
+        Self { colors: iter.into_iter().collect() }
+    }
+}
+
+impl<'a> IntoIterator for &'a Palette {
+    type Item = &'a Color32;
+    type IntoIter = slice::Iter<'a, Color32>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.colors.iter()
+    }
+}
+
+impl From<Vec<Color32>> for Palette {
+    fn from(colors: Vec<Color32>) -> Self {
+        Self { colors }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Spread of packed values covering every byte position, plus the edges
+    fn samples() -> impl Iterator<Item = u32> {
+        (0..4096u32)
+            .map(|i| i.wrapping_mul(2654435761) ^ (i << 7))
+            .chain([0, u32::MAX, 0xFF000000, 0x00FF0000, 0x0000FF00, 0x000000FF])
+    }
+
+    #[test]
+    fn test_packings_round_trip() {
+        for v in samples() {
+            assert_eq!(Color32::from_rgba_u32(v).to_rgba_u32(), v);
+            assert_eq!(Color32::from_argb_u32(v).to_argb_u32(), v);
+            assert_eq!(Color32::from_rgb_u32(v).to_rgb_u32(), v & 0x00FFFFFF);
+            assert_eq!(Color32::from_rgb_u32(v).a, 255);
+
+            let color = Color32::from_rgba_u32(v);
+            assert_eq!(Color32::from(color.rgba()), color);
+            assert_eq!(Color32::from_argb_u32(color.to_argb_u32()), color);
+            assert_eq!(color.to_argb_u32(), v.rotate_right(8));
+            assert_eq!(Color32::from(color.rgb()), Color32::from_rgb_u32(color.to_rgb_u32()));
+        }
+    }
+
+    #[test]
+    fn test_channels_land_in_named_fields() {
+        let red = Color32::opaque(255, 0, 0);
+        assert_eq!(Color32::from_rgba_u32(0xFF0000FF), red);
+        assert_eq!(Color32::from_argb_u32(0xFFFF0000), red);
+        assert_eq!(Color32::from_rgb_u32(0x00FF0000), red);
+        assert_eq!(red.to_rgb_u32(), 0xFF0000);
+        assert_eq!(red.rgb(), [255, 0, 0]);
+    }
+
+    #[test]
+    fn test_palette_bytes_round_trip() {
+        let palette: Palette = samples().map(Color32::from_rgba_u32).collect();
+        assert_eq!(Palette::from_rgba_bytes(&palette.to_rgba_bytes()), palette);
+
+        let opaque: Palette = palette.iter().map(|c| Color32::from(c.rgb())).collect();
+        assert_eq!(Palette::from_rgb_bytes(&palette.to_rgb_bytes()), opaque);
+    }
+
+    #[test]
+    fn test_gif_color_table_pads_to_power_of_two() {
+        let palette: Palette = (0..5).map(|i| Color32::opaque(i, i, i)).collect();
+        let table = palette.to_gif_color_table();
+        assert_eq!(table.len(), 8 * 3);
+        assert_eq!(&table[..15], &palette.to_rgb_bytes()[..]);
+        assert!(table[15..].iter().all(|&b| b == 0));
+        assert_eq!(Palette::new().to_gif_color_table().len(), 2 * 3);
+    }
+}
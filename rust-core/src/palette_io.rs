@@ -0,0 +1,190 @@
+// Palette Files
+// GIMP .gpl, Adobe .act and JASC .pal, for sharing palettes with pixel-art tools
+
+use crate::palette::{Color32, Palette};
+use crate::{ProcessorError, Result};
+use std::path::Path;
+
+/// Palette file layouts `parse` and `write` understand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteFormat {
+    /// GIMP palette: text header, then one `R G B name` line per color
+    Gpl,
+    /// Adobe Color Table: 256 RGB triples, optionally followed by count and transparent index
+    Act,
+    /// JASC (Paint Shop Pro) palette: `JASC-PAL`, version, count, then `R G B` lines
+    Pal,
+}
+
+impl PaletteFormat {
+    /// Format for a file name's extension (case-insensitive)
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "gpl" => Some(PaletteFormat::Gpl),
+            "act" => Some(PaletteFormat::Act),
+            "pal" => Some(PaletteFormat::Pal),
+            _ => None,
+        }
+    }
+}
+
+/// ACT files hold exactly this many slots; shorter palettes are padded with black
+const ACT_SLOTS: usize = 256;
+
+pub fn parse(data: &[u8], format: PaletteFormat) -> Result<Palette> {
+    match format {
+        PaletteFormat::Gpl => parse_gpl(data),
+        PaletteFormat::Act => parse_act(data),
+        PaletteFormat::Pal => parse_pal(data),
+    }
+}
+
+/// Serialize `palette`; `name` only appears in GPL files
+///
+/// Alpha is dropped, as none of these formats store it. ACT fails above 256 colors.
+pub fn write(palette: &Palette, format: PaletteFormat, name: &str) -> Result<Vec<u8>> {
+    match format {
+        PaletteFormat::Gpl => {
+            let mut text = format!("GIMP Palette\nName: {}\nColumns: 16\n#\n", name);
+            for (i, c) in palette.iter().enumerate() {
+                text.push_str(&format!("{:3} {:3} {:3}\tIndex {}\n", c.r, c.g, c.b, i));
+            }
+            Ok(text.into_bytes())
+        }
+        PaletteFormat::Act => {
+            if palette.len() > ACT_SLOTS {
+                return Err(ProcessorError::invalid_input("palette file", format!("ACT holds at most 256 colors, not {}", palette.len())));
+            }
+            let mut data = palette.to_rgb_bytes();
+            data.resize(ACT_SLOTS * 3, 0);
+            data.extend_from_slice(&(palette.len() as u16).to_be_bytes());
+            data.extend_from_slice(&0xFFFFu16.to_be_bytes()); // No transparent index
+            Ok(data)
+        }
+        PaletteFormat::Pal => {
+            let mut text = format!("JASC-PAL\r\n0100\r\n{}\r\n", palette.len());
+            for c in palette {
+                text.push_str(&format!("{} {} {}\r\n", c.r, c.g, c.b));
+            }
+            Ok(text.into_bytes())
+        }
+    }
+}
+
+fn parse_gpl(data: &[u8]) -> Result<Palette> {
+    let text = as_text(data)?;
+    let mut lines = text.lines();
+    if lines.next().map(str::trim) != Some("GIMP Palette") {
+        return Err(invalid("missing \"GIMP Palette\" header"));
+    }
+
+    lines
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with("Name:") && !line.starts_with("Columns:"))
+        .map(|line| parse_rgb(line.split_whitespace()).ok_or_else(|| invalid(format!("bad color line {:?}", line))))
+        .collect()
+}
+
+fn parse_act(data: &[u8]) -> Result<Palette> {
+    let count = match data.len() {
+        768 => ACT_SLOTS,
+        // A count of 0 or past the end means all 256 slots, as Photoshop reads it
+        772 => match u16::from_be_bytes([data[768], data[769]]) as usize {
+            0 => ACT_SLOTS,
+            n => n.min(ACT_SLOTS),
+        },
+        n => return Err(invalid(format!("ACT files are 768 or 772 bytes, not {}", n))),
+    };
+    Ok(Palette::from_rgb_bytes(&data[..count * 3]))
+}
+
+fn parse_pal(data: &[u8]) -> Result<Palette> {
+    if data.starts_with(b"RIFF") {
+        return Err(invalid("RIFF palettes are not supported, only JASC-PAL"));
+    }
+    let text = as_text(data)?;
+    let mut lines = text.lines().map(str::trim);
+    if lines.next() != Some("JASC-PAL") {
+        return Err(invalid("missing \"JASC-PAL\" header"));
+    }
+    let _version = lines.next();
+    let count: usize = lines
+        .next()
+        .and_then(|line| line.parse().ok())
+        .ok_or_else(|| invalid("missing color count"))?;
+
+    let palette: Palette = lines
+        .filter(|line| !line.is_empty())
+        .take(count)
+        .map(|line| parse_rgb(line.split_whitespace()).ok_or_else(|| invalid(format!("bad color line {:?}", line))))
+        .collect::<Result<_>>()?;
+    if palette.len() != count {
+        return Err(invalid(format!("header promises {} colors, file has {}", count, palette.len())));
+    }
+    Ok(palette)
+}
+
+/// First three fields as 0–255 channel values
+fn parse_rgb<'a>(mut fields: impl Iterator<Item = &'a str>) -> Option<Color32> {
+    let mut channel = || fields.next()?.parse::<u8>().ok();
+    Some(Color32::opaque(channel()?, channel()?, channel()?))
+}
+
+fn as_text(data: &[u8]) -> Result<&str> {
+    std::str::from_utf8(data).map_err(|_| invalid("not a text file"))
+}
+
+fn invalid(reason: impl std::fmt::Display) -> ProcessorError {
+    ProcessorError::invalid_input("palette file", reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Palette {
+        (0..20u8).map(|i| Color32::opaque(i * 12, 255 - i, i ^ 0x5A)).collect()
+    }
+
+    #[test]
+    fn test_formats_round_trip() {
+        for format in [PaletteFormat::Gpl, PaletteFormat::Act, PaletteFormat::Pal] {
+            let data = write(&sample(), format, "Sample").unwrap();
+            assert_eq!(parse(&data, format).unwrap(), sample(), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_reads_files_from_other_tools() {
+        let gpl = b"GIMP Palette\nName: Two\nColumns: 2\n# comment\n255   0   0\tRed\n  0 0 255 Blue\n";
+        let two: Palette = [Color32::opaque(255, 0, 0), Color32::opaque(0, 0, 255)].into_iter().collect();
+        assert_eq!(parse(gpl, PaletteFormat::Gpl).unwrap(), two);
+
+        let pal = b"JASC-PAL\n0100\n2\n255 0 0\n0 0 255\n";
+        assert_eq!(parse(pal, PaletteFormat::Pal).unwrap(), two);
+
+        // Plain 768-byte ACT has all 256 slots
+        assert_eq!(parse(&[7; 768], PaletteFormat::Act).unwrap().len(), 256);
+    }
+
+    #[test]
+    fn test_rejects_malformed_files() {
+        assert!(parse(b"Name: x\n1 2 3\n", PaletteFormat::Gpl).is_err());
+        assert!(parse(b"GIMP Palette\n1 2 300\n", PaletteFormat::Gpl).is_err());
+        assert!(parse(b"JASC-PAL\n0100\n3\n1 2 3\n", PaletteFormat::Pal).is_err());
+        assert!(parse(b"RIFF\0\0\0\0PAL data", PaletteFormat::Pal).is_err());
+        assert!(parse(&[0; 100], PaletteFormat::Act).is_err());
+
+        let big: Palette = (0..300u32).map(Color32::from_rgb_u32).collect();
+        assert!(write(&big, PaletteFormat::Act, "").is_err());
+    }
+
+    #[test]
+    fn test_format_from_extension() {
+        assert_eq!(PaletteFormat::from_path("art/NES.GPL"), Some(PaletteFormat::Gpl));
+        assert_eq!(PaletteFormat::from_path("x.act"), Some(PaletteFormat::Act));
+        assert_eq!(PaletteFormat::from_path("x.pal"), Some(PaletteFormat::Pal));
+        assert_eq!(PaletteFormat::from_path("x.rgb"), None);
+    }
+}
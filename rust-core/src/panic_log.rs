@@ -0,0 +1,128 @@
+// Panic Log
+// Keeps recent panic reports in memory, and optionally on disk, for hosts that only see a crash
+
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::sync::{Mutex, Once};
+
+/// Reports kept in memory; older ones are dropped
+const CAPACITY: usize = 8;
+
+/// Longest report kept, so a deep backtrace can't hog memory or the log file
+const MAX_REPORT_BYTES: usize = 16 * 1024;
+
+static INSTALL: Once = Once::new();
+static REPORTS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+static LOG_PATH: Mutex<Option<String>> = Mutex::new(None);
+
+/// Record every panic from here on, then hand it to the hook that was set before
+///
+/// Installs once per process; later calls only change `log_path`. With a path,
+/// each report also overwrites that file, so a panic that takes the process
+/// down (one crossing a C boundary aborts) can be read back on the next launch.
+pub fn install(log_path: Option<String>) {
+    if let Ok(mut path) = LOG_PATH.lock() {
+        *path = log_path;
+    }
+    ensure_installed();
+}
+
+/// `install` without touching the log path; cheap enough for every entry point
+pub fn ensure_installed() {
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let thread = std::thread::current();
+            let mut report = format!(
+                "thread '{}' {}\n{}",
+                thread.name().unwrap_or("<unnamed>"),
+                info,
+                Backtrace::force_capture()
+            );
+            truncate(&mut report);
+            record(report);
+            previous(info);
+        }));
+    });
+}
+
+/// The latest report, from this run or else the log file a previous run left
+pub fn last() -> Option<String> {
+    let latest = REPORTS.lock().ok().and_then(|reports| reports.back().cloned());
+    latest.or_else(|| {
+        let path = LOG_PATH.lock().ok()?.clone()?;
+        std::fs::read_to_string(path).ok().filter(|report| !report.is_empty())
+    })
+}
+
+/// Forget the reports in memory and delete the log file
+pub fn clear() {
+    if let Ok(mut reports) = REPORTS.lock() {
+        reports.clear();
+    }
+    if let Some(path) = LOG_PATH.lock().ok().and_then(|path| path.clone()) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+fn record(report: String) {
+    // A panic while another thread holds a lock must not deadlock or panic again
+    if let Some(path) = LOG_PATH.try_lock().ok().and_then(|path| path.clone()) {
+        let _ = std::fs::write(path, &report);
+    }
+    if let Ok(mut reports) = REPORTS.try_lock() {
+        if reports.len() == CAPACITY {
+            reports.pop_front();
+        }
+        reports.push_back(report);
+    }
+}
+
+fn truncate(report: &mut String) {
+    if report.len() > MAX_REPORT_BYTES {
+        let mut end = MAX_REPORT_BYTES;
+        while !report.is_char_boundary(end) {
+            end -= 1;
+        }
+        report.truncate(end);
+        report.push_str("\n[truncated]");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panics_are_recorded_and_persisted() {
+        let path = std::env::temp_dir().join(format!("rgb2gif-panic-{}.log", std::process::id()));
+        install(Some(path.to_string_lossy().into_owned()));
+
+        let caught = std::thread::Builder::new()
+            .name("encoder".into())
+            .spawn(|| panic!("palette slot {} out of range", 300))
+            .unwrap()
+            .join();
+        assert!(caught.is_err());
+
+        let report = last().unwrap();
+        assert!(report.contains("thread 'encoder'"), "{report}");
+        assert!(report.contains("palette slot 300 out of range"), "{report}");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), report);
+
+        // A fresh process only has the file; cleared, there is nothing left
+        REPORTS.lock().unwrap().clear();
+        assert_eq!(last(), Some(report));
+        clear();
+        assert_eq!(last(), None);
+        install(None);
+    }
+
+    #[test]
+    fn test_long_reports_are_truncated() {
+        let mut report = "é".repeat(MAX_REPORT_BYTES);
+        truncate(&mut report);
+        assert!(report.len() <= MAX_REPORT_BYTES + 12);
+        assert!(report.ends_with("[truncated]"));
+    }
+}
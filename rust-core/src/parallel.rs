@@ -168,7 +168,7 @@ impl BatchProcessor {
             .collect::<Vec<_>>()
             .into_par_iter()
             .with_max_len(self.max_parallel)
-            .flat_map(|batch| processor(batch))
+            .flat_map(&processor)
             .collect()
     }
 }
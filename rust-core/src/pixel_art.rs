@@ -0,0 +1,120 @@
+// Pixel-Art Scaling
+// Grid detection and integer-factor nearest-neighbor downscaling that keeps art pixels crisp
+
+/// Largest grid `detect_grid` will report
+const MAX_GRID: u32 = 64;
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Source pixels per art pixel, found from the runs of identical pixels
+///
+/// An art pixel scaled up by `g` shows up as runs whose lengths are all
+/// multiples of `g`, so the GCD of every run recovers it. Runs touching an
+/// edge are skipped since the frame may cut an art pixel short. Returns 1
+/// when nothing points to a grid.
+pub fn detect_grid(frame: &[u8], width: u32, height: u32) -> u32 {
+    let (w, h) = (width as usize, height as usize);
+    let same = |a: usize, b: usize| frame[a * 4..a * 4 + 4] == frame[b * 4..b * 4 + 4];
+    let mut grid = 0;
+
+    // Rows then columns, as (first pixel, stride, length)
+    let lines = (0..h).map(|y| (y * w, 1, w)).chain((0..w).map(|x| (x, w, h)));
+    for (first, stride, len) in lines {
+        let mut run_start = 0;
+        for i in 1..=len {
+            if i == len || !same(first + i * stride, first + run_start * stride) {
+                if run_start > 0 && i < len {
+                    grid = gcd(grid, (i - run_start) as u32);
+                }
+                run_start = i;
+            }
+        }
+    }
+
+    if grid == 0 || grid > MAX_GRID { 1 } else { grid }
+}
+
+/// Integer downscale factor that fits `width`×`height` within `max_width`×`max_height`
+///
+/// Divisors of `grid` come first, since they map every art pixel onto the
+/// same number of output pixels. When even `grid` is too large, multiples of
+/// it drop whole art pixels rather than blending them.
+pub fn choose_factor(width: u32, height: u32, grid: u32, max_width: u32, max_height: u32) -> u32 {
+    let grid = grid.max(1);
+    let fits = |f: u32| width / f <= max_width && height / f <= max_height;
+    (1..=grid)
+        .filter(|&f| grid.is_multiple_of(f))
+        .chain((2..).map(|k| k * grid))
+        .find(|&f| fits(f) || f >= width.max(height))
+        .unwrap_or(grid)
+}
+
+/// Keep the centre pixel of every `factor`×`factor` block
+///
+/// Output is `width / factor` × `height / factor`; a partial block at the
+/// right or bottom edge is dropped.
+pub fn downscale_nearest(src: &[u8], width: u32, height: u32, factor: u32, out: &mut Vec<u8>) -> (u32, u32) {
+    let factor = factor.max(1) as usize;
+    let (w, h) = (width as usize, height as usize);
+    let (out_w, out_h) = ((w / factor).max(1), (h / factor).max(1));
+    let offset = factor / 2;
+
+    out.clear();
+    out.reserve(out_w * out_h * 4);
+    for y in 0..out_h {
+        let sy = (y * factor + offset).min(h - 1);
+        for x in 0..out_w {
+            let sx = (x * factor + offset).min(w - 1);
+            let i = (sy * w + sx) * 4;
+            out.extend_from_slice(&src[i..i + 4]);
+        }
+    }
+    (out_w as u32, out_h as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 4×3 art upscaled by `scale`
+    fn upscaled_art(scale: u32) -> (Vec<u8>, u32, u32) {
+        let art = [[1u8, 2, 3, 1], [2, 2, 1, 3], [3, 1, 1, 2]];
+        let (w, h) = (4 * scale, 3 * scale);
+        let frame = (0..w * h)
+            .flat_map(|i| {
+                let v = art[(i / w / scale) as usize][(i % w / scale) as usize] * 60;
+                [v, 255 - v, v / 2, 255]
+            })
+            .collect();
+        (frame, w, h)
+    }
+
+    #[test]
+    fn test_detects_grid() {
+        for scale in [1, 3, 8] {
+            let (frame, w, h) = upscaled_art(scale);
+            assert_eq!(detect_grid(&frame, w, h), scale);
+        }
+        assert_eq!(detect_grid(&[7; 16 * 4], 4, 4), 1);
+    }
+
+    #[test]
+    fn test_factor_prefers_grid_divisors() {
+        // 64×48 on an 8px grid
+        assert_eq!(choose_factor(64, 48, 8, 64, 64), 1);
+        assert_eq!(choose_factor(64, 48, 8, 20, 20), 4);
+        assert_eq!(choose_factor(64, 48, 8, 5, 5), 16);
+        assert_eq!(choose_factor(30, 30, 1, 10, 10), 3);
+    }
+
+    #[test]
+    fn test_downscale_recovers_art() {
+        let (frame, w, h) = upscaled_art(5);
+        let (art, _, _) = upscaled_art(1);
+        let mut out = Vec::new();
+        assert_eq!(downscale_nearest(&frame, w, h, 5, &mut out), (4, 3));
+        assert_eq!(out, art);
+    }
+}
@@ -0,0 +1,288 @@
+// GIF Playback
+// Frame-accurate random access into an encoded GIF, decoding only the frames asked for
+
+use crate::backdrop;
+use crate::{Backdrop, ProcessorError, Result};
+use gif::streaming_decoder::FrameDecoder;
+use gif::{ColorOutput, DecodeOptions, DisposalMethod, Frame};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Upper bound on cached canvases
+const MAX_CACHED_FRAMES: usize = 256;
+
+struct PlayerState {
+    decoder: FrameDecoder,
+    cache: VecDeque<(usize, Vec<u8>)>, // (frame index, composited canvas), most recent last
+}
+
+/// Decoded view of a GIF for scrubbing
+///
+/// Opening only walks the block structure and keeps each frame LZW-compressed.
+/// `frame_rgba` composites the requested frame onto the canvas the way a
+/// viewer would (offsets, transparency, disposal), decoding from the nearest
+/// cached canvas before it. The last `cache_frames` canvases are kept, so
+/// scrubbing back and forth over a short range decodes nothing.
+pub struct GifPlayer {
+    width: u32,
+    height: u32,
+    global_palette: Option<Vec<u8>>,
+    frames: Vec<Frame<'static>>,
+    cache_frames: usize,
+    state: Mutex<PlayerState>,
+}
+
+impl GifPlayer {
+    pub fn new(gif_data: Vec<u8>, cache_frames: u32) -> Result<Self> {
+        let mut options = DecodeOptions::new();
+        options.set_color_output(ColorOutput::Indexed);
+        options.skip_frame_decoding(true);
+        let mut decoder = options
+            .read_info(gif_data.as_slice())
+            .map_err(|e| ProcessorError::invalid_input("player", e))?;
+
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().map_err(|e| ProcessorError::invalid_input("player", e).at_frame(frames.len()))? {
+            frames.push(frame.clone());
+        }
+        if frames.is_empty() {
+            return Err(ProcessorError::invalid_input("player", "GIF has no frames"));
+        }
+
+        let mut options = DecodeOptions::new();
+        options.set_color_output(ColorOutput::Indexed);
+        Ok(Self {
+            width: decoder.width() as u32,
+            height: decoder.height() as u32,
+            global_palette: decoder.global_palette().map(|p| p.to_vec()),
+            frames,
+            cache_frames: (cache_frames as usize).clamp(1, MAX_CACHED_FRAMES),
+            state: Mutex::new(PlayerState {
+                decoder: FrameDecoder::new(options),
+                cache: VecDeque::new(),
+            }),
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.frames.len() as u32
+    }
+
+    /// Per-frame delays in centiseconds, as stored in the file
+    pub fn frame_delays(&self) -> Vec<u16> {
+        self.frames.iter().map(|f| f.delay).collect()
+    }
+
+    /// Frame showing `time_ms` into playback, looping past the end
+    pub fn frame_at_time(&self, time_ms: u64) -> u32 {
+        let total: u64 = self.frames.iter().map(|f| f.delay as u64 * 10).sum();
+        if total == 0 {
+            return 0;
+        }
+        let mut remaining = time_ms % total;
+        for (index, frame) in self.frames.iter().enumerate() {
+            let duration = frame.delay as u64 * 10;
+            if remaining < duration {
+                return index as u32;
+            }
+            remaining -= duration;
+        }
+        self.frame_count() - 1
+    }
+
+    /// Canvas-sized RGBA of frame `index` as a viewer would show it
+    pub fn frame_rgba(&self, index: u32) -> Result<Vec<u8>> {
+        let index = index as usize;
+        if index >= self.frames.len() {
+            return Err(ProcessorError::invalid_input("player", format!("GIF has {} frames", self.frames.len())).at_frame(index));
+        }
+        let mut state = self.state.lock().map_err(|e| ProcessorError::memory("player", e))?;
+
+        if let Some(position) = state.cache.iter().position(|(cached, _)| *cached == index) {
+            let entry = state.cache.remove(position).unwrap_or_default();
+            let canvas = entry.1.clone();
+            state.cache.push_back(entry);
+            return Ok(canvas);
+        }
+
+        // Resume from the latest cached canvas before `index` that can be built on;
+        // restore-to-previous needs the canvas under it, which isn't cached
+        let start = state
+            .cache
+            .iter()
+            .filter(|(cached, _)| *cached < index && self.frames[*cached].dispose != DisposalMethod::Previous)
+            .max_by_key(|(cached, _)| *cached)
+            .map(|(cached, canvas)| (*cached, canvas.clone()));
+        let (mut next, mut base) = match start {
+            Some((cached, canvas)) => (cached + 1, self.dispose(cached, canvas, None)),
+            None => (0, vec![0; (self.width * self.height * 4) as usize]),
+        };
+
+        loop {
+            let mut canvas = base.clone();
+            self.draw(&mut state.decoder, next, &mut canvas)?;
+            self.remember(&mut state.cache, next, canvas.clone());
+            if next == index {
+                return Ok(canvas);
+            }
+            base = self.dispose(next, canvas, Some(base));
+            next += 1;
+        }
+    }
+
+    /// `frame_rgba` composited over `backdrop`, opaque and ready to display
+    pub fn frame_preview(&self, index: u32, backdrop: Backdrop) -> Result<Vec<u8>> {
+        let mut canvas = self.frame_rgba(index)?;
+        backdrop::composite(&mut canvas, self.width, &backdrop);
+        Ok(canvas)
+    }
+
+    /// Drop every cached canvas
+    pub fn clear_cache(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.cache.clear();
+        }
+    }
+
+    fn remember(&self, cache: &mut VecDeque<(usize, Vec<u8>)>, index: usize, canvas: Vec<u8>) {
+        if cache.len() == self.cache_frames {
+            cache.pop_front();
+        }
+        cache.push_back((index, canvas));
+    }
+
+    /// Canvas the frame after `index` is drawn onto
+    ///
+    /// `under` is the canvas `index` was drawn onto, needed only for
+    /// restore-to-previous; without it that frame is left in place.
+    fn dispose(&self, index: usize, mut canvas: Vec<u8>, under: Option<Vec<u8>>) -> Vec<u8> {
+        let frame = &self.frames[index];
+        match (frame.dispose, under) {
+            (DisposalMethod::Previous, Some(under)) => under,
+            (DisposalMethod::Background, _) => {
+                for (x, y) in self.frame_pixels(frame) {
+                    let at = ((y * self.width + x) * 4) as usize;
+                    canvas[at..at + 4].fill(0);
+                }
+                canvas
+            }
+            _ => canvas,
+        }
+    }
+
+    /// Canvas coordinates covered by `frame`, row-major, clipped to the canvas
+    fn frame_pixels<'a>(&'a self, frame: &'a Frame<'static>) -> impl Iterator<Item = (u32, u32)> + 'a {
+        let (left, top) = (frame.left as u32, frame.top as u32);
+        (0..frame.height as u32)
+            .flat_map(move |y| (0..frame.width as u32).map(move |x| (left + x, top + y)))
+            .filter(move |&(x, y)| x < self.width && y < self.height)
+    }
+
+    /// Decode frame `index` and paint its opaque pixels over `canvas`
+    fn draw(&self, decoder: &mut FrameDecoder, index: usize, canvas: &mut [u8]) -> Result<()> {
+        let frame = &self.frames[index];
+        let palette = frame
+            .palette
+            .as_deref()
+            .or(self.global_palette.as_deref())
+            .ok_or_else(|| ProcessorError::invalid_input("player", "frame has no color table").at_frame(index))?;
+
+        let mut indices = vec![0; frame.width as usize * frame.height as usize];
+        decoder
+            .decode_lzw_encoded_frame_into_buffer(frame, &mut indices)
+            .map_err(|e| ProcessorError::invalid_input("player", e).at_frame(index))?;
+
+        let (left, top) = (frame.left as u32, frame.top as u32);
+        for (i, &color) in indices.iter().enumerate() {
+            let (x, y) = (left + i as u32 % frame.width as u32, top + i as u32 / frame.width as u32);
+            if Some(color) == frame.transparent || x >= self.width || y >= self.height {
+                continue;
+            }
+            let rgb = palette.get(color as usize * 3..color as usize * 3 + 3).unwrap_or(&[0, 0, 0]);
+            let at = ((y * self.width + x) * 4) as usize;
+            canvas[at..at + 3].copy_from_slice(rgb);
+            canvas[at + 3] = 255;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gif::{Encoder, Repeat};
+
+    /// 4×4 clip: full red frame, then a 2×2 blue patch per frame with the given disposal
+    fn sample_gif(dispose: DisposalMethod) -> Vec<u8> {
+        let palette = [255, 0, 0, 0, 0, 255, 0, 0, 0];
+        let mut out = Vec::new();
+        {
+            let mut encoder = Encoder::new(&mut out, 4, 4, &palette).unwrap();
+            encoder.set_repeat(Repeat::Infinite).unwrap();
+            encoder
+                .write_frame(&Frame { width: 4, height: 4, delay: 5, buffer: vec![0; 16].into(), ..Default::default() })
+                .unwrap();
+            for (left, top) in [(0, 0), (2, 2)] {
+                let patch = Frame {
+                    left,
+                    top,
+                    width: 2,
+                    height: 2,
+                    delay: 10,
+                    dispose,
+                    buffer: vec![1; 4].into(),
+                    ..Default::default()
+                };
+                encoder.write_frame(&patch).unwrap();
+            }
+        }
+        out
+    }
+
+    fn color_at(canvas: &[u8], x: usize, y: usize) -> [u8; 4] {
+        let at = (y * 4 + x) * 4;
+        [canvas[at], canvas[at + 1], canvas[at + 2], canvas[at + 3]]
+    }
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    #[test]
+    fn test_metadata_and_time_lookup() {
+        let player = GifPlayer::new(sample_gif(DisposalMethod::Keep), 4).unwrap();
+        assert_eq!((player.width(), player.height(), player.frame_count()), (4, 4, 3));
+        assert_eq!(player.frame_delays(), vec![5, 10, 10]);
+        assert_eq!(player.frame_at_time(49), 0);
+        assert_eq!(player.frame_at_time(50), 1);
+        assert_eq!(player.frame_at_time(249), 2);
+        assert_eq!(player.frame_at_time(250), 0);
+        assert!(player.frame_rgba(3).is_err());
+        assert!(GifPlayer::new(vec![1, 2, 3], 4).is_err());
+    }
+
+    #[test]
+    fn test_composites_with_disposal_in_any_order() {
+        let keep = GifPlayer::new(sample_gif(DisposalMethod::Keep), 1).unwrap();
+        let last = keep.frame_rgba(2).unwrap();
+        assert_eq!((color_at(&last, 0, 0), color_at(&last, 3, 3), color_at(&last, 3, 0)), (BLUE, BLUE, RED));
+        // Seeking backwards past the single cached canvas re-decodes from the start
+        assert_eq!(color_at(&keep.frame_rgba(0).unwrap(), 0, 0), RED);
+
+        let previous = GifPlayer::new(sample_gif(DisposalMethod::Previous), 2).unwrap();
+        let last = previous.frame_rgba(2).unwrap();
+        assert_eq!((color_at(&last, 0, 0), color_at(&last, 3, 3)), (RED, BLUE));
+
+        let background = GifPlayer::new(sample_gif(DisposalMethod::Background), 2).unwrap();
+        background.frame_rgba(1).unwrap();
+        let last = background.frame_rgba(2).unwrap();
+        assert_eq!((color_at(&last, 0, 0)[3], color_at(&last, 3, 3)), (0, BLUE));
+    }
+}
@@ -0,0 +1,73 @@
+// Posterize
+// Per-channel bit-depth reduction before quantization, through one lookup table per channel
+
+use crate::PreprocessOpts;
+
+/// Lookup tables that cut each color channel to a few bits
+///
+/// Levels are spread over the full 0-255 range, so black and white survive
+/// and 5-6-5 reproduces the 16-bit display look. Alpha is left alone.
+pub struct Posterize {
+    tables: [[u8; 256]; 3],
+}
+
+impl Posterize {
+    /// Tables for `opts`, or `None` when every channel keeps all 8 bits
+    pub fn new(opts: &PreprocessOpts) -> Option<Self> {
+        let bits = [opts.posterize_red_bits, opts.posterize_green_bits, opts.posterize_blue_bits];
+        if bits.iter().all(|&b| b >= 8) {
+            return None;
+        }
+        Some(Self { tables: bits.map(table) })
+    }
+
+    pub fn apply(&self, frame: &mut [u8]) {
+        for px in frame.chunks_exact_mut(4) {
+            for (c, table) in px.iter_mut().zip(&self.tables) {
+                *c = table[*c as usize];
+            }
+        }
+    }
+}
+
+/// Nearest of `2^bits` evenly spaced levels for every 8-bit value
+fn table(bits: u8) -> [u8; 256] {
+    let top = ((1u32 << bits.clamp(1, 8)) - 1) as f32;
+    core::array::from_fn(|v| ((v as f32 * top / 255.0).round() * 255.0 / top).round() as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opts(red: u8, green: u8, blue: u8) -> PreprocessOpts {
+        PreprocessOpts { posterize_red_bits: red, posterize_green_bits: green, posterize_blue_bits: blue }
+    }
+
+    #[test]
+    fn test_565_keeps_32_64_32_levels_and_the_extremes() {
+        let posterize = Posterize::new(&opts(5, 6, 5)).unwrap();
+        let mut frame: Vec<u8> = (0..=255u8).flat_map(|v| [v, v, v, v]).collect();
+        posterize.apply(&mut frame);
+
+        for (channel, levels) in [(0, 32), (1, 64), (2, 32)] {
+            let mut seen: Vec<u8> = frame.chunks_exact(4).map(|px| px[channel]).collect();
+            seen.dedup();
+            assert_eq!(seen.len(), levels);
+            assert_eq!((seen[0], seen[levels - 1]), (0, 255));
+        }
+        assert!(frame.chunks_exact(4).enumerate().all(|(v, px)| px[3] == v as u8));
+    }
+
+    #[test]
+    fn test_levels_are_stable_and_nearest() {
+        let t = table(2);
+        assert_eq!(t.iter().copied().collect::<std::collections::BTreeSet<_>>().into_iter().collect::<Vec<_>>(), [0, 85, 170, 255]);
+        assert_eq!((t[42], t[43], t[200]), (0, 85, 170));
+        // Reapplying changes nothing, so noise can't walk between levels
+        assert!(t.iter().all(|&v| t[v as usize] == v));
+        assert_eq!(table(8), core::array::from_fn(|v| v as u8));
+
+        assert!(Posterize::new(&opts(8, 8, 8)).is_none());
+    }
+}
@@ -0,0 +1,253 @@
+// Export Pre-flight
+// Everything `process_all_frames` would check and allocate, worked out from the settings alone
+
+use crate::{
+    calculate_buffer_size, filters, posterize, text_art, thermal, validation, GifOpts, OversizePolicy, ProcessorError, QuantizeOpts,
+    Result, ScaleMode, TensorMode, ValidationReport,
+};
+
+/// Screen descriptor, a full palette and the loop extension, plus room for the metadata and integrity extensions
+const HEADER_BYTES: u64 = 13 + 768 + 19 + 4096;
+
+/// Color-space copy and alpha weight per pixel while building the palette and remapping
+const QUANTIZE_BYTES_PER_PIXEL: u64 = 16;
+
+/// Pre-flight `width`×`height`×`frame_count` input with these settings
+///
+/// `error` is the first problem `process_all_frames` would stop on; the
+/// projections are still filled in from the settings as given. Sizes follow
+/// the capped output, so PixelArt's smaller integer downscale and motion
+/// cropping only lower them.
+pub fn report(width: u32, height: u32, frame_count: u32, quantize_opts: &QuantizeOpts, gif_opts: &GifOpts) -> ValidationReport {
+    let input_bytes = calculate_buffer_size(width, height, frame_count);
+    let error = check(input_bytes, width, height, frame_count, quantize_opts, gif_opts).err();
+    let error_field = match &error {
+        Some(ProcessorError::InvalidOption { field, .. }) => Some(field.clone()),
+        _ => None,
+    };
+
+    let mut warnings = Vec::new();
+    let (out_width, out_height) = capped(gif_opts.width, gif_opts.height, gif_opts.oversize);
+    if (out_width, out_height) != (gif_opts.width, gif_opts.height) {
+        warnings.push(format!(
+            "{}x{} is over {} a side and will be downscaled to {}x{}",
+            gif_opts.width, gif_opts.height, validation::MAX_GIF_SIDE, out_width, out_height
+        ));
+    }
+    let (source_width, source_height) = gif_opts.roi.map_or((width, height), |roi| (roi.width as u32, roi.height as u32));
+    if gif_opts.scale_mode != ScaleMode::PixelArt && (out_width as u32 > source_width || out_height as u32 > source_height) {
+        warnings.push(format!("{}x{} is larger than the {}x{} source and will be upscaled", out_width, out_height, source_width, source_height));
+    }
+
+    // Frames left after the range and the budget
+    let mut frames = frame_count;
+    if let Some(range) = gif_opts.frame_range {
+        if range.end > frame_count {
+            warnings.push(format!("frame_range ends at {}, past the clip's {} frames", range.end, frame_count));
+        }
+        frames = frames.min(range.end).saturating_sub(range.start);
+    }
+    if gif_opts.frame_count > 0 {
+        frames = frames.min(gif_opts.frame_count);
+    }
+
+    let state = thermal::current();
+    let (_, throttled) = thermal::throttle(quantize_opts.clone(), gif_opts.clone(), state);
+    let skipped: Vec<&str> = [
+        (gif_opts.sharpen_amount > 0.0 && throttled.sharpen_amount == 0.0, "sharpening"),
+        (gif_opts.decimation != throttled.decimation && frame_count > frames, "content-aware decimation"),
+        (gif_opts.align_tensor_slices && !throttled.align_tensor_slices, "slice alignment"),
+        (gif_opts.include_motion && !throttled.include_motion, "the motion volume"),
+        (gif_opts.seamless_loop && !throttled.seamless_loop, "loop smoothing"),
+    ]
+    .into_iter()
+    .filter_map(|(skipped, pass)| skipped.then_some(pass))
+    .collect();
+    if !skipped.is_empty() {
+        warnings.push(format!("thermal state {:?} skips {}", state, skipped.join(", ")));
+    }
+    if gif_opts.include_motion && !gif_opts.include_tensor {
+        warnings.push("include_motion has no effect without include_tensor".to_string());
+    }
+
+    let out_pixels = out_width as u64 * out_height as u64;
+    let (tensor_bytes, max_gif) = (tensor_bytes(&throttled, frames), max_gif_bytes(out_width, out_height, frames));
+    ValidationReport {
+        error: error.map(|e| e.to_string()),
+        error_field,
+        warnings,
+        width: out_width,
+        height: out_height,
+        frame_count: frames,
+        input_bytes,
+        max_gif_bytes: max_gif,
+        tensor_bytes,
+        peak_memory_bytes: input_bytes
+            + preprocess_bytes(&throttled, width, height, out_pixels, frames)
+            + out_pixels * (QUANTIZE_BYTES_PER_PIXEL + 1) * frames as u64
+            + max_gif
+            + variant_bytes(gif_opts, frames)
+            + extras_bytes(gif_opts, out_pixels, frames)
+            + tensor_bytes,
+    }
+}
+
+/// Everything `process_all_frames` checks before doing any work, in the order it does
+fn check(input_bytes: u64, width: u32, height: u32, frame_count: u32, quantize_opts: &QuantizeOpts, gif_opts: &GifOpts) -> Result<()> {
+    let frames_len = usize::try_from(input_bytes).unwrap_or(usize::MAX);
+    validation::validate(frames_len, width, height, frame_count, quantize_opts, gif_opts)?;
+    if let Some(range) = gif_opts.frame_range {
+        if range.start >= frame_count {
+            return Err(ProcessorError::invalid_input("frame range", format!("{}..{} selects none of {} frames", range.start, range.end, frame_count)));
+        }
+    }
+    filters::resolve(&gif_opts.frame_filters)?;
+    Ok(())
+}
+
+/// The size `cap_output_size` leaves; Reject sizes are already an error
+fn capped(width: u16, height: u16, oversize: OversizePolicy) -> (u16, u16) {
+    let (max, longest) = (validation::MAX_GIF_SIDE, width.max(height) as u32);
+    if longest <= max || oversize == OversizePolicy::Reject {
+        return (width, height);
+    }
+    let fit = |side: u16| ((side as u32 * max + longest / 2) / longest).max(1) as u16;
+    (fit(width), fit(height))
+}
+
+/// Most bytes a `width`×`height` GIF of `frames` frames can take
+///
+/// Every LZW code covers at least one pixel, and codes are 12 bits at most;
+/// on top of those come a clear code each time the table fills and the frame's blocks.
+pub fn max_gif_bytes(width: u16, height: u16, frames: u32) -> u64 {
+    let pixels = width as u64 * height as u64;
+    let codes = pixels + pixels / 2048 + 3;
+    let data = (codes * 12).div_ceil(8);
+    let frame = 8 + 10 + 1 + data + data.div_ceil(255) + 1;
+    HEADER_BYTES + frame * frames as u64 + 1
+}
+
+/// Frame buffers the scaling, sharpening, loop smoothing and styling passes hold at once
+fn preprocess_bytes(gif_opts: &GifOpts, width: u32, height: u32, out_pixels: u64, frames: u32) -> u64 {
+    let frame_bytes = out_pixels * 4;
+    let reshaped = gif_opts.roi.is_some() || (width, height) != (gif_opts.width as u32, gif_opts.height as u32);
+    let resizes = match gif_opts.scale_mode {
+        ScaleMode::Area => reshaped || !gif_opts.frame_transforms.is_empty(),
+        ScaleMode::Fit => reshaped,
+        ScaleMode::PixelArt => true,
+    };
+    // Pixel art with a region cuts it out at full size first
+    let cropped = match (gif_opts.scale_mode, gif_opts.roi) {
+        (ScaleMode::PixelArt, Some(roi)) => roi.width as u64 * roi.height as u64 * 4,
+        _ => 0,
+    };
+    let styled = !gif_opts.effects.effects.is_empty()
+        || !gif_opts.frame_filters.is_empty()
+        || posterize::Posterize::new(&gif_opts.preprocess).is_some();
+    let full_passes = [resizes, gif_opts.sharpen_amount > 0.0, styled].into_iter().filter(|&pass| pass).count() as u64;
+    let seam_frames = if gif_opts.seamless_loop { gif_opts.loop_crossfade_frames.min(frames as u16) as u64 } else { 0 };
+    (cropped + frame_bytes * full_passes) * frames as u64 + frame_bytes * seam_frames
+}
+
+/// Uncompressed tensor and motion volume
+fn tensor_bytes(gif_opts: &GifOpts, frames: u32) -> u64 {
+    if !gif_opts.include_tensor {
+        return 0;
+    }
+    let tensor = &gif_opts.tensor;
+    match tensor.mode {
+        TensorMode::ColorHistogram => (crate::tensor::HISTOGRAM_BINS as u64).pow(3) * 4,
+        TensorMode::FrameStack => {
+            let voxels = tensor.cube_width as u64 * tensor.cube_height as u64 * frames as u64;
+            voxels * 4 + if gif_opts.include_motion { voxels } else { 0 }
+        }
+    }
+}
+
+/// Frames and GIF of every variant
+fn variant_bytes(gif_opts: &GifOpts, frames: u32) -> u64 {
+    gif_opts
+        .variants
+        .iter()
+        .map(|variant| {
+            let (width, height) = capped(variant.width, variant.height, gif_opts.oversize);
+            width as u64 * height as u64 * (4 + 1) * frames as u64 + max_gif_bytes(width, height, frames)
+        })
+        .sum()
+}
+
+/// Raw turntable frames, the long exposure still with its build-up frames and GIF, and the rendered text art
+fn extras_bytes(gif_opts: &GifOpts, out_pixels: u64, frames: u32) -> u64 {
+    let mut bytes = 0;
+    if gif_opts.include_turntable {
+        let turntable = &gif_opts.turntable;
+        bytes += turntable.size as u64 * turntable.size as u64 * 4 * turntable.frame_count as u64;
+    }
+    if gif_opts.include_long_exposure && frames > 0 {
+        let steps = gif_opts.long_exposure.build_up_frames;
+        bytes += out_pixels * 4 * (1 + steps as u64);
+        if steps > 0 {
+            let (width, height) = capped(gif_opts.width, gif_opts.height, gif_opts.oversize);
+            bytes += max_gif_bytes(width, height, steps as u32);
+        }
+    }
+    if gif_opts.include_text_art && gif_opts.text_art.render_gif && frames > 0 {
+        let (out_width, out_height) = capped(gif_opts.width, gif_opts.height, gif_opts.oversize);
+        let (columns, rows) = text_art::grid(out_width as u32, out_height as u32, &gif_opts.text_art);
+        let (width, height) = text_art::rendered_size(columns, rows, gif_opts.text_art.style);
+        let (width, height) = capped(width.min(u16::MAX as u32) as u16, height.min(u16::MAX as u32) as u16, OversizePolicy::Downscale);
+        bytes += width as u64 * height as u64 * (4 + 1) * frames as u64 + max_gif_bytes(width, height, frames);
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FrameRange;
+
+    #[test]
+    fn test_report_names_the_failing_field_and_projects_the_output() {
+        let gif_opts = GifOpts { width: 8192, height: 1024, frame_count: 0, frame_range: Some(FrameRange { start: 2, end: 20 }), ..Default::default() };
+        let preflight = report(64, 64, 10, &QuantizeOpts::default(), &gif_opts);
+        assert_eq!((preflight.error, preflight.error_field), (None, None));
+        assert_eq!((preflight.width, preflight.height, preflight.frame_count), (4096, 512, 8));
+        assert_eq!(preflight.input_bytes, 64 * 64 * 4 * 10);
+        assert_eq!(preflight.warnings.len(), 3, "{:?}", preflight.warnings);
+        assert!(preflight.peak_memory_bytes > preflight.input_bytes + preflight.max_gif_bytes);
+
+        let quantize_opts = QuantizeOpts { speed: 0, ..Default::default() };
+        let preflight = report(64, 64, 10, &quantize_opts, &gif_opts);
+        assert_eq!(preflight.error_field.as_deref(), Some("quantize_opts.speed"));
+        // The projections don't depend on the quantizer settings being usable
+        assert_eq!(preflight.frame_count, 8);
+
+        let missing = GifOpts { frame_filters: vec!["preflight-missing".to_string()], ..Default::default() };
+        let preflight = report(64, 64, 10, &QuantizeOpts::default(), &missing);
+        assert!(preflight.error.unwrap().contains("preflight-missing"));
+        assert_eq!(preflight.error_field, None);
+    }
+
+    #[test]
+    fn test_max_gif_bytes_bounds_noise() {
+        // Every pixel a different index from its neighbours is LZW's worst case
+        let (width, height) = (64u16, 48u16);
+        let mut state = 0x2545_f491u32;
+        let indices: Vec<u8> = (0..width as usize * height as usize)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        let mut writer = crate::indexed::GifWriter::new(width, height, &[0; 768]);
+        writer.write_repeat(0);
+        for _ in 0..3 {
+            writer.write_frame(&indices, 4, crate::indexed::Disposal::Keep, None);
+        }
+        let size = writer.finish().len() as u64;
+        assert!(size <= max_gif_bytes(width, height, 3), "{} over {}", size, max_gif_bytes(width, height, 3));
+        assert!(size * 2 > max_gif_bytes(width, height, 3));
+    }
+}
@@ -0,0 +1,295 @@
+// Live Preview Quantization for the Camera Viewfinder
+// Octree palette + 4x4 Bayer ordered dither, tuned for one small frame per call
+
+/// Octree depth; 5 levels keeps 32 steps per channel, plenty for a preview
+const MAX_DEPTH: usize = 5;
+
+/// 4x4 Bayer matrix, values 0..16
+const BAYER_4X4: [[u8; 4]; 4] = [
+    [0, 8, 2, 10],
+    [12, 4, 14, 6],
+    [3, 11, 1, 9],
+    [15, 7, 13, 5],
+];
+
+/// Peak RGB offset applied by the ordered dither at full strength
+const DITHER_SPREAD: f32 = 32.0;
+
+const NO_CHILD: u32 = u32::MAX;
+
+#[derive(Clone)]
+struct OctreeNode {
+    children: [u32; 8],
+    sum: [u64; 3],
+    count: u32,
+    leaf: bool,
+    palette_index: u8,
+}
+
+impl OctreeNode {
+    fn new(leaf: bool) -> Self {
+        Self {
+            children: [NO_CHILD; 8],
+            sum: [0; 3],
+            count: 0,
+            leaf,
+            palette_index: 0,
+        }
+    }
+}
+
+/// Octree color quantizer over a bounded arena
+pub struct Octree {
+    nodes: Vec<OctreeNode>,
+    reducible: [Vec<u32>; MAX_DEPTH],
+    leaves: usize,
+    palette: Vec<[u8; 3]>,
+}
+
+#[inline]
+fn child_slot(rgb: [u8; 3], depth: usize) -> usize {
+    let shift = 7 - depth;
+    (((rgb[0] >> shift) & 1) << 2 | ((rgb[1] >> shift) & 1) << 1 | ((rgb[2] >> shift) & 1)) as usize
+}
+
+impl Octree {
+    /// Build a palette of at most `max_colors` (2-256) from RGBA pixels
+    ///
+    /// Fully transparent pixels are ignored.
+    pub fn build(rgba: &[u8], max_colors: usize) -> Self {
+        let mut tree = Self {
+            nodes: vec![OctreeNode::new(false)],
+            reducible: Default::default(),
+            leaves: 0,
+            palette: Vec::new(),
+        };
+        // The root is reducible too, so any budget down to one color is reachable
+        tree.reducible[0].push(0);
+
+        for px in rgba.chunks_exact(4) {
+            if px[3] != 0 {
+                tree.insert([px[0], px[1], px[2]]);
+            }
+        }
+
+        let max_colors = max_colors.clamp(2, 256);
+        while tree.leaves > max_colors && tree.reduce() {}
+
+        tree.assign_palette();
+        tree
+    }
+
+    pub fn palette(&self) -> &[[u8; 3]] {
+        &self.palette
+    }
+
+    fn insert(&mut self, rgb: [u8; 3]) {
+        let mut node = 0usize;
+        for depth in 0..MAX_DEPTH {
+            if self.nodes[node].leaf {
+                break;
+            }
+            let slot = child_slot(rgb, depth);
+            let mut child = self.nodes[node].children[slot];
+            if child == NO_CHILD {
+                let leaf = depth + 1 == MAX_DEPTH;
+                child = self.nodes.len() as u32;
+                self.nodes.push(OctreeNode::new(leaf));
+                self.nodes[node].children[slot] = child;
+                if leaf {
+                    self.leaves += 1;
+                } else {
+                    self.reducible[depth + 1].push(child);
+                }
+            }
+            node = child as usize;
+        }
+
+        let n = &mut self.nodes[node];
+        n.sum[0] += rgb[0] as u64;
+        n.sum[1] += rgb[1] as u64;
+        n.sum[2] += rgb[2] as u64;
+        n.count += 1;
+    }
+
+    /// Fold the children of the most recently added deepest inner node into it
+    fn reduce(&mut self) -> bool {
+        let Some(depth) = (0..MAX_DEPTH).rev().find(|&d| !self.reducible[d].is_empty()) else {
+            return false;
+        };
+        let node = self.reducible[depth].pop().unwrap() as usize;
+
+        let mut sum = [0u64; 3];
+        let mut count = 0u32;
+        let mut merged = 0usize;
+        for slot in 0..8 {
+            let child = self.nodes[node].children[slot];
+            if child == NO_CHILD {
+                continue;
+            }
+            let c = &self.nodes[child as usize];
+            for (s, cs) in sum.iter_mut().zip(c.sum) {
+                *s += cs;
+            }
+            count += c.count;
+            merged += 1;
+            self.nodes[node].children[slot] = NO_CHILD;
+        }
+
+        let n = &mut self.nodes[node];
+        n.sum = sum;
+        n.count = count;
+        n.leaf = true;
+        self.leaves = self.leaves + 1 - merged;
+        true
+    }
+
+    fn assign_palette(&mut self) {
+        let mut stack = vec![0usize];
+        while let Some(node) = stack.pop() {
+            let n = &mut self.nodes[node];
+            if n.leaf {
+                if n.count > 0 && self.palette.len() < 256 {
+                    let c = n.count as u64;
+                    n.palette_index = self.palette.len() as u8;
+                    self.palette.push([
+                        (n.sum[0] / c) as u8,
+                        (n.sum[1] / c) as u8,
+                        (n.sum[2] / c) as u8,
+                    ]);
+                }
+                continue;
+            }
+            stack.extend(n.children.iter().rev().filter(|&&c| c != NO_CHILD).map(|&c| c as usize));
+        }
+
+        if self.palette.is_empty() {
+            self.palette.push([0, 0, 0]);
+        }
+    }
+
+    /// Palette index for a color, falling back to a linear search off the tree
+    pub fn lookup(&self, rgb: [u8; 3]) -> u8 {
+        let mut node = 0usize;
+        for depth in 0..MAX_DEPTH {
+            let n = &self.nodes[node];
+            if n.leaf {
+                break;
+            }
+            let child = n.children[child_slot(rgb, depth)];
+            if child == NO_CHILD {
+                return self.nearest(rgb);
+            }
+            node = child as usize;
+        }
+
+        let n = &self.nodes[node];
+        if n.leaf && n.count > 0 {
+            n.palette_index
+        } else {
+            self.nearest(rgb)
+        }
+    }
+
+    fn nearest(&self, rgb: [u8; 3]) -> u8 {
+        self.palette
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, p)| {
+                let dr = rgb[0] as i32 - p[0] as i32;
+                let dg = rgb[1] as i32 - p[1] as i32;
+                let db = rgb[2] as i32 - p[2] as i32;
+                dr * dr + dg * dg + db * db
+            })
+            .map(|(i, _)| i as u8)
+            .unwrap_or(0)
+    }
+}
+
+/// Nearest-neighbour downscale so the longer side is at most `max_dimension`
+///
+/// Returns the (possibly unchanged) pixels and their size.
+pub fn downscale(rgba: &[u8], width: usize, height: usize, max_dimension: usize) -> (Vec<u8>, usize, usize) {
+    let longest = width.max(height);
+    if max_dimension == 0 || longest <= max_dimension {
+        return (rgba.to_vec(), width, height);
+    }
+
+    let dst_w = (width * max_dimension / longest).max(1);
+    let dst_h = (height * max_dimension / longest).max(1);
+    let mut out = Vec::with_capacity(dst_w * dst_h * 4);
+    for y in 0..dst_h {
+        let sy = y * height / dst_h;
+        for x in 0..dst_w {
+            let sx = x * width / dst_w;
+            let i = (sy * width + sx) * 4;
+            out.extend_from_slice(&rgba[i..i + 4]);
+        }
+    }
+
+    (out, dst_w, dst_h)
+}
+
+/// Quantize one frame to `max_colors` with ordered dithering, returning RGBA
+///
+/// Alpha passes through untouched so the viewfinder can composite the result.
+pub fn quantize_preview(rgba: &[u8], width: usize, max_colors: usize, dithering_level: f32) -> Vec<u8> {
+    let tree = Octree::build(rgba, max_colors);
+    let palette = tree.palette();
+    let strength = dithering_level.clamp(0.0, 1.0) * DITHER_SPREAD;
+
+    let mut out = Vec::with_capacity(rgba.len());
+    for (i, px) in rgba.chunks_exact(4).enumerate() {
+        let (x, y) = (i % width.max(1), i / width.max(1));
+        let threshold = (BAYER_4X4[y & 3][x & 3] as f32 + 0.5) / 16.0 - 0.5;
+        let offset = threshold * strength;
+
+        let dithered = [
+            (px[0] as f32 + offset).clamp(0.0, 255.0) as u8,
+            (px[1] as f32 + offset).clamp(0.0, 255.0) as u8,
+            (px[2] as f32 + offset).clamp(0.0, 255.0) as u8,
+        ];
+        let color = palette[tree.lookup(dithered) as usize];
+        out.extend_from_slice(&[color[0], color[1], color[2], px[3]]);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_octree_respects_color_budget() {
+        let rgba: Vec<u8> = (0..64 * 64)
+            .flat_map(|i| [(i % 64 * 4) as u8, (i / 64 * 4) as u8, ((i * 7) % 256) as u8, 255])
+            .collect();
+
+        for colors in [2, 16, 64, 256] {
+            let tree = Octree::build(&rgba, colors);
+            assert!(tree.palette().len() <= colors, "{} > {colors}", tree.palette().len());
+            assert!(tree.palette().len() * 8 >= colors, "{} colors for {colors}", tree.palette().len());
+        }
+    }
+
+    #[test]
+    fn test_few_colors_are_reproduced_exactly() {
+        let colors = [[255u8, 0, 0], [0, 255, 0], [0, 0, 255], [40, 40, 40]];
+        let rgba: Vec<u8> = (0..64).flat_map(|i| {
+            let c = colors[i % 4];
+            [c[0], c[1], c[2], 255]
+        }).collect();
+
+        let out = quantize_preview(&rgba, 8, 16, 0.0);
+        assert_eq!(out, rgba);
+    }
+
+    #[test]
+    fn test_downscale_keeps_aspect() {
+        let rgba = vec![7u8; 256 * 128 * 4];
+        let (out, w, h) = downscale(&rgba, 256, 128, 64);
+        assert_eq!((w, h), (64, 32));
+        assert_eq!(out.len(), 64 * 32 * 4);
+    }
+}
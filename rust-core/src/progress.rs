@@ -0,0 +1,73 @@
+// Encode Progress
+// Frames quantized and encoded so far, for callers like JobQueue that watch an encode from outside
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+thread_local! {
+    /// Counter the encode running on this thread reports into, if anyone is watching
+    static CURRENT: RefCell<Option<Arc<FrameProgress>>> = const { RefCell::new(None) };
+}
+
+/// Each output frame counts twice: once quantized, once encoded
+#[derive(Debug, Default)]
+pub struct FrameProgress {
+    done: AtomicU32,
+    total: AtomicU32,
+}
+
+impl FrameProgress {
+    /// Fraction of steps done, 0 until the encode knows its frame count
+    pub fn fraction(&self) -> f32 {
+        match self.total.load(Ordering::Relaxed) {
+            0 => 0.0,
+            total => (self.done.load(Ordering::Relaxed) as f32 / total as f32).min(1.0),
+        }
+    }
+
+    /// Start over for an encode of `frames` output frames
+    pub fn begin(&self, frames: usize) {
+        self.done.store(0, Ordering::Relaxed);
+        self.total.store((frames as u32).saturating_mul(2), Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, steps: usize) {
+        self.done.fetch_add(steps as u32, Ordering::Relaxed);
+    }
+}
+
+/// Run `f` with `progress` as this thread's counter, restoring the previous one after
+pub fn scoped<T>(progress: Option<Arc<FrameProgress>>, f: impl FnOnce() -> T) -> T {
+    let previous = CURRENT.with(|current| current.replace(progress));
+    let result = f();
+    CURRENT.with(|current| *current.borrow_mut() = previous);
+    result
+}
+
+/// This thread's counter; capture it before handing work to another thread pool
+pub fn current() -> Option<Arc<FrameProgress>> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_steps_and_scopes_to_the_thread() {
+        let progress = Arc::new(FrameProgress::default());
+        assert_eq!(progress.fraction(), 0.0);
+
+        scoped(Some(Arc::clone(&progress)), || {
+            current().unwrap().begin(4);
+            current().unwrap().advance(4);
+            assert!(std::thread::spawn(|| current().is_none()).join().unwrap());
+        });
+        assert_eq!(progress.fraction(), 0.5);
+        assert!(current().is_none());
+
+        progress.advance(10);
+        assert_eq!(progress.fraction(), 1.0);
+    }
+}
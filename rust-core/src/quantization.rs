@@ -1,9 +1,73 @@
 // Quantization module using libimagequant
 // High-quality color quantization with speed/quality trade-offs
 
-use imagequant::{Attributes, Image};
+use imagequant::{Attributes, Image, RGBA};
+use crate::palette::{Color32, Palette};
 use crate::{ProcessorError, Result};
 use rayon::prelude::*;
+use std::mem::MaybeUninit;
+
+/// Channel order of 4-byte pixels handed to `row_image`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelLayout {
+    Rgba,
+    Bgra, // Camera buffers
+}
+
+/// `bytes` viewed as RGBA pixels, without copying
+///
+/// bytemuck checks the length is whole pixels and the start is aligned for
+/// `RGBA`, so a layout assumption that stops holding is an error, not UB.
+pub fn as_rgba(bytes: &[u8]) -> Result<&[RGBA]> {
+    bytemuck::try_cast_slice(bytes)
+        .map_err(|e| ProcessorError::invalid_input("quantize", format!("{} bytes as RGBA pixels: {e}", bytes.len())))
+}
+
+/// An imagequant image that reads its rows straight out of `pixels`
+///
+/// `stride` is bytes per row, at least `width * 4`, so padded buffers work
+/// without repacking. RGBA rows on a whole-pixel stride are borrowed as they
+/// are; anything else is swizzled a row at a time as imagequant asks for it,
+/// so the frame is never copied.
+pub fn row_image<'a>(
+    attr: &Attributes,
+    pixels: &'a [u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+    layout: PixelLayout,
+) -> Result<Image<'a>> {
+    let row_bytes = width as usize * 4;
+    if width == 0 || height == 0 {
+        return Err(ProcessorError::invalid_input("quantize", "frame is empty"));
+    }
+    if stride < row_bytes {
+        return Err(ProcessorError::invalid_input("quantize", format!("{stride}-byte stride for a {width}-pixel row")));
+    }
+    if pixels.len() < stride * (height as usize - 1) + row_bytes {
+        return Err(ProcessorError::invalid_input("quantize", format!("{} bytes for a {width}x{height} frame", pixels.len())));
+    }
+
+    if layout == PixelLayout::Rgba && stride.is_multiple_of(4) {
+        let rows = as_rgba(&pixels[..stride * (height as usize - 1) + row_bytes])?;
+        return Image::new_stride_borrowed(attr, rows, width as usize, height as usize, stride / 4, 0.0)
+            .map_err(|e| ProcessorError::quantization("quantize", e));
+    }
+
+    let convert_row = move |row: &mut [MaybeUninit<RGBA>], y: usize| {
+        let source = &pixels[y * stride..y * stride + row_bytes];
+        for (out, px) in row.iter_mut().zip(source.chunks_exact(4)) {
+            out.write(match layout {
+                PixelLayout::Rgba => RGBA::new(px[0], px[1], px[2], px[3]),
+                PixelLayout::Bgra => RGBA::new(px[2], px[1], px[0], px[3]),
+            });
+        }
+    };
+    // SAFETY: rows are `width` pixels long and `source` holds `width` pixels,
+    // so the callback writes every pixel of every row it's given
+    unsafe { Image::new_fn(attr, convert_row, width as usize, height as usize, 0.0) }
+        .map_err(|e| ProcessorError::quantization("quantize", e))
+}
 
 pub struct QuantizeOptions {
     pub quality_min: u8,     // 0-100, lower = better compression
@@ -27,7 +91,7 @@ impl Default for QuantizeOptions {
 
 pub struct QuantizeResult {
     pub indices: Vec<u8>,      // Palette indices for each pixel
-    pub palette: Vec<u32>,     // RGBA palette as packed u32 (0xRRGGBBAA)
+    pub palette: Palette,      // Colors the indices point into
     pub width: u32,
     pub height: u32,
 }
@@ -38,56 +102,48 @@ pub fn quantize_frame(
     width: u32,
     height: u32,
     options: &QuantizeOptions,
+) -> Result<QuantizeResult> {
+    quantize_pixels(rgba_data, width, height, width as usize * 4, PixelLayout::Rgba, options)
+}
+
+/// Quantize a single frame of any layout and stride, read in place
+pub fn quantize_pixels(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+    layout: PixelLayout,
+    options: &QuantizeOptions,
 ) -> Result<QuantizeResult> {
     // Create attributes with quality settings
     let mut attr = Attributes::new();
     attr.set_quality(options.quality_min, options.quality_max)
-        .map_err(|_| ProcessorError::QuantizationError)?;
+        .map_err(|e| ProcessorError::quantization("quantize", e))?;
 
     attr.set_speed(options.speed)
-        .map_err(|_| ProcessorError::QuantizationError)?;
+        .map_err(|e| ProcessorError::quantization("quantize", e))?;
 
     attr.set_max_colors(options.palette_size as u32)
-        .map_err(|_| ProcessorError::QuantizationError)?;
-
-    // Convert raw bytes to RGBA slice
-    use imagequant::RGBA;
-    let pixels = unsafe {
-        std::slice::from_raw_parts(
-            rgba_data.as_ptr() as *const RGBA,
-            (width * height) as usize,
-        )
-    };
+        .map_err(|e| ProcessorError::quantization("quantize", e))?;
 
-    // Create image from RGBA data
-    let mut image = Image::new_borrowed(
-        &attr,
-        pixels,
-        width as usize,
-        height as usize,
-        0.0, // gamma (0 = sRGB)
-    ).map_err(|_| ProcessorError::QuantizationError)?;
+    // Rows are read from the frame as imagequant needs them
+    let mut image = row_image(&attr, pixels, width, height, stride, layout)?;
 
     // Perform quantization
     let mut result = attr.quantize(&mut image)
-        .map_err(|_| ProcessorError::QuantizationError)?;
+        .map_err(|e| ProcessorError::quantization("quantize", e))?;
 
     // Set dithering level
     result.set_dithering_level(options.dithering_level)
-        .map_err(|_| ProcessorError::QuantizationError)?;
+        .map_err(|e| ProcessorError::quantization("quantize", e))?;
 
     // Remap to palette indices
     let (palette, indices) = result.remapped(&mut image)
-        .map_err(|_| ProcessorError::QuantizationError)?;
-
-    // Convert palette to packed u32 RGBA
-    let palette_rgba: Vec<u32> = palette.iter()
-        .map(|c| ((c.r as u32) << 24) | ((c.g as u32) << 16) | ((c.b as u32) << 8) | (c.a as u32))
-        .collect();
+        .map_err(|e| ProcessorError::quantization("quantize", e))?;
 
     Ok(QuantizeResult {
         indices,
-        palette: palette_rgba,
+        palette: palette.into_iter().map(Color32::from).collect(),
         width,
         height,
     })
@@ -123,13 +179,13 @@ fn quantize_with_shared_palette(
     // Create shared attributes
     let mut attr = Attributes::new();
     attr.set_quality(options.quality_min, options.quality_max)
-        .map_err(|_| ProcessorError::QuantizationError)?;
+        .map_err(|e| ProcessorError::quantization("quantize batch", e))?;
 
     attr.set_speed(options.speed)
-        .map_err(|_| ProcessorError::QuantizationError)?;
+        .map_err(|e| ProcessorError::quantization("quantize batch", e))?;
 
     attr.set_max_colors(options.palette_size as u32)
-        .map_err(|_| ProcessorError::QuantizationError)?;
+        .map_err(|e| ProcessorError::quantization("quantize batch", e))?;
 
     // Build histogram from all frames
     // For simplicity, just use first frame's palette for all
@@ -140,70 +196,42 @@ fn quantize_with_shared_palette(
     }
 
     // Get palette from first frame
-    let first_frame = &frames[0];
-    use imagequant::RGBA;
-    let first_pixels = unsafe {
-        std::slice::from_raw_parts(
-            first_frame.as_ptr() as *const RGBA,
-            (width * height) as usize,
-        )
-    };
-
-    let mut first_image = Image::new_borrowed(
-        &attr,
-        first_pixels,
-        width as usize,
-        height as usize,
-        0.0,
-    ).map_err(|_| ProcessorError::QuantizationError)?;
+    let stride = width as usize * 4;
+    let mut first_image = row_image(&attr, &frames[0], width, height, stride, PixelLayout::Rgba)?;
 
     let mut quant_result = attr.quantize(&mut first_image)
-        .map_err(|_| ProcessorError::QuantizationError)?;
+        .map_err(|e| ProcessorError::quantization("quantize batch", e))?;
 
     quant_result.set_dithering_level(options.dithering_level)
-        .map_err(|_| ProcessorError::QuantizationError)?;
+        .map_err(|e| ProcessorError::quantization("quantize batch", e))?;
 
     let (palette, _) = quant_result.remapped(&mut first_image)
-        .map_err(|_| ProcessorError::QuantizationError)?;
+        .map_err(|e| ProcessorError::quantization("quantize batch", e))?;
 
-    // Convert palette to packed format
-    let palette_rgba: Vec<u32> = palette.iter()
-        .map(|c| ((c.r as u32) << 24) | ((c.g as u32) << 16) | ((c.b as u32) << 8) | (c.a as u32))
-        .collect();
+    let shared_palette: Palette = palette.into_iter().map(Color32::from).collect();
 
     // Apply shared palette to all frames
     let results: Result<Vec<QuantizeResult>> = frames
-        .into_par_iter()
-        .map(|frame_data| {
+        .par_iter()
+        .enumerate()
+        .map(|(i, frame_data)| {
             // Create image for this frame
-            let pixels = unsafe {
-                std::slice::from_raw_parts(
-                    frame_data.as_ptr() as *const RGBA,
-                    (width * height) as usize,
-                )
-            };
-
-            let mut image = Image::new_borrowed(
-                &attr,
-                pixels,
-                width as usize,
-                height as usize,
-                0.0,
-            ).map_err(|_| ProcessorError::QuantizationError)?;
+            let mut image = row_image(&attr, frame_data, width, height, stride, PixelLayout::Rgba)
+                .map_err(|e| e.at_frame(i))?;
 
             // Quantize with the shared attribute (will reuse palette)
             let mut result = attr.quantize(&mut image)
-                .map_err(|_| ProcessorError::QuantizationError)?;
+                .map_err(|e| ProcessorError::quantization("quantize batch", e).at_frame(i))?;
 
             result.set_dithering_level(options.dithering_level)
-                .map_err(|_| ProcessorError::QuantizationError)?;
+                .map_err(|e| ProcessorError::quantization("quantize batch", e).at_frame(i))?;
 
             let (_, indices) = result.remapped(&mut image)
-                .map_err(|_| ProcessorError::QuantizationError)?;
+                .map_err(|e| ProcessorError::quantization("quantize batch", e).at_frame(i))?;
 
             Ok(QuantizeResult {
                 indices,
-                palette: palette_rgba.clone(),
+                palette: shared_palette.clone(),
                 width,
                 height,
             })
@@ -237,6 +265,31 @@ mod tests {
     use super::*;
 
     #[test]
+    fn test_as_rgba_needs_whole_pixels() {
+        let bytes = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+        assert_eq!(as_rgba(&bytes[..8]).unwrap(), [RGBA::new(1, 2, 3, 4), RGBA::new(5, 6, 7, 8)]);
+        // One-byte alignment, so any offset works
+        assert_eq!(as_rgba(&bytes[1..]).unwrap(), [RGBA::new(2, 3, 4, 5), RGBA::new(6, 7, 8, 9)]);
+        assert!(as_rgba(&bytes).is_err());
+        assert!(as_rgba(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_row_image_checks_the_buffer() {
+        let attr = Attributes::new();
+        let frame = [0u8; 4 * 3 * 2];
+        for layout in [PixelLayout::Rgba, PixelLayout::Bgra] {
+            assert!(row_image(&attr, &frame, 3, 2, 12, layout).is_ok());
+            assert!(row_image(&attr, &frame, 3, 2, 8, layout).is_err());
+            assert!(row_image(&attr, &frame[..23], 3, 2, 12, layout).is_err());
+            assert!(row_image(&attr, &frame, 0, 2, 12, layout).is_err());
+        }
+        // Odd strides fall back to the row callback
+        assert!(row_image(&attr, &[0; 26], 3, 2, 14, PixelLayout::Rgba).is_ok());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)] // Full quantization is too slow under miri
     fn test_quantize_basic() {
         // Create test image data
         let mut data = vec![0u8; 256 * 256 * 4];
@@ -257,4 +310,30 @@ mod tests {
         assert!(!result.palette.is_empty());
         assert!(result.palette.len() <= 256);
     }
-}
\ No newline at end of file
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_strided_bgra_matches_packed_rgba() {
+        let (width, height) = (40u32, 30u32);
+        let rgba: Vec<u8> = (0..width * height)
+            .flat_map(|i| [(i % width * 6) as u8, (i / width * 8) as u8, (i % 7 * 30) as u8, 255])
+            .collect();
+        // Same pixels as BGRA, each row padded to 48 pixels like a camera buffer
+        let stride = 48 * 4;
+        let mut bgra = vec![0xAB; stride * height as usize];
+        for (y, row) in rgba.chunks_exact(width as usize * 4).enumerate() {
+            for (x, px) in row.chunks_exact(4).enumerate() {
+                bgra[y * stride + x * 4..][..4].copy_from_slice(&[px[2], px[1], px[0], px[3]]);
+            }
+        }
+
+        let options = QuantizeOptions { quality_min: 0, dithering_level: 0.0, ..QuantizeOptions::default() };
+        let packed = quantize_frame(&rgba, width, height, &options).unwrap();
+        let strided = quantize_pixels(&bgra, width, height, stride, PixelLayout::Bgra, &options).unwrap();
+        assert_eq!(strided.indices, packed.indices);
+        assert_eq!(strided.palette, packed.palette);
+
+        assert!(quantize_pixels(&bgra, width, height, 100, PixelLayout::Bgra, &options).is_err());
+        assert!(quantize_pixels(&bgra[..stride * 29], width, height, stride, PixelLayout::Bgra, &options).is_err());
+    }
+}
@@ -0,0 +1,271 @@
+// Frame Resizing to the Output Size
+// Box-filter downscaling, with a centre crop (or letterbox) when the aspect ratio changes
+
+use crate::{LetterboxFill, LetterboxOpts};
+
+/// Box-filter resample; every destination pixel averages the source pixels it covers
+pub fn area_resample(src: &[u8], src_w: u32, src_h: u32, dst_w: u32, dst_h: u32, out: &mut Vec<u8>) {
+    area_resample_rect(src, src_w as usize, (0, 0, src_w as usize, src_h as usize), dst_w, dst_h, out);
+}
+
+/// Fill `dst_w`×`dst_h` from the centre of the `(left, top, width, height)`
+/// region of a `src_w`-wide frame, cropping whichever axis is too long for the
+/// output aspect, then box-filtering
+///
+/// A 9:16 camera frame resized to 4:3 loses rows top and bottom rather than
+/// being squashed. The region is read in place, so cropping to it costs
+/// nothing beyond the resize.
+pub fn cover_resize_region(
+    src: &[u8],
+    src_w: u32,
+    (left, top, region_w, region_h): (u32, u32, u32, u32),
+    dst_w: u32,
+    dst_h: u32,
+    out: &mut Vec<u8>,
+) {
+    let rect = cover_rect((left, top, region_w, region_h), dst_w, dst_h);
+    area_resample_rect(src, src_w as usize, rect, dst_w, dst_h, out);
+}
+
+/// The centre of the region that has the `dst_w`:`dst_h` aspect, as `cover_resize_region` crops it
+pub fn cover_rect((left, top, region_w, region_h): (u32, u32, u32, u32), dst_w: u32, dst_h: u32) -> (usize, usize, usize, usize) {
+    let (sw, sh, dw, dh) = (region_w as u64, region_h as u64, dst_w as u64, dst_h as u64);
+    let (crop_w, crop_h) = if sw * dh > sh * dw {
+        ((sh * dw / dh).max(1), sh)
+    } else {
+        (sw, (sw * dh / dw).max(1))
+    };
+    (
+        left as usize + ((sw - crop_w) / 2) as usize,
+        top as usize + ((sh - crop_h) / 2) as usize,
+        crop_w as usize,
+        crop_h as usize,
+    )
+}
+
+/// Fit the whole region inside `dst_w`×`dst_h`, centred, and fill the borders per `fill`
+///
+/// Nothing is cropped: a portrait frame in a landscape output keeps its full
+/// height with bars either side. Blur fills the bars with the frame itself,
+/// cover-resized to the output and heavily blurred, like a phone showing a
+/// portrait video full screen.
+pub fn fit_resize_region(
+    src: &[u8],
+    src_w: u32,
+    region: (u32, u32, u32, u32),
+    dst_w: u32,
+    dst_h: u32,
+    fill: &LetterboxOpts,
+    out: &mut Vec<u8>,
+) {
+    let (fit_x, fit_y, fit_w, fit_h) = fit_rect(region, dst_w, dst_h);
+    let (left, top, region_w, region_h) = region;
+    let rect = (left as usize, top as usize, region_w as usize, region_h as usize);
+    if (fit_w, fit_h) == (dst_w, dst_h) {
+        area_resample_rect(src, src_w as usize, rect, dst_w, dst_h, out);
+        return;
+    }
+    let mut fitted = Vec::new();
+    area_resample_rect(src, src_w as usize, rect, fit_w, fit_h, &mut fitted);
+
+    match fill.fill {
+        LetterboxFill::Solid => {
+            let color = [(fill.color >> 16) as u8, (fill.color >> 8) as u8, fill.color as u8, 255];
+            out.clear();
+            out.extend(std::iter::repeat_n(color, (dst_w * dst_h) as usize).flatten());
+        }
+        LetterboxFill::Transparent => {
+            out.clear();
+            out.resize((dst_w * dst_h * 4) as usize, 0);
+        }
+        LetterboxFill::Blur => {
+            cover_resize_region(src, src_w, region, dst_w, dst_h, out);
+            let radius = match fill.blur_radius {
+                0 => (dst_w.max(dst_h) as usize / BLUR_DIVISOR).max(1),
+                radius => radius as usize,
+            };
+            box_blur(out, dst_w as usize, dst_h as usize, radius);
+            if fill.dim > 0.0 {
+                let keep = 1.0 - fill.dim.min(1.0);
+                for px in out.chunks_exact_mut(4) {
+                    px[..3].iter_mut().for_each(|c| *c = (*c as f32 * keep).round() as u8);
+                }
+            }
+        }
+    }
+    for (row, line) in fitted.chunks_exact(fit_w as usize * 4).enumerate() {
+        let start = (((fit_y as usize + row) * dst_w as usize) + fit_x as usize) * 4;
+        out[start..start + line.len()].copy_from_slice(line);
+    }
+}
+
+/// Output side divided by this gives the default letterbox blur radius
+const BLUR_DIVISOR: usize = 16;
+
+/// Where `fit_resize_region` places the region in the output, as (x, y, width, height)
+pub fn fit_rect((_, _, region_w, region_h): (u32, u32, u32, u32), dst_w: u32, dst_h: u32) -> (u32, u32, u32, u32) {
+    let (sw, sh, dw, dh) = (region_w as u64, region_h as u64, dst_w as u64, dst_h as u64);
+    let (fit_w, fit_h) = if sw * dh > sh * dw {
+        (dw, (sh * dw).div_ceil(sw).clamp(1, dh))
+    } else {
+        ((sw * dh).div_ceil(sh).clamp(1, dw), dh)
+    };
+    (((dw - fit_w) / 2) as u32, ((dh - fit_h) / 2) as u32, fit_w as u32, fit_h as u32)
+}
+
+/// Three box passes per axis, close to a Gaussian of about `radius`
+///
+/// Each pass keeps a running sum, so the cost doesn't grow with the radius.
+fn box_blur(frame: &mut [u8], width: usize, height: usize, radius: usize) {
+    let mut scratch = vec![0u8; frame.len()];
+    for _ in 0..3 {
+        box_pass(frame, &mut scratch, width, height, radius, (4, width * 4));
+        box_pass(&scratch, frame, height, width, radius, (width * 4, 4));
+    }
+}
+
+/// Average `2 * radius + 1` pixels along each of `lines` runs of `len` pixels, clamping at the ends
+///
+/// `along` is the byte distance between neighbours in a run, `across` between runs.
+fn box_pass(src: &[u8], dst: &mut [u8], len: usize, lines: usize, radius: usize, (along, across): (usize, usize)) {
+    let taps = (2 * radius + 1) as u32;
+    for line in 0..lines {
+        let at = |i: usize| line * across + i.min(len - 1) * along;
+        let mut sum = [0u32; 4];
+        for i in 0..=2 * radius {
+            let p = at(i.saturating_sub(radius));
+            sum.iter_mut().zip(&src[p..p + 4]).for_each(|(s, &c)| *s += c as u32);
+        }
+        for i in 0..len {
+            let p = line * across + i * along;
+            dst[p..p + 4].iter_mut().zip(sum).for_each(|(d, s)| *d = ((s + taps / 2) / taps) as u8);
+            let (enter, leave) = (at(i + radius + 1), at(i.saturating_sub(radius)));
+            for c in 0..4 {
+                sum[c] = sum[c] + src[enter + c] as u32 - src[leave + c] as u32;
+            }
+        }
+    }
+}
+
+/// Copy the `(left, top, width, height)` region out of a `src_w`-wide frame
+pub fn crop(src: &[u8], src_w: u32, (left, top, width, height): (u32, u32, u32, u32), out: &mut Vec<u8>) {
+    out.clear();
+    out.reserve((width * height * 4) as usize);
+    for y in top..top + height {
+        let start = ((y * src_w + left) * 4) as usize;
+        out.extend_from_slice(&src[start..start + (width * 4) as usize]);
+    }
+}
+
+/// Box-filter the `(left, top, width, height)` rectangle of a `stride`-wide frame
+fn area_resample_rect(
+    src: &[u8],
+    stride: usize,
+    (left, top, src_w, src_h): (usize, usize, usize, usize),
+    dst_w: u32,
+    dst_h: u32,
+    out: &mut Vec<u8>,
+) {
+    let (dst_w, dst_h) = (dst_w as usize, dst_h as usize);
+    out.clear();
+    out.reserve(dst_w * dst_h * 4);
+
+    for y in 0..dst_h {
+        let y0 = y * src_h / dst_h;
+        let y1 = ((y + 1) * src_h / dst_h).max(y0 + 1);
+        for x in 0..dst_w {
+            let x0 = x * src_w / dst_w;
+            let x1 = ((x + 1) * src_w / dst_w).max(x0 + 1);
+
+            let mut sum = [0u32; 4];
+            for sy in top + y0..top + y1 {
+                let row = &src[(sy * stride + left + x0) * 4..(sy * stride + left + x1) * 4];
+                for px in row.chunks_exact(4) {
+                    for (s, &c) in sum.iter_mut().zip(px) {
+                        *s += c as u32;
+                    }
+                }
+            }
+
+            let n = ((y1 - y0) * (x1 - x0)) as u32;
+            out.extend(sum.iter().map(|&s| ((s + n / 2) / n) as u8));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cover_resize_crops_portrait_to_landscape() {
+        // 2×8 portrait: rows 0-2 red, 3-4 green, 5-7 blue
+        let src: Vec<u8> = (0..16)
+            .flat_map(|i| match i / 2 {
+                0..=2 => [255, 0, 0, 255],
+                3 | 4 => [0, 255, 0, 255],
+                _ => [0, 0, 255, 255],
+            })
+            .collect();
+
+        // Output 2×1 keeps only the centre 2×1 crop, rows 3-4 → one green row
+        let mut out = Vec::new();
+        cover_resize_region(&src, 2, (0, 0, 2, 8), 2, 1, &mut out);
+        assert_eq!(out, vec![0, 255, 0, 255, 0, 255, 0, 255]);
+
+        // Restricted to the bottom three rows only blue is left
+        cover_resize_region(&src, 2, (0, 5, 2, 3), 1, 1, &mut out);
+        assert_eq!(out, vec![0, 0, 255, 255]);
+
+        let mut cropped = Vec::new();
+        crop(&src, 2, (1, 2, 1, 2), &mut cropped);
+        assert_eq!(cropped, vec![255, 0, 0, 255, 0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn test_fit_letterboxes_with_each_fill() {
+        // 2×4 portrait into 4×4: two columns of bars either side of a 2×4 image
+        let src: Vec<u8> = (0..8u8).flat_map(|i| [200, i * 20, 0, 255]).collect();
+        assert_eq!(fit_rect((0, 0, 2, 4), 4, 4), (1, 0, 2, 4));
+        let solid = LetterboxOpts { fill: LetterboxFill::Solid, color: 0x0000FF, ..Default::default() };
+        let mut out = Vec::new();
+        fit_resize_region(&src, 2, (0, 0, 2, 4), 4, 4, &solid, &mut out);
+        assert_eq!(out[..16], [[0, 0, 255, 255], src[..4].try_into().unwrap(), src[4..8].try_into().unwrap(), [0, 0, 255, 255]].concat());
+
+        let clear = LetterboxOpts { fill: LetterboxFill::Transparent, ..solid };
+        fit_resize_region(&src, 2, (0, 0, 2, 4), 4, 4, &clear, &mut out);
+        assert!(out.chunks_exact(16).all(|row| row[3] == 0 && row[15] == 0 && row[7] == 255));
+
+        // Blurred bars take the frame's own colors, smoothed out
+        let blur = LetterboxOpts { fill: LetterboxFill::Blur, ..solid };
+        fit_resize_region(&src, 2, (0, 0, 2, 4), 4, 4, &blur, &mut out);
+        assert!(out.chunks_exact(16).all(|row| row[0] == 200 && row[2] == 0 && row[3] == 255));
+        assert!(out[..4][1] < out[48..52][1], "{out:?}");
+
+        // Dimming darkens only the bars; a wider radius flattens them further
+        let dimmed = LetterboxOpts { dim: 0.5, ..blur };
+        let mut dark = Vec::new();
+        fit_resize_region(&src, 2, (0, 0, 2, 4), 4, 4, &dimmed, &mut dark);
+        assert_eq!((dark[0], dark[4..12] == out[4..12]), (100, true));
+        let wide = LetterboxOpts { blur_radius: 8, ..blur };
+        let mut flat = Vec::new();
+        fit_resize_region(&src, 2, (0, 0, 2, 4), 4, 4, &wide, &mut flat);
+        assert!(flat[48 + 1] - flat[1] < out[48 + 1] - out[1]);
+
+        // Same aspect leaves no border to fill
+        let mut area = Vec::new();
+        fit_resize_region(&src, 2, (0, 0, 2, 4), 1, 2, &solid, &mut out);
+        area_resample(&src, 2, 4, 1, 2, &mut area);
+        assert_eq!(out, area);
+    }
+
+    #[test]
+    fn test_same_aspect_is_plain_area_resample() {
+        let src: Vec<u8> = (0..16u8).flat_map(|i| [i * 10, 0, 0, 255]).collect();
+        let (mut cover, mut area) = (Vec::new(), Vec::new());
+        cover_resize_region(&src, 4, (0, 0, 4, 4), 2, 2, &mut cover);
+        area_resample(&src, 4, 4, 2, 2, &mut area);
+        assert_eq!(cover, area);
+        assert_eq!(&cover[..4], &[25, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,277 @@
+// Result Cache
+// Encoded GIFs and tensors kept on disk under a hash of the frames and settings, so re-exports are instant
+
+use crate::{atomic_file, build_info, CacheConfig, GifOpts, ProcessResult, ProcessorError, QuantizeOpts, Result};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const MAGIC: &[u8; 4] = b"RGBR";
+
+/// Bumped whenever the layout below changes; older entries are treated as misses
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Entry file extension; partial writes end in `.partial` and are never picked up
+const EXTENSION: &str = "rgbr";
+
+/// Whether a result can be cached by its frames and settings alone
+///
+/// Registered filters can change behind a name, and results with files
+/// written beside them or extra outputs that aren't stored here would come
+/// back incomplete, so those skip the cache.
+pub fn cacheable(gif_opts: &GifOpts) -> bool {
+    gif_opts.frame_filters.is_empty()
+        && gif_opts.output_path.is_none()
+        && gif_opts.checkpoint_path.is_none()
+        && gif_opts.tensor.handoff_path.is_none()
+        && gif_opts.variants.is_empty()
+        && gif_opts.segment_max_bytes == 0
+        && gif_opts.segment_max_frames == 0
+        && !gif_opts.include_turntable
+        && !gif_opts.include_long_exposure
+        && !gif_opts.include_text_art
+}
+
+/// Hex BLAKE3 of the library version, the clip and every setting, including the ones presets leave out
+pub fn key(frames_rgba: &[u8], width: u32, height: u32, frame_count: u32, quantize_opts: &QuantizeOpts, gif_opts: &GifOpts) -> Result<String> {
+    let settings = serde_json::to_vec(&(quantize_opts, gif_opts, &gif_opts.frame_transforms, &gif_opts.frame_metadata))
+        .map_err(|e| ProcessorError::encoding("cache key", e))?;
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(build_info::version().as_bytes());
+    hasher.update(&FORMAT_VERSION.to_le_bytes());
+    for value in [width, height, frame_count] {
+        hasher.update(&value.to_le_bytes());
+    }
+    hasher.update(&(settings.len() as u64).to_le_bytes());
+    hasher.update(&settings);
+    hasher.update(frames_rgba);
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+fn entry_path(config: &CacheConfig, key: &str) -> PathBuf {
+    Path::new(&config.directory).join(format!("{key}.{EXTENSION}"))
+}
+
+/// The stored result for `key`, marked as just used; unreadable entries are deleted and count as misses
+pub fn load(config: &CacheConfig, key: &str) -> Option<ProcessResult> {
+    let path = entry_path(config, key);
+    let data = fs::read(&path).ok()?;
+    match decode(&data) {
+        Some(result) => {
+            // Eviction goes by modification time, so a hit moves the entry to the back of the line
+            if let Ok(file) = File::options().write(true).open(&path) {
+                let _ = file.set_modified(SystemTime::now());
+            }
+            Some(result)
+        }
+        None => {
+            let _ = fs::remove_file(&path);
+            None
+        }
+    }
+}
+
+/// Store `result` under `key`, then evict down to the config's limits
+///
+/// Returns how many older entries were evicted.
+pub fn store(config: &CacheConfig, key: &str, result: &ProcessResult) -> Result<u32> {
+    fs::create_dir_all(&config.directory).map_err(|e| ProcessorError::memory("cache", e))?;
+    let data = encode(result)?;
+    atomic_file::write(entry_path(config, key), &data, false).map_err(|e| ProcessorError::memory("cache", e))?;
+    evict(config, config.max_bytes, config.max_entries)
+}
+
+/// Delete every entry in the cache directory, returning how many there were
+pub fn clear(config: &CacheConfig) -> Result<u32> {
+    let entries = entries(config)?;
+    let count = entries.len();
+    remove_oldest(entries, count)
+}
+
+/// Entries as (last used, size, path), oldest first
+fn entries(config: &CacheConfig) -> Result<Vec<(SystemTime, u64, PathBuf)>> {
+    let dir = match fs::read_dir(&config.directory) {
+        Ok(dir) => dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(ProcessorError::memory("cache", e)),
+    };
+    let mut entries: Vec<(SystemTime, u64, PathBuf)> = dir
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+        .filter_map(|path| {
+            let meta = fs::metadata(&path).ok()?;
+            Some((meta.modified().unwrap_or(SystemTime::UNIX_EPOCH), meta.len(), path))
+        })
+        .collect();
+    entries.sort();
+    Ok(entries)
+}
+
+/// Drop the least recently used entries until at most `max_bytes` and `max_entries` remain (0 = no limit)
+fn evict(config: &CacheConfig, max_bytes: u64, max_entries: u32) -> Result<u32> {
+    let entries = entries(config)?;
+    let mut total: u64 = entries.iter().map(|e| e.1).sum();
+    let mut count = entries.len();
+    let mut excess = 0;
+    for (_, size, _) in &entries {
+        let over_bytes = max_bytes > 0 && total > max_bytes;
+        let over_entries = max_entries > 0 && count > max_entries as usize;
+        if !over_bytes && !over_entries {
+            break;
+        }
+        total -= size;
+        count -= 1;
+        excess += 1;
+    }
+    remove_oldest(entries, excess)
+}
+
+fn remove_oldest(entries: Vec<(SystemTime, u64, PathBuf)>, count: usize) -> Result<u32> {
+    for (_, _, path) in entries.into_iter().take(count) {
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(ProcessorError::memory("cache", e)),
+        }
+    }
+    Ok(count as u32)
+}
+
+/// Magic, version, then the result's JSON and its GIF, tensor and motion bytes, each length-prefixed
+fn encode(result: &ProcessResult) -> Result<Vec<u8>> {
+    let meta = serde_json::to_vec(result).map_err(|e| ProcessorError::encoding("cache", e))?;
+    let blobs = [Some(&result.gif_data), result.tensor_data.as_ref(), result.motion_data.as_ref()];
+    let mut out = Vec::with_capacity(meta.len() + blobs.iter().flatten().map(|b| b.len() + 9).sum::<usize>() + 16);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    out.extend_from_slice(&(meta.len() as u64).to_le_bytes());
+    out.extend_from_slice(&meta);
+    for blob in blobs {
+        out.push(blob.is_some() as u8);
+        let blob = blob.map_or(&[][..], |b| b.as_slice());
+        out.extend_from_slice(&(blob.len() as u64).to_le_bytes());
+        out.extend_from_slice(blob);
+    }
+    Ok(out)
+}
+
+fn decode(data: &[u8]) -> Option<ProcessResult> {
+    let mut rest = data.strip_prefix(MAGIC)?;
+    if u16::from_le_bytes(take(&mut rest, 2)?.try_into().ok()?) != FORMAT_VERSION {
+        return None;
+    }
+    let meta_len = take_len(&mut rest)?;
+    let mut result: ProcessResult = serde_json::from_slice(take(&mut rest, meta_len)?).ok()?;
+    let mut blobs = [None, None, None];
+    for blob in &mut blobs {
+        let present = take(&mut rest, 1)?[0] != 0;
+        let len = take_len(&mut rest)?;
+        let bytes = take(&mut rest, len)?;
+        *blob = present.then(|| bytes.to_vec());
+    }
+    if !rest.is_empty() {
+        return None;
+    }
+    let [gif_data, tensor_data, motion_data] = blobs;
+    result.gif_data = gif_data?;
+    result.tensor_data = tensor_data;
+    result.motion_data = motion_data;
+    Some(result)
+}
+
+/// The next `len` bytes of `rest`, moving past them
+fn take<'a>(rest: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if rest.len() < len {
+        return None;
+    }
+    let (head, tail) = rest.split_at(len);
+    *rest = tail;
+    Some(head)
+}
+
+fn take_len(rest: &mut &[u8]) -> Option<usize> {
+    usize::try_from(u64::from_le_bytes(take(rest, 8)?.try_into().ok()?)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorSpace, StageTimings};
+    use std::time::Duration;
+
+    fn result(gif_data: Vec<u8>, tensor_data: Option<Vec<u8>>) -> ProcessResult {
+        ProcessResult {
+            gif_data,
+            tensor_data,
+            motion_data: None,
+            turntable_data: None,
+            long_exposure_data: None,
+            long_exposure_gif: None,
+            text_art: Vec::new(),
+            text_art_gif: None,
+            tensor_handle: None,
+            final_file_size: 3,
+            processing_time_ms: 12.5,
+            actual_frame_count: 2,
+            palette_size_used: 4,
+            variants: Vec::new(),
+            dropped_frames: 0,
+            segments: Vec::new(),
+            stage_timings: StageTimings::default(),
+            palette_rgba: vec![1, 2, 3, 255],
+            motion_rect: None,
+            color_space: ColorSpace::DisplayP3,
+            quantization: None,
+        }
+    }
+
+    fn config(name: &str, max_entries: u32) -> CacheConfig {
+        let dir = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        CacheConfig { directory: dir.to_string_lossy().into_owned(), max_bytes: 0, max_entries }
+    }
+
+    /// Back-date an entry so eviction order doesn't hang on timestamp resolution
+    fn age(config: &CacheConfig, key: &str, secs: u64) {
+        let file = File::options().write(true).open(entry_path(config, key)).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(secs)).unwrap();
+    }
+
+    #[test]
+    fn test_entries_round_trip_and_bad_ones_are_dropped() {
+        let config = config("result-cache-round-trip", 0);
+        let stored = result(b"GIF".to_vec(), Some(vec![7; 10]));
+        store(&config, "a", &stored).unwrap();
+        let loaded = load(&config, "a").expect("cache miss");
+        assert_eq!((loaded.gif_data, loaded.tensor_data), (stored.gif_data, stored.tensor_data));
+        assert_eq!((loaded.palette_rgba, loaded.color_space, loaded.actual_frame_count), (stored.palette_rgba, ColorSpace::DisplayP3, 2));
+        assert!(load(&config, "b").is_none());
+
+        fs::write(entry_path(&config, "a"), b"RGBR junk").unwrap();
+        assert!(load(&config, "a").is_none());
+        assert!(!entry_path(&config, "a").exists());
+        let _ = fs::remove_dir_all(&config.directory);
+    }
+
+    #[test]
+    fn test_eviction_drops_least_recently_used() {
+        let config = config("result-cache-evict", 2);
+        store(&config, "a", &result(vec![0; 100], None)).unwrap();
+        store(&config, "b", &result(vec![0; 100], None)).unwrap();
+        age(&config, "a", 30);
+        age(&config, "b", 20);
+        // Reading "a" makes "b" the oldest
+        assert!(load(&config, "a").is_some());
+        assert_eq!(store(&config, "c", &result(vec![0; 100], None)).unwrap(), 1);
+        assert!(entry_path(&config, "a").exists() && !entry_path(&config, "b").exists());
+
+        // A byte limit smaller than any entry empties the cache
+        let tight = CacheConfig { max_bytes: 1, max_entries: 0, ..config.clone() };
+        assert_eq!(evict(&tight, tight.max_bytes, tight.max_entries).unwrap(), 2);
+        store(&config, "d", &result(vec![0; 100], None)).unwrap();
+        assert_eq!(clear(&config).unwrap(), 1);
+        assert_eq!(clear(&config).unwrap(), 0);
+        let _ = fs::remove_dir_all(&config.directory);
+    }
+}
@@ -0,0 +1,137 @@
+// Palette Sampling
+// Stratified pixel subsets, so long clips build their palette from a fraction of the pixels
+
+use rayon::prelude::*;
+
+/// About `fraction` of each frame's pixels, one per cell of a jittered grid
+///
+/// Cells are square, roughly `1 / fraction` pixels in area, and each frame
+/// picks a different spot inside its cells, so the samples cover the whole
+/// frame and every frame contributes equally. Returns the sampled RGBA pixels
+/// back to back; a fraction of 1 or more returns every pixel.
+pub fn stratified(frames: &[&[u8]], width: u32, height: u32, fraction: f32) -> Vec<u8> {
+    if fraction >= 1.0 {
+        return frames.concat();
+    }
+    let cell = (1.0 / fraction.max(1.0e-6)).sqrt().round().max(1.0) as u32;
+
+    let per_frame: Vec<Vec<u8>> = frames
+        .par_iter()
+        .enumerate()
+        .map(|(z, frame)| {
+            let mut samples = Vec::with_capacity((width.div_ceil(cell) * height.div_ceil(cell) * 4) as usize);
+            for y0 in (0..height).step_by(cell as usize) {
+                for x0 in (0..width).step_by(cell as usize) {
+                    let (cell_w, cell_h) = (cell.min(width - x0), cell.min(height - y0));
+                    let jitter = hash(x0, y0, z as u32);
+                    let (x, y) = (x0 + jitter % cell_w, y0 + (jitter / cell_w) % cell_h);
+                    let i = ((y * width + x) * 4) as usize;
+                    samples.extend_from_slice(&frame[i..i + 4]);
+                }
+            }
+            samples
+        })
+        .collect();
+    per_frame.concat()
+}
+
+/// Cheap integer mix; only has to differ between neighbouring cells and frames
+fn hash(x: u32, y: u32, z: u32) -> u32 {
+    let mut h = x.wrapping_mul(0x9E37_79B1) ^ y.wrapping_mul(0x85EB_CA77) ^ z.wrapping_mul(0xC2B2_AE3D);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2C1B_3C6D);
+    h ^ (h >> 12)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oklab_quantization::{alpha_weights, build_weighted_oklab_palette, srgb_to_oklab_batch, OklabColor};
+
+    /// Drifting gradients with per-pixel noise, so the palette has real work to do
+    fn clip(frames: u32, width: u32, height: u32) -> Vec<Vec<u8>> {
+        (0..frames)
+            .map(|z| {
+                (0..width * height)
+                    .flat_map(|i| {
+                        let (x, y) = (i % width, i / width);
+                        let noise = hash(x, y, z) % 24;
+                        [(x * 4 + z * 3 + noise) as u8, (y * 4 + noise) as u8, ((x + y) * 2 + z * 5) as u8, 255]
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Palette from the sampled pixels, and how many pixels it was built from
+    fn sampled_palette(frames: &[&[u8]], width: u32, height: u32, fraction: f32) -> (Vec<OklabColor>, usize) {
+        let samples = stratified(frames, width, height, fraction);
+        let palette = build_weighted_oklab_palette(&srgb_to_oklab_batch(&samples), &alpha_weights(&samples), 255);
+        (palette, samples.len() / 4)
+    }
+
+    /// Mean OKLab distance (ΔE) from every pixel to its nearest palette entry
+    fn mean_error(frames: &[&[u8]], palette: &[OklabColor]) -> f32 {
+        let pixels: Vec<OklabColor> = frames.iter().flat_map(|f| srgb_to_oklab_batch(f)).collect();
+        let total: f32 = pixels
+            .par_iter()
+            .map(|p| {
+                palette
+                    .iter()
+                    .map(|c| ((p.l - c.l).powi(2) + (p.a - c.a).powi(2) + (p.b - c.b).powi(2)).sqrt())
+                    .fold(f32::MAX, f32::min)
+            })
+            .sum();
+        total / pixels.len() as f32
+    }
+
+    #[test]
+    fn test_sampled_palette_is_nearly_as_good_from_far_fewer_pixels() {
+        let (width, height) = (64u32, 64u32);
+        let frames = clip(48, width, height);
+        let refs: Vec<&[u8]> = frames.iter().map(|f| f.as_slice()).collect();
+
+        let (full, full_pixels) = sampled_palette(&refs, width, height, 1.0);
+        let (sampled, sampled_pixels) = sampled_palette(&refs, width, height, 0.1);
+
+        let (full_error, sampled_error) = (mean_error(&refs, &full), mean_error(&refs, &sampled));
+        assert!(sampled_error <= full_error * 1.05 + 0.001, "ΔE {sampled_error} sampled vs {full_error} full");
+        assert_eq!(full_pixels, 48 * 64 * 64);
+        assert!(sampled_pixels * 6 < full_pixels, "{sampled_pixels} pixels sampled of {full_pixels}");
+    }
+
+    #[test]
+    fn test_every_frame_contributes_the_same_share() {
+        // Each frame is filled with its own index in the red channel
+        let (width, height) = (30u32, 20u32);
+        let frames: Vec<Vec<u8>> = (0..4u8).map(|z| [z, 0, 0, 255].repeat((width * height) as usize)).collect();
+        let refs: Vec<&[u8]> = frames.iter().map(|f| f.as_slice()).collect();
+
+        let samples = stratified(&refs, width, height, 0.1);
+        // 3×3 cells: 10 × 7 per frame, edge cells included
+        assert_eq!(samples.len(), 4 * 10 * 7 * 4);
+        for z in 0..4u8 {
+            assert_eq!(samples.chunks_exact(4).filter(|px| px[0] == z).count(), 70);
+        }
+
+        assert_eq!(stratified(&refs, width, height, 1.0).len(), frames.concat().len());
+    }
+
+    #[test]
+    fn test_samples_move_between_frames() {
+        // One frame holding each pixel's own position
+        let (width, height) = (16u32, 16u32);
+        let frame: Vec<u8> = (0..width * height).flat_map(|i| [(i % width) as u8, (i / width) as u8, 0, 255]).collect();
+        let refs = vec![frame.as_slice(); 2];
+
+        let samples = stratified(&refs, width, height, 0.25);
+        let (first, second) = samples.split_at(samples.len() / 2);
+        assert_ne!(first, second);
+
+        // Each sample stays inside its 2×2 cell
+        for (cell, px) in first.chunks_exact(4).enumerate() {
+            let (cx, cy) = ((cell as u32 % 8) * 2, (cell as u32 / 8) * 2);
+            assert!((cx..cx + 2).contains(&(px[0] as u32)) && (cy..cy + 2).contains(&(px[1] as u32)));
+        }
+    }
+}
@@ -0,0 +1,110 @@
+// Unsharp Mask
+// Restores perceived detail lost when camera frames are downscaled to GIF size
+
+use crate::parallel::process_rows_parallel;
+
+/// Kernel taps past this many sigmas are negligible
+const KERNEL_SIGMAS: f32 = 3.0;
+
+/// Normalized 1D Gaussian with standard deviation `radius`
+fn gaussian_kernel(radius: f32) -> Vec<f32> {
+    let sigma = radius.max(0.1);
+    let half = (sigma * KERNEL_SIGMAS).ceil() as i32;
+    let weights: Vec<f32> = (-half..=half)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f32 = weights.iter().sum();
+    weights.into_iter().map(|w| w / total).collect()
+}
+
+/// Sharpen an RGBA frame in place: `out = src + amount × (src - blur(src))`
+///
+/// The blur is a separable Gaussian of sigma `radius` pixels with edges
+/// clamped. Both blur passes and the final blend run row-parallel. Alpha is
+/// left untouched so transparency keying still sees the original mask.
+pub fn unsharp_mask(frame: &mut [u8], width: u32, height: u32, radius: f32, amount: f32) {
+    let (w, h) = (width as usize, height as usize);
+    if amount <= 0.0 || w == 0 || h == 0 {
+        return;
+    }
+    let kernel = gaussian_kernel(radius);
+    let half = (kernel.len() / 2) as isize;
+    let source: &[u8] = frame;
+
+    let mut horizontal = vec![0u8; w * h * 4];
+    process_rows_parallel(&mut horizontal, w, h, 4, |row, y| {
+        let src_row = &source[y * w * 4..(y + 1) * w * 4];
+        for x in 0..w {
+            for c in 0..3 {
+                let sum: f32 = kernel
+                    .iter()
+                    .enumerate()
+                    .map(|(k, weight)| {
+                        let sx = (x as isize + k as isize - half).clamp(0, w as isize - 1) as usize;
+                        src_row[sx * 4 + c] as f32 * weight
+                    })
+                    .sum();
+                row[x * 4 + c] = sum.round() as u8;
+            }
+        }
+    });
+
+    let mut blurred = vec![0u8; w * h * 4];
+    process_rows_parallel(&mut blurred, w, h, 4, |row, y| {
+        for x in 0..w {
+            for c in 0..3 {
+                let sum: f32 = kernel
+                    .iter()
+                    .enumerate()
+                    .map(|(k, weight)| {
+                        let sy = (y as isize + k as isize - half).clamp(0, h as isize - 1) as usize;
+                        horizontal[(sy * w + x) * 4 + c] as f32 * weight
+                    })
+                    .sum();
+                row[x * 4 + c] = sum.round() as u8;
+            }
+        }
+    });
+
+    process_rows_parallel(frame, w, h, 4, |row, y| {
+        let blur_row = &blurred[y * w * 4..(y + 1) * w * 4];
+        for (pixel, blur) in row.chunks_exact_mut(4).zip(blur_row.chunks_exact(4)) {
+            for c in 0..3 {
+                let detail = pixel[c] as f32 - blur[c] as f32;
+                pixel[c] = (pixel[c] as f32 + amount * detail).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flat_areas_are_unchanged() {
+        let mut frame = [90u8, 140, 200, 255].repeat(16 * 16);
+        let original = frame.clone();
+        unsharp_mask(&mut frame, 16, 16, 1.5, 2.0);
+        assert_eq!(frame, original);
+    }
+
+    #[test]
+    fn test_edges_gain_contrast() {
+        // Vertical edge from 100 to 150 gray, half transparent
+        let (w, h) = (16u32, 4u32);
+        let mut frame: Vec<u8> = (0..w * h)
+            .flat_map(|i| {
+                let v = if i % w < w / 2 { 100 } else { 150 };
+                [v, v, v, 128]
+            })
+            .collect();
+        unsharp_mask(&mut frame, w, h, 1.0, 1.0);
+
+        let at = |x: u32| frame[(x * 4) as usize];
+        assert!(at(w / 2 - 1) < 100, "dark side of the edge should darken");
+        assert!(at(w / 2) > 150, "bright side of the edge should brighten");
+        assert_eq!((at(0), at(w - 1)), (100, 150));
+        assert!(frame.chunks_exact(4).all(|p| p[3] == 128));
+    }
+}
@@ -1,7 +1,9 @@
 // Tensor module for 128×128×128 cube operations (N=128 optimal)
 // Handles frame-major layout and efficient memory access
 
-use crate::{ProcessorError, Result};
+use crate::palette::Color32;
+use crate::resize;
+use crate::{OccupancyMode, OccupancyOpts, ProcessorError, Result, SliceAxis, TensorLayout, VoxelEffect};
 use rayon::prelude::*;
 
 /// Tensor shape for 3D cube data
@@ -41,9 +43,7 @@ pub fn build_tensor(
 ) -> Result<Vec<u8>> {
     let expected_size = shape.total_elements() * 4; // RGBA
     if frames_rgba.len() != expected_size {
-        return Err(ProcessorError::InvalidInput(
-            format!("Expected {} bytes, got {}", expected_size, frames_rgba.len())
-        ));
+        return Err(ProcessorError::invalid_input("tensor", format!("{} bytes for a {}x{}x{} tensor", frames_rgba.len(), shape.width, shape.height, shape.frames)));
     }
 
     // For frame-major layout, data is already in the correct order
@@ -58,9 +58,7 @@ pub fn extract_frame(
     frame_index: u32,
 ) -> Result<Vec<u8>> {
     if frame_index >= shape.frames {
-        return Err(ProcessorError::InvalidInput(
-            format!("Frame index {} out of range (0..{})", frame_index, shape.frames)
-        ));
+        return Err(ProcessorError::invalid_input("tensor", format!("tensor has {} frames", shape.frames)).at_frame(frame_index as usize));
     }
 
     let frame_size = shape.frame_size() * 4; // RGBA
@@ -68,7 +66,7 @@ pub fn extract_frame(
     let end = start + frame_size;
 
     if end > tensor.len() {
-        return Err(ProcessorError::TensorError("Tensor data too small".into()));
+        return Err(ProcessorError::invalid_input("tensor", "tensor is truncated").at_frame(frame_index as usize));
     }
 
     Ok(tensor[start..end].to_vec())
@@ -95,7 +93,63 @@ pub fn process_tensor_parallel<F>(
 
     tensor
         .par_chunks_mut(frame_size)
-        .for_each(|frame| processor(frame));
+        .for_each(&processor);
+}
+
+/// Side of the frame-stack cube's slices
+pub const SLICE_SIDE: u32 = 128;
+
+/// Nearest-neighbour resample one RGBA frame to a `slice_w`×`slice_h` slice, appended to `out`
+pub fn resample_slice(frame: &[u8], width: u32, height: u32, slice_w: u32, slice_h: u32, out: &mut Vec<u8>) {
+    for y in 0..slice_h {
+        for x in 0..slice_w {
+            // Map to source coordinates
+            let src_x = (x as f32 * width as f32 / slice_w as f32) as usize;
+            let src_y = (y as f32 * height as f32 / slice_h as f32) as usize;
+            let src_idx = (src_y.min(height as usize - 1) * width as usize + src_x.min(width as usize - 1)) * 4;
+
+            match frame.get(src_idx..src_idx + 4) {
+                Some(px) => out.extend_from_slice(px),
+                None => out.extend_from_slice(&[0, 0, 0, 0]),
+            }
+        }
+    }
+}
+
+/// Resample an RGBA cube to `out`, box-filtering along every axis
+///
+/// Each output slice averages the source slices it covers in time, then is
+/// area-resized like a frame; enlarging an axis repeats voxels instead.
+pub fn resample(tensor: &[u8], shape: TensorShape, out: TensorShape) -> Result<Vec<u8>> {
+    if tensor.len() != shape.total_elements() * 4 || shape.total_elements() == 0 {
+        return Err(ProcessorError::invalid_input("tensor resample", format!("{} bytes for a {}x{}x{} tensor", tensor.len(), shape.width, shape.height, shape.frames)));
+    }
+    if out.total_elements() == 0 {
+        return Err(ProcessorError::invalid_input("tensor resample", "output shape is empty"));
+    }
+
+    let (depth, out_depth) = (shape.frames as usize, out.frames as usize);
+    let frame_bytes = shape.frame_size() * 4;
+    let slices: Vec<Vec<u8>> = (0..out_depth)
+        .into_par_iter()
+        .map(|z| {
+            let z0 = z * depth / out_depth;
+            let z1 = ((z + 1) * depth / out_depth).max(z0 + 1);
+            let mut sum = vec![0u32; frame_bytes];
+            for source in tensor[z0 * frame_bytes..z1 * frame_bytes].chunks_exact(frame_bytes) {
+                for (s, &c) in sum.iter_mut().zip(source) {
+                    *s += c as u32;
+                }
+            }
+            let n = (z1 - z0) as u32;
+            let averaged: Vec<u8> = sum.iter().map(|&s| ((s + n / 2) / n) as u8).collect();
+
+            let mut slice = Vec::new();
+            resize::area_resample(&averaged, shape.width, shape.height, out.width, out.height, &mut slice);
+            slice
+        })
+        .collect();
+    Ok(slices.concat())
 }
 
 /// Apply 3D convolution kernel (for future voxel operations)
@@ -105,8 +159,8 @@ pub fn convolve_3d(
     kernel: &[f32],
     kernel_size: u32,
 ) -> Result<Vec<u8>> {
-    if kernel_size % 2 == 0 {
-        return Err(ProcessorError::InvalidInput("Kernel size must be odd".into()));
+    if kernel_size.is_multiple_of(2) {
+        return Err(ProcessorError::invalid_input("tensor blur", format!("kernel size {} is even", kernel_size)));
     }
 
     let half_kernel = (kernel_size / 2) as i32;
@@ -155,10 +209,523 @@ pub fn convolve_3d(
     Ok(output)
 }
 
+/// Largest per-slice correction searched by `align_slices` at 128×128
+pub const MAX_SLICE_SHIFT: u32 = 8;
+
+/// Sample every Nth pixel per axis when matching slices
+const ALIGN_SAMPLE_STRIDE: usize = 2;
+
+/// Translation-align consecutive slices so the Z axis isn't smeared by camera shake
+///
+/// Each slice's offset from its predecessor is found by exhaustive luma block
+/// matching within `max_shift` pixels. Offsets are accumulated along Z and
+/// re-centred on their mean, so the cube is corrected around its average
+/// position rather than pinned to the first slice. Pixels shifted in from
+/// outside the slice repeat the nearest edge.
+pub fn align_slices(tensor: &mut [u8], shape: TensorShape, max_shift: u32) -> Result<()> {
+    let frame_bytes = shape.frame_size() * 4;
+    if tensor.len() != shape.total_elements() * 4 {
+        return Err(ProcessorError::invalid_input("slice alignment", format!("{} bytes for a {}x{}x{} tensor", tensor.len(), shape.width, shape.height, shape.frames)));
+    }
+    if shape.frames < 2 || max_shift == 0 {
+        return Ok(());
+    }
+
+    let (width, height) = (shape.width as usize, shape.height as usize);
+    let lumas: Vec<Vec<f32>> = tensor.par_chunks(frame_bytes).map(slice_luma).collect();
+
+    // Motion of slice z relative to slice z - 1
+    let steps: Vec<(i32, i32)> = lumas
+        .par_windows(2)
+        .map(|pair| estimate_shift(&pair[0], &pair[1], width, height, max_shift as i32))
+        .collect();
+
+    let mut offsets = Vec::with_capacity(shape.frames as usize);
+    offsets.push((0i32, 0i32));
+    for &(dx, dy) in &steps {
+        let &(x, y) = offsets.last().unwrap();
+        offsets.push((x + dx, y + dy));
+    }
+    let count = offsets.len() as f32;
+    let mean_x = (offsets.iter().map(|o| o.0).sum::<i32>() as f32 / count).round() as i32;
+    let mean_y = (offsets.iter().map(|o| o.1).sum::<i32>() as f32 / count).round() as i32;
+
+    tensor
+        .par_chunks_mut(frame_bytes)
+        .zip(offsets.par_iter())
+        .for_each(|(slice, &(x, y))| {
+            let (dx, dy) = (x - mean_x, y - mean_y);
+            if dx != 0 || dy != 0 {
+                let source = slice.to_vec();
+                translate_slice(&source, slice, width, height, dx, dy);
+            }
+        });
+
+    Ok(())
+}
+
+fn slice_luma(rgba: &[u8]) -> Vec<f32> {
+    rgba.chunks_exact(4).map(luma).collect()
+}
+
+/// Offset `(dx, dy)` such that `next(x, y) ≈ prev(x - dx, y - dy)`
+fn estimate_shift(prev: &[f32], next: &[f32], width: usize, height: usize, max_shift: i32) -> (i32, i32) {
+    // Only compare the interior every candidate shift keeps in bounds
+    let margin = max_shift as usize;
+    if width <= 2 * margin || height <= 2 * margin {
+        return (0, 0);
+    }
+
+    let mut best = (0i32, 0i32);
+    let mut best_cost = f32::INFINITY;
+    for dy in -max_shift..=max_shift {
+        for dx in -max_shift..=max_shift {
+            let mut cost = 0.0f32;
+            for y in (margin..height - margin).step_by(ALIGN_SAMPLE_STRIDE) {
+                let py = (y as i32 - dy) as usize;
+                for x in (margin..width - margin).step_by(ALIGN_SAMPLE_STRIDE) {
+                    let px = (x as i32 - dx) as usize;
+                    cost += (next[y * width + x] - prev[py * width + px]).abs();
+                }
+            }
+            // Prefer the smaller shift on ties so flat slices stay put
+            let closer = dx.abs() + dy.abs() < best.0.abs() + best.1.abs();
+            if cost < best_cost || (cost == best_cost && closer) {
+                best_cost = cost;
+                best = (dx, dy);
+            }
+        }
+    }
+
+    best
+}
+
+/// Write `source` moved by `(-dx, -dy)` into `out`, clamping reads to the edges
+fn translate_slice(source: &[u8], out: &mut [u8], width: usize, height: usize, dx: i32, dy: i32) {
+    for y in 0..height {
+        let sy = (y as i32 + dy).clamp(0, height as i32 - 1) as usize;
+        for x in 0..width {
+            let sx = (x as i32 + dx).clamp(0, width as i32 - 1) as usize;
+            let (src, dst) = ((sy * width + sx) * 4, (y * width + x) * 4);
+            out[dst..dst + 4].copy_from_slice(&source[src..src + 4]);
+        }
+    }
+}
+
+/// Restyle a cube with a symmetry or time transform, returning a new tensor of the same shape
+///
+/// Every output voxel copies one source voxel (nearest neighbour), so no new
+/// colors are introduced and the result quantizes as well as the input. The
+/// time effects read each voxel from a skewed plane through the cube instead
+/// of its own slice, wrapping around so the clip still loops.
+pub fn apply_effect(tensor: &[u8], shape: TensorShape, effect: VoxelEffect) -> Result<Vec<u8>> {
+    if tensor.len() != shape.total_elements() * 4 || shape.total_elements() == 0 {
+        return Err(ProcessorError::invalid_input("voxel effect", format!("{} bytes for a {}x{}x{} tensor", tensor.len(), shape.width, shape.height, shape.frames)));
+    }
+
+    let (width, height, depth) = (shape.width as usize, shape.height as usize, shape.frames as usize);
+    let plane = plane_map(effect, width, height);
+    let frame_bytes = shape.frame_size() * 4;
+    let mut output = vec![0u8; tensor.len()];
+
+    output
+        .par_chunks_mut(frame_bytes)
+        .enumerate()
+        .for_each(|(z, out_slice)| {
+            let sz = match effect {
+                VoxelEffect::MirrorOctants => fold(z, depth),
+                _ => z,
+            };
+            let source = &tensor[sz * frame_bytes..(sz + 1) * frame_bytes];
+            for (i, (dst, &src)) in out_slice.chunks_exact_mut(4).zip(&plane).enumerate() {
+                let delay = match effect {
+                    VoxelEffect::SlitScan => i % width * depth / width,
+                    VoxelEffect::TimeDisplacement => {
+                        let px = &source[src * 4..src * 4 + 4];
+                        let luma = 0.299 * px[0] as f32 + 0.587 * px[1] as f32 + 0.114 * px[2] as f32;
+                        depth - (luma / 255.0 * (depth - 1) as f32).round() as usize
+                    }
+                    _ => 0,
+                };
+                let voxel = ((sz + delay) % depth * shape.frame_size() + src) * 4;
+                dst.copy_from_slice(&tensor[voxel..voxel + 4]);
+            }
+        });
+
+    Ok(output)
+}
+
+/// Mirror the upper half of `0..n` onto the lower half
+#[inline]
+fn fold(v: usize, n: usize) -> usize {
+    v.min(n - 1 - v)
+}
+
+/// Source pixel index for every pixel of one slice
+fn plane_map(effect: VoxelEffect, width: usize, height: usize) -> Vec<usize> {
+    use std::f32::consts::FRAC_PI_2;
+
+    let (cx, cy) = ((width - 1) as f32 / 2.0, (height - 1) as f32 / 2.0);
+    // Ring width for the radial repeat: the inner quarter-size disc tiles outward
+    let period = (width.min(height) as f32 / 4.0).max(1.0);
+
+    let polar_source = |radius: f32, angle: f32| {
+        let sx = (cx + radius * angle.cos()).round().clamp(0.0, (width - 1) as f32) as usize;
+        let sy = (cy + radius * angle.sin()).round().clamp(0.0, (height - 1) as f32) as usize;
+        sy * width + sx
+    };
+
+    (0..width * height)
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            match effect {
+                VoxelEffect::MirrorOctants => fold(y, height) * width + fold(x, width),
+                VoxelEffect::SlitScan | VoxelEffect::TimeDisplacement => i,
+                VoxelEffect::Kaleidoscope => {
+                    let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+                    // Fold the angle into one 45° wedge, mirrored so wedge edges meet
+                    let mut angle = dy.atan2(dx).rem_euclid(FRAC_PI_2);
+                    if angle > FRAC_PI_2 / 2.0 {
+                        angle = FRAC_PI_2 - angle;
+                    }
+                    polar_source(dx.hypot(dy), angle)
+                }
+                VoxelEffect::RadialRepeat => {
+                    let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+                    // Triangle wave over radius, so rings alternate in and out
+                    let phase = dx.hypot(dy) % (2.0 * period);
+                    let radius = if phase > period { 2.0 * period - phase } else { phase };
+                    polar_source(radius, dy.atan2(dx))
+                }
+            }
+        })
+        .collect()
+}
+
+/// Cut a frame-major cube into slices across `axis`, returned back to back with their shape
+///
+/// `voxel_bytes` is 4 for RGBA tensors and 1 for indexed volumes. X slices are
+/// `frames` wide and `height` tall with time running left to right, one per
+/// column; Y slices are `width` wide and `frames` tall with time running down,
+/// one per row. Z gives the cube back as it is.
+pub fn reslice_to_frames(data: &[u8], shape: TensorShape, voxel_bytes: usize, axis: SliceAxis) -> Result<(Vec<u8>, TensorShape)> {
+    if voxel_bytes == 0 || data.len() != shape.total_elements() * voxel_bytes || shape.total_elements() == 0 {
+        return Err(ProcessorError::invalid_input("reslice", format!("{} bytes for a {}x{}x{} tensor of {}-byte voxels", data.len(), shape.width, shape.height, shape.frames, voxel_bytes)));
+    }
+
+    let (width, height) = (shape.width as usize, shape.height as usize);
+    let out = match axis {
+        SliceAxis::X => TensorShape::new(shape.frames, shape.height, shape.width),
+        SliceAxis::Y => TensorShape::new(shape.width, shape.frames, shape.height),
+        SliceAxis::Z => return Ok((data.to_vec(), shape)),
+    };
+
+    let (out_w, out_h) = (out.width as usize, out.height as usize);
+    let mut output = vec![0u8; data.len()];
+    output
+        .par_chunks_mut(out_w * out_h * voxel_bytes)
+        .enumerate()
+        .for_each(|(slice, out_slice)| {
+            for (i, dst) in out_slice.chunks_exact_mut(voxel_bytes).enumerate() {
+                let (col, row) = (i % out_w, i / out_w);
+                let (x, y, z) = if axis == SliceAxis::X { (slice, row, col) } else { (col, slice, row) };
+                let src = ((z * height + y) * width + x) * voxel_bytes;
+                dst.copy_from_slice(&data[src..src + voxel_bytes]);
+            }
+        });
+    Ok((output, out))
+}
+
+/// Derive voxel alpha from the cube's content so the renderer can hollow it out
+///
+/// Voxels that fail the test become fully transparent; the rest keep their
+/// alpha, so already-transparent voxels stay empty. `threshold` is normalised
+/// to 0-1 for every mode. Motion compares each slice with the one before it
+/// (the first slice with the second).
+pub fn compute_occupancy(tensor: &mut [u8], shape: TensorShape, opts: &OccupancyOpts) -> Result<()> {
+    if tensor.len() != shape.total_elements() * 4 {
+        return Err(ProcessorError::invalid_input("occupancy", format!("{} bytes for a {}x{}x{} tensor", tensor.len(), shape.width, shape.height, shape.frames)));
+    }
+
+    let threshold = opts.threshold.clamp(0.0, 1.0);
+    let frame_bytes = shape.frame_size() * 4;
+    let key = Color32::from_rgb_u32(opts.key_color).rgb();
+
+    match opts.mode {
+        OccupancyMode::Solid => {}
+        OccupancyMode::Luminance => {
+            tensor.par_chunks_mut(4).for_each(|px| {
+                if luma(px) / 255.0 < threshold {
+                    px[3] = 0;
+                }
+            });
+        }
+        OccupancyMode::ChromaKey => {
+            // Normalised by the RGB cube diagonal
+            let max_distance = 255.0 * 3f32.sqrt();
+            tensor.par_chunks_mut(4).for_each(|px| {
+                let distance = px[..3]
+                    .iter()
+                    .zip(key)
+                    .map(|(&c, k)| (c as f32 - k as f32).powi(2))
+                    .sum::<f32>()
+                    .sqrt();
+                if distance / max_distance <= threshold {
+                    px[3] = 0;
+                }
+            });
+        }
+        OccupancyMode::Motion => {
+            if shape.frames < 2 {
+                return Ok(());
+            }
+            let lumas: Vec<Vec<f32>> = tensor.par_chunks(frame_bytes).map(slice_luma).collect();
+            tensor
+                .par_chunks_mut(frame_bytes)
+                .enumerate()
+                .for_each(|(z, slice)| {
+                    let other = &lumas[if z == 0 { 1 } else { z - 1 }];
+                    for ((px, &now), &before) in slice.chunks_exact_mut(4).zip(&lumas[z]).zip(other) {
+                        if (now - before).abs() / 255.0 <= threshold {
+                            px[3] = 0;
+                        }
+                    }
+                });
+        }
+    }
+
+    Ok(())
+}
+
+/// Motion energy volume: one byte per voxel holding |slice[z] - slice[z-1]|
+///
+/// The difference is the mean absolute RGB change, so hue shifts count as well
+/// as brightness. The first slice has no predecessor and stays zero.
+pub fn motion_energy(tensor: &[u8], shape: TensorShape) -> Result<Vec<u8>> {
+    if tensor.len() != shape.total_elements() * 4 {
+        return Err(ProcessorError::invalid_input("motion energy", format!("{} bytes for a {}x{}x{} tensor", tensor.len(), shape.width, shape.height, shape.frames)));
+    }
+
+    let frame_bytes = shape.frame_size() * 4;
+    let mut energy = vec![0u8; shape.total_elements()];
+    if shape.frames < 2 {
+        return Ok(energy);
+    }
+
+    energy
+        .par_chunks_mut(shape.frame_size())
+        .enumerate()
+        .skip(1)
+        .for_each(|(z, out)| {
+            let now = &tensor[z * frame_bytes..(z + 1) * frame_bytes];
+            let before = &tensor[(z - 1) * frame_bytes..z * frame_bytes];
+            for ((e, a), b) in out.iter_mut().zip(now.chunks_exact(4)).zip(before.chunks_exact(4)) {
+                let diff: u32 = (0..3).map(|c| a[c].abs_diff(b[c]) as u32).sum();
+                *e = ((diff + 1) / 3) as u8;
+            }
+        });
+
+    Ok(energy)
+}
+
+/// Bins per channel in the color histogram cube
+pub const HISTOGRAM_BINS: usize = 64;
+
+/// 64³ RGB histogram of every opaque pixel in the clip, as an RGBA cube
+///
+/// Voxel (x, y, z) is the bin for (red, green, blue) = (x, y, z) × 4, laid out
+/// frame-major like the frame-stack tensor. Its color is the bin centre and its
+/// alpha the log-scaled pixel count, so empty bins are transparent and the
+/// viewer renders the clip's color distribution directly.
+pub fn color_histogram(frames: &[&[u8]]) -> Vec<u8> {
+    const SHIFT: u32 = 8 - HISTOGRAM_BINS.trailing_zeros();
+    let bins = HISTOGRAM_BINS * HISTOGRAM_BINS * HISTOGRAM_BINS;
+
+    let counts = frames
+        .par_iter()
+        .fold(
+            || vec![0u32; bins],
+            |mut counts, frame| {
+                for px in frame.chunks_exact(4).filter(|px| px[3] != 0) {
+                    let (r, g, b) = ((px[0] >> SHIFT) as usize, (px[1] >> SHIFT) as usize, (px[2] >> SHIFT) as usize);
+                    counts[(b * HISTOGRAM_BINS + g) * HISTOGRAM_BINS + r] += 1;
+                }
+                counts
+            },
+        )
+        .reduce(
+            || vec![0u32; bins],
+            |mut a, b| {
+                a.iter_mut().zip(&b).for_each(|(x, y)| *x += y);
+                a
+            },
+        );
+
+    let peak = counts.iter().copied().max().unwrap_or(0);
+    let scale = if peak > 0 { 255.0 / (peak as f32).ln_1p() } else { 0.0 };
+    let centre = |bin: usize| ((bin << SHIFT) + (1 << (SHIFT - 1))) as u8;
+
+    let mut cube = Vec::with_capacity(bins * 4);
+    for (i, &count) in counts.iter().enumerate() {
+        let (r, g, b) = (i % HISTOGRAM_BINS, i / HISTOGRAM_BINS % HISTOGRAM_BINS, i / (HISTOGRAM_BINS * HISTOGRAM_BINS));
+        // Any populated bin stays visible, however rare
+        let alpha = if count == 0 { 0 } else { ((count as f32).ln_1p() * scale).round().max(1.0) as u8 };
+        cube.extend_from_slice(&[centre(r), centre(g), centre(b), alpha]);
+    }
+    cube
+}
+
+/// Reorder voxels from one linearization to another
+///
+/// `voxel_bytes` is 4 for RGBA tensors and 1 for single-channel volumes such as
+/// motion energy. Morton and Hilbert orders walk the smallest power-of-two cube
+/// enclosing the shape and skip codes that fall outside it, so any shape works.
+pub fn relayout(
+    data: Vec<u8>,
+    shape: TensorShape,
+    voxel_bytes: usize,
+    from: TensorLayout,
+    to: TensorLayout,
+) -> Result<Vec<u8>> {
+    if voxel_bytes == 0 || data.len() != shape.total_elements() * voxel_bytes {
+        return Err(ProcessorError::invalid_input("tensor layout", format!("{} bytes for a {}x{}x{} tensor of {}-byte voxels", data.len(), shape.width, shape.height, shape.frames, voxel_bytes)));
+    }
+    if from == to {
+        return Ok(data);
+    }
+
+    // Frame-major voxel index at every position of each layout
+    let frame_major = match from {
+        TensorLayout::FrameMajor => data,
+        _ => {
+            let order = curve_order(shape, from);
+            let mut out = vec![0u8; data.len()];
+            for (src, &voxel) in data.chunks_exact(voxel_bytes).zip(&order) {
+                let dst = voxel as usize * voxel_bytes;
+                out[dst..dst + voxel_bytes].copy_from_slice(src);
+            }
+            out
+        }
+    };
+
+    match to {
+        TensorLayout::FrameMajor => Ok(frame_major),
+        _ => {
+            let order = curve_order(shape, to);
+            let mut out = Vec::with_capacity(frame_major.len());
+            for &voxel in &order {
+                let src = voxel as usize * voxel_bytes;
+                out.extend_from_slice(&frame_major[src..src + voxel_bytes]);
+            }
+            Ok(out)
+        }
+    }
+}
+
+/// Frame-major indices of every voxel, sorted along the layout's curve
+fn curve_order(shape: TensorShape, layout: TensorLayout) -> Vec<u32> {
+    let side = shape.width.max(shape.height).max(shape.frames).next_power_of_two();
+    let bits = side.trailing_zeros();
+
+    let (width, plane) = (shape.width as usize, shape.frame_size());
+    let mut keyed: Vec<(u64, u32)> = (0..shape.total_elements())
+        .into_par_iter()
+        .map(|i| {
+            let axes = [(i % width) as u32, (i % plane / width) as u32, (i / plane) as u32];
+            let key = match layout {
+                TensorLayout::FrameMajor => i as u64,
+                TensorLayout::Morton => morton_index(axes, bits),
+                TensorLayout::Hilbert => hilbert_index(axes, bits),
+            };
+            (key, i as u32)
+        })
+        .collect();
+    keyed.par_sort_unstable_by_key(|&(key, _)| key);
+    keyed.into_iter().map(|(_, i)| i).collect()
+}
+
+/// Interleave coordinate bits, most significant first, z above y above x
+fn morton_index(axes: [u32; 3], bits: u32) -> u64 {
+    let mut index = 0u64;
+    for b in (0..bits).rev() {
+        for axis in axes.iter().rev() {
+            index = index << 1 | (axis >> b & 1) as u64;
+        }
+    }
+    index
+}
+
+/// Position along the 3D Hilbert curve (Skilling's transpose algorithm)
+fn hilbert_index(mut x: [u32; 3], bits: u32) -> u64 {
+    if bits == 0 {
+        return 0;
+    }
+
+    // Inverse undo of the excess work
+    let m = 1u32 << (bits - 1);
+    let mut q = m;
+    while q > 1 {
+        let p = q - 1;
+        for i in 0..3 {
+            if x[i] & q != 0 {
+                x[0] ^= p;
+            } else {
+                let t = (x[0] ^ x[i]) & p;
+                x[0] ^= t;
+                x[i] ^= t;
+            }
+        }
+        q >>= 1;
+    }
+
+    // Gray encode
+    for i in 1..3 {
+        x[i] ^= x[i - 1];
+    }
+    let mut t = 0;
+    let mut q = m;
+    while q > 1 {
+        if x[2] & q != 0 {
+            t ^= q - 1;
+        }
+        q >>= 1;
+    }
+    for v in &mut x {
+        *v ^= t;
+    }
+
+    // The transposed form interleaves into the index with x[0] most significant
+    morton_index([x[2], x[1], x[0]], bits)
+}
+
+#[inline]
+fn luma(px: &[u8]) -> f32 {
+    px[0] as f32 * 0.299 + px[1] as f32 * 0.587 + px[2] as f32 * 0.114
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_resample_averages_space_and_time() {
+        // 2×2×4 cube: slice z is filled with value 40·z, alpha opaque
+        let shape = TensorShape::new(2, 2, 4);
+        let tensor: Vec<u8> = (0..4u8).flat_map(|z| [40 * z, 40 * z, 40 * z, 255].repeat(4)).collect();
+
+        // Halving depth averages slice pairs; 1×1 averages each slice
+        let out = resample(&tensor, shape, TensorShape::new(1, 1, 2)).unwrap();
+        assert_eq!(out, vec![20, 20, 20, 255, 100, 100, 100, 255]);
+
+        // Doubling repeats voxels
+        let up = resample(&tensor, shape, TensorShape::new(4, 4, 8)).unwrap();
+        assert_eq!(up.len(), 4 * 4 * 8 * 4);
+        assert_eq!(&up[16 * 4 * 3..16 * 4 * 3 + 4], &[40, 40, 40, 255]);
+
+        assert!(resample(&tensor[1..], shape, TensorShape::cube(1)).is_err());
+        assert!(resample(&tensor, shape, TensorShape::new(0, 1, 1)).is_err());
+    }
+
     #[test]
     fn test_tensor_shape() {
         let shape = TensorShape::cube(128);
@@ -194,4 +761,239 @@ mod tests {
         // Out of bounds
         assert!(extract_frame(&tensor, shape, 2).is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_align_slices_removes_shake() {
+        let shape = TensorShape { width: 32, height: 32, frames: 4 };
+        // The whole scene, with a bright square in it, jitters from slice to slice
+        let jitter = [(0i32, 0i32), (3, -2), (-1, 2), (2, 1)];
+        let mut tensor = Vec::with_capacity(shape.total_elements() * 4);
+        for &(jx, jy) in &jitter {
+            for y in 0..32i32 {
+                for x in 0..32i32 {
+                    let (sx, sy) = (x - jx, y - jy);
+                    let inside = (12..20).contains(&sx) && (12..20).contains(&sy);
+                    let v = if inside { 250 } else { (sx * 3 + sy * 2 + 60) as u8 };
+                    tensor.extend_from_slice(&[v, v, v, 255]);
+                }
+            }
+        }
+
+        align_slices(&mut tensor, shape, 4).unwrap();
+
+        // Every slice now has the square at the same place
+        let square_origin = |z: u32| {
+            (0..32u32 * 32)
+                .find(|&i| tensor[voxel_to_index(i % 32, i / 32, z, shape)] == 250)
+                .map(|i| (i % 32, i / 32))
+        };
+        let first = square_origin(0);
+        assert!(first.is_some());
+        for z in 1..4 {
+            assert_eq!(square_origin(z), first, "slice {z}");
+        }
+    }
+
+    fn noise_cube(shape: TensorShape) -> Vec<u8> {
+        (0..shape.total_elements() as u32)
+            .flat_map(|i| {
+                let v = (i.wrapping_mul(2654435761) >> 24) as u8;
+                [v, v / 2, 255 - v, 255]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_mirror_octants_is_symmetric_on_every_axis() {
+        let shape = TensorShape { width: 8, height: 6, frames: 4 };
+        let out = apply_effect(&noise_cube(shape), shape, VoxelEffect::MirrorOctants).unwrap();
+
+        for z in 0..4 {
+            for y in 0..6 {
+                for x in 0..8 {
+                    let voxel = &out[voxel_to_index(x, y, z, shape)..][..4];
+                    assert_eq!(voxel, &out[voxel_to_index(7 - x, y, z, shape)..][..4]);
+                    assert_eq!(voxel, &out[voxel_to_index(x, 5 - y, z, shape)..][..4]);
+                    assert_eq!(voxel, &out[voxel_to_index(x, y, 3 - z, shape)..][..4]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_kaleidoscope_has_quarter_turn_symmetry() {
+        let shape = TensorShape { width: 33, height: 33, frames: 1 };
+        let out = apply_effect(&noise_cube(shape), shape, VoxelEffect::Kaleidoscope).unwrap();
+
+        // Rotating 90° about the centre maps (x, y) to (32 - y, x); angle rounding
+        // may flip a handful of pixels on the wedge boundaries
+        let matching = (0..33u32 * 33)
+            .filter(|&i| {
+                let (x, y) = (i % 33, i / 33);
+                out[voxel_to_index(x, y, 0, shape)..][..4] == out[voxel_to_index(32 - y, x, 0, shape)..][..4]
+            })
+            .count();
+        assert!(matching * 100 >= 33 * 33 * 95, "{matching} of {}", 33 * 33);
+    }
+
+    #[test]
+    fn test_radial_repeat_keeps_inner_disc() {
+        let shape = TensorShape { width: 32, height: 32, frames: 2 };
+        let cube = noise_cube(shape);
+        let out = apply_effect(&cube, shape, VoxelEffect::RadialRepeat).unwrap();
+
+        // Rings are 8 pixels wide; everything inside the first one is the input
+        for y in 0..32u32 {
+            for x in 0..32u32 {
+                if (x as f32 - 15.5).hypot(y as f32 - 15.5) < 7.0 {
+                    let i = voxel_to_index(x, y, 1, shape);
+                    assert_eq!(out[i..i + 4], cube[i..i + 4], "({x}, {y})");
+                }
+            }
+        }
+        assert!(apply_effect(&cube[4..], shape, VoxelEffect::RadialRepeat).is_err());
+    }
+
+    #[test]
+    fn test_time_effects_read_along_skewed_planes() {
+        // Every voxel of slice z is gray level z * 50, so the source slice shows in the output
+        let shape = TensorShape { width: 4, height: 2, frames: 4 };
+        let cube: Vec<u8> = (0..shape.total_elements()).flat_map(|i| [(i / 8 * 50) as u8; 3].into_iter().chain([255])).collect();
+        let slice_of = |out: &[u8], x: u32, z: u32| out[voxel_to_index(x, 1, z, shape)] / 50;
+
+        // Column x reads x slices ahead, wrapping at the end of the loop
+        let slit = apply_effect(&cube, shape, VoxelEffect::SlitScan).unwrap();
+        assert_eq!((0..4).map(|x| slice_of(&slit, x, 0)).collect::<Vec<_>>(), [0, 1, 2, 3]);
+        assert_eq!((0..4).map(|x| slice_of(&slit, x, 2)).collect::<Vec<_>>(), [2, 3, 0, 1]);
+
+        // Brighter slices reach further back: slice 3 lags two, black slice 0 stays put
+        let displaced = apply_effect(&cube, shape, VoxelEffect::TimeDisplacement).unwrap();
+        assert_eq!((0..4).map(|z| slice_of(&displaced, 1, z)).collect::<Vec<_>>(), [0, 0, 1, 1]);
+    }
+
+    fn occupancy(mode: OccupancyMode, threshold: f32, key_color: u32) -> OccupancyOpts {
+        OccupancyOpts { mode, threshold, key_color }
+    }
+
+    #[test]
+    fn test_luminance_occupancy_clears_dark_voxels() {
+        let shape = TensorShape { width: 2, height: 1, frames: 1 };
+        let mut tensor = vec![10, 10, 10, 255, 200, 200, 200, 255];
+        compute_occupancy(&mut tensor, shape, &occupancy(OccupancyMode::Luminance, 0.5, 0)).unwrap();
+        assert_eq!((tensor[3], tensor[7]), (0, 255));
+    }
+
+    #[test]
+    fn test_chroma_key_clears_key_color() {
+        let shape = TensorShape { width: 3, height: 1, frames: 1 };
+        let mut tensor = vec![0, 250, 5, 255, 10, 240, 0, 255, 200, 30, 40, 255];
+        compute_occupancy(&mut tensor, shape, &occupancy(OccupancyMode::ChromaKey, 0.1, 0x00FF00)).unwrap();
+        assert_eq!((tensor[3], tensor[7], tensor[11]), (0, 0, 255));
+    }
+
+    #[test]
+    fn test_motion_occupancy_keeps_changing_voxels() {
+        let shape = TensorShape { width: 2, height: 1, frames: 3 };
+        // Left voxel is static, right voxel flickers
+        let mut tensor = vec![
+            50, 50, 50, 255, 0, 0, 0, 255,
+            50, 50, 50, 255, 255, 255, 255, 255,
+            50, 50, 50, 255, 0, 0, 0, 255,
+        ];
+        compute_occupancy(&mut tensor, shape, &occupancy(OccupancyMode::Motion, 0.2, 0)).unwrap();
+        let alphas: Vec<u8> = tensor.chunks_exact(4).map(|px| px[3]).collect();
+        assert_eq!(alphas, vec![0, 255, 0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn test_motion_energy_measures_slice_change() {
+        let shape = TensorShape { width: 2, height: 1, frames: 2 };
+        let tensor = vec![
+            10, 10, 10, 255, 0, 0, 0, 255,
+            10, 10, 10, 255, 90, 0, 30, 255,
+        ];
+        let energy = motion_energy(&tensor, shape).unwrap();
+        assert_eq!(energy, vec![0, 0, 0, 40]);
+    }
+
+    #[test]
+    fn test_color_histogram_counts_bins() {
+        let red = [250u8, 2, 1, 255];
+        let gray = [128u8, 128, 128, 255];
+        let hidden = [0u8, 255, 0, 0];
+        let frame_a: Vec<u8> = [red, red, red, gray].concat();
+        let frame_b: Vec<u8> = [red, hidden].concat();
+
+        let cube = color_histogram(&[&frame_a, &frame_b]);
+        let shape = TensorShape::cube(HISTOGRAM_BINS as u32);
+        assert_eq!(cube.len(), shape.total_elements() * 4);
+
+        let red_bin = voxel_to_index(62, 0, 0, shape);
+        assert_eq!(cube[red_bin..red_bin + 4], [250, 2, 2, 255]);
+        let gray_bin = voxel_to_index(32, 32, 32, shape);
+        assert_eq!(cube[gray_bin + 3], (2f32.ln() / 5f32.ln() * 255.0).round() as u8);
+        // Transparent pixels are not counted
+        let green_bin = voxel_to_index(0, 63, 0, shape);
+        assert_eq!(cube[green_bin + 3], 0);
+        assert_eq!(cube.chunks_exact(4).filter(|v| v[3] != 0).count(), 2);
+    }
+
+    #[test]
+    fn test_hilbert_order_steps_to_neighbours() {
+        let shape = TensorShape::cube(8);
+        let order = curve_order(shape, TensorLayout::Hilbert);
+
+        let coords = |i: u32| ((i % 8) as i32, (i / 8 % 8) as i32, (i / 64) as i32);
+        for pair in order.windows(2) {
+            let (a, b) = (coords(pair[0]), coords(pair[1]));
+            let step = (a.0 - b.0).abs() + (a.1 - b.1).abs() + (a.2 - b.2).abs();
+            assert_eq!(step, 1, "{a:?} -> {b:?}");
+        }
+    }
+
+    #[test]
+    fn test_morton_order_visits_octants_in_turn() {
+        let order = curve_order(TensorShape::cube(2), TensorLayout::Morton);
+        assert_eq!(order, vec![0, 1, 2, 3, 4, 5, 6, 7]);
+
+        // The first 8 voxels of a 4³ cube are its first 2³ block
+        let order = curve_order(TensorShape::cube(4), TensorLayout::Morton);
+        assert_eq!(order[..8], [0, 1, 4, 5, 16, 17, 20, 21]);
+    }
+
+    #[test]
+    fn test_reslice_plays_the_cube_sideways() {
+        // 1-byte voxels numbered in frame-major order: value = z * 6 + y * 3 + x
+        let shape = TensorShape::new(3, 2, 4);
+        let cube: Vec<u8> = (0..24).collect();
+
+        let (x_slices, x_shape) = reslice_to_frames(&cube, shape, 1, SliceAxis::X).unwrap();
+        assert_eq!((x_shape.width, x_shape.height, x_shape.frames), (4, 2, 3));
+        // Column 1 through time: top row then bottom row
+        assert_eq!(x_slices[8..16], [1, 7, 13, 19, 4, 10, 16, 22]);
+
+        let (y_slices, y_shape) = reslice_to_frames(&cube, shape, 1, SliceAxis::Y).unwrap();
+        assert_eq!((y_shape.width, y_shape.height, y_shape.frames), (3, 4, 2));
+        assert_eq!(y_slices[12..], [3, 4, 5, 9, 10, 11, 15, 16, 17, 21, 22, 23]);
+
+        let rgba = noise_cube(shape);
+        assert_eq!(reslice_to_frames(&rgba, shape, 4, SliceAxis::Z).unwrap().0, rgba);
+        assert_eq!(reslice_to_frames(&rgba, shape, 4, SliceAxis::X).unwrap().0[4..8], rgba[6 * 4..7 * 4]);
+        assert!(reslice_to_frames(&cube, shape, 4, SliceAxis::Y).is_err());
+    }
+
+    #[test]
+    fn test_relayout_round_trips_any_shape() {
+        let shape = TensorShape { width: 5, height: 3, frames: 6 };
+        let cube = noise_cube(shape);
+
+        for layout in [TensorLayout::Morton, TensorLayout::Hilbert] {
+            let curved = relayout(cube.clone(), shape, 4, TensorLayout::FrameMajor, layout).unwrap();
+            assert_ne!(curved, cube);
+            let other = relayout(curved.clone(), shape, 4, layout, TensorLayout::Morton).unwrap();
+            let back = relayout(other, shape, 4, TensorLayout::Morton, TensorLayout::FrameMajor).unwrap();
+            assert_eq!(back, cube);
+        }
+        assert!(relayout(cube, shape, 1, TensorLayout::FrameMajor, TensorLayout::Hilbert).is_err());
+    }
+}
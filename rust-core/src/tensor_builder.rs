@@ -0,0 +1,125 @@
+// Incremental Tensor Building
+// Resamples each captured frame into a cube slice as it arrives
+
+use crate::tensor::{resample_slice, SLICE_SIDE};
+use crate::{ProcessorError, Result};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Upper bound on buffered slices, matching the capture ring
+const MAX_SLICES: usize = 1024;
+
+const SLICE_BYTES: usize = (SLICE_SIDE * SLICE_SIDE * 4) as usize;
+
+/// Rolling frame-major tensor built one frame at a time
+///
+/// Each pushed frame is resampled straight to a 128×128 slice, so the cube is
+/// ready as soon as capture stops. Once `capacity` slices are held the oldest
+/// is dropped. `finish` returns the raw tensor; alignment, occupancy and layout
+/// options are applied by the processing pipeline, not here.
+pub struct TensorBuilder {
+    capacity: usize,
+    slices: Mutex<VecDeque<Vec<u8>>>,
+}
+
+impl TensorBuilder {
+    pub fn new(capacity: u32) -> Result<Self> {
+        let capacity = capacity as usize;
+        if capacity == 0 || capacity > MAX_SLICES {
+            return Err(ProcessorError::invalid_input("tensor builder", format!("capacity {} is outside 1-{}", capacity, MAX_SLICES)));
+        }
+
+        Ok(Self {
+            capacity,
+            slices: Mutex::new(VecDeque::with_capacity(capacity)),
+        })
+    }
+
+    /// Resample one RGBA frame into the next slice
+    pub fn push_frame(&self, frame_rgba: Vec<u8>, width: u32, height: u32) -> Result<()> {
+        self.push_rgba(&frame_rgba, width, height)
+    }
+
+    pub(crate) fn push_rgba(&self, frame_rgba: &[u8], width: u32, height: u32) -> Result<()> {
+        if width == 0 || height == 0 || frame_rgba.len() != (width * height * 4) as usize {
+            return Err(ProcessorError::invalid_input("tensor builder", format!("{} bytes for a {}x{} frame", frame_rgba.len(), width, height)));
+        }
+
+        let mut slices = self.slices.lock().map_err(|e| ProcessorError::memory("tensor builder", e))?;
+        // Reuse the evicted slice's allocation
+        let mut slice = if slices.len() == self.capacity {
+            slices.pop_front().unwrap_or_default()
+        } else {
+            Vec::with_capacity(SLICE_BYTES)
+        };
+
+        slice.clear();
+        if width == SLICE_SIDE && height == SLICE_SIDE {
+            slice.extend_from_slice(frame_rgba);
+        } else {
+            resample_slice(frame_rgba, width, height, SLICE_SIDE, SLICE_SIDE, &mut slice);
+        }
+        slices.push_back(slice);
+        Ok(())
+    }
+
+    /// Slices currently held
+    pub fn frame_count(&self) -> u32 {
+        self.slices.lock().map(|slices| slices.len() as u32).unwrap_or(0)
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut slices) = self.slices.lock() {
+            slices.clear();
+        }
+    }
+
+    /// Tensor of the most recent `frame_count` slices (or all, if fewer are held)
+    pub fn finish(&self, frame_count: u32) -> Result<Vec<u8>> {
+        let slices = self.slices.lock().map_err(|e| ProcessorError::memory("tensor builder", e))?;
+        let taken = (frame_count as usize).min(slices.len());
+        if taken == 0 {
+            return Err(ProcessorError::invalid_input("tensor builder", "no frames pushed"));
+        }
+
+        let mut tensor = Vec::with_capacity(taken * SLICE_BYTES);
+        for slice in slices.iter().skip(slices.len() - taken) {
+            tensor.extend_from_slice(slice);
+        }
+        Ok(tensor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slices_are_resampled_on_push() {
+        let builder = TensorBuilder::new(4).unwrap();
+        builder.push_frame(vec![9; 64 * 32 * 4], 64, 32).unwrap();
+        builder.push_frame(vec![3; SLICE_BYTES], 128, 128).unwrap();
+
+        let tensor = builder.finish(8).unwrap();
+        assert_eq!(tensor.len(), 2 * SLICE_BYTES);
+        assert!(tensor[..SLICE_BYTES].iter().all(|&b| b == 9));
+        assert!(tensor[SLICE_BYTES..].iter().all(|&b| b == 3));
+    }
+
+    #[test]
+    fn test_oldest_slice_is_dropped_at_capacity() {
+        let builder = TensorBuilder::new(2).unwrap();
+        for value in 1..=3u8 {
+            builder.push_frame(vec![value; 16], 2, 2).unwrap();
+        }
+        assert_eq!(builder.frame_count(), 2);
+
+        let tensor = builder.finish(1).unwrap();
+        assert_eq!(tensor.len(), SLICE_BYTES);
+        assert_eq!(tensor[0], 3);
+
+        builder.clear();
+        assert!(builder.finish(1).is_err());
+        assert!(TensorBuilder::new(0).is_err());
+    }
+}
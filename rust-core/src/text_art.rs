@@ -0,0 +1,240 @@
+// Text Art
+// Frames as characters or emoji, picked per cell by brightness and color, and the same art drawn back into pixels
+
+use crate::oklab_quantization::{srgb_to_oklab_batch, OklabColor};
+use crate::resize;
+use crate::{TextArtOpts, TextArtStyle};
+use std::fmt::Write;
+
+/// Characters from empty to full, each with a 5×7 bitmap, top row first and the high bit leftmost
+const RAMP: [(char, [u8; 7]); 10] = [
+    (' ', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100]),
+    (':', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000]),
+    ('-', [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000]),
+    ('=', [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000]),
+    ('+', [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000]),
+    ('*', [0b00000, 0b10101, 0b01110, 0b11111, 0b01110, 0b10101, 0b00000]),
+    ('#', [0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010]),
+    ('%', [0b11001, 0b11010, 0b00010, 0b00100, 0b01000, 0b01011, 0b10011]),
+    ('@', [0b01110, 0b10001, 0b10111, 0b10101, 0b10111, 0b10000, 0b01111]),
+];
+
+/// Colored squares and the color each is drawn in, as emoji fonts commonly show them
+const EMOJI: [(&str, [u8; 3]); 9] = [
+    ("🟥", [221, 46, 68]),
+    ("🟧", [244, 144, 12]),
+    ("🟨", [253, 203, 88]),
+    ("🟩", [120, 177, 89]),
+    ("🟦", [85, 172, 238]),
+    ("🟪", [170, 142, 214]),
+    ("🟫", [193, 105, 79]),
+    ("⬛", [49, 55, 61]),
+    ("⬜", [230, 231, 232]),
+];
+
+/// Pixels per character cell in the rendered frames; text cells are twice as tall as wide, like a terminal's
+const GLYPH_CELL: (u32, u32) = (6, 12);
+const EMOJI_CELL: (u32, u32) = (12, 12);
+
+/// Cells with less alpha than this are left blank
+const MIN_ALPHA: u8 = 128;
+
+/// Columns and rows for a `width`×`height` frame, keeping its aspect on screen
+///
+/// Characters are about twice as tall as they are wide, so text art has half
+/// as many rows as a square grid would; emoji are about square, two
+/// columns wide each.
+pub fn grid(width: u32, height: u32, opts: &TextArtOpts) -> (u32, u32) {
+    let columns = opts.columns as u32;
+    let (cell_w, cell_h) = cell_size(opts.style);
+    let rows = (height as u64 * columns as u64 * cell_w as u64 * 2 / (width as u64 * cell_h as u64)).div_ceil(2);
+    (columns, (rows as u32).max(1))
+}
+
+/// Pixel size of the rendered frames for a `columns`×`rows` grid
+pub fn rendered_size(columns: u32, rows: u32, style: TextArtStyle) -> (u32, u32) {
+    let (cell_w, cell_h) = cell_size(style);
+    (columns * cell_w, rows * cell_h)
+}
+
+fn cell_size(style: TextArtStyle) -> (u32, u32) {
+    match style {
+        TextArtStyle::Ascii | TextArtStyle::Ansi => GLYPH_CELL,
+        TextArtStyle::Emoji => EMOJI_CELL,
+    }
+}
+
+/// Glyph of a cell too transparent to draw
+const BLANK: u8 = 0xFF;
+
+/// One frame's art: the glyph picked for each cell, row by row, and the cell's average color
+pub struct Cells {
+    pub columns: u32,
+    pub glyphs: Vec<u8>,         // Index into RAMP, or EMOJI for Emoji; BLANK for none
+    pub colors: Vec<[u8; 3]>,
+}
+
+/// Average each cell of a `width`×`height` frame and pick its glyph
+///
+/// Text styles pick by OKLab lightness, densest for the brightest cells as on
+/// a dark terminal (or the darkest with `invert`). Emoji pick the nearest
+/// square in OKLab.
+pub fn cells(frame: &[u8], width: u32, height: u32, opts: &TextArtOpts) -> Cells {
+    let (columns, rows) = grid(width, height, opts);
+    let mut averaged = Vec::new();
+    resize::area_resample(frame, width, height, columns, rows, &mut averaged);
+
+    let emoji_lab = srgb_to_oklab_batch(&EMOJI.iter().flat_map(|(_, [r, g, b])| [*r, *g, *b, 255]).collect::<Vec<u8>>());
+    let lab = srgb_to_oklab_batch(&averaged);
+    let glyphs = averaged
+        .chunks_exact(4)
+        .zip(&lab)
+        .map(|(px, color)| {
+            if px[3] < MIN_ALPHA {
+                return BLANK;
+            }
+            match opts.style {
+                TextArtStyle::Ascii | TextArtStyle::Ansi => {
+                    let lightness = if opts.invert { 1.0 - color.l } else { color.l };
+                    (lightness.clamp(0.0, 1.0) * (RAMP.len() - 1) as f32).round() as u8
+                }
+                TextArtStyle::Emoji => {
+                    let distance = |e: &OklabColor| {
+                        (e.l - color.l).powi(2) + (e.a - color.a).powi(2) + (e.b - color.b).powi(2)
+                    };
+                    (0..EMOJI.len()).min_by(|&a, &b| distance(&emoji_lab[a]).total_cmp(&distance(&emoji_lab[b]))).unwrap_or(0) as u8
+                }
+            }
+        })
+        .collect();
+    let colors = averaged.chunks_exact(4).map(|px| [px[0], px[1], px[2]]).collect();
+    Cells { columns, glyphs, colors }
+}
+
+/// The cells as lines of text, each ending in a newline
+///
+/// Ansi colors each run of characters with 24-bit escapes and resets at the
+/// end of every line. Blank emoji cells are two spaces, the width of an emoji.
+pub fn to_text(cells: &Cells, style: TextArtStyle) -> String {
+    let mut text = String::new();
+    for (glyphs, colors) in cells.glyphs.chunks(cells.columns as usize).zip(cells.colors.chunks(cells.columns as usize)) {
+        let mut current: Option<[u8; 3]> = None;
+        for (&glyph, &color) in glyphs.iter().zip(colors) {
+            match style {
+                TextArtStyle::Emoji => text.push_str(if glyph == BLANK { "  " } else { EMOJI[glyph as usize].0 }),
+                TextArtStyle::Ascii => text.push(if glyph == BLANK { ' ' } else { RAMP[glyph as usize].0 }),
+                TextArtStyle::Ansi if glyph == BLANK => text.push(' '),
+                TextArtStyle::Ansi => {
+                    if current != Some(color) {
+                        let _ = write!(text, "\x1b[38;2;{};{};{}m", color[0], color[1], color[2]);
+                        current = Some(color);
+                    }
+                    text.push(RAMP[glyph as usize].0);
+                }
+            }
+        }
+        if current.is_some() {
+            text.push_str("\x1b[0m");
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// The cells drawn as an RGBA frame of `rendered_size`, on black
+///
+/// Ascii glyphs are white, Ansi ones take their cell's color, and emoji are
+/// squares with the corners clipped.
+pub fn render(cells: &Cells, style: TextArtStyle) -> Vec<u8> {
+    let rows = (cells.glyphs.len() / cells.columns.max(1) as usize) as u32;
+    let (width, height) = rendered_size(cells.columns, rows, style);
+    let (cell_w, cell_h) = cell_size(style);
+    let mut out = [0, 0, 0, 255].repeat((width * height) as usize);
+
+    for (i, (&glyph, &color)) in cells.glyphs.iter().zip(&cells.colors).enumerate() {
+        if glyph == BLANK {
+            continue;
+        }
+        let (left, top) = ((i as u32 % cells.columns) * cell_w, (i as u32 / cells.columns) * cell_h);
+        let mut plot = |x: u32, y: u32, rgb: [u8; 3]| {
+            let at = (((top + y) * width + left + x) * 4) as usize;
+            out[at..at + 3].copy_from_slice(&rgb);
+        };
+        match style {
+            TextArtStyle::Ascii | TextArtStyle::Ansi => {
+                let ink = if style == TextArtStyle::Ascii { [255, 255, 255] } else { color };
+                // The 5×7 bitmap stretched over the cell's rows, a blank row above and below
+                let bitmap = RAMP[glyph as usize].1;
+                for y in 1..cell_h - 1 {
+                    let bits = bitmap[((y - 1) * 7 / (cell_h - 2)) as usize];
+                    for x in (0..5).filter(|x| bits & (0b10000 >> x) != 0) {
+                        plot(x, y, ink);
+                    }
+                }
+            }
+            TextArtStyle::Emoji => {
+                let edge = cell_w - 2;
+                for y in 1..=edge {
+                    for x in 1..=edge {
+                        let corner = (x == 1 || x == edge) && (y == 1 || y == edge);
+                        if !corner {
+                            plot(x, y, EMOJI[glyph as usize].1);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opts(style: TextArtStyle, columns: u16) -> TextArtOpts {
+        TextArtOpts { style, columns, ..Default::default() }
+    }
+
+    /// 4×1 frame: black, white, transparent and red
+    fn frame() -> Vec<u8> {
+        [[0, 0, 0, 255], [255, 255, 255, 255], [0, 0, 0, 0], [255, 0, 0, 255]].concat()
+    }
+
+    #[test]
+    fn test_cells_pick_glyphs_by_lightness_and_color() {
+        // Text rows are half as many as emoji rows for the same frame
+        assert_eq!(grid(160, 90, &opts(TextArtStyle::Ascii, 80)), (80, 23));
+        assert_eq!(grid(160, 90, &opts(TextArtStyle::Emoji, 80)), (80, 45));
+        assert_eq!(grid(4, 1, &opts(TextArtStyle::Ascii, 4)), (4, 1));
+
+        let ascii = cells(&frame(), 4, 1, &opts(TextArtStyle::Ascii, 4));
+        assert_eq!(to_text(&ascii, TextArtStyle::Ascii), " @ *\n");
+        let inverted = cells(&frame(), 4, 1, &TextArtOpts { invert: true, ..opts(TextArtStyle::Ascii, 4) });
+        assert_eq!(to_text(&inverted, TextArtStyle::Ascii), "@  -\n");
+        let emoji = cells(&frame(), 4, 1, &opts(TextArtStyle::Emoji, 4));
+        assert_eq!(to_text(&emoji, TextArtStyle::Emoji), "⬛⬜  🟥\n");
+    }
+
+    #[test]
+    fn test_ansi_colors_runs_and_render_draws_cells() {
+        let red = cells(&[255, 0, 0, 255].repeat(8), 4, 2, &opts(TextArtStyle::Ansi, 2));
+        // One escape for the run, a reset at the end of the line
+        assert_eq!(to_text(&red, TextArtStyle::Ansi), "\x1b[38;2;255;0;0m**\x1b[0m\n");
+
+        let rendered = render(&red, TextArtStyle::Ansi);
+        assert_eq!(rendered.len(), 2 * 6 * 12 * 4);
+        assert!(rendered.chunks_exact(4).any(|px| px[..3] == [255, 0, 0]));
+        assert!(rendered.chunks_exact(4).all(|px| px[..3] == [255, 0, 0] || px[..3] == [0, 0, 0]));
+
+        let blue = EMOJI[4].1;
+        let squares = cells(&[blue[0], blue[1], blue[2], 255].repeat(4), 2, 2, &opts(TextArtStyle::Emoji, 1));
+        let rendered = render(&squares, TextArtStyle::Emoji);
+        assert_eq!(rendered.len(), 12 * 12 * 4);
+        // The square fills the middle; its clipped corner and the border stay black
+        assert_eq!(rendered[(6 * 12 + 6) * 4..][..3], blue);
+        assert_eq!(rendered[(12 + 1) * 4..][..3], [0, 0, 0]);
+        assert_eq!(rendered[..3], [0, 0, 0]);
+    }
+}
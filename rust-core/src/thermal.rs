@@ -0,0 +1,96 @@
+// Thermal Throttling
+// Host-reported thermal state scales back threads, quantizer speed and optional passes
+
+use crate::{DecimationStrategy, DistanceMetric, GifOpts, QuantizeOpts, ThermalState};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Process-wide, like the OS thermal state it mirrors
+static THERMAL_STATE: AtomicU8 = AtomicU8::new(ThermalState::Nominal as u8);
+
+pub fn set(state: ThermalState) {
+    THERMAL_STATE.store(state as u8, Ordering::Relaxed);
+}
+
+pub fn current() -> ThermalState {
+    match THERMAL_STATE.load(Ordering::Relaxed) {
+        0 => ThermalState::Nominal,
+        1 => ThermalState::Fair,
+        2 => ThermalState::Serious,
+        _ => ThermalState::Critical,
+    }
+}
+
+/// Worker threads allowed at `state`, or `None` for no cap
+pub fn thread_limit(state: ThermalState, cores: usize) -> Option<usize> {
+    match state {
+        ThermalState::Nominal => None,
+        ThermalState::Fair => Some(cores.saturating_sub(1).max(1)),
+        ThermalState::Serious => Some((cores / 2).max(1)),
+        ThermalState::Critical => Some(1),
+    }
+}
+
+/// Options adjusted for `state`
+///
+/// Fair only nudges imagequant faster. Serious also drops the costly optional
+/// passes: ΔE2000 matching, content-aware decimation, sharpening, slice
+/// alignment and the motion volume. Critical runs at the fastest speed and skips loop smoothing.
+/// Requested outputs (GIF, variants, tensor) are always produced.
+pub fn throttle(quantize_opts: QuantizeOpts, gif_opts: GifOpts, state: ThermalState) -> (QuantizeOpts, GifOpts) {
+    let mut quantize_opts = quantize_opts;
+    let mut gif_opts = gif_opts;
+
+    let min_speed = match state {
+        ThermalState::Nominal => return (quantize_opts, gif_opts),
+        ThermalState::Fair => 6,
+        ThermalState::Serious => 8,
+        ThermalState::Critical => 10,
+    };
+    quantize_opts.speed = quantize_opts.speed.max(min_speed);
+
+    if state >= ThermalState::Serious {
+        quantize_opts.distance_metric = DistanceMetric::Euclidean;
+        gif_opts.decimation = DecimationStrategy::Uniform;
+        gif_opts.sharpen_amount = 0.0;
+        gif_opts.align_tensor_slices = false;
+        gif_opts.include_motion = false;
+    }
+    if state == ThermalState::Critical {
+        gif_opts.seamless_loop = false;
+    }
+    (quantize_opts, gif_opts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hotter_states_throttle_harder() {
+        assert_eq!(thread_limit(ThermalState::Nominal, 6), None);
+        assert_eq!(thread_limit(ThermalState::Fair, 6), Some(5));
+        assert_eq!(thread_limit(ThermalState::Serious, 6), Some(3));
+        assert_eq!(thread_limit(ThermalState::Critical, 6), Some(1));
+        assert_eq!(thread_limit(ThermalState::Fair, 1), Some(1));
+
+        let gif_opts = GifOpts {
+            include_motion: true,
+            seamless_loop: true,
+            decimation: DecimationStrategy::MotionWeighted,
+            ..Default::default()
+        };
+        let quantize_opts = QuantizeOpts { speed: 3, ..Default::default() };
+
+        let (q, g) = throttle(quantize_opts.clone(), gif_opts.clone(), ThermalState::Nominal);
+        assert_eq!((q.speed, g.include_motion), (3, true));
+
+        let (q, g) = throttle(quantize_opts.clone(), gif_opts.clone(), ThermalState::Serious);
+        assert_eq!(q.speed, 8);
+        assert!(!g.include_motion && g.seamless_loop);
+        assert_eq!(g.decimation, DecimationStrategy::Uniform);
+
+        let (q, g) = throttle(quantize_opts, gif_opts, ThermalState::Critical);
+        assert_eq!(q.speed, 10);
+        assert!(!g.seamless_loop);
+    }
+}
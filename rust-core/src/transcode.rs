@@ -0,0 +1,234 @@
+// GIF Transcoding
+// Trims, crops and retimes an encoded GIF by reusing its palettes and indices
+
+use crate::{frame_delays, CropRect, ProcessorError, Result, TranscodeOpts};
+use gif::{ColorOutput, DecodeOptions, DisposalMethod, Encoder, Frame, Repeat};
+use std::borrow::Cow;
+
+/// Palette a canvas pixel was drawn with: 0 = global, n = frame n - 1's local table
+type PaletteId = usize;
+
+/// Re-encode `gif_data` without requantizing
+///
+/// Frames are composited in index space exactly as a viewer would (offsets,
+/// transparency, disposal), so a trimmed range can start on a partial frame.
+/// Each kept frame is written as a full canvas with its original palette and
+/// the result is pixel-identical to the source. The one thing index space
+/// can't express is a frame showing pixels from two different color tables
+/// (a local-palette frame partly over another); that fails with
+/// `InvalidInput`, as it would need requantization.
+pub fn transcode(gif_data: &[u8], opts: &TranscodeOpts) -> Result<Vec<u8>> {
+    let mut options = DecodeOptions::new();
+    options.set_color_output(ColorOutput::Indexed);
+    let mut decoder = options.read_info(gif_data).map_err(|e| ProcessorError::invalid_input("transcode", e))?;
+    let (canvas_w, canvas_h) = (decoder.width() as usize, decoder.height() as usize);
+    let global_palette = decoder.global_palette().map(|p| p.to_vec());
+
+    let crop = opts.crop.unwrap_or(CropRect {
+        x: 0,
+        y: 0,
+        width: canvas_w as u16,
+        height: canvas_h as u16,
+    });
+    let (crop_x, crop_y, crop_w, crop_h) = (crop.x as usize, crop.y as usize, crop.width as usize, crop.height as usize);
+    if crop_w == 0 || crop_h == 0 || crop_x + crop_w > canvas_w || crop_y + crop_h > canvas_h {
+        return Err(ProcessorError::invalid_input("transcode", format!("crop is outside the {}x{} canvas", canvas_w, canvas_h)));
+    }
+
+    let first = opts.first_frame as usize;
+    let end = match opts.frame_count {
+        0 => usize::MAX,
+        count => first + count as usize,
+    };
+
+    let mut canvas: Vec<Option<(PaletteId, u8)>> = vec![None; canvas_w * canvas_h];
+    // Last transparent index seen for each palette, to mark canvas holes with
+    let mut transparent_for: Vec<Option<u8>> = vec![None];
+    let mut local_palettes: Vec<Vec<u8>> = Vec::new();
+    let mut kept: Vec<(PaletteId, Option<u8>, Vec<u8>, u16)> = Vec::new();
+
+    let mut index = 0;
+    while let Some(frame) = decoder.read_next_frame().map_err(|e| ProcessorError::invalid_input("transcode", e).at_frame(index))? {
+        if index >= end {
+            break;
+        }
+        let palette_id = match &frame.palette {
+            Some(palette) => {
+                local_palettes.push(palette.clone());
+                transparent_for.push(None);
+                local_palettes.len()
+            }
+            None if global_palette.is_some() => 0,
+            None => return Err(ProcessorError::invalid_input("transcode", "frame has no color table").at_frame(index)),
+        };
+        if frame.transparent.is_some() {
+            transparent_for[palette_id] = frame.transparent;
+        }
+
+        let under = (frame.dispose == DisposalMethod::Previous).then(|| canvas.clone());
+        let (left, top, width) = (frame.left as usize, frame.top as usize, frame.width as usize);
+        let covered: Vec<(usize, u8)> = frame
+            .buffer
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &color)| {
+                let (x, y) = (left + i % width, top + i / width);
+                (x < canvas_w && y < canvas_h).then_some((y * canvas_w + x, color))
+            })
+            .collect();
+        for &(at, color) in &covered {
+            if Some(color) != frame.transparent {
+                canvas[at] = Some((palette_id, color));
+            }
+        }
+
+        if index >= first {
+            kept.push(crop_frame(&canvas, canvas_w, &crop, &transparent_for, frame.delay).map_err(|e| e.at_frame(index))?);
+        }
+
+        match frame.dispose {
+            DisposalMethod::Background => covered.iter().for_each(|&(at, _)| canvas[at] = None),
+            DisposalMethod::Previous => canvas = under.unwrap_or(canvas),
+            _ => {}
+        }
+        index += 1;
+    }
+    if kept.is_empty() {
+        return Err(ProcessorError::invalid_input("transcode", "no frames in the requested range"));
+    }
+
+    let delays = match opts.fps {
+        0 => kept.iter().map(|(_, _, _, delay)| *delay).collect(),
+        fps => frame_delays(fps, kept.len())?,
+    };
+
+    let mut out = Vec::new();
+    {
+        let mut encoder = Encoder::new(&mut out, crop.width, crop.height, global_palette.as_deref().unwrap_or(&[]))
+            .map_err(|e| ProcessorError::encoding("transcode", e))?;
+        let repeat = match opts.loop_count {
+            0 => Repeat::Infinite,
+            count => Repeat::Finite(count),
+        };
+        encoder.set_repeat(repeat).map_err(|e| ProcessorError::encoding("transcode", e))?;
+
+        for (i, ((palette_id, transparent, indices, _), delay)) in kept.into_iter().zip(delays).enumerate() {
+            let frame = Frame {
+                width: crop.width,
+                height: crop.height,
+                delay,
+                // Full-canvas frames; clearing after each keeps holes see-through
+                dispose: DisposalMethod::Background,
+                transparent,
+                palette: match palette_id {
+                    0 => None,
+                    id => Some(local_palettes[id - 1].clone()),
+                },
+                buffer: Cow::Owned(indices),
+                ..Default::default()
+            };
+            encoder.write_frame(&frame).map_err(|e| ProcessorError::encoding("transcode", e).at_frame(first + i))?;
+        }
+    }
+    Ok(out)
+}
+
+/// Indices of the cropped canvas, with the palette they all belong to
+fn crop_frame(
+    canvas: &[Option<(PaletteId, u8)>],
+    canvas_w: usize,
+    crop: &CropRect,
+    transparent_for: &[Option<u8>],
+    delay: u16,
+) -> Result<(PaletteId, Option<u8>, Vec<u8>, u16)> {
+    let (x0, y0) = (crop.x as usize, crop.y as usize);
+    let region = (y0..y0 + crop.height as usize)
+        .flat_map(|y| (x0..x0 + crop.width as usize).map(move |x| canvas[y * canvas_w + x]));
+
+    let mut palette_id = None;
+    let mut has_holes = false;
+    for pixel in region.clone() {
+        match pixel {
+            Some((id, _)) if palette_id.is_some_and(|current| current != id) => {
+                return Err(ProcessorError::invalid_input("transcode", "crop mixes pixels from different color tables"))
+            }
+            Some((id, _)) => palette_id = Some(id),
+            None => has_holes = true,
+        }
+    }
+
+    // An empty region still needs some palette; holes then cover everything
+    let palette_id = palette_id.unwrap_or_else(|| transparent_for.iter().position(Option::is_some).unwrap_or(0));
+    let transparent = if has_holes {
+        Some(transparent_for[palette_id].ok_or_else(|| ProcessorError::invalid_input("transcode", "no transparent index for uncovered pixels"))?)
+    } else {
+        None
+    };
+    let hole = transparent.unwrap_or(0);
+    let indices = region.map(|pixel| pixel.map_or(hole, |(_, color)| color)).collect();
+    Ok((palette_id, transparent, indices, delay))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_rgba(gif_data: &[u8]) -> (u16, u16, Vec<(u16, Vec<u8>)>) {
+        let mut options = DecodeOptions::new();
+        options.set_color_output(ColorOutput::RGBA);
+        let mut decoder = options.read_info(gif_data).unwrap();
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frames.push((frame.delay, frame.buffer.to_vec()));
+        }
+        (decoder.width(), decoder.height(), frames)
+    }
+
+    /// 4×2 red canvas, then a 1×1 blue patch at (3, 1), then a 2×1 green patch
+    fn partial_frames_gif() -> Vec<u8> {
+        let palette = [255, 0, 0, 0, 0, 255, 0, 255, 0, 0, 0, 0];
+        let mut out = Vec::new();
+        {
+            let mut encoder = Encoder::new(&mut out, 4, 2, &palette).unwrap();
+            let frames = [
+                Frame { width: 4, height: 2, delay: 4, buffer: vec![0; 8].into(), ..Default::default() },
+                Frame { left: 3, top: 1, width: 1, height: 1, delay: 5, buffer: vec![1].into(), ..Default::default() },
+                Frame { left: 0, top: 0, width: 2, height: 1, delay: 6, buffer: vec![2, 2].into(), ..Default::default() },
+            ];
+            for frame in &frames {
+                encoder.write_frame(frame).unwrap();
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_trim_and_crop_keep_pixels() {
+        let opts = TranscodeOpts {
+            first_frame: 1,
+            frame_count: 0,
+            fps: 0,
+            loop_count: 3,
+            crop: Some(CropRect { x: 1, y: 0, width: 3, height: 2 }),
+        };
+        let (width, height, frames) = decode_rgba(&transcode(&partial_frames_gif(), &opts).unwrap());
+        assert_eq!((width, height, frames.len()), (3, 2, 2));
+
+        let colors = |frame: &[u8]| -> Vec<u8> { frame.chunks_exact(4).map(|p| p[0] / 255 + p[1] / 255 * 2 + p[2] / 255 * 4).collect() };
+        // Red = 1, green = 2, blue = 4, laid out over the 3×2 crop
+        assert_eq!((frames[0].0, frames[1].0), (5, 6));
+        assert_eq!(colors(&frames[0].1), vec![1, 1, 1, 1, 1, 4]);
+        assert_eq!(colors(&frames[1].1), vec![2, 1, 1, 1, 1, 4]);
+    }
+
+    #[test]
+    fn test_retime_and_reject_bad_crop() {
+        let opts = TranscodeOpts { first_frame: 0, frame_count: 2, fps: 50, loop_count: 0, crop: None };
+        let (_, _, frames) = decode_rgba(&transcode(&partial_frames_gif(), &opts).unwrap());
+        assert_eq!(frames.iter().map(|f| f.0).collect::<Vec<_>>(), vec![2, 2]);
+
+        let crop = Some(CropRect { x: 2, y: 0, width: 3, height: 2 });
+        assert!(transcode(&partial_frames_gif(), &TranscodeOpts { crop, ..opts.clone() }).is_err());
+        assert!(transcode(&partial_frames_gif(), &TranscodeOpts { first_frame: 3, ..opts }).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+// Turntable Preview
+// Raycasts the voxel cube from a circling camera, so galleries can show it without the 3D viewer
+
+use crate::tensor::{self, TensorShape};
+use crate::{OccupancyOpts, Result, TurntableOpts};
+use rayon::prelude::*;
+
+/// Largest volume side the preview is cast through; previews are small, so finer detail never shows
+const VOLUME_SIDE: u32 = 64;
+
+/// Steps per voxel along each ray
+const STEPS_PER_VOXEL: f32 = 2.0;
+
+/// Accumulated opacity at which a ray stops
+const OPAQUE: f32 = 0.99;
+
+/// Half the cube's diagonal, so the cube stays in view at every angle
+const VIEW_EXTENT: f32 = 1.732_051;
+
+/// The clip as a volume: frames become Z slices, at most `VOLUME_SIDE` voxels along each axis
+///
+/// `occupancy` keys voxels out the same way it does for the tensor, so the
+/// preview shows the object the viewer would.
+pub fn volume(frames: &[&[u8]], width: u32, height: u32, occupancy: &OccupancyOpts) -> Result<(Vec<u8>, TensorShape)> {
+    let depth = (frames.len() as u32).min(VOLUME_SIDE);
+    let shape = TensorShape::new(width.min(VOLUME_SIDE), height.min(VOLUME_SIDE), depth);
+    let mut volume = Vec::with_capacity(shape.total_elements() * 4);
+    for z in 0..depth as usize {
+        let frame = frames[z * frames.len() / depth as usize];
+        tensor::resample_slice(frame, width, height, shape.width, shape.height, &mut volume);
+    }
+    tensor::compute_occupancy(&mut volume, shape, occupancy)?;
+    Ok((volume, shape))
+}
+
+/// One full turn around the volume's vertical axis, `opts.frame_count` RGBA frames back to back
+///
+/// The camera is orthographic and starts facing the first slice, raised by
+/// `opts.elevation_deg`. Rays composite voxels front to back through their
+/// alpha; pixels that miss the cube or only pass empty voxels stay transparent.
+pub fn render(volume: &[u8], shape: TensorShape, opts: &TurntableOpts) -> Vec<u8> {
+    let size = opts.size as usize;
+    let frame_bytes = size * size * 4;
+    let mut frames = vec![0u8; frame_bytes * opts.frame_count as usize];
+
+    // A voxel's alpha is its opacity across one voxel; rays take shorter steps
+    let longest = shape.width.max(shape.height).max(shape.frames) as f32;
+    let step = 2.0 / (longest * STEPS_PER_VOXEL);
+    let opacity: Vec<f32> = (0..=255).map(|a| 1.0 - (1.0 - a as f32 / 255.0).powf(1.0 / STEPS_PER_VOXEL)).collect();
+    let (sin_pitch, cos_pitch) = opts.elevation_deg.to_radians().sin_cos();
+
+    for (i, frame) in frames.chunks_exact_mut(frame_bytes).enumerate() {
+        let yaw = std::f32::consts::TAU * i as f32 / opts.frame_count as f32;
+        let (sin_yaw, cos_yaw) = yaw.sin_cos();
+        // Camera space to volume space: pitch about X, then yaw about Y
+        let rotate = |[x, y, z]: [f32; 3]| {
+            let (y, z) = (y * cos_pitch - z * sin_pitch, y * sin_pitch + z * cos_pitch);
+            [x * cos_yaw + z * sin_yaw, y, z * cos_yaw - x * sin_yaw]
+        };
+        let direction = rotate([0.0, 0.0, 1.0]);
+
+        frame.par_chunks_exact_mut(size * 4).enumerate().for_each(|(py, row)| {
+            let v = (1.0 - 2.0 * (py as f32 + 0.5) / size as f32) * VIEW_EXTENT;
+            for (px, out) in row.chunks_exact_mut(4).enumerate() {
+                let u = (2.0 * (px as f32 + 0.5) / size as f32 - 1.0) * VIEW_EXTENT;
+                let origin = rotate([u, v, -2.0 * VIEW_EXTENT]);
+                out.copy_from_slice(&cast(volume, shape, origin, direction, step, &opacity));
+            }
+        });
+    }
+    frames
+}
+
+/// Composite one ray through the volume, which spans -1 to 1 on every axis
+fn cast(volume: &[u8], shape: TensorShape, origin: [f32; 3], direction: [f32; 3], step: f32, opacity: &[f32]) -> [u8; 4] {
+    let Some((enter, exit)) = box_span(origin, direction) else {
+        return [0; 4];
+    };
+    let voxel = |c: f32, side: u32| ((c.clamp(0.0, 1.0) * side as f32) as usize).min(side as usize - 1);
+    let (mut color, mut alpha) = ([0.0f32; 3], 0.0f32);
+    let mut t = enter + step * 0.5;
+    while t < exit && alpha < OPAQUE {
+        let point = [0, 1, 2].map(|axis| origin[axis] + direction[axis] * t);
+        // Image rows run down while Y runs up; Z runs from the first slice to the last
+        let x = voxel((point[0] + 1.0) * 0.5, shape.width);
+        let y = voxel((1.0 - point[1]) * 0.5, shape.height);
+        let z = voxel((point[2] + 1.0) * 0.5, shape.frames);
+        let i = ((z * shape.height as usize + y) * shape.width as usize + x) * 4;
+        let rgba = &volume[i..i + 4];
+        let a = opacity[rgba[3] as usize] * (1.0 - alpha);
+        for (c, &v) in color.iter_mut().zip(rgba) {
+            *c += a * v as f32;
+        }
+        alpha += a;
+        t += step;
+    }
+    if alpha <= 0.0 {
+        return [0; 4];
+    }
+    let [r, g, b] = color.map(|c| (c / alpha).round().min(255.0) as u8);
+    [r, g, b, (alpha * 255.0).round() as u8]
+}
+
+/// Where a ray enters and leaves the -1 to 1 cube, if it hits it
+fn box_span(origin: [f32; 3], direction: [f32; 3]) -> Option<(f32, f32)> {
+    let (mut enter, mut exit) = (f32::NEG_INFINITY, f32::INFINITY);
+    for axis in 0..3 {
+        if direction[axis].abs() < 1e-6 {
+            if origin[axis].abs() > 1.0 {
+                return None;
+            }
+            continue;
+        }
+        let (a, b) = ((-1.0 - origin[axis]) / direction[axis], (1.0 - origin[axis]) / direction[axis]);
+        enter = enter.max(a.min(b));
+        exit = exit.min(a.max(b));
+    }
+    (enter < exit && exit > 0.0).then_some((enter.max(0.0), exit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TurntableFormat;
+
+    /// Solid slices, each filled with its own red level
+    fn slices(count: usize, side: u32) -> Vec<Vec<u8>> {
+        (0..count).map(|z| [(z * 255 / (count - 1)) as u8, 40, 90, 255].repeat((side * side) as usize)).collect()
+    }
+
+    fn opts(frame_count: u16, elevation_deg: f32) -> TurntableOpts {
+        TurntableOpts { size: 33, frame_count, fps: 30, elevation_deg, format: TurntableFormat::Rgba }
+    }
+
+    fn pixel(frames: &[u8], frame: usize, x: usize, y: usize) -> &[u8] {
+        let i = ((frame * 33 + y) * 33 + x) * 4;
+        &frames[i..i + 4]
+    }
+
+    #[test]
+    fn test_camera_circles_from_first_slice_to_last() {
+        let frames = slices(8, 16);
+        let refs: Vec<&[u8]> = frames.iter().map(|f| f.as_slice()).collect();
+        let (volume, shape) = volume(&refs, 16, 16, &OccupancyOpts::default()).unwrap();
+        assert_eq!((shape.width, shape.height, shape.frames), (16, 16, 8));
+
+        let rendered = render(&volume, shape, &opts(4, 0.0));
+        assert_eq!(rendered.len(), 4 * 33 * 33 * 4);
+        // Facing the first slice, then after half a turn the last one
+        assert_eq!(pixel(&rendered, 0, 16, 16), [0, 40, 90, 255]);
+        assert_eq!(pixel(&rendered, 2, 16, 16), [255, 40, 90, 255]);
+        // The view fits the cube's diagonal, so corners straight on are empty
+        assert_eq!(pixel(&rendered, 0, 0, 0), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_keyed_voxels_let_rays_through() {
+        // Only the last slice survives a luminance key
+        let mut frames = slices(4, 8);
+        for frame in &mut frames[..3] {
+            frame.chunks_exact_mut(4).for_each(|px| px[..3].copy_from_slice(&[0, 0, 0]));
+        }
+        let refs: Vec<&[u8]> = frames.iter().map(|f| f.as_slice()).collect();
+        let occupancy = OccupancyOpts { mode: crate::OccupancyMode::Luminance, threshold: 0.1, ..Default::default() };
+        let (volume, shape) = volume(&refs, 8, 8, &occupancy).unwrap();
+
+        let rendered = render(&volume, shape, &opts(1, 30.0));
+        assert_eq!(pixel(&rendered, 0, 16, 16), [255, 40, 90, 255]);
+
+        let empty = vec![0u8; volume.len()];
+        assert!(render(&empty, shape, &opts(2, 30.0)).iter().all(|&b| b == 0));
+    }
+}
@@ -0,0 +1,300 @@
+// Option Validation
+// Up-front sanity checks that name the offending field instead of failing mid-pipeline
+
+use crate::{CacheConfig, CompositeClip, CompositeLayout, CompositeOpts, CropRect, GifOpts, OversizePolicy, ProcessorError, QuantizeOpts, Result, ScaleMode};
+
+/// Largest input or output side accepted, in pixels
+pub const MAX_DIMENSION: u32 = 8192;
+
+/// Largest GIF side written; above it `OversizePolicy` downscales or rejects
+pub const MAX_GIF_SIDE: u32 = 4096;
+
+/// Highest frame rate accepted; delays are capped at 50fps anyway
+pub const MAX_FPS: u16 = 100;
+
+fn invalid(field: &str, reason: impl Into<String>) -> ProcessorError {
+    ProcessorError::InvalidOption {
+        field: field.to_string(),
+        reason: reason.into(),
+    }
+}
+
+/// Everything `process_all_frames` is given, checked before any work starts
+pub fn validate(
+    frames_len: usize,
+    width: u32,
+    height: u32,
+    frame_count: u32,
+    quantize_opts: &QuantizeOpts,
+    gif_opts: &GifOpts,
+) -> Result<()> {
+    input(frames_len, width, height, frame_count)?;
+    quantize(quantize_opts)?;
+    gif(gif_opts)?;
+    if let Some(roi) = &gif_opts.roi {
+        region(roi, width, height)?;
+    }
+    Ok(())
+}
+
+/// Dimensions and the frame buffer length they imply
+pub fn input(frames_len: usize, width: u32, height: u32, frame_count: u32) -> Result<()> {
+    for (field, side) in [("width", width), ("height", height)] {
+        if side == 0 || side > MAX_DIMENSION {
+            return Err(invalid(field, format!("{} is outside 1-{}", side, MAX_DIMENSION)));
+        }
+    }
+    if frame_count == 0 {
+        return Err(invalid("frame_count", "at least one frame is required"));
+    }
+    let expected = width as u64 * height as u64 * 4 * frame_count as u64;
+    if frames_len as u64 != expected {
+        return Err(invalid(
+            "frames_rgba",
+            format!("{} bytes for {} {}x{} RGBA frames, expected {}", frames_len, frame_count, width, height, expected),
+        ));
+    }
+    Ok(())
+}
+
+pub fn quantize(opts: &QuantizeOpts) -> Result<()> {
+    if opts.quality_max > 100 {
+        return Err(invalid("quantize_opts.quality_max", format!("{} is over 100", opts.quality_max)));
+    }
+    if opts.quality_min > opts.quality_max {
+        return Err(invalid(
+            "quantize_opts.quality_min",
+            format!("{} is above quality_max {}", opts.quality_min, opts.quality_max),
+        ));
+    }
+    if !(1..=10).contains(&opts.speed) {
+        return Err(invalid("quantize_opts.speed", format!("{} is outside 1-10", opts.speed)));
+    }
+    if !(2..=256).contains(&opts.palette_size) {
+        return Err(invalid("quantize_opts.palette_size", format!("{} is outside 2-256", opts.palette_size)));
+    }
+    if let Some(locked) = &opts.locked_palette {
+        let opaque = locked.chunks_exact(4).filter(|c| c[3] != 0).count();
+        if locked.len() % 4 != 0 || locked.len() > 256 * 4 || opaque == 0 {
+            return Err(invalid(
+                "quantize_opts.locked_palette",
+                format!("{} bytes is not 1-256 RGBA entries with at least one opaque color", locked.len()),
+            ));
+        }
+    }
+    if !(opts.sample_fraction > 0.0 && opts.sample_fraction <= 1.0) {
+        return Err(invalid("quantize_opts.sample_fraction", format!("{} is outside (0, 1]", opts.sample_fraction)));
+    }
+    if opts.lut_resolution == 1 || opts.lut_resolution > 64 {
+        return Err(invalid("quantize_opts.lut_resolution", format!("{} is not 0 or 2-64", opts.lut_resolution)));
+    }
+    if !(0.0..=10.0).contains(&opts.motion_weight) {
+        return Err(invalid("quantize_opts.motion_weight", format!("{} is outside 0-10", opts.motion_weight)));
+    }
+    unit_range("quantize_opts.dithering_level", opts.dithering_level)?;
+    unit_range("quantize_opts.edge_preservation", opts.edge_preservation)
+}
+
+pub fn gif(opts: &GifOpts) -> Result<()> {
+    for (field, side) in [("gif_opts.width", opts.width), ("gif_opts.height", opts.height)] {
+        if side == 0 {
+            return Err(invalid(field, "must be at least 1"));
+        }
+        if side as u32 > MAX_GIF_SIDE && opts.oversize == OversizePolicy::Reject {
+            return Err(invalid(field, format!("{} is over {}; OversizePolicy::Downscale would fit it", side, MAX_GIF_SIDE)));
+        }
+    }
+    if !(1..=MAX_FPS).contains(&opts.fps) {
+        return Err(invalid("gif_opts.fps", format!("{} is outside 1-{}", opts.fps, MAX_FPS)));
+    }
+    if let Some(range) = &opts.frame_range {
+        if range.start >= range.end {
+            return Err(invalid("gif_opts.frame_range", format!("start {} is not before end {}", range.start, range.end)));
+        }
+    }
+    if let Some(i) = opts.frame_transforms.iter().position(|t| crate::warp::invert(t).is_none()) {
+        return Err(invalid("gif_opts.frame_transforms", format!("transform {} is not finite and invertible", i)));
+    }
+    if !opts.frame_transforms.is_empty() && opts.scale_mode != ScaleMode::Area {
+        return Err(invalid("gif_opts.frame_transforms", "warps need ScaleMode::Area"));
+    }
+    if !(opts.sharpen_amount >= 0.0 && opts.sharpen_amount.is_finite()) {
+        return Err(invalid("gif_opts.sharpen_amount", format!("{} is negative or not finite", opts.sharpen_amount)));
+    }
+    if opts.sharpen_amount > 0.0 && !(opts.sharpen_radius > 0.0 && opts.sharpen_radius.is_finite()) {
+        return Err(invalid("gif_opts.sharpen_radius", format!("{} is not a positive blur radius", opts.sharpen_radius)));
+    }
+    if !(0.0..=1.0).contains(&opts.letterbox.dim) {
+        return Err(invalid("gif_opts.letterbox.dim", format!("{} is outside 0-1", opts.letterbox.dim)));
+    }
+    let preprocess = &opts.preprocess;
+    for (field, bits) in [
+        ("gif_opts.preprocess.posterize_red_bits", preprocess.posterize_red_bits),
+        ("gif_opts.preprocess.posterize_green_bits", preprocess.posterize_green_bits),
+        ("gif_opts.preprocess.posterize_blue_bits", preprocess.posterize_blue_bits),
+    ] {
+        if !(1..=8).contains(&bits) {
+            return Err(invalid(field, format!("{} is outside 1-8", bits)));
+        }
+    }
+    let accessibility = &opts.accessibility;
+    if accessibility.preserve_contrast && !(0.0..=0.5).contains(&accessibility.min_contrast) {
+        return Err(invalid("gif_opts.accessibility.min_contrast", format!("{} is outside 0-0.5", accessibility.min_contrast)));
+    }
+    if opts.include_long_exposure && opts.long_exposure.build_up_frames > 360 {
+        return Err(invalid("gif_opts.long_exposure.build_up_frames", format!("{} is over 360", opts.long_exposure.build_up_frames)));
+    }
+    if opts.include_text_art && !(4..=320).contains(&opts.text_art.columns) {
+        return Err(invalid("gif_opts.text_art.columns", format!("{} is outside 4-320", opts.text_art.columns)));
+    }
+    let audio = &opts.audio_sync;
+    if let Some(i) = audio.energy.iter().position(|e| !(*e >= 0.0 && e.is_finite())) {
+        return Err(invalid("gif_opts.audio_sync.energy", format!("value {} is {}, not a finite non-negative energy", i, audio.energy[i])));
+    }
+    if !(0.0..=0.95).contains(&audio.timing) {
+        return Err(invalid("gif_opts.audio_sync.timing", format!("{} is outside 0-0.95", audio.timing)));
+    }
+    unit_range("gif_opts.audio_sync.effect_gain", audio.effect_gain)?;
+    if opts.include_turntable {
+        let turntable = &opts.turntable;
+        if !(16..=1024).contains(&turntable.size) {
+            return Err(invalid("gif_opts.turntable.size", format!("{} is outside 16-1024", turntable.size)));
+        }
+        if !(1..=360).contains(&turntable.frame_count) {
+            return Err(invalid("gif_opts.turntable.frame_count", format!("{} is outside 1-360", turntable.frame_count)));
+        }
+        if !(1..=MAX_FPS).contains(&turntable.fps) {
+            return Err(invalid("gif_opts.turntable.fps", format!("{} is outside 1-{}", turntable.fps, MAX_FPS)));
+        }
+        if !(-89.0..=89.0).contains(&turntable.elevation_deg) {
+            return Err(invalid("gif_opts.turntable.elevation_deg", format!("{} is outside -89 to 89", turntable.elevation_deg)));
+        }
+    }
+    for variant in &opts.variants {
+        if variant.width == 0 || variant.height == 0 {
+            return Err(invalid("gif_opts.variants", format!("{}x{} has an empty side", variant.width, variant.height)));
+        }
+        if variant.width.max(variant.height) as u32 > MAX_GIF_SIDE && opts.oversize == OversizePolicy::Reject {
+            return Err(invalid("gif_opts.variants", format!("{}x{} is over {} a side", variant.width, variant.height, MAX_GIF_SIDE)));
+        }
+    }
+    Ok(())
+}
+
+/// The cache needs somewhere to live
+pub fn cache(config: &CacheConfig) -> Result<()> {
+    if config.directory.is_empty() {
+        return Err(invalid("cache.directory", "is empty"));
+    }
+    Ok(())
+}
+
+/// `roi` must be non-empty and inside the `width`×`height` input
+pub fn region(roi: &CropRect, width: u32, height: u32) -> Result<()> {
+    let (right, bottom) = (roi.x as u32 + roi.width as u32, roi.y as u32 + roi.height as u32);
+    if roi.width == 0 || roi.height == 0 || right > width || bottom > height {
+        return Err(invalid(
+            "gif_opts.roi",
+            format!("{}x{} at ({}, {}) doesn't fit a {}x{} frame", roi.width, roi.height, roi.x, roi.y, width, height),
+        ));
+    }
+    Ok(())
+}
+
+/// Both clips and the layout `compose_clips` is given, against the canvas `gif_opts` sets
+pub fn composite(primary: &CompositeClip, secondary: &CompositeClip, opts: &CompositeOpts, gif_opts: &GifOpts) -> Result<()> {
+    gif(gif_opts)?;
+    for (name, clip) in [("primary", primary), ("secondary", secondary)] {
+        input(clip.frames_rgba.len(), clip.width, clip.height, clip.frame_count).map_err(|e| match e {
+            ProcessorError::InvalidOption { field, reason } => invalid(&format!("{}.{}", name, field), reason),
+            e => e,
+        })?;
+        if clip.scale_mode == ScaleMode::PixelArt {
+            return Err(invalid(&format!("{}.scale_mode", name), "panes scale with Area or Fit"));
+        }
+        if let Some(roi) = &clip.roi {
+            let (right, bottom) = (roi.x as u32 + roi.width as u32, roi.y as u32 + roi.height as u32);
+            if roi.width == 0 || roi.height == 0 || right > clip.width || bottom > clip.height {
+                return Err(invalid(
+                    &format!("{}.roi", name),
+                    format!("{}x{} at ({}, {}) doesn't fit a {}x{} frame", roi.width, roi.height, roi.x, roi.y, clip.width, clip.height),
+                ));
+            }
+        }
+    }
+    let (width, height, gap) = (gif_opts.width as u32, gif_opts.height as u32, opts.gap as u32);
+    match opts.layout {
+        CompositeLayout::SideBySide if gap + 2 > width => {
+            Err(invalid("composite_opts.gap", format!("{} leaves no room for two panes {} wide", gap, width)))
+        }
+        CompositeLayout::Stacked if gap + 2 > height => {
+            Err(invalid("composite_opts.gap", format!("{} leaves no room for two panes {} high", gap, height)))
+        }
+        CompositeLayout::PictureInPicture if !(0.05..=1.0).contains(&opts.inset_width) => {
+            Err(invalid("composite_opts.inset_width", format!("{} is outside 0.05-1", opts.inset_width)))
+        }
+        CompositeLayout::PictureInPicture if 2 * opts.inset_margin as u32 >= width.min(height) => {
+            Err(invalid("composite_opts.inset_margin", format!("{} leaves no room for an inset in {}x{}", opts.inset_margin, width, height)))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn unit_range(field: &str, value: f32) -> Result<()> {
+    if !(0.0..=1.0).contains(&value) {
+        return Err(invalid(field, format!("{} is outside 0-1", value)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LetterboxOpts, PreprocessOpts};
+
+    fn field_of(result: Result<()>) -> String {
+        match result {
+            Err(ProcessorError::InvalidOption { field, .. }) => field,
+            other => panic!("expected InvalidOption, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_defaults_pass() {
+        let len = 16 * 16 * 4 * 2;
+        assert!(validate(len, 16, 16, 2, &QuantizeOpts::default(), &GifOpts::default()).is_ok());
+    }
+
+    #[test]
+    fn test_reports_offending_field() {
+        assert_eq!(field_of(input(100, 16, 16, 2)), "frames_rgba");
+        assert_eq!(field_of(input(0, 0, 16, 0)), "width");
+        assert_eq!(field_of(input(0, 16, 16, 0)), "frame_count");
+
+        let quantize_opts = |opts: QuantizeOpts| field_of(quantize(&opts));
+        assert_eq!(quantize_opts(QuantizeOpts { palette_size: 1, ..Default::default() }), "quantize_opts.palette_size");
+        assert_eq!(quantize_opts(QuantizeOpts { palette_size: 300, ..Default::default() }), "quantize_opts.palette_size");
+        assert_eq!(quantize_opts(QuantizeOpts { dithering_level: 1.5, ..Default::default() }), "quantize_opts.dithering_level");
+        assert_eq!(quantize_opts(QuantizeOpts { dithering_level: f32::NAN, ..Default::default() }), "quantize_opts.dithering_level");
+        assert_eq!(quantize_opts(QuantizeOpts { quality_min: 90, quality_max: 80, ..Default::default() }), "quantize_opts.quality_min");
+        assert_eq!(quantize_opts(QuantizeOpts { speed: 0, ..Default::default() }), "quantize_opts.speed");
+        assert_eq!(quantize_opts(QuantizeOpts { lut_resolution: 1, ..Default::default() }), "quantize_opts.lut_resolution");
+        assert!(quantize(&QuantizeOpts { lut_resolution: 0, ..Default::default() }).is_ok());
+
+        let gif_opts = |opts: GifOpts| field_of(gif(&opts));
+        assert_eq!(gif_opts(GifOpts { fps: 0, ..Default::default() }), "gif_opts.fps");
+        assert_eq!(gif_opts(GifOpts { fps: 101, ..Default::default() }), "gif_opts.fps");
+        assert_eq!(gif_opts(GifOpts { height: 0, ..Default::default() }), "gif_opts.height");
+        let oversize = OversizePolicy::Reject;
+        assert_eq!(gif_opts(GifOpts { width: 5000, oversize, ..Default::default() }), "gif_opts.width");
+        assert!(gif(&GifOpts { width: 5000, ..Default::default() }).is_ok());
+        let posterize_to = |bits| PreprocessOpts { posterize_green_bits: bits, ..Default::default() };
+        assert_eq!(gif_opts(GifOpts { preprocess: posterize_to(0), ..Default::default() }), "gif_opts.preprocess.posterize_green_bits");
+        let letterbox = LetterboxOpts { dim: 1.5, ..Default::default() };
+        assert_eq!(gif_opts(GifOpts { letterbox, ..Default::default() }), "gif_opts.letterbox.dim");
+        assert!(gif(&GifOpts { fps: 100, ..Default::default() }).is_ok());
+
+        let roi = CropRect { x: 8, y: 0, width: 10, height: 4 };
+        assert_eq!(field_of(region(&roi, 16, 16)), "gif_opts.roi");
+    }
+}
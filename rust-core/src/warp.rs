@@ -0,0 +1,134 @@
+// Per-Frame Warps
+// Host-supplied affine transforms (stabilization, horizon lock, spin correction) applied while resizing
+
+use crate::resize::cover_rect;
+use crate::AffineTransform;
+
+/// Most taps per axis averaged into one output pixel
+const MAX_TAPS: usize = 4;
+
+/// The transform that undoes `t`, if it has one
+pub fn invert(t: &AffineTransform) -> Option<AffineTransform> {
+    let det = t.a * t.d - t.b * t.c;
+    if !det.is_normal() || ![t.a, t.b, t.c, t.d, t.tx, t.ty].iter().all(|v| v.is_finite()) {
+        return None;
+    }
+    let (a, b, c, d) = (t.d / det, -t.b / det, -t.c / det, t.a / det);
+    Some(AffineTransform { a, b, c, d, tx: -(a * t.tx + c * t.ty), ty: -(b * t.tx + d * t.ty) })
+}
+
+fn apply(t: &AffineTransform, x: f32, y: f32) -> (f32, f32) {
+    (t.a * x + t.c * y + t.tx, t.b * x + t.d * y + t.ty)
+}
+
+/// `cover_resize_region` on the frame as `transform` moves it
+///
+/// The region and the output aspect crop are taken in the transformed frame.
+/// Each output pixel averages a grid of bilinear taps, about one per source
+/// pixel it covers, so a downscale stays filtered however far the frame turns.
+/// Taps that land outside the frame read transparent; colors are averaged
+/// weighted by alpha so those edges don't darken. A transform without an
+/// inverse leaves the frame as it is.
+pub fn cover_warp_region(
+    src: &[u8],
+    src_w: u32,
+    region: (u32, u32, u32, u32),
+    transform: &AffineTransform,
+    dst_w: u32,
+    dst_h: u32,
+    out: &mut Vec<u8>,
+) {
+    let inverse = invert(transform).unwrap_or_default();
+    let src_h = (src.len() / (src_w as usize * 4)) as u32;
+    let (left, top, rect_w, rect_h) = cover_rect(region, dst_w, dst_h);
+    let (step_x, step_y) = (rect_w as f32 / dst_w as f32, rect_h as f32 / dst_h as f32);
+    let taps_x = (step_x.ceil() as usize).clamp(1, MAX_TAPS);
+    let taps_y = (step_y.ceil() as usize).clamp(1, MAX_TAPS);
+
+    out.clear();
+    out.reserve((dst_w * dst_h * 4) as usize);
+    for y in 0..dst_h {
+        for x in 0..dst_w {
+            let mut sum = [0.0f32; 4];
+            for ty in 0..taps_y {
+                for tx in 0..taps_x {
+                    let px = left as f32 + (x as f32 + (tx as f32 + 0.5) / taps_x as f32) * step_x;
+                    let py = top as f32 + (y as f32 + (ty as f32 + 0.5) / taps_y as f32) * step_y;
+                    let (sx, sy) = apply(&inverse, px, py);
+                    let tap = bilinear(src, src_w, src_h, sx - 0.5, sy - 0.5);
+                    sum.iter_mut().zip(tap).for_each(|(s, t)| *s += t);
+                }
+            }
+            let alpha = sum[3] / (taps_x * taps_y) as f32;
+            let color = |c: f32| if sum[3] > 0.0 { (c / sum[3] * 255.0).round().min(255.0) as u8 } else { 0 };
+            out.extend_from_slice(&[color(sum[0]), color(sum[1]), color(sum[2]), alpha.round() as u8]);
+        }
+    }
+}
+
+/// Alpha-premultiplied sample between the four pixels around `(x, y)`; pixels off the frame are transparent
+fn bilinear(src: &[u8], width: u32, height: u32, x: f32, y: f32) -> [f32; 4] {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let mut sample = [0.0f32; 4];
+    for (dy, wy) in [(0, 1.0 - fy), (1, fy)] {
+        for (dx, wx) in [(0, 1.0 - fx), (1, fx)] {
+            let (px, py) = (x0 as i64 + dx, y0 as i64 + dy);
+            if px < 0 || py < 0 || px >= width as i64 || py >= height as i64 {
+                continue;
+            }
+            let i = ((py as usize * width as usize) + px as usize) * 4;
+            let weight = wx * wy * src[i + 3] as f32;
+            for c in 0..3 {
+                sample[c] += weight * src[i + c] as f32 / 255.0;
+            }
+            sample[3] += weight;
+        }
+    }
+    sample
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 8×8 frame, left half red and right half blue
+    fn halves() -> Vec<u8> {
+        (0..64).flat_map(|i| if i % 8 < 4 { [255, 0, 0, 255] } else { [0, 0, 255, 255] }).collect()
+    }
+
+    fn rotation(degrees: f32, center: f32) -> AffineTransform {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        AffineTransform { a: cos, b: sin, c: -sin, d: cos, tx: center - cos * center + sin * center, ty: center - sin * center - cos * center }
+    }
+
+    #[test]
+    fn test_identity_matches_plain_resize_and_half_turn_swaps_sides() {
+        let src = halves();
+        let mut out = Vec::new();
+        cover_warp_region(&src, 8, (0, 0, 8, 8), &AffineTransform::default(), 2, 2, &mut out);
+        assert_eq!(out, [[255, 0, 0, 255], [0, 0, 255, 255]].repeat(2).concat());
+
+        // Half a turn about the centre puts blue on the left
+        cover_warp_region(&src, 8, (0, 0, 8, 8), &rotation(180.0, 4.0), 2, 2, &mut out);
+        assert_eq!(out, [[0, 0, 255, 255], [255, 0, 0, 255]].repeat(2).concat());
+    }
+
+    #[test]
+    fn test_corners_turned_off_the_frame_go_transparent() {
+        let src = [255u8, 255, 255, 255].repeat(64);
+        let mut out = Vec::new();
+        cover_warp_region(&src, 8, (0, 0, 8, 8), &rotation(45.0, 4.0), 8, 8, &mut out);
+        assert_eq!(&out[..4], [0, 0, 0, 0]);
+        // Partly covered pixels keep their color, only alpha drops
+        assert!(out.chunks_exact(4).all(|px| px[3] == 0 || px[..3] == [255, 255, 255]));
+        assert_eq!(&out[(4 * 8 + 4) * 4..(4 * 8 + 5) * 4], [255, 255, 255, 255]);
+
+        let singular = AffineTransform { a: 0.0, d: 0.0, ..Default::default() };
+        assert!(invert(&singular).is_none());
+        let turn = rotation(30.0, 4.0);
+        let (x, y) = apply(&turn, 1.0, 2.0);
+        let (x, y) = apply(&invert(&turn).unwrap(), x, y);
+        assert!((x - 1.0).abs() < 1e-5 && (y - 2.0).abs() < 1e-5, "{x}, {y}");
+    }
+}
@@ -33,6 +33,7 @@ fn test_single_ffi_256_frames() {
         palette_size: 256,
         dithering_level: 1.0,
         shared_palette: true,
+        ..Default::default()
     };
 
     let gif_opts = GifOpts {
@@ -43,6 +44,7 @@ fn test_single_ffi_256_frames() {
         loop_count: 0,
         optimize: true,
         include_tensor: false,
+        ..Default::default()
     };
 
     let start = Instant::now();
@@ -84,6 +86,7 @@ fn test_with_tensor_output() {
         palette_size: 256,
         dithering_level: 0.5,
         shared_palette: false,
+        ..Default::default()
     };
 
     let gif_opts = GifOpts {
@@ -94,6 +97,7 @@ fn test_with_tensor_output() {
         loop_count: 0,
         optimize: false,
         include_tensor: true,  // Request tensor
+        ..Default::default()
     };
 
     let result = process_all_frames(
@@ -134,16 +138,18 @@ fn test_performance_targets() {
             palette_size: 256,
             dithering_level: 0.0,
             shared_palette: true,
+            ..Default::default()
         };
 
         let gif_opts = GifOpts {
             width: 256,
             height: 256,
-            frame_count: frame_count as u16,
+            frame_count: frame_count as u32,
             fps: 25,
             loop_count: 0,
             optimize: false,
             include_tensor: false,
+            ..Default::default()
         };
 
         let start = Instant::now();
@@ -180,6 +186,7 @@ fn test_gif_validation() {
         palette_size: 128,
         dithering_level: 1.0,
         shared_palette: true,
+        ..Default::default()
     };
 
     let gif_opts = GifOpts {
@@ -190,6 +197,7 @@ fn test_gif_validation() {
         loop_count: 5,
         optimize: true,
         include_tensor: false,
+        ..Default::default()
     };
 
     let result = process_all_frames(
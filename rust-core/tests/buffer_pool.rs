@@ -0,0 +1,41 @@
+// Buffer pool tests
+// Own binary so no other test shares the process-wide pool
+
+use rgb2gif_processor::{buffer_pool_stats, release_buffer_pool, CaptureSession, GifOpts, QuantizeOpts};
+
+fn gradient(width: u32, height: u32, shift: u8) -> Vec<u8> {
+    (0..width * height)
+        .flat_map(|i| [(i % width * 255 / width) as u8, (i / width * 255 / height) as u8, shift, 255])
+        .collect()
+}
+
+#[test]
+fn test_second_capture_allocates_no_frame_buffers() {
+    let (width, height, frames) = (32u32, 32u32, 12u8);
+    // Camera frames at twice the capture size, downscaled into the ring
+    let session = CaptureSession::new(width, height, 1.0, frames as u16).expect("Session creation failed");
+    let gif_opts = GifOpts { sharpen_amount: 0.5, ..Default::default() };
+    let capture = || {
+        for shift in 0..frames {
+            session.push_frame(gradient(width * 2, height * 2, shift * 10), width * 2, height * 2).unwrap();
+        }
+        let result = session.finalize(frames as u32, QuantizeOpts::default(), gif_opts.clone()).unwrap();
+        session.clear();
+        result
+    };
+
+    let first = capture();
+    let warm = buffer_pool_stats();
+    // Ring slots, the concatenated clip and the sharpened frames
+    assert_eq!(warm.allocations, frames as u64 * 2 + 1);
+    assert!(warm.pooled_bytes > 0);
+
+    let second = capture();
+    let after = buffer_pool_stats();
+    assert_eq!(after.allocations, warm.allocations, "every buffer should come from the pool");
+    assert_eq!(after.reuses - warm.reuses, frames as u64 * 2 + 1);
+    assert_eq!(first.gif_data, second.gif_data);
+
+    release_buffer_pool();
+    assert_eq!(buffer_pool_stats().pooled_bytes, 0);
+}
@@ -32,6 +32,7 @@ fn test_basic_processing() {
         palette_size: 256,
         dithering_level: 1.0,
         shared_palette: true,
+        ..Default::default()
     };
 
     let gif_opts = GifOpts {
@@ -42,6 +43,7 @@ fn test_basic_processing() {
         loop_count: 0,
         optimize: true,
         include_tensor: false,
+        ..Default::default()
     };
 
     let result = process_all_frames(frames, 256, 256, 32, quantize_opts, gif_opts);
@@ -71,16 +73,18 @@ fn test_different_sizes() {
             palette_size: 256,
             dithering_level: 0.5,
             shared_palette: true,
+            ..Default::default()
         };
 
         let gif_opts = GifOpts {
             width: width as u16,
             height: height as u16,
-            frame_count: frame_count as u16,
+            frame_count: frame_count as u32,
             fps: 25,
             loop_count: 0,
             optimize: false,
             include_tensor: false,
+            ..Default::default()
         };
 
         let result = process_all_frames(
@@ -108,6 +112,7 @@ fn test_performance_256_frames() {
         palette_size: 256,
         dithering_level: 0.5,
         shared_palette: true,
+        ..Default::default()
     };
 
     let gif_opts = GifOpts {
@@ -118,6 +123,7 @@ fn test_performance_256_frames() {
         loop_count: 0,
         optimize: false, // Skip optimization for speed
         include_tensor: false,
+        ..Default::default()
     };
 
     let start = Instant::now();
@@ -156,6 +162,7 @@ fn test_error_handling() {
         palette_size: 256,
         dithering_level: 1.0,
         shared_palette: true,
+        ..Default::default()
     };
 
     let gif_opts = GifOpts {
@@ -166,8 +173,2162 @@ fn test_error_handling() {
         loop_count: 0,
         optimize: true,
         include_tensor: false,
+        ..Default::default()
     };
 
     let result = process_all_frames(frames, 256, 256, 0, quantize_opts, gif_opts);
     assert!(result.is_err(), "Should fail with empty input");
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_oklab_backend_reserves_transparent_index() {
+    use rgb2gif_processor::QuantizerBackend;
+
+    // Left half opaque gradient, right half fully transparent
+    let (width, height, frame_count) = (32u32, 32u32, 4usize);
+    let mut frames = create_test_frames(frame_count, width, height);
+    for (i, pixel) in frames.chunks_exact_mut(4).enumerate() {
+        if i as u32 % width >= width / 2 {
+            pixel[3] = 0;
+        }
+    }
+
+    let quantize_opts = QuantizeOpts {
+        palette_size: 16,
+        backend: QuantizerBackend::Oklab,
+        ..Default::default()
+    };
+
+    let gif_opts = GifOpts {
+        width: width as u16,
+        height: height as u16,
+        frame_count: frame_count as u32,
+        ..Default::default()
+    };
+
+    let output = process_all_frames(frames, width, height, frame_count as u32, quantize_opts, gif_opts)
+        .expect("OKLab processing failed");
+
+    let mut decoder = gif::DecodeOptions::new();
+    decoder.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = decoder.read_info(output.gif_data.as_slice()).unwrap();
+    let frame = decoder.read_next_frame().unwrap().unwrap();
+
+    let transparent = frame.transparent.expect("Transparent index should be set");
+    assert_eq!(transparent as u16, output.palette_size_used - 1);
+    assert_eq!(frame.buffer[(width - 1) as usize], transparent);
+    assert_ne!(frame.buffer[0], transparent);
+}
+
+#[test]
+fn test_preview_variant_reuses_quantized_frames() {
+    use rgb2gif_processor::GifVariant;
+
+    let frames = create_test_frames(8, 64, 64);
+
+    let gif_opts = GifOpts {
+        width: 64,
+        height: 64,
+        frame_count: 8,
+        variants: vec![GifVariant { width: 32, height: 32 }],
+        ..Default::default()
+    };
+
+    let output = process_all_frames(frames, 64, 64, 8, QuantizeOpts::default(), gif_opts)
+        .expect("Processing with variants failed");
+
+    assert_eq!(output.variants.len(), 1);
+    let preview = &output.variants[0];
+    assert_eq!((preview.width, preview.height), (32, 32));
+    assert!(preview.gif_data.len() < output.gif_data.len());
+
+    // Same global palette, same frame count, smaller canvas
+    let main = gif::DecodeOptions::new().read_info(output.gif_data.as_slice()).unwrap();
+    let mut thumb = gif::DecodeOptions::new().read_info(preview.gif_data.as_slice()).unwrap();
+    assert_eq!((thumb.width(), thumb.height()), (32, 32));
+    assert_eq!(main.global_palette(), thumb.global_palette());
+
+    let mut thumb_frames = 0;
+    while thumb.read_next_frame().unwrap().is_some() {
+        thumb_frames += 1;
+    }
+    assert_eq!(thumb_frames, 8);
+}
+
+#[test]
+fn test_oklab_dither_modes() {
+    use rgb2gif_processor::{DitherMode, QuantizerBackend};
+
+    let (width, height, frame_count) = (32u32, 32u32, 4usize);
+    let mut frames = create_test_frames(frame_count, width, height);
+    for (i, pixel) in frames.chunks_exact_mut(4).enumerate() {
+        if i as u32 % width >= width / 2 {
+            pixel[3] = 0;
+        }
+    }
+
+    for dither_mode in [
+        DitherMode::Atkinson,
+        DitherMode::Stucki,
+        DitherMode::Jarvis,
+        DitherMode::BlueNoise,
+        DitherMode::AdaptiveBlueNoise,
+    ] {
+        let quantize_opts = QuantizeOpts {
+            palette_size: 16,
+            backend: QuantizerBackend::Oklab,
+            dither_mode,
+            edge_preservation: 1.0,
+            ..Default::default()
+        };
+
+        let gif_opts = GifOpts {
+            width: width as u16,
+            height: height as u16,
+            frame_count: frame_count as u32,
+            ..Default::default()
+        };
+
+        let output = process_all_frames(frames.clone(), width, height, frame_count as u32, quantize_opts, gif_opts)
+            .expect("Dithered processing failed");
+
+        let mut decoder = gif::DecodeOptions::new();
+        decoder.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = decoder.read_info(output.gif_data.as_slice()).unwrap();
+
+        let mut decoded = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            let transparent = frame.transparent.expect("Transparent index should be set");
+            assert_eq!(frame.buffer[(width - 1) as usize], transparent);
+            decoded += 1;
+        }
+        assert_eq!(decoded, frame_count, "{:?}", dither_mode);
+    }
+}
+
+#[test]
+fn test_encoded_gifs_pass_strict_validation() {
+    use rgb2gif_processor::gif_validator::validate_gif;
+    use rgb2gif_processor::{GifVariant, QuantizerBackend};
+
+    let (width, height, frame_count) = (32u32, 32u32, 4usize);
+    let mut frames = create_test_frames(frame_count, width, height);
+    for pixel in frames.chunks_exact_mut(4).step_by(7) {
+        pixel[3] = 0;
+    }
+
+    for backend in [QuantizerBackend::Imagequant, QuantizerBackend::Oklab] {
+        let quantize_opts = QuantizeOpts {
+            backend,
+            ..Default::default()
+        };
+        let gif_opts = GifOpts {
+            width: width as u16,
+            height: height as u16,
+            frame_count: frame_count as u32,
+            variants: vec![GifVariant { width: 16, height: 16 }],
+            ..Default::default()
+        };
+
+        let output = process_all_frames(frames.clone(), width, height, frame_count as u32, quantize_opts, gif_opts)
+            .expect("Processing failed");
+
+        for gif_data in [&output.gif_data, &output.variants[0].gif_data] {
+            let report = validate_gif(gif_data, true);
+            assert!(report.findings.is_empty(), "{:?}: {:?}", backend, report.findings);
+            assert_eq!(report.frames.len(), frame_count);
+            assert_eq!(report.loop_count, Some(0));
+        }
+    }
+}
+
+#[test]
+fn test_frames_over_budget_are_decimated() {
+    use rgb2gif_processor::DecimationStrategy;
+
+    let frames = create_test_frames(16, 32, 32);
+
+    for decimation in [
+        DecimationStrategy::Uniform,
+        DecimationStrategy::MotionWeighted,
+        DecimationStrategy::SharpnessWeighted,
+    ] {
+        let gif_opts = GifOpts {
+            width: 32,
+            height: 32,
+            frame_count: 8,
+            decimation,
+            ..Default::default()
+        };
+
+        let output = process_all_frames(frames.clone(), 32, 32, 16, QuantizeOpts::default(), gif_opts)
+            .expect("Decimated processing failed");
+        assert_eq!(output.actual_frame_count, 8, "{:?}", decimation);
+
+        let mut decoder = gif::DecodeOptions::new().read_info(output.gif_data.as_slice()).unwrap();
+        let mut decoded = 0;
+        while decoder.read_next_frame().unwrap().is_some() {
+            decoded += 1;
+        }
+        assert_eq!(decoded, 8, "{:?}", decimation);
+    }
+}
+
+#[test]
+fn test_preview_quantize_viewfinder_frame() {
+    use rgb2gif_processor::{preview_quantize, PreviewOpts};
+    use std::collections::HashSet;
+
+    let frame = create_test_frames(1, 256, 256);
+    let opts = PreviewOpts {
+        max_colors: 32,
+        ..Default::default()
+    };
+
+    let preview = preview_quantize(frame, 256, 256, opts).expect("Preview failed");
+
+    assert_eq!((preview.width, preview.height), (128, 128));
+    assert_eq!(preview.rgba.len(), 128 * 128 * 4);
+
+    let colors: HashSet<&[u8]> = preview.rgba.chunks_exact(4).collect();
+    assert!(colors.len() <= 32, "{} colors", colors.len());
+
+    assert!(preview_quantize(vec![0; 10], 4, 4, PreviewOpts::default()).is_err());
+}
+
+#[test]
+fn test_capture_session_retroactive_clip() {
+    use rgb2gif_processor::CaptureSession;
+
+    // Two-second window at 16fps, camera frames at 64² downscaled to 32²
+    let session = CaptureSession::new(32, 32, 2.0, 16).expect("Session creation failed");
+    let camera = create_test_frames(40, 64, 64);
+    for frame in camera.chunks_exact(64 * 64 * 4) {
+        session.push_frame(frame.to_vec(), 64, 64).unwrap();
+    }
+    assert_eq!(session.frame_count(), 32);
+
+    let gif_opts = GifOpts {
+        include_tensor: true,
+        ..Default::default()
+    };
+    // The test gradient drifts every frame, too far for one palette to meet the default quality floor
+    let quantize_opts = QuantizeOpts {
+        quality_min: 0,
+        ..Default::default()
+    };
+    let output = session.finalize(24, quantize_opts, gif_opts)
+        .expect("Finalize failed");
+
+    assert_eq!(output.actual_frame_count, 24);
+    assert!(output.tensor_data.is_some());
+
+    let decoder = gif::DecodeOptions::new().read_info(output.gif_data.as_slice()).unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (32, 32));
+
+    // Finalizing leaves the window intact
+    assert_eq!(session.frame_count(), 32);
+    session.clear();
+    assert_eq!(session.frame_count(), 0);
+}
+
+#[test]
+fn test_concatenate_clips_with_crossfade() {
+    use rgb2gif_processor::gif_validator::validate_gif;
+    use rgb2gif_processor::{concatenate_clips, Transition, TransitionKind};
+
+    let clips: Vec<Vec<u8>> = [4u32, 6]
+        .iter()
+        .map(|&count| {
+            let gif_opts = GifOpts {
+                width: 32,
+                height: 32,
+                frame_count: count,
+                ..Default::default()
+            };
+            let quantize_opts = QuantizeOpts {
+                quality_min: 0,
+                ..Default::default()
+            };
+            process_all_frames(create_test_frames(count as usize, 32, 32), 32, 32, count, quantize_opts, gif_opts)
+                .expect("Clip encoding failed")
+                .gif_data
+        })
+        .collect();
+
+    let transition = Transition { kind: TransitionKind::Crossfade, frame_count: 3 };
+    let joined = concatenate_clips(clips, transition).expect("Concatenation failed");
+
+    let report = validate_gif(&joined, true);
+    assert!(report.findings.is_empty(), "{:?}", report.findings);
+    assert_eq!(report.frames.len(), 4 + 3 + 6);
+    assert_eq!(report.loop_count, Some(0));
+}
+
+#[test]
+fn test_seamless_loop_narrows_wraparound_jump() {
+    let decode = |data: &[u8]| -> Vec<Vec<u8>> {
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(data).unwrap();
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frames.push(frame.buffer.to_vec());
+        }
+        frames
+    };
+    let seam_jump = |frames: &[Vec<u8>]| -> u64 {
+        let (first, last) = (&frames[0], &frames[frames.len() - 1]);
+        first.iter().zip(last).map(|(&a, &b)| (a as i64 - b as i64).unsigned_abs()).sum()
+    };
+
+    let mut jumps = Vec::new();
+    for seamless_loop in [false, true] {
+        let gif_opts = GifOpts {
+            width: 32,
+            height: 32,
+            frame_count: 16,
+            seamless_loop,
+            loop_crossfade_frames: 4,
+            ..Default::default()
+        };
+        let quantize_opts = QuantizeOpts {
+            quality_min: 0,
+            ..Default::default()
+        };
+        let output = process_all_frames(create_test_frames(16, 32, 32), 32, 32, 16, quantize_opts, gif_opts)
+            .expect("Processing failed");
+
+        let frames = decode(&output.gif_data);
+        assert_eq!(frames.len(), 16);
+        jumps.push(seam_jump(&frames));
+    }
+
+    assert!(jumps[1] < jumps[0], "seam jump {} not below {}", jumps[1], jumps[0]);
+}
+
+#[test]
+fn test_tensor_slice_alignment() {
+    let gif_opts = GifOpts {
+        width: 64,
+        height: 64,
+        frame_count: 8,
+        include_tensor: true,
+        align_tensor_slices: true,
+        ..Default::default()
+    };
+    let quantize_opts = QuantizeOpts {
+        quality_min: 0,
+        ..Default::default()
+    };
+    let output = process_all_frames(create_test_frames(8, 64, 64), 64, 64, 8, quantize_opts, gif_opts)
+        .expect("Processing failed");
+
+    let tensor = output.tensor_data.expect("Tensor missing");
+    assert_eq!(tensor.len(), 128 * 128 * 8 * 4);
+    assert!(tensor.chunks_exact(4).all(|px| px[3] == 255));
+}
+
+#[test]
+fn test_voxel_effect_reencodes_cube() {
+    use rgb2gif_processor::{apply_voxel_effect, VoxelEffect};
+
+    let cube = create_test_frames(8, 32, 32);
+    let quantize_opts = QuantizeOpts {
+        quality_min: 0,
+        ..Default::default()
+    };
+    let output = apply_voxel_effect(cube.clone(), 32, 32, 8, VoxelEffect::Kaleidoscope, quantize_opts, GifOpts::default())
+        .expect("Effect failed");
+
+    let styled = output.tensor_data.expect("Tensor missing");
+    assert_eq!(styled.len(), cube.len());
+    assert_ne!(styled, cube);
+
+    let decoder = gif::DecodeOptions::new().read_info(output.gif_data.as_slice()).unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (32, 32));
+    assert_eq!(output.actual_frame_count, 8);
+}
+
+#[test]
+fn test_reslice_plays_cube_along_width() {
+    use rgb2gif_processor::{reslice_voxel_cube, SliceAxis};
+
+    let cube = create_test_frames(8, 32, 16);
+    let quantize_opts = QuantizeOpts {
+        quality_min: 0,
+        ..Default::default()
+    };
+    let gif_opts = GifOpts {
+        include_tensor: true,
+        ..Default::default()
+    };
+    let output = reslice_voxel_cube(cube.clone(), 32, 16, 8, SliceAxis::X, quantize_opts, gif_opts)
+        .expect("Reslice failed");
+
+    // One 8-wide slice through time per column
+    let decoder = gif::DecodeOptions::new().read_info(output.gif_data.as_slice()).unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (8, 16));
+    assert_eq!(output.actual_frame_count, 32);
+    let slices = output.tensor_data.expect("Tensor missing");
+    assert_eq!(slices.len(), cube.len());
+    assert_eq!(slices[4..8], cube[32 * 16 * 4..32 * 16 * 4 + 4]);
+}
+
+#[test]
+fn test_luminance_occupancy_hollows_tensor() {
+    use rgb2gif_processor::{OccupancyMode, OccupancyOpts};
+
+    let gif_opts = GifOpts {
+        width: 64,
+        height: 64,
+        frame_count: 4,
+        include_tensor: true,
+        occupancy: OccupancyOpts {
+            mode: OccupancyMode::Luminance,
+            threshold: 0.5,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let quantize_opts = QuantizeOpts {
+        quality_min: 0,
+        ..Default::default()
+    };
+    let output = process_all_frames(create_test_frames(4, 64, 64), 64, 64, 4, quantize_opts, gif_opts)
+        .expect("Processing failed");
+
+    // The gradient runs dark to bright, so the cube is partly hollow
+    let tensor = output.tensor_data.expect("Tensor missing");
+    let empty = tensor.chunks_exact(4).filter(|px| px[3] == 0).count();
+    assert!(empty > 0 && empty < tensor.len() / 4, "{empty} empty voxels");
+}
+
+#[test]
+fn test_motion_volume_alongside_tensor() {
+    let gif_opts = GifOpts {
+        width: 64,
+        height: 64,
+        frame_count: 6,
+        include_tensor: true,
+        include_motion: true,
+        ..Default::default()
+    };
+    let quantize_opts = QuantizeOpts {
+        quality_min: 0,
+        ..Default::default()
+    };
+    let output = process_all_frames(create_test_frames(6, 64, 64), 64, 64, 6, quantize_opts, gif_opts)
+        .expect("Processing failed");
+
+    let motion = output.motion_data.expect("Motion volume missing");
+    assert_eq!(motion.len(), 128 * 128 * 6);
+    // Nothing precedes the first slice; the gradient drifts on every later one
+    assert!(motion[..128 * 128].iter().all(|&e| e == 0));
+    assert!(motion[128 * 128..].iter().any(|&e| e > 0));
+}
+
+#[test]
+fn test_color_histogram_tensor_mode() {
+    use rgb2gif_processor::{build_color_histogram, TensorMode, TensorOpts};
+
+    let frames = create_test_frames(4, 64, 64);
+    let gif_opts = GifOpts {
+        width: 64,
+        height: 64,
+        frame_count: 4,
+        include_tensor: true,
+        tensor: TensorOpts {
+            mode: TensorMode::ColorHistogram,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let quantize_opts = QuantizeOpts {
+        quality_min: 0,
+        ..Default::default()
+    };
+    let output = process_all_frames(frames.clone(), 64, 64, 4, quantize_opts, gif_opts)
+        .expect("Processing failed");
+
+    let cube = output.tensor_data.expect("Histogram missing");
+    assert_eq!(cube.len(), 64 * 64 * 64 * 4);
+    assert!(cube.chunks_exact(4).any(|voxel| voxel[3] == 255));
+    assert_eq!(cube, build_color_histogram(frames, 64, 64, 4).unwrap());
+}
+
+#[test]
+fn test_hilbert_tensor_layout_converts_back() {
+    use rgb2gif_processor::{convert_tensor_layout, TensorLayout, TensorOpts};
+
+    let mut tensors = Vec::new();
+    for layout in [TensorLayout::FrameMajor, TensorLayout::Hilbert] {
+        let gif_opts = GifOpts {
+            width: 64,
+            height: 64,
+            frame_count: 4,
+            include_tensor: true,
+            tensor: TensorOpts {
+                layout,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let quantize_opts = QuantizeOpts {
+            quality_min: 0,
+            ..Default::default()
+        };
+        let output = process_all_frames(create_test_frames(4, 64, 64), 64, 64, 4, quantize_opts, gif_opts)
+            .expect("Processing failed");
+        tensors.push(output.tensor_data.expect("Tensor missing"));
+    }
+
+    assert_ne!(tensors[0], tensors[1]);
+    let restored = convert_tensor_layout(tensors[1].clone(), 128, 128, 4, TensorLayout::Hilbert, TensorLayout::FrameMajor)
+        .expect("Conversion failed");
+    assert_eq!(restored, tensors[0]);
+}
+
+#[test]
+fn test_compressed_tensor_output() {
+    use rgb2gif_processor::{decompress_tensor, TensorOpts};
+
+    let run = |tensor: TensorOpts| {
+        let gif_opts = GifOpts {
+            width: 64,
+            height: 64,
+            frame_count: 4,
+            include_tensor: true,
+            tensor,
+            ..Default::default()
+        };
+        let quantize_opts = QuantizeOpts {
+            quality_min: 0,
+            ..Default::default()
+        };
+        process_all_frames(create_test_frames(4, 64, 64), 64, 64, 4, quantize_opts, gif_opts)
+            .expect("Processing failed")
+            .tensor_data
+            .expect("Tensor missing")
+    };
+
+    let raw = run(TensorOpts::default());
+    let packed = run(TensorOpts {
+        compression_level: 3,
+        ..Default::default()
+    });
+    assert!(packed.len() < raw.len() / 2, "{} vs {}", packed.len(), raw.len());
+    assert_eq!(decompress_tensor(packed, None).unwrap(), raw);
+}
+
+#[test]
+fn test_tensor_handoff_file() {
+    use rgb2gif_processor::TensorOpts;
+
+    let path = std::env::temp_dir().join(format!("integration-tensor-{}.bin", std::process::id()));
+    let gif_opts = GifOpts {
+        width: 64,
+        height: 64,
+        frame_count: 4,
+        include_tensor: true,
+        tensor: TensorOpts {
+            handoff_path: Some(path.to_string_lossy().into_owned()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let quantize_opts = QuantizeOpts {
+        quality_min: 0,
+        ..Default::default()
+    };
+    let output = process_all_frames(create_test_frames(4, 64, 64), 64, 64, 4, quantize_opts, gif_opts)
+        .expect("Processing failed");
+
+    assert!(output.tensor_data.is_none());
+    let handle = output.tensor_handle.expect("Handle missing");
+    assert_eq!((handle.width, handle.height, handle.depth), (128, 128, 4));
+    assert_eq!(handle.byte_length, 128 * 128 * 4 * 4);
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), handle.byte_length);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_portrait_capture_to_non_square_gif() {
+    use rgb2gif_processor::gif_validator::validate_gif;
+    use rgb2gif_processor::TensorOpts;
+
+    // 9:16 camera frames into a 3:4 GIF and a 64×32 tensor cube
+    let gif_opts = GifOpts {
+        width: 48,
+        height: 64,
+        frame_count: 4,
+        include_tensor: true,
+        tensor: TensorOpts {
+            cube_width: 64,
+            cube_height: 32,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let quantize_opts = QuantizeOpts {
+        quality_min: 0,
+        ..Default::default()
+    };
+    let output = process_all_frames(create_test_frames(4, 90, 160), 90, 160, 4, quantize_opts, gif_opts)
+        .expect("Processing failed");
+
+    let report = validate_gif(&output.gif_data, true);
+    assert!(report.findings.is_empty(), "{:?}", report.findings);
+    assert_eq!((report.width, report.height), (48, 64));
+    assert_eq!(report.frames.len(), 4);
+
+    let tensor = output.tensor_data.expect("Tensor missing");
+    assert_eq!(tensor.len(), 64 * 32 * 4 * 4);
+}
+
+#[test]
+fn test_resume_encoding_from_checkpoint() {
+    use rgb2gif_processor::{resume_encoding, TensorOpts};
+
+    let path = std::env::temp_dir().join(format!("integration-checkpoint-{}.rgbq", std::process::id()));
+    let path = path.to_string_lossy().into_owned();
+    let quantize_opts = QuantizeOpts {
+        quality_min: 0,
+        ..Default::default()
+    };
+    let gif_opts = GifOpts {
+        width: 32,
+        height: 32,
+        frame_count: 4,
+        checkpoint_path: Some(path.clone()),
+        ..Default::default()
+    };
+
+    // A clean run leaves no checkpoint behind
+    let reference = process_all_frames(create_test_frames(4, 32, 32), 32, 32, 4, quantize_opts.clone(), gif_opts.clone())
+        .expect("Processing failed");
+    assert!(std::fs::metadata(&path).is_err());
+
+    // Fail after quantization by handing the tensor off to a missing directory
+    let interrupted = GifOpts {
+        include_tensor: true,
+        tensor: TensorOpts {
+            handoff_path: Some("/nonexistent-dir/tensor.bin".to_string()),
+            ..Default::default()
+        },
+        ..gif_opts.clone()
+    };
+    assert!(process_all_frames(create_test_frames(4, 32, 32), 32, 32, 4, quantize_opts, interrupted).is_err());
+    assert!(std::fs::metadata(&path).is_ok());
+
+    let resumed = resume_encoding(path.clone(), gif_opts).expect("Resume failed");
+    assert_eq!(resumed.gif_data, reference.gif_data);
+    assert_eq!(resumed.actual_frame_count, 4);
+    assert!(std::fs::metadata(&path).is_err());
+    assert!(resume_encoding(path, GifOpts::default()).is_err());
+}
+
+#[test]
+fn test_adaptive_speed_meets_tight_budget() {
+    // A budget nothing can meet falls back to speed 10 and relaxes quality_min
+    let quantize_opts = QuantizeOpts {
+        quality_min: 99,
+        speed: 1,
+        time_budget_ms: 1,
+        ..Default::default()
+    };
+    let gif_opts = GifOpts {
+        width: 64,
+        height: 64,
+        frame_count: 8,
+        ..Default::default()
+    };
+
+    let output = process_all_frames(create_test_frames(8, 64, 64), 64, 64, 8, quantize_opts, gif_opts)
+        .expect("Adaptive processing failed");
+    assert_eq!(output.actual_frame_count, 8);
+    assert_eq!(&output.gif_data[..6], b"GIF89a");
+}
+
+#[test]
+fn test_frame_delays_keep_average_fps() {
+    let decode_delays = |fps: u16| -> Vec<u16> {
+        let gif_opts = GifOpts {
+            width: 16,
+            height: 16,
+            frame_count: 6,
+            fps,
+            ..Default::default()
+        };
+        let quantize_opts = QuantizeOpts {
+            quality_min: 0,
+            ..Default::default()
+        };
+        let output = process_all_frames(create_test_frames(6, 16, 16), 16, 16, 6, quantize_opts, gif_opts)
+            .expect("Processing failed");
+
+        let mut decoder = gif::DecodeOptions::new().read_info(output.gif_data.as_slice()).unwrap();
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+        }
+        delays
+    };
+
+    assert_eq!(decode_delays(30), vec![3, 3, 4, 3, 3, 4]);
+    // 100 / fps used to round these down to 0
+    assert_eq!(decode_delays(100), vec![2; 6]);
+
+    let gif_opts = GifOpts { width: 16, height: 16, fps: 0, ..Default::default() };
+    assert!(process_all_frames(create_test_frames(2, 16, 16), 16, 16, 2, QuantizeOpts::default(), gif_opts).is_err());
+}
+
+#[test]
+fn test_few_color_clip_is_lossless() {
+    // Pixel-art style clip: four colors in moving stripes plus a transparent border
+    let (width, height, frame_count) = (24u32, 16u32, 4usize);
+    let colors = [[255u8, 0, 0, 255], [0, 128, 255, 255], [20, 20, 20, 255], [250, 240, 10, 255]];
+    let mut frames = Vec::new();
+    for f in 0..frame_count {
+        for y in 0..height {
+            for x in 0..width {
+                if x == 0 || y == 0 {
+                    frames.extend_from_slice(&[0, 0, 0, 0]);
+                } else {
+                    frames.extend_from_slice(&colors[(x as usize / 3 + f) % colors.len()]);
+                }
+            }
+        }
+    }
+
+    let gif_opts = GifOpts {
+        width: width as u16,
+        height: height as u16,
+        frame_count: frame_count as u32,
+        ..Default::default()
+    };
+    let output = process_all_frames(frames.clone(), width, height, frame_count as u32, QuantizeOpts::default(), gif_opts)
+        .expect("Processing failed");
+    assert_eq!(output.palette_size_used, 5);
+
+    let mut decoder = gif::DecodeOptions::new();
+    decoder.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = decoder.read_info(output.gif_data.as_slice()).unwrap();
+    for expected in frames.chunks_exact((width * height * 4) as usize) {
+        let frame = decoder.read_next_frame().unwrap().expect("Frame missing");
+        for (decoded, original) in frame.buffer.chunks_exact(4).zip(expected.chunks_exact(4)) {
+            match original[3] {
+                0 => assert_eq!(decoded[3], 0),
+                _ => assert_eq!(decoded, original),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_pixel_art_preset_keeps_pixels_crisp() {
+    use rgb2gif_processor::ProcessorOptions;
+
+    // 8×6 art on an 8px grid, shifted one art pixel per frame
+    let (width, height, frame_count) = (64u32, 48u32, 3usize);
+    let art_color = |x: u32, y: u32, f: u32| -> [u8; 4] {
+        let v = ((x + f) * 7 + y * 3) % 5;
+        [(v * 60) as u8, 255 - (v * 50) as u8, (v * 20) as u8, 255]
+    };
+    let mut frames = Vec::new();
+    for f in 0..frame_count as u32 {
+        for y in 0..height {
+            for x in 0..width {
+                frames.extend_from_slice(&art_color(x / 8, y / 8, f));
+            }
+        }
+    }
+
+    let ProcessorOptions { quantize_opts, mut gif_opts, .. } = ProcessorOptions::pixel_art(0);
+    gif_opts.width = 20;
+    gif_opts.height = 20;
+    gif_opts.frame_count = frame_count as u32;
+    let output = process_all_frames(frames, width, height, frame_count as u32, quantize_opts, gif_opts)
+        .expect("Processing failed");
+
+    // Largest grid divisor that fits 20×20 is 4: each art pixel becomes 2×2
+    let mut decoder = gif::DecodeOptions::new();
+    decoder.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = decoder.read_info(output.gif_data.as_slice()).unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (16, 12));
+    for f in 0..frame_count as u32 {
+        let frame = decoder.read_next_frame().unwrap().expect("Frame missing");
+        for (i, pixel) in frame.buffer.chunks_exact(4).enumerate() {
+            let (x, y) = (i as u32 % 16, i as u32 / 16);
+            assert_eq!(pixel, art_color(x / 2, y / 2, f));
+        }
+    }
+}
+
+#[test]
+fn test_effect_chain_applies_before_quantization() {
+    use rgb2gif_processor::{Effect, EffectChain, EffectKind};
+
+    let (width, height) = (32u32, 32u32);
+    let frames = [200u8, 200, 200, 255].repeat((width * height * 2) as usize);
+    let gif_opts = GifOpts {
+        width: width as u16,
+        height: height as u16,
+        frame_count: 2,
+        effects: EffectChain {
+            effects: vec![Effect { kind: EffectKind::Vignette, intensity: 1.0, size: 0.0, seed: 0 }],
+        },
+        ..Default::default()
+    };
+    let output = process_all_frames(frames, width, height, 2, QuantizeOpts::default(), gif_opts)
+        .expect("Processing failed");
+
+    let mut decoder = gif::DecodeOptions::new();
+    decoder.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = decoder.read_info(output.gif_data.as_slice()).unwrap();
+    let frame = decoder.read_next_frame().unwrap().unwrap();
+    let center = ((height / 2 * width + width / 2) * 4) as usize;
+    assert!(frame.buffer[0] < 40, "corner should be darkened");
+    assert!(frame.buffer[center] > 180, "center should stay bright");
+}
+
+#[test]
+fn test_registered_frame_filter_runs_in_pipeline() {
+    use rgb2gif_processor::{register_native_frame_filter, unregister_frame_filter, FrameFilter};
+    use std::sync::Arc;
+
+    /// Paints the top-left pixel of every frame pure red
+    struct MarkCorner;
+
+    impl FrameFilter for MarkCorner {
+        fn process(&self, frame_rgba: &mut [u8], _width: u32, _height: u32, _frame_index: u32) {
+            frame_rgba[..4].copy_from_slice(&[255, 0, 0, 255]);
+        }
+    }
+
+    register_native_frame_filter("integration-mark-corner", Arc::new(MarkCorner));
+    let gif_opts = GifOpts {
+        width: 8,
+        height: 8,
+        frame_count: 2,
+        frame_filters: vec!["integration-mark-corner".to_string()],
+        ..Default::default()
+    };
+    let frames = [0u8, 0, 255, 255].repeat(8 * 8 * 2);
+    let output = process_all_frames(frames.clone(), 8, 8, 2, QuantizeOpts::default(), gif_opts.clone())
+        .expect("Processing failed");
+
+    let mut decoder = gif::DecodeOptions::new();
+    decoder.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = decoder.read_info(output.gif_data.as_slice()).unwrap();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        assert_eq!(&frame.buffer[..8], &[255, 0, 0, 255, 0, 0, 255, 255]);
+    }
+
+    assert!(unregister_frame_filter("integration-mark-corner".to_string()));
+    assert!(process_all_frames(frames, 8, 8, 2, QuantizeOpts::default(), gif_opts).is_err());
+}
+
+#[test]
+fn test_gif_player_matches_full_decode() {
+    use rgb2gif_processor::GifPlayer;
+
+    let gif_opts = GifOpts { width: 32, height: 32, frame_count: 6, ..Default::default() };
+    let quantize_opts = QuantizeOpts { quality_min: 0, ..Default::default() };
+    let output = process_all_frames(create_test_frames(6, 32, 32), 32, 32, 6, quantize_opts, gif_opts)
+        .expect("Processing failed");
+
+    let mut decoder = gif::DecodeOptions::new();
+    decoder.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = decoder.read_info(output.gif_data.as_slice()).unwrap();
+    let mut expected = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        expected.push(frame.buffer.to_vec());
+    }
+
+    let player = GifPlayer::new(output.gif_data, 2).expect("Player failed to open");
+    assert_eq!(player.frame_count() as usize, expected.len());
+    assert_eq!(player.frame_delays(), vec![3, 3, 4, 3, 3, 4]);
+    // Scrub out of order so some frames come from the cache and some are re-decoded
+    for index in [5, 1, 2, 0, 4, 3, 5] {
+        assert_eq!(player.frame_rgba(index).unwrap(), expected[index as usize], "frame {}", index);
+    }
+}
+
+#[test]
+fn test_preview_frames_show_transparency_over_backdrop() {
+    use rgb2gif_processor::{preview_gif_frames, Backdrop, BackdropKind, GifPlayer, QuantizerBackend};
+
+    // Left half cleared, so every frame is half transparent
+    let mut frames = create_test_frames(3, 16, 16);
+    for (i, px) in frames.chunks_exact_mut(4).enumerate() {
+        if i % 16 < 8 {
+            px[3] = 0;
+        }
+    }
+    let gif_opts = GifOpts { width: 16, height: 16, frame_count: 3, ..Default::default() };
+    let quantize_opts = QuantizeOpts { backend: QuantizerBackend::Oklab, quality_min: 0, ..Default::default() };
+    let output = process_all_frames(frames, 16, 16, 3, quantize_opts, gif_opts).expect("Processing failed");
+
+    let backdrop = Backdrop { cell_size: 4, ..Default::default() };
+    let previews = preview_gif_frames(output.gif_data.clone(), backdrop.clone()).expect("Preview failed");
+    assert_eq!(previews.len(), 3 * 16 * 16 * 4);
+    assert!(previews.chunks_exact(4).all(|px| px[3] == 255));
+    // Checker squares alternate across the cleared half, and the opaque half keeps the clip
+    let player = GifPlayer::new(output.gif_data, 1).unwrap();
+    let plain = player.frame_rgba(2).unwrap();
+    let last = &previews[2 * 16 * 16 * 4..];
+    assert_eq!(last[..4], [255, 255, 255, 255]);
+    assert_eq!(last[4 * 4..5 * 4], [204, 204, 204, 255]);
+    assert_eq!(last[12 * 4..13 * 4], plain[12 * 4..13 * 4]);
+    assert_eq!(player.frame_preview(2, backdrop).unwrap(), last);
+
+    let solid = Backdrop { kind: BackdropKind::Solid, color: 0x000000, ..Default::default() };
+    assert_eq!(player.frame_preview(0, solid).unwrap()[4 * 4..5 * 4], [0, 0, 0, 255]);
+}
+
+#[test]
+fn test_transcode_trims_without_requantizing() {
+    use rgb2gif_processor::{transcode_gif, CropRect, TranscodeOpts};
+
+    let gif_opts = GifOpts { width: 32, height: 32, frame_count: 8, ..Default::default() };
+    let quantize_opts = QuantizeOpts { quality_min: 0, ..Default::default() };
+    let output = process_all_frames(create_test_frames(8, 32, 32), 32, 32, 8, quantize_opts, gif_opts)
+        .expect("Processing failed");
+
+    let decode = |data: &[u8]| -> Vec<Vec<u8>> {
+        let mut decoder = gif::DecodeOptions::new();
+        decoder.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = decoder.read_info(data).unwrap();
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frames.push(frame.buffer.to_vec());
+        }
+        frames
+    };
+    let original = decode(&output.gif_data);
+
+    let opts = TranscodeOpts {
+        first_frame: 2,
+        frame_count: 4,
+        crop: Some(CropRect { x: 8, y: 4, width: 16, height: 20 }),
+        ..Default::default()
+    };
+    let trimmed = decode(&transcode_gif(output.gif_data, opts).expect("Transcode failed"));
+    assert_eq!(trimmed.len(), 4);
+    for (frame, source) in trimmed.iter().zip(&original[2..6]) {
+        for y in 0..20 {
+            let row = &frame[y * 16 * 4..(y + 1) * 16 * 4];
+            let at = ((y + 4) * 32 + 8) * 4;
+            assert_eq!(row, &source[at..at + 16 * 4]);
+        }
+    }
+}
+
+#[test]
+fn test_frame_range_and_roi_export_part_of_capture() {
+    use rgb2gif_processor::{CropRect, FrameRange};
+
+    // Frame i is a flat gray of level 20·i, with a white 8×8 square at (16, 8)
+    let (width, height, frame_count) = (48u32, 32u32, 6usize);
+    let mut frames = Vec::new();
+    for i in 0..frame_count {
+        for y in 0..height {
+            for x in 0..width {
+                let v = if (16..24).contains(&x) && (8..16).contains(&y) { 255 } else { (i * 20) as u8 };
+                frames.extend_from_slice(&[v, v, v, 255]);
+            }
+        }
+    }
+
+    let gif_opts = GifOpts {
+        width: 8,
+        height: 8,
+        frame_count: 0,
+        frame_range: Some(FrameRange { start: 2, end: 5 }),
+        roi: Some(CropRect { x: 16, y: 8, width: 16, height: 16 }),
+        ..Default::default()
+    };
+    let output = process_all_frames(frames, width, height, frame_count as u32, QuantizeOpts::default(), gif_opts)
+        .expect("Processing failed");
+    assert_eq!(output.actual_frame_count, 3);
+
+    let mut decoder = gif::DecodeOptions::new();
+    decoder.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = decoder.read_info(output.gif_data.as_slice()).unwrap();
+    for i in 2..5u8 {
+        let frame = decoder.read_next_frame().unwrap().unwrap();
+        // The square fills the ROI's top-left quarter, which scales to 4×4
+        assert_eq!(frame.buffer[0], 255);
+        assert_eq!(frame.buffer[(7 * 8 + 7) * 4], i * 20);
+    }
+
+    let out_of_bounds = GifOpts {
+        roi: Some(CropRect { x: 40, y: 0, width: 16, height: 16 }),
+        ..Default::default()
+    };
+    assert!(process_all_frames(create_test_frames(2, 48, 32), 48, 32, 2, QuantizeOpts::default(), out_of_bounds).is_err());
+}
+
+#[test]
+fn test_segments_fit_share_limits() {
+    let gif_opts = GifOpts {
+        width: 64,
+        height: 64,
+        frame_count: 12,
+        segment_max_frames: 5,
+        ..Default::default()
+    };
+    let quantize_opts = QuantizeOpts { quality_min: 0, ..Default::default() };
+    let frames = create_test_frames(12, 64, 64);
+    let by_frames = process_all_frames(frames.clone(), 64, 64, 12, quantize_opts.clone(), gif_opts.clone())
+        .expect("Processing failed");
+    let counts: Vec<u32> = by_frames.segments.iter().map(|s| s.frame_count).collect();
+    assert_eq!(counts, vec![5, 5, 2]);
+
+    // A byte limit of roughly a third of the full GIF
+    let limit = by_frames.final_file_size / 3;
+    let gif_opts = GifOpts { segment_max_frames: 0, segment_max_bytes: limit, ..gif_opts };
+    let by_bytes = process_all_frames(frames, 64, 64, 12, quantize_opts, gif_opts).expect("Processing failed");
+    assert!(by_bytes.segments.len() >= 3);
+
+    let decode = |data: &[u8]| -> (Vec<u8>, Vec<Vec<u8>>) {
+        let mut decoder = gif::DecodeOptions::new();
+        decoder.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = decoder.read_info(data).unwrap();
+        let palette = decoder.global_palette().unwrap().to_vec();
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frames.push(frame.buffer.to_vec());
+        }
+        (palette, frames)
+    };
+    let (palette, all_frames) = decode(&by_bytes.gif_data);
+    let mut next = 0;
+    for segment in &by_bytes.segments {
+        assert!(segment.gif_data.len() as u32 <= limit);
+        assert_eq!(segment.first_frame, next);
+        let (segment_palette, segment_frames) = decode(&segment.gif_data);
+        assert_eq!(segment_palette, palette);
+        assert_eq!(segment_frames, all_frames[next as usize..(next + segment.frame_count) as usize]);
+        next += segment.frame_count;
+    }
+    assert_eq!(next, 12);
+}
+
+#[test]
+fn test_disposal_and_background_index() {
+    use rgb2gif_processor::{FrameDisposal, QuantizerBackend};
+
+    let (width, height, frame_count) = (32u32, 32u32, 4usize);
+    let mut frames = create_test_frames(frame_count, width, height);
+    for (i, pixel) in frames.chunks_exact_mut(4).enumerate() {
+        if i as u32 % width >= width / 2 {
+            pixel[3] = 0;
+        }
+    }
+    let quantize_opts = QuantizeOpts {
+        palette_size: 16,
+        backend: QuantizerBackend::Oklab,
+        ..Default::default()
+    };
+    let gif_opts = GifOpts {
+        width: width as u16,
+        height: height as u16,
+        frame_count: frame_count as u32,
+        disposal: FrameDisposal::Background,
+        frame_disposals: vec![FrameDisposal::Keep, FrameDisposal::Previous],
+        background_index: 15,
+        ..Default::default()
+    };
+
+    let output = process_all_frames(frames.clone(), width, height, frame_count as u32, quantize_opts.clone(), gif_opts.clone())
+        .expect("Processing failed");
+    assert_eq!(output.gif_data[11], 15);
+
+    let mut decoder = gif::DecodeOptions::new();
+    decoder.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = decoder.read_info(output.gif_data.as_slice()).unwrap();
+    let mut disposals = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        disposals.push(frame.dispose);
+    }
+    use gif::DisposalMethod::{Background, Keep, Previous};
+    assert_eq!(disposals, vec![Keep, Previous, Background, Background]);
+
+    // Background index past the palette
+    let gif_opts = GifOpts { background_index: 200, ..gif_opts };
+    assert!(process_all_frames(frames, width, height, frame_count as u32, quantize_opts, gif_opts).is_err());
+}
+
+#[test]
+fn test_errors_carry_stage_and_frame() {
+    use rgb2gif_processor::{transcode_gif, ProcessorError, TranscodeOpts};
+
+    let gif_opts = GifOpts {
+        width: 16,
+        height: 16,
+        frame_filters: vec!["integration-missing-filter".to_string()],
+        ..Default::default()
+    };
+    match process_all_frames(create_test_frames(2, 16, 16), 16, 16, 2, QuantizeOpts::default(), gif_opts) {
+        Err(ProcessorError::InvalidInput { stage, message, .. }) => {
+            assert_eq!(stage, "frame filters");
+            assert!(message.contains("integration-missing-filter"));
+        }
+        other => panic!("expected InvalidInput, got {:?}", other.map(|_| ())),
+    }
+
+    // A single frame over the segment byte limit names that frame
+    let gif_opts = GifOpts { width: 16, height: 16, segment_max_bytes: 64, ..Default::default() };
+    let error = process_all_frames(create_test_frames(2, 16, 16), 16, 16, 2, QuantizeOpts::default(), gif_opts)
+        .expect_err("64 bytes can't hold a frame");
+    assert!(matches!(&error, ProcessorError::InvalidInput { stage, frame_index: Some(0), .. } if stage == "segments"));
+    assert!(error.to_string().contains("(frame 0)"));
+
+    let error = transcode_gif(vec![1, 2, 3], TranscodeOpts::default()).expect_err("not a GIF");
+    assert!(matches!(error, ProcessorError::InvalidInput { stage, .. } if stage == "transcode"));
+}
+
+#[test]
+fn test_single_frame_building_blocks() {
+    use rgb2gif_processor::{encode_indexed_frames, quantize_frame, resize_frame};
+
+    let (width, height) = (64u32, 48u32);
+    let frame = create_test_frames(1, width, height);
+    let small = resize_frame(&frame, width, height, 16, 16).expect("Resize failed");
+    assert_eq!(small.len(), 16 * 16 * 4);
+
+    let quantize_opts = QuantizeOpts { quality_min: 0, palette_size: 32, ..Default::default() };
+    let (palette, indices) = quantize_frame(&small, 16, 16, &quantize_opts).expect("Quantize failed");
+    assert!(!palette.is_empty() && palette.len() <= 32);
+    assert!(indices.iter().all(|&i| (i as usize) < palette.len()));
+
+    // Explicit delays win over fps for the frames they cover
+    let gif_opts = GifOpts {
+        width: 16,
+        height: 16,
+        fps: 10,
+        frame_delays: vec![7, 2],
+        ..Default::default()
+    };
+    let frames = vec![indices.clone(), indices.clone(), indices];
+    let gif_data = encode_indexed_frames(&frames, &palette, None, &gif_opts).expect("Encode failed");
+
+    let mut decoder = gif::DecodeOptions::new();
+    decoder.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = decoder.read_info(gif_data.as_slice()).unwrap();
+    let mut delays = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        delays.push(frame.delay);
+    }
+    assert_eq!(delays, vec![7, 2, 10]);
+
+    // Frames that don't match the output size
+    let short = vec![vec![0u8; 10]];
+    assert!(encode_indexed_frames(&short, &palette, None, &gif_opts).is_err());
+}
+
+#[test]
+fn test_indices_past_the_palette_are_rejected() {
+    use rgb2gif_processor::palette::Palette;
+    use rgb2gif_processor::{encode_gif_to, encode_indexed_frames, ProcessorError};
+
+    // Four colors get a four-entry table, so index 4 and up have no code
+    let palette = Palette::from_rgb_bytes(&[0, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0, 255]);
+    let gif_opts = GifOpts { width: 8, height: 8, ..Default::default() };
+    let in_range = vec![(0..64).map(|i| (i % 4) as u8).collect::<Vec<u8>>()];
+    let past = vec![in_range[0].clone(), (0..64).collect()];
+
+    assert!(encode_indexed_frames(&in_range, &palette, None, &gif_opts).is_ok());
+    let error = encode_indexed_frames(&past, &palette, None, &gif_opts).unwrap_err();
+    assert!(matches!(error, ProcessorError::InvalidInput { ref stage, frame_index: Some(1), .. } if stage == "encode"), "{error:?}");
+    assert!(matches!(encode_gif_to(&past, &palette, None, &gif_opts, &mut Vec::new()), Err(ProcessorError::InvalidInput { .. })));
+
+    // The transparent and background indices are held to the same palette
+    assert!(matches!(encode_indexed_frames(&in_range, &palette, Some(4), &gif_opts), Err(ProcessorError::InvalidInput { .. })));
+    let background = GifOpts { background_index: 4, ..gif_opts };
+    assert!(matches!(encode_indexed_frames(&in_range, &palette, None, &background), Err(ProcessorError::InvalidInput { .. })));
+}
+
+#[test]
+fn test_frame_metadata_round_trips_through_gif() {
+    use rgb2gif_processor::gif_validator::validate_gif;
+    use rgb2gif_processor::{read_gif_metadata, summarize_frame_metadata, FrameMetadata};
+
+    let (width, height, frame_count) = (16u32, 16u32, 3usize);
+    let frame_metadata: Vec<FrameMetadata> = (0..frame_count as u64)
+        .map(|i| FrameMetadata {
+            timestamp_ms: Some(1_700_000_000_000 + i * 33),
+            exposure_s: Some(0.008 * (i + 1) as f64),
+            iso: Some(100 + i as u32 * 50),
+            latitude: Some(48.8566),
+            longitude: Some(2.3522),
+        })
+        .collect();
+    let gif_opts = GifOpts {
+        width: width as u16,
+        height: height as u16,
+        frame_count: frame_count as u32,
+        frame_metadata: frame_metadata.clone(),
+        ..Default::default()
+    };
+
+    let frames = create_test_frames(frame_count, width, height);
+    let output = process_all_frames(frames.clone(), width, height, frame_count as u32, QuantizeOpts::default(), gif_opts)
+        .expect("Processing failed");
+
+    let stored = read_gif_metadata(output.gif_data.clone()).unwrap().expect("No metadata in GIF");
+    assert_eq!(Some(stored.clone()), summarize_frame_metadata(frame_metadata));
+    assert_eq!((stored.iso_min, stored.iso_max), (Some(100), Some(200)));
+    assert!(validate_gif(&output.gif_data, true).is_valid());
+
+    // Without metadata nothing is written
+    let gif_opts = GifOpts { width: width as u16, height: height as u16, ..Default::default() };
+    let plain = process_all_frames(frames, width, height, frame_count as u32, QuantizeOpts::default(), gif_opts).unwrap();
+    assert_eq!(read_gif_metadata(plain.gif_data).unwrap(), None);
+}
+
+#[test]
+fn test_color_space_is_recorded_in_gif() {
+    use rgb2gif_processor::{read_gif_metadata, ColorSpace};
+
+    let gif_opts = GifOpts { width: 16, height: 16, frame_count: 2, color_space: ColorSpace::DisplayP3, ..Default::default() };
+    let output = process_all_frames(create_test_frames(2, 16, 16), 16, 16, 2, QuantizeOpts::default(), gif_opts)
+        .expect("Processing failed");
+    assert_eq!(output.color_space, ColorSpace::DisplayP3);
+    let stored = read_gif_metadata(output.gif_data).unwrap().expect("No metadata in GIF");
+    assert_eq!((stored.frame_count, stored.color_space), (0, Some(ColorSpace::DisplayP3)));
+
+    let gif_opts = GifOpts { width: 16, height: 16, frame_count: 2, ..Default::default() };
+    let plain = process_all_frames(create_test_frames(2, 16, 16), 16, 16, 2, QuantizeOpts::default(), gif_opts).unwrap();
+    assert_eq!(plain.color_space, ColorSpace::Srgb);
+}
+
+#[test]
+fn test_quality_ladder_trades_size_for_fidelity() {
+    use rgb2gif_processor::{encode_quality_ladder, DitherMode, QualityLadder, QuantizerBackend};
+
+    let ladder = QualityLadder { palette_sizes: vec![16, 64, 256], dither_modes: vec![DitherMode::Sierra, DitherMode::BlueNoise] };
+    let quantize_opts = QuantizeOpts { backend: QuantizerBackend::Oklab, quality_min: 0, ..Default::default() };
+    let gif_opts = GifOpts { width: 32, height: 32, frame_count: 4, ..Default::default() };
+    let rungs = encode_quality_ladder(create_test_frames(4, 32, 32), 32, 32, 4, ladder, quantize_opts.clone(), gif_opts.clone())
+        .expect("Ladder failed");
+
+    let settings: Vec<(u16, DitherMode)> = rungs.iter().map(|r| (r.palette_size, r.dither_mode)).collect();
+    assert_eq!(settings[..3], [(16, DitherMode::Sierra), (16, DitherMode::BlueNoise), (64, DitherMode::Sierra)]);
+    assert_eq!(rungs.len(), 6);
+    for rung in &rungs {
+        assert_eq!(rung.file_size as usize, rung.gif_data.len());
+        let decoder = gif::DecodeOptions::new().read_info(rung.gif_data.as_slice()).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (32, 32));
+    }
+    // More colors cost bytes and buy fidelity
+    let (small, large) = (&rungs[0], &rungs[4]);
+    assert!(small.mean_error > large.mean_error, "{} vs {}", small.mean_error, large.mean_error);
+    assert!(small.file_size < large.file_size);
+
+    let empty = QualityLadder { palette_sizes: Vec::new(), ..Default::default() };
+    assert!(encode_quality_ladder(create_test_frames(4, 32, 32), 32, 32, 4, empty, quantize_opts, gif_opts).is_err());
+}
+
+#[test]
+fn test_stage_timings_cover_the_pipeline() {
+    let (width, height, frame_count) = (32u32, 32u32, 4usize);
+    let gif_opts = GifOpts {
+        width: 16,
+        height: 16,
+        frame_count: frame_count as u32,
+        ..Default::default()
+    };
+    let quantize_opts = QuantizeOpts { exact_colors: false, ..Default::default() };
+
+    let frames = create_test_frames(frame_count, width, height);
+    let output = process_all_frames(frames, width, height, frame_count as u32, quantize_opts, gif_opts)
+        .expect("Processing failed");
+
+    let timings = output.stage_timings;
+    assert!(timings.preprocess_ms > 0.0, "resize should take measurable time");
+    assert!(timings.quantize_ms > 0.0);
+    assert!(timings.encode_ms > 0.0);
+    assert!(timings.tensor_ms >= 0.0);
+    // Backend stages fall inside its total, give or take rounding to whole milliseconds
+    assert!(timings.quantize_ms + timings.encode_ms + timings.tensor_ms <= output.processing_time_ms + 1.0);
+}
+
+#[test]
+fn test_locked_palette_carries_over_to_the_next_clip() {
+    let (width, height, frame_count) = (24u32, 24u32, 3usize);
+    let gif_opts = GifOpts {
+        width: width as u16,
+        height: height as u16,
+        frame_count: frame_count as u32,
+        ..Default::default()
+    };
+    let quantize_opts = QuantizeOpts { palette_size: 16, exact_colors: false, ..Default::default() };
+
+    let first = process_all_frames(create_test_frames(frame_count, width, height), width, height, frame_count as u32, quantize_opts.clone(), gif_opts.clone())
+        .expect("First clip failed");
+    assert_eq!(first.palette_rgba.len(), first.palette_size_used as usize * 4);
+
+    // A differently colored clip still comes out in the first clip's colors
+    let mut second_frames = create_test_frames(frame_count, width, height);
+    second_frames.chunks_exact_mut(4).for_each(|px| px.swap(0, 2));
+    let locked = QuantizeOpts { locked_palette: Some(first.palette_rgba.clone()), ..quantize_opts };
+    let second = process_all_frames(second_frames, width, height, frame_count as u32, locked, gif_opts)
+        .expect("Locked clip failed");
+    assert_eq!(second.palette_rgba, first.palette_rgba);
+
+    let global_palette = |gif_data: &[u8]| {
+        let decoder = gif::DecodeOptions::new().read_info(gif_data).unwrap();
+        decoder.global_palette().unwrap().to_vec()
+    };
+    assert_eq!(global_palette(&second.gif_data), global_palette(&first.gif_data));
+
+    // Malformed palettes are rejected up front
+    let bad = QuantizeOpts { locked_palette: Some(vec![1, 2, 3]), ..Default::default() };
+    let result = process_all_frames(create_test_frames(1, 4, 4), 4, 4, 1, bad, GifOpts { width: 4, height: 4, ..Default::default() });
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_analysis_previews_the_encode() {
+    use rgb2gif_processor::{analyze_frames, encode_analyzed, QuantizerBackend};
+
+    let (width, height, frame_count) = (32u32, 32u32, 12usize);
+    let gif_opts = GifOpts {
+        width: 24,
+        height: 24,
+        frame_count: frame_count as u32,
+        ..Default::default()
+    };
+    let quantize_opts = QuantizeOpts {
+        palette_size: 32,
+        exact_colors: false,
+        backend: QuantizerBackend::Oklab,
+        ..Default::default()
+    };
+    let frames = create_test_frames(frame_count, width, height);
+
+    let analysis = analyze_frames(frames.clone(), width, height, frame_count as u32, quantize_opts.clone(), gif_opts.clone())
+        .expect("Analysis failed");
+    assert!(!analysis.exact);
+    assert_eq!((analysis.width, analysis.height, analysis.frame_count), (24, 24, frame_count as u32));
+    assert!(analysis.palette_rgba.len() <= 32 * 4);
+
+    let result = encode_analyzed(frames, width, height, frame_count as u32, analysis.clone(), quantize_opts, gif_opts)
+        .expect("Encode failed");
+    assert_eq!(result.palette_rgba, analysis.palette_rgba);
+
+    // Sampled frames stand in for the rest, so the guess is close but not exact
+    let actual = result.final_file_size as f32;
+    let error = (analysis.estimated_size as f32 - actual).abs() / actual;
+    assert!(error < 0.15, "estimated {} bytes, encoded {}", analysis.estimated_size, actual);
+}
+
+#[test]
+fn test_analysis_of_exact_clip_stays_lossless() {
+    use rgb2gif_processor::{analyze_frames, encode_analyzed};
+
+    // Four flat colors
+    let (width, height) = (8u32, 8u32);
+    let frames: Vec<u8> = (0..4u32 * width * height)
+        .flat_map(|i| [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255], [9, 9, 9, 255]][(i % 4) as usize])
+        .collect();
+    let gif_opts = GifOpts { width: width as u16, height: height as u16, frame_count: 4, ..Default::default() };
+
+    let analysis = analyze_frames(frames.clone(), width, height, 4, QuantizeOpts::default(), gif_opts.clone())
+        .expect("Analysis failed");
+    assert!(analysis.exact);
+    assert_eq!(analysis.palette_rgba.len(), 4 * 4);
+
+    let result = encode_analyzed(frames, width, height, 4, analysis.clone(), QuantizeOpts::default(), gif_opts)
+        .expect("Encode failed");
+    assert_eq!(result.palette_rgba, analysis.palette_rgba);
+}
+
+#[test]
+fn test_estimate_brackets_the_encoded_size() {
+    use rgb2gif_processor::{estimate_encode, QuantizerBackend};
+
+    let (width, height, frame_count) = (32u32, 32u32, 24usize);
+    let gif_opts = GifOpts { width: 32, height: 32, frame_count: 0, ..Default::default() };
+    let quantize_opts = QuantizeOpts {
+        palette_size: 64,
+        exact_colors: false,
+        backend: QuantizerBackend::Oklab,
+        ..Default::default()
+    };
+    let frames = create_test_frames(frame_count, width, height);
+
+    // Every sixth frame as the probe
+    let frame_size = (width * height * 4) as usize;
+    let probe: Vec<u8> = frames.chunks_exact(frame_size).step_by(6).flatten().copied().collect();
+    let estimate = estimate_encode(probe, width, height, 4, frame_count as u32, quantize_opts.clone(), gif_opts.clone())
+        .expect("Estimate failed");
+    assert!(estimate.bytes_low < estimate.bytes_high);
+    assert!(estimate.est_ms > 0.0);
+
+    let result = process_all_frames(frames, width, height, frame_count as u32, quantize_opts.clone(), gif_opts.clone())
+        .expect("Encode failed");
+    assert!(
+        (estimate.bytes_low..=estimate.bytes_high).contains(&result.final_file_size),
+        "{} bytes outside {}..={}",
+        result.final_file_size,
+        estimate.bytes_low,
+        estimate.bytes_high
+    );
+
+    // A frame budget caps what gets encoded
+    let probe = create_test_frames(2, width, height);
+    let capped = estimate_encode(probe.clone(), width, height, 2, 1000, quantize_opts.clone(), GifOpts { frame_count: 10, ..gif_opts.clone() })
+        .expect("Estimate failed");
+    let uncapped = estimate_encode(probe, width, height, 2, 1000, quantize_opts, gif_opts).expect("Estimate failed");
+    assert!(capped.bytes_high * 50 < uncapped.bytes_high);
+}
+
+#[test]
+fn test_adaptive_palette_shrinks_simple_scenes() {
+    use rgb2gif_processor::QuantizerBackend;
+
+    // Three flat bands with faint noise: too many colors to be exact, few worth keeping
+    let (width, height, frame_count) = (32u32, 32u32, 4usize);
+    let frames: Vec<u8> = (0..frame_count as u32 * width * height)
+        .flat_map(|i| {
+            let (band, noise) = ((i % (width * height)) / (width * height / 3), (i.wrapping_mul(2_654_435_761) >> 29) as u8);
+            [(band * 90) as u8 + noise, 180 - (band * 60) as u8, 40 + noise, 255]
+        })
+        .collect();
+    let gif_opts = GifOpts { width: 32, height: 32, frame_count: frame_count as u32, ..Default::default() };
+    let quantize_opts = QuantizeOpts { backend: QuantizerBackend::Oklab, exact_colors: false, ..Default::default() };
+
+    let full = process_all_frames(frames.clone(), width, height, frame_count as u32, quantize_opts.clone(), gif_opts.clone())
+        .expect("Processing failed");
+    let adaptive = QuantizeOpts { adaptive_palette: true, ..quantize_opts };
+    let trimmed = process_all_frames(frames, width, height, frame_count as u32, adaptive, gif_opts).expect("Processing failed");
+
+    assert!(trimmed.palette_size_used * 4 <= full.palette_size_used, "{} of {}", trimmed.palette_size_used, full.palette_size_used);
+    assert_eq!(trimmed.palette_rgba.len(), trimmed.palette_size_used as usize * 4);
+    assert!(trimmed.final_file_size < full.final_file_size);
+
+    let decoder = gif::DecodeOptions::new().read_info(trimmed.gif_data.as_slice()).unwrap();
+    assert!(decoder.global_palette().unwrap().len() / 3 < 256);
+}
+
+#[test]
+fn test_posterized_clip_lands_on_channel_levels() {
+    use rgb2gif_processor::PreprocessOpts;
+
+    // Two bits per channel leaves at most 64 colors, so the exact palette holds them all
+    let (width, height, frame_count) = (32u32, 32u32, 3usize);
+    let preprocess = PreprocessOpts { posterize_red_bits: 2, posterize_green_bits: 2, posterize_blue_bits: 2 };
+    let gif_opts = GifOpts { width: 32, height: 32, frame_count: frame_count as u32, preprocess, ..Default::default() };
+    let frames = create_test_frames(frame_count, width, height);
+
+    let output = process_all_frames(frames, width, height, frame_count as u32, QuantizeOpts::default(), gif_opts)
+        .expect("Processing failed");
+    assert!(output.palette_size_used <= 64);
+
+    let mut decoder = gif::DecodeOptions::new();
+    decoder.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = decoder.read_info(output.gif_data.as_slice()).unwrap();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        for px in frame.buffer.chunks_exact(4) {
+            assert!(px[..3].iter().all(|c| [0, 85, 170, 255].contains(c)), "{px:?}");
+        }
+    }
+}
+
+#[test]
+fn test_gif_streams_to_a_sink_or_file() {
+    use rgb2gif_processor::palette::{Color32, Palette};
+    use rgb2gif_processor::{encode_gif_to, encode_indexed_frames};
+
+    let palette: Palette = (0..16u8).map(|i| Color32::from_rgb_u32(u32::from(i) * 0x0F0F0F)).collect();
+    let frames: Vec<Vec<u8>> = (0..5u8).map(|z| (0..24 * 24).map(|i| ((i / 24 + z as usize) % 16) as u8).collect()).collect();
+    let gif_opts = GifOpts { width: 24, height: 24, ..Default::default() };
+
+    let buffered = encode_indexed_frames(&frames, &palette, None, &gif_opts).expect("Encode failed");
+    let mut streamed = Vec::new();
+    let written = encode_gif_to(&frames, &palette, None, &gif_opts, &mut streamed).expect("Stream failed");
+    assert_eq!(streamed, buffered);
+    assert_eq!(written, buffered.len() as u64);
+
+    // The pipeline writes the same GIF to output_path and hands back no bytes
+    let path = std::env::temp_dir().join(format!("integration-output-{}.gif", std::process::id()));
+    let quantize_opts = QuantizeOpts { quality_min: 0, ..Default::default() };
+    let gif_opts = GifOpts { width: 32, height: 32, frame_count: 4, ..Default::default() };
+    let reference = process_all_frames(create_test_frames(4, 32, 32), 32, 32, 4, quantize_opts.clone(), gif_opts.clone())
+        .expect("Processing failed");
+    let to_file = GifOpts { output_path: Some(path.to_string_lossy().into_owned()), ..gif_opts };
+    let output = process_all_frames(create_test_frames(4, 32, 32), 32, 32, 4, quantize_opts, to_file).expect("Processing failed");
+    assert!(output.gif_data.is_empty());
+    assert_eq!(output.final_file_size, reference.final_file_size);
+    assert_eq!(std::fs::read(&path).unwrap(), reference.gif_data);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_save_gif_replaces_the_file_whole() {
+    use rgb2gif_processor::{save_gif, save_yxv};
+
+    let dir = std::env::temp_dir().join(format!("integration-save-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("export.gif").to_string_lossy().into_owned();
+    let quantize_opts = QuantizeOpts { quality_min: 0, ..Default::default() };
+    let gif_opts = GifOpts { width: 16, height: 16, frame_count: 2, ..Default::default() };
+    let output = process_all_frames(create_test_frames(2, 16, 16), 16, 16, 2, quantize_opts, gif_opts).expect("Processing failed");
+
+    save_gif(path.clone(), output.gif_data.clone(), true).expect("Save failed");
+    assert_eq!(std::fs::read(&path).unwrap(), output.gif_data);
+
+    // Truncated or foreign data never replaces what's there, and no staging file is left over
+    let truncated = output.gif_data[..output.gif_data.len() / 2].to_vec();
+    assert!(save_gif(path.clone(), truncated, false).is_err());
+    assert!(save_yxv(path.clone(), output.gif_data.clone(), false).is_err());
+    assert_eq!(std::fs::read(&path).unwrap(), output.gif_data);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_integrity_hash_flags_damaged_gifs() {
+    use rgb2gif_processor::gif_validator::validate_gif;
+    use rgb2gif_processor::{verify_gif_integrity, IntegrityCheck};
+
+    let quantize_opts = QuantizeOpts { quality_min: 0, ..Default::default() };
+    let gif_opts = GifOpts { width: 24, height: 24, frame_count: 3, ..Default::default() };
+    let plain = process_all_frames(create_test_frames(3, 24, 24), 24, 24, 3, quantize_opts.clone(), gif_opts.clone())
+        .expect("Processing failed");
+    let signed_opts = GifOpts { integrity_hash: true, ..gif_opts };
+    let signed = process_all_frames(create_test_frames(3, 24, 24), 24, 24, 3, quantize_opts, signed_opts).expect("Processing failed");
+
+    assert_eq!(verify_gif_integrity(plain.gif_data.clone()), IntegrityCheck::Unsigned);
+    assert_eq!(verify_gif_integrity(signed.gif_data.clone()), IntegrityCheck::Verified);
+    assert_eq!(signed.final_file_size as usize, signed.gif_data.len());
+    assert!(validate_gif(&signed.gif_data, true).is_valid());
+
+    // Same frames either way; a flipped bit anywhere is caught
+    let frames = |gif_data: &[u8]| {
+        let mut decoder = gif::DecodeOptions::new().read_info(gif_data).unwrap();
+        std::iter::from_fn(|| decoder.read_next_frame().unwrap().map(|f| f.buffer.to_vec())).collect::<Vec<_>>()
+    };
+    assert_eq!(frames(&signed.gif_data), frames(&plain.gif_data));
+    let mut damaged = signed.gif_data.clone();
+    let middle = damaged.len() / 2;
+    damaged[middle] ^= 0x01;
+    assert_eq!(verify_gif_integrity(damaged), IntegrityCheck::Corrupted);
+}
+
+#[test]
+fn test_turntable_preview_rides_along() {
+    use rgb2gif_processor::{TurntableFormat, TurntableOpts};
+
+    let quantize_opts = QuantizeOpts { quality_min: 0, ..Default::default() };
+    let turntable = TurntableOpts { size: 32, frame_count: 12, ..Default::default() };
+    let gif_opts = GifOpts { width: 32, height: 32, frame_count: 6, include_turntable: true, turntable: turntable.clone(), ..Default::default() };
+    let output = process_all_frames(create_test_frames(6, 32, 32), 32, 32, 6, quantize_opts.clone(), gif_opts.clone())
+        .expect("Processing failed");
+
+    let preview = output.turntable_data.expect("Turntable missing");
+    let mut decoder = gif::DecodeOptions::new().read_info(preview.as_slice()).unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (32, 32));
+    let mut frames = 0;
+    while decoder.read_next_frame().unwrap().is_some() {
+        frames += 1;
+    }
+    assert_eq!(frames, 12);
+    assert_eq!(output.actual_frame_count, 6);
+
+    let raw_opts = GifOpts { turntable: TurntableOpts { format: TurntableFormat::Rgba, ..turntable }, ..gif_opts };
+    let raw = process_all_frames(create_test_frames(6, 32, 32), 32, 32, 6, quantize_opts, raw_opts).expect("Processing failed");
+    let raw = raw.turntable_data.expect("Turntable missing");
+    assert_eq!(raw.len(), 12 * 32 * 32 * 4);
+    assert!(raw.chunks_exact(4).any(|px| px[3] == 255) && raw.chunks_exact(4).any(|px| px[3] == 0));
+}
+
+#[test]
+fn test_capture_profile_survives_json() {
+    use rgb2gif_processor::{
+        capture_profile_from_json, capture_profile_to_json, CaptureProfile, DitherMode, ProcessorOptions, QuantizerBackend,
+    };
+
+    let mut options = ProcessorOptions::pixel_art(4);
+    options.quantize_opts.backend = QuantizerBackend::Oklch;
+    options.quantize_opts.dither_mode = DitherMode::Atkinson;
+    options.gif_opts.width = 96;
+    options.gif_opts.frame_delays = vec![4, 8];
+    options.gif_opts.output_path = Some("/capture/clip.gif".to_string());
+    let profile = CaptureProfile { name: "night-walk".to_string(), options };
+
+    let replayed = capture_profile_from_json(capture_profile_to_json(profile).unwrap()).unwrap();
+    assert_eq!(replayed.name, "night-walk");
+    assert_eq!(replayed.options.quantize_opts.backend, QuantizerBackend::Oklch);
+    assert_eq!(replayed.options.quantize_opts.dither_mode, DitherMode::Atkinson);
+    assert!(replayed.options.quantize_opts.exact_colors);
+    assert_eq!(replayed.options.gif_opts.width, 96);
+    assert_eq!(replayed.options.gif_opts.pixel_grid, 4);
+    assert_eq!(replayed.options.gif_opts.frame_delays, [4, 8]);
+    // Paths belong to the original machine
+    assert_eq!(replayed.options.gif_opts.output_path, None);
+
+    // Fields missing from older profiles fall back to their defaults
+    let sparse = r#"{"name":"old","options":{"quantize_opts":{"palette_size":64},"gif_opts":{"fps":12}}}"#;
+    let sparse = capture_profile_from_json(sparse.to_string()).unwrap();
+    assert_eq!(sparse.options.quantize_opts.palette_size, 64);
+    assert_eq!(sparse.options.quantize_opts.speed, QuantizeOpts::default().speed);
+    assert_eq!((sparse.options.gif_opts.fps, sparse.options.gif_opts.width), (12, 128));
+    assert!(capture_profile_from_json("{\"name\":\"broken\"}".to_string()).is_err());
+}
+
+#[test]
+fn test_parallel_frame_compression_round_trips() {
+    use rgb2gif_processor::encode_indexed_frames;
+    use rgb2gif_processor::palette::{Color32, Palette};
+
+    // More frames than threads, so several batches run; noise overflows the LZW dictionary
+    let palette: Palette = (0..=255u32).map(|i| Color32::from_rgb_u32(i * 0x010203)).collect();
+    let mut seed = 0x9E37_79B9u32;
+    let frames: Vec<Vec<u8>> = (0..37)
+        .map(|z| {
+            (0..40 * 30)
+                .map(|i| match z % 3 {
+                    0 => {
+                        seed ^= seed << 13;
+                        seed ^= seed >> 17;
+                        seed ^= seed << 5;
+                        seed as u8
+                    }
+                    1 => (i / 40 + z) as u8,
+                    _ => z as u8,
+                })
+                .collect()
+        })
+        .collect();
+    let gif_opts = GifOpts { width: 40, height: 30, frame_delays: (0..37).map(|z| 2 + z % 5).collect(), ..Default::default() };
+
+    let gif_data = encode_indexed_frames(&frames, &palette, None, &gif_opts).expect("Encode failed");
+    let serial = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap()
+        .install(|| encode_indexed_frames(&frames, &palette, None, &gif_opts))
+        .expect("Encode failed");
+    assert_eq!(gif_data, serial);
+
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(gif_data.as_slice()).unwrap();
+    let mut decoded = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        decoded.push((frame.buffer.to_vec(), frame.delay));
+    }
+    let expected: Vec<(Vec<u8>, u16)> = frames.into_iter().zip(gif_opts.frame_delays).collect();
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+fn test_motion_weight_spends_palette_on_moving_subject() {
+    use rgb2gif_processor::QuantizerBackend;
+
+    // A static gray ramp fills the frame while a small square of reds slides across it
+    let (width, height, frame_count) = (48u32, 48u32, 6usize);
+    let subject = |x: u32, y: u32, z: u32| (x.wrapping_sub(z * 6) < 12 && (18..30).contains(&y)).then(|| (x + y) % 12);
+    let frames: Vec<u8> = (0..frame_count as u32)
+        .flat_map(|z| {
+            (0..width * height).flat_map(move |i| {
+                let (x, y) = (i % width, i / width);
+                match subject(x, y, z) {
+                    Some(shade) => [120 + shade as u8 * 11, 30, 40, 255],
+                    None => {
+                        let gray = ((x + y) * 255 / (width + height)) as u8;
+                        [gray, gray, gray, 255]
+                    }
+                }
+            })
+        })
+        .collect();
+    let gif_opts = GifOpts { width: 48, height: 48, frame_count: frame_count as u32, ..Default::default() };
+    let quantize_opts = QuantizeOpts {
+        backend: QuantizerBackend::Oklab,
+        exact_colors: false,
+        palette_size: 8,
+        dithering_level: 0.0,
+        ..Default::default()
+    };
+
+    // Mean red error over the subject's pixels once decoded
+    let subject_error = |quantize_opts: QuantizeOpts| {
+        let output = process_all_frames(frames.clone(), width, height, frame_count as u32, quantize_opts, gif_opts.clone())
+            .expect("Processing failed");
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(output.gif_data.as_slice()).unwrap();
+        let (mut error, mut count) = (0u32, 0u32);
+        let mut z = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            for (i, px) in frame.buffer.chunks_exact(4).enumerate() {
+                let (x, y) = (i as u32 % width, i as u32 / width);
+                if let Some(shade) = subject(x, y, z) {
+                    error += px[0].abs_diff(120 + shade as u8 * 11) as u32;
+                    count += 1;
+                }
+            }
+            z += 1;
+        }
+        error as f32 / count as f32
+    };
+
+    let plain = subject_error(quantize_opts.clone());
+    let weighted = subject_error(QuantizeOpts { motion_weight: 8.0, ..quantize_opts.clone() });
+    assert!(weighted < plain * 0.75, "{weighted} vs {plain}");
+
+    // The sampled path sees the same mask
+    let sampled = QuantizeOpts { sample_fraction: 0.25, ..quantize_opts };
+    let sampled_plain = subject_error(sampled.clone());
+    let sampled_weighted = subject_error(QuantizeOpts { motion_weight: 8.0, ..sampled });
+    assert!(sampled_weighted < sampled_plain * 0.75, "{sampled_weighted} vs {sampled_plain}");
+}
+
+#[test]
+fn test_motion_crop_finds_and_crops_to_moving_subject() {
+    use rgb2gif_processor::{analyze_frames, CropRect, MotionCropMode, MotionCropOpts};
+
+    // A red block slides along row 20 of an otherwise still gradient
+    let (width, height, frame_count) = (64u32, 48u32, 5usize);
+    let frames: Vec<u8> = (0..frame_count as u32)
+        .flat_map(|z| {
+            (0..width * height).flat_map(move |i| {
+                let (x, y) = (i % width, i / width);
+                if (10 + z * 4..18 + z * 4).contains(&x) && (20..26).contains(&y) {
+                    [230, 20, 20, 255]
+                } else {
+                    [(x * 3) as u8, (y * 4) as u8, 90, 255]
+                }
+            })
+        })
+        .collect();
+    let gif_opts = GifOpts { width: 32, height: 32, frame_count: frame_count as u32, ..Default::default() };
+    let run = |mode: MotionCropMode| {
+        let motion_crop = MotionCropOpts { mode, padding: 2, ..Default::default() };
+        let gif_opts = GifOpts { motion_crop, ..gif_opts.clone() };
+        process_all_frames(frames.clone(), width, height, frame_count as u32, QuantizeOpts::default(), gif_opts)
+            .expect("Processing failed")
+    };
+
+    // The block covers x 10-33 and y 20-25; padded by 2 and squared up for the 32×32 output
+    let expected = CropRect { x: 8, y: 9, width: 28, height: 28 };
+    let off = run(MotionCropMode::Off);
+    let detect = run(MotionCropMode::Detect);
+    let crop = run(MotionCropMode::Crop);
+    assert_eq!(off.motion_rect, None);
+    assert_eq!(detect.motion_rect, Some(expected));
+    assert_eq!(detect.gif_data, off.gif_data);
+    assert_eq!(crop.motion_rect, Some(expected));
+    assert_ne!(crop.gif_data, off.gif_data);
+
+    // The analysis pass offers the same region, which works as a region of interest
+    let motion_crop = MotionCropOpts { mode: MotionCropMode::Detect, padding: 2, ..Default::default() };
+    let analysis = analyze_frames(frames.clone(), width, height, frame_count as u32, QuantizeOpts::default(), GifOpts { motion_crop, ..gif_opts.clone() })
+        .expect("Analysis failed");
+    assert_eq!(analysis.motion_rect, Some(expected));
+    let confirmed = process_all_frames(frames, width, height, frame_count as u32, QuantizeOpts::default(), GifOpts { roi: Some(expected), ..gif_opts })
+        .expect("Processing failed");
+    assert_eq!(confirmed.gif_data, crop.gif_data);
+}
+
+#[test]
+fn test_matte_masks_drive_transparency_palette_and_occupancy() {
+    use rgb2gif_processor::process_masked_frames;
+
+    // A red subject on the right, a green backdrop on the left that the matte removes
+    let (width, height, frame_count) = (32u32, 32u32, 3usize);
+    let frames: Vec<u8> = (0..frame_count as u32 * width * height)
+        .flat_map(|i| if i % width < 16 { [20, 220, 40, 255] } else { [200 + (i % 7) as u8, 30, 30, 255] })
+        .collect();
+    // Soft edge: a faint fringe just left of the subject stays under the threshold
+    let masks: Vec<u8> = (0..frame_count as u32 * width * height)
+        .map(|i| match i % width { 0..=13 => 0, 14 | 15 => 60, _ => 255 })
+        .collect();
+    let gif_opts = GifOpts { width: 32, height: 32, frame_count: frame_count as u32, include_tensor: true, ..Default::default() };
+    let output = process_masked_frames(frames.clone(), masks, width, height, frame_count as u32, QuantizeOpts::default(), gif_opts.clone())
+        .expect("Processing failed");
+
+    // No backdrop green made it into the palette
+    assert!(output.palette_rgba.chunks_exact(4).all(|c| c[3] == 0 || c[1] < 100), "{:?}", output.palette_rgba);
+
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(output.gif_data.as_slice()).unwrap();
+    let frame = decoder.read_next_frame().unwrap().unwrap();
+    let pixel = |x: usize| &frame.buffer[(5 * 32 + x) * 4..(5 * 32 + x) * 4 + 4];
+    assert_eq!(pixel(3)[3], 0);
+    assert_eq!(pixel(15)[3], 0);
+    assert_eq!(pixel(20)[3], 255);
+
+    // The tensor keys the backdrop out too
+    let tensor = output.tensor_data.expect("Tensor missing");
+    let voxel = |x: usize| tensor[(64 * 128 + x) * 4 + 3];
+    assert_eq!((voxel(10), voxel(100)), (0, 255));
+
+    assert!(process_masked_frames(frames, vec![255; 10], width, height, frame_count as u32, QuantizeOpts::default(), gif_opts).is_err());
+}
+
+#[test]
+fn test_fit_scale_letterboxes_portrait_clip() {
+    use rgb2gif_processor::{LetterboxFill, LetterboxOpts, ProcessorOptions, QuantizerBackend, ScaleMode};
+
+    // 16×32 portrait into a 32×32 GIF leaves 8-pixel bars either side
+    let frames = create_test_frames(2, 16, 32);
+    let quantize_opts = QuantizeOpts { backend: QuantizerBackend::Oklab, quality_min: 0, ..Default::default() };
+    let decode_first = |fill: LetterboxFill| {
+        let gif_opts = GifOpts {
+            width: 32,
+            height: 32,
+            frame_count: 2,
+            scale_mode: ScaleMode::Fit,
+            letterbox: LetterboxOpts { fill, color: 0xFF00FF, ..Default::default() },
+            ..Default::default()
+        };
+        let output = process_all_frames(frames.clone(), 16, 32, 2, quantize_opts.clone(), gif_opts).expect("Processing failed");
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(output.gif_data.as_slice()).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (32, 32));
+        decoder.read_next_frame().unwrap().unwrap().buffer.to_vec()
+    };
+    let pixel = |frame: &[u8], x: usize, y: usize| frame[(y * 32 + x) * 4..(y * 32 + x) * 4 + 4].to_vec();
+
+    let transparent = decode_first(LetterboxFill::Transparent);
+    assert_eq!(pixel(&transparent, 3, 16)[3], 0);
+    assert_eq!(pixel(&transparent, 28, 16)[3], 0);
+    assert_eq!(pixel(&transparent, 16, 16)[3], 255);
+
+    let solid = decode_first(LetterboxFill::Solid);
+    let magenta = pixel(&solid, 3, 5);
+    assert!(magenta[0] > 200 && magenta[1] < 60 && magenta[2] > 200, "{magenta:?}");
+
+    // Blurred bars are opaque and follow the frame's vertical gradient
+    let blurred = decode_first(LetterboxFill::Blur);
+    let (top, bottom) = (pixel(&blurred, 2, 2), pixel(&blurred, 2, 29));
+    assert!(top[3] == 255 && bottom[3] == 255 && top[1] < bottom[1], "{top:?} {bottom:?}");
+
+    // The social-video preset: blurred, dimmed bars around the whole frame
+    let ProcessorOptions { gif_opts, .. } = ProcessorOptions::blurred_square(32);
+    let gif_opts = GifOpts { frame_count: 2, ..gif_opts };
+    let output = process_all_frames(frames.clone(), 16, 32, 2, quantize_opts, gif_opts).expect("Processing failed");
+    let decoder = gif::DecodeOptions::new().read_info(output.gif_data.as_slice()).unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (32, 32));
+}
+
+#[test]
+fn test_frame_transforms_undo_camera_shake() {
+    use rgb2gif_processor::{AffineTransform, ScaleMode};
+
+    // The scene drifts 3 pixels right per frame; the host reports the shift back
+    let (width, height, frame_count) = (32u32, 24u32, 4usize);
+    let scene = |x: i32, y: i32| [(x * 8).clamp(0, 255) as u8, (y * 10) as u8, ((x + y) * 3).clamp(0, 255) as u8, 255];
+    let frames: Vec<u8> = (0..frame_count as i32)
+        .flat_map(|z| (0..(width * height) as i32).flat_map(move |i| scene(i % width as i32 - 3 * z, i / width as i32)))
+        .collect();
+    let frame_transforms: Vec<AffineTransform> =
+        (0..frame_count).map(|z| AffineTransform { tx: -3.0 * z as f32, ..Default::default() }).collect();
+    let gif_opts = GifOpts { width: 32, height: 24, frame_count: frame_count as u32, frame_transforms, ..Default::default() };
+    let output = process_all_frames(frames.clone(), width, height, frame_count as u32, QuantizeOpts::default(), gif_opts.clone())
+        .expect("Processing failed");
+
+    // Steadied, everything left of the uncovered strip matches the first frame
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(output.gif_data.as_slice()).unwrap();
+    let mut first = None;
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        let steady: Vec<u8> = frame.buffer.chunks_exact(32 * 4).flat_map(|row| row[..20 * 4].to_vec()).collect();
+        match &first {
+            None => first = Some(steady),
+            Some(first) => assert_eq!(&steady, first),
+        }
+    }
+
+    let singular = vec![AffineTransform { a: 0.0, ..Default::default() }];
+    let bad = GifOpts { frame_transforms: singular, ..gif_opts.clone() };
+    assert!(process_all_frames(frames.clone(), width, height, frame_count as u32, QuantizeOpts::default(), bad).is_err());
+    let pixel_art = GifOpts { scale_mode: ScaleMode::PixelArt, ..gif_opts };
+    assert!(process_all_frames(frames, width, height, frame_count as u32, QuantizeOpts::default(), pixel_art).is_err());
+}
+
+#[test]
+fn test_long_exposure_traces_light_trail() {
+    use rgb2gif_processor::{ExposureBlend, LongExposureOpts};
+
+    // A white dot crosses a dark frame, one column per frame
+    let (width, height, frame_count) = (16u32, 16u32, 8usize);
+    let frames: Vec<u8> = (0..frame_count as u32)
+        .flat_map(|z| (0..width * height).flat_map(move |i| if i == 8 * width + 4 + z { [255; 4] } else { [10, 10, 20, 255] }))
+        .collect();
+    let long_exposure = LongExposureOpts { blend: ExposureBlend::Max, build_up_frames: 4 };
+    let gif_opts = GifOpts { width: 16, height: 16, frame_count: frame_count as u32, include_long_exposure: true, long_exposure, ..Default::default() };
+    let output = process_all_frames(frames, width, height, frame_count as u32, QuantizeOpts::default(), gif_opts)
+        .expect("Processing failed");
+
+    // The still holds the whole trail and nothing else
+    let still = output.long_exposure_data.expect("Still missing");
+    assert_eq!(still.len(), (width * height * 4) as usize);
+    for x in 0..16 {
+        let px = &still[((8 * 16 + x) * 4) as usize..((8 * 16 + x) * 4 + 4) as usize];
+        let expected = if (4..12).contains(&x) { [255; 4] } else { [10, 10, 20, 255] };
+        assert_eq!(px, expected, "column {x}");
+    }
+
+    // The build-up GIF grows the trail over its own frames
+    let build_up = output.long_exposure_gif.expect("Build-up missing");
+    let mut decoder = gif::DecodeOptions::new().read_info(build_up.as_slice()).unwrap();
+    let mut frames = 0;
+    while decoder.read_next_frame().unwrap().is_some() {
+        frames += 1;
+    }
+    assert_eq!(frames, 4);
+}
+
+#[test]
+fn test_imagequant_reports_quantization_error() {
+    use rgb2gif_processor::QuantizerBackend;
+
+    let gif_opts = GifOpts { width: 32, height: 32, frame_count: 4, ..Default::default() };
+    let encode = |quality_max| {
+        let quantize_opts = QuantizeOpts { quality_min: 0, quality_max, ..Default::default() };
+        process_all_frames(create_test_frames(4, 32, 32), 32, 32, 4, quantize_opts, gif_opts.clone())
+            .expect("Processing failed")
+            .quantization
+            .expect("No quantization report")
+    };
+    let (coarse, fine) = (encode(10), encode(100));
+    assert!(coarse.mean_square_error > fine.mean_square_error, "{coarse:?} vs {fine:?}");
+    assert!(coarse.quality < fine.quality, "{coarse:?} vs {fine:?}");
+    assert!(coarse.worst_frame < 4 && coarse.worst_frame_error >= coarse.mean_square_error);
+
+    let quantize_opts = QuantizeOpts { backend: QuantizerBackend::Oklab, ..Default::default() };
+    let oklab = process_all_frames(create_test_frames(4, 32, 32), 32, 32, 4, quantize_opts, gif_opts).unwrap();
+    assert_eq!(oklab.quantization, None);
+}
+
+#[test]
+fn test_oversized_output_is_downscaled_or_rejected() {
+    use rgb2gif_processor::{OversizePolicy, ProcessorError};
+
+    let gif_opts = GifOpts { width: 65000, height: 64, frame_count: 1, ..Default::default() };
+    let output = process_all_frames(create_test_frames(1, 16, 16), 16, 16, 1, QuantizeOpts::default(), gif_opts.clone())
+        .expect("Processing failed");
+    let decoder = gif::DecodeOptions::new().read_info(output.gif_data.as_slice()).unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (4096, 4));
+
+    let reject = GifOpts { oversize: OversizePolicy::Reject, ..gif_opts };
+    match process_all_frames(create_test_frames(1, 16, 16), 16, 16, 1, QuantizeOpts::default(), reject) {
+        Err(ProcessorError::InvalidOption { field, .. }) => assert_eq!(field, "gif_opts.width"),
+        other => panic!("expected InvalidOption, got {:?}", other.map(|r| r.final_file_size)),
+    }
+}
+
+#[test]
+fn test_frame_counts_past_u16_are_kept() {
+    let frame_count = u16::MAX as u32 + 10;
+    let gif_opts = GifOpts { width: 1, height: 1, frame_count: 0, ..Default::default() };
+    let output = process_all_frames(create_test_frames(frame_count as usize, 1, 1), 1, 1, frame_count, QuantizeOpts::default(), gif_opts)
+        .expect("Processing failed");
+    assert_eq!(output.actual_frame_count, frame_count);
+}
+
+#[test]
+fn test_cached_reexport_returns_stored_result() {
+    use rgb2gif_processor::{clear_result_cache, process_with_options, CacheConfig, ProcessorOptions};
+
+    let dir = std::env::temp_dir().join(format!("integration-cache-{}", std::process::id()));
+    let cache = CacheConfig { directory: dir.to_string_lossy().into_owned(), max_entries: 1, ..Default::default() };
+    let options = |fps| ProcessorOptions {
+        quantize_opts: QuantizeOpts::default(),
+        gif_opts: GifOpts { width: 16, height: 16, frame_count: 4, fps, include_tensor: true, ..Default::default() },
+        cache: Some(cache.clone()),
+    };
+    let export = |fps| process_with_options(create_test_frames(4, 16, 16), 16, 16, 4, options(fps)).expect("Processing failed");
+
+    let first = export(30);
+    let again = export(30);
+    assert_eq!(again.gif_data, first.gif_data);
+    assert_eq!(again.tensor_data, first.tensor_data);
+    assert_eq!(again.palette_rgba, first.palette_rgba);
+    // Nothing ran the second time
+    assert_eq!(again.stage_timings.quantize_ms, 0.0);
+    assert!(first.stage_timings.quantize_ms > 0.0);
+
+    // Different settings miss, and the one-entry limit evicts the first export
+    let slower = export(10);
+    assert_ne!(slower.gif_data, first.gif_data);
+    assert!(slower.stage_timings.quantize_ms > 0.0);
+    let entries = std::fs::read_dir(&dir).unwrap().count();
+    assert_eq!(entries, 1);
+
+    assert_eq!(clear_result_cache(cache.clone()).unwrap(), 1);
+    assert!(clear_result_cache(CacheConfig::default()).is_err());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_validate_matches_the_export_it_vets() {
+    use rgb2gif_processor::process_all_frames_validate;
+
+    let gif_opts = GifOpts { width: 48, height: 32, frame_count: 6, include_tensor: true, ..Default::default() };
+    let report = process_all_frames_validate(64, 64, 10, QuantizeOpts::default(), gif_opts.clone());
+    assert_eq!(report.error, None);
+    assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+
+    let result = process_all_frames(create_test_frames(10, 64, 64), 64, 64, 10, QuantizeOpts::default(), gif_opts.clone())
+        .expect("Processing failed");
+    assert_eq!(report.frame_count, result.actual_frame_count);
+    assert_eq!(report.input_bytes, 64 * 64 * 4 * 10);
+    assert!(result.gif_data.len() as u64 <= report.max_gif_bytes);
+    assert_eq!(report.tensor_bytes, result.tensor_data.unwrap().len() as u64);
+
+    // The same options process_all_frames rejects, named the same way
+    let bad = GifOpts { fps: 0, ..gif_opts };
+    let report = process_all_frames_validate(64, 64, 10, QuantizeOpts::default(), bad.clone());
+    let err = process_all_frames(create_test_frames(10, 64, 64), 64, 64, 10, QuantizeOpts::default(), bad).unwrap_err();
+    assert_eq!(report.error, Some(err.to_string()));
+    assert_eq!(report.error_field.as_deref(), Some("gif_opts.fps"));
+}
+
+#[test]
+fn test_tiny_palettes_get_tiny_color_tables() {
+    use rgb2gif_processor::{ProcessorOptions, QuantizerBackend};
+
+    // Entries in the GIF's global color table
+    let table_entries = |gif: &[u8]| 2usize << (gif[10] & 0x07);
+    let export = |options: ProcessorOptions| {
+        let gif_opts = GifOpts { width: 32, height: 32, frame_count: 3, ..options.gif_opts };
+        process_all_frames(create_test_frames(3, 32, 32), 32, 32, 3, options.quantize_opts, gif_opts).expect("Processing failed")
+    };
+
+    for colors in [2u16, 4, 16] {
+        let result = export(ProcessorOptions::low_color(colors));
+        assert!(result.palette_size_used <= colors);
+        assert_eq!(table_entries(&result.gif_data), colors as usize, "{} colors", colors);
+
+        // imagequant keeps to the budget too
+        let quantize_opts = QuantizeOpts { palette_size: colors, quality_min: 0, backend: QuantizerBackend::Imagequant, ..Default::default() };
+        let result = export(ProcessorOptions { quantize_opts, ..ProcessorOptions::low_color(colors) });
+        assert!(result.palette_size_used <= colors, "imagequant used {} of {}", result.palette_size_used, colors);
+        assert!(table_entries(&result.gif_data) <= colors as usize);
+    }
+
+    let one_bit = export(ProcessorOptions::one_bit());
+    assert_eq!(one_bit.palette_rgba, [0, 0, 0, 255, 255, 255, 255, 255]);
+    assert_eq!(table_entries(&one_bit.gif_data), 2);
+
+    let ProcessorOptions { quantize_opts, gif_opts, .. } = ProcessorOptions::game_boy();
+    assert_eq!((gif_opts.width, gif_opts.height), (160, 144));
+    let game_boy = export(ProcessorOptions { quantize_opts, gif_opts, cache: None });
+    assert_eq!(game_boy.palette_size_used, 4);
+    assert_eq!(game_boy.palette_rgba[..4], [15, 56, 15, 255]);
+}
+
+#[test]
+fn test_text_art_comes_as_text_and_gif() {
+    use rgb2gif_processor::{TextArtOpts, TextArtStyle};
+
+    for (style, rows) in [(TextArtStyle::Ascii, 8), (TextArtStyle::Emoji, 16)] {
+        let gif_opts = GifOpts {
+            width: 32,
+            height: 32,
+            frame_count: 4,
+            include_text_art: true,
+            text_art: TextArtOpts { style, columns: 16, ..Default::default() },
+            ..Default::default()
+        };
+        let output = process_all_frames(create_test_frames(4, 32, 32), 32, 32, 4, QuantizeOpts::default(), gif_opts)
+            .expect("Processing failed");
+
+        assert_eq!(output.text_art.len(), 4);
+        assert!(output.text_art.iter().all(|frame| frame.lines().count() == rows), "{:?}", output.text_art[0]);
+        // The gradient brightens across the frame, so each line ends denser than it starts
+        assert_ne!(output.text_art[0].lines().next().unwrap().chars().next(), output.text_art[0].lines().last().unwrap().chars().last());
+
+        let mut decoder = gif::DecodeOptions::new().read_info(output.text_art_gif.as_deref().unwrap()).unwrap();
+        let cell = if style == TextArtStyle::Emoji { 12 } else { 6 };
+        assert_eq!((decoder.width(), decoder.height()), (16 * cell, rows as u16 * 12));
+        let mut frames = 0;
+        while decoder.read_next_frame().unwrap().is_some() {
+            frames += 1;
+        }
+        assert_eq!(frames, 4);
+    }
+
+    let too_narrow = GifOpts { include_text_art: true, text_art: TextArtOpts { columns: 2, ..Default::default() }, ..Default::default() };
+    assert!(process_all_frames(create_test_frames(1, 32, 32), 32, 32, 1, QuantizeOpts::default(), too_narrow).is_err());
+}
+
+#[test]
+fn test_audio_energy_paces_the_frames() {
+    use rgb2gif_processor::AudioSyncOpts;
+
+    let decode_delays = |audio_sync: AudioSyncOpts| -> Vec<u16> {
+        let gif_opts = GifOpts { width: 16, height: 16, frame_count: 0, fps: 10, audio_sync, ..Default::default() };
+        let quantize_opts = QuantizeOpts { quality_min: 0, ..Default::default() };
+        let output = process_all_frames(create_test_frames(8, 16, 16), 16, 16, 8, quantize_opts, gif_opts)
+            .expect("Processing failed");
+        let mut decoder = gif::DecodeOptions::new().read_info(output.gif_data.as_slice()).unwrap();
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+        }
+        delays
+    };
+
+    // Beats on every other frame; they flash by and the clip still lasts 0.8s
+    let beats = vec![0.1, 0.9, 0.1, 0.9, 0.1, 0.9, 0.1, 0.9];
+    let synced = decode_delays(AudioSyncOpts { energy: beats.clone(), timing: 0.8, ..Default::default() });
+    assert_eq!(synced.iter().sum::<u16>(), 80);
+    assert!(synced.chunks(2).all(|pair| pair[1] < pair[0]), "{synced:?}");
+    assert_eq!(decode_delays(AudioSyncOpts { energy: beats, timing: 0.0, ..Default::default() }), [10; 8]);
+
+    let bad = AudioSyncOpts { energy: vec![0.5, f32::NAN], ..Default::default() };
+    let gif_opts = GifOpts { width: 16, height: 16, audio_sync: bad, ..Default::default() };
+    assert!(process_all_frames(create_test_frames(2, 16, 16), 16, 16, 2, QuantizeOpts::default(), gif_opts).is_err());
+}
+
+#[test]
+fn test_composed_clips_share_one_palette() {
+    use rgb2gif_processor::{compose_clips, CompositeClip, CompositeLayout, CompositeOpts, ScaleMode};
+
+    let clip = |color: [u8; 4], width: u32, height: u32, frame_count: u32| CompositeClip {
+        frames_rgba: color.repeat((width * height * frame_count) as usize),
+        width,
+        height,
+        frame_count,
+        scale_mode: ScaleMode::Area,
+        roi: None,
+    };
+    let (before, after) = (clip([200, 40, 40, 255], 64, 48, 4), clip([40, 40, 200, 255], 32, 32, 2));
+    let quantize_opts = QuantizeOpts { quality_min: 0, ..Default::default() };
+    let gif_opts = GifOpts { width: 64, height: 32, frame_count: 0, ..Default::default() };
+
+    for layout in [CompositeLayout::SideBySide, CompositeLayout::PictureInPicture] {
+        let composite_opts = CompositeOpts { layout, gap: 2, background: 0xffffff, inset_margin: 2, ..Default::default() };
+        let output = compose_clips(before.clone(), after.clone(), composite_opts, quantize_opts.clone(), gif_opts.clone())
+            .expect("Composing failed");
+        assert_eq!(output.actual_frame_count, 4);
+
+        let mut decoder = gif::DecodeOptions::new();
+        decoder.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = decoder.read_info(output.gif_data.as_slice()).unwrap();
+        assert!(decoder.global_palette().is_some());
+        let mut frames = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert!(frame.palette.is_none());
+            let pixel = |x: usize, y: usize| &frame.buffer[(y * 64 + x) * 4..][..3];
+            // Red top left either way; the blue clip on the right half or inset bottom right
+            assert!(pixel(1, 1)[0] > 150 && pixel(1, 1)[2] < 100);
+            assert!(pixel(60, 28)[2] > 150 && pixel(60, 28)[0] < 100);
+            frames += 1;
+        }
+        assert_eq!(frames, 4);
+    }
+
+    let too_wide_gap = CompositeOpts { gap: 63, ..Default::default() };
+    assert!(compose_clips(before, after, too_wide_gap, quantize_opts, gif_opts).is_err());
+}
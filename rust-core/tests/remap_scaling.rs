@@ -0,0 +1,57 @@
+// Remap scaling benchmark
+// How the imagequant path speeds up with cores; run with
+// `cargo test --release --features bench --test remap_scaling -- --nocapture`
+
+#![cfg(feature = "bench")]
+
+use rgb2gif_processor::{process_all_frames, GifOpts, QuantizeOpts};
+use std::time::Instant;
+
+/// Threads to time with, up to the cores this machine has
+const POOL_SIZES: [usize; 5] = [1, 2, 4, 6, 8];
+
+fn clip(count: u32, side: u32) -> Vec<u8> {
+    (0..count * side * side)
+        .flat_map(|i| {
+            let (frame, x, y) = (i / (side * side), i % side, i / side % side);
+            [(x * 255 / side + frame) as u8, (y * 255 / side) as u8, ((x ^ y) + frame * 3) as u8, 255]
+        })
+        .collect()
+}
+
+/// Best of three runs of the 256-frame clip, in milliseconds spent quantizing and remapping
+fn quantize_ms(threads: usize, frames: &[u8]) -> f32 {
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+    let quantize_opts = QuantizeOpts { quality_min: 0, ..Default::default() };
+    let gif_opts = GifOpts { width: 128, height: 128, frame_count: 256, ..Default::default() };
+    (0..3)
+        .map(|_| {
+            let result = pool.install(|| {
+                process_all_frames(frames.to_vec(), 128, 128, 256, quantize_opts.clone(), gif_opts.clone()).unwrap()
+            });
+            result.stage_timings.quantize_ms
+        })
+        .fold(f32::MAX, f32::min)
+}
+
+#[test]
+fn bench_remap_scales_with_cores() {
+    let frames = clip(256, 128);
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let start = Instant::now();
+    let serial = quantize_ms(1, &frames);
+    println!("threads  quantize_ms  speedup");
+    println!("{:>7}  {:>11.1}  {:>7.2}", 1, serial, 1.0);
+    let mut timings = vec![(1, serial)];
+    for threads in POOL_SIZES.into_iter().skip(1).filter(|&threads| threads <= cores) {
+        let ms = quantize_ms(threads, &frames);
+        println!("{:>7}  {:>11.1}  {:>7.2}", threads, ms, serial / ms);
+        timings.push((threads, ms));
+    }
+    println!("({} cores, {:.1}s)", cores, start.elapsed().as_secs_f32());
+
+    // Palette generation stays serial, so six cores should still give well over twice the speed
+    if let Some(&(_, ms)) = timings.iter().find(|(threads, _)| *threads == 6) {
+        assert!(serial / ms > 2.5, "6 threads only {:.2}x faster", serial / ms);
+    }
+}
@@ -47,11 +47,11 @@ cargo build --target aarch64-apple-ios-sim --release
 echo ""
 echo "Creating universal library for simulator..."
 lipo -create \
-    target/x86_64-apple-ios/release/libyingif.a \
-    target/aarch64-apple-ios-sim/release/libyingif.a \
-    -output target/universal-ios-sim/libyingif.a
+    ../target/x86_64-apple-ios/release/libyingif.a \
+    ../target/aarch64-apple-ios-sim/release/libyingif.a \
+    -output ../target/universal-ios-sim/libyingif.a
 
-mkdir -p target/universal-ios-sim
+mkdir -p ../target/universal-ios-sim
 
 echo ""
 echo "Generating C header..."
@@ -62,9 +62,9 @@ echo "Creating XCFramework..."
 rm -rf ../YinGif.xcframework
 
 xcodebuild -create-xcframework \
-    -library target/aarch64-apple-ios/release/libyingif.a \
+    -library ../target/aarch64-apple-ios/release/libyingif.a \
     -headers include \
-    -library target/universal-ios-sim/libyingif.a \
+    -library ../target/universal-ios-sim/libyingif.a \
     -headers include \
     -output ../YinGif.xcframework
 
@@ -0,0 +1,154 @@
+#ifndef YINGIF_H
+#define YINGIF_H
+
+/* This file is auto-generated by cbindgen. Do not edit manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Browsers replace delays below 2cs with 10cs, so never emit less
+ */
+#define MIN_DELAY_CS 2
+
+/**
+ * Create a new processor instance
+ */
+void *yingif_processor_new(void);
+
+/**
+ * Cap the frames a processor keeps, so a capture that is never finished can't grow forever
+ *
+ * `max_frames` and `max_bytes` of 0 lift that limit; new processors keep up
+ * to 256 MiB. `policy` 0 refuses frames past a limit (the add returns -4),
+ * 1 drops the oldest frames to make room. Returns 0, or -1 on bad arguments.
+ */
+int32_t yingif_processor_set_limits(void *processor,
+                                    int32_t max_frames,
+                                    int64_t max_bytes,
+                                    int32_t policy);
+
+/**
+ * Bytes of frame data a processor currently holds, or -1 for a bad handle
+ */
+int64_t yingif_processor_memory_usage(void *processor);
+
+/**
+ * Free a processor instance
+ */
+void yingif_processor_free(void *processor);
+
+/**
+ * Process a single BGRA frame
+ *
+ * Returns 0, -1 on bad arguments, or -4 if the processor's limits refuse the frame.
+ */
+int32_t yingif_process_frame(void *processor,
+                             const uint8_t *bgra_data,
+                             int32_t width,
+                             int32_t height,
+                             int32_t target_size,
+                             int32_t palette_size,
+                             uint8_t *out_indices,
+                             uint32_t *out_palette);
+
+/**
+ * Process a BGRA frame captured at `timestamp_ns` (monotonic clock)
+ *
+ * Same as `yingif_process_frame`, but the timestamp is kept so
+ * `yingif_processor_create_gif` can reproduce the real frame pacing.
+ * Returns -3 if the timestamp is earlier than the previous frame's.
+ */
+int32_t yingif_processor_add_frame_with_timestamp(void *processor,
+                                                  const uint8_t *bgra_data,
+                                                  int32_t width,
+                                                  int32_t height,
+                                                  int32_t target_size,
+                                                  int32_t palette_size,
+                                                  uint64_t timestamp_ns,
+                                                  uint8_t *out_indices,
+                                                  uint32_t *out_palette);
+
+/**
+ * Create GIF from accumulated frames
+ */
+int32_t yingif_create_gif89a(const uint8_t *indices,
+                             const uint32_t *palette,
+                             int32_t cube_size,
+                             int32_t palette_size,
+                             int32_t delay_ms,
+                             uint8_t *out_data,
+                             int32_t out_capacity,
+                             int32_t *out_size);
+
+/**
+ * Create a GIF89a from the frames accumulated in `processor`
+ *
+ * The frames go through the full core pipeline, so the clip gets one shared
+ * palette and the core's dithering and transparency handling. With
+ * `resample_fps` 0 the delays follow the capture timestamps, so every frame
+ * must have been added with `yingif_processor_add_frame_with_timestamp` (-3
+ * otherwise). A positive `resample_fps` re-times the clip onto that constant
+ * rate, repeating or skipping frames as needed; without timestamps the frames
+ * are simply played at that rate. Returns 0, -1 on bad arguments, -2 if
+ * `out_capacity` is too small.
+ */
+int32_t yingif_processor_create_gif(void *processor,
+                                    int32_t resample_fps,
+                                    uint8_t *out_data,
+                                    int32_t out_capacity,
+                                    int32_t *out_size);
+
+/**
+ * `yingif_processor_create_gif`, streamed to the file at `path` instead of a buffer
+ *
+ * For exports too big to hold in memory: frames are written as they are
+ * compressed, and the file only appears once it is complete. Writes the
+ * file's size to `out_size`. Returns 0, -1 on bad arguments or a failed
+ * write, -3 as for `yingif_processor_create_gif`.
+ */
+int32_t yingif_processor_create_gif_to_file(void *processor,
+                                            int32_t resample_fps,
+                                            const char *path,
+                                            int64_t *out_size);
+
+/**
+ * Predict the size and encode time of `yingif_processor_create_gif`
+ *
+ * Encodes a few evenly spaced accumulated frames and extrapolates to the
+ * whole clip, writing the likely size range in bytes and the time in
+ * milliseconds. Returns 0, or -1 on bad arguments or an empty processor.
+ */
+int32_t yingif_processor_estimate(void *processor,
+                                  int32_t *out_bytes_low,
+                                  int32_t *out_bytes_high,
+                                  float *out_ms);
+
+/**
+ * Output buffer size that always fits a cube_size³ GIF
+ *
+ * A worst-case bound rather than a guess: every pixel costs at most one
+ * 12-bit LZW code, plus clear codes, sub-block lengths and per-frame blocks.
+ * Use `yingif_processor_estimate` for the size the clip will likely have.
+ */
+int32_t yingif_estimate_gif_size(int32_t cube_size, int32_t palette_size);
+
+/**
+ * Record panics for `yingif_last_panic`, also writing each to `log_path` if not null
+ *
+ * Call once at launch. A panic inside a C call aborts the process, so the file
+ * is what the next launch can read back. Returns 0, or -1 if `log_path` isn't UTF-8.
+ */
+int32_t yingif_install_panic_hook(const char *log_path);
+
+/**
+ * Copy the latest panic report (message, thread, backtrace) into `out` as a C string
+ *
+ * Returns the report's length without the terminator, 0 if nothing has
+ * panicked, -1 on bad arguments or -2 if `out_capacity` is too small.
+ */
+int32_t yingif_last_panic(char *out, int32_t out_capacity);
+
+#endif /* YINGIF_H */
@@ -1,57 +1,170 @@
 //! iOS FFI for RGB2GIF2VOXEL
 //! Provides C API matching Swift expectations
+//!
+//! A thin C adapter over `rgb2gif_processor`: resizing, quantization and GIF
+//! encoding all run through the shared core, so this crate only marshals
+//! pointers and keeps per-processor state.
 
-use std::collections::HashMap;
+// Every entry point checks its pointers before touching them
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use std::collections::BTreeMap;
 use std::ptr;
 use std::slice;
-use std::sync::Mutex;
-use color_quant::NeuQuant;
-use image::{ImageBuffer, Rgba, DynamicImage};
-use gif::{Encoder, Frame, Repeat};
-use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use rgb2gif_processor::palette::{Color32, Palette};
+use rgb2gif_processor::{
+    encode_indexed_frames, estimate_encode, install_panic_hook, process_all_frames, quantize_pixels, resize_frame, rgb2gif_last_panic,
+    GifOpts, PixelLayout, ProcessResult, QuantizeOpts,
+};
+
+mod timing;
 
 // Processor state for accumulating frames
 pub struct YinGifProcessor {
-    frames: Vec<Vec<u8>>,  // Accumulated frames
+    frames: Vec<Vec<u8>>,  // Accumulated RGBA frames at target size
+    timestamps_ns: Vec<u64>, // Capture time per frame, when added with a timestamp
     target_size: usize,     // Target dimension (e.g., 132)
     palette_size: usize,    // Palette size (e.g., 256)
+    max_frames: usize,      // Frames kept at most (0 = no limit)
+    max_bytes: usize,       // Frame memory kept at most (0 = no limit)
+    retention: Retention,   // What happens to a frame past either limit
 }
 
-// Global processor storage (for simplicity)
-static mut PROCESSORS: Option<Mutex<HashMap<usize, YinGifProcessor>>> = None;
-static mut NEXT_ID: usize = 1;
+/// What `process_frame` does when a new frame would go past a processor's limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Retention {
+    Reject,     // Refuse the new frame with -4
+    DropOldest, // Forget the oldest frames to make room
+}
 
-/// Initialize the processor system (called once)
-fn ensure_initialized() {
-    unsafe {
-        if PROCESSORS.is_none() {
-            PROCESSORS = Some(Mutex::new(HashMap::new()));
+/// Frame memory a processor keeps until told otherwise, enough for a minute of 132² at 30fps
+const DEFAULT_MAX_BYTES: usize = 256 << 20;
+
+impl YinGifProcessor {
+    /// Bytes held by the accumulated frames and their timestamps
+    fn memory_usage(&self) -> usize {
+        self.frames.iter().map(Vec::len).sum::<usize>() + self.timestamps_ns.len() * std::mem::size_of::<u64>()
+    }
+
+    /// Apply the retention policy for a new `frame_bytes` frame; false if it must be refused
+    fn make_room(&mut self, frame_bytes: usize) -> bool {
+        let max_frames = if self.max_frames == 0 { usize::MAX } else { self.max_frames };
+        let max_bytes = if self.max_bytes == 0 { usize::MAX } else { self.max_bytes };
+        if frame_bytes > max_bytes {
+            return false;
+        }
+        match self.retention {
+            Retention::Reject => self.frames.len() < max_frames && self.memory_usage() + frame_bytes <= max_bytes,
+            Retention::DropOldest => {
+                let (mut drop, mut kept_bytes) = (0, self.memory_usage());
+                while self.frames.len() - drop >= max_frames || kept_bytes + frame_bytes > max_bytes {
+                    kept_bytes -= self.frames[drop].len();
+                    if drop < self.timestamps_ns.len() {
+                        kept_bytes -= std::mem::size_of::<u64>();
+                    }
+                    drop += 1;
+                }
+                self.frames.drain(..drop);
+                self.timestamps_ns.drain(..drop.min(self.timestamps_ns.len()));
+                true
+            }
         }
     }
 }
 
+// Every live processor by handle; each has its own lock, so the map's lock is
+// only held to look a handle up and separate captures never wait on each other
+static PROCESSORS: Mutex<BTreeMap<usize, Arc<Mutex<YinGifProcessor>>>> = Mutex::new(BTreeMap::new());
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// The processor behind a handle, or `None` if it was never created or is freed
+fn lookup(processor: *mut libc::c_void) -> Option<Arc<Mutex<YinGifProcessor>>> {
+    PROCESSORS.lock().ok()?.get(&(processor as usize)).cloned()
+}
+
+/// Quantizer settings for a `palette_size` color palette
+///
+/// No quality floor, so a hard frame gets its best palette instead of an error.
+fn quantize_opts(palette_size: usize) -> QuantizeOpts {
+    QuantizeOpts {
+        quality_min: 0,
+        palette_size: palette_size as u16,
+        ..Default::default()
+    }
+}
+
+/// Copy a finished GIF to the caller's buffer; -2 if it doesn't fit
+unsafe fn write_output(gif_data: &[u8], out_data: *mut u8, out_capacity: i32, out_size: *mut i32) -> i32 {
+    if gif_data.len() > out_capacity.max(0) as usize {
+        return -2; // Buffer too small
+    }
+    ptr::copy_nonoverlapping(gif_data.as_ptr(), out_data, gif_data.len());
+    *out_size = gif_data.len() as i32;
+    0
+}
+
 /// Create a new processor instance
 #[no_mangle]
 pub extern "C" fn yingif_processor_new() -> *mut libc::c_void {
-    ensure_initialized();
-    
     let processor = YinGifProcessor {
         frames: Vec::new(),
+        timestamps_ns: Vec::new(),
         target_size: 132,  // Default
         palette_size: 256, // Default
+        max_frames: 0,
+        max_bytes: DEFAULT_MAX_BYTES,
+        retention: Retention::Reject,
     };
-    
-    unsafe {
-        let id = NEXT_ID;
-        NEXT_ID += 1;
-        
-        if let Some(ref processors) = PROCESSORS {
-            processors.lock().unwrap().insert(id, processor);
-            return id as *mut libc::c_void;
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    match PROCESSORS.lock() {
+        Ok(mut processors) => {
+            processors.insert(id, Arc::new(Mutex::new(processor)));
+            id as *mut libc::c_void
         }
+        Err(_) => ptr::null_mut(),
     }
-    
-    ptr::null_mut()
+}
+
+/// Cap the frames a processor keeps, so a capture that is never finished can't grow forever
+///
+/// `max_frames` and `max_bytes` of 0 lift that limit; new processors keep up
+/// to 256 MiB. `policy` 0 refuses frames past a limit (the add returns -4),
+/// 1 drops the oldest frames to make room. Returns 0, or -1 on bad arguments.
+#[no_mangle]
+pub extern "C" fn yingif_processor_set_limits(processor: *mut libc::c_void, max_frames: i32, max_bytes: i64, policy: i32) -> i32 {
+    let retention = match policy {
+        0 => Retention::Reject,
+        1 => Retention::DropOldest,
+        _ => return -1,
+    };
+    if processor.is_null() || max_frames < 0 || max_bytes < 0 {
+        return -1;
+    }
+    let Some(shared) = lookup(processor) else {
+        return -1;
+    };
+    let Ok(mut proc) = shared.lock() else {
+        return -1;
+    };
+    proc.max_frames = max_frames as usize;
+    proc.max_bytes = usize::try_from(max_bytes).unwrap_or(usize::MAX);
+    proc.retention = retention;
+    0
+}
+
+/// Bytes of frame data a processor currently holds, or -1 for a bad handle
+#[no_mangle]
+pub extern "C" fn yingif_processor_memory_usage(processor: *mut libc::c_void) -> i64 {
+    let Some(shared) = lookup(processor) else {
+        return -1;
+    };
+    let Ok(proc) = shared.lock() else {
+        return -1;
+    };
+    proc.memory_usage() as i64
 }
 
 /// Free a processor instance
@@ -60,16 +173,15 @@ pub extern "C" fn yingif_processor_free(processor: *mut libc::c_void) {
     if processor.is_null() {
         return;
     }
-    
-    unsafe {
-        let id = processor as usize;
-        if let Some(ref processors) = PROCESSORS {
-            processors.lock().unwrap().remove(&id);
-        }
+
+    if let Ok(mut processors) = PROCESSORS.lock() {
+        processors.remove(&(processor as usize));
     }
 }
 
 /// Process a single BGRA frame
+///
+/// Returns 0, -1 on bad arguments, or -4 if the processor's limits refuse the frame.
 #[no_mangle]
 pub extern "C" fn yingif_process_frame(
     processor: *mut libc::c_void,
@@ -80,59 +192,121 @@ pub extern "C" fn yingif_process_frame(
     palette_size: i32,
     out_indices: *mut u8,
     out_palette: *mut u32,
+) -> i32 {
+    process_frame(processor, bgra_data, width, height, target_size, palette_size, None, out_indices, out_palette)
+}
+
+/// Process a BGRA frame captured at `timestamp_ns` (monotonic clock)
+///
+/// Same as `yingif_process_frame`, but the timestamp is kept so
+/// `yingif_processor_create_gif` can reproduce the real frame pacing.
+/// Returns -3 if the timestamp is earlier than the previous frame's.
+#[no_mangle]
+pub extern "C" fn yingif_processor_add_frame_with_timestamp(
+    processor: *mut libc::c_void,
+    bgra_data: *const u8,
+    width: i32,
+    height: i32,
+    target_size: i32,
+    palette_size: i32,
+    timestamp_ns: u64,
+    out_indices: *mut u8,
+    out_palette: *mut u32,
+) -> i32 {
+    process_frame(processor, bgra_data, width, height, target_size, palette_size, Some(timestamp_ns), out_indices, out_palette)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn process_frame(
+    processor: *mut libc::c_void,
+    bgra_data: *const u8,
+    width: i32,
+    height: i32,
+    target_size: i32,
+    palette_size: i32,
+    timestamp_ns: Option<u64>,
+    out_indices: *mut u8,
+    out_palette: *mut u32,
 ) -> i32 {
     if processor.is_null() || bgra_data.is_null() || out_indices.is_null() || out_palette.is_null() {
         return -1;
     }
-    
+    if width <= 0 || height <= 0 || target_size <= 0 || !(2..=256).contains(&palette_size) {
+        return -1;
+    }
+
+    let Some(shared) = lookup(processor) else {
+        return -1;
+    };
+    let Ok(mut proc) = shared.lock() else {
+        return -1;
+    };
+    if let Some(ts) = timestamp_ns {
+        // Timestamps must cover every frame and never go backwards
+        if proc.timestamps_ns.len() != proc.frames.len()
+            || proc.timestamps_ns.last().is_some_and(|&last| ts < last)
+        {
+            return -3;
+        }
+    }
+    // Resizing treats the channels alike, and the quantizer reads BGRA in
+    // place, so a camera frame at the target size is quantized without a copy
+    let Some(frame_len) = (width as usize).checked_mul(height as usize).and_then(|n| n.checked_mul(4)) else {
+        return -1;
+    };
+    let (width, height, side) = (width as u32, height as u32, target_size as u32);
+    let bgra = unsafe { slice::from_raw_parts(bgra_data, frame_len) };
+    let resized = if (width, height) != (side, side) {
+        match resize_frame(bgra, width, height, side, side) {
+            Ok(frame) => Some(frame),
+            Err(_) => return -1,
+        }
+    } else {
+        None
+    };
+    let frame_bgra = resized.as_deref().unwrap_or(bgra);
+
+    // Quantize
+    let opts = quantize_opts(palette_size as usize);
+    let Ok((palette, indices)) = quantize_pixels(frame_bgra, side, side, side as usize * 4, PixelLayout::Bgra, &opts) else {
+        return -1;
+    };
+
+    // Only a frame that made it this far may push older ones out
+    let frame_bytes = frame_bgra.len() + timestamp_ns.map_or(0, |_| std::mem::size_of::<u64>());
+    if !proc.make_room(frame_bytes) {
+        return -4;
+    }
+
+    // Update settings
+    proc.target_size = target_size as usize;
+    proc.palette_size = palette_size as usize;
+
+    // Copy outputs; unused palette slots are black
     unsafe {
-        let id = processor as usize;
-        if let Some(ref processors) = PROCESSORS {
-            if let Some(proc) = processors.lock().unwrap().get_mut(&id) {
-                // Update settings
-                proc.target_size = target_size as usize;
-                proc.palette_size = palette_size as usize;
-                
-                // Convert BGRA to RGBA
-                let pixel_count = (width * height) as usize;
-                let bgra_slice = slice::from_raw_parts(bgra_data, pixel_count * 4);
-                let mut rgba_data = vec![0u8; pixel_count * 4];
-                
-                for i in 0..pixel_count {
-                    rgba_data[i * 4] = bgra_slice[i * 4 + 2];     // R
-                    rgba_data[i * 4 + 1] = bgra_slice[i * 4 + 1]; // G
-                    rgba_data[i * 4 + 2] = bgra_slice[i * 4];     // B
-                    rgba_data[i * 4 + 3] = bgra_slice[i * 4 + 3]; // A
-                }
-                
-                // Resize if needed
-                let resized = if width != target_size || height != target_size {
-                    resize_lanczos3(&rgba_data, width as u32, height as u32, target_size as u32)
-                } else {
-                    rgba_data
-                };
-                
-                // Quantize
-                let (palette, indices) = quantize_neuquant(&resized, target_size as u32, palette_size as usize);
-                
-                // Copy outputs
-                let out_indices_slice = slice::from_raw_parts_mut(out_indices, (target_size * target_size) as usize);
-                out_indices_slice.copy_from_slice(&indices);
-                
-                let out_palette_slice = slice::from_raw_parts_mut(out_palette, palette_size as usize);
-                for (i, &color) in palette.iter().enumerate() {
-                    out_palette_slice[i] = color;
-                }
-                
-                // Store processed frame for later GIF creation
-                proc.frames.push(indices);
-                
-                return 0;
-            }
+        let out_indices_slice = slice::from_raw_parts_mut(out_indices, indices.len());
+        out_indices_slice.copy_from_slice(&indices);
+
+        let out_palette_slice = slice::from_raw_parts_mut(out_palette, proc.palette_size);
+        for (i, slot) in out_palette_slice.iter_mut().enumerate() {
+            *slot = palette.get(i).map_or(0, Color32::to_rgb_u32);
+        }
+    }
+
+    // Keep an RGBA copy so `yingif_processor_create_gif` can run the full pipeline
+    let frame = match resized {
+        Some(mut frame) => {
+            frame.chunks_exact_mut(4).for_each(|px| px.swap(0, 2));
+            frame
         }
+        None => bgra.chunks_exact(4).flat_map(|px| [px[2], px[1], px[0], px[3]]).collect(),
+    };
+    proc.frames.push(frame);
+    if let Some(ts) = timestamp_ns {
+        proc.timestamps_ns.push(ts);
     }
-    
-    -1
+
+    0
 }
 
 /// Create GIF from accumulated frames
@@ -150,117 +324,383 @@ pub extern "C" fn yingif_create_gif89a(
     if indices.is_null() || palette.is_null() || out_data.is_null() || out_size.is_null() {
         return -1;
     }
-    
-    unsafe {
-        let frame_count = cube_size as usize;
-        let frame_pixels = (cube_size * cube_size) as usize;
-        let total_pixels = frame_count * frame_pixels;
-        
-        // Read input data
-        let indices_slice = slice::from_raw_parts(indices, total_pixels);
-        let palette_slice = slice::from_raw_parts(palette, palette_size as usize);
-        
-        // Convert palette from u32 to RGB bytes
-        let mut palette_rgb = vec![0u8; palette_size as usize * 3];
-        for i in 0..palette_size as usize {
-            let color = palette_slice[i];
-            palette_rgb[i * 3] = ((color >> 16) & 0xFF) as u8; // R
-            palette_rgb[i * 3 + 1] = ((color >> 8) & 0xFF) as u8; // G
-            palette_rgb[i * 3 + 2] = (color & 0xFF) as u8; // B
+    if !(1..=u16::MAX as i32).contains(&cube_size) || !(1..=256).contains(&palette_size) {
+        return -1;
+    }
+
+    let frame_count = cube_size as usize;
+    let frame_pixels = frame_count * frame_count;
+
+    // Read input data; the palette is packed 0x00RRGGBB
+    let (indices_slice, palette_slice) = unsafe {
+        (
+            slice::from_raw_parts(indices, frame_count * frame_pixels),
+            slice::from_raw_parts(palette, palette_size as usize),
+        )
+    };
+    let palette: Palette = palette_slice.iter().map(|&c| Color32::from_rgb_u32(c)).collect();
+    let frames: Vec<Vec<u8>> = indices_slice.chunks_exact(frame_pixels).map(<[u8]>::to_vec).collect();
+
+    // Round to centiseconds; a plain `/ 10` turned short delays into 0
+    let delay_cs = ((delay_ms.max(0) as u32 + 5) / 10).max(timing::MIN_DELAY_CS as u32) as u16;
+    let gif_opts = GifOpts {
+        width: cube_size as u16,
+        height: cube_size as u16,
+        frame_delays: vec![delay_cs; frame_count],
+        ..Default::default()
+    };
+
+    match encode_indexed_frames(&frames, &palette, None, &gif_opts) {
+        Ok(gif_data) => unsafe { write_output(&gif_data, out_data, out_capacity, out_size) },
+        Err(_) => -1,
+    }
+}
+
+/// Create a GIF89a from the frames accumulated in `processor`
+///
+/// The frames go through the full core pipeline, so the clip gets one shared
+/// palette and the core's dithering and transparency handling. With
+/// `resample_fps` 0 the delays follow the capture timestamps, so every frame
+/// must have been added with `yingif_processor_add_frame_with_timestamp` (-3
+/// otherwise). A positive `resample_fps` re-times the clip onto that constant
+/// rate, repeating or skipping frames as needed; without timestamps the frames
+/// are simply played at that rate. Returns 0, -1 on bad arguments, -2 if
+/// `out_capacity` is too small.
+#[no_mangle]
+pub extern "C" fn yingif_processor_create_gif(
+    processor: *mut libc::c_void,
+    resample_fps: i32,
+    out_data: *mut u8,
+    out_capacity: i32,
+    out_size: *mut i32,
+) -> i32 {
+    if out_data.is_null() || out_size.is_null() {
+        return -1;
+    }
+    match encode_processor(processor, resample_fps, None) {
+        Ok(result) => unsafe { write_output(&result.gif_data, out_data, out_capacity, out_size) },
+        Err(status) => status,
+    }
+}
+
+/// `yingif_processor_create_gif`, streamed to the file at `path` instead of a buffer
+///
+/// For exports too big to hold in memory: frames are written as they are
+/// compressed, and the file only appears once it is complete. Writes the
+/// file's size to `out_size`. Returns 0, -1 on bad arguments or a failed
+/// write, -3 as for `yingif_processor_create_gif`.
+#[no_mangle]
+pub extern "C" fn yingif_processor_create_gif_to_file(
+    processor: *mut libc::c_void,
+    resample_fps: i32,
+    path: *const libc::c_char,
+    out_size: *mut i64,
+) -> i32 {
+    if path.is_null() || out_size.is_null() {
+        return -1;
+    }
+    let Ok(path) = unsafe { std::ffi::CStr::from_ptr(path) }.to_str() else {
+        return -1;
+    };
+    match encode_processor(processor, resample_fps, Some(path.to_string())) {
+        Ok(result) => {
+            unsafe { *out_size = result.final_file_size as i64 };
+            0
         }
-        
-        // Create GIF
-        let mut gif_data = Vec::new();
-        {
-            let mut encoder = Encoder::new(&mut gif_data, cube_size as u16, cube_size as u16, &palette_rgb).unwrap();
-            encoder.set_repeat(Repeat::Infinite).unwrap();
-            
-            // Add frames
-            for frame_idx in 0..frame_count {
-                let start = frame_idx * frame_pixels;
-                let end = start + frame_pixels;
-                let frame_data = &indices_slice[start..end];
-                
-                let mut frame = Frame::from_indexed_pixels(cube_size as u16, cube_size as u16, frame_data, None);
-                frame.delay = (delay_ms / 10) as u16; // Convert to centiseconds
-                encoder.write_frame(&frame).unwrap();
+        Err(status) => status,
+    }
+}
+
+/// Run the processor's frames through the pipeline, or say which status code to return
+///
+/// With `output_path` the GIF goes to that file and `gif_data` comes back empty.
+fn encode_processor(processor: *mut libc::c_void, resample_fps: i32, output_path: Option<String>) -> Result<ProcessResult, i32> {
+    if processor.is_null() || resample_fps < 0 {
+        return Err(-1);
+    }
+
+    let shared = lookup(processor).ok_or(-1)?;
+    let proc = shared.lock().map_err(|_| -1)?;
+    if proc.frames.is_empty() {
+        return Err(-1);
+    }
+
+    let timed = proc.timestamps_ns.len() == proc.frames.len();
+    let (sources, delays) = match (resample_fps, timed) {
+        (0, false) => return Err(-3),
+        (0, true) => ((0..proc.frames.len()).collect(), timing::delays_from_timestamps(&proc.timestamps_ns)),
+        (fps, true) => timing::resample_to_fps(&proc.timestamps_ns, fps as u32),
+        (fps, false) => ((0..proc.frames.len()).collect(), timing::constant_delays(fps as u32, proc.frames.len())),
+    };
+
+    let side = proc.target_size as u32;
+    let mut clip = Vec::with_capacity(sources.len() * proc.frames[0].len());
+    for &source in &sources {
+        clip.extend_from_slice(&proc.frames[source]);
+    }
+    let gif_opts = GifOpts {
+        width: side as u16,
+        height: side as u16,
+        frame_count: 0, // Timing already chose the frames; don't decimate
+        frame_delays: delays,
+        output_path,
+        ..Default::default()
+    };
+
+    process_all_frames(clip, side, side, sources.len() as u32, quantize_opts(proc.palette_size), gif_opts).map_err(|_| -1)
+}
+
+/// Frames of the clip `yingif_processor_estimate` actually encodes
+const ESTIMATE_PROBE_FRAMES: usize = 6;
+
+/// Predict the size and encode time of `yingif_processor_create_gif`
+///
+/// Encodes a few evenly spaced accumulated frames and extrapolates to the
+/// whole clip, writing the likely size range in bytes and the time in
+/// milliseconds. Returns 0, or -1 on bad arguments or an empty processor.
+#[no_mangle]
+pub extern "C" fn yingif_processor_estimate(
+    processor: *mut libc::c_void,
+    out_bytes_low: *mut i32,
+    out_bytes_high: *mut i32,
+    out_ms: *mut f32,
+) -> i32 {
+    if processor.is_null() || out_bytes_low.is_null() || out_bytes_high.is_null() || out_ms.is_null() {
+        return -1;
+    }
+
+    let Some(shared) = lookup(processor) else {
+        return -1;
+    };
+    let Ok(proc) = shared.lock() else {
+        return -1;
+    };
+    if proc.frames.is_empty() {
+        return -1;
+    }
+
+    let step = proc.frames.len().div_ceil(ESTIMATE_PROBE_FRAMES);
+    let probe: Vec<u8> = proc.frames.iter().step_by(step).flatten().copied().collect();
+    let probe_count = proc.frames.len().div_ceil(step) as u32;
+    let side = proc.target_size as u32;
+    let gif_opts = GifOpts { width: side as u16, height: side as u16, frame_count: 0, ..Default::default() };
+
+    match estimate_encode(probe, side, side, probe_count, proc.frames.len() as u32, quantize_opts(proc.palette_size), gif_opts) {
+        Ok(estimate) => {
+            unsafe {
+                *out_bytes_low = estimate.bytes_low.min(i32::MAX as u32) as i32;
+                *out_bytes_high = estimate.bytes_high.min(i32::MAX as u32) as i32;
+                *out_ms = estimate.est_ms;
             }
+            0
         }
-        
-        // Copy to output buffer
-        let gif_size = gif_data.len() as i32;
-        if gif_size > out_capacity {
-            return -2; // Buffer too small
-        }
-        
-        let out_slice = slice::from_raw_parts_mut(out_data, gif_size as usize);
-        out_slice.copy_from_slice(&gif_data);
-        *out_size = gif_size;
-        
-        0
+        Err(_) => -1,
     }
 }
 
-/// Estimate GIF size
+/// Output buffer size that always fits a cube_size³ GIF
+///
+/// A worst-case bound rather than a guess: every pixel costs at most one
+/// 12-bit LZW code, plus clear codes, sub-block lengths and per-frame blocks.
+/// Use `yingif_processor_estimate` for the size the clip will likely have.
 #[no_mangle]
 pub extern "C" fn yingif_estimate_gif_size(cube_size: i32, palette_size: i32) -> i32 {
-    // Rough estimate: header + palette + compressed frames
-    let header_size = 13; // GIF header
-    let palette_bytes = palette_size * 3;
-    let frame_pixels = cube_size * cube_size;
-    let frames = cube_size;
-    
-    // Assume ~50% compression with LZW
-    let compressed_frame_size = frame_pixels / 2;
-    let total_frame_size = compressed_frame_size * frames;
-    
-    header_size + palette_bytes + total_frame_size + 1024 // Extra overhead
+    if cube_size <= 0 || !(1..=256).contains(&palette_size) {
+        return 0;
+    }
+    let (side, frames) = (cube_size as u64, cube_size as u64);
+    let header = 13 + 768 + 19 + 1; // Screen, padded global table, loop extension, trailer
+    let pixels = side * side;
+    let lzw = (pixels + pixels / 4000 + 4) * 12 / 8 + 1;
+    let frame = 8 + 10 + 1 + lzw + lzw.div_ceil(255) + 1; // Delay, descriptor, code size, data, terminator
+    (header + frame * frames).min(i32::MAX as u64) as i32
 }
 
-// Helper functions
-
-fn resize_lanczos3(rgba: &[u8], width: u32, height: u32, target_size: u32) -> Vec<u8> {
-    let img = ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, rgba.to_vec()).unwrap();
-    let resized = DynamicImage::ImageRgba8(img).resize_exact(
-        target_size,
-        target_size,
-        image::imageops::FilterType::Lanczos3,
-    );
-    resized.to_rgba8().into_raw()
+/// Record panics for `yingif_last_panic`, also writing each to `log_path` if not null
+///
+/// Call once at launch. A panic inside a C call aborts the process, so the file
+/// is what the next launch can read back. Returns 0, or -1 if `log_path` isn't UTF-8.
+#[no_mangle]
+pub extern "C" fn yingif_install_panic_hook(log_path: *const libc::c_char) -> i32 {
+    let log_path = if log_path.is_null() {
+        None
+    } else {
+        match unsafe { std::ffi::CStr::from_ptr(log_path) }.to_str() {
+            Ok(path) => Some(path.to_string()),
+            Err(_) => return -1,
+        }
+    };
+    install_panic_hook(log_path);
+    0
 }
 
-fn quantize_neuquant(rgba: &[u8], size: u32, colors: usize) -> (Vec<u32>, Vec<u8>) {
-    let pixel_count = (size * size) as usize;
-    
-    // Extract RGB data (skip alpha)
-    let mut rgb = vec![0u8; pixel_count * 3];
-    for i in 0..pixel_count {
-        rgb[i * 3] = rgba[i * 4];
-        rgb[i * 3 + 1] = rgba[i * 4 + 1];
-        rgb[i * 3 + 2] = rgba[i * 4 + 2];
-    }
-    
-    // Quantize
-    let mut quantizer = NeuQuant::new(10, colors, &rgb);
-    
-    // Build palette
-    let mut palette = vec![0u32; colors];
-    for i in 0..colors {
-        let [r, g, b, _] = quantizer.color(i);
-        palette[i] = ((r as u32) << 16) | ((g as u32) << 8) | (b as u32);
-    }
-    
-    // Map pixels to indices
-    let mut indices = vec![0u8; pixel_count];
-    for i in 0..pixel_count {
-        let r = rgb[i * 3];
-        let g = rgb[i * 3 + 1];
-        let b = rgb[i * 3 + 2];
-        indices[i] = quantizer.index_of(&[r, g, b, 255]) as u8;
-    }
-    
-    (palette, indices)
+/// Copy the latest panic report (message, thread, backtrace) into `out` as a C string
+///
+/// Returns the report's length without the terminator, 0 if nothing has
+/// panicked, -1 on bad arguments or -2 if `out_capacity` is too small.
+#[no_mangle]
+pub extern "C" fn yingif_last_panic(out: *mut libc::c_char, out_capacity: i32) -> i32 {
+    if out.is_null() || out_capacity <= 0 {
+        return -1;
+    }
+    let report = rgb2gif_last_panic().unwrap_or_default();
+    if report.len() >= out_capacity as usize {
+        return -2;
+    }
+    unsafe {
+        ptr::copy_nonoverlapping(report.as_ptr(), out as *mut u8, report.len());
+        *out.add(report.len()) = 0;
+    }
+    report.len() as i32
 }
 
 // Add libc for C types
-extern crate libc;
\ No newline at end of file
+extern crate libc;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    /// Frames each thread adds
+    const FRAMES: usize = 10;
+
+    #[test]
+    fn test_processors_on_separate_threads_keep_their_own_frames() {
+        // Camera color (BGRA), target size and palette size per capture
+        let captures = [([0u8, 0, 255, 255], 8usize, 16usize), ([255, 0, 0, 255], 12, 32)];
+        let handles: Vec<usize> = captures.iter().map(|_| yingif_processor_new() as usize).collect();
+
+        // Two threads per processor, interleaved
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let (handle, (bgra, side, palette_size)) = (handles[t % 2], captures[t % 2]);
+                thread::spawn(move || {
+                    let frame = bgra.repeat(40 * 30);
+                    let mut indices = vec![0u8; side * side];
+                    let mut palette = vec![0u32; palette_size];
+                    for _ in 0..FRAMES {
+                        let status = yingif_process_frame(
+                            handle as *mut libc::c_void,
+                            frame.as_ptr(),
+                            40,
+                            30,
+                            side as i32,
+                            palette_size as i32,
+                            indices.as_mut_ptr(),
+                            palette.as_mut_ptr(),
+                        );
+                        assert_eq!(status, 0);
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        for (&handle, &(bgra, side, palette_size)) in handles.iter().zip(&captures) {
+            let shared = lookup(handle as *mut libc::c_void).unwrap();
+            let proc = shared.lock().unwrap();
+            assert_eq!((proc.target_size, proc.palette_size), (side, palette_size));
+            assert_eq!(proc.frames.len(), 2 * FRAMES);
+            let rgba = [bgra[2], bgra[1], bgra[0], bgra[3]];
+            assert!(proc.frames.iter().all(|f| f.len() == side * side * 4 && f.chunks_exact(4).all(|px| px == rgba)));
+        }
+
+        // Both clips encode at once, each at its own size
+        let encodes: Vec<_> = handles
+            .iter()
+            .zip(&captures)
+            .map(|(&handle, &(_, side, _))| {
+                thread::spawn(move || {
+                    let mut gif = vec![0u8; 1 << 20];
+                    let mut size = 0;
+                    let status = yingif_processor_create_gif(handle as *mut libc::c_void, 10, gif.as_mut_ptr(), gif.len() as i32, &mut size);
+                    assert_eq!(status, 0);
+                    assert_eq!(&gif[6..10], [side as u8, 0, side as u8, 0]);
+                })
+            })
+            .collect();
+        for t in encodes {
+            t.join().unwrap();
+        }
+
+        for handle in handles {
+            yingif_processor_free(handle as *mut libc::c_void);
+            assert!(lookup(handle as *mut libc::c_void).is_none());
+        }
+    }
+
+    /// Add a solid 8×8 frame whose blue channel is `tag`, at target size 8
+    fn add_tagged_frame(handle: *mut libc::c_void, tag: u8) -> i32 {
+        let frame = [tag, 0, 0, 255].repeat(64);
+        let (mut indices, mut palette) = ([0u8; 64], [0u32; 16]);
+        yingif_process_frame(handle, frame.as_ptr(), 8, 8, 8, 16, indices.as_mut_ptr(), palette.as_mut_ptr())
+    }
+
+    #[test]
+    fn test_limits_refuse_or_drop_frames() {
+        let frame_bytes = 8 * 8 * 4;
+        let handle = yingif_processor_new();
+        assert_eq!(yingif_processor_memory_usage(handle), 0);
+
+        // Reject: the fourth frame is refused and nothing changes
+        assert_eq!(yingif_processor_set_limits(handle, 3, 0, 0), 0);
+        for tag in 0..3 {
+            assert_eq!(add_tagged_frame(handle, tag), 0);
+        }
+        assert_eq!(add_tagged_frame(handle, 3), -4);
+        assert_eq!(yingif_processor_memory_usage(handle), 3 * frame_bytes);
+
+        // Drop oldest under a byte cap: only the newest two frames survive
+        assert_eq!(yingif_processor_set_limits(handle, 0, 2 * frame_bytes, 1), 0);
+        for tag in 3..6 {
+            assert_eq!(add_tagged_frame(handle, tag), 0);
+        }
+        assert_eq!(yingif_processor_memory_usage(handle), 2 * frame_bytes);
+        let tags: Vec<u8> = lookup(handle).unwrap().lock().unwrap().frames.iter().map(|f| f[2]).collect();
+        assert_eq!(tags, [4, 5]);
+
+        // A frame that fails to resize leaves the buffered ones alone
+        assert_eq!(yingif_processor_set_limits(handle, 2, 0, 1), 0);
+        let too_wide = [0u8, 0, 0, 255].repeat(9000);
+        let (mut indices, mut palette) = ([0u8; 64], [0u32; 16]);
+        let status = yingif_process_frame(handle, too_wide.as_ptr(), 9000, 1, 8, 16, indices.as_mut_ptr(), palette.as_mut_ptr());
+        assert_eq!(status, -1);
+        let tags: Vec<u8> = lookup(handle).unwrap().lock().unwrap().frames.iter().map(|f| f[2]).collect();
+        assert_eq!(tags, [4, 5]);
+
+        // A frame bigger than the cap never fits
+        assert_eq!(yingif_processor_set_limits(handle, 0, frame_bytes - 1, 1), 0);
+        assert_eq!(add_tagged_frame(handle, 6), -4);
+
+        assert_eq!(yingif_processor_set_limits(handle, 0, 0, 2), -1);
+        yingif_processor_free(handle);
+        assert_eq!(yingif_processor_memory_usage(handle), -1);
+    }
+
+    #[test]
+    fn test_create_gif_to_file_matches_the_buffer() {
+        let handle = yingif_processor_new();
+        for tag in [0, 80, 160] {
+            assert_eq!(add_tagged_frame(handle, tag), 0);
+        }
+        let mut gif = vec![0u8; 1 << 16];
+        let mut size = 0;
+        assert_eq!(yingif_processor_create_gif(handle, 10, gif.as_mut_ptr(), gif.len() as i32, &mut size), 0);
+
+        let path = std::env::temp_dir().join(format!("ios-ffi-export-{}.gif", std::process::id()));
+        let c_path = std::ffi::CString::new(path.to_string_lossy().into_owned()).unwrap();
+        let mut file_size = 0i64;
+        assert_eq!(yingif_processor_create_gif_to_file(handle, 10, c_path.as_ptr(), &mut file_size), 0);
+        assert_eq!(file_size, size as i64);
+        assert_eq!(std::fs::read(&path).unwrap(), gif[..size as usize]);
+        std::fs::remove_file(&path).unwrap();
+
+        // An unwritable path fails without leaving anything behind
+        let missing = std::ffi::CString::new("/nonexistent-dir/export.gif").unwrap();
+        assert_eq!(yingif_processor_create_gif_to_file(handle, 10, missing.as_ptr(), &mut file_size), -1);
+        assert_eq!(yingif_processor_create_gif_to_file(handle, 10, ptr::null(), &mut file_size), -1);
+        yingif_processor_free(handle);
+    }
+}
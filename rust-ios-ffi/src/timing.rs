@@ -0,0 +1,69 @@
+//! Frame timing for GIF delays
+//! GIF delays are whole centiseconds, so delays are scheduled against a running
+//! clock instead of rounding each frame on its own.
+
+/// Browsers replace delays below 2cs with 10cs, so never emit less
+pub const MIN_DELAY_CS: u16 = 2;
+
+const NS_PER_CS: u64 = 10_000_000;
+
+/// Delays that play `count` frames at a steady `fps`
+///
+/// 30fps becomes 3, 3, 4, ... so the clip runs at 30fps on average instead of
+/// the 33fps a flat 3cs gives. Rates above 50fps are capped at 50.
+pub fn constant_delays(fps: u32, count: usize) -> Vec<u16> {
+    let fps = fps.clamp(1, 100 / MIN_DELAY_CS as u32) as u64;
+    (0..count as u64)
+        .map(|i| ((i + 1) * 100 / fps - i * 100 / fps) as u16)
+        .collect()
+}
+
+/// Per-frame delays that follow capture timestamps (nanoseconds, non-decreasing)
+///
+/// Each delay is the rounded time until the next frame not yet covered by a
+/// delay, so rounding and the 2cs floor never accumulate drift. The last frame
+/// gets the clip's mean interval.
+pub fn delays_from_timestamps(timestamps_ns: &[u64]) -> Vec<u16> {
+    let Some(&first) = timestamps_ns.first() else {
+        return Vec::new();
+    };
+    let to_cs = |ts: u64| (ts - first + NS_PER_CS / 2) / NS_PER_CS;
+
+    let mut delays = Vec::with_capacity(timestamps_ns.len());
+    let mut clock_cs = 0u64;
+    for &next in &timestamps_ns[1..] {
+        let delay = to_cs(next).saturating_sub(clock_cs).max(MIN_DELAY_CS as u64);
+        clock_cs += delay;
+        delays.push(delay.min(u16::MAX as u64) as u16);
+    }
+
+    let span_cs = to_cs(*timestamps_ns.last().unwrap_or(&first));
+    let intervals = (timestamps_ns.len() as u64 - 1).max(1);
+    let last = ((span_cs + intervals / 2) / intervals).max(MIN_DELAY_CS as u64);
+    delays.push(last.min(u16::MAX as u64) as u16);
+    delays
+}
+
+/// Re-time timestamped frames onto a constant `fps` grid
+///
+/// Returns the source frame shown at each tick and the tick delays; frames
+/// are repeated across gaps and skipped when several land in one tick.
+pub fn resample_to_fps(timestamps_ns: &[u64], fps: u32) -> (Vec<usize>, Vec<u16>) {
+    let Some(&first) = timestamps_ns.first() else {
+        return (Vec::new(), Vec::new());
+    };
+    let fps = fps.clamp(1, 100 / MIN_DELAY_CS as u32) as u64;
+    let span = timestamps_ns[timestamps_ns.len() - 1] - first;
+    let ticks = (span * fps / 1_000_000_000) as usize + 1;
+
+    let mut sources = Vec::with_capacity(ticks);
+    let mut source = 0;
+    for tick in 0..ticks as u64 {
+        let at = first + tick * 1_000_000_000 / fps;
+        while source + 1 < timestamps_ns.len() && timestamps_ns[source + 1] <= at {
+            source += 1;
+        }
+        sources.push(source);
+    }
+    (sources, constant_delays(fps as u32, ticks))
+}
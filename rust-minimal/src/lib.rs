@@ -1,8 +1,27 @@
-// Minimal Rust FFI implementation - just enough to compile and benchmark
+// Minimal Rust FFI implementation - a stripped-down baseline to benchmark the full pipeline against
+
+// The entry point checks its pointers before touching them
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+extern crate alloc;
 
 use std::slice;
+#[cfg(feature = "pipeline")]
+use rgb2gif_processor::resize_frame;
+#[cfg(all(feature = "pipeline", feature = "minimal"))]
+use std::time::Instant;
+
+// The core's writer, compiled here on its own so the `minimal` build needs no other crate
+#[cfg(feature = "minimal")]
+#[path = "../../rust-core/src/indexed.rs"]
+#[allow(dead_code)] // Only part of the writer is exposed over C
+mod indexed;
 
 /// Minimal frame processor - just downscale, no quantization yet
+///
+/// Downscales with the core's area resize (cropping to square), then writes
+/// one luma byte per output pixel.
+#[cfg(feature = "pipeline")]
 #[no_mangle]
 pub extern "C" fn process_frame_minimal(
     bgra_ptr: *const u8,
@@ -15,41 +34,267 @@ pub extern "C" fn process_frame_minimal(
     if bgra_ptr.is_null() || output_ptr.is_null() {
         return -1;
     }
+    if width <= 0 || height <= 0 || target_size <= 0 {
+        return -1;
+    }
 
-    let width = width as usize;
-    let height = height as usize;
-    let target_size = target_size as usize;
+    let Some(input_len) = (width as usize).checked_mul(height as usize).and_then(|n| n.checked_mul(4)) else {
+        return -1;
+    };
+    let Some(output_len) = (target_size as usize).checked_mul(target_size as usize) else {
+        return -1;
+    };
+    let (width, height, target_size) = (width as u32, height as u32, target_size as u32);
 
     // Create slices from pointers
     let bgra = unsafe {
-        slice::from_raw_parts(bgra_ptr, width * height * 4)
+        slice::from_raw_parts(bgra_ptr, input_len)
     };
 
     let output = unsafe {
-        slice::from_raw_parts_mut(output_ptr, target_size * target_size)
+        slice::from_raw_parts_mut(output_ptr, output_len)
     };
 
-    // Simple nearest-neighbor downsampling (fast, good enough for benchmarking)
-    let scale_x = width / target_size;
-    let scale_y = height / target_size;
+    // Channel order doesn't matter to the resize, so the frame stays BGRA
+    let Ok(resized) = resize_frame(bgra, width, height, target_size, target_size) else {
+        return -1;
+    };
+
+    for (gray, px) in output.iter_mut().zip(resized.chunks_exact(4)) {
+        let (b, g, r) = (px[0] as u32, px[1] as u32, px[2] as u32);
+
+        // Simple grayscale conversion
+        *gray = ((r * 299 + g * 587 + b * 114) / 1000) as u8;
+    }
+
+    0 // Success
+}
+
+/// Encode RGBA frames into a looping GIF against a palette the caller picked
+///
+/// Each pixel maps to its nearest entry of `palette_rgb` (`palette_len`
+/// colors, up to 256); pixels under half alpha become transparent when
+/// `transparent_index` is in the palette, and stay opaque when it's -1.
+/// Returns the bytes written, -1 for bad arguments or -2 when `output_ptr`
+/// is too small.
+#[cfg(feature = "minimal")]
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn encode_gif_minimal(
+    rgba_ptr: *const u8,
+    width: i32,
+    height: i32,
+    frame_count: i32,
+    palette_rgb: *const u8,
+    palette_len: i32,
+    transparent_index: i32,
+    delay_cs: i32,
+    output_ptr: *mut u8,
+    output_capacity: i32,
+) -> i32 {
+    if rgba_ptr.is_null() || palette_rgb.is_null() || output_ptr.is_null() {
+        return -1;
+    }
+    if !(1..=u16::MAX as i32).contains(&width) || !(1..=u16::MAX as i32).contains(&height) {
+        return -1;
+    }
+    if frame_count <= 0 || !(1..=256).contains(&palette_len) || !(0..=u16::MAX as i32).contains(&delay_cs) {
+        return -1;
+    }
+    let transparent = match transparent_index {
+        -1 => None,
+        i if (0..palette_len).contains(&i) => Some(i as u8),
+        _ => return -1,
+    };
+
+    let frame_bytes = width as usize * height as usize * 4;
+    let Some(total_bytes) = frame_bytes.checked_mul(frame_count as usize) else {
+        return -1;
+    };
+    let rgba = unsafe { slice::from_raw_parts(rgba_ptr, total_bytes) };
+    let palette = unsafe { slice::from_raw_parts(palette_rgb, palette_len as usize * 3) };
+
+    let mut map = indexed::PaletteMap::new(palette);
+    let mut writer = indexed::GifWriter::new(width as u16, height as u16, palette);
+    writer.write_repeat(0);
+    for frame in rgba.chunks_exact(frame_bytes) {
+        let indices = map.map_rgba(frame, transparent);
+        writer.write_frame(&indices, delay_cs as u16, indexed::Disposal::Background, transparent);
+    }
+    copy_out(&writer.finish(), output_ptr, output_capacity)
+}
+
+/// Milliseconds spent in each stage of `benchmark_gif_minimal`
+#[cfg(all(feature = "pipeline", feature = "minimal"))]
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MinimalTimings {
+    pub downscale_ms: f32,
+    pub quantize_ms: f32, // Palette plus per-pixel mapping
+    pub encode_ms: f32,   // LZW and GIF blocks
+    pub total_ms: f32,
+}
+
+/// Stripped-down baseline for the full pipeline: downscale, quantize, write a GIF
+///
+/// BGRA frames are downscaled with the core's resize to `target_size`
+/// squared, then share one 256-color palette. `neuquant_sample` of 1-30
+/// trains it with NeuQuant at that sampling factor (1 is slowest and best);
+/// 0 skips training and uses a fixed 6×7×6 color cube. Nothing is dithered.
+/// Fills `timings` when it isn't null and returns the bytes written, -1 for
+/// bad arguments, -2 when `output_ptr` is too small, or -3 when NeuQuant was
+/// asked for but left out of the build.
+#[cfg(all(feature = "pipeline", feature = "minimal"))]
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn benchmark_gif_minimal(
+    bgra_ptr: *const u8,
+    width: i32,
+    height: i32,
+    frame_count: i32,
+    target_size: i32,
+    neuquant_sample: i32,
+    delay_cs: i32,
+    output_ptr: *mut u8,
+    output_capacity: i32,
+    timings: *mut MinimalTimings,
+) -> i32 {
+    if bgra_ptr.is_null() || output_ptr.is_null() {
+        return -1;
+    }
+    if width <= 0 || height <= 0 || frame_count <= 0 || !(1..=u16::MAX as i32).contains(&target_size) {
+        return -1;
+    }
+    if !(0..=30).contains(&neuquant_sample) || !(0..=u16::MAX as i32).contains(&delay_cs) {
+        return -1;
+    }
+    if neuquant_sample > 0 && !cfg!(feature = "neuquant") {
+        return -3;
+    }
+
+    let start = Instant::now();
+    let (width, height, side) = (width as u32, height as u32, target_size as u32);
+    let Some(frame_bytes) = (width as usize).checked_mul(height as usize).and_then(|n| n.checked_mul(4)) else {
+        return -1;
+    };
+    let Some(total_bytes) = frame_bytes.checked_mul(frame_count as usize) else {
+        return -1;
+    };
+    let bgra = unsafe { slice::from_raw_parts(bgra_ptr, total_bytes) };
 
-    for y in 0..target_size {
-        for x in 0..target_size {
-            let src_x = x * scale_x;
-            let src_y = y * scale_y;
-            let src_idx = (src_y * width + src_x) * 4;
+    let side_pixels = side as usize * side as usize;
+    let Some(rgba_bytes) = (side_pixels * 4).checked_mul(frame_count as usize) else {
+        return -1;
+    };
+    let mut rgba = Vec::with_capacity(rgba_bytes);
+    for frame in bgra.chunks_exact(frame_bytes) {
+        let Ok(resized) = resize_frame(frame, width, height, side, side) else {
+            return -1;
+        };
+        rgba.extend(resized.chunks_exact(4).flat_map(|px| [px[2], px[1], px[0], px[3]]));
+    }
+    let downscaled = Instant::now();
 
-            // Convert BGRA to grayscale for now
-            let b = bgra[src_idx] as u32;
-            let g = bgra[src_idx + 1] as u32;
-            let r = bgra[src_idx + 2] as u32;
+    let (palette, indices) = if neuquant_sample > 0 {
+        neuquant(&rgba, neuquant_sample)
+    } else {
+        color_cube(&rgba)
+    };
+    let quantized = Instant::now();
 
-            // Simple grayscale conversion
-            let gray = ((r * 299 + g * 587 + b * 114) / 1000) as u8;
+    let mut writer = indexed::GifWriter::new(side as u16, side as u16, &palette);
+    writer.write_repeat(0);
+    for frame in indices.chunks_exact(side_pixels) {
+        writer.write_frame(frame, delay_cs as u16, indexed::Disposal::Keep, None);
+    }
+    let gif_data = writer.finish();
+    let encoded = Instant::now();
 
-            output[y * target_size + x] = gray;
+    if !timings.is_null() {
+        let ms = |from: Instant, to: Instant| (to - from).as_secs_f32() * 1000.0;
+        unsafe {
+            *timings = MinimalTimings {
+                downscale_ms: ms(start, downscaled),
+                quantize_ms: ms(downscaled, quantized),
+                encode_ms: ms(quantized, encoded),
+                total_ms: ms(start, encoded),
+            };
         }
     }
+    copy_out(&gif_data, output_ptr, output_capacity)
+}
 
-    0 // Success
-}
\ No newline at end of file
+/// One palette trained on every frame, and each pixel's index into it
+#[cfg(all(feature = "pipeline", feature = "minimal", feature = "neuquant"))]
+fn neuquant(rgba: &[u8], sample: i32) -> (Vec<u8>, Vec<u8>) {
+    let quant = color_quant::NeuQuant::new(sample, 256, rgba);
+    let indices = rgba.chunks_exact(4).map(|px| quant.index_of(px) as u8).collect();
+    (quant.color_map_rgb(), indices)
+}
+
+#[cfg(all(feature = "pipeline", feature = "minimal", not(feature = "neuquant")))]
+fn neuquant(_rgba: &[u8], _sample: i32) -> (Vec<u8>, Vec<u8>) {
+    unreachable!("checked before the pipeline starts")
+}
+
+/// 6 red × 7 green × 6 blue levels; the untrained baseline
+#[cfg(all(feature = "pipeline", feature = "minimal"))]
+fn color_cube(rgba: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let level = |value: u8, levels: u32| (value as u32 * levels / 256) as u8;
+    let palette = (0..6u32)
+        .flat_map(|r| (0..7u32).flat_map(move |g| (0..6u32).map(move |b| [r * 51, g * 255 / 6, b * 51])))
+        .flat_map(|c| c.map(|v| v as u8))
+        .collect();
+    let indices = rgba
+        .chunks_exact(4)
+        .map(|px| level(px[0], 6) * 42 + level(px[1], 7) * 6 + level(px[2], 6))
+        .collect();
+    (palette, indices)
+}
+
+/// Copy a finished GIF to the caller's buffer: its length, or -2 when it doesn't fit
+#[cfg(feature = "minimal")]
+fn copy_out(gif_data: &[u8], output_ptr: *mut u8, output_capacity: i32) -> i32 {
+    if gif_data.len() > output_capacity.max(0) as usize {
+        return -2;
+    }
+    let output = unsafe { slice::from_raw_parts_mut(output_ptr, gif_data.len()) };
+    output.copy_from_slice(gif_data);
+    gif_data.len() as i32
+}
+
+#[cfg(all(test, feature = "pipeline", feature = "minimal"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_benchmark_writes_a_decodable_gif_with_timings() {
+        // Four 64×48 BGRA gradients, downscaled to 32×32
+        let frames: Vec<u8> = (0..4u32)
+            .flat_map(|z| (0..64 * 48u32).flat_map(move |i| [(i % 64 * 4) as u8, (i / 64 * 5) as u8, (z * 60) as u8, 255]))
+            .collect();
+        let mut output = vec![0u8; 64 * 1024];
+
+        for sample in [0, 10] {
+            let mut timings = MinimalTimings::default();
+            let written = benchmark_gif_minimal(
+                frames.as_ptr(), 64, 48, 4, 32, sample, 4, output.as_mut_ptr(), output.len() as i32, &mut timings,
+            );
+            assert!(written > 0, "sample {sample}: {written}");
+
+            let mut decoder = gif::DecodeOptions::new().read_info(&output[..written as usize]).unwrap();
+            assert_eq!((decoder.width(), decoder.height()), (32, 32));
+            let mut decoded = 0;
+            while decoder.read_next_frame().unwrap().is_some() {
+                decoded += 1;
+            }
+            assert_eq!(decoded, 4);
+
+            assert!(timings.total_ms >= timings.downscale_ms + timings.quantize_ms);
+            assert!(timings.total_ms > 0.0);
+        }
+
+        assert_eq!(benchmark_gif_minimal(frames.as_ptr(), 64, 48, 4, 32, 31, 4, output.as_mut_ptr(), 64, std::ptr::null_mut()), -1);
+        assert_eq!(benchmark_gif_minimal(frames.as_ptr(), 64, 48, 4, 32, 0, 4, output.as_mut_ptr(), 64, std::ptr::null_mut()), -2);
+    }
+}
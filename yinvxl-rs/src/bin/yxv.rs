@@ -3,8 +3,19 @@
 
 use clap::{Parser, Subcommand};
 use anyhow::Result;
-use yinvxl::{YxvContainer, Compression};
-use std::path::PathBuf;
+use yinvxl::{YxvContainer, YxvReader, YxvWriter, Compression};
+use rgb2gif_processor::gif_validator::{validate_gif, Severity};
+use rgb2gif_processor::integrity;
+use rgb2gif_processor::palette::Palette;
+use rgb2gif_processor::palette_io::{self, PaletteFormat};
+use rgb2gif_processor::{
+    apply_voxel_effect, build_color_histogram, capture_profile_from_json, encode_indexed_frames, process_all_frames,
+    reslice_indexed_frames, resample_tensor, save_gif, summarize_frame_metadata, AccessibilityOpts, CaptureProfile, ColorSpace, ColorVisionDeficiency, FrameMetadata, GifOpts, IntegrityCheck,
+    ProcessorOptions, QuantizeOpts, QuantizerBackend, SliceAxis, StageTimings, VoxelEffect,
+};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 #[derive(Parser)]
 #[command(name = "yxv")]
@@ -18,11 +29,11 @@ struct Cli {
 enum Commands {
     /// Pack raw voxel data into YXV format
     Pack {
-        /// Input file (raw voxel data)
-        #[arg(short, long)]
-        input: PathBuf,
+        /// Input files (raw voxel data) or glob patterns
+        #[arg(short, long, num_args = 1.., required = true)]
+        input: Vec<String>,
 
-        /// Output YXV file
+        /// Output YXV file, or a directory when packing several inputs
         #[arg(short, long)]
         output: PathBuf,
 
@@ -42,9 +53,25 @@ enum Commands {
         #[arg(short, long, default_value = "lz4")]
         compression: String,
 
-        /// Palette file (768 bytes RGB)
+        /// Palette file (.gpl, .act, .pal, or raw RGB triples)
         #[arg(short, long)]
         palette: Option<PathBuf>,
+
+        /// Per-frame capture metadata (JSON array), stored as a clip summary
+        #[arg(short, long)]
+        metadata: Option<PathBuf>,
+
+        /// Capture profile (JSON `CaptureProfile`), replayed by `reprocess --like`
+        #[arg(long)]
+        profile: Option<PathBuf>,
+
+        /// End each file with a BLAKE3 content hash, checked by `validate --verify`
+        #[arg(long)]
+        hash: bool,
+
+        /// Files processed at once (default: one per core)
+        #[arg(short, long)]
+        jobs: Option<usize>,
     },
 
     /// Unpack YXV file to raw voxel data
@@ -66,12 +93,236 @@ enum Commands {
 
     /// Validate YXV file integrity
     Validate {
+        /// Input YXV files or glob patterns
+        #[arg(required = true)]
+        input: Vec<String>,
+
+        /// Also check the content hash, for files packed with one
+        #[arg(short, long)]
+        verify: bool,
+
+        /// Files processed at once (default: one per core)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+    },
+
+    /// Check a GIF against the GIF89a block structure
+    ValidateGif {
+        /// Input GIF file
+        input: PathBuf,
+
+        /// Treat plain-text/unknown extensions and trailing bytes as errors
+        #[arg(short, long)]
+        strict: bool,
+    },
+
+    /// Apply a symmetry or time effect to the voxel cube and encode it as a GIF
+    Effect {
         /// Input YXV file
+        #[arg(short, long)]
         input: PathBuf,
 
-        /// Verify checksums
+        /// Output GIF file
         #[arg(short, long)]
-        verify: bool,
+        output: PathBuf,
+
+        /// Effect (mirror, kaleidoscope, radial, slitscan, displace)
+        #[arg(short, long, default_value = "kaleidoscope")]
+        effect: String,
+
+        /// Frames per second
+        #[arg(long, default_value = "30")]
+        fps: u16,
+    },
+
+    /// Build the 64³ RGB histogram cube of a GIF (raw frame-major RGBA)
+    Histogram {
+        /// Input GIF file
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output raw RGBA cube
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+
+    /// Time the full encode pipeline over repeated runs
+    Bench {
+        /// GIF to take frames from; synthesized frames are used otherwise
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+
+        /// Width of synthesized frames
+        #[arg(short = 'W', long, default_value = "640")]
+        width: u32,
+
+        /// Height of synthesized frames
+        #[arg(short = 'H', long, default_value = "480")]
+        height: u32,
+
+        /// Number of synthesized frames
+        #[arg(short = 'D', long, default_value = "48")]
+        frames: u32,
+
+        /// Output side length (square GIF)
+        #[arg(short, long, default_value = "128")]
+        size: u16,
+
+        /// Quantizer backend (imagequant, oklab, oklch)
+        #[arg(short, long, default_value = "imagequant")]
+        backend: String,
+
+        /// Palette size
+        #[arg(short, long, default_value = "256")]
+        colors: u16,
+
+        /// Worker threads (default: one per core)
+        #[arg(short, long)]
+        threads: Option<usize>,
+
+        /// Timed runs
+        #[arg(short = 'n', long, default_value = "10")]
+        iterations: usize,
+
+        /// Untimed runs first, to warm caches and the frame pool
+        #[arg(long, default_value = "1")]
+        warmup: usize,
+    },
+
+    /// Play a YXV or GIF in the terminal
+    Preview {
+        /// Input YXV or GIF file
+        input: PathBuf,
+
+        /// Output protocol (auto, kitty, iterm, ansi)
+        #[arg(short, long, default_value = "auto")]
+        protocol: String,
+
+        /// Width in terminal columns
+        #[arg(short = 'W', long, default_value = "64")]
+        columns: u32,
+
+        /// Playback rate; YXV files default to 30, GIFs to their own delays
+        #[arg(long)]
+        fps: Option<u16>,
+
+        /// Times to play the clip (0 = until interrupted)
+        #[arg(short, long, default_value = "1")]
+        loops: u32,
+    },
+
+    /// Tile every Kth frame of a YXV or GIF into a numbered PNG contact sheet
+    Montage {
+        /// Input YXV or GIF file
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output PNG file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Keep every Kth frame
+        #[arg(short = 'k', long, default_value = "1")]
+        every: usize,
+
+        /// Tiles per row (default: as square as possible)
+        #[arg(short, long)]
+        columns: Option<usize>,
+
+        /// Integer upscale per tile, for small cubes
+        #[arg(short, long, default_value = "1")]
+        scale: u32,
+
+        /// Pixels between tiles
+        #[arg(long, default_value = "2")]
+        gap: u32,
+
+        /// Leave frame numbers off
+        #[arg(long)]
+        no_labels: bool,
+    },
+
+    /// Per-frame color counts, index entropy and change from the previous frame
+    Stats {
+        /// Input YXV or GIF file
+        input: PathBuf,
+
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Rescale a YXV to new dimensions and depth, box-filtering every axis
+    Resize {
+        /// Input YXV file
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output YXV file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// New width
+        #[arg(short = 'W', long)]
+        width: u32,
+
+        /// New height
+        #[arg(short = 'H', long)]
+        height: u32,
+
+        /// New depth (number of frames)
+        #[arg(short = 'D', long)]
+        depth: u32,
+    },
+
+    /// Trim a YXV to a rectangle and frame range, one frame in memory at a time
+    Crop {
+        /// Input YXV file
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output YXV file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Left edge of the kept rectangle
+        #[arg(short, long, default_value = "0")]
+        x: u32,
+
+        /// Top edge of the kept rectangle
+        #[arg(short, long, default_value = "0")]
+        y: u32,
+
+        /// Rectangle width (default: to the right edge)
+        #[arg(short = 'W', long)]
+        width: Option<u32>,
+
+        /// Rectangle height (default: to the bottom edge)
+        #[arg(short = 'H', long)]
+        height: Option<u32>,
+
+        /// First frame kept
+        #[arg(long, default_value = "0")]
+        start: usize,
+
+        /// Frame after the last one kept (default: through the end)
+        #[arg(long)]
+        end: Option<usize>,
+    },
+
+    /// Save the palette of a YXV, or a GIF's global palette, as .gpl, .act or .pal
+    ExportPalette {
+        /// Input YXV or GIF file
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output palette file; the format follows the extension
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Palette name written to .gpl files (default: the input file name)
+        #[arg(short, long)]
+        name: Option<String>,
     },
 
     /// Extract a single frame from YXV
@@ -90,19 +341,53 @@ enum Commands {
     },
 
     /// Convert YXV to animated GIF
-    #[cfg(feature = "gif")]
     ToGif {
-        /// Input YXV file
-        #[arg(short, long)]
-        input: PathBuf,
+        /// Input YXV files or glob patterns
+        #[arg(short, long, num_args = 1.., required = true)]
+        input: Vec<String>,
 
-        /// Output GIF file
+        /// Output GIF file, or a directory when converting several inputs
         #[arg(short, long)]
         output: PathBuf,
 
         /// Frame delay in milliseconds
         #[arg(short, long, default_value = "40")]
         delay: u16,
+
+        /// Keep palette colors distinguishable for this color blindness (deuteranopia, protanopia, tritanopia)
+        #[arg(long)]
+        color_safe: Option<String>,
+
+        /// Show the GIF as a viewer with this color blindness sees it
+        #[arg(long)]
+        simulate: Option<String>,
+
+        /// Axis the cube plays along: z (frames in order), x (one frame per column) or y (one per row)
+        #[arg(long, default_value = "z")]
+        axis: String,
+
+        /// Files processed at once (default: one per core)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+    },
+
+    /// Encode a YXV or GIF with the capture profile stored in another YXV
+    Reprocess {
+        /// Input YXV or GIF file
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// YXV whose capture profile, compression and hashing are replayed
+        #[arg(long)]
+        like: PathBuf,
+
+        /// Output YXV file, carrying the same profile
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Also save the encoded GIF here
+        #[arg(short, long)]
+        gif: Option<PathBuf>,
     },
 }
 
@@ -118,14 +403,13 @@ fn main() -> Result<()> {
             depth,
             compression,
             palette,
+            metadata,
+            profile,
+            hash,
+            jobs,
         } => {
-            println!("Packing voxel data to YXV...");
-
-            // Read raw voxel data
-            let voxel_data = std::fs::read(&input)?;
-
             // Parse compression type
-            let comp = match compression.as_str() {
+            let compression = match compression.as_str() {
                 "none" => Compression::None,
                 "lz4" => Compression::Lz4,
                 "lzfse" => Compression::Lzfse,
@@ -136,32 +420,29 @@ fn main() -> Result<()> {
                 }
             };
 
-            // Create container
-            let mut container = YxvContainer::new((width, height, depth));
-            container.compression = comp;
-
-            // Load palette if provided
-            if let Some(palette_path) = palette {
-                let palette_data = std::fs::read(&palette_path)?;
-                for chunk in palette_data.chunks_exact(3) {
-                    container.palette.push([chunk[0], chunk[1], chunk[2]]);
+            // Palette and capture metadata are shared by every input
+            let palette = match palette {
+                Some(palette_path) => load_palette(&palette_path)?,
+                None => Palette::new(),
+            };
+            let metadata = match metadata {
+                Some(metadata_path) => {
+                    let frames: Vec<FrameMetadata> = serde_json::from_slice(&std::fs::read(&metadata_path)?)?;
+                    summarize_frame_metadata(frames)
                 }
-            }
-
-            // Split voxel data into frames
-            let frame_size = (width * height) as usize;
-            for chunk in voxel_data.chunks_exact(frame_size) {
-                container.frames.push(chunk.to_vec());
-            }
-
-            // Write to file
-            container.write_to_file(&output)?;
+                None => None,
+            };
+            let profile = match profile {
+                Some(profile_path) => Some(capture_profile_from_json(std::fs::read_to_string(&profile_path)?)?),
+                None => None,
+            };
+            let options = PackOptions { dimensions: (width, height, depth), compression, palette, metadata, profile, hash };
 
-            println!("✅ Created YXV file: {}", output.display());
-            println!("   Dimensions: {}×{}×{}", width, height, depth);
-            println!("   Compression: {}", compression);
-            println!("   Palette colors: {}", container.palette.len());
-            println!("   Frames: {}", container.frames.len());
+            let inputs = expand_inputs(&input)?;
+            let outputs = output_paths(&output, &inputs, "yxv")?;
+            run_files(&inputs, jobs, "Packing voxel data to YXV...", |path| {
+                pack_file(path, &outputs[path], &options)
+            })?;
         }
 
         Commands::Unpack { input, output } => {
@@ -175,11 +456,7 @@ fn main() -> Result<()> {
             // Write palette
             if !container.palette.is_empty() {
                 let palette_path = output.join("palette.rgb");
-                let mut palette_data = Vec::new();
-                for color in &container.palette {
-                    palette_data.extend_from_slice(color);
-                }
-                std::fs::write(&palette_path, &palette_data)?;
+                std::fs::write(&palette_path, container.palette.to_rgb_bytes())?;
                 println!("   Palette saved to: {}", palette_path.display());
             }
 
@@ -208,6 +485,12 @@ fn main() -> Result<()> {
             println!("   Compression: {:?}", container.compression);
             println!("   Palette colors: {}", container.palette.len());
             println!("   Frames: {}", container.frames.len());
+            if let Some(metadata) = &container.metadata {
+                println!("   Metadata: {}", serde_json::to_string(metadata)?);
+            }
+            if let Some(profile) = &container.profile {
+                println!("   Capture profile: {}", profile.name);
+            }
 
             let voxel_count = container.dimensions.0 *
                               container.dimensions.1 *
@@ -215,32 +498,373 @@ fn main() -> Result<()> {
             println!("   Total voxels: {}", voxel_count);
         }
 
-        Commands::Validate { input, verify } => {
-            println!("Validating YXV file...");
+        Commands::Validate { input, verify, jobs } => {
+            let inputs = expand_inputs(&input)?;
+            run_files(&inputs, jobs, "Validating YXV file...", |path| validate_file(path, verify))?;
+        }
+
+        Commands::ValidateGif { input, strict } => {
+            println!("Validating GIF file...");
+
+            let data = std::fs::read(&input)?;
+            let report = validate_gif(&data, strict);
+
+            println!("   Version: GIF{}", report.version);
+            println!("   Screen: {}×{}", report.width, report.height);
+            println!("   Global colors: {}", report.global_color_table_size);
+            println!("   Frames: {}", report.frames.len());
+            match report.loop_count {
+                Some(0) => println!("   Loop: forever"),
+                Some(n) => println!("   Loop: {} times", n),
+                None => println!("   Loop: none"),
+            }
+            let hash = integrity::check_gif(&data);
+            match hash {
+                IntegrityCheck::Verified => println!("   Content hash: matches"),
+                IntegrityCheck::Corrupted => println!("❌ Content hash: mismatch, the file is damaged"),
+                IntegrityCheck::Unsigned => println!("   Content hash: none"),
+            }
+
+            for finding in &report.findings {
+                let marker = match finding.severity {
+                    Severity::Error => "❌",
+                    Severity::Warning => "⚠️ ",
+                };
+                println!("{} @0x{:06X}: {}", marker, finding.offset, finding.message);
+            }
+
+            let errors = report.errors().count() + (hash == IntegrityCheck::Corrupted) as usize;
+            if errors == 0 {
+                println!("✅ GIF structure is valid");
+            } else {
+                println!("❌ Validation failed: {} error(s)", errors);
+                std::process::exit(1);
+            }
+        }
+
+        Commands::Effect { input, output, effect, fps } => {
+            println!("Applying {} effect...", effect);
+
+            let voxel_effect = match effect.as_str() {
+                "mirror" => VoxelEffect::MirrorOctants,
+                "kaleidoscope" => VoxelEffect::Kaleidoscope,
+                "radial" => VoxelEffect::RadialRepeat,
+                "slitscan" => VoxelEffect::SlitScan,
+                "displace" => VoxelEffect::TimeDisplacement,
+                _ => {
+                    eprintln!("Invalid effect: {}", effect);
+                    std::process::exit(1);
+                }
+            };
+
+            let container = YxvContainer::read_from_file(&input)?;
+            let (width, height, _) = container.dimensions;
+            let frame_size = (width * height) as usize;
+            if container.frames.is_empty() {
+                eprintln!("No frame data in {}", input.display());
+                std::process::exit(1);
+            }
+            if container.frames.iter().any(|f| f.len() != frame_size) {
+                eprintln!("Frames do not match dimensions {}×{}", width, height);
+                std::process::exit(1);
+            }
+
+            let cube = expand_frames(&container.frames, &effective_palette(&container));
+
+            let gif_opts = GifOpts { fps, ..Default::default() };
+            let depth = container.frames.len() as u32;
+            let result = apply_voxel_effect(cube, width, height, depth, voxel_effect, QuantizeOpts::default(), gif_opts)?;
+            std::fs::write(&output, &result.gif_data)?;
+
+            println!("✅ Saved {}×{}×{} cube to: {}", width, height, depth, output.display());
+            println!("   GIF size: {} bytes", result.gif_data.len());
+        }
+
+        Commands::Histogram { input, output } => {
+            println!("Building color histogram cube...");
+
+            let (frames_rgba, width, height, frame_count) = read_gif_frames(&input)?;
+            let cube = build_color_histogram(frames_rgba, width, height, frame_count)?;
+            std::fs::write(&output, &cube)?;
+
+            let occupied = cube.chunks_exact(4).filter(|voxel| voxel[3] != 0).count();
+            println!("✅ Saved 64×64×64 cube to: {}", output.display());
+            println!("   Frames: {}", frame_count);
+            println!("   Occupied bins: {} of {}", occupied, cube.len() / 4);
+        }
+
+        Commands::Bench { input, width, height, frames, size, backend, colors, threads, iterations, warmup } => {
+            let backend = match backend.as_str() {
+                "imagequant" => QuantizerBackend::Imagequant,
+                "oklab" => QuantizerBackend::Oklab,
+                "oklch" => QuantizerBackend::Oklch,
+                _ => {
+                    eprintln!("Invalid backend: {}", backend);
+                    std::process::exit(1);
+                }
+            };
+            if iterations == 0 {
+                eprintln!("Need at least one iteration");
+                std::process::exit(1);
+            }
 
-            match YxvContainer::read_from_file(&input) {
-                Ok(container) => {
-                    println!("✅ File structure is valid");
+            let (frames_rgba, width, height, frame_count) = match &input {
+                Some(path) => read_gif_frames(path)?,
+                None => (synthesize_frames(width, height, frames), width, height, frames),
+            };
+            let pool = match threads {
+                Some(threads) => Some(rayon::ThreadPoolBuilder::new().num_threads(threads).build()?),
+                None => None,
+            };
+            let thread_count = pool.as_ref().map_or_else(rayon::current_num_threads, |p| p.current_num_threads());
 
-                    if verify {
-                        // TODO: Verify chunk checksums
-                        println!("   Checksum verification: TODO");
-                    }
+            println!("Benchmarking {:?} on {} {}×{} frames → {}×{}", backend, frame_count, width, height, size, size);
+            println!("   Threads: {}, runs: {} (+{} warmup)", thread_count, iterations, warmup);
 
-                    println!("   Frames: {}", container.frames.len());
-                    println!("   Expected: {}", container.dimensions.2);
+            // Exact palettes would skip the quantizer being measured
+            let quantize_opts = QuantizeOpts { backend, palette_size: colors, exact_colors: false, ..Default::default() };
+            let gif_opts = GifOpts { width: size, height: size, frame_count: 0, ..Default::default() };
+            let run = || -> Result<BenchRun> {
+                let frames_rgba = frames_rgba.clone();
+                let start = Instant::now();
+                let result = process_all_frames(frames_rgba, width, height, frame_count, quantize_opts.clone(), gif_opts.clone())?;
+                Ok((start.elapsed().as_secs_f32() * 1000.0, result.stage_timings, result.gif_data.len()))
+            };
 
-                    if container.frames.len() == container.dimensions.2 as usize {
-                        println!("✅ Frame count matches dimensions");
-                    } else {
-                        println!("⚠️  Frame count mismatch!");
-                    }
+            let mut runs = Vec::with_capacity(iterations);
+            for i in 0..warmup + iterations {
+                let sample = match &pool {
+                    Some(pool) => pool.install(run)?,
+                    None => run()?,
+                };
+                if i >= warmup {
+                    runs.push(sample);
                 }
-                Err(e) => {
-                    println!("❌ Validation failed: {}", e);
+            }
+
+            println!();
+            println!("   {:<12}{:>10}{:>10}{:>10}{:>10}{:>10}", "stage (ms)", "min", "p50", "p90", "p99", "max");
+            let stages: [(&str, BenchField); 5] = [
+                ("preprocess", |r| r.1.preprocess_ms),
+                ("quantize", |r| r.1.quantize_ms),
+                ("encode", |r| r.1.encode_ms),
+                ("tensor", |r| r.1.tensor_ms),
+                ("total", |r| r.0),
+            ];
+            for (name, stage) in stages {
+                let mut samples: Vec<f32> = runs.iter().map(stage).collect();
+                samples.sort_by(f32::total_cmp);
+                println!(
+                    "   {:<12}{:>10.2}{:>10.2}{:>10.2}{:>10.2}{:>10.2}",
+                    name,
+                    samples[0],
+                    percentile(&samples, 50.0),
+                    percentile(&samples, 90.0),
+                    percentile(&samples, 99.0),
+                    samples[samples.len() - 1],
+                );
+            }
+
+            // Throughput at the median run
+            let mut totals: Vec<f32> = runs.iter().map(|r| r.0).collect();
+            totals.sort_by(f32::total_cmp);
+            let median_s = percentile(&totals, 50.0) / 1000.0;
+            let megapixels = (width * height) as f32 * frame_count as f32 / 1.0e6;
+            println!();
+            println!("   Throughput: {:.1} frames/s, {:.1} Mpx/s", frame_count as f32 / median_s, megapixels / median_s);
+            println!("   GIF size: {} bytes", runs[0].2);
+        }
+
+        Commands::Preview { input, protocol, columns, fps, loops } => {
+            let protocol = match protocol.as_str() {
+                "auto" => TerminalProtocol::detect(),
+                "kitty" => TerminalProtocol::Kitty,
+                "iterm" => TerminalProtocol::Iterm,
+                "ansi" => TerminalProtocol::Ansi,
+                _ => {
+                    eprintln!("Invalid protocol: {}", protocol);
                     std::process::exit(1);
                 }
+            };
+            if columns == 0 {
+                eprintln!("Need at least one column");
+                std::process::exit(1);
+            }
+
+            let clip = Clip::load(&input, fps)?;
+            if clip.frames.is_empty() {
+                eprintln!("No frame data in {}", input.display());
+                std::process::exit(1);
+            }
+            play_preview(&clip, protocol, columns, loops)?;
+        }
+
+        Commands::Montage { input, output, every, columns, scale, gap, no_labels } => {
+            if every == 0 || scale == 0 || columns == Some(0) {
+                eprintln!("--every, --scale and --columns must be at least 1");
+                std::process::exit(1);
+            }
+
+            let clip = Clip::load(&input, None)?;
+            let picked: Vec<usize> = (0..clip.frames.len()).step_by(every).collect();
+            if picked.is_empty() {
+                eprintln!("No frame data in {}", input.display());
+                std::process::exit(1);
+            }
+            let columns = columns.unwrap_or_else(|| (picked.len() as f64).sqrt().ceil() as usize);
+
+            let sheet = render_montage(&clip, &picked, columns, scale, gap, !no_labels);
+            let mut encoder = png::Encoder::new(std::io::BufWriter::new(std::fs::File::create(&output)?), sheet.width, sheet.height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.write_header()?.write_image_data(&sheet.rgba)?;
+
+            println!("✅ Saved contact sheet to: {}", output.display());
+            println!("   Frames: {} of {} (every {})", picked.len(), clip.frames.len(), every);
+            println!("   Sheet: {}×{} ({} per row)", sheet.width, sheet.height, columns);
+        }
+
+        Commands::Stats { input, json } => {
+            let frames = read_symbols(&input)?;
+            let stats = frame_stats(&frames);
+
+            if json {
+                let frames: Vec<_> = stats
+                    .iter()
+                    .map(|s| {
+                        serde_json::json!({
+                            "frame": s.index,
+                            "unique_colors": s.unique_colors,
+                            "entropy_bits": s.entropy_bits,
+                            "changed_percent": s.changed_percent,
+                        })
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "frames": frames }))?);
+                return Ok(());
+            }
+
+            println!("   {:>6}{:>10}{:>10}{:>10}", "frame", "colors", "entropy", "changed");
+            for s in &stats {
+                let changed = s.changed_percent.map_or("-".to_string(), |p| format!("{:.1}%", p));
+                println!("   {:>6}{:>10}{:>10.3}{:>10}", s.index, s.unique_colors, s.entropy_bits, changed);
+            }
+
+            // High entropy and big changes are what LZW pays for
+            if let Some(worst) = stats.iter().max_by(|a, b| a.entropy_bits.total_cmp(&b.entropy_bits)) {
+                println!();
+                println!("   Highest entropy: frame {} ({:.3} bits/pixel)", worst.index, worst.entropy_bits);
+            }
+            let changes = stats.iter().filter_map(|s| s.changed_percent.map(|p| (s.index, p)));
+            if let Some((index, percent)) = changes.max_by(|a, b| a.1.total_cmp(&b.1)) {
+                println!("   Largest change: frame {} ({:.1}% of pixels)", index, percent);
+            }
+        }
+
+        Commands::Resize { input, output, width: out_width, height: out_height, depth: out_depth } => {
+            println!("Resizing YXV...");
+
+            let container = YxvContainer::read_from_file(&input)?;
+            let (width, height, _) = container.dimensions;
+            if container.frames.is_empty() {
+                eprintln!("No frame data in {}", input.display());
+                std::process::exit(1);
+            }
+            if container.frames.iter().any(|f| f.len() != (width * height) as usize) {
+                eprintln!("Frames do not match dimensions {}×{}", width, height);
+                std::process::exit(1);
+            }
+
+            // Filter in color, then map back onto the same palette
+            let palette = effective_palette(&container);
+            let depth = container.frames.len() as u32;
+            let cube = expand_frames(&container.frames, &palette);
+            let resampled = resample_tensor(cube, width, height, depth, out_width, out_height, out_depth)?;
+
+            let mut nearest = std::collections::HashMap::new();
+            let indices: Vec<u8> = resampled
+                .chunks_exact(4)
+                .map(|px| *nearest.entry([px[0], px[1], px[2]]).or_insert_with(|| nearest_index(&palette, [px[0], px[1], px[2]])))
+                .collect();
+
+            let mut resized = YxvContainer::new((out_width, out_height, out_depth));
+            resized.compression = container.compression;
+            resized.palette = container.palette.clone();
+            resized.metadata = container.metadata.clone();
+            resized.profile = container.profile.clone();
+            resized.frames = indices.chunks_exact((out_width * out_height) as usize).map(<[u8]>::to_vec).collect();
+            resized.write_to_file(&output)?;
+
+            println!("✅ Saved {}×{}×{} → {}×{}×{} to: {}", width, height, depth, out_width, out_height, out_depth, output.display());
+        }
+
+        Commands::Crop { input, output, x, y, width: crop_width, height: crop_height, start, end } => {
+            println!("Cropping YXV...");
+
+            let mut reader = YxvReader::open(&input)?;
+            let (width, height, _) = reader.dimensions;
+            let crop_width = crop_width.unwrap_or(width.saturating_sub(x));
+            let crop_height = crop_height.unwrap_or(height.saturating_sub(y));
+            if crop_width == 0 || crop_height == 0 || x + crop_width > width || y + crop_height > height {
+                eprintln!("{}×{} at ({}, {}) is outside the {}×{} frame", crop_width, crop_height, x, y, width, height);
+                std::process::exit(1);
+            }
+            let end = end.unwrap_or(reader.frame_count()).min(reader.frame_count());
+            if start >= end {
+                eprintln!("Frames {}..{} select none of {}", start, end, reader.frame_count());
+                std::process::exit(1);
+            }
+
+            let mut writer = YxvWriter::create(
+                &output,
+                (crop_width, crop_height, (end - start) as u32),
+                reader.compression,
+                &reader.palette,
+                reader.metadata.as_ref(),
+                reader.profile.as_ref(),
+                end - start,
+            )?;
+            writer.set_integrity_hash(reader.integrity_hash);
+            for index in start..end {
+                let frame = reader.read_frame(index)?;
+                if frame.len() != (width * height) as usize {
+                    anyhow::bail!("frame {} does not match dimensions {}×{}", index, width, height);
+                }
+                let cropped: Vec<u8> = frame
+                    .chunks_exact(width as usize)
+                    .skip(y as usize)
+                    .take(crop_height as usize)
+                    .flat_map(|row| &row[x as usize..(x + crop_width) as usize])
+                    .copied()
+                    .collect();
+                writer.write_frame(&cropped)?;
             }
+            writer.finish()?;
+
+            println!("✅ Saved {}×{}×{} to: {}", crop_width, crop_height, end - start, output.display());
+            println!("   Frames: {}..{} of {}", start, end, reader.frame_count());
+        }
+
+        Commands::ExportPalette { input, output, name } => {
+            let Some(format) = PaletteFormat::from_path(&output) else {
+                eprintln!("Unknown palette format for {}; use .gpl, .act or .pal", output.display());
+                std::process::exit(1);
+            };
+
+            let palette = if std::fs::read(&input)?.starts_with(b"GIF") {
+                let decoder = gif::DecodeOptions::new().read_info(std::fs::File::open(&input)?)?;
+                decoder.global_palette().map(Palette::from_rgb_bytes).unwrap_or_default()
+            } else {
+                YxvContainer::read_from_file(&input)?.palette
+            };
+            if palette.is_empty() {
+                eprintln!("{} has no palette", input.display());
+                std::process::exit(1);
+            }
+
+            let name = name.unwrap_or_else(|| input.file_stem().unwrap_or_default().to_string_lossy().into_owned());
+            std::fs::write(&output, palette_io::write(&palette, format, &name)?)?;
+            println!("✅ Saved {} colors to: {}", palette.len(), output.display());
         }
 
         Commands::Extract { input, frame, output } => {
@@ -258,13 +882,705 @@ fn main() -> Result<()> {
             println!("✅ Frame saved to: {}", output.display());
         }
 
-        #[cfg(feature = "gif")]
-        Commands::ToGif { input, output, delay } => {
-            println!("Converting YXV to GIF...");
-            // TODO: Implement GIF conversion
-            println!("GIF conversion not yet implemented");
+        Commands::ToGif { input, output, delay, color_safe, simulate, axis, jobs } => {
+            let axis = match axis.as_str() {
+                "x" => SliceAxis::X,
+                "y" => SliceAxis::Y,
+                "z" => SliceAxis::Z,
+                _ => {
+                    eprintln!("Invalid axis: {}", axis);
+                    std::process::exit(1);
+                }
+            };
+            if color_safe.is_some() && simulate.is_some() && color_safe != simulate {
+                eprintln!("--color-safe and --simulate name different color blindness types");
+                std::process::exit(1);
+            }
+            let deficiency = match color_safe.as_deref().or(simulate.as_deref()) {
+                Some("deuteranopia") => Some(ColorVisionDeficiency::Deuteranopia),
+                Some("protanopia") => Some(ColorVisionDeficiency::Protanopia),
+                Some("tritanopia") => Some(ColorVisionDeficiency::Tritanopia),
+                Some(name) => {
+                    eprintln!("Invalid color blindness type: {}", name);
+                    std::process::exit(1);
+                }
+                None => None,
+            };
+            let accessibility = AccessibilityOpts {
+                deficiency,
+                preserve_contrast: color_safe.is_some(),
+                simulate: simulate.is_some(),
+                ..Default::default()
+            };
+
+            let inputs = expand_inputs(&input)?;
+            let outputs = output_paths(&output, &inputs, "gif")?;
+            run_files(&inputs, jobs, "Converting YXV to GIF...", |path| {
+                to_gif_file(path, &outputs[path], delay, axis, &accessibility)
+            })?;
+        }
+
+        Commands::Reprocess { input, like, output, gif } => {
+            println!("Reprocessing with a stored capture profile...");
+
+            let reference = YxvReader::open(&like)?;
+            let Some(profile) = reference.profile.clone() else {
+                eprintln!("{} has no capture profile", like.display());
+                std::process::exit(1);
+            };
+            let clip = Clip::load(&input, None)?;
+            if clip.frames.is_empty() {
+                eprintln!("No frame data in {}", input.display());
+                std::process::exit(1);
+            }
+
+            let ProcessorOptions { quantize_opts, gif_opts, .. } = profile.options.clone();
+            let result = process_all_frames(
+                clip.frames.concat(),
+                clip.width,
+                clip.height,
+                clip.frames.len() as u32,
+                quantize_opts,
+                gif_opts,
+            )?;
+            if let Some(gif_path) = &gif {
+                save_gif(gif_path.display().to_string(), result.gif_data.clone(), false)?;
+                println!("   GIF saved to: {} ({} bytes)", gif_path.display(), result.gif_data.len());
+            }
+
+            // Index the encoded frames against the GIF's palette, so the container holds what the GIF shows
+            let (frames_rgba, width, height, frame_count) = decode_gif_frames(result.gif_data.as_slice())?;
+            let palette = Palette::from_rgba_bytes(&result.palette_rgba);
+            let mut nearest = std::collections::HashMap::new();
+            let indices: Vec<u8> = frames_rgba
+                .chunks_exact(4)
+                .map(|px| *nearest.entry([px[0], px[1], px[2]]).or_insert_with(|| nearest_index(&palette, [px[0], px[1], px[2]])))
+                .collect();
+
+            let mut container = YxvContainer::new((width, height, frame_count));
+            container.compression = reference.compression;
+            container.integrity_hash = reference.integrity_hash;
+            container.palette = palette;
+            container.frames = indices.chunks_exact((width * height) as usize).map(<[u8]>::to_vec).collect();
+            container.profile = Some(profile.clone());
+            container.write_to_file(&output)?;
+
+            println!("✅ Saved {}×{}×{} to: {}", width, height, frame_count, output.display());
+            println!("   Capture profile: {} (from {})", profile.name, like.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Read a palette file, by extension; anything else is raw RGB triples
+fn load_palette(path: &Path) -> Result<Palette> {
+    let data = std::fs::read(path)?;
+    Ok(match PaletteFormat::from_path(path) {
+        Some(format) => palette_io::parse(&data, format)?,
+        None => Palette::from_rgb_bytes(&data),
+    })
+}
+
+/// Settings `yxv pack` applies to every input
+struct PackOptions {
+    dimensions: (u32, u32, u32),
+    compression: Compression,
+    palette: Palette,
+    metadata: Option<rgb2gif_processor::ClipMetadata>,
+    profile: Option<CaptureProfile>,
+    hash: bool,
+}
+
+/// Pack one raw voxel file, returning the report lines
+fn pack_file(input: &Path, output: &Path, options: &PackOptions) -> Result<String> {
+    let (width, height, depth) = options.dimensions;
+    let voxel_data = std::fs::read(input)?;
+
+    // Create container
+    let mut container = YxvContainer::new(options.dimensions);
+    container.compression = options.compression;
+    container.palette = options.palette.clone();
+    container.metadata = options.metadata.clone();
+    container.profile = options.profile.clone();
+    container.integrity_hash = options.hash;
+
+    // Split voxel data into frames
+    let frame_size = (width * height) as usize;
+    for chunk in voxel_data.chunks_exact(frame_size) {
+        container.frames.push(chunk.to_vec());
+    }
+
+    // Write to file
+    container.write_to_file(output)?;
+
+    Ok([
+        format!("✅ Created YXV file: {}", output.display()),
+        format!("   Dimensions: {}×{}×{}", width, height, depth),
+        format!("   Compression: {:?}", options.compression),
+        format!("   Palette colors: {}", container.palette.len()),
+        format!("   Frames: {}", container.frames.len()),
+    ]
+    .join("\n"))
+}
+
+/// Check one YXV file; unreadable files and hash mismatches are errors, a short frame count only a warning
+///
+/// Reading every frame already checks each chunk's CRC; `verify` adds the
+/// whole-file hash.
+fn validate_file(input: &Path, verify: bool) -> Result<String> {
+    let container = YxvContainer::read_from_file(input)?;
+
+    let mut report = vec!["✅ File structure is valid".to_string()];
+    if verify {
+        match YxvReader::open(input)?.verify_integrity()? {
+            IntegrityCheck::Verified => report.push("✅ Content hash matches".to_string()),
+            IntegrityCheck::Corrupted => anyhow::bail!("content hash mismatch, the file is damaged"),
+            IntegrityCheck::Unsigned => report.push("⚠️  No content hash to verify".to_string()),
+        }
+    }
+    report.push(format!("   Frames: {}", container.frames.len()));
+    report.push(format!("   Expected: {}", container.dimensions.2));
+    if container.frames.len() == container.dimensions.2 as usize {
+        report.push("✅ Frame count matches dimensions".to_string());
+    } else {
+        report.push("⚠️  Frame count mismatch!".to_string());
+    }
+    Ok(report.join("\n"))
+}
+
+/// Encode one YXV as a GIF with the container's palette (gray levels without one) and color space, played along `axis`
+fn to_gif_file(input: &Path, output: &Path, delay_ms: u16, axis: SliceAxis, accessibility: &AccessibilityOpts) -> Result<String> {
+    let container = YxvContainer::read_from_file(input)?;
+    let (width, height, _) = container.dimensions;
+    if container.frames.is_empty() {
+        anyhow::bail!("no frame data");
+    }
+    let palette = effective_palette(&container);
+    let color_space = container.metadata.as_ref().and_then(|m| m.color_space).unwrap_or(ColorSpace::Srgb);
+    let (frames, width, height) = match axis {
+        SliceAxis::Z => (container.frames, width, height),
+        _ => reslice_indexed_frames(&container.frames, width, height, axis)?,
+    };
+
+    let gif_opts = GifOpts {
+        width: width as u16,
+        height: height as u16,
+        frame_delays: vec![(delay_ms / 10).max(2); frames.len()],
+        accessibility: accessibility.clone(),
+        color_space,
+        ..Default::default()
+    };
+    let gif_data = encode_indexed_frames(&frames, &palette, None, &gif_opts)?;
+    std::fs::write(output, &gif_data)?;
+
+    Ok([
+        format!("✅ Saved {} frames to: {}", frames.len(), output.display()),
+        format!("   GIF size: {} bytes", gif_data.len()),
+    ]
+    .join("\n"))
+}
+
+/// Expand glob patterns; plain paths pass through untouched so a missing file is reported by name
+fn expand_inputs(patterns: &[String]) -> Result<Vec<PathBuf>> {
+    let mut inputs = Vec::new();
+    for pattern in patterns {
+        if !pattern.contains(['*', '?', '[']) {
+            inputs.push(PathBuf::from(pattern));
+            continue;
+        }
+        let matches = glob::glob(pattern)?.collect::<std::result::Result<Vec<_>, _>>()?;
+        if matches.is_empty() {
+            anyhow::bail!("no files match {}", pattern);
+        }
+        inputs.extend(matches);
+    }
+    Ok(inputs)
+}
+
+/// Where each input's result goes: `output` itself for a single file,
+/// otherwise `output/<input stem>.<extension>`
+///
+/// A batch creates `output` if it's missing, and fails up front when two
+/// inputs share a stem rather than letting them overwrite each other.
+fn output_paths(output: &Path, inputs: &[PathBuf], extension: &str) -> Result<std::collections::HashMap<PathBuf, PathBuf>> {
+    let batch = inputs.len() > 1;
+    let mut paths = std::collections::HashMap::new();
+    let mut claimed = std::collections::HashMap::new();
+    for input in inputs {
+        let path = if batch || output.is_dir() {
+            output.join(input.file_stem().unwrap_or_default()).with_extension(extension)
+        } else {
+            output.to_path_buf()
+        };
+        if let Some(other) = claimed.insert(path.clone(), input) {
+            anyhow::bail!("{} and {} would both write {}", other.display(), input.display(), path.display());
+        }
+        paths.insert(input.clone(), path);
+    }
+
+    if batch {
+        std::fs::create_dir_all(output)?;
+    }
+    Ok(paths)
+}
+
+/// Run `task` on each input and print its report
+///
+/// A single input behaves like before: the heading, then the report or a
+/// failure exit. Several inputs run on `jobs` threads behind a progress bar,
+/// followed by a summary; the exit status is 1 if any of them failed.
+fn run_files<F>(inputs: &[PathBuf], jobs: Option<usize>, heading: &str, task: F) -> Result<()>
+where
+    F: Fn(&Path) -> Result<String> + Sync,
+{
+    use rayon::prelude::*;
+
+    if let [input] = inputs {
+        println!("{}", heading);
+        match task(input) {
+            Ok(report) => println!("{}", report),
+            Err(e) => {
+                println!("❌ {}: {}", input.display(), e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    println!("{}", heading);
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs.unwrap_or(0)).build()?;
+    let bar = indicatif::ProgressBar::new(inputs.len() as u64);
+    bar.set_style(indicatif::ProgressStyle::with_template("{bar:40} {pos}/{len} [{elapsed_precise}] {wide_msg}")?);
+
+    let failures: Vec<(&PathBuf, anyhow::Error)> = pool.install(|| {
+        inputs
+            .par_iter()
+            .filter_map(|input| {
+                bar.set_message(input.display().to_string());
+                let outcome = task(input).err().map(|e| (input, e));
+                bar.inc(1);
+                outcome
+            })
+            .collect()
+    });
+    bar.finish_and_clear();
+
+    println!("✅ {} of {} files succeeded", inputs.len() - failures.len(), inputs.len());
+    if !failures.is_empty() {
+        println!("❌ {} failed:", failures.len());
+        for (input, e) in &failures {
+            println!("   {}: {}", input.display(), e);
+        }
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// RGBA image assembled by `render_montage`
+struct Sheet {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+/// Lay out `picked` frames row by row on a dark background, numbered top-left
+fn render_montage(clip: &Clip, picked: &[usize], columns: usize, scale: u32, gap: u32, labels: bool) -> Sheet {
+    let (tile_w, tile_h) = (clip.width * scale, clip.height * scale);
+    let columns = columns.min(picked.len()) as u32;
+    let rows = picked.len().div_ceil(columns as usize) as u32;
+    let width = columns * tile_w + (columns + 1) * gap;
+    let height = rows * tile_h + (rows + 1) * gap;
+
+    let mut rgba = [32u8, 32, 32, 255].repeat((width * height) as usize);
+    // Digits stay legible without covering small tiles
+    let glyph_scale = (tile_w / 64).clamp(1, 4);
+
+    for (slot, &index) in picked.iter().enumerate() {
+        let (col, row) = (slot as u32 % columns, slot as u32 / columns);
+        let (left, top) = (gap + col * (tile_w + gap), gap + row * (tile_h + gap));
+        let frame = &clip.frames[index];
+        for y in 0..tile_h {
+            for x in 0..tile_w {
+                let src = (((y / scale) * clip.width + x / scale) * 4) as usize;
+                let dst = (((top + y) * width + left + x) * 4) as usize;
+                rgba[dst..dst + 4].copy_from_slice(&frame[src..src + 4]);
+            }
+        }
+        if labels {
+            draw_number(&mut rgba, width, left, top, (tile_w, tile_h), index, glyph_scale);
+        }
+    }
+    Sheet { width, height, rgba }
+}
+
+/// 3×5 digit glyphs, one row per 3-bit mask, high bit on the left
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// White digits on a black box at (`left`, `top`), clipped to the tile
+fn draw_number(rgba: &mut [u8], width: u32, left: u32, top: u32, tile: (u32, u32), number: usize, scale: u32) {
+    let digits: Vec<usize> = number.to_string().bytes().map(|b| (b - b'0') as usize).collect();
+    let box_w = (digits.len() as u32 * 4 + 1) * scale;
+    let box_h = 7 * scale;
+    for y in 0..box_h.min(tile.1) {
+        for x in 0..box_w.min(tile.0) {
+            // Cell coordinates inside the box: one pixel of padding, 4-pixel advance
+            let (cx, cy) = (x / scale, y / scale);
+            let lit = cx >= 1 && (1..6).contains(&cy) && (cx - 1) % 4 < 3 && {
+                let glyph = DIGITS[digits[((cx - 1) / 4) as usize]];
+                glyph[(cy - 1) as usize] >> (2 - (cx - 1) % 4) & 1 == 1
+            };
+            let value = if lit { 255 } else { 0 };
+            let i = (((top + y) * width + left + x) * 4) as usize;
+            rgba[i..i + 4].copy_from_slice(&[value, value, value, 255]);
+        }
+    }
+}
+
+/// The container's palette, or a gray ramp when it has none (indices are gray levels)
+fn effective_palette(container: &YxvContainer) -> Palette {
+    if container.palette.is_empty() {
+        (0..=255u8).map(|v| [v; 3].into()).collect()
+    } else {
+        container.palette.clone()
+    }
+}
+
+/// Indexed frames as back-to-back opaque RGBA
+fn expand_frames(frames: &[Vec<u8>], palette: &Palette) -> Vec<u8> {
+    frames
+        .iter()
+        .flatten()
+        .flat_map(|&index| {
+            let [r, g, b] = palette.get(index as usize).map_or([index; 3], |c| c.rgb());
+            [r, g, b, 255]
+        })
+        .collect()
+}
+
+/// Palette entry closest to `rgb` by squared RGB distance
+fn nearest_index(palette: &Palette, rgb: [u8; 3]) -> u8 {
+    let distance = |c: [u8; 3]| c.iter().zip(&rgb).map(|(&a, &b)| (a as i32 - b as i32).pow(2)).sum::<i32>();
+    palette.iter().enumerate().min_by_key(|(_, c)| distance(c.rgb())).map_or(0, |(i, _)| i as u8)
+}
+
+/// One row of `yxv stats`
+struct FrameStats {
+    index: usize,
+    unique_colors: usize,
+    entropy_bits: f64,            // Shannon entropy of the pixel values, bits per pixel
+    changed_percent: Option<f64>, // Pixels differing from the previous frame; none for the first
+}
+
+/// Frames as one symbol per pixel: palette indices for YXV, packed RGBA for GIF
+///
+/// YXV indices are what gets compressed, so they're measured as stored even
+/// when two entries share a color.
+fn read_symbols(path: &Path) -> Result<Vec<Vec<u32>>> {
+    if std::fs::read(path)?.starts_with(b"GIF") {
+        let (frames_rgba, width, height, _) = read_gif_frames(path)?;
+        return Ok(frames_rgba
+            .chunks_exact((width * height * 4) as usize)
+            .map(|frame| frame.chunks_exact(4).map(|px| u32::from_be_bytes([px[0], px[1], px[2], px[3]])).collect())
+            .collect());
+    }
+    let container = YxvContainer::read_from_file(path)?;
+    Ok(container.frames.iter().map(|frame| frame.iter().map(|&i| i as u32).collect()).collect())
+}
+
+fn frame_stats(frames: &[Vec<u32>]) -> Vec<FrameStats> {
+    let mut previous: Option<&Vec<u32>> = None;
+    frames
+        .iter()
+        .enumerate()
+        .map(|(index, frame)| {
+            let mut counts = std::collections::HashMap::new();
+            for &symbol in frame {
+                *counts.entry(symbol).or_insert(0usize) += 1;
+            }
+            let total = frame.len().max(1) as f64;
+            let entropy_bits = counts.values().map(|&n| n as f64 / total).map(|p| -p * p.log2()).sum::<f64>();
+
+            let changed_percent = previous.filter(|prev| prev.len() == frame.len()).map(|prev| {
+                let changed = prev.iter().zip(frame).filter(|(a, b)| a != b).count();
+                changed as f64 * 100.0 / total
+            });
+            previous = Some(frame);
+
+            FrameStats { index, unique_colors: counts.len(), entropy_bits, changed_percent }
+        })
+        .collect()
+}
+
+/// How `yxv preview` draws frames
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TerminalProtocol {
+    /// Kitty graphics protocol, raw RGBA frames replaced in place
+    Kitty,
+    /// iTerm2 inline images; the terminal animates the GIF itself
+    Iterm,
+    /// Truecolor upper-half blocks, two pixel rows per text row
+    Ansi,
+}
+
+impl TerminalProtocol {
+    /// Pick from the environment, falling back to ANSI (e.g. over SSH into tmux)
+    fn detect() -> Self {
+        let var = |name| std::env::var(name).unwrap_or_default();
+        if var("TERM") == "xterm-kitty" || !var("KITTY_WINDOW_ID").is_empty() {
+            TerminalProtocol::Kitty
+        } else if var("TERM_PROGRAM") == "iTerm.app" || !var("ITERM_SESSION_ID").is_empty() {
+            TerminalProtocol::Iterm
+        } else {
+            TerminalProtocol::Ansi
+        }
+    }
+}
+
+/// Frames of a YXV or GIF expanded to RGBA, with display times
+struct Clip {
+    width: u32,
+    height: u32,
+    frames: Vec<Vec<u8>>,
+    delays: Vec<Duration>,
+    gif_data: Vec<u8>,
+}
+
+impl Clip {
+    fn load(path: &Path, fps: Option<u16>) -> Result<Self> {
+        let data = std::fs::read(path)?;
+        let fixed_delay = |fps: u16| Duration::from_millis(1000 / fps.max(1) as u64);
+
+        if data.starts_with(b"GIF") {
+            let (frames_rgba, width, height, _) = read_gif_frames(path)?;
+            let frames: Vec<Vec<u8>> = frames_rgba.chunks_exact((width * height * 4) as usize).map(<[u8]>::to_vec).collect();
+            let delays = match fps {
+                Some(fps) => vec![fixed_delay(fps); frames.len()],
+                // Like browsers, treat delays under 2cs as 10cs
+                None => read_gif_delays(path)?
+                    .into_iter()
+                    .map(|cs| Duration::from_millis(if cs < 2 { 100 } else { cs as u64 * 10 }))
+                    .collect(),
+            };
+            return Ok(Self { width, height, frames, delays, gif_data: data });
+        }
+
+        let container = YxvContainer::read_from_file(path)?;
+        let (width, height, _) = container.dimensions;
+        if container.frames.iter().any(|f| f.len() != (width * height) as usize) {
+            anyhow::bail!("frames do not match dimensions {}×{}", width, height);
         }
+
+        let palette = effective_palette(&container);
+        let frames = expand_frames(&container.frames, &palette)
+            .chunks_exact((width * height * 4) as usize)
+            .map(<[u8]>::to_vec)
+            .collect();
+
+        // iTerm2 only shows files, so the cube goes out as a GIF
+        let delay = fixed_delay(fps.unwrap_or(30));
+        let gif_opts = GifOpts {
+            width: width as u16,
+            height: height as u16,
+            frame_delays: vec![(delay.as_millis() as u16 / 10).max(2); container.frames.len()],
+            ..Default::default()
+        };
+        let gif_data = encode_indexed_frames(&container.frames, &palette, None, &gif_opts)?;
+        Ok(Self { width, height, frames, delays: vec![delay; container.frames.len()], gif_data })
+    }
+}
+
+/// Draw `clip` in place, `loops` times (0 = forever)
+fn play_preview(clip: &Clip, protocol: TerminalProtocol, columns: u32, loops: u32) -> Result<()> {
+    let mut out = std::io::stdout().lock();
+
+    // Character cells are about twice as tall as wide
+    let rows = (columns * clip.height).div_ceil(clip.width * 2).max(1);
+
+    if protocol == TerminalProtocol::Iterm {
+        let args = format!("inline=1;width={};height={};preserveAspectRatio=1;size={}", columns, rows, clip.gif_data.len());
+        writeln!(out, "\x1b]1337;File={}:{}\x07", args, base64(&clip.gif_data))?;
+        return Ok(());
     }
 
+    write!(out, "\x1b[?25l")?; // Hide the cursor while drawing
+    let mut pass = 0;
+    while loops == 0 || pass < loops {
+        for (i, (frame, delay)) in clip.frames.iter().zip(&clip.delays).enumerate() {
+            let shown = Instant::now();
+            if i > 0 || pass > 0 {
+                write!(out, "\x1b[{}A\r", rows)?; // Back to the top of the previous frame
+            }
+            match protocol {
+                TerminalProtocol::Kitty => write_kitty_frame(&mut out, frame, clip.width, clip.height, columns, rows)?,
+                _ => write_ansi_frame(&mut out, frame, clip.width, clip.height, columns, rows)?,
+            }
+            out.flush()?;
+            std::thread::sleep(delay.saturating_sub(shown.elapsed()));
+        }
+        pass += 1;
+    }
+    write!(out, "\x1b[?25h")?;
+    out.flush()?;
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Transmit and place one frame as kitty image 1, replacing the previous one
+fn write_kitty_frame(out: &mut impl Write, rgba: &[u8], width: u32, height: u32, columns: u32, rows: u32) -> Result<()> {
+    let encoded = base64(rgba);
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(4096).collect();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = (i + 1 < chunks.len()) as u8;
+        if i == 0 {
+            // C=1 keeps the cursor put; the newlines below step past the image
+            write!(out, "\x1b_Ga=T,f=32,s={},v={},c={},r={},i=1,C=1,q=2,m={};", width, height, columns, rows, more)?;
+        } else {
+            write!(out, "\x1b_Gm={};", more)?;
+        }
+        out.write_all(chunk)?;
+        write!(out, "\x1b\\")?;
+    }
+    write!(out, "{}", "\n".repeat(rows as usize))?;
+    Ok(())
+}
+
+/// Nearest-neighbour downsample into half-block cells, alpha composited over black
+fn write_ansi_frame(out: &mut impl Write, rgba: &[u8], width: u32, height: u32, columns: u32, rows: u32) -> Result<()> {
+    let sample = |x: u32, y: u32| {
+        let sx = (x * width / columns).min(width - 1);
+        let sy = (y * height / (rows * 2)).min(height - 1);
+        let px = &rgba[((sy * width + sx) * 4) as usize..][..4];
+        let a = px[3] as u32;
+        [px[0], px[1], px[2]].map(|c| (c as u32 * a / 255) as u8)
+    };
+    for row in 0..rows {
+        for x in 0..columns {
+            let [tr, tg, tb] = sample(x, row * 2);
+            let [br, bg, bb] = sample(x, row * 2 + 1);
+            write!(out, "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m▀", tr, tg, tb, br, bg, bb)?;
+        }
+        writeln!(out, "\x1b[0m")?;
+    }
+    Ok(())
+}
+
+/// Standard base64 with padding, for the terminal image protocols
+fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |acc, (i, &b)| acc | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(TABLE[(bits >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Frame delays of a GIF in centiseconds, without decoding the image data
+fn read_gif_delays(path: &Path) -> Result<Vec<u16>> {
+    let mut options = gif::DecodeOptions::new();
+    options.skip_frame_decoding(true);
+    let mut decoder = options.read_info(std::fs::File::open(path)?)?;
+    let mut delays = Vec::new();
+    while let Some(frame) = decoder.read_next_frame()? {
+        delays.push(frame.delay);
+    }
+    Ok(delays)
+}
+
+/// One timed `yxv bench` run: total ms, per-stage times, GIF size
+type BenchRun = (f32, StageTimings, usize);
+type BenchField = fn(&BenchRun) -> f32;
+
+/// Nearest-rank percentile of ascending `sorted` samples
+fn percentile(sorted: &[f32], p: f32) -> f32 {
+    let rank = (p / 100.0 * sorted.len() as f32).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Moving gradients with a hashed texture, so the quantizer has real work
+fn synthesize_frames(width: u32, height: u32, frame_count: u32) -> Vec<u8> {
+    let mut frames = Vec::with_capacity((width * height * 4 * frame_count) as usize);
+    for t in 0..frame_count {
+        for y in 0..height {
+            for x in 0..width {
+                let grain = (x.wrapping_mul(73_856_093) ^ y.wrapping_mul(19_349_663) ^ t.wrapping_mul(83_492_791)) >> 27;
+                frames.extend_from_slice(&[
+                    ((x * 255 / width.max(1) + t * 3) as u8).wrapping_add(grain as u8),
+                    ((y * 255 / height.max(1) + t * 2) as u8).wrapping_add(grain as u8),
+                    (((x + y) * 128 / (width + height).max(1) + t * 5) as u8) ^ (grain as u8),
+                    255,
+                ]);
+            }
+        }
+    }
+    frames
+}
+
+/// Decode a GIF into composited full-canvas RGBA frames
+///
+/// Returns the frames back to back with the canvas width, height and frame count.
+fn read_gif_frames(path: &Path) -> Result<(Vec<u8>, u32, u32, u32)> {
+    decode_gif_frames(std::fs::File::open(path)?)
+}
+
+/// `read_gif_frames` for a GIF already in memory or any other reader
+fn decode_gif_frames(source: impl std::io::Read) -> Result<(Vec<u8>, u32, u32, u32)> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(source)?;
+    let (width, height) = (decoder.width() as usize, decoder.height() as usize);
+
+    let mut canvas = vec![0u8; width * height * 4];
+    let mut frames = Vec::new();
+    let mut count = 0u32;
+    while let Some(frame) = decoder.read_next_frame()? {
+        let previous = (frame.dispose == gif::DisposalMethod::Previous).then(|| canvas.clone());
+        let (left, top) = (frame.left as usize, frame.top as usize);
+        let frame_width = frame.width as usize;
+        for (y, row) in frame.buffer.chunks_exact(frame_width * 4).enumerate() {
+            if top + y >= height {
+                break;
+            }
+            for (x, px) in row.chunks_exact(4).enumerate() {
+                // Transparent pixels let the canvas show through
+                if left + x < width && px[3] != 0 {
+                    let i = ((top + y) * width + left + x) * 4;
+                    canvas[i..i + 4].copy_from_slice(px);
+                }
+            }
+        }
+        frames.extend_from_slice(&canvas);
+        count += 1;
+
+        match frame.dispose {
+            gif::DisposalMethod::Background => {
+                for y in top..(top + frame.height as usize).min(height) {
+                    let start = (y * width + left.min(width)) * 4;
+                    let end = (y * width + (left + frame_width).min(width)) * 4;
+                    canvas[start..end].fill(0);
+                }
+            }
+            gif::DisposalMethod::Previous => canvas = previous.unwrap_or(canvas),
+            _ => {}
+        }
+    }
+
+    Ok((frames, width as u32, height as u32, count))
+}
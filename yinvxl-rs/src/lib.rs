@@ -8,6 +8,10 @@ use anyhow::{Result, Context, bail};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use lz4;
 use crc32fast::Hasher;
+use rgb2gif_processor::atomic_file::AtomicFile;
+use rgb2gif_processor::integrity;
+use rgb2gif_processor::palette::Palette;
+use rgb2gif_processor::{CaptureProfile, ClipMetadata, IntegrityCheck};
 
 #[cfg(target_os = "macos")]
 use lzfse;
@@ -22,6 +26,7 @@ use yinvxl_generated::yin_voxel::*;
 const MAGIC: &[u8; 4] = b"YXV\0";
 const VERSION: u32 = 1;
 const CHUNK_ALIGNMENT: u64 = 64;
+const CHUNK_RECORD_SIZE: u64 = 24;
 
 // Compression types
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -51,6 +56,7 @@ pub enum ChunkType {
     Frame,
     Metadata,
     Thumbnail,
+    Profile,
 }
 
 // Chunk record (24 bytes)
@@ -71,16 +77,21 @@ impl ChunkRecord {
             1 => ChunkType::Frame,
             2 => ChunkType::Metadata,
             3 => ChunkType::Thumbnail,
+            4 => ChunkType::Profile,
             _ => bail!("Invalid chunk type: {}", type_byte),
         };
 
-        Ok(ChunkRecord {
+        let record = ChunkRecord {
             chunk_type,
             offset: reader.read_u64::<LittleEndian>()?,
             compressed_size: reader.read_u32::<LittleEndian>()?,
             uncompressed_size: reader.read_u32::<LittleEndian>()?,
             checksum: reader.read_u32::<LittleEndian>()?,
-        })
+        };
+
+        // Skip the padding to 24 bytes
+        reader.read_exact(&mut [0u8; 3])?;
+        Ok(record)
     }
 
     fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
@@ -89,6 +100,7 @@ impl ChunkRecord {
             ChunkType::Frame => 1,
             ChunkType::Metadata => 2,
             ChunkType::Thumbnail => 3,
+            ChunkType::Profile => 4,
         };
 
         writer.write_u8(type_byte)?;
@@ -107,86 +119,80 @@ impl ChunkRecord {
 // YXV Container
 pub struct YxvContainer {
     pub dimensions: (u32, u32, u32),  // width, height, depth
-    pub palette: Vec<[u8; 3]>,        // RGB palette
+    pub palette: Palette,             // Stored as RGB triples
     pub frames: Vec<Vec<u8>>,         // Frame data (indexed)
+    pub metadata: Option<ClipMetadata>, // Capture summary, stored as a JSON chunk
+    pub profile: Option<CaptureProfile>, // Settings the capture was made with, stored as a JSON chunk
     pub compression: Compression,
+    pub integrity_hash: bool,         // Close the file with a BLAKE3 footer
 }
 
 impl YxvContainer {
     pub fn new(dimensions: (u32, u32, u32)) -> Self {
         YxvContainer {
             dimensions,
-            palette: Vec::new(),
+            palette: Palette::new(),
             frames: Vec::new(),
+            metadata: None,
+            profile: None,
             compression: Compression::Lz4,
+            integrity_hash: false,
         }
     }
 
     // Write to file
     pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        let file = File::create(path)?;
-        let mut writer = BufWriter::new(file);
-
-        // Write magic
-        writer.write_all(MAGIC)?;
-
-        // Build FlatBuffers header
-        let header_data = self.build_header()?;
-
-        // Write header size and data
-        writer.write_u32::<LittleEndian>(header_data.len() as u32)?;
-        writer.write_all(&header_data)?;
-
-        let mut chunks = Vec::new();
-        let mut current_offset = writer.seek(SeekFrom::Current(0))?;
-
-        // Write palette chunk
-        if !self.palette.is_empty() {
-            let palette_data = self.encode_palette();
-            let compressed = self.compress(&palette_data)?;
-            let checksum = calculate_crc32(&compressed);
-
-            chunks.push(ChunkRecord {
-                chunk_type: ChunkType::Palette,
-                offset: current_offset,
-                compressed_size: compressed.len() as u32,
-                uncompressed_size: palette_data.len() as u32,
-                checksum,
-            });
-
-            writer.write_all(&compressed)?;
-            current_offset = align_offset(&mut writer, CHUNK_ALIGNMENT)?;
-        }
-
-        // Write frame chunks
+        let mut writer = YxvWriter::create(
+            path,
+            self.dimensions,
+            self.compression,
+            &self.palette,
+            self.metadata.as_ref(),
+            self.profile.as_ref(),
+            self.frames.len(),
+        )?;
+        writer.set_integrity_hash(self.integrity_hash);
         for frame in &self.frames {
-            let compressed = self.compress(frame)?;
-            let checksum = calculate_crc32(&compressed);
-
-            chunks.push(ChunkRecord {
-                chunk_type: ChunkType::Frame,
-                offset: current_offset,
-                compressed_size: compressed.len() as u32,
-                uncompressed_size: frame.len() as u32,
-                checksum,
-            });
-
-            writer.write_all(&compressed)?;
-            current_offset = align_offset(&mut writer, CHUNK_ALIGNMENT)?;
-        }
-
-        // Write chunk table
-        for chunk in &chunks {
-            chunk.write_to(&mut writer)?;
+            writer.write_frame(frame)?;
         }
-
-        writer.flush()?;
-        Ok(())
+        writer.finish()
     }
 
     // Read from file
     pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut reader = YxvReader::open(path)?;
+        let frames = (0..reader.frame_count())
+            .map(|i| reader.read_frame(i))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(YxvContainer {
+            dimensions: reader.dimensions,
+            palette: reader.palette,
+            frames,
+            metadata: reader.metadata,
+            profile: reader.profile,
+            compression: reader.compression,
+            integrity_hash: reader.integrity_hash,
+        })
+    }
+}
+
+// Streaming reader: header, palette and metadata up front, frames on demand
+pub struct YxvReader {
+    pub dimensions: (u32, u32, u32),
+    pub palette: Palette,
+    pub metadata: Option<ClipMetadata>,
+    pub profile: Option<CaptureProfile>,
+    pub compression: Compression,
+    pub integrity_hash: bool,  // Whether the file ends in a footer; `verify_integrity` checks it
+    reader: BufReader<File>,
+    frames: Vec<ChunkRecord>,
+}
+
+impl YxvReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
         let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
         let mut reader = BufReader::new(file);
 
         // Read and verify magic
@@ -198,6 +204,9 @@ impl YxvContainer {
 
         // Read header
         let header_size = reader.read_u32::<LittleEndian>()?;
+        if header_size as u64 > file_len {
+            bail!("Header of {} bytes is longer than the {}-byte file", header_size, file_len);
+        }
         let mut header_data = vec![0u8; header_size as usize];
         reader.read_exact(&mut header_data)?;
 
@@ -214,121 +223,335 @@ impl YxvContainer {
             dims.get(2) as u32,
         );
 
-        // Extract compression
-        let compression = Compression::from(header.compression());
+        // The chunk table closes the file, one record per chunk, unless a footer follows it
+        let table_size = header.chunk_count() as u64 * CHUNK_RECORD_SIZE;
+        let mut content_end = reader.seek(SeekFrom::End(0))?;
+        let mut tail = [0u8; 4];
+        if content_end >= tail.len() as u64 {
+            reader.seek(SeekFrom::End(-(tail.len() as i64)))?;
+            reader.read_exact(&mut tail)?;
+        }
+        let integrity_hash = integrity::has_yxv_footer(&tail);
+        if integrity_hash {
+            content_end = content_end.checked_sub(integrity::YXV_FOOTER_BYTES as u64).context("Footer is truncated")?;
+        }
+        let table_start = content_end.checked_sub(table_size).context("Chunk table is truncated")?;
+        reader.seek(SeekFrom::Start(table_start))?;
+        let records = (0..header.chunk_count())
+            .map(|_| ChunkRecord::read_from(&mut reader))
+            .collect::<Result<Vec<_>>>()?;
+
+        // Chunks sit aligned between the header and the table; anything else is a damaged file
+        let chunks_start = (MAGIC.len() + 4) as u64 + header_size as u64;
+        for record in &records {
+            if record.offset % CHUNK_ALIGNMENT != 0 {
+                bail!("{:?} chunk at offset {} is not {}-byte aligned", record.chunk_type, record.offset, CHUNK_ALIGNMENT);
+            }
+            if record.offset < chunks_start || record.offset + record.compressed_size as u64 > table_start {
+                bail!("{:?} chunk at offset {} runs outside the file (truncated?)", record.chunk_type, record.offset);
+            }
+        }
+
+        let mut yxv = YxvReader {
+            dimensions,
+            palette: Palette::new(),
+            metadata: None,
+            profile: None,
+            compression: Compression::from(header.compression()),
+            integrity_hash,
+            reader,
+            frames: Vec::new(),
+        };
+        for record in records {
+            match record.chunk_type {
+                ChunkType::Palette => yxv.palette = Palette::from_rgb_bytes(&yxv.read_chunk(&record)?),
+                ChunkType::Frame => yxv.frames.push(record),
+                ChunkType::Metadata => {
+                    let data = yxv.read_chunk(&record)?;
+                    yxv.metadata = Some(serde_json::from_slice(&data).context("Malformed metadata chunk")?);
+                }
+                ChunkType::Profile => {
+                    let data = yxv.read_chunk(&record)?;
+                    yxv.profile = Some(serde_json::from_slice(&data).context("Malformed profile chunk")?);
+                }
+                ChunkType::Thumbnail => {}
+            }
+        }
+
+        Ok(yxv)
+    }
 
-        // Read chunk table (simplified for now)
-        // In production, read from header.chunk_table_offset()
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
 
-        let mut container = YxvContainer::new(dimensions);
-        container.compression = compression;
+    // Hash the whole file against its footer
+    pub fn verify_integrity(&mut self) -> Result<IntegrityCheck> {
+        let mut data = Vec::new();
+        self.reader.seek(SeekFrom::Start(0))?;
+        self.reader.read_to_end(&mut data)?;
+        Ok(integrity::check_yxv(&data))
+    }
 
-        Ok(container)
+    // Load and decompress one frame
+    pub fn read_frame(&mut self, index: usize) -> Result<Vec<u8>> {
+        let record = self.frames.get(index).cloned()
+            .with_context(|| format!("Frame {} out of range (0-{})", index, self.frames.len().saturating_sub(1)))?;
+        self.read_chunk(&record)
     }
 
-    // Build FlatBuffers header
-    fn build_header(&self) -> Result<Vec<u8>> {
-        let mut builder = flatbuffers::FlatBufferBuilder::new();
-
-        // Create dimensions vector
-        let dims = builder.create_vector(&[
-            self.dimensions.0 as u16,
-            self.dimensions.1 as u16,
-            self.dimensions.2 as u16,
-        ]);
-
-        // Create header
-        let header = VoxelHeader::create(&mut builder, &VoxelHeaderArgs {
-            version: Some(VERSION),
-            dimensions: Some(dims),
-            color_mode: ColorMode::INDEXED,
-            palette_size: self.palette.len() as u16,
-            compression: match self.compression {
-                Compression::None => CompressionType::NONE,
-                Compression::Lz4 => CompressionType::LZ4,
-                Compression::Lzfse => CompressionType::LZFSE,
-                Compression::Zstd => CompressionType::ZSTD,
-            },
-            chunk_count: (1 + self.frames.len()) as u32,  // palette + frames
-            chunk_table_offset: 0,  // Will be set later
-            view_hints: None,
-            creator: Some(builder.create_string("yinvxl-rs")),
-            creation_timestamp: Some(std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs()),
-            frame_rate: 30,
-            metadata: None,
+    fn read_chunk(&mut self, record: &ChunkRecord) -> Result<Vec<u8>> {
+        self.reader.seek(SeekFrom::Start(record.offset))?;
+        let mut compressed = vec![0u8; record.compressed_size as usize];
+        self.reader.read_exact(&mut compressed)?;
+        if calculate_crc32(&compressed) != record.checksum {
+            bail!("Checksum mismatch in {:?} chunk at offset {}", record.chunk_type, record.offset);
+        }
+        decompress(self.compression, &compressed, record.uncompressed_size as usize)
+    }
+}
+
+// Streaming writer: one frame in memory at a time
+//
+// The header records the chunk count, so the frame count is fixed at creation
+// and `finish` fails if a different number was written.
+// Written under a hidden staging name; the container only appears at `path` once `finish` succeeds
+pub struct YxvWriter {
+    writer: BufWriter<HashingWriter>,
+    compression: Compression,
+    chunks: Vec<ChunkRecord>,
+    frames_left: usize,
+    sync: bool,
+    integrity_hash: bool,
+}
+
+impl YxvWriter {
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        dimensions: (u32, u32, u32),
+        compression: Compression,
+        palette: &Palette,
+        metadata: Option<&ClipMetadata>,
+        profile: Option<&CaptureProfile>,
+        frame_count: usize,
+    ) -> Result<Self> {
+        let file = AtomicFile::create(path)?;
+        let mut yxv = YxvWriter {
+            writer: BufWriter::new(HashingWriter { file, hasher: integrity::Hasher::new(), position: 0 }),
+            compression,
+            chunks: Vec::new(),
+            frames_left: frame_count,
+            sync: false,
+            integrity_hash: false,
+        };
+
+        // Write magic
+        yxv.writer.write_all(MAGIC)?;
+
+        // Build FlatBuffers header; chunks are palette, metadata, profile, then one per frame
+        let optional = [!palette.is_empty(), metadata.is_some(), profile.is_some()];
+        let chunk_count = (optional.iter().filter(|&&present| present).count() + frame_count) as u32;
+        let header_data = build_header(dimensions, palette.len() as u16, compression, chunk_count);
+
+        // Write header size and data
+        yxv.writer.write_u32::<LittleEndian>(header_data.len() as u32)?;
+        yxv.writer.write_all(&header_data)?;
+
+        // Write palette chunk
+        if !palette.is_empty() {
+            yxv.write_chunk(ChunkType::Palette, &palette.to_rgb_bytes())?;
+        }
+
+        // Write metadata chunk
+        if let Some(metadata) = metadata {
+            yxv.write_chunk(ChunkType::Metadata, &serde_json::to_vec(metadata)?)?;
+        }
+
+        // Write profile chunk
+        if let Some(profile) = profile {
+            yxv.write_chunk(ChunkType::Profile, &serde_json::to_vec(profile)?)?;
+        }
+
+        Ok(yxv)
+    }
+
+    // Flush to disk before and after the final rename, so the save survives a power loss
+    pub fn set_sync(&mut self, sync: bool) {
+        self.sync = sync;
+    }
+
+    // Close the file with a BLAKE3 hash of everything before it
+    pub fn set_integrity_hash(&mut self, integrity_hash: bool) {
+        self.integrity_hash = integrity_hash;
+    }
+
+    pub fn write_frame(&mut self, frame: &[u8]) -> Result<()> {
+        if self.frames_left == 0 {
+            bail!("More frames written than the header declares");
+        }
+        self.frames_left -= 1;
+        self.write_chunk(ChunkType::Frame, frame)
+    }
+
+    // Write the chunk table and move the file into place
+    pub fn finish(mut self) -> Result<()> {
+        if self.frames_left > 0 {
+            bail!("{} frame(s) declared in the header were never written", self.frames_left);
+        }
+        for chunk in &self.chunks {
+            chunk.write_to(&mut self.writer)?;
+        }
+
+        let HashingWriter { mut file, hasher, .. } = self.writer.into_inner().map_err(|e| e.into_error())?;
+        if self.integrity_hash {
+            file.write_all(&integrity::yxv_footer(hasher.finalize().as_bytes()))?;
+        }
+        file.commit(self.sync)?;
+        Ok(())
+    }
+
+    fn write_chunk(&mut self, chunk_type: ChunkType, data: &[u8]) -> Result<()> {
+        let offset = align_offset(&mut self.writer, CHUNK_ALIGNMENT)?;
+        let compressed = compress(self.compression, data)?;
+
+        self.chunks.push(ChunkRecord {
+            chunk_type,
+            offset,
+            compressed_size: compressed.len() as u32,
+            uncompressed_size: data.len() as u32,
+            checksum: calculate_crc32(&compressed),
         });
 
-        builder.finish(header, None);
-        Ok(builder.finished_data().to_vec())
+        self.writer.write_all(&compressed)?;
+        Ok(())
+    }
+}
+
+// Hashes the container as it streams out, and reports the write position for chunk alignment
+struct HashingWriter {
+    file: AtomicFile,
+    hasher: integrity::Hasher,
+    position: u64,
+}
+
+impl Write for HashingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.file.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.position += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
     }
+}
 
-    // Encode palette
-    fn encode_palette(&self) -> Vec<u8> {
-        let mut data = Vec::with_capacity(self.palette.len() * 3);
-        for color in &self.palette {
-            data.extend_from_slice(color);
+// Only the current position can be asked for: moving back would invalidate the hash
+impl Seek for HashingWriter {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match pos {
+            SeekFrom::Current(0) => Ok(self.position),
+            _ => Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "YXV output is written front to back")),
         }
-        data
     }
+}
 
-    // Compression
-    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
-        match self.compression {
-            Compression::None => Ok(data.to_vec()),
-            Compression::Lz4 => {
-                let compressed = lz4::block::compress(data, None, false)?;
-                Ok(compressed)
-            }
-            #[cfg(target_os = "macos")]
-            Compression::Lzfse => {
-                // Use lzfse crate on macOS
-                Ok(lzfse::encode(data))
-            }
-            #[cfg(not(target_os = "macos"))]
-            Compression::Lzfse => {
-                bail!("LZFSE compression not available on this platform")
+// Build FlatBuffers header
+fn build_header(dimensions: (u32, u32, u32), palette_size: u16, compression: Compression, chunk_count: u32) -> Vec<u8> {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+
+    // Create dimensions vector
+    let dims = builder.create_vector(&[
+        dimensions.0 as u16,
+        dimensions.1 as u16,
+        dimensions.2 as u16,
+    ]);
+
+    let creator = builder.create_string("yinvxl-rs");
+
+    // Create header
+    let header = VoxelHeader::create(&mut builder, &VoxelHeaderArgs {
+        version: VERSION,
+        dimensions: Some(dims),
+        color_mode: ColorMode::INDEXED,
+        palette_size,
+        compression: match compression {
+            Compression::None => CompressionType::NONE,
+            Compression::Lz4 => CompressionType::LZ4,
+            Compression::Lzfse => CompressionType::LZFSE,
+            Compression::Zstd => CompressionType::ZSTD,
+        },
+        chunk_count,
+        chunk_table_offset: 0,  // Will be set later
+        view_hints: None,
+        creator: Some(creator),
+        creation_timestamp: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        frame_rate: 30,
+        metadata: None,
+    });
+
+    builder.finish(header, None);
+    builder.finished_data().to_vec()
+}
+
+// Compression
+fn compress(compression: Compression, data: &[u8]) -> Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(data.to_vec()),
+        Compression::Lz4 => {
+            let compressed = lz4::block::compress(data, None, false)?;
+            Ok(compressed)
+        }
+        #[cfg(target_os = "macos")]
+        Compression::Lzfse => {
+            // Use lzfse crate on macOS
+            Ok(lzfse::encode(data))
+        }
+        #[cfg(not(target_os = "macos"))]
+        Compression::Lzfse => {
+            bail!("LZFSE compression not available on this platform")
+        }
+        Compression::Zstd => {
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            {
+                Ok(zstd::encode_all(data, 3)?)
             }
-            Compression::Zstd => {
-                #[cfg(any(target_os = "windows", target_os = "linux"))]
-                {
-                    Ok(zstd::encode_all(data, 3)?)
-                }
-                #[cfg(not(any(target_os = "windows", target_os = "linux")))]
-                {
-                    bail!("ZSTD compression not available on this platform")
-                }
+            #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+            {
+                bail!("ZSTD compression not available on this platform")
             }
         }
     }
+}
 
-    // Decompression
-    fn decompress(&self, data: &[u8], expected_size: usize) -> Result<Vec<u8>> {
-        match self.compression {
-            Compression::None => Ok(data.to_vec()),
-            Compression::Lz4 => {
-                let decompressed = lz4::block::decompress(data, Some(expected_size as i32))?;
-                Ok(decompressed)
-            }
-            #[cfg(target_os = "macos")]
-            Compression::Lzfse => {
-                Ok(lzfse::decode(data))
-            }
-            #[cfg(not(target_os = "macos"))]
-            Compression::Lzfse => {
-                bail!("LZFSE decompression not available on this platform")
+// Decompression
+fn decompress(compression: Compression, data: &[u8], expected_size: usize) -> Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(data.to_vec()),
+        Compression::Lz4 => {
+            let decompressed = lz4::block::decompress(data, Some(expected_size as i32))?;
+            Ok(decompressed)
+        }
+        #[cfg(target_os = "macos")]
+        Compression::Lzfse => {
+            Ok(lzfse::decode(data))
+        }
+        #[cfg(not(target_os = "macos"))]
+        Compression::Lzfse => {
+            bail!("LZFSE decompression not available on this platform")
+        }
+        Compression::Zstd => {
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            {
+                Ok(zstd::decode_all(data)?)
             }
-            Compression::Zstd => {
-                #[cfg(any(target_os = "windows", target_os = "linux"))]
-                {
-                    Ok(zstd::decode_all(data)?)
-                }
-                #[cfg(not(any(target_os = "windows", target_os = "linux")))]
-                {
-                    bail!("ZSTD decompression not available on this platform")
-                }
+            #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+            {
+                bail!("ZSTD decompression not available on this platform")
             }
         }
     }
@@ -383,4 +606,99 @@ mod ffi {
         // Implementation for FFI read
         0
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rgb2gif_processor::palette::Palette;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("yinvxl-{}-{}.yxv", name, std::process::id()))
+    }
+
+    fn frames() -> Vec<Vec<u8>> {
+        (0..3u8).map(|z| (0..64u8).map(|i| i.wrapping_mul(z + 1)).collect()).collect()
+    }
+
+    // An 8×8×3 Lz4 container with its palette and metadata, and its bytes
+    fn written(name: &str) -> (std::path::PathBuf, Vec<u8>) {
+        let path = temp_path(name);
+        let palette = Palette::from_rgb_bytes(&[255, 0, 0, 0, 255, 0, 0, 0, 255]);
+        let metadata = ClipMetadata { frame_count: 3, iso_min: Some(100), ..Default::default() };
+        let mut writer = YxvWriter::create(&path, (8, 8, 3), Compression::Lz4, &palette, Some(&metadata), None, 3).unwrap();
+        for frame in frames() {
+            writer.write_frame(&frame).unwrap();
+        }
+        writer.finish().unwrap();
+        let data = std::fs::read(&path).unwrap();
+        (path, data)
+    }
+
+    #[test]
+    fn test_frames_palette_and_metadata_round_trip() {
+        for compression in [Compression::None, Compression::Lz4, Compression::Zstd] {
+            let path = temp_path("round-trip");
+            let palette = Palette::from_rgb_bytes(&[255, 0, 0, 0, 255, 0, 0, 0, 255]);
+            let metadata = ClipMetadata { frame_count: 3, iso_min: Some(100), latitude: Some(59.3), ..Default::default() };
+            let mut writer = YxvWriter::create(&path, (8, 8, 3), compression, &palette, Some(&metadata), None, 3).unwrap();
+            writer.set_integrity_hash(true);
+            for frame in frames() {
+                writer.write_frame(&frame).unwrap();
+            }
+            writer.finish().unwrap();
+
+            let mut reader = YxvReader::open(&path).unwrap();
+            assert_eq!((reader.dimensions, reader.compression), ((8, 8, 3), compression));
+            assert_eq!(reader.palette.to_rgb_bytes(), palette.to_rgb_bytes());
+            assert_eq!(reader.metadata.as_ref(), Some(&metadata));
+            assert!(reader.profile.is_none() && reader.integrity_hash);
+            let read: Vec<Vec<u8>> = (0..reader.frame_count()).map(|i| reader.read_frame(i).unwrap()).collect();
+            assert_eq!(read, frames());
+            assert!(reader.read_frame(3).is_err());
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+
+    #[test]
+    fn test_frame_count_must_match_the_header() {
+        let path = temp_path("count");
+        let frame = [0u8; 64];
+        let create = |declared| YxvWriter::create(&path, (8, 8, 2), Compression::None, &Palette::new(), None, None, declared).unwrap();
+
+        let mut short = create(2);
+        short.write_frame(&frame).unwrap();
+        assert!(short.finish().is_err());
+        assert!(!path.exists());
+
+        let mut long = create(1);
+        long.write_frame(&frame).unwrap();
+        assert!(long.write_frame(&frame).is_err());
+    }
+
+    #[test]
+    fn test_damaged_chunks_are_errors() {
+        let (path, data) = written("damaged");
+        let table_start = data.len() - 5 * CHUNK_RECORD_SIZE as usize;
+
+        // Bytes lost before the table cut the last frame short
+        let mut truncated = data.clone();
+        truncated.drain(table_start - 10..table_start);
+        std::fs::write(&path, &truncated).unwrap();
+        assert!(YxvReader::open(&path).is_err());
+
+        // The last record's offset nudged off the alignment
+        let mut misaligned = data.clone();
+        let offset_at = data.len() - CHUNK_RECORD_SIZE as usize + 1;
+        misaligned[offset_at] += 1;
+        std::fs::write(&path, &misaligned).unwrap();
+        let err = YxvReader::open(&path).err().unwrap();
+        assert!(err.to_string().contains("aligned"), "{err}");
+
+        // Cut off in the middle, or down to the header
+        for len in [data.len() / 2, 12, 3] {
+            std::fs::write(&path, &data[..len]).unwrap();
+            assert!(YxvReader::open(&path).is_err());
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+}